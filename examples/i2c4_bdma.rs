@@ -13,7 +13,7 @@ use core::{mem, mem::MaybeUninit};
 mod utilities;
 
 use stm32h7xx_hal::dma::{
-    bdma::{BdmaConfig, StreamsTuple},
+    bdma::{BdmaConfig, Sram4Buffer, StreamsTuple},
     PeripheralToMemory, Transfer,
 };
 
@@ -90,13 +90,14 @@ fn main() -> ! {
     let config = BdmaConfig::default().memory_increment(true);
 
     // We need to specify the direction with a type annotation
-    let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> = Transfer::init(
-        streams.0,
-        i2c,
-        unsafe { &mut BUFFER }, // uninitialised memory
-        None,
-        config,
-    );
+    let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+        Transfer::init_bdma(
+            streams.0,
+            i2c,
+            Sram4Buffer::new(unsafe { &mut BUFFER }), // uninitialised memory
+            None,
+            config,
+        );
 
     transfer.start(|i2c| {
         // This closure runs right after enabling the stream
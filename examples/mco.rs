@@ -32,8 +32,10 @@ fn main() -> ! {
     let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
     let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
 
-    let _mco1_pin = gpioa.pa8.into_alternate_af0();
-    let _mco2_pin = gpioc.pc9.into_alternate_af0().set_speed(Speed::High);
+    let mco1 = ccdr.clocks.mco1(gpioa.pa8.into_alternate_af0());
+    let mco2 = ccdr
+        .clocks
+        .mco2(gpioc.pc9.into_alternate_af0().set_speed(Speed::High));
 
     info!("");
     info!("stm32h7xx-hal example - MCO output");
@@ -44,18 +46,12 @@ fn main() -> ! {
     assert_eq!(ccdr.clocks.sys_ck().0, 100_000_000);
 
     // MCO
-    info!(
-        "mco1 = {} MHz",
-        ccdr.clocks.mco1_ck().unwrap().0 as f32 / 1e6
-    );
-    assert_eq!(ccdr.clocks.mco1_ck().unwrap().0, 24_000_000);
+    info!("mco1 = {} MHz", mco1.frequency().0 as f32 / 1e6);
+    assert_eq!(mco1.frequency().0, 24_000_000);
 
     // MCO
-    info!(
-        "mco2 = {} MHz",
-        ccdr.clocks.mco2_ck().unwrap().0 as f32 / 1e6
-    );
-    assert_eq!(ccdr.clocks.mco2_ck().unwrap().0, 25_600_000);
+    info!("mco2 = {} MHz", mco2.frequency().0 as f32 / 1e6);
+    assert_eq!(mco2.frequency().0, 25_600_000);
 
     info!("MCO outputs running!");
 
@@ -0,0 +1,87 @@
+//! Example that receives variable-length serial frames using the DMA
+//!
+//! Data is received continuously into a circular buffer. Each time the line
+//! becomes idle, the frame received since the previous idle line is echoed
+//! back.
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+#[macro_use]
+mod utilities;
+use stm32h7xx_hal::{pac, prelude::*};
+
+use stm32h7xx_hal::dma::dma::{DmaConfig, StreamsTuple};
+use stm32h7xx_hal::serial::CircularRx;
+
+use log::info;
+
+// DMA1/DMA2 cannot interact with our stack. Instead, buffers for use with the
+// DMA must be placed somewhere that DMA1/DMA2 can access. In this case we use
+// AXI SRAM.
+//
+// The runtime does not initialise these SRAM banks
+#[link_section = ".axisram.buffers"]
+static mut BUFFER: [u8; 256] = [0; 256];
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc
+        .sys_ck(400.mhz())
+        .pll1_q_ck(200.mhz())
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+    let tx = gpioc.pc10.into_alternate_af7();
+    let rx = gpioc.pc11.into_alternate_af7();
+
+    info!("");
+    info!("stm32h7xx-hal example - Serial DMA with idle line");
+    info!("");
+
+    // Configure the serial peripheral.
+    let serial = dp
+        .USART3
+        .serial(
+            (tx, rx),
+            1_000_000.bps(),
+            ccdr.peripheral.USART3,
+            &ccdr.clocks,
+        )
+        .unwrap();
+
+    let (mut tx, rx) = serial.split();
+
+    // Receive continuously on stream 1
+    let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+    let mut rx = CircularRx::new(
+        streams.1,
+        rx,
+        unsafe { &mut BUFFER },
+        DmaConfig::default(),
+    );
+
+    let mut frame = [0u8; 256];
+    loop {
+        if let Some(len) = rx.read_frame(&mut frame) {
+            info!("Received {} bytes", len);
+
+            for byte in frame[..len].iter() {
+                nb::block!(tx.write(*byte)).unwrap();
+            }
+        }
+    }
+}
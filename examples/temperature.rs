@@ -10,13 +10,7 @@ use cortex_m_rt::entry;
 #[macro_use]
 mod utilities;
 
-use stm32h7xx_hal::{
-    adc,
-    delay::Delay,
-    pac,
-    prelude::*,
-    signature::{TS_CAL_110, TS_CAL_30},
-};
+use stm32h7xx_hal::{adc, delay::Delay, pac, prelude::*};
 
 use log::info;
 
@@ -63,27 +57,21 @@ fn main() -> ! {
     // Set resolution
     adc.set_resolution(adc::Resolution::SIXTEENBIT);
 
-    // Setup Temperature Sensor on the disabled ADC
+    // Setup Temperature Sensor and VREFINT on the disabled ADC
     let mut channel = adc::Temperature::new();
     channel.enable(&adc);
+    let mut vrefint = adc::Vrefint::new();
+    vrefint.enable(&adc);
     delay.delay_us(25_u16);
     let mut adc = adc.enable();
 
-    let vdda = 2.500; // Volts
-
     loop {
-        let word: u32 =
-            adc.read(&mut channel).expect("Temperature read failed.");
-
-        // Average slope
-        let cal = (110.0 - 30.0)
-            / (TS_CAL_110::get().read() - TS_CAL_30::get().read()) as f32;
-        // Calibration values are measured at VDDA = 3.3 V ± 10 mV
-        let word_3v3 = word as f32 * vdda / 3.3;
-        // Linear interpolation
-        let temperature =
-            cal * (word_3v3 - TS_CAL_30::get().read() as f32) + 30.0;
-
-        info!("ADC reading: {}, Temperature: {:.1} °C", word, temperature);
+        // Measure VDDA using the factory calibration of VREFINT
+        let vdda = adc.read_vdda(&mut vrefint);
+
+        // Corrected with the factory calibration of the sensor
+        let temperature = adc.read_temperature(&mut channel, vdda);
+
+        info!("VDDA: {:.3} V, Temperature: {:.1} °C", vdda, temperature);
     }
 }
@@ -2,57 +2,176 @@
 //!
 //! ADC1 and ADC2 share a reset line. To initialise both of them, use the
 //! [`adc12`](adc12) method.
+//!
+//! # Scanning with DMA
+//!
+//! A sequence of up to 16 channels, each with its own sample time, can be
+//! converted continuously or each time a timer or EXTI trigger occurs. The
+//! results are transferred to a circular buffer by DMA. See
+//! [`start_scan`](Adc#method.start_scan).
+//!
+//! # Dual mode
+//!
+//! ADC1 and ADC2 can be combined into a [`DualAdc`], which converts on both
+//! ADCs at the same time or interleaved, and transfers pairs of results by
+//! DMA.
+//!
+//! # Injected conversions
+//!
+//! A sequence of up to 4 channels can be converted as an injected group,
+//! either once or each time a timer or EXTI trigger occurs. See
+//! [`start_injected`](Adc#method.start_injected).
+//!
+//! # Oversampling and differential channels
+//!
+//! Hardware oversampling of up to 1024x is configured with
+//! [`set_oversampling`](Adc#method.set_oversampling). Channels are single
+//! ended by default, and can be made differential with
+//! [`set_differential`](Adc#method.set_differential) while the ADC is
+//! disabled.
+//!
+//! # Resolution and alignment
+//!
+//! The resolution can be changed at any time with
+//! [`set_resolution`](Adc#method.set_resolution), and takes effect at the
+//! next conversion. Results are right aligned, or left aligned within 16
+//! bits by [`set_alignment`](Adc#method.set_alignment).
+//! [`sign_extend`](Adc#method.sign_extend) and
+//! [`differential_to_signed`](Adc#method.differential_to_signed) convert
+//! results to signed values.
+//!
+//! # Analog watchdog
+//!
+//! Analog watchdog 1 flags [`Event::AnalogWatchdog1`] when a conversion of
+//! one channel is outside a window, see
+//! [`enable_analog_watchdog`](Adc#method.enable_analog_watchdog). It is
+//! used by [`OvercurrentProtection`](crate::overcurrent).
+//!
+//! # Internal channels
+//!
+//! The temperature sensor, VBAT/4 and the internal reference voltage are
+//! converted by ADC3, or by ADC2 on RM0455 parts. The conversions are
+//! corrected with the factory calibration values in system memory by
+//! [`read_vdda`](Adc#method.read_vdda),
+//! [`read_temperature`](Adc#method.read_temperature) and
+//! [`read_vbat`](Adc#method.read_vbat).
+//!
+//! ```
+//! let mut vrefint = adc::Vrefint::new();
+//! let mut temperature = adc::Temperature::new();
+//! vrefint.enable(&adc);
+//! temperature.enable(&adc);
+//! let mut adc = adc.enable();
+//!
+//! let vdda = adc.read_vdda(&mut vrefint);
+//! let celsius = adc.read_temperature(&mut temperature, vdda);
+//! ```
 
 use crate::hal::adc::{Channel, OneShot};
 use crate::hal::blocking::delay::DelayUs;
 
+use crate::dma::{traits::TargetAddress, PeripheralToMemory};
+
 use core::marker::PhantomData;
 
 #[cfg(feature = "rm0455")]
 use crate::stm32::ADC12_COMMON;
 use crate::stm32::{ADC1, ADC2};
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 use crate::stm32::{ADC3, ADC3_COMMON};
 
 use crate::gpio::gpioa::{PA0, PA1, PA2, PA3, PA4, PA5, PA6, PA7};
 use crate::gpio::gpiob::{PB0, PB1};
 use crate::gpio::gpioc::{PC0, PC1, PC2, PC3, PC4, PC5};
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 use crate::gpio::gpiof::{PF10, PF3, PF4, PF5, PF6, PF7, PF8, PF9};
 use crate::gpio::gpiof::{PF11, PF12, PF13, PF14};
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 use crate::gpio::gpioh::{PH2, PH3, PH4, PH5};
 use crate::gpio::Analog;
 use crate::rcc::rec::AdcClkSelGetter;
 use crate::rcc::{rec, CoreClocks, ResetEnable};
+#[cfg(not(feature = "rm0468"))]
+use crate::signature::{TS_CAL_110, TS_CAL_30, VREFIN_CAL};
 use crate::time::Hertz;
 use stm32h7::Variant::Val;
 
+type DMAReq = crate::stm32::dmamux1::ccr::DMAREQ_ID_A;
+
 #[cfg(not(feature = "revision_v"))]
 const ADC_KER_CK_MAX: u32 = 36_000_000;
 
 #[cfg(feature = "revision_v")]
 const ADC_KER_CK_MAX: u32 = 100_000_000;
 
-#[cfg(not(feature = "rm0455"))]
-pub type Resolution = crate::stm32::adc3::cfgr::RES_A;
-#[cfg(feature = "rm0455")]
-pub type Resolution = crate::stm32::adc1::cfgr::RES_A;
-
-trait NumberOfBits {
-    fn number_of_bits(&self) -> u32;
+/// ADC resolution
+///
+/// The encoding of the RES\[2:0\] bits depends on the silicon revision, and
+/// is chosen by the `revision_v` feature. Refer to RM0433 Rev 7 - Chapter
+/// 25.6.4
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    /// 16-bit resolution
+    SIXTEENBIT,
+    /// 14-bit resolution
+    FOURTEENBIT,
+    /// 12-bit resolution
+    TWELVEBIT,
+    /// 10-bit resolution
+    TENBIT,
+    /// 8-bit resolution
+    EIGHTBIT,
 }
 
-impl NumberOfBits for Resolution {
-    fn number_of_bits(&self) -> u32 {
-        match *self {
-            Resolution::EIGHTBIT => 8,
-            Resolution::TENBIT => 10,
-            Resolution::TWELVEBIT => 12,
+impl Resolution {
+    /// Returns the number of bits of a conversion result
+    pub fn number_of_bits(self) -> u32 {
+        match self {
+            Resolution::SIXTEENBIT => 16,
             Resolution::FOURTEENBIT => 14,
-            _ => 16,
+            Resolution::TWELVEBIT => 12,
+            Resolution::TENBIT => 10,
+            Resolution::EIGHTBIT => 8,
+        }
+    }
+
+    /// Returns the RES\[2:0\] bits of revision Y silicon
+    #[cfg(not(feature = "revision_v"))]
+    fn bits(self) -> u8 {
+        match self {
+            Resolution::SIXTEENBIT => 0b000,
+            Resolution::FOURTEENBIT => 0b001,
+            Resolution::TWELVEBIT => 0b010,
+            Resolution::TENBIT => 0b011,
+            Resolution::EIGHTBIT => 0b100,
         }
     }
+
+    /// Returns the RES\[2:0\] bits of revision V silicon. The 14-bit and
+    /// 12-bit encodings of revision Y are kept as legacy modes, so use the
+    /// optimised ones
+    #[cfg(feature = "revision_v")]
+    fn bits(self) -> u8 {
+        match self {
+            Resolution::SIXTEENBIT => 0b000,
+            Resolution::FOURTEENBIT => 0b101,
+            Resolution::TWELVEBIT => 0b110,
+            Resolution::TENBIT => 0b011,
+            Resolution::EIGHTBIT => 0b111,
+        }
+    }
+}
+
+/// Alignment of conversion results within 16 bits
+///
+/// The ADC has no alignment bit, so left alignment is made with
+/// LSHIFT\[3:0\]. See [`set_alignment`](Adc#method.set_alignment)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alignment {
+    /// The least significant bit of the result is bit 0
+    Right,
+    /// The most significant bit of the result is bit 15
+    Left,
 }
 
 /// Enabled ADC (type state)
@@ -162,6 +281,217 @@ impl AdcCalLinear {
     }
 }
 
+/// Active edge of an external trigger
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerEdge {
+    Rising = 0b01,
+    Falling = 0b10,
+    Both = 0b11,
+}
+
+/// External trigger sources for regular conversions
+//
+// Refer to RM0433 Rev 7 - Table 211
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum RegularTriggerSource {
+    TIM1_CH1 = 0,
+    TIM1_CH2 = 1,
+    TIM1_CH3 = 2,
+    TIM2_CH2 = 3,
+    TIM3_TRGO = 4,
+    TIM4_CH4 = 5,
+    EXTI11 = 6,
+    TIM8_TRGO = 7,
+    TIM8_TRGO2 = 8,
+    TIM1_TRGO = 9,
+    TIM1_TRGO2 = 10,
+    TIM2_TRGO = 11,
+    TIM4_TRGO = 12,
+    TIM6_TRGO = 13,
+    TIM15_TRGO = 14,
+    TIM3_CH4 = 15,
+    HRTIM_ADCTRG1 = 16,
+    HRTIM_ADCTRG3 = 17,
+    LPTIM1_OUT = 18,
+    LPTIM2_OUT = 19,
+    LPTIM3_OUT = 20,
+}
+
+/// Trigger for a regular sequence
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegularTrigger {
+    /// The sequence is converted continuously
+    Continuous,
+    /// The sequence is converted each time the trigger occurs
+    External(RegularTriggerSource, TriggerEdge),
+}
+
+/// A sequence of up to 16 channels for regular conversion, each with its
+/// own sample time
+///
+/// ```
+/// let seq = RegularSequence::new()
+///     .channel(&pin_a, AdcSampleTime::T_16)
+///     .channel(&pin_b, AdcSampleTime::T_64);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegularSequence<ADC> {
+    channels: [(u8, AdcSampleTime); 16],
+    len: usize,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC> RegularSequence<ADC> {
+    /// Create an empty regular sequence
+    pub fn new() -> Self {
+        RegularSequence {
+            channels: [(0, AdcSampleTime::default()); 16],
+            len: 0,
+            _adc: PhantomData,
+        }
+    }
+
+    /// Adds the channel for `pin` to the end of the sequence, sampled for
+    /// `sample_time`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sequence already contains 16 channels
+    pub fn channel<PIN>(
+        mut self,
+        _pin: &PIN,
+        sample_time: AdcSampleTime,
+    ) -> Self
+    where
+        PIN: Channel<ADC, ID = u8>,
+    {
+        assert!(self.len < 16, "Regular sequences have at most 16 channels");
+        self.channels[self.len] = (PIN::channel(), sample_time);
+        self.len += 1;
+        self
+    }
+
+    /// Returns the number of channels in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the sequence contains no channels
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<ADC> Default for RegularSequence<ADC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// External trigger sources for injected conversions
+//
+// Refer to RM0433 Rev 7 - Table 212
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum InjectedTriggerSource {
+    TIM1_TRGO = 0,
+    TIM1_CH4 = 1,
+    TIM2_TRGO = 2,
+    TIM2_CH1 = 3,
+    TIM3_CH4 = 4,
+    TIM4_TRGO = 5,
+    EXTI15 = 6,
+    TIM8_CH4 = 7,
+    TIM1_TRGO2 = 8,
+    TIM8_TRGO = 9,
+    TIM8_TRGO2 = 10,
+    TIM3_CH3 = 11,
+    TIM3_TRGO = 12,
+    TIM3_CH1 = 13,
+    TIM6_TRGO = 14,
+    TIM15_TRGO = 15,
+    HRTIM_ADCTRG2 = 16,
+    HRTIM_ADCTRG4 = 17,
+    LPTIM1_OUT = 18,
+    LPTIM2_OUT = 19,
+    LPTIM3_OUT = 20,
+}
+
+/// Trigger for an injected sequence
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InjectedTrigger {
+    /// The sequence is converted once, immediately
+    Software,
+    /// The sequence is converted each time the trigger occurs
+    External(InjectedTriggerSource, TriggerEdge),
+}
+
+/// A sequence of up to 4 channels for injected conversion
+///
+/// ```
+/// let seq = InjectedSequence::new().channel(&pin_a).channel(&pin_b);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InjectedSequence<ADC> {
+    channels: [u8; 4],
+    len: usize,
+    _adc: PhantomData<ADC>,
+}
+
+impl<ADC> InjectedSequence<ADC> {
+    /// Create an empty injected sequence
+    pub fn new() -> Self {
+        InjectedSequence {
+            channels: [0; 4],
+            len: 0,
+            _adc: PhantomData,
+        }
+    }
+
+    /// Adds the channel for `pin` to the end of the sequence
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sequence already contains 4 channels
+    pub fn channel<PIN>(mut self, _pin: &PIN) -> Self
+    where
+        PIN: Channel<ADC, ID = u8>,
+    {
+        assert!(self.len < 4, "Injected sequences have at most 4 channels");
+        self.channels[self.len] = PIN::channel();
+        self.len += 1;
+        self
+    }
+
+    /// Returns the number of channels in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the sequence contains no channels
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<ADC> Default for InjectedSequence<ADC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// ADC Events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// End of an injected conversion
+    InjectedConversion,
+    /// End of an injected sequence
+    InjectedSequence,
+    /// Conversion outside the window of analog watchdog 1
+    AnalogWatchdog1,
+}
+
 macro_rules! adc_pins {
     ($ADC:ident, $($input:ty => $chan:expr),+ $(,)*) => {
         $(
@@ -204,6 +534,55 @@ macro_rules! adc_internal {
 
             adc_pins!($INT_ADC, $input => $chan);
         )+
+
+        impl Adc<$INT_ADC, Enabled> {
+            /// Converts `chan`, and scales the result to 16 bits, the
+            /// resolution of the factory calibration values
+            fn convert_16bit(&mut self, chan: u8) -> f32 {
+                let bits = self.get_resolution().number_of_bits();
+                let raw = self.convert(chan) >> self.get_lshift().value();
+                (raw << (16 - bits)) as f32
+            }
+
+            /// Measures VDDA in volts from the internal reference voltage,
+            /// using its factory calibration value. `vrefint` must be
+            /// enabled, and oversampling disabled
+            pub fn read_vdda(&mut self, _vrefint: &mut Vrefint) -> f32 {
+                let data = self.convert_16bit(<Vrefint as Channel<$INT_ADC>>::channel());
+
+                // The calibration value is measured at VDDA = 3.3 V
+                3.3 * f32::from(VREFIN_CAL::get().read()) / data
+            }
+
+            /// Measures the junction temperature in °C, using the factory
+            /// calibration values of the temperature sensor. `temperature`
+            /// must be enabled, and oversampling disabled
+            ///
+            /// `vdda` is the analog supply in volts, see
+            /// [`read_vdda`](#method.read_vdda)
+            pub fn read_temperature(&mut self, _temperature: &mut Temperature, vdda: f32) -> f32 {
+                let data = self.convert_16bit(<Temperature as Channel<$INT_ADC>>::channel());
+
+                // The calibration values are measured at VDDA = 3.3 V
+                let cal_30 = f32::from(TS_CAL_30::get().read());
+                let cal_110 = f32::from(TS_CAL_110::get().read());
+                let data_3v3 = data * vdda / 3.3;
+
+                // Linear interpolation
+                (110.0 - 30.0) / (cal_110 - cal_30) * (data_3v3 - cal_30) + 30.0
+            }
+
+            /// Measures VBAT in volts, through its internal divider by
+            /// 4. `vbat` must be enabled, and oversampling disabled
+            ///
+            /// `vdda` is the analog supply in volts, see
+            /// [`read_vdda`](#method.read_vdda)
+            pub fn read_vbat(&mut self, _vbat: &mut Vbat, vdda: f32) -> f32 {
+                let data = self.convert_16bit(<Vbat as Channel<$INT_ADC>>::channel());
+
+                4.0 * vdda * data / 65535.0
+            }
+        }
     };
 }
 
@@ -264,7 +643,7 @@ adc_pins!(ADC2,
           PA5<Analog> => 19,
 );
 
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 adc_pins!(ADC3,
           // 0, 1 are Pxy_C pins
           PF9<Analog> => 2,
@@ -283,7 +662,7 @@ adc_pins!(ADC3,
           PH4<Analog> => 15,
           PH5<Analog> => 16,
 );
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 adc_internal!(
     [ADC3, ADC3_COMMON];
 
@@ -316,8 +695,21 @@ pub trait AdcExt<ADC>: Sized {
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct StoredConfig(AdcSampleTime, Resolution, AdcLshift);
 
+/// ADC configuration error, see [try_new](Adc#method.try_new)
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// adc_ker_ck_input is not running
+    KernelClockNotRunning,
+    /// adc_ker_ck_input is faster than the datasheet maximum
+    KernelClockTooFast,
+}
+
 /// Get and check the adc_ker_ck_input
-fn check_clock(prec: &impl AdcClkSelGetter, clocks: &CoreClocks) -> Hertz {
+fn try_check_clock(
+    prec: &impl AdcClkSelGetter,
+    clocks: &CoreClocks,
+) -> Result<Hertz, ConfigError> {
     // Select Kernel Clock
     let adc_clock = match prec.get_kernel_clk_mux() {
         Val(rec::AdcClkSel::PLL2_P) => clocks.pll2_p_ck(),
@@ -325,15 +717,27 @@ fn check_clock(prec: &impl AdcClkSelGetter, clocks: &CoreClocks) -> Hertz {
         Val(rec::AdcClkSel::PER) => clocks.per_ck(),
         _ => unreachable!(),
     }
-    .expect("adc_ker_ck_input is not running!");
+    .ok_or(ConfigError::KernelClockNotRunning)?;
 
     // Check against datasheet requirements
-    assert!(
-        adc_clock.0 <= ADC_KER_CK_MAX,
-        "adc_ker_ck_input is too fast"
-    );
+    if adc_clock.0 > ADC_KER_CK_MAX {
+        return Err(ConfigError::KernelClockTooFast);
+    }
 
-    adc_clock
+    Ok(adc_clock)
+}
+
+/// Get and check the adc_ker_ck_input
+fn check_clock(prec: &impl AdcClkSelGetter, clocks: &CoreClocks) -> Hertz {
+    match try_check_clock(prec, clocks) {
+        Ok(adc_clock) => adc_clock,
+        Err(ConfigError::KernelClockNotRunning) => {
+            panic!("adc_ker_ck_input is not running!")
+        }
+        Err(ConfigError::KernelClockTooFast) => {
+            panic!("adc_ker_ck_input is too fast")
+        }
+    }
 }
 
 // ADC12 is a unique case where a single reset line is used to control two
@@ -378,7 +782,171 @@ pub fn adc12(
     (adc1, adc2)
 }
 
-#[cfg(not(feature = "rm0455"))]
+/// Dual ADC modes for ADC1 and ADC2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DualMode {
+    /// ADC1 and ADC2 convert their regular sequences at the same time. The
+    /// sequences should have the same length and sample times
+    RegularSimultaneous,
+    /// ADC1 and ADC2 convert the same channel alternately. ADC2 starts
+    /// `delay` + 1 ADC clock cycles after ADC1, where `delay` is 0 - 15
+    Interleaved { delay: u8 },
+}
+
+/// ADC1 and ADC2 operating as a master and slave pair
+///
+/// The results of both ADCs are packed into a single 32-bit word in the
+/// common data register, and transferred by one DMA stream. This ensures
+/// that results from the two ADCs cannot be interleaved incorrectly. Use
+/// [`unpack`](DualAdc::unpack) to split the words in the buffer.
+///
+/// ```
+/// let (adc1, adc2) = adc::adc12(dp.ADC1, dp.ADC2, &mut delay, prec, &clocks);
+/// let mut adc = DualAdc::new(adc1.enable(), adc2.enable(),
+///                            DualMode::RegularSimultaneous);
+///
+/// let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+///     Transfer::init(stream, adc, buffer, None, config);
+/// transfer.start(|adc| adc.start_scan(&seq1, &seq2, trigger));
+///
+/// for (adc1, adc2) in DualAdc::unpack(&buffer[..]) {
+///     // ...
+/// }
+/// ```
+pub struct DualAdc {
+    master: Adc<ADC1, Enabled>,
+    slave: Adc<ADC2, Enabled>,
+}
+
+impl DualAdc {
+    /// Combines ADC1 and ADC2 into a dual ADC in `mode`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two ADCs have different resolutions, or the interleaved
+    /// delay is greater than 15
+    pub fn new(
+        master: Adc<ADC1, Enabled>,
+        slave: Adc<ADC2, Enabled>,
+        mode: DualMode,
+    ) -> Self {
+        assert!(
+            master.get_resolution() == slave.get_resolution(),
+            "ADC1 and ADC2 must have the same resolution"
+        );
+
+        // Refer to RM0433 Rev 7 - Chapter 25.4.32
+        let (dual, delay) = match mode {
+            DualMode::RegularSimultaneous => (0b00110, 0),
+            DualMode::Interleaved { delay } => {
+                assert!(delay <= 15, "Interleaved delay is 0 - 15");
+                (0b00111, delay)
+            }
+        };
+
+        // unsafe: ADC1 and ADC2 are owned by this struct
+        let common = unsafe { &*ADC12_COMMON::ptr() };
+        common.ccr.modify(|_, w| unsafe {
+            w.dual().bits(dual).delay().bits(delay).damdf().bits(0b10) // 32-bit data, two 16-bit results
+        });
+
+        DualAdc { master, slave }
+    }
+
+    /// Starts scanning the regular sequences `master_seq` on ADC1 and
+    /// `slave_seq` on ADC2, with the results transferred by DMA from the
+    /// common data register
+    ///
+    /// Set up a circular DMA transfer from this dual ADC before calling this
+    /// method. `trigger` starts conversions on both ADCs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either sequence is empty, or in regular simultaneous mode
+    /// if the sequences have different lengths
+    pub fn start_scan(
+        &mut self,
+        master_seq: &RegularSequence<ADC1>,
+        slave_seq: &RegularSequence<ADC2>,
+        trigger: RegularTrigger,
+    ) {
+        let common = unsafe { &*ADC12_COMMON::ptr() };
+        if common.ccr.read().dual().bits() == 0b00110 {
+            assert!(
+                master_seq.len() == slave_seq.len(),
+                "Sequences must have the same length"
+            );
+        }
+
+        // The slave is triggered by the master, and results are transferred
+        // from the common data register by the master's DMA request
+        self.slave
+            .configure_scan(slave_seq, RegularTrigger::Continuous, 0b00);
+        self.master.configure_scan(master_seq, trigger, 0b11);
+
+        self.slave
+            .rb
+            .isr
+            .modify(|_, w| w.eoc().set_bit().eos().set_bit().ovr().set_bit());
+        self.master
+            .rb
+            .isr
+            .modify(|_, w| w.eoc().set_bit().eos().set_bit().ovr().set_bit());
+        self.master.rb.cr.modify(|_, w| w.adstart().set_bit());
+    }
+
+    /// Stops scanning on both ADCs
+    pub fn stop_scan(&mut self) {
+        self.master.stop_scan();
+        self.slave.stop_scan();
+    }
+
+    /// Returns `true` if a result was lost on either ADC because it was not
+    /// read in time. Clears the flags
+    pub fn check_overrun(&mut self) -> bool {
+        let master = self.master.check_overrun();
+        let slave = self.slave.check_overrun();
+        master || slave
+    }
+
+    /// Splits a word from the common data register into the (ADC1, ADC2)
+    /// results
+    pub fn unpack_word(word: u32) -> (u16, u16) {
+        (word as u16, (word >> 16) as u16)
+    }
+
+    /// Returns an iterator over the (ADC1, ADC2) results in a buffer of
+    /// words from the common data register
+    pub fn unpack(buffer: &[u32]) -> impl Iterator<Item = (u16, u16)> + '_ {
+        buffer.iter().map(|&word| Self::unpack_word(word))
+    }
+
+    /// Returns ADC1 and ADC2 to independent mode
+    pub fn split(mut self) -> (Adc<ADC1, Enabled>, Adc<ADC2, Enabled>) {
+        self.stop_scan();
+
+        let common = unsafe { &*ADC12_COMMON::ptr() };
+        common.ccr.modify(|_, w| unsafe {
+            w.dual().bits(0b00000).delay().bits(0).damdf().bits(0b00)
+        });
+
+        (self.master, self.slave)
+    }
+}
+
+unsafe impl TargetAddress<PeripheralToMemory> for DualAdc {
+    #[inline(always)]
+    fn address(&self) -> usize {
+        let common = unsafe { &*ADC12_COMMON::ptr() };
+        &common.cdr as *const _ as usize
+    }
+
+    type MemSize = u32;
+
+    const REQUEST_LINE: Option<u8> = Some(DMAReq::ADC1_DMA as u8);
+}
+
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 /// Freeing both the periperhal and PREC is possible for ADC3
 impl<ED> Adc<ADC3, ED> {
     /// Releases the ADC peripheral
@@ -397,7 +965,8 @@ macro_rules! adc_hal {
     ($(
         $ADC:ident: (
             $adcX: ident,
-            $Rec:ident
+            $Rec:ident,
+            $dmareq:ident
         )
     ),+ $(,)*) => {
         $(
@@ -420,14 +989,38 @@ macro_rules! adc_hal {
                 /// performs a boot-time calibration.
                 pub fn $adcX(adc: $ADC, delay: &mut impl DelayUs<u8>,
                              prec: rec::$Rec, clocks: &CoreClocks
+                ) -> Self {
+                    // Check adc_ker_ck_input
+                    check_clock(&prec, clocks);
+
+                    Self::init(adc, delay, prec)
+                }
+
+                /// Initialise ADC
+                ///
+                /// Sets all configurable parameters to one-shot defaults,
+                /// performs a boot-time calibration. Returns an error if
+                /// adc_ker_ck_input is not running or is too fast. The
+                /// error is returned with the peripheral and its `rec`, so
+                /// that the peripheral can be configured again.
+                pub fn try_new(adc: $ADC, delay: &mut impl DelayUs<u8>,
+                               prec: rec::$Rec, clocks: &CoreClocks
+                ) -> Result<Self, (ConfigError, $ADC, rec::$Rec)> {
+                    // Check adc_ker_ck_input
+                    if let Err(e) = try_check_clock(&prec, clocks) {
+                        return Err((e, adc, prec));
+                    }
+
+                    Ok(Self::init(adc, delay, prec))
+                }
+
+                fn init(adc: $ADC, delay: &mut impl DelayUs<u8>,
+                        prec: rec::$Rec
                 ) -> Self {
                     // Consume ADC register block, produce Self with default
                     // settings
                     let mut adc = Self::default_from_rb(adc);
 
-                    // Check adc_ker_ck_input
-                    check_clock(&prec, clocks);
-
                     // Enable AHB clock
                     let prec = prec.enable();
 
@@ -506,6 +1099,47 @@ macro_rules! adc_hal {
                     }
                 }
 
+                /// Calibrates the ADC for differential channels
+                ///
+                /// Note: The ADC must be disabled
+                pub fn calibrate_differential(&mut self) {
+                    // Refer to RM0433 Rev 6 - Chapter 24.4.8
+                    self.check_calibration_conditions();
+
+                    // differential channels
+                    self.rb.cr.modify(|_, w|
+                        w.adcaldif().set_bit()
+                            .adcallin().set_bit()
+                    );
+                    // calibrate
+                    self.rb.cr.modify(|_, w| w.adcal().set_bit());
+                    while self.rb.cr.read().adcal().bit_is_set() {}
+                }
+
+                /// Sets the channel for `pin` to differential mode
+                ///
+                /// The positive input is `pin`, and the negative input is the
+                /// INNx pin of the same channel, see the datasheet. The
+                /// result of a conversion is offset binary: mid-scale is a
+                /// differential input of zero.
+                ///
+                /// Run [`calibrate_differential`](#method.calibrate_differential)
+                /// before using differential channels.
+                pub fn set_differential<PIN>(&mut self, _pin: &PIN, differential: bool)
+                where
+                    PIN: Channel<$ADC, ID = u8>,
+                {
+                    // Refer to RM0433 Rev 6 - Chapter 24.4.7
+                    let chan = PIN::channel();
+                    self.rb.difsel.modify(|r, w| unsafe {
+                        if differential {
+                            w.difsel().bits(r.difsel().bits() | (1 << chan))
+                        } else {
+                            w.difsel().bits(r.difsel().bits() & !(1 << chan))
+                        }
+                    });
+                }
+
                 /// Configuration process prior to enabling the ADC
                 ///
                 /// Note: the ADC must be disabled
@@ -569,27 +1203,31 @@ macro_rules! adc_hal {
                 }
 
                 fn set_chan_smp(&mut self, chan: u8) {
+                    self.set_chan_smp_time(chan, self.get_sample_time());
+                }
+
+                fn set_chan_smp_time(&mut self, chan: u8, t_samp: AdcSampleTime) {
                     match chan {
-                        0 => self.rb.smpr1.modify(|_, w| w.smp0().bits(self.get_sample_time().into())),
-                        1 => self.rb.smpr1.modify(|_, w| w.smp1().bits(self.get_sample_time().into())),
-                        2 => self.rb.smpr1.modify(|_, w| w.smp2().bits(self.get_sample_time().into())),
-                        3 => self.rb.smpr1.modify(|_, w| w.smp3().bits(self.get_sample_time().into())),
-                        4 => self.rb.smpr1.modify(|_, w| w.smp4().bits(self.get_sample_time().into())),
-                        5 => self.rb.smpr1.modify(|_, w| w.smp5().bits(self.get_sample_time().into())),
-                        6 => self.rb.smpr1.modify(|_, w| w.smp6().bits(self.get_sample_time().into())),
-                        7 => self.rb.smpr1.modify(|_, w| w.smp7().bits(self.get_sample_time().into())),
-                        8 => self.rb.smpr1.modify(|_, w| w.smp8().bits(self.get_sample_time().into())),
-                        9 => self.rb.smpr1.modify(|_, w| w.smp9().bits(self.get_sample_time().into())),
-                        10 => self.rb.smpr2.modify(|_, w| w.smp10().bits(self.get_sample_time().into())),
-                        11 => self.rb.smpr2.modify(|_, w| w.smp11().bits(self.get_sample_time().into())),
-                        12 => self.rb.smpr2.modify(|_, w| w.smp12().bits(self.get_sample_time().into())),
-                        13 => self.rb.smpr2.modify(|_, w| w.smp13().bits(self.get_sample_time().into())),
-                        14 => self.rb.smpr2.modify(|_, w| w.smp14().bits(self.get_sample_time().into())),
-                        15 => self.rb.smpr2.modify(|_, w| w.smp15().bits(self.get_sample_time().into())),
-                        16 => self.rb.smpr2.modify(|_, w| w.smp16().bits(self.get_sample_time().into())),
-                        17 => self.rb.smpr2.modify(|_, w| w.smp17().bits(self.get_sample_time().into())),
-                        18 => self.rb.smpr2.modify(|_, w| w.smp18().bits(self.get_sample_time().into())),
-                        19 => self.rb.smpr2.modify(|_, w| w.smp19().bits(self.get_sample_time().into())),
+                        0 => self.rb.smpr1.modify(|_, w| w.smp0().bits(t_samp.into())),
+                        1 => self.rb.smpr1.modify(|_, w| w.smp1().bits(t_samp.into())),
+                        2 => self.rb.smpr1.modify(|_, w| w.smp2().bits(t_samp.into())),
+                        3 => self.rb.smpr1.modify(|_, w| w.smp3().bits(t_samp.into())),
+                        4 => self.rb.smpr1.modify(|_, w| w.smp4().bits(t_samp.into())),
+                        5 => self.rb.smpr1.modify(|_, w| w.smp5().bits(t_samp.into())),
+                        6 => self.rb.smpr1.modify(|_, w| w.smp6().bits(t_samp.into())),
+                        7 => self.rb.smpr1.modify(|_, w| w.smp7().bits(t_samp.into())),
+                        8 => self.rb.smpr1.modify(|_, w| w.smp8().bits(t_samp.into())),
+                        9 => self.rb.smpr1.modify(|_, w| w.smp9().bits(t_samp.into())),
+                        10 => self.rb.smpr2.modify(|_, w| w.smp10().bits(t_samp.into())),
+                        11 => self.rb.smpr2.modify(|_, w| w.smp11().bits(t_samp.into())),
+                        12 => self.rb.smpr2.modify(|_, w| w.smp12().bits(t_samp.into())),
+                        13 => self.rb.smpr2.modify(|_, w| w.smp13().bits(t_samp.into())),
+                        14 => self.rb.smpr2.modify(|_, w| w.smp14().bits(t_samp.into())),
+                        15 => self.rb.smpr2.modify(|_, w| w.smp15().bits(t_samp.into())),
+                        16 => self.rb.smpr2.modify(|_, w| w.smp16().bits(t_samp.into())),
+                        17 => self.rb.smpr2.modify(|_, w| w.smp17().bits(t_samp.into())),
+                        18 => self.rb.smpr2.modify(|_, w| w.smp18().bits(t_samp.into())),
+                        19 => self.rb.smpr2.modify(|_, w| w.smp19().bits(t_samp.into())),
                         _ => unreachable!(),
                     }
                 }
@@ -600,7 +1238,7 @@ macro_rules! adc_hal {
                     self.check_conversion_conditions();
 
                     // Set resolution
-                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().bits()) });
 
                     // Set LSHIFT[3:0]
                     self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
@@ -644,6 +1282,306 @@ macro_rules! adc_hal {
                     }
                 }
 
+                /// Starts scanning the regular sequence `seq`, with the results
+                /// transferred by DMA
+                ///
+                /// Set up a circular DMA transfer from this ADC before
+                /// calling this method. Each conversion result is one 16-bit
+                /// word, and the buffer holds the results of the sequence in
+                /// order. Use the half transfer and transfer complete flags of
+                /// the DMA stream to process one half of the buffer while
+                /// the other is being written.
+                ///
+                /// ```
+                /// let config = DmaConfig::default()
+                ///     .memory_increment(true)
+                ///     .circular_buffer(true)
+                ///     .half_transfer_interrupt(true)
+                ///     .transfer_complete_interrupt(true);
+                /// let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+                ///     Transfer::init(stream, adc1, buffer, None, config);
+                ///
+                /// transfer.start(|adc| adc.start_scan(&seq, trigger));
+                /// ```
+                ///
+                /// The current resolution is used. With oversampling, only the
+                /// lower 16 bits of each result are transferred.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `seq` is empty
+                pub fn start_scan(&mut self, seq: &RegularSequence<$ADC>,
+                                  trigger: RegularTrigger) {
+                    self.configure_scan(seq, trigger, 0b11);
+
+                    // Clear flags and start
+                    self.rb.isr.modify(|_, w| w.eoc().set_bit().eos().set_bit().ovr().set_bit());
+                    self.rb.cr.modify(|_, w| w.adstart().set_bit());
+                }
+
+                /// Configures scanning of the regular sequence `seq`,
+                /// with DMNGT[1:0] set to `dmngt`
+                fn configure_scan(&mut self, seq: &RegularSequence<$ADC>,
+                                  trigger: RegularTrigger, dmngt: u8) {
+                    // Refer to RM0433 Rev 7 - Chapter 25.4.18
+                    assert!(!seq.is_empty(), "Regular sequence is empty");
+                    self.check_conversion_conditions();
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().bits()) });
+
+                    // Set LSHIFT[3:0]
+                    self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
+
+                    // Preselect channels and set sample times
+                    let channels = &seq.channels[..seq.len];
+                    for &(chan, t_samp) in channels {
+                        self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | (1 << chan)) });
+                        self.set_chan_smp_time(chan, t_samp);
+                    }
+
+                    // Sequence registers. SQ1 - SQ4 follow L in SQR1, then
+                    // 5 channels per register
+                    let sq = |n: usize| u32::from(channels.get(n).map(|c| c.0).unwrap_or(0));
+                    let sqr = |first: usize| (0..5).fold(0, |acc, i| acc | (sq(first + i) << (6 * i)));
+                    let sqr1 = (seq.len as u32 - 1)
+                        | (sq(0) << 6) | (sq(1) << 12) | (sq(2) << 18) | (sq(3) << 24);
+                    self.rb.sqr1.write(|w| unsafe { w.bits(sqr1) });
+                    self.rb.sqr2.write(|w| unsafe { w.bits(sqr(4)) });
+                    self.rb.sqr3.write(|w| unsafe { w.bits(sqr(9)) });
+                    self.rb.sqr4.write(|w| unsafe { w.bits(sqr(14) & 0xFFF) });
+
+                    let (cont, extsel, exten) = match trigger {
+                        RegularTrigger::Continuous => (true, 0, 0),
+                        RegularTrigger::External(source, edge) => (false, source as u8, edge as u8),
+                    };
+
+                    self.rb.cfgr.modify(|_, w| unsafe {
+                        w.cont().bit(cont)
+                            .discen().clear_bit()
+                            .extsel().bits(extsel)
+                            .exten().bits(exten)
+                            .dmngt().bits(dmngt)
+                    });
+                }
+
+                /// Stops scanning the regular sequence, and returns to
+                /// single conversion mode
+                pub fn stop_scan(&mut self) {
+                    if self.rb.cr.read().adstart().bit_is_set() {
+                        self.stop_regular_conversion();
+                    }
+
+                    // Disable preselection of all channels except those used
+                    // by injected conversions
+                    if self.rb.cr.read().jadstart().bit_is_clear() {
+                        self.rb.pcsel.reset();
+                    }
+
+                    // Single conversion mode, Software trigger
+                    self.rb.cfgr.modify(|_, w| unsafe {
+                        w.cont().clear_bit()
+                            .exten().disabled()
+                            .discen().set_bit()
+                            .dmngt().bits(0b00)
+                    });
+                }
+
+                /// Returns `true` if a regular conversion result was lost
+                /// because it was not read in time. Clears the flag
+                pub fn check_overrun(&mut self) -> bool {
+                    let ovr = self.rb.isr.read().ovr().bit_is_set();
+                    if ovr {
+                        self.rb.isr.modify(|_, w| w.ovr().set_bit());
+                    }
+                    ovr
+                }
+
+                /// Starts injected conversions of `seq`
+                ///
+                /// With [`InjectedTrigger::Software`] the sequence is
+                /// converted once. With an external trigger, the sequence is
+                /// converted each time the trigger occurs until
+                /// [`stop_injected`](#method.stop_injected) is called. Use
+                /// [`read_injected`](#method.read_injected) or
+                /// [`listen`](#method.listen) for the results.
+                ///
+                /// The current sample time and resolution are used. Regular
+                /// conversions cannot be started until the injected
+                /// conversions are stopped.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `seq` is empty
+                pub fn start_injected(&mut self, seq: &InjectedSequence<$ADC>,
+                                      trigger: InjectedTrigger) {
+                    // Refer to RM0433 Rev 6 - Chapter 24.4.21
+                    assert!(!seq.is_empty(), "Injected sequence is empty");
+                    self.check_conversion_conditions();
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().bits()) });
+
+                    // Set LSHIFT[3:0]
+                    self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
+
+                    // Preselect channels and set sample times
+                    let channels = &seq.channels[..seq.len];
+                    for &chan in channels {
+                        self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | (1 << chan)) });
+                        self.set_chan_smp(chan);
+                    }
+
+                    let (jextsel, jexten) = match trigger {
+                        InjectedTrigger::Software => (0, 0),
+                        InjectedTrigger::External(source, edge) => (source as u8, edge as u8),
+                    };
+                    let jsq = |n: usize| channels.get(n).copied().unwrap_or(0);
+
+                    self.rb.jsqr.write(|w| unsafe {
+                        w.jl().bits((seq.len - 1) as u8)
+                            .jextsel().bits(jextsel)
+                            .jexten().bits(jexten)
+                            .jsq1().bits(jsq(0))
+                            .jsq2().bits(jsq(1))
+                            .jsq3().bits(jsq(2))
+                            .jsq4().bits(jsq(3))
+                    });
+
+                    // Clear flags and start
+                    self.rb.isr.modify(|_, w| w.jeoc().set_bit().jeos().set_bit());
+                    self.rb.cr.modify(|_, w| w.jadstart().set_bit());
+                }
+
+                /// Stops injected conversions
+                pub fn stop_injected(&mut self) {
+                    if self.rb.cr.read().jadstart().bit_is_set() {
+                        self.stop_injected_conversion();
+                    }
+
+                    // Disable preselection of the injected channels
+                    let jsqr = self.rb.jsqr.read();
+                    let len = jsqr.jl().bits() + 1;
+                    let channels = [
+                        jsqr.jsq1().bits(),
+                        jsqr.jsq2().bits(),
+                        jsqr.jsq3().bits(),
+                        jsqr.jsq4().bits(),
+                    ];
+                    for &chan in &channels[..len as usize] {
+                        self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() & !(1 << chan)) });
+                    }
+                }
+
+                /// Returns the results of the last injected sequence, in the
+                /// order of the sequence. Entries after the end of the
+                /// sequence are zero.
+                ///
+                /// Returns `WouldBlock` if the sequence has not completed
+                /// since the last call.
+                pub fn read_injected(&mut self) -> nb::Result<[u32; 4], ()> {
+                    if self.rb.isr.read().jeos().bit_is_clear() {
+                        return Err(nb::Error::WouldBlock);
+                    }
+
+                    let len = self.rb.jsqr.read().jl().bits() + 1;
+                    let mut result = [
+                        self.rb.jdr1.read().bits(),
+                        self.rb.jdr2.read().bits(),
+                        self.rb.jdr3.read().bits(),
+                        self.rb.jdr4.read().bits(),
+                    ];
+                    for r in result.iter_mut().skip(len as usize) {
+                        *r = 0;
+                    }
+
+                    self.rb.isr.modify(|_, w| w.jeos().set_bit());
+                    Ok(result)
+                }
+
+                /// Start listening for `event`
+                pub fn listen(&mut self, event: Event) {
+                    match event {
+                        Event::InjectedConversion => self.rb.ier.modify(|_, w| w.jeocie().set_bit()),
+                        Event::InjectedSequence => self.rb.ier.modify(|_, w| w.jeosie().set_bit()),
+                        Event::AnalogWatchdog1 => self.rb.ier.modify(|_, w| w.awd1ie().set_bit()),
+                    }
+                }
+
+                /// Stop listening for `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    match event {
+                        Event::InjectedConversion => self.rb.ier.modify(|_, w| w.jeocie().clear_bit()),
+                        Event::InjectedSequence => self.rb.ier.modify(|_, w| w.jeosie().clear_bit()),
+                        Event::AnalogWatchdog1 => self.rb.ier.modify(|_, w| w.awd1ie().clear_bit()),
+                    }
+                    let _ = self.rb.ier.read();
+                    let _ = self.rb.ier.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns `true` if `event` is pending
+                pub fn is_pending(&self, event: Event) -> bool {
+                    match event {
+                        Event::InjectedConversion => self.rb.isr.read().jeoc().bit_is_set(),
+                        Event::InjectedSequence => self.rb.isr.read().jeos().bit_is_set(),
+                        Event::AnalogWatchdog1 => self.rb.isr.read().awd1().bit_is_set(),
+                    }
+                }
+
+                /// Clears the interrupt flag for `event`
+                pub fn unpend(&mut self, event: Event) {
+                    // Flags are cleared by writing 1
+                    match event {
+                        Event::InjectedConversion => self.rb.isr.modify(|_, w| w.jeoc().set_bit()),
+                        Event::InjectedSequence => self.rb.isr.modify(|_, w| w.jeos().set_bit()),
+                        Event::AnalogWatchdog1 => self.rb.isr.modify(|_, w| w.awd1().set_bit()),
+                    }
+                }
+
+                /// Guards `pin` with analog watchdog 1, which flags
+                /// [`Event::AnalogWatchdog1`] when a regular or injected
+                /// conversion of `pin` is below `low` or above `high`
+                ///
+                /// The thresholds are compared with the result at the
+                /// current resolution, before the left shift. The watchdog
+                /// can only be configured while no conversions are ongoing.
+                ///
+                /// # Panics
+                ///
+                /// Panics if a conversion is ongoing, or if `low` is above
+                /// `high`
+                pub fn enable_analog_watchdog<PIN>(&mut self, _pin: &PIN, low: u32, high: u32)
+                where
+                    PIN: Channel<$ADC, ID = u8>,
+                {
+                    // Refer to RM0433 Rev 7 - Chapter 25.4.28
+                    assert!(low <= high, "Watchdog window is empty");
+                    self.check_conversion_conditions();
+
+                    // The thresholds are 26 bits
+                    let max = (1 << 26) - 1;
+                    self.rb.ltr1.write(|w| unsafe { w.ltr1().bits(low.min(max)) });
+                    self.rb.htr1.write(|w| unsafe { w.htr1().bits(high.min(max)) });
+                    self.rb.cfgr.modify(|_, w| unsafe {
+                        w.awd1ch().bits(PIN::channel())
+                            .awd1sgl().set_bit()
+                            .awd1en().set_bit()
+                            .jawd1en().set_bit()
+                    });
+                    self.rb.isr.modify(|_, w| w.awd1().set_bit());
+                }
+
+                /// Disables analog watchdog 1
+                ///
+                /// # Panics
+                ///
+                /// Panics if a conversion is ongoing
+                pub fn disable_analog_watchdog(&mut self) {
+                    self.check_conversion_conditions();
+
+                    self.rb.cfgr.modify(|_, w| w.awd1en().clear_bit().jawd1en().clear_bit().awd1sgl().clear_bit());
+                }
+
                 /// Disable ADC
                 pub fn disable(mut self) -> Adc<$ADC, Disabled> {
                     // Refer to RM0433 Rev 6 - Chapter 24.4.9
@@ -723,6 +1661,91 @@ macro_rules! adc_hal {
                     self.lshift = lshift;
                 }
 
+                /// Aligns conversion results within 16 bits, by setting
+                /// the lshift for the current resolution
+                ///
+                /// Must be called again after the resolution is changed.
+                /// Left alignment is meant for conversions without
+                /// oversampling, whose results fit in 16 bits.
+                pub fn set_alignment(&mut self, alignment: Alignment) {
+                    let lshift = match alignment {
+                        Alignment::Right => 0,
+                        Alignment::Left => 16 - self.get_resolution().number_of_bits() as u8,
+                    };
+                    self.set_lshift(AdcLshift::new(lshift));
+                }
+
+                /// Returns the current alignment. Any lshift other than
+                /// that of left alignment is reported as right aligned
+                pub fn get_alignment(&self) -> Alignment {
+                    let left = 16 - self.get_resolution().number_of_bits() as u8;
+                    if left != 0 && self.get_lshift().value() == left {
+                        Alignment::Left
+                    } else {
+                        Alignment::Right
+                    }
+                }
+
+                /// Returns the width in bits of a conversion result with
+                /// the current resolution and lshift, without oversampling
+                fn sample_width(&self) -> u32 {
+                    self.get_resolution().number_of_bits() + self.get_lshift().value() as u32
+                }
+
+                /// Sign extends a two's complement conversion result of
+                /// the current resolution and lshift
+                ///
+                /// This restores the sign of a signed result that was
+                /// truncated, for example when it was transferred to a
+                /// buffer of `u16`. Without oversampling.
+                pub fn sign_extend(&self, sample: u32) -> i32 {
+                    let shift = 32 - self.sample_width().min(32);
+
+                    ((sample << shift) as i32) >> shift
+                }
+
+                /// Converts the offset binary result of a differential
+                /// channel to a signed value, where zero is a differential
+                /// input of zero. Without oversampling
+                pub fn differential_to_signed(&self, sample: u32) -> i32 {
+                    let mid_scale = 1 << (self.sample_width() - 1);
+
+                    sample as i32 - mid_scale
+                }
+
+                /// Enables hardware oversampling of regular and injected
+                /// conversions
+                ///
+                /// `ratio` conversions are accumulated and the sum is shifted
+                /// right by `rshift` bits. Without a shift, the result can be
+                /// up to 26 bits wide.
+                ///
+                /// Note: No conversion may be ongoing
+                ///
+                /// # Panics
+                ///
+                /// Panics if `ratio` is not in the range 1..=1024 or `rshift`
+                /// is greater than 11
+                pub fn set_oversampling(&mut self, ratio: u16, rshift: u8) {
+                    // Refer to RM0433 Rev 7 - Chapter 25.4.31
+                    assert!((1..=1024).contains(&ratio), "Oversampling ratio must be in range of 1..=1024");
+                    assert!(rshift <= 11, "Oversampling shift must be in range of 0..=11");
+
+                    self.rb.cfgr2.modify(|_, w| unsafe {
+                        w.osvr().bits(ratio - 1)
+                            .ovss().bits(rshift)
+                            .rovse().set_bit()
+                            .jovse().set_bit()
+                    });
+                }
+
+                /// Disables hardware oversampling
+                ///
+                /// Note: No conversion may be ongoing
+                pub fn disable_oversampling(&mut self) {
+                    self.rb.cfgr2.modify(|_, w| w.rovse().clear_bit().jovse().clear_bit());
+                }
+
                 /// Returns the largest possible sample value for the current settings
                 pub fn max_sample(&self) -> u32 {
                     ((1 << self.get_resolution().number_of_bits() as u32) - 1) << self.get_lshift().value() as u32
@@ -788,6 +1811,17 @@ macro_rules! adc_hal {
                 }
             }
 
+            unsafe impl TargetAddress<PeripheralToMemory> for Adc<$ADC, Enabled> {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    &self.rb.dr as *const _ as usize
+                }
+
+                type MemSize = u16;
+
+                const REQUEST_LINE: Option<u8> = Some(DMAReq::$dmareq as u8);
+            }
+
             impl<WORD, PIN> OneShot<$ADC, WORD, PIN> for Adc<$ADC, Enabled>
             where
                 WORD: From<u32>,
@@ -805,9 +1839,9 @@ macro_rules! adc_hal {
 }
 
 adc_hal!(
-    ADC1: (adc1, Adc12), // ADC1
-    ADC2: (adc2, Adc12), // ADC2
+    ADC1: (adc1, Adc12, ADC1_DMA), // ADC1
+    ADC2: (adc2, Adc12, ADC2_DMA), // ADC2
 );
 
-#[cfg(not(feature = "rm0455"))]
-adc_hal!(ADC3: (adc3, Adc3));
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+adc_hal!(ADC3: (adc3, Adc3, ADC3_DMA));
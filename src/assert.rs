@@ -0,0 +1,44 @@
+//! Assertions on the invariants of the HAL
+//!
+//! Drivers check invariants with `hal_assert!` and panic with
+//! `hal_panic!`, so that the policy for a violation is chosen by
+//! features. So far these are used by `rcc` and by the kernel clock checks
+//! of `serial` and `spi`:
+//!
+//! * `assert-defmt` panics with `defmt::panic!`, so that the message is
+//!   formatted on the host. Format strings are literals for this reason
+//! * `assert-error` returns an error for violations that a function can
+//!   report in its result, for example a clock that is out of range in
+//!   [`try_freeze`](crate::rcc::Rcc::try_freeze). Other violations still
+//!   panic
+
+/// Panics with a message formatted from a string literal, using
+/// `defmt::panic!` with the `assert-defmt` feature
+macro_rules! hal_panic {
+    ($msg:literal $(, $arg:expr)*) => {{
+        #[cfg(feature = "assert-defmt")]
+        defmt::panic!($msg $(, $arg)*);
+        #[cfg(not(feature = "assert-defmt"))]
+        core::panic!($msg $(, $arg)*);
+    }};
+}
+
+/// Asserts that `$cond` holds
+///
+/// With an error `$err`, the enclosing function returns `Err($err.into())`
+/// instead of panicking when the `assert-error` feature is enabled
+macro_rules! hal_assert {
+    ($cond:expr, $err:expr, $msg:literal) => {
+        if !$cond {
+            #[cfg(feature = "assert-error")]
+            return Err($err.into());
+            #[cfg(not(feature = "assert-error"))]
+            hal_panic!($msg);
+        }
+    };
+    ($cond:expr, $msg:literal) => {
+        if !$cond {
+            hal_panic!($msg);
+        }
+    };
+}
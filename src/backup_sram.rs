@@ -0,0 +1,142 @@
+//! Backup SRAM
+//!
+//! The 4kB backup SRAM is in the backup domain. While the backup regulator
+//! is enabled, it retains its contents in Standby mode, and in VBAT mode
+//! when the device is powered from a battery.
+//!
+//! # Example
+//!
+//! ```
+//! let pwr = dp.PWR.constrain();
+//! let mut pwrcfg = pwr.freeze();
+//! let backup = pwrcfg.backup().unwrap();
+//!
+//! let mut sram = BackupSram::new(backup.BKPRAM);
+//! let boot_count = &mut sram.words_mut()[0];
+//! *boot_count += 1;
+//! ```
+//!
+//! The backup SRAM is also kept across resets of the core, and the
+//! [RTC](crate::rtc::Rtc) has 32 backup registers that are kept without the
+//! backup regulator. Both can store records with
+//! [`persist`](crate::persist).
+//!
+//! # Data cache
+//!
+//! The backup SRAM is cacheable by default. Data that is still in the data
+//! cache when power is lost is not retained, so either configure the
+//! region as write-through with the MPU, or clean the data cache after
+//! writing.
+
+use core::mem;
+use core::slice;
+
+use crate::persist::WordStorage;
+use crate::rcc::backup::BkpRam;
+
+/// Address of the backup SRAM
+pub const BACKUP_SRAM_ADDRESS: usize = 0x3880_0000;
+/// Size of the backup SRAM in bytes
+pub const BACKUP_SRAM_SIZE: usize = 4 * 1024;
+
+/// The backup SRAM
+pub struct BackupSram {
+    prec: BkpRam,
+}
+
+impl BackupSram {
+    /// Enables the backup regulator and the backup SRAM clock, and waits
+    /// for the backup regulator to be ready
+    ///
+    /// Write access to the backup domain was already granted by
+    /// `Pwr::freeze`. The contents of the backup SRAM are not changed.
+    pub fn new(mut prec: BkpRam) -> Self {
+        prec.enable();
+
+        BackupSram { prec }
+    }
+
+    /// Returns the backup SRAM as bytes
+    pub fn as_slice(&self) -> &[u8] {
+        // NOTE(unsafe) The backup SRAM is owned by self, and any content
+        // of it is valid as bytes
+        unsafe {
+            slice::from_raw_parts(
+                BACKUP_SRAM_ADDRESS as *const u8,
+                BACKUP_SRAM_SIZE,
+            )
+        }
+    }
+
+    /// Returns the backup SRAM as mutable bytes
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // NOTE(unsafe) As above
+        unsafe {
+            slice::from_raw_parts_mut(
+                BACKUP_SRAM_ADDRESS as *mut u8,
+                BACKUP_SRAM_SIZE,
+            )
+        }
+    }
+
+    /// Returns the backup SRAM as 32-bit words
+    pub fn words(&self) -> &[u32] {
+        // NOTE(unsafe) As above. The address is word aligned
+        unsafe {
+            slice::from_raw_parts(
+                BACKUP_SRAM_ADDRESS as *const u32,
+                BACKUP_SRAM_SIZE / 4,
+            )
+        }
+    }
+
+    /// Returns the backup SRAM as mutable 32-bit words
+    pub fn words_mut(&mut self) -> &mut [u32] {
+        // NOTE(unsafe) As above
+        unsafe {
+            slice::from_raw_parts_mut(
+                BACKUP_SRAM_ADDRESS as *mut u32,
+                BACKUP_SRAM_SIZE / 4,
+            )
+        }
+    }
+
+    /// Returns the start of the backup SRAM as a `T`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` is larger than the backup SRAM, or is aligned to more
+    /// than 4kB
+    ///
+    /// # Safety
+    ///
+    /// The contents of the backup SRAM are undefined after the backup
+    /// domain loses power, so any bit pattern must be a valid `T`. Prefer
+    /// types made of integers, and check the contents, for example with a
+    /// magic number or CRC.
+    pub unsafe fn as_typed_mut<T>(&mut self) -> &mut T {
+        assert!(mem::size_of::<T>() <= BACKUP_SRAM_SIZE);
+        assert!(mem::align_of::<T>() <= BACKUP_SRAM_SIZE);
+
+        &mut *(BACKUP_SRAM_ADDRESS as *mut T)
+    }
+
+    /// Disables the backup SRAM clock and the backup regulator. The
+    /// contents of the backup SRAM are lost in Standby and VBAT mode
+    pub fn free(mut self) -> BkpRam {
+        self.prec.disable();
+        self.prec
+    }
+}
+
+impl WordStorage for BackupSram {
+    fn words(&self) -> usize {
+        BACKUP_SRAM_SIZE / 4
+    }
+    fn read_word(&self, index: usize) -> u32 {
+        BackupSram::words(self)[index]
+    }
+    fn write_word(&mut self, index: usize, value: u32) {
+        self.words_mut()[index] = value;
+    }
+}
@@ -0,0 +1,212 @@
+//! Bit-banged I2C master
+//!
+//! SCL and SDA must be open-drain outputs with pull-up resistors. Clock
+//! stretching by the slave is supported.
+
+use crate::hal::blocking::i2c::{Read, Write, WriteRead};
+use crate::hal::digital::v2::{InputPin, OutputPin};
+use crate::hal::timer::{CountDown, Periodic};
+use crate::time::Hertz;
+
+/// Bit-banged I2C error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error<E> {
+    /// Error from a pin
+    Pin(E),
+    /// No ack received
+    NotAcknowledge,
+}
+
+/// Bit-banged I2C master
+pub struct I2c<SCL, SDA, TIMER> {
+    scl: SCL,
+    sda: SDA,
+    timer: TIMER,
+}
+
+impl<SCL, SDA, TIMER, E> I2c<SCL, SDA, TIMER>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+    /// Creates a bit-banged I2C master. `timer` is started at twice
+    /// `frequency`
+    pub fn new<T>(scl: SCL, sda: SDA, mut timer: TIMER, frequency: T) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        timer.start(Hertz(frequency.into().0 * 2));
+
+        I2c { scl, sda, timer }
+    }
+
+    /// Releases the pins and the timer
+    pub fn free(self) -> (SCL, SDA, TIMER) {
+        (self.scl, self.sda, self.timer)
+    }
+
+    /// Waits for half a bit period
+    fn wait(&mut self) {
+        nb::block!(self.timer.wait()).ok();
+    }
+
+    /// Releases SCL, and waits while a slave stretches the clock
+    fn scl_high(&mut self) -> Result<(), Error<E>> {
+        self.scl.set_high().map_err(Error::Pin)?;
+        while self.scl.is_low().map_err(Error::Pin)? {}
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<(), Error<E>> {
+        // SDA and SCL are released, also for a repeated start
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.scl_high()?;
+        self.wait();
+
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait();
+        self.scl.set_low().map_err(Error::Pin)?;
+        self.wait();
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Error<E>> {
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait();
+        self.scl_high()?;
+        self.wait();
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait();
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error<E>> {
+        if bit {
+            self.sda.set_high().map_err(Error::Pin)?;
+        } else {
+            self.sda.set_low().map_err(Error::Pin)?;
+        }
+        self.wait();
+        self.scl_high()?;
+        self.wait();
+        self.scl.set_low().map_err(Error::Pin)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Error<E>> {
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait();
+        self.scl_high()?;
+        self.wait();
+        let bit = self.sda.is_high().map_err(Error::Pin)?;
+        self.scl.set_low().map_err(Error::Pin)?;
+        Ok(bit)
+    }
+
+    /// Writes a byte, and returns an error if it is not acknowledged
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error<E>> {
+        for i in (0..8).rev() {
+            self.write_bit(byte & (1 << i) != 0)?;
+        }
+
+        // The ack bit is low
+        if self.read_bit()? {
+            return Err(Error::NotAcknowledge);
+        }
+        Ok(())
+    }
+
+    /// Reads a byte, acknowledging it if `ack`
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error<E>> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    fn write_bytes(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error<E>> {
+        self.write_byte(addr << 1)?;
+        for &byte in bytes {
+            self.write_byte(byte)?;
+        }
+        Ok(())
+    }
+
+    fn read_bytes(
+        &mut self,
+        addr: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.write_byte((addr << 1) | 1)?;
+
+        // The last byte is not acknowledged
+        let len = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.read_byte(i + 1 < len)?;
+        }
+        Ok(())
+    }
+
+    /// Runs a transaction, sending a STOP condition also on error
+    fn transaction<F>(&mut self, f: F) -> Result<(), Error<E>>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Error<E>>,
+    {
+        self.start()?;
+        let result = f(self);
+        self.stop()?;
+        result
+    }
+}
+
+impl<SCL, SDA, TIMER, E> Write for I2c<SCL, SDA, TIMER>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+    type Error = Error<E>;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error<E>> {
+        self.transaction(|i2c| i2c.write_bytes(addr, bytes))
+    }
+}
+
+impl<SCL, SDA, TIMER, E> Read for I2c<SCL, SDA, TIMER>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+    type Error = Error<E>;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.transaction(|i2c| i2c.read_bytes(addr, buffer))
+    }
+}
+
+impl<SCL, SDA, TIMER, E> WriteRead for I2c<SCL, SDA, TIMER>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+    type Error = Error<E>;
+
+    fn write_read(
+        &mut self,
+        addr: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.transaction(|i2c| {
+            i2c.write_bytes(addr, bytes)?;
+            // Repeated start
+            i2c.start()?;
+            i2c.read_bytes(addr, buffer)
+        })
+    }
+}
@@ -0,0 +1,30 @@
+//! Bit-banged I2C and SPI
+//!
+//! Software implementations of an I2C master and an SPI master on GPIO
+//! pins, for boards where the pins cannot be used with a hardware
+//! peripheral. They implement the same embedded-hal traits as the
+//! [`i2c`](crate::i2c) and [`spi`](crate::spi) drivers, so application
+//! code does not change.
+//!
+//! The bit timing is paced by a [timer](crate::timer), which runs at twice
+//! the bus frequency. The timer is only polled, it does not need an
+//! interrupt. There is always some jitter, and the bus frequency is limited
+//! by the time taken to toggle the pins in software, which is typically a
+//! few hundred kHz to a few MHz depending on `c_ck` and compiler
+//! optimisation.
+//!
+//! # Example
+//!
+//! ```
+//! use stm32h7xx_hal::bitbang;
+//!
+//! let scl = gpiob.pb1.into_open_drain_output();
+//! let sda = gpiob.pb2.into_open_drain_output();
+//! let timer = dp.TIM2.timer(1.khz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//!
+//! let mut i2c = bitbang::i2c::I2c::new(scl, sda, timer, 100.khz());
+//! i2c.write(0x42, &[0x00, 0xFF]).unwrap();
+//! ```
+
+pub mod i2c;
+pub mod spi;
@@ -0,0 +1,200 @@
+//! Bit-banged SPI master
+//!
+//! SCK and MOSI are push-pull outputs, and MISO is an input. All four SPI
+//! modes are supported. Chip select is handled by the application, as for
+//! the hardware [`spi`](crate::spi) driver.
+//!
+//! ```
+//! use stm32h7xx_hal::bitbang;
+//! use stm32h7xx_hal::spi::MODE_0;
+//!
+//! let sck = gpioe.pe2.into_push_pull_output();
+//! let mosi = gpioe.pe6.into_push_pull_output();
+//! let miso = gpioe.pe5.into_floating_input();
+//! let timer = dp.TIM2.timer(1.khz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//!
+//! let mut spi =
+//!     bitbang::spi::Spi::new(sck, mosi, miso, timer, MODE_0, 1.mhz()).unwrap();
+//! spi.write(&[0x9F]).unwrap();
+//! ```
+
+use crate::hal;
+use crate::hal::digital::v2::{InputPin, OutputPin};
+use crate::hal::spi::{Mode, Phase, Polarity};
+use crate::hal::timer::{CountDown, Periodic};
+use crate::time::Hertz;
+
+/// Order of the bits in each byte
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Bit-banged SPI master
+pub struct Spi<SCK, MOSI, MISO, TIMER> {
+    sck: SCK,
+    mosi: MOSI,
+    miso: MISO,
+    timer: TIMER,
+    mode: Mode,
+    bit_order: BitOrder,
+    /// Byte received by the last `send`
+    received: Option<u8>,
+}
+
+impl<SCK, MOSI, MISO, TIMER, E> Spi<SCK, MOSI, MISO, TIMER>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+    /// Creates a bit-banged SPI master. `timer` is started at twice
+    /// `frequency`
+    pub fn new<T>(
+        sck: SCK,
+        mosi: MOSI,
+        miso: MISO,
+        mut timer: TIMER,
+        mode: Mode,
+        frequency: T,
+    ) -> Result<Self, E>
+    where
+        T: Into<Hertz>,
+    {
+        timer.start(Hertz(frequency.into().0 * 2));
+
+        let mut spi = Spi {
+            sck,
+            mosi,
+            miso,
+            timer,
+            mode,
+            bit_order: BitOrder::MsbFirst,
+            received: None,
+        };
+        spi.sck_idle()?;
+        Ok(spi)
+    }
+
+    /// Sets the bit order. The default is MSB first
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+
+    /// Releases the pins and the timer
+    pub fn free(self) -> (SCK, MOSI, MISO, TIMER) {
+        (self.sck, self.mosi, self.miso, self.timer)
+    }
+
+    /// Waits for half a bit period
+    fn wait(&mut self) {
+        nb::block!(self.timer.wait()).ok();
+    }
+
+    fn sck_idle(&mut self) -> Result<(), E> {
+        match self.mode.polarity {
+            Polarity::IdleLow => self.sck.set_low(),
+            Polarity::IdleHigh => self.sck.set_high(),
+        }
+    }
+
+    fn sck_active(&mut self) -> Result<(), E> {
+        match self.mode.polarity {
+            Polarity::IdleLow => self.sck.set_high(),
+            Polarity::IdleHigh => self.sck.set_low(),
+        }
+    }
+
+    fn set_mosi(&mut self, bit: bool) -> Result<(), E> {
+        if bit {
+            self.mosi.set_high()
+        } else {
+            self.mosi.set_low()
+        }
+    }
+
+    /// Exchanges one bit
+    fn transfer_bit(&mut self, bit: bool) -> Result<bool, E> {
+        let read = match self.mode.phase {
+            // Data is captured on the leading edge
+            Phase::CaptureOnFirstTransition => {
+                self.set_mosi(bit)?;
+                self.wait();
+                self.sck_active()?;
+                let read = self.miso.is_high()?;
+                self.wait();
+                self.sck_idle()?;
+                read
+            }
+            // Data is captured on the trailing edge
+            Phase::CaptureOnSecondTransition => {
+                self.sck_active()?;
+                self.set_mosi(bit)?;
+                self.wait();
+                self.sck_idle()?;
+                let read = self.miso.is_high()?;
+                self.wait();
+                read
+            }
+        };
+        Ok(read)
+    }
+
+    /// Exchanges one byte
+    fn transfer_byte(&mut self, byte: u8) -> Result<u8, E> {
+        let mut read = 0;
+        for i in 0..8 {
+            let bit = match self.bit_order {
+                BitOrder::MsbFirst => 7 - i,
+                BitOrder::LsbFirst => i,
+            };
+            if self.transfer_bit(byte & (1 << bit) != 0)? {
+                read |= 1 << bit;
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl<SCK, MOSI, MISO, TIMER, E> hal::spi::FullDuplex<u8>
+    for Spi<SCK, MOSI, MISO, TIMER>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+    type Error = E;
+
+    fn read(&mut self) -> nb::Result<u8, E> {
+        self.received.take().ok_or(nb::Error::WouldBlock)
+    }
+
+    /// Exchanges a byte. This blocks until the byte has been sent
+    fn send(&mut self, byte: u8) -> nb::Result<(), E> {
+        self.received = Some(self.transfer_byte(byte)?);
+        Ok(())
+    }
+}
+
+impl<SCK, MOSI, MISO, TIMER, E> hal::blocking::spi::transfer::Default<u8>
+    for Spi<SCK, MOSI, MISO, TIMER>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+}
+
+impl<SCK, MOSI, MISO, TIMER, E> hal::blocking::spi::write::Default<u8>
+    for Spi<SCK, MOSI, MISO, TIMER>
+where
+    SCK: OutputPin<Error = E>,
+    MOSI: OutputPin<Error = E>,
+    MISO: InputPin<Error = E>,
+    TIMER: CountDown<Time = Hertz> + Periodic,
+{
+}
@@ -0,0 +1,570 @@
+//! Input capture
+//!
+//! Input capture is available for the advanced control timers (`TIM1`,
+//! `TIM8`) and the general purpose timers `TIM[2-5]`. Each timer has a
+//! free-running counter, and each channel latches the value of this counter
+//! when an edge is detected on its input.
+//!
+//! ## Usage
+//!
+//! ```
+//! use stm32h7xx_hal::capture::{CaptureExt, Edge, Prescaler};
+//!
+//! // Counter runs at 1MHz
+//! let (mut c1, mut c2) = dp.TIM3.capture(
+//!     (gpioc.pc6.into_alternate_af2(), gpioc.pc7.into_alternate_af2()),
+//!     1.mhz(),
+//!     ccdr.peripheral.TIM3,
+//!     &ccdr.clocks,
+//! );
+//!
+//! c1.set_edge(Edge::Rising);
+//! c1.enable();
+//!
+//! // Capture on every 8th falling edge, ignoring glitches
+//! c2.set_edge(Edge::Falling);
+//! c2.set_prescaler(Prescaler::Div8);
+//! c2.set_filter(0b0011);
+//! c2.enable();
+//!
+//! let t: u16 = block!(c1.read()).unwrap();
+//! ```
+//!
+//! Capture channels use the same pins as the corresponding
+//! [PWM](crate::pwm) channels.
+//!
+//! The counter of the timer is shared between all its channels, so the
+//! interval between two captures is the wrapping difference of their
+//! values.
+//!
+//! ## Timestamps with DMA
+//!
+//! A [`CaptureRing`] transfers each captured value into a circular buffer
+//! by DMA, so that no edges are lost while the application is busy. The
+//! captures are extended in software to 64-bit timestamps, in counter
+//! ticks, that do not wrap.
+//!
+//! ```
+//! #[link_section = ".axisram.buffers"]
+//! static mut BUFFER: [u16; 64] = [0; 64];
+//!
+//! c1.set_edge(Edge::Both);
+//! let mut ring = CaptureRing::new(
+//!     streams.0,
+//!     c1,
+//!     unsafe { &mut BUFFER },
+//!     DmaConfig::default(),
+//! );
+//!
+//! while let Some(timestamp) = ring.read() {
+//!     // `timestamp` in ticks of the 1MHz counter
+//! }
+//! ```
+//!
+//! To extend the captures correctly, [`read`](CaptureRing::read) or
+//! [`now`](CaptureRing::now) must be called at least once per period of
+//! the counter, and each capture must be read within one period of being
+//! made. For a 16-bit timer at 1MHz, the period is 65.5ms. The buffer must
+//! be large enough to hold the captures made between two reads, otherwise
+//! they are overwritten. If the data cache is enabled, the buffer should be
+//! in a non-cacheable region.
+
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{fence, Ordering};
+
+use embedded_dma::StaticWriteBuffer;
+
+use crate::dma::{
+    dma::DmaConfig,
+    traits::{DoubleBufferedStream, Stream, TargetAddress},
+    DBTransfer, PeripheralToMemory, Transfer,
+};
+
+use crate::pwm::{Pins, C1, C2, C3, C4};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{self, TIM1, TIM2, TIM3, TIM4, TIM5, TIM8};
+use crate::time::Hertz;
+use crate::timer::GetClk;
+
+/// Edge that triggers a capture
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+/// Number of edges per capture
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Prescaler {
+    /// Capture on every edge
+    Div1 = 0b00,
+    /// Capture on every 2nd edge
+    Div2 = 0b01,
+    /// Capture on every 4th edge
+    Div4 = 0b10,
+    /// Capture on every 8th edge
+    Div8 = 0b11,
+}
+
+/// Capture errors
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A capture was made while the previous capture was still unread. The
+    /// previous capture was lost
+    Overcapture,
+}
+
+/// Capture represents one input capture channel; it is created by calling
+/// TIM?.capture(...)
+pub struct Capture<TIM, CHANNEL> {
+    _channel: PhantomData<CHANNEL>,
+    _tim: PhantomData<TIM>,
+}
+
+/// Input capture channel with DMA into a circular buffer, that extends the
+/// captures to 64-bit timestamps
+pub struct CaptureRing<STREAM, TIM, CHANNEL, BUF>
+where
+    STREAM: Stream,
+    Capture<TIM, CHANNEL>: TargetAddress<PeripheralToMemory>,
+{
+    transfer: Transfer<
+        STREAM,
+        Capture<TIM, CHANNEL>,
+        PeripheralToMemory,
+        BUF,
+        DBTransfer,
+    >,
+    /// Address of the buffer
+    buffer: usize,
+    len: usize,
+    /// Index of the next capture to be read
+    read: usize,
+    /// Extended value of the counter at the last update
+    now: u64,
+}
+
+/// Maps channel markers (and tuples of them) to the corresponding capture
+/// channels
+pub trait Channels<TIM> {
+    type Channels;
+}
+
+macro_rules! channels {
+    ($($CH:ty),+) => {
+        $(
+            impl<TIM> Channels<TIM> for $CH {
+                type Channels = Capture<TIM, $CH>;
+            }
+        )+
+    };
+}
+channels!(C1, C2, C3, C4);
+
+impl<TIM, A, B> Channels<TIM> for (A, B)
+where
+    A: Channels<TIM>,
+    B: Channels<TIM>,
+{
+    type Channels = (A::Channels, B::Channels);
+}
+impl<TIM, A, B, C> Channels<TIM> for (A, B, C)
+where
+    A: Channels<TIM>,
+    B: Channels<TIM>,
+    C: Channels<TIM>,
+{
+    type Channels = (A::Channels, B::Channels, C::Channels);
+}
+impl<TIM, A, B, C, D> Channels<TIM> for (A, B, C, D)
+where
+    A: Channels<TIM>,
+    B: Channels<TIM>,
+    C: Channels<TIM>,
+    D: Channels<TIM>,
+{
+    type Channels = (A::Channels, B::Channels, C::Channels, D::Channels);
+}
+
+/// Allows the capture() method to be added to the peripheral register
+/// structs from the device crate
+pub trait CaptureExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures the timer as a free-running counter at `frequency`, with
+    /// input capture on `pins`. The capture channels are disabled until
+    /// they are enabled individually
+    ///
+    /// Because this only uses the timer prescaler, the counter runs at the
+    /// timer's kernel clock divided by an integer. The divider is rounded
+    /// down, so the frequency is the lowest such frequency that is at
+    /// least `frequency`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is higher than the kernel clock, or lower than
+    /// the kernel clock divided by 65536
+    fn capture<PINS, CH, T>(
+        self,
+        _pins: PINS,
+        frequency: T,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> CH::Channels
+    where
+        PINS: Pins<Self, CH>,
+        CH: Channels<Self>,
+        T: Into<Hertz>;
+}
+
+macro_rules! tim_hal {
+    ($($TIMX:ident: ($Rec:ident, $typ:ty),)+) => {
+        $(
+            impl CaptureExt for $TIMX {
+                type Rec = rec::$Rec;
+
+                fn capture<PINS, CH, T>(
+                    self,
+                    _pins: PINS,
+                    frequency: T,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> CH::Channels
+                where
+                    PINS: Pins<Self, CH>,
+                    CH: Channels<Self>,
+                    T: Into<Hertz>,
+                {
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!").0;
+                    let div = clk / frequency.into().0;
+                    assert!(div >= 1 && div <= 1 << 16);
+
+                    self.psc.write(|w| w.psc().bits((div - 1) as u16));
+                    self.arr.write(|w| w.arr().bits(<$typ>::MAX));
+
+                    // Load the prescaler
+                    self.egr.write(|w| w.ug().set_bit());
+                    self.cr1.write(|w| w.cen().enabled());
+
+                    unsafe { MaybeUninit::<CH::Channels>::uninit().assume_init() }
+                }
+            }
+        )+
+    };
+}
+
+tim_hal! {
+    TIM1: (Tim1, u16),
+    TIM2: (Tim2, u32),
+    TIM3: (Tim3, u16),
+    TIM4: (Tim4, u16),
+    TIM5: (Tim5, u32),
+    TIM8: (Tim8, u16),
+}
+
+macro_rules! tim_channel_hal {
+    ($($TIMX:ident:
+       ($CH:ty, $ccmrx_input:ident, $ccxs:ident, $icxpsc:ident, $icxf:ident,
+        $ccxe:ident, $ccxp:ident, $ccxnp:ident, $ccxie:ident,
+        $ccxif:ident, $ccxof:ident, $ccrx:ident, $typ:ty),)+
+    ) => {
+        $(
+            impl Capture<$TIMX, $CH> {
+                /// Enables captures on this channel
+                pub fn enable(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // CCxS can only be written while the channel is off
+                    tim.ccer.modify(|_, w| w.$ccxe().clear_bit());
+                    // Input is mapped to TIx
+                    tim.$ccmrx_input()
+                        .modify(|_, w| unsafe { w.$ccxs().bits(0b01) });
+                    tim.ccer.modify(|_, w| w.$ccxe().set_bit());
+                }
+
+                /// Disables captures on this channel
+                pub fn disable(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.ccer.modify(|_, w| w.$ccxe().clear_bit());
+                }
+
+                /// Sets the edge that triggers a capture
+                pub fn set_edge(&mut self, edge: Edge) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let (p, np) = match edge {
+                        Edge::Rising => (false, false),
+                        Edge::Falling => (true, false),
+                        Edge::Both => (true, true),
+                    };
+                    tim.ccer.modify(|_, w| w.$ccxp().bit(p).$ccxnp().bit(np));
+                }
+
+                /// Sets the number of edges per capture
+                pub fn set_prescaler(&mut self, prescaler: Prescaler) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.$ccmrx_input().modify(|_, w| unsafe {
+                        w.$icxpsc().bits(prescaler as u8)
+                    });
+                }
+
+                /// Sets the digital input filter. An edge is only detected
+                /// after the input has been stable for a number of samples,
+                /// from 0 (no filter) to 0b1111. See the reference manual
+                /// for the sampling frequency and the number of samples
+                pub fn set_filter(&mut self, filter: u8) {
+                    assert!(filter <= 0b1111);
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.$ccmrx_input()
+                        .modify(|_, w| unsafe { w.$icxf().bits(filter) });
+                }
+
+                /// Starts listening for captures on this channel
+                pub fn listen(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dier.modify(|_, w| w.$ccxie().set_bit());
+                }
+
+                /// Stops listening for captures on this channel
+                pub fn unlisten(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dier.modify(|_, w| w.$ccxie().clear_bit());
+                    let _ = tim.dier.read();
+                    let _ = tim.dier.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns `true` if a capture has been made that has not
+                /// been read yet
+                pub fn is_pending(&self) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.sr.read().$ccxif().bit_is_set()
+                }
+
+                /// Reads the captured counter value
+                ///
+                /// Returns `Error::Overcapture` if more than one capture
+                /// was made since the last read. In this case the latest
+                /// capture is kept, and can be read by calling this method
+                /// again
+                pub fn read(&mut self) -> nb::Result<$typ, Error> {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let sr = tim.sr.read();
+                    if sr.$ccxof().bit_is_set() {
+                        tim.sr.modify(|_, w| w.$ccxof().clear_bit());
+                        Err(nb::Error::Other(Error::Overcapture))
+                    } else if sr.$ccxif().bit_is_set() {
+                        // Reading CCRx clears CCxIF
+                        Ok(tim.$ccrx.read().ccr().bits())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                /// Returns the current value of the timer's counter
+                pub fn counter(&self) -> $typ {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cnt.read().bits() as $typ
+                }
+            }
+        )+
+    };
+}
+
+tim_channel_hal! {
+    TIM1: (C1, ccmr1_input, cc1s, ic1psc, ic1f, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1, u16),
+    TIM1: (C2, ccmr1_input, cc2s, ic2psc, ic2f, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2, u16),
+    TIM1: (C3, ccmr2_input, cc3s, ic3psc, ic3f, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3, u16),
+    TIM1: (C4, ccmr2_input, cc4s, ic4psc, ic4f, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4, u16),
+}
+tim_channel_hal! {
+    TIM2: (C1, ccmr1_input, cc1s, ic1psc, ic1f, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1, u32),
+    TIM2: (C2, ccmr1_input, cc2s, ic2psc, ic2f, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2, u32),
+    TIM2: (C3, ccmr2_input, cc3s, ic3psc, ic3f, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3, u32),
+    TIM2: (C4, ccmr2_input, cc4s, ic4psc, ic4f, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4, u32),
+}
+tim_channel_hal! {
+    TIM3: (C1, ccmr1_input, cc1s, ic1psc, ic1f, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1, u16),
+    TIM3: (C2, ccmr1_input, cc2s, ic2psc, ic2f, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2, u16),
+    TIM3: (C3, ccmr2_input, cc3s, ic3psc, ic3f, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3, u16),
+    TIM3: (C4, ccmr2_input, cc4s, ic4psc, ic4f, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4, u16),
+}
+tim_channel_hal! {
+    TIM4: (C1, ccmr1_input, cc1s, ic1psc, ic1f, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1, u16),
+    TIM4: (C2, ccmr1_input, cc2s, ic2psc, ic2f, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2, u16),
+    TIM4: (C3, ccmr2_input, cc3s, ic3psc, ic3f, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3, u16),
+    TIM4: (C4, ccmr2_input, cc4s, ic4psc, ic4f, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4, u16),
+}
+tim_channel_hal! {
+    TIM5: (C1, ccmr1_input, cc1s, ic1psc, ic1f, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1, u32),
+    TIM5: (C2, ccmr1_input, cc2s, ic2psc, ic2f, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2, u32),
+    TIM5: (C3, ccmr2_input, cc3s, ic3psc, ic3f, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3, u32),
+    TIM5: (C4, ccmr2_input, cc4s, ic4psc, ic4f, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4, u32),
+}
+tim_channel_hal! {
+    TIM8: (C1, ccmr1_input, cc1s, ic1psc, ic1f, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1, u16),
+    TIM8: (C2, ccmr1_input, cc2s, ic2psc, ic2f, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2, u16),
+    TIM8: (C3, ccmr2_input, cc3s, ic3psc, ic3f, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3, u16),
+    TIM8: (C4, ccmr2_input, cc4s, ic4psc, ic4f, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4, u16),
+}
+
+type DMAReq = stm32::dmamux1::ccr::DMAREQ_ID_A;
+
+macro_rules! capture_dma {
+    ($($TIMX:ident: ($CH:ty, $ccrx:ident, $ccxde:ident, $dmareq:ident, $typ:ty),)+) => {
+        $(
+            unsafe impl TargetAddress<PeripheralToMemory> for Capture<$TIMX, $CH> {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    // unsafe: only the address of the register is taken
+                    unsafe { &(*$TIMX::ptr()).$ccrx as *const _ as usize }
+                }
+
+                type MemSize = $typ;
+
+                const REQUEST_LINE: Option<u8> = Some(DMAReq::$dmareq as u8);
+            }
+
+            impl<STREAM, BUF> CaptureRing<STREAM, $TIMX, $CH, BUF>
+            where
+                STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+                BUF: StaticWriteBuffer<Word = $typ>,
+            {
+                /// Starts transferring the captures of `capture` into
+                /// `buffer` with a circular DMA transfer on `stream`, and
+                /// enables the channel. Memory increment and circular mode
+                /// are enabled in `config`
+                pub fn new(
+                    stream: STREAM,
+                    capture: Capture<$TIMX, $CH>,
+                    mut buffer: BUF,
+                    config: DmaConfig,
+                ) -> Self {
+                    // unsafe: Only used to read captures that the DMA has
+                    // already written
+                    let (ptr, len) = unsafe { buffer.static_write_buffer() };
+                    let config = config.memory_increment(true).circular_buffer(true);
+
+                    let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+                        Transfer::init(stream, capture, buffer, None, config);
+                    transfer.start(|capture| {
+                        let tim = unsafe { &*$TIMX::ptr() };
+
+                        tim.dier.modify(|_, w| w.$ccxde().set_bit());
+                        capture.enable();
+                    });
+
+                    let mut ring = CaptureRing {
+                        transfer,
+                        buffer: ptr as usize,
+                        len,
+                        read: 0,
+                        now: 0,
+                    };
+                    ring.now = u64::from(ring.counter());
+                    ring
+                }
+
+                fn counter(&self) -> $typ {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.cnt.read().bits() as $typ
+                }
+
+                /// Returns the current value of the counter, extended to 64
+                /// bits
+                pub fn now(&mut self) -> u64 {
+                    let last = self.now as $typ;
+                    let elapsed = self.counter().wrapping_sub(last);
+                    self.now += u64::from(elapsed);
+                    self.now
+                }
+
+                /// Returns the number of captures made and not yet read
+                pub fn available(&self) -> usize {
+                    let remaining = self.transfer.get_number_of_transfers() as usize;
+                    let write = self.len - remaining;
+                    (write + self.len - self.read) % self.len
+                }
+
+                /// Returns the timestamp of the next capture, in counter
+                /// ticks, or `None` if there are no new captures
+                pub fn read(&mut self) -> Option<u64> {
+                    if self.available() == 0 {
+                        // Keep the extended counter up to date
+                        self.now();
+                        return None;
+                    }
+
+                    // Ensure the DMA writes are visible before reading
+                    fence(Ordering::SeqCst);
+                    // unsafe: read is always less than len
+                    let capture: $typ = unsafe {
+                        ptr::read_volatile(
+                            (self.buffer + self.read * core::mem::size_of::<$typ>())
+                                as *const $typ,
+                        )
+                    };
+                    self.read = (self.read + 1) % self.len;
+
+                    // The capture was made before now
+                    let now = self.now();
+                    let age = (now as $typ).wrapping_sub(capture);
+                    Some(now.saturating_sub(u64::from(age)))
+                }
+
+                /// Stops the transfer and releases the DMA stream, the
+                /// capture channel and the buffer. The channel is disabled
+                pub fn free(self) -> (STREAM, Capture<$TIMX, $CH>, BUF) {
+                    let (stream, mut capture, buffer, _) = self.transfer.free();
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dier.modify(|_, w| w.$ccxde().clear_bit());
+                    capture.disable();
+                    (stream, capture, buffer)
+                }
+            }
+        )+
+    };
+}
+
+capture_dma! {
+    TIM1: (C1, ccr1, cc1de, TIM1_CH1, u16),
+    TIM1: (C2, ccr2, cc2de, TIM1_CH2, u16),
+    TIM1: (C3, ccr3, cc3de, TIM1_CH3, u16),
+    TIM1: (C4, ccr4, cc4de, TIM1_CH4, u16),
+    TIM2: (C1, ccr1, cc1de, TIM2_CH1, u32),
+    TIM2: (C2, ccr2, cc2de, TIM2_CH2, u32),
+    TIM2: (C3, ccr3, cc3de, TIM2_CH3, u32),
+    TIM2: (C4, ccr4, cc4de, TIM2_CH4, u32),
+    TIM3: (C1, ccr1, cc1de, TIM3_CH1, u16),
+    TIM3: (C2, ccr2, cc2de, TIM3_CH2, u16),
+    TIM3: (C3, ccr3, cc3de, TIM3_CH3, u16),
+    TIM3: (C4, ccr4, cc4de, TIM3_CH4, u16),
+    TIM4: (C1, ccr1, cc1de, TIM4_CH1, u16),
+    TIM4: (C2, ccr2, cc2de, TIM4_CH2, u16),
+    TIM4: (C3, ccr3, cc3de, TIM4_CH3, u16),
+    // TIM4 channel 4 has no DMA request
+    TIM5: (C1, ccr1, cc1de, TIM5_CH1, u32),
+    TIM5: (C2, ccr2, cc2de, TIM5_CH2, u32),
+    TIM5: (C3, ccr3, cc3de, TIM5_CH3, u32),
+    TIM5: (C4, ccr4, cc4de, TIM5_CH4, u32),
+    TIM8: (C1, ccr1, cc1de, TIM8_CH1, u16),
+    TIM8: (C2, ccr2, cc2de, TIM8_CH2, u16),
+    TIM8: (C3, ccr3, cc3de, TIM8_CH3, u16),
+    TIM8: (C4, ccr4, cc4de, TIM8_CH4, u16),
+}
@@ -0,0 +1,197 @@
+//! Health of the backup battery
+//!
+//! The RTC and the backup domain run from VBAT when the main supply is off,
+//! usually from a coin cell. A [`CoinCell`] turns VBAT measurements into a
+//! state of charge and a [`Health`], so that a device can warn about a dying
+//! cell before the time is lost. The lowest voltage measured is kept in an
+//! RTC backup register, so it is not lost across resets and power cycles.
+//!
+//! # Example
+//!
+//! ```
+//! let mut cell = CoinCell::new(BatteryModel::CR2032, 31);
+//!
+//! // VBAT is measured by ADC3 (ADC2 on RM0455 parts)
+//! let mut vbat = adc::Vbat::new();
+//! vbat.enable(&adc3);
+//! let mut adc3 = adc3.enable();
+//! let vdda = adc3.read_vdda(&mut vrefint);
+//! let volts = adc3.read_vbat(&mut vbat, vdda);
+//! // The divider loads the cell, so disable it again
+//! let adc3 = adc3.disable();
+//! vbat.disable(&adc3);
+//!
+//! let report = cell.update(&mut rtc, volts);
+//! if report.health != Health::Good {
+//!     // Ask for the coin cell to be replaced
+//! }
+//! ```
+//!
+//! Measure while the main supply is on and with the backup domain idle, and
+//! not more often than needed.
+
+use crate::rtc::Rtc;
+
+/// Marks the backup register as holding a lowest voltage
+const MAGIC: u32 = 0xCE11_0000;
+const MAGIC_MASK: u32 = 0xFFFF_0000;
+
+/// Discharge model of a backup battery
+///
+/// The state of charge is linear between `empty` and `full`. Most lithium
+/// coin cells have a flat discharge curve, so this is only a rough guide
+/// until the voltage approaches `low`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BatteryModel {
+    /// Voltage of a fresh cell
+    pub full: f32,
+    /// Below this voltage the cell should be replaced soon
+    pub low: f32,
+    /// Below this voltage the backup domain may lose its contents
+    pub empty: f32,
+}
+
+impl BatteryModel {
+    /// A CR2032 lithium coin cell
+    pub const CR2032: BatteryModel = BatteryModel {
+        full: 3.0,
+        low: 2.5,
+        empty: 2.0,
+    };
+
+    /// Returns the state of charge in percent for `volts`
+    pub fn percent(&self, volts: f32) -> u8 {
+        let fraction = (volts - self.empty) / (self.full - self.empty);
+        let fraction = if fraction < 0.0 {
+            0.0
+        } else if fraction > 1.0 {
+            1.0
+        } else {
+            fraction
+        };
+
+        (fraction * 100.0 + 0.5) as u8
+    }
+
+    /// Returns the health of a cell at `volts`
+    pub fn health(&self, volts: f32) -> Health {
+        if volts < self.empty {
+            Health::Critical
+        } else if volts < self.low {
+            Health::Low
+        } else {
+            Health::Good
+        }
+    }
+}
+
+/// Health of the backup battery
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Health {
+    /// Above the low voltage of the model
+    Good,
+    /// Between the empty and low voltages of the model. The cell should be
+    /// replaced soon
+    Low,
+    /// Below the empty voltage of the model. The time and the backup
+    /// domain may already have been lost
+    Critical,
+}
+
+/// Report of one VBAT measurement
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Report {
+    /// Measured voltage
+    pub volts: f32,
+    /// State of charge in percent
+    pub percent: u8,
+    /// Health, from the lowest voltage measured
+    pub health: Health,
+    /// Lowest voltage measured since the cell was replaced
+    pub lowest_volts: f32,
+}
+
+/// Fuel gauge for the backup battery
+pub struct CoinCell {
+    model: BatteryModel,
+    register: u8,
+}
+
+impl CoinCell {
+    /// Creates a fuel gauge for a battery described by `model`, that keeps
+    /// the lowest voltage measured in the RTC backup register `register`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `register` is greater than 31
+    pub fn new(model: BatteryModel, register: u8) -> Self {
+        assert!(register < 32, "There are 32 RTC backup registers");
+
+        CoinCell { model, register }
+    }
+
+    /// Reports on a measurement of VBAT, `volts`. The health is judged by
+    /// the lowest voltage measured, since the voltage of a coin cell
+    /// recovers while it is not loaded
+    pub fn update(&mut self, rtc: &mut Rtc, volts: f32) -> Report {
+        let millivolts = (volts * 1000.0) as u32 & !MAGIC_MASK;
+        let lowest = match self.lowest_millivolts(rtc) {
+            Some(lowest) if lowest < millivolts => lowest,
+            _ => {
+                rtc.write_backup_reg(self.register, MAGIC | millivolts);
+                millivolts
+            }
+        };
+        let lowest_volts = lowest as f32 / 1000.0;
+
+        Report {
+            volts,
+            percent: self.model.percent(volts),
+            health: self.model.health(lowest_volts),
+            lowest_volts,
+        }
+    }
+
+    /// Forgets the lowest voltage measured, after the cell is replaced
+    pub fn replaced(&mut self, rtc: &mut Rtc) {
+        rtc.write_backup_reg(self.register, 0);
+    }
+
+    /// Returns the lowest voltage measured in millivolts, if the backup
+    /// register holds one
+    fn lowest_millivolts(&self, rtc: &Rtc) -> Option<u32> {
+        let value = rtc.read_backup_reg(self.register);
+
+        if value & MAGIC_MASK == MAGIC {
+            Some(value & !MAGIC_MASK)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cr2032_percent() {
+        let model = BatteryModel::CR2032;
+        assert_eq!(model.percent(3.0), 100);
+        assert_eq!(model.percent(2.5), 50);
+        assert_eq!(model.percent(2.0), 0);
+        // The state of charge is clamped
+        assert_eq!(model.percent(3.3), 100);
+        assert_eq!(model.percent(1.5), 0);
+    }
+
+    #[test]
+    fn cr2032_health() {
+        let model = BatteryModel::CR2032;
+        assert_eq!(model.health(2.9), Health::Good);
+        assert_eq!(model.health(2.5), Health::Good);
+        assert_eq!(model.health(2.4), Health::Low);
+        assert_eq!(model.health(2.0), Health::Low);
+        assert_eq!(model.health(1.9), Health::Critical);
+    }
+}
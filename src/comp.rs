@@ -0,0 +1,343 @@
+//! Analog comparators (COMP1 and COMP2)
+//!
+//! Each comparator compares a non-inverting input pin with an inverting
+//! input, which is a pin, a DAC channel or a fraction of VREFINT. The
+//! output can be read by software, trigger an interrupt through EXTI, drive
+//! a GPIO pin or a timer break input.
+//!
+//! # Example
+//!
+//! ```
+//! let (mut comp1, _comp2) = dp.COMP1.comp(ccdr.peripheral.COMP12);
+//!
+//! // Overcurrent threshold at VREFINT / 2 on PB0
+//! let _pb0 = gpiob.pb0.into_analog();
+//! comp1.configure(
+//!     comp::Config::new()
+//!         .non_inverting(comp::NonInvertingInput::Inp1)
+//!         .inverting(comp::InvertingInput::VrefintHalf)
+//!         .hysteresis(comp::Hysteresis::Medium),
+//! );
+//! comp1.enable();
+//!
+//! // Stop the PWM outputs of TIM1 when the current is too high
+//! comp1.connect_break(comp::BreakInput::Tim1Break, true);
+//! comp1.listen(&mut dp.EXTI, Edge::Rising);
+//! ```
+//!
+//! # Pins
+//!
+//! The input pins must be in analog mode. The output is available as
+//! alternate function 13 of PC5 and PE12 (COMP1), and PE8 and PE13
+//! (COMP2).
+//!
+//! | Input | COMP1 | COMP2 |
+//! |-------|-------|-------|
+//! | INP1  | PB0   | PE9   |
+//! | INP2  | PB2   | PE11  |
+//! | INM6  | PB1   | PE10  |
+//! | INM7  | PC4   | PE7   |
+
+use crate::exti::{Event, ExtiExt};
+use crate::gpio::Edge;
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::{COMP1, EXTI, TIM1, TIM15, TIM16, TIM17, TIM8};
+
+/// Non-inverting input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NonInvertingInput {
+    /// INP1 pin
+    Inp1,
+    /// INP2 pin
+    Inp2,
+}
+
+/// Inverting input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InvertingInput {
+    /// VREFINT / 4
+    VrefintQuarter = 0b000,
+    /// VREFINT / 2
+    VrefintHalf = 0b001,
+    /// VREFINT * 3 / 4
+    VrefintThreeQuarter = 0b010,
+    /// VREFINT
+    Vrefint = 0b011,
+    /// DAC1 channel 1
+    Dac1Out1 = 0b100,
+    /// DAC1 channel 2
+    Dac1Out2 = 0b101,
+    /// INM6 pin
+    Inm6 = 0b110,
+    /// INM7 pin
+    Inm7 = 0b111,
+}
+
+/// Hysteresis
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Hysteresis {
+    None = 0b00,
+    Low = 0b01,
+    Medium = 0b10,
+    High = 0b11,
+}
+
+/// Power mode, trading speed for current consumption
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PowerMode {
+    HighSpeed = 0b00,
+    MediumSpeed = 0b01,
+    UltraLowPower = 0b11,
+}
+
+/// Timer output that blanks the comparator output, for example to hide
+/// current spikes when a switch turns on
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Blanking {
+    None = 0b0000,
+    Tim1Oc5 = 0b0001,
+    Tim2Oc3 = 0b0010,
+    Tim3Oc3 = 0b0011,
+    Tim3Oc4 = 0b0100,
+    Tim8Oc5 = 0b0101,
+    Tim15Oc1 = 0b0110,
+}
+
+/// Timer break input that can be driven by a comparator
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BreakInput {
+    Tim1Break,
+    Tim1Break2,
+    Tim8Break,
+    Tim8Break2,
+    Tim15Break,
+    Tim16Break,
+    Tim17Break,
+}
+
+/// Comparator configuration
+///
+/// This structure uses builder semantics. The default compares INP1 with
+/// VREFINT / 2, without hysteresis, blanking or output inversion, in
+/// high-speed mode.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    non_inverting: NonInvertingInput,
+    inverting: InvertingInput,
+    hysteresis: Hysteresis,
+    power_mode: PowerMode,
+    blanking: Blanking,
+    invert_output: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Creates a default configuration
+    pub fn new() -> Self {
+        Config {
+            non_inverting: NonInvertingInput::Inp1,
+            inverting: InvertingInput::VrefintHalf,
+            hysteresis: Hysteresis::None,
+            power_mode: PowerMode::HighSpeed,
+            blanking: Blanking::None,
+            invert_output: false,
+        }
+    }
+
+    /// Selects the non-inverting input
+    pub fn non_inverting(mut self, input: NonInvertingInput) -> Self {
+        self.non_inverting = input;
+        self
+    }
+
+    /// Selects the inverting input
+    pub fn inverting(mut self, input: InvertingInput) -> Self {
+        self.inverting = input;
+        self
+    }
+
+    /// Sets the hysteresis
+    pub fn hysteresis(mut self, hysteresis: Hysteresis) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Sets the power mode
+    pub fn power_mode(mut self, power_mode: PowerMode) -> Self {
+        self.power_mode = power_mode;
+        self
+    }
+
+    /// Selects a timer output that blanks the comparator output
+    pub fn blanking(mut self, blanking: Blanking) -> Self {
+        self.blanking = blanking;
+        self
+    }
+
+    /// Inverts the comparator output
+    pub fn invert_output(mut self, invert: bool) -> Self {
+        self.invert_output = invert;
+        self
+    }
+
+    /// Returns SCALEN and BRGEN. The VREFINT scaler is required for
+    /// VREFINT, and the resistor bridge for fractions of VREFINT
+    fn scaler(&self) -> (bool, bool) {
+        match self.inverting {
+            InvertingInput::Vrefint => (true, false),
+            InvertingInput::VrefintQuarter
+            | InvertingInput::VrefintHalf
+            | InvertingInput::VrefintThreeQuarter => (true, true),
+            _ => (false, false),
+        }
+    }
+}
+
+/// Extension trait for the COMP1/COMP2 peripheral
+pub trait CompExt {
+    /// Enables and resets the comparators, and splits them into
+    /// independent halves
+    fn comp(self, prec: rec::Comp12) -> (Comp1, Comp2);
+}
+
+impl CompExt for COMP1 {
+    fn comp(self, prec: rec::Comp12) -> (Comp1, Comp2) {
+        prec.enable().reset();
+
+        (Comp1 { _0: () }, Comp2 { _0: () })
+    }
+}
+
+macro_rules! comp {
+    ($($COMPX:ident: ($cfgr:ident, $Event:ident, $val:ident, $ccif:ident,
+                      $bkcmpe:ident, $bk2cmpe:ident),)+) => {
+        $(
+            /// Comparator
+            pub struct $COMPX {
+                _0: (),
+            }
+
+            impl $COMPX {
+                fn rb(&self) -> &crate::stm32::comp1::RegisterBlock {
+                    // NOTE(unsafe) this half has exclusive access to its
+                    // CFGR register and its bits in the shared registers
+                    unsafe { &*COMP1::ptr() }
+                }
+
+                /// Configures the comparator. The comparator is disabled
+                /// if it was enabled
+                ///
+                /// # Panics
+                ///
+                /// Panics if the comparator is locked
+                pub fn configure(&mut self, config: Config) {
+                    assert!(!self.is_locked(), "Comparator is locked");
+
+                    let (scalen, brgen) = config.scaler();
+                    self.rb().$cfgr.modify(|_, w| unsafe {
+                        w.en()
+                            .clear_bit()
+                            .brgen()
+                            .bit(brgen)
+                            .scalen()
+                            .bit(scalen)
+                            .polarity()
+                            .bit(config.invert_output)
+                            .hyst()
+                            .bits(config.hysteresis as u8)
+                            .pwrmode()
+                            .bits(config.power_mode as u8)
+                            .inmsel()
+                            .bits(config.inverting as u8)
+                            .inpsel()
+                            .bit(config.non_inverting == NonInvertingInput::Inp2)
+                            .blanking()
+                            .bits(config.blanking as u8)
+                    });
+                }
+
+                /// Enables the comparator
+                pub fn enable(&mut self) {
+                    self.rb().$cfgr.modify(|_, w| w.en().set_bit());
+                }
+
+                /// Disables the comparator
+                pub fn disable(&mut self) {
+                    self.rb().$cfgr.modify(|_, w| w.en().clear_bit());
+                }
+
+                /// Returns the comparator output, after polarity selection
+                /// and blanking
+                pub fn output(&self) -> bool {
+                    self.rb().sr.read().$val().bit_is_set()
+                }
+
+                /// Locks the configuration until the next reset, so that it
+                /// cannot be changed by errant code
+                pub fn lock(&mut self) {
+                    self.rb().$cfgr.modify(|_, w| w.lock().set_bit());
+                }
+
+                /// Returns `true` if the configuration is locked
+                pub fn is_locked(&self) -> bool {
+                    self.rb().$cfgr.read().lock().bit_is_set()
+                }
+
+                /// Generates an interrupt on `edge` of the output, through
+                /// the EXTI
+                pub fn listen(&mut self, exti: &mut EXTI, edge: Edge) {
+                    exti.trigger(Event::$Event, edge);
+                    exti.listen(Event::$Event);
+                    self.rb().$cfgr.modify(|_, w| w.iten().set_bit());
+                }
+
+                /// Stops generating interrupts
+                pub fn unlisten(&mut self, exti: &mut EXTI) {
+                    self.rb().$cfgr.modify(|_, w| w.iten().clear_bit());
+                    exti.unlisten(Event::$Event);
+                }
+
+                /// Clears the interrupt flag of the comparator and the
+                /// pending flag of the EXTI line
+                pub fn clear_irq(&mut self, exti: &mut EXTI) {
+                    self.rb().icfr.write(|w| w.$ccif().set_bit());
+                    exti.unpend(Event::$Event);
+                }
+
+                /// Connects or disconnects the output from the break input
+                /// of a timer. The break input must also be enabled in the
+                /// timer
+                pub fn connect_break(&mut self, input: BreakInput, connect: bool) {
+                    // NOTE(unsafe) Only the BKCMPxE bit of this comparator
+                    // is modified
+                    macro_rules! connect {
+                        ($TIM:ident, $af:ident, $bk:ident) => {
+                            unsafe { &*$TIM::ptr() }
+                                .$af
+                                .modify(|_, w| w.$bk().bit(connect))
+                        };
+                    }
+                    match input {
+                        BreakInput::Tim1Break => connect!(TIM1, af1, $bkcmpe),
+                        BreakInput::Tim1Break2 => connect!(TIM1, af2, $bk2cmpe),
+                        BreakInput::Tim8Break => connect!(TIM8, af1, $bkcmpe),
+                        BreakInput::Tim8Break2 => connect!(TIM8, af2, $bk2cmpe),
+                        BreakInput::Tim15Break => connect!(TIM15, af1, $bkcmpe),
+                        BreakInput::Tim16Break => connect!(TIM16, af1, $bkcmpe),
+                        BreakInput::Tim17Break => connect!(TIM17, af1, $bkcmpe),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+comp! {
+    Comp1: (cfgr1, COMP1, c1val, cc1if, bkcmp1e, bk2cmp1e),
+    Comp2: (cfgr2, COMP2, c2val, cc2if, bkcmp2e, bk2cmp2e),
+}
@@ -0,0 +1,260 @@
+//! Cyclic Redundancy Check (CRC) calculation unit
+//!
+//! The CRC unit calculates a CRC with a programmable polynomial of 7, 8, 16
+//! or 32 bits. Data is fed in bytes, half-words or words, in any
+//! combination, and the result can be read at any time.
+//!
+//! # Example
+//!
+//! ```
+//! // CRC-32 as used by Ethernet and zlib
+//! let config = crc::Config::new()
+//!     .polynomial(crc::Polynomial::Bits32(0x04C1_1DB7))
+//!     .initial_value(0xFFFF_FFFF)
+//!     .input_reversal(crc::InputReversal::Byte)
+//!     .output_reversal(true)
+//!     .output_xor(0xFFFF_FFFF);
+//!
+//! let mut crc = dp.CRC.crc(config, ccdr.peripheral.CRC);
+//!
+//! crc.update(b"123456789");
+//! assert_eq!(crc.finalize(), 0xCBF4_3926);
+//! ```
+//!
+//! [`finalize`](Crc::finalize) returns the result and starts a new
+//! calculation with the same configuration. Use [`peek`](Crc::peek) to read
+//! the result of the data fed so far without resetting.
+
+use core::ptr;
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::CRC;
+
+/// CRC polynomial, without the highest order bit. For example, the
+/// CRC-8 polynomial x<sup>8</sup> + x<sup>2</sup> + x + 1 is `Bits8(0x07)`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Polynomial {
+    /// 7 bit polynomial
+    Bits7(u8),
+    /// 8 bit polynomial
+    Bits8(u8),
+    /// 16 bit polynomial
+    Bits16(u16),
+    /// 32 bit polynomial
+    Bits32(u32),
+}
+
+impl Polynomial {
+    /// Returns the value of the POLYSIZE field
+    fn polysize(&self) -> u8 {
+        match self {
+            Polynomial::Bits32(_) => 0b00,
+            Polynomial::Bits16(_) => 0b01,
+            Polynomial::Bits8(_) => 0b10,
+            Polynomial::Bits7(_) => 0b11,
+        }
+    }
+
+    /// Returns the polynomial, to be written to the POL register
+    fn value(&self) -> u32 {
+        match *self {
+            Polynomial::Bits32(p) => p,
+            Polynomial::Bits16(p) => p as u32,
+            Polynomial::Bits8(p) => p as u32,
+            Polynomial::Bits7(p) => (p & 0x7F) as u32,
+        }
+    }
+
+    /// Returns a mask of the bits in the CRC
+    fn mask(&self) -> u32 {
+        match self {
+            Polynomial::Bits32(_) => 0xFFFF_FFFF,
+            Polynomial::Bits16(_) => 0xFFFF,
+            Polynomial::Bits8(_) => 0xFF,
+            Polynomial::Bits7(_) => 0x7F,
+        }
+    }
+}
+
+/// Bit reversal of the input data
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputReversal {
+    /// The input data is not reversed
+    None = 0b00,
+    /// The bits of each byte are reversed. This corresponds to a reflected
+    /// input in the usual CRC parameter notation
+    Byte = 0b01,
+    /// The bits of each half-word are reversed
+    HalfWord = 0b10,
+    /// The bits of each word are reversed
+    Word = 0b11,
+}
+
+/// CRC configuration
+///
+/// This structure uses builder semantics. The default is the CRC-32
+/// polynomial with an initial value of `0xFFFF_FFFF`, no reversal and no
+/// output XOR, which is the reset configuration of the CRC unit.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    polynomial: Polynomial,
+    initial: u32,
+    input_reversal: InputReversal,
+    output_reversal: bool,
+    output_xor: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Creates a default configuration
+    pub fn new() -> Self {
+        Config {
+            polynomial: Polynomial::Bits32(0x04C1_1DB7),
+            initial: 0xFFFF_FFFF,
+            input_reversal: InputReversal::None,
+            output_reversal: false,
+            output_xor: 0,
+        }
+    }
+
+    /// Sets the polynomial and the width of the CRC
+    pub fn polynomial(mut self, polynomial: Polynomial) -> Self {
+        self.polynomial = polynomial;
+        self
+    }
+
+    /// Sets the initial value of the CRC. For CRCs of less than 32 bits,
+    /// only the least significant bits are used
+    pub fn initial_value(mut self, initial: u32) -> Self {
+        self.initial = initial;
+        self
+    }
+
+    /// Sets the bit reversal of the input data
+    pub fn input_reversal(mut self, reversal: InputReversal) -> Self {
+        self.input_reversal = reversal;
+        self
+    }
+
+    /// Reverses the bits of the result. This corresponds to a reflected
+    /// output in the usual CRC parameter notation
+    pub fn output_reversal(mut self, reverse: bool) -> Self {
+        self.output_reversal = reverse;
+        self
+    }
+
+    /// Sets a value that is XORed with the result. This is done in
+    /// software
+    pub fn output_xor(mut self, xor: u32) -> Self {
+        self.output_xor = xor;
+        self
+    }
+}
+
+/// Extension trait for the CRC peripheral
+pub trait CrcExt: Sized {
+    /// Enable and reset the CRC unit, and configure it with `config`
+    fn crc(self, config: Config, prec: rec::Crc) -> Crc;
+}
+
+impl CrcExt for CRC {
+    fn crc(self, config: Config, prec: rec::Crc) -> Crc {
+        prec.enable().reset();
+
+        let mut crc = Crc { rb: self, config };
+        crc.set_config(config);
+        crc
+    }
+}
+
+/// CRC calculation unit
+pub struct Crc {
+    rb: CRC,
+    config: Config,
+}
+
+impl Crc {
+    /// Changes the configuration and starts a new calculation
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+
+        let polynomial = config.polynomial;
+        self.rb
+            .pol
+            .write(|w| unsafe { w.pol().bits(polynomial.value()) });
+        self.rb.init.write(|w| unsafe {
+            w.init().bits(config.initial & polynomial.mask())
+        });
+        self.rb.cr.write(|w| unsafe {
+            w.polysize()
+                .bits(polynomial.polysize())
+                .rev_in()
+                .bits(config.input_reversal as u8)
+                .rev_out()
+                .bit(config.output_reversal)
+        });
+        self.reset();
+    }
+
+    /// Starts a new calculation with the current configuration
+    pub fn reset(&mut self) {
+        // RESET loads the initial value and is cleared by hardware
+        self.rb.cr.modify(|_, w| w.reset().set_bit());
+    }
+
+    /// Feeds bytes to the calculation
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            // NOTE(write_volatile) 8 bit write to the data register
+            unsafe {
+                ptr::write_volatile(&self.rb.dr as *const _ as *mut u8, byte)
+            }
+        }
+    }
+
+    /// Feeds half-words to the calculation
+    pub fn update_u16(&mut self, data: &[u16]) {
+        for &half_word in data {
+            // NOTE(write_volatile) 16 bit write to the data register
+            unsafe {
+                ptr::write_volatile(
+                    &self.rb.dr as *const _ as *mut u16,
+                    half_word,
+                )
+            }
+        }
+    }
+
+    /// Feeds words to the calculation. This is the fastest way to feed
+    /// data
+    pub fn update_u32(&mut self, data: &[u32]) {
+        for &word in data {
+            self.rb.dr.write(|w| unsafe { w.dr().bits(word) });
+        }
+    }
+
+    /// Returns the result of the data fed since the last reset, without
+    /// resetting
+    pub fn peek(&self) -> u32 {
+        (self.rb.dr.read().dr().bits() ^ self.config.output_xor)
+            & self.config.polynomial.mask()
+    }
+
+    /// Returns the result of the data fed since the last reset, and
+    /// starts a new calculation
+    pub fn finalize(&mut self) -> u32 {
+        let result = self.peek();
+        self.reset();
+        result
+    }
+
+    /// Releases the CRC peripheral
+    pub fn free(self) -> CRC {
+        self.rb
+    }
+}
@@ -0,0 +1,315 @@
+//! Clock Recovery System (CRS)
+//!
+//! The CRS trims the HSI48 oscillator against a synchronisation signal, so
+//! that it is accurate enough for USB without a crystal. The signal is the
+//! USB start-of-frame (SOF), which arrives every 1ms when a host is
+//! connected, the LSE, or an external signal on the CRS_SYNC pin.
+//!
+//! # Example
+//!
+//! ```
+//! let ccdr = rcc.hsi48_crs_trimmed().freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! // Trim from the USB SOF, automatically
+//! let mut crs = dp.CRS.crs(crs::Config::new(), ccdr.peripheral.CRS);
+//!
+//! assert!(ccdr.clocks.hsi48_crs_trimmed());
+//! ```
+//!
+//! With the LSE as the synchronisation source, the reload and error limit
+//! values can be calculated from its frequency:
+//!
+//! ```
+//! let config = crs::Config::new()
+//!     .source(crs::SyncSource::Lse)
+//!     .sync_frequency(32_768.hz());
+//! ```
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::CRS;
+use crate::time::Hertz;
+
+/// Target frequency of the HSI48
+const HSI48: u32 = 48_000_000;
+
+/// Synchronisation source
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SyncSource {
+    /// The CRS_SYNC pin
+    Pin = 0b00,
+    /// The LSE oscillator
+    Lse = 0b01,
+    /// The USB start-of-frame signal
+    UsbSof = 0b10,
+}
+
+/// Division of the synchronisation signal
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SyncDivider {
+    Div1 = 0b000,
+    Div2 = 0b001,
+    Div4 = 0b010,
+    Div8 = 0b011,
+    Div16 = 0b100,
+    Div32 = 0b101,
+    Div64 = 0b110,
+    Div128 = 0b111,
+}
+
+/// CRS events
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The frequency error is within the limit after a synchronisation
+    SyncOk,
+    /// The frequency error is large, and the trimming is being corrected
+    SyncWarning,
+    /// A synchronisation error or miss, or a trimming overflow. See
+    /// [`Crs::check_errors`]
+    Error,
+    /// The expected synchronisation time was reached
+    ExpectedSync,
+}
+
+/// CRS error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The frequency error was too large to be corrected
+    SyncError,
+    /// A synchronisation signal was missed
+    SyncMissed,
+    /// The trimming value overflowed or underflowed
+    TrimOverflow,
+}
+
+/// CRS configuration
+///
+/// This structure uses builder semantics. The default is to synchronise to
+/// the USB SOF, with automatic trimming
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    source: SyncSource,
+    divider: SyncDivider,
+    falling_edge: bool,
+    reload: u16,
+    felim: u8,
+    auto_trim: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Creates a default configuration
+    pub fn new() -> Self {
+        Config {
+            source: SyncSource::UsbSof,
+            divider: SyncDivider::Div1,
+            falling_edge: false,
+            reload: 47_999,
+            felim: 34,
+            auto_trim: true,
+        }
+    }
+
+    /// Sets the synchronisation source
+    pub fn source(mut self, source: SyncSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Sets the division of the synchronisation signal
+    pub fn divider(mut self, divider: SyncDivider) -> Self {
+        self.divider = divider;
+        self
+    }
+
+    /// Synchronises on the falling edge of the signal, rather than the
+    /// rising edge
+    pub fn falling_edge(mut self, falling_edge: bool) -> Self {
+        self.falling_edge = falling_edge;
+        self
+    }
+
+    /// Sets the reload value, which is the number of HSI48 cycles in a
+    /// period of the (divided) synchronisation signal, minus one
+    pub fn reload(mut self, reload: u16) -> Self {
+        self.reload = reload;
+        self
+    }
+
+    /// Sets the frequency error limit FELIM
+    pub fn error_limit(mut self, felim: u8) -> Self {
+        self.felim = felim;
+        self
+    }
+
+    /// Calculates the reload value and the error limit for a (divided)
+    /// synchronisation signal at `frequency`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reload value does not fit in 16 bits. Use a larger
+    /// divider
+    pub fn sync_frequency<T: Into<Hertz>>(mut self, frequency: T) -> Self {
+        let frequency = frequency.into().0;
+        let cycles = HSI48 / frequency;
+        assert!(cycles <= 0x1_0000, "Use a larger sync divider");
+
+        self.reload = (cycles - 1) as u16;
+        // FELIM = cycles × trimming step / 2, with a trimming step of
+        // 0.14%. Rounded up
+        self.felim = ((cycles * 14 + 19_999) / 20_000).min(255) as u8;
+        self
+    }
+
+    /// Enables automatic trimming
+    pub fn auto_trim(mut self, auto_trim: bool) -> Self {
+        self.auto_trim = auto_trim;
+        self
+    }
+}
+
+/// Extension trait for the CRS peripheral
+pub trait CrsExt {
+    /// Enables the CRS with `config`
+    fn crs(self, config: Config, prec: rec::Crs) -> Crs;
+}
+
+impl CrsExt for CRS {
+    fn crs(self, config: Config, prec: rec::Crs) -> Crs {
+        prec.enable().reset();
+
+        // The configuration can only be written while the counter is
+        // disabled
+        self.cfgr.write(|w| unsafe {
+            w.reload()
+                .bits(config.reload)
+                .felim()
+                .bits(config.felim)
+                .syncdiv()
+                .bits(config.divider as u8)
+                .syncsrc()
+                .bits(config.source as u8)
+                .syncpol()
+                .bit(config.falling_edge)
+        });
+
+        self.cr.modify(|_, w| {
+            w.autotrimen().bit(config.auto_trim).cen().set_bit()
+        });
+
+        Crs { rb: self }
+    }
+}
+
+/// Clock recovery system
+pub struct Crs {
+    rb: CRS,
+}
+
+impl Crs {
+    /// Starts listening for an event
+    pub fn listen(&mut self, event: Event) {
+        self.rb.cr.modify(|_, w| match event {
+            Event::SyncOk => w.syncokie().set_bit(),
+            Event::SyncWarning => w.syncwarnie().set_bit(),
+            Event::Error => w.errie().set_bit(),
+            Event::ExpectedSync => w.esyncie().set_bit(),
+        });
+    }
+
+    /// Stops listening for an event
+    pub fn unlisten(&mut self, event: Event) {
+        self.rb.cr.modify(|_, w| match event {
+            Event::SyncOk => w.syncokie().clear_bit(),
+            Event::SyncWarning => w.syncwarnie().clear_bit(),
+            Event::Error => w.errie().clear_bit(),
+            Event::ExpectedSync => w.esyncie().clear_bit(),
+        });
+    }
+
+    /// Returns `true` if `event` is pending
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.rb.isr.read();
+        match event {
+            Event::SyncOk => isr.syncokf().bit_is_set(),
+            Event::SyncWarning => isr.syncwarnf().bit_is_set(),
+            Event::Error => isr.errf().bit_is_set(),
+            Event::ExpectedSync => isr.esyncf().bit_is_set(),
+        }
+    }
+
+    /// Clears a pending event. Clearing [`Event::Error`] also clears the
+    /// error flags
+    pub fn clear(&mut self, event: Event) {
+        self.rb.icr.write(|w| match event {
+            Event::SyncOk => w.syncokc().set_bit(),
+            Event::SyncWarning => w.syncwarnc().set_bit(),
+            Event::Error => w.errc().set_bit(),
+            Event::ExpectedSync => w.esyncc().set_bit(),
+        });
+    }
+
+    /// Checks for an error. The error flags are cleared
+    pub fn check_errors(&mut self) -> Result<(), Error> {
+        let isr = self.rb.isr.read();
+        let result = if isr.syncerr().bit_is_set() {
+            Err(Error::SyncError)
+        } else if isr.syncmiss().bit_is_set() {
+            Err(Error::SyncMissed)
+        } else if isr.trimovf().bit_is_set() {
+            Err(Error::TrimOverflow)
+        } else {
+            return Ok(());
+        };
+        self.clear(Event::Error);
+
+        result
+    }
+
+    /// Returns the frequency error captured at the last synchronisation,
+    /// in HSI48 cycles. A positive value means that the HSI48 is too fast
+    pub fn frequency_error(&self) -> i32 {
+        let isr = self.rb.isr.read();
+        let fecap = i32::from(isr.fecap().bits());
+
+        // FEDIR is set when the counter was counting down at the
+        // synchronisation, so the HSI48 is too slow
+        if isr.fedir().bit_is_set() {
+            -fecap
+        } else {
+            fecap
+        }
+    }
+
+    /// Returns the HSI48 trimming value
+    pub fn trim(&self) -> u8 {
+        self.rb.cr.read().trim().bits()
+    }
+
+    /// Sets the HSI48 trimming value (0 - 63). With automatic trimming, the
+    /// value is changed by hardware at the next synchronisation
+    pub fn set_trim(&mut self, trim: u8) {
+        self.rb
+            .cr
+            .modify(|_, w| unsafe { w.trim().bits(trim & 0x3F) });
+    }
+
+    /// Generates a synchronisation event in software
+    pub fn software_sync(&mut self) {
+        self.rb.cr.modify(|_, w| w.swsync().set_bit());
+    }
+
+    /// Disables the CRS and releases the peripheral. The trimming value is
+    /// kept
+    pub fn free(self) -> CRS {
+        self.rb
+            .cr
+            .modify(|_, w| w.cen().clear_bit().autotrimen().clear_bit());
+        self.rb
+    }
+}
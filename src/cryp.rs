@@ -0,0 +1,466 @@
+//! Cryptographic processor (CRYP)
+//!
+//! The cryptographic processor encrypts and decrypts data with AES in ECB,
+//! CBC, CTR and GCM modes, with 128, 192 or 256 bit keys.
+//!
+//! This module is only available for the parts with the crypto accelerator
+//! (750, 753, 7B3, 7B0, 730, 733, 735).
+//!
+//! # Example
+//!
+//! ```
+//! let mut cryp = dp.CRYP.cryp(ccdr.peripheral.CRYP);
+//!
+//! let key = [0u8; 32];
+//! let iv = [0u8; 16];
+//! let mut data = [0u8; 64];
+//!
+//! // Encrypt in place
+//! cryp.encrypt(&key, Mode::Cbc(&iv), &mut data).unwrap();
+//! cryp.decrypt(&key, Mode::Cbc(&iv), &mut data).unwrap();
+//!
+//! // Authenticated encryption
+//! let nonce = [0u8; 12];
+//! let tag = cryp.gcm_encrypt(&key, &nonce, b"header", &mut data).unwrap();
+//! cryp.gcm_decrypt(&key, &nonce, b"header", &mut data, &tag).unwrap();
+//! ```
+//!
+//! # RustCrypto
+//!
+//! With the `aead` feature, [`AesGcm`] implements the `AeadInPlace` trait
+//! of the [`aead`](https://docs.rs/aead) crate.
+
+use core::marker::PhantomData;
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::CRYP;
+
+/// AES block size in bytes
+const BLOCK: usize = 16;
+
+/// ALGOMODE values. Bit 3 is ALGOMODE3, bits 2:0 are ALGOMODE0
+const ALGOMODE_AES_ECB: u8 = 0b0100;
+const ALGOMODE_AES_CBC: u8 = 0b0101;
+const ALGOMODE_AES_CTR: u8 = 0b0110;
+const ALGOMODE_AES_KEY: u8 = 0b0111;
+const ALGOMODE_AES_GCM: u8 = 0b1000;
+
+/// GCM_CCMPH values
+const PHASE_HEADER: u8 = 0b01;
+const PHASE_PAYLOAD: u8 = 0b10;
+const PHASE_FINAL: u8 = 0b11;
+
+/// CRYP error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The key is not 16, 24 or 32 bytes long
+    KeySize,
+    /// The data length is not a multiple of the block size, which is
+    /// required in ECB and CBC modes
+    DataLength,
+    /// The authentication tag does not match. The decrypted data must not
+    /// be used
+    Authentication,
+}
+
+/// AES chaining mode, with the initialization vector where required
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode<'a> {
+    /// Electronic codebook
+    Ecb,
+    /// Cipher block chaining
+    Cbc(&'a [u8; 16]),
+    /// Counter mode. The initial counter block is given
+    Ctr(&'a [u8; 16]),
+}
+
+impl Mode<'_> {
+    fn algomode(&self) -> u8 {
+        match self {
+            Mode::Ecb => ALGOMODE_AES_ECB,
+            Mode::Cbc(_) => ALGOMODE_AES_CBC,
+            Mode::Ctr(_) => ALGOMODE_AES_CTR,
+        }
+    }
+
+    fn iv(&self) -> Option<&[u8; 16]> {
+        match *self {
+            Mode::Ecb => None,
+            Mode::Cbc(iv) | Mode::Ctr(iv) => Some(iv),
+        }
+    }
+}
+
+/// Returns the KEYSIZE field of the CR register
+fn keysize(key: &[u8]) -> Result<u8, Error> {
+    match key.len() {
+        16 => Ok(0b00),
+        24 => Ok(0b01),
+        32 => Ok(0b10),
+        _ => Err(Error::KeySize),
+    }
+}
+
+/// Extension trait for the CRYP peripheral
+pub trait CrypExt: Sized {
+    /// Enable and reset the cryptographic processor
+    fn cryp(self, prec: rec::Crypt) -> Cryp;
+}
+
+impl CrypExt for CRYP {
+    fn cryp(self, prec: rec::Crypt) -> Cryp {
+        prec.enable().reset();
+
+        Cryp { rb: self }
+    }
+}
+
+/// Cryptographic processor
+pub struct Cryp {
+    rb: CRYP,
+}
+
+impl Cryp {
+    /// Encrypts `data` in place
+    ///
+    /// In ECB and CBC modes, the length of `data` must be a multiple of 16
+    /// bytes
+    pub fn encrypt(
+        &mut self,
+        key: &[u8],
+        mode: Mode,
+        data: &mut [u8],
+    ) -> Result<(), Error> {
+        self.process(key, mode, false, data)
+    }
+
+    /// Decrypts `data` in place
+    ///
+    /// In ECB and CBC modes, the length of `data` must be a multiple of 16
+    /// bytes
+    pub fn decrypt(
+        &mut self,
+        key: &[u8],
+        mode: Mode,
+        data: &mut [u8],
+    ) -> Result<(), Error> {
+        self.process(key, mode, true, data)
+    }
+
+    /// Encrypts `data` in place with AES-GCM, authenticating `aad` and
+    /// `data`. Returns the authentication tag
+    pub fn gcm_encrypt(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8; 12],
+        aad: &[u8],
+        data: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        self.gcm(key, nonce, aad, data, false)
+    }
+
+    /// Decrypts `data` in place with AES-GCM, and checks the
+    /// authentication tag of `aad` and `data`
+    ///
+    /// If the tag does not match, [`Error::Authentication`] is returned
+    /// and `data` is cleared
+    pub fn gcm_decrypt(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8; 12],
+        aad: &[u8],
+        data: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error> {
+        let calculated = self.gcm(key, nonce, aad, data, true)?;
+
+        // Compare in constant time
+        let diff = calculated
+            .iter()
+            .zip(tag.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b));
+        if diff != 0 {
+            data.iter_mut().for_each(|b| *b = 0);
+            return Err(Error::Authentication);
+        }
+        Ok(())
+    }
+
+    /// Releases the CRYP peripheral
+    pub fn free(self) -> (CRYP, rec::Crypt) {
+        (
+            self.rb,
+            rec::Crypt {
+                _marker: PhantomData,
+            },
+        )
+    }
+
+    fn process(
+        &mut self,
+        key: &[u8],
+        mode: Mode,
+        decrypt: bool,
+        data: &mut [u8],
+    ) -> Result<(), Error> {
+        let keysize = keysize(key)?;
+        if !matches!(mode, Mode::Ctr(_)) && data.len() % BLOCK != 0 {
+            return Err(Error::DataLength);
+        }
+
+        self.disable();
+        self.write_key(key);
+
+        // ECB and CBC decryption require the key schedule to be prepared
+        if decrypt && mode.algomode() != ALGOMODE_AES_CTR {
+            self.configure(keysize, ALGOMODE_AES_KEY, false);
+            self.enable();
+            self.wait_busy();
+        }
+
+        self.configure(keysize, mode.algomode(), decrypt);
+        if let Some(iv) = mode.iv() {
+            self.write_iv(iv);
+        }
+        self.enable();
+
+        self.process_blocks(data, 0);
+        self.disable();
+        Ok(())
+    }
+
+    fn gcm(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8; 12],
+        aad: &[u8],
+        data: &mut [u8],
+        decrypt: bool,
+    ) -> Result<[u8; 16], Error> {
+        let keysize = keysize(key)?;
+
+        // Initialisation phase. The counter starts at 2 for the payload
+        self.disable();
+        self.configure(keysize, ALGOMODE_AES_GCM, false);
+        self.write_key(key);
+        let mut iv = [0; 16];
+        iv[..12].copy_from_slice(nonce);
+        iv[15] = 2;
+        self.write_iv(&iv);
+        self.enable();
+        while self.rb.cr.read().crypen().bit_is_set() {}
+
+        // Header phase
+        if !aad.is_empty() {
+            self.set_phase(PHASE_HEADER);
+            self.enable();
+            for block in aad.chunks(BLOCK) {
+                let mut padded = [0; BLOCK];
+                padded[..block.len()].copy_from_slice(block);
+                self.write_block(&padded);
+            }
+            self.wait_busy();
+            self.disable();
+        }
+
+        // Payload phase
+        self.rb.cr.modify(|_, w| w.algodir().bit(decrypt));
+        if !data.is_empty() {
+            self.set_phase(PHASE_PAYLOAD);
+            let padding = ((BLOCK - data.len() % BLOCK) % BLOCK) as u8;
+            self.enable();
+            self.process_blocks(data, padding);
+            self.wait_busy();
+            self.disable();
+        }
+
+        // Final phase, always in encryption direction
+        self.set_phase(PHASE_FINAL);
+        self.rb.cr.modify(|_, w| w.algodir().clear_bit());
+        self.enable();
+        let aad_bits = (aad.len() as u64) * 8;
+        let data_bits = (data.len() as u64) * 8;
+        for &word in [
+            (aad_bits >> 32) as u32,
+            aad_bits as u32,
+            (data_bits >> 32) as u32,
+            data_bits as u32,
+        ]
+        .iter()
+        {
+            self.write_word(word);
+        }
+        let mut tag = [0; BLOCK];
+        self.read_block(&mut tag);
+        self.disable();
+
+        Ok(tag)
+    }
+
+    /// Feeds `data` through the processor in place. The last block is
+    /// padded with zeros, `padding` is the number of padding bytes
+    /// written to the NPBLB field
+    fn process_blocks(&mut self, data: &mut [u8], padding: u8) {
+        for block in data.chunks_mut(BLOCK) {
+            let mut input = [0; BLOCK];
+            input[..block.len()].copy_from_slice(block);
+            if block.len() < BLOCK {
+                self.rb.cr.modify(|_, w| unsafe { w.npblb().bits(padding) });
+            }
+            self.write_block(&input);
+
+            let mut output = [0; BLOCK];
+            self.read_block(&mut output);
+            block.copy_from_slice(&output[..block.len()]);
+        }
+    }
+
+    fn write_key(&mut self, key: &[u8]) {
+        // The key is aligned to the end of K0LR - K3RR
+        let words = key
+            .chunks(4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]));
+        let mut regs: [u32; 8] = [0; 8];
+        for (reg, word) in regs[8 - key.len() / 4..].iter_mut().zip(words) {
+            *reg = word;
+        }
+        let rb = &self.rb;
+        rb.k0lr.write(|w| unsafe { w.bits(regs[0]) });
+        rb.k0rr.write(|w| unsafe { w.bits(regs[1]) });
+        rb.k1lr.write(|w| unsafe { w.bits(regs[2]) });
+        rb.k1rr.write(|w| unsafe { w.bits(regs[3]) });
+        rb.k2lr.write(|w| unsafe { w.bits(regs[4]) });
+        rb.k2rr.write(|w| unsafe { w.bits(regs[5]) });
+        rb.k3lr.write(|w| unsafe { w.bits(regs[6]) });
+        rb.k3rr.write(|w| unsafe { w.bits(regs[7]) });
+    }
+
+    fn write_iv(&mut self, iv: &[u8; 16]) {
+        let word = |i: usize| {
+            u32::from_be_bytes([iv[i], iv[i + 1], iv[i + 2], iv[i + 3]])
+        };
+        let rb = &self.rb;
+        rb.iv0lr.write(|w| unsafe { w.bits(word(0)) });
+        rb.iv0rr.write(|w| unsafe { w.bits(word(4)) });
+        rb.iv1lr.write(|w| unsafe { w.bits(word(8)) });
+        rb.iv1rr.write(|w| unsafe { w.bits(word(12)) });
+    }
+
+    fn write_word(&mut self, word: u32) {
+        while self.rb.sr.read().ifnf().bit_is_clear() {}
+        self.rb.din.write(|w| unsafe { w.bits(word) });
+    }
+
+    fn write_block(&mut self, block: &[u8; BLOCK]) {
+        for word in block.chunks(4) {
+            self.write_word(u32::from_be_bytes([
+                word[0], word[1], word[2], word[3],
+            ]));
+        }
+    }
+
+    fn read_block(&mut self, block: &mut [u8; BLOCK]) {
+        for word in block.chunks_mut(4) {
+            while self.rb.sr.read().ofne().bit_is_clear() {}
+            word.copy_from_slice(&self.rb.dout.read().bits().to_be_bytes());
+        }
+    }
+
+    /// Writes the key size, the algorithm and the direction to CR. The
+    /// processor is disabled and the GCM phase is the initialisation phase
+    fn configure(&mut self, keysize: u8, algomode: u8, decrypt: bool) {
+        self.rb.cr.write(|w| unsafe {
+            w.keysize()
+                .bits(keysize)
+                .algomode0()
+                .bits(algomode & 0b111)
+                .algomode3()
+                .bit(algomode & 0b1000 != 0)
+                .algodir()
+                .bit(decrypt)
+        });
+    }
+
+    fn set_phase(&mut self, phase: u8) {
+        self.rb
+            .cr
+            .modify(|_, w| unsafe { w.gcm_ccmph().bits(phase) });
+    }
+
+    fn enable(&mut self) {
+        self.rb.cr.modify(|_, w| w.crypen().set_bit());
+    }
+
+    fn disable(&mut self) {
+        self.rb.cr.modify(|_, w| w.crypen().clear_bit());
+        self.rb.cr.modify(|_, w| w.fflush().set_bit());
+    }
+
+    fn wait_busy(&self) {
+        while self.rb.sr.read().busy().bit_is_set() {}
+    }
+}
+
+/// AES-GCM with the cryptographic processor, as a RustCrypto AEAD, see
+/// the [module level documentation](self)
+#[cfg(feature = "aead")]
+pub struct AesGcm<'a> {
+    cryp: core::cell::RefCell<&'a mut Cryp>,
+    key: &'a [u8],
+}
+
+#[cfg(feature = "aead")]
+impl<'a> AesGcm<'a> {
+    /// Returns an AEAD with `key`, which must be 16, 24 or 32 bytes long
+    pub fn new(cryp: &'a mut Cryp, key: &'a [u8]) -> Result<Self, Error> {
+        keysize(key)?;
+        Ok(AesGcm {
+            cryp: core::cell::RefCell::new(cryp),
+            key,
+        })
+    }
+}
+
+#[cfg(feature = "aead")]
+impl aead::AeadCore for AesGcm<'_> {
+    type NonceSize = aead::consts::U12;
+    type TagSize = aead::consts::U16;
+    type CiphertextOverhead = aead::consts::U0;
+}
+
+#[cfg(feature = "aead")]
+impl aead::AeadInPlace for AesGcm<'_> {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<aead::Tag<Self::TagSize>, aead::Error> {
+        let mut n = [0; 12];
+        n.copy_from_slice(nonce);
+        let tag = self
+            .cryp
+            .borrow_mut()
+            .gcm_encrypt(self.key, &n, associated_data, buffer)
+            .map_err(|_| aead::Error)?;
+        Ok(tag.into())
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &aead::Tag<Self::TagSize>,
+    ) -> Result<(), aead::Error> {
+        let mut n = [0; 12];
+        n.copy_from_slice(nonce);
+        let mut t = [0; 16];
+        t.copy_from_slice(tag);
+        self.cryp
+            .borrow_mut()
+            .gcm_decrypt(self.key, &n, associated_data, buffer, &t)
+            .map_err(|_| aead::Error)
+    }
+}
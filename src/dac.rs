@@ -1,4 +1,50 @@
 //! Digital to Analog Converter (DAC)
+//!
+//! Each channel converts the value written with
+//! [`set_value`](crate::traits::DacOut::set_value), either immediately or on
+//! each occurrence of a [`TriggerSource`]. The channel can also generate a
+//! noise or triangle [`Wave`], which is added to the written value on each
+//! trigger.
+//!
+//! # Waveform playback with DMA
+//!
+//! An arbitrary waveform can be played from a buffer of samples by
+//! triggering the channel from a timer, and using a circular DMA transfer to
+//! supply the samples. DMA is not yet implemented for RM0455 parts.
+//!
+//! ```
+//! // Calibrate output buffer, trigger each time TIM6 overflows
+//! let mut dac = dac.calibrate_buffer(&mut delay);
+//! dac.set_trigger(Some(TriggerSource::TIM6_TRGO));
+//! let dac = dac.enable();
+//!
+//! let config = DmaConfig::default()
+//!     .memory_increment(true)
+//!     .circular_buffer(true);
+//! let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+//!     Transfer::init_const(streams.0, dac, &SAMPLES, None, config);
+//!
+//! transfer.start(|dac| dac.enable_dma(true));
+//! ```
+//!
+//! # Connection to on-chip peripherals
+//!
+//! A channel of DAC1 can be connected to the inverting input of the
+//! comparators. A channel made from an [`Internal1`] or [`Internal2`]
+//! marker instead of a pin is an [`InternalC1`] or [`InternalC2`], which is
+//! only connected internally, so its pin is free for other uses. It can
+//! only be enabled with
+//! [`enable_internal`](InternalC1#method.enable_internal).
+//!
+//! ```
+//! let threshold = dp.DAC.dac(dac::Internal1, ccdr.peripheral.DAC12);
+//! let mut threshold = threshold.enable_internal();
+//! threshold.set_value(2048);
+//!
+//! comp1.configure(
+//!     comp::Config::new().inverting(threshold.comparator_input()),
+//! );
+//! ```
 
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
@@ -7,6 +53,9 @@ use core::mem::MaybeUninit;
 use crate::gpio::gpioa::PA6;
 use crate::gpio::gpioa::{PA4, PA5};
 
+use crate::comp::InvertingInput;
+#[cfg(not(feature = "rm0455"))]
+use crate::dma::{traits::TargetAddress, MemoryToPeripheral};
 use crate::gpio::Analog;
 use crate::hal::blocking::delay::DelayUs;
 use crate::rcc::{rec, ResetEnable};
@@ -16,18 +65,85 @@ use crate::stm32::DAC as DAC1;
 use crate::stm32::{DAC1, DAC2};
 use crate::traits::DacOut;
 
+#[cfg(not(feature = "rm0455"))]
+type DMAReq = crate::stm32::dmamux1::ccr::DMAREQ_ID_A;
+
 /// Enabled DAC (type state)
 pub struct Enabled;
 /// Enabled DAC without output buffer (type state)
 pub struct EnabledUnbuffered;
+/// Enabled DAC, only connected to on-chip peripherals (type state)
+pub struct EnabledInternal;
 /// Disabled DAC (type state)
 pub struct Disabled;
 
 pub trait ED {}
 impl ED for Enabled {}
 impl ED for EnabledUnbuffered {}
+impl ED for EnabledInternal {}
 impl ED for Disabled {}
 
+/// Trigger sources for a DAC channel
+///
+/// The triggers that differ between the families are only available on the
+/// parts where the trigger input connects them. On RM0455 parts the values
+/// are those of DAC1.
+//
+// Refer to RM0433 Rev 7 - Table 225, and the DAC interconnection tables of
+// RM0455 and RM0468
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum TriggerSource {
+    /// Triggered by calling `software_trigger`
+    Software = 0,
+    TIM1_TRGO = 1,
+    TIM2_TRGO = 2,
+    TIM4_TRGO = 3,
+    TIM5_TRGO = 4,
+    TIM6_TRGO = 5,
+    TIM7_TRGO = 6,
+    TIM8_TRGO = 7,
+    TIM15_TRGO = 8,
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    HRTIM1_DACTRG1 = 9,
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    HRTIM1_DACTRG2 = 10,
+    #[cfg(feature = "rm0468")]
+    TIM23_TRGO = 9,
+    #[cfg(feature = "rm0468")]
+    TIM24_TRGO = 10,
+    LPTIM1_OUT = 11,
+    LPTIM2_OUT = 12,
+    EXTI9 = 13,
+    LPTIM3_OUT = 14,
+}
+
+/// Waves generated by a DAC channel
+///
+/// On each trigger, the wave is added to the value written with
+/// [`set_value`](crate::traits::DacOut::set_value)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wave {
+    /// Pseudo-random noise. The lowest `bits` of the LFSR are used, where
+    /// `bits` is 1 - 12
+    Noise { bits: u8 },
+    /// Triangle wave with an amplitude of 2^`bits` - 1, where `bits` is
+    /// 1 - 12
+    Triangle { bits: u8 },
+}
+
+impl Wave {
+    /// Returns (WAVE, MAMP)
+    fn bits(self) -> (u8, u8) {
+        let (wave, bits) = match self {
+            Wave::Noise { bits } => (0b01, bits),
+            Wave::Triangle { bits } => (0b10, bits),
+        };
+        assert!((1..=12).contains(&bits), "DAC waves have 1 - 12 bits");
+        (wave, bits - 1)
+    }
+}
+
 pub struct C1<DAC, ED> {
     _dac: PhantomData<DAC>,
     _enabled: PhantomData<ED>,
@@ -36,12 +152,29 @@ pub struct C2<DAC, ED> {
     _dac: PhantomData<DAC>,
     _enabled: PhantomData<ED>,
 }
+/// Channel 1, only connected to on-chip peripherals
+pub struct InternalC1<DAC, ED> {
+    _dac: PhantomData<DAC>,
+    _enabled: PhantomData<ED>,
+}
+/// Channel 2, only connected to on-chip peripherals
+pub struct InternalC2<DAC, ED> {
+    _dac: PhantomData<DAC>,
+    _enabled: PhantomData<ED>,
+}
 
 /// Trait for GPIO pins that can be converted to DAC output pins
 pub trait Pins<DAC> {
     type Output;
 }
 
+/// Marker for channel 1 when it is only connected to on-chip peripherals,
+/// and not to its pin. The channel is an [`InternalC1`]
+pub struct Internal1;
+/// Marker for channel 2 when it is only connected to on-chip peripherals,
+/// and not to its pin. The channel is an [`InternalC2`]
+pub struct Internal2;
+
 // DAC1
 
 impl Pins<DAC1> for PA4<Analog> {
@@ -56,6 +189,26 @@ impl Pins<DAC1> for (PA4<Analog>, PA5<Analog>) {
     type Output = (C1<DAC1, Disabled>, C2<DAC1, Disabled>);
 }
 
+impl Pins<DAC1> for Internal1 {
+    type Output = InternalC1<DAC1, Disabled>;
+}
+
+impl Pins<DAC1> for Internal2 {
+    type Output = InternalC2<DAC1, Disabled>;
+}
+
+impl Pins<DAC1> for (Internal1, PA5<Analog>) {
+    type Output = (InternalC1<DAC1, Disabled>, C2<DAC1, Disabled>);
+}
+
+impl Pins<DAC1> for (PA4<Analog>, Internal2) {
+    type Output = (C1<DAC1, Disabled>, InternalC2<DAC1, Disabled>);
+}
+
+impl Pins<DAC1> for (Internal1, Internal2) {
+    type Output = (InternalC1<DAC1, Disabled>, InternalC2<DAC1, Disabled>);
+}
+
 // DAC2
 
 #[cfg(feature = "rm0455")]
@@ -81,8 +234,100 @@ where
 }
 
 macro_rules! dac {
-    ($DAC:ident, $CX:ident, $en:ident, $cen:ident, $cal_flag:ident, $trim:ident,
-     $mode:ident, $dhrx:ident, $dor:ident, $daccxdhr:ident) => {
+    (@channel $DAC:ident, $CX:ident, $en:ident, $mode:ident, $dhrx:ident,
+     $dor:ident, $ten:ident, $tsel:ident, $wave:ident, $mamp:ident,
+     $swtrig:ident, $dmaen:ident, $dmaudr:ident) => {
+        impl $CX<$DAC, Disabled> {
+            /// Sets the trigger for conversions. If `None`, the value written
+            /// with `set_value` is converted immediately
+            pub fn set_trigger(&mut self, trigger: Option<TriggerSource>) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                match trigger {
+                    Some(source) => dac.cr.modify(|_, w| unsafe {
+                        w.$tsel().bits(source as u8).$ten().set_bit()
+                    }),
+                    None => dac.cr.modify(|_, w| w.$ten().clear_bit()),
+                }
+            }
+
+            /// Sets the wave generated by the channel, or disables wave
+            /// generation if `None`. Wave generation requires a trigger
+            ///
+            /// # Panics
+            ///
+            /// Panics if the number of bits is not 1 - 12
+            pub fn set_wave(&mut self, wave: Option<Wave>) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                let (wave, mamp) = wave.map(Wave::bits).unwrap_or((0, 0));
+                dac.cr.modify(|_, w| unsafe {
+                    w.$wave().bits(wave).$mamp().bits(mamp)
+                });
+            }
+        }
+
+        impl<ED> $CX<$DAC, ED> {
+            /// Triggers a conversion, when the trigger is
+            /// [`TriggerSource::Software`]
+            pub fn software_trigger(&mut self) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.swtrgr.write(|w| w.$swtrig().set_bit());
+            }
+
+            /// Enables or disables DMA requests. When enabled, a request is
+            /// made on each trigger
+            pub fn enable_dma(&mut self, enable: bool) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.cr.modify(|_, w| w.$dmaen().bit(enable));
+            }
+
+            /// Returns `true` if a trigger occurred before the DMA supplied
+            /// the previous sample. Clears the flag
+            ///
+            /// DMA requests are not made after an underrun until the flag is
+            /// cleared.
+            pub fn check_dma_underrun(&mut self) -> bool {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                let underrun = dac.sr.read().$dmaudr().bit_is_set();
+                if underrun {
+                    dac.sr.write(|w| w.$dmaudr().set_bit());
+                }
+                underrun
+            }
+
+            /// Disable the DAC channel
+            pub fn disable(self) -> $CX<$DAC, Disabled> {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.cr.modify(|_, w| w.$en().clear_bit());
+
+                $CX {
+                    _dac: PhantomData,
+                    _enabled: PhantomData,
+                }
+            }
+        }
+
+        /// DacOut implementation available in any Enabled/Disabled state
+        impl<ED> DacOut<u16> for $CX<$DAC, ED> {
+            fn set_value(&mut self, val: u16) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.$dhrx.write(|w| unsafe { w.bits(val as u32) });
+            }
+
+            fn get_value(&mut self) -> u16 {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.$dor.read().bits() as u16
+            }
+        }
+    };
+    ($DAC:ident, $CX:ident $(/ $ICX:ident)?, $en:ident, $cen:ident,
+     $cal_flag:ident, $trim:ident, $mode:ident, $dhrx:ident, $dor:ident,
+     $daccxdhr:ident, $ten:ident, $tsel:ident, $wave:ident, $mamp:ident,
+     $swtrig:ident, $dmaen:ident, $dmaudr:ident) => {
+        dac!(@channel $DAC, $CX, $en, $mode, $dhrx, $dor, $ten, $tsel, $wave,
+             $mamp, $swtrig, $dmaen, $dmaudr);
+
         impl $CX<$DAC, Disabled> {
             pub fn enable(self) -> $CX<$DAC, Enabled> {
                 let dac = unsafe { &(*$DAC::ptr()) };
@@ -107,6 +352,21 @@ macro_rules! dac {
                     _enabled: PhantomData,
                 }
             }
+
+            /// Enables the channel with its output buffer, connected to
+            /// both its pin and on-chip peripherals
+            pub fn enable_with_internal(self) -> $CX<$DAC, Enabled> {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                // Refer to RM0433 Rev 7 - Chapter 26.5.2
+                dac.mcr.modify(|_, w| unsafe { w.$mode().bits(1) });
+                dac.cr.modify(|_, w| w.$en().set_bit());
+
+                $CX {
+                    _dac: PhantomData,
+                    _enabled: PhantomData,
+                }
+            }
         }
 
         impl<ED> $CX<$DAC, ED> {
@@ -148,31 +408,28 @@ macro_rules! dac {
                     _enabled: PhantomData,
                 }
             }
+        }
 
-            /// Disable the DAC channel
-            pub fn disable(self) -> $CX<$DAC, Disabled> {
-                let dac = unsafe { &(*$DAC::ptr()) };
-                dac.cr.modify(|_, w| w.$en().clear_bit());
+        $(
+            dac!(@channel $DAC, $ICX, $en, $mode, $dhrx, $dor, $ten, $tsel,
+                 $wave, $mamp, $swtrig, $dmaen, $dmaudr);
 
-                $CX {
-                    _dac: PhantomData,
-                    _enabled: PhantomData,
-                }
-            }
-        }
+            impl $ICX<$DAC, Disabled> {
+                /// Enables the channel without its output buffer, connected
+                /// only to on-chip peripherals. Its pin is not driven
+                pub fn enable_internal(self) -> $ICX<$DAC, EnabledInternal> {
+                    let dac = unsafe { &(*$DAC::ptr()) };
 
-        /// DacOut implementation available in any Enabled/Disabled state
-        impl<ED> DacOut<u16> for $CX<$DAC, ED> {
-            fn set_value(&mut self, val: u16) {
-                let dac = unsafe { &(*$DAC::ptr()) };
-                dac.$dhrx.write(|w| unsafe { w.bits(val as u32) });
-            }
+                    dac.mcr.modify(|_, w| unsafe { w.$mode().bits(3) });
+                    dac.cr.modify(|_, w| w.$en().set_bit());
 
-            fn get_value(&mut self) -> u16 {
-                let dac = unsafe { &(*$DAC::ptr()) };
-                dac.$dor.read().bits() as u16
+                    $ICX {
+                        _dac: PhantomData,
+                        _enabled: PhantomData,
+                    }
+                }
             }
-        }
+        )?
     };
 }
 
@@ -210,10 +467,110 @@ impl DacExt for DAC2 {
     }
 }
 
-dac!(DAC1, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
-dac!(DAC1, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+dac!(
+    DAC1,
+    C1 / InternalC1,
+    en1,
+    cen1,
+    cal_flag1,
+    otrim1,
+    mode1,
+    dhr12r1,
+    dor1,
+    dacc1dhr,
+    ten1,
+    tsel1,
+    wave1,
+    mamp1,
+    swtrig1,
+    dmaen1,
+    dmaudr1
+);
+dac!(
+    DAC1,
+    C2 / InternalC2,
+    en2,
+    cen2,
+    cal_flag2,
+    otrim2,
+    mode2,
+    dhr12r2,
+    dor2,
+    dacc2dhr,
+    ten2,
+    tsel2,
+    wave2,
+    mamp2,
+    swtrig2,
+    dmaen2,
+    dmaudr2
+);
 
 #[cfg(feature = "rm0455")]
-dac!(DAC2, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
+dac!(
+    DAC2, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr,
+    ten1, tsel1, wave1, mamp1, swtrig1, dmaen1, dmaudr1
+);
 #[cfg(feature = "rm0455")]
-dac!(DAC2, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+dac!(
+    DAC2, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr,
+    ten2, tsel2, wave2, mamp2, swtrig2, dmaen2, dmaudr2
+);
+
+impl<ED> C1<DAC1, ED> {
+    /// Returns the comparator inverting input connected to this channel.
+    /// The channel must be enabled with `enable_with_internal`
+    pub fn comparator_input(&self) -> InvertingInput {
+        InvertingInput::Dac1Out1
+    }
+}
+
+impl<ED> C2<DAC1, ED> {
+    /// Returns the comparator inverting input connected to this channel.
+    /// The channel must be enabled with `enable_with_internal`
+    pub fn comparator_input(&self) -> InvertingInput {
+        InvertingInput::Dac1Out2
+    }
+}
+
+impl<ED> InternalC1<DAC1, ED> {
+    /// Returns the comparator inverting input connected to this channel
+    pub fn comparator_input(&self) -> InvertingInput {
+        InvertingInput::Dac1Out1
+    }
+}
+
+impl<ED> InternalC2<DAC1, ED> {
+    /// Returns the comparator inverting input connected to this channel
+    pub fn comparator_input(&self) -> InvertingInput {
+        InvertingInput::Dac1Out2
+    }
+}
+
+// Samples are written to the 12-bit right aligned data holding register
+#[cfg(not(feature = "rm0455"))]
+macro_rules! dac_dma {
+    ($($CX:ident: $dhr:ident, $request:ident;)+) => {
+        $(
+            unsafe impl<ED> TargetAddress<MemoryToPeripheral> for $CX<DAC1, ED> {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    let dac = unsafe { &(*DAC1::ptr()) };
+                    &dac.$dhr as *const _ as usize
+                }
+
+                type MemSize = u16;
+
+                const REQUEST_LINE: Option<u8> = Some(DMAReq::$request as u8);
+            }
+        )+
+    };
+}
+
+#[cfg(not(feature = "rm0455"))]
+dac_dma! {
+    C1: dhr12r1, DAC_CH1_DMA;
+    C2: dhr12r2, DAC_CH2_DMA;
+    InternalC1: dhr12r1, DAC_CH1_DMA;
+    InternalC2: dhr12r2, DAC_CH2_DMA;
+}
@@ -0,0 +1,246 @@
+//! Debouncing of input pins
+//!
+//! Buttons and limit switches bounce for some milliseconds when they
+//! change state. An EXTI interrupt on such an input fires on every bounce,
+//! and masking it for a while with a timer easily drops the final change.
+//! A [`Debouncer`] instead samples each registered input from a periodic
+//! timer interrupt, and reports a change once the input has been stable
+//! at its new level for the debounce time.
+//!
+//! # Example
+//!
+//! ```
+//! let button = cortex_m::singleton!(: PC13<Input<PullUp>> =
+//!     gpioc.pc13.into_pull_up_input()).unwrap();
+//! let limit = cortex_m::singleton!(: PE3<Input<PullUp>> =
+//!     gpioe.pe3.into_pull_up_input()).unwrap();
+//!
+//! // Sampled every 1ms, stable after 20ms
+//! let mut debouncer = Debouncer::new(1.ms(), 20.ms());
+//! let button = debouncer.register(button).unwrap();
+//! let limit = debouncer.register(limit).unwrap();
+//!
+//! let mut timer = dp.TIM2.timer(1.khz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//! timer.listen(Event::TimeOut);
+//!
+//! // In the TIM2 interrupt
+//! timer.clear_irq();
+//! for event in debouncer.sample().unwrap() {
+//!     if event.input == button && !event.high {
+//!         // Button pressed
+//!     }
+//! }
+//! ```
+//!
+//! Events can also be delivered to a callback set with
+//! [`on_change`](Debouncer::on_change), which is called from
+//! [`sample`](Debouncer::sample) for each event.
+//!
+//! The level of each input when it is first sampled is its initial stable
+//! level, and is not reported as an event.
+
+use crate::found::Found;
+use crate::hal::digital::v2::InputPin;
+use crate::time::MilliSeconds;
+
+/// Maximum number of inputs
+pub const MAX_INPUTS: usize = 16;
+
+/// Change of an input to a new stable level
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Index of the input, as returned by
+    /// [`register`](Debouncer::register)
+    pub input: usize,
+    /// The new level of the input
+    pub high: bool,
+}
+
+/// Events from one call to [`sample`](Debouncer::sample)
+#[derive(Clone, Debug)]
+pub struct Events(Found<Event, [Option<Event>; MAX_INPUTS]>);
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.0.next()
+    }
+}
+
+/// Samples input pins, and reports the changes that are stable
+pub struct Debouncer<'a, E> {
+    inputs: [Option<&'a dyn InputPin<Error = E>>; MAX_INPUTS],
+    /// Number of registered inputs
+    count: usize,
+    /// Number of consecutive samples at a new level for it to be stable
+    samples: u8,
+    /// Stable level of each input
+    stable: u16,
+    /// Inputs that have been sampled at least once
+    known: u16,
+    /// Consecutive samples of each input that differ from its stable level
+    changed: [u8; MAX_INPUTS],
+    callback: Option<fn(Event)>,
+}
+
+impl<'a, E> Debouncer<'a, E> {
+    /// Creates a debouncer that is sampled every `period`, and reports a
+    /// change once an input has been at its new level for `debounce`
+    ///
+    /// The debounce time is rounded up to a multiple of the sample period,
+    /// and is at least one period.
+    pub fn new<P, D>(period: P, debounce: D) -> Self
+    where
+        P: Into<MilliSeconds>,
+        D: Into<MilliSeconds>,
+    {
+        let period = period.into().0.max(1);
+        let samples = (debounce.into().0 + period - 1) / period;
+
+        Debouncer {
+            inputs: [None; MAX_INPUTS],
+            count: 0,
+            samples: samples.max(1).min(u32::from(u8::MAX)) as u8,
+            stable: 0,
+            known: 0,
+            changed: [0; MAX_INPUTS],
+            callback: None,
+        }
+    }
+
+    /// Registers `pin`, and returns its index in the events. Returns
+    /// `None` if [`MAX_INPUTS`] inputs are already registered
+    pub fn register(
+        &mut self,
+        pin: &'a dyn InputPin<Error = E>,
+    ) -> Option<usize> {
+        if self.count == MAX_INPUTS {
+            return None;
+        }
+        let index = self.count;
+        self.inputs[index] = Some(pin);
+        self.changed[index] = 0;
+        self.count += 1;
+
+        Some(index)
+    }
+
+    /// Calls `callback` from [`sample`](Debouncer::sample) for each event,
+    /// or no longer calls a callback with `None`
+    pub fn on_change(&mut self, callback: Option<fn(Event)>) {
+        self.callback = callback;
+    }
+
+    /// Samples the inputs, and returns the inputs that have become stable
+    /// at a new level. Must be called every sample period
+    pub fn sample(&mut self) -> Result<Events, E> {
+        let mut events = Found::new([None; MAX_INPUTS]);
+
+        for input in 0..self.count {
+            let pin = match self.inputs[input] {
+                Some(pin) => pin,
+                None => continue,
+            };
+            let high = pin.is_high()?;
+            let mask = 1 << input;
+
+            if self.known & mask == 0 {
+                self.known |= mask;
+                self.set_stable(mask, high);
+                continue;
+            }
+
+            if high == (self.stable & mask != 0) {
+                self.changed[input] = 0;
+                continue;
+            }
+            self.changed[input] += 1;
+            if self.changed[input] < self.samples {
+                continue;
+            }
+
+            self.changed[input] = 0;
+            self.set_stable(mask, high);
+            let event = Event { input, high };
+            if let Some(callback) = self.callback {
+                callback(event);
+            }
+            events.push(event);
+        }
+
+        Ok(Events(events))
+    }
+
+    /// Returns the stable level of input `input`, or `None` if it has not
+    /// been sampled yet
+    pub fn is_high(&self, input: usize) -> Option<bool> {
+        if input >= self.count || self.known & (1 << input) == 0 {
+            return None;
+        }
+        Some(self.stable & (1 << input) != 0)
+    }
+
+    fn set_stable(&mut self, mask: u16, high: bool) {
+        if high {
+            self.stable |= mask;
+        } else {
+            self.stable &= !mask;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    struct Pin(Cell<bool>);
+
+    impl InputPin for Pin {
+        type Error = Infallible;
+
+        fn is_high(&self) -> Result<bool, Infallible> {
+            Ok(self.0.get())
+        }
+        fn is_low(&self) -> Result<bool, Infallible> {
+            Ok(!self.0.get())
+        }
+    }
+
+    #[test]
+    fn stable_after_debounce_time() {
+        let button = Pin(Cell::new(true));
+        let mut debouncer = Debouncer::new(MilliSeconds(1), MilliSeconds(3));
+        let input = debouncer.register(&button).unwrap();
+
+        // The first sample is the initial level, not an event
+        assert_eq!(debouncer.is_high(input), None);
+        assert_eq!(debouncer.sample().unwrap().count(), 0);
+        assert_eq!(debouncer.is_high(input), Some(true));
+
+        // A bounce shorter than the debounce time is not reported
+        button.0.set(false);
+        assert_eq!(debouncer.sample().unwrap().count(), 0);
+        button.0.set(true);
+        assert_eq!(debouncer.sample().unwrap().count(), 0);
+
+        button.0.set(false);
+        assert_eq!(debouncer.sample().unwrap().count(), 0);
+        assert_eq!(debouncer.sample().unwrap().count(), 0);
+        let events: Vec<Event> = debouncer.sample().unwrap().collect();
+        assert_eq!(events, [Event { input, high: false }]);
+        assert_eq!(debouncer.is_high(input), Some(false));
+    }
+
+    #[test]
+    fn register_up_to_max_inputs() {
+        let pin = Pin(Cell::new(false));
+        let mut debouncer = Debouncer::new(MilliSeconds(1), MilliSeconds(0));
+        for i in 0..MAX_INPUTS {
+            assert_eq!(debouncer.register(&pin), Some(i));
+        }
+        assert_eq!(debouncer.register(&pin), None);
+    }
+}
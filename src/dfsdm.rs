@@ -0,0 +1,685 @@
+//! Digital filter for sigma delta modulators (DFSDM)
+//!
+//! The DFSDM has 8 serial input channels and 4 digital filters. Each
+//! channel receives a bitstream from an external sigma-delta modulator, such
+//! as a PDM microphone, over a SPI or Manchester coded interface. Each filter
+//! can then be connected to any channel to produce 24-bit signed samples.
+//!
+//! Filters convert channels either as a single *regular* channel, optionally
+//! in continuous mode, or as an *injected* group of one or more channels that
+//! are scanned in sequence.
+//!
+//! The DFSDM can generate a clock for the external modulators on its CKOUT
+//! pin, derived from its kernel clock.
+//!
+//! GPIOs must be set to the correct alternate function by the user.
+//!
+//! # Example
+//!
+//! ```
+//! // 3.072 MHz PDM clock
+//! let dfsdm = dp.DFSDM.dfsdm(Some(3_072.khz()), ccdr.peripheral.DFSDM1, &ccdr.clocks);
+//! let mut parts = dfsdm.split();
+//!
+//! parts.ch0.configure(
+//!     ChannelConfig::new(SerialInterface::SpiRising, SpiClock::Internal)
+//!         .right_shift(2),
+//! );
+//! parts.ch0.enable();
+//!
+//! // Sinc4 filter, 1.536 MHz / 64 = 48 kHz
+//! parts.flt0.configure(FilterConfig::new(FilterOrder::Sinc4, 64));
+//! parts.flt0.set_regular_channel(&parts.ch0, true);
+//! parts.flt0.enable();
+//! parts.flt0.start_regular();
+//!
+//! let (sample, _channel) = block!(parts.flt0.read_regular()).unwrap();
+//! ```
+//!
+//! # DMA
+//!
+//! Each filter implements [`TargetAddress`](crate::dma::traits::TargetAddress)
+//! for regular conversions. After setting up a transfer, call
+//! [`enable_regular_dma`](Filter0::enable_regular_dma). Each word contains the
+//! sample in the upper 24 bits and the channel number in the lower 3 bits.
+
+use core::marker::PhantomData;
+
+use crate::dma::{traits::TargetAddress, PeripheralToMemory};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::DFSDM;
+use crate::time::Hertz;
+
+type DMAReq = crate::stm32::dmamux1::ccr::DMAREQ_ID_A;
+
+/// DFSDM error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A conversion result was overwritten before it was read
+    Overrun,
+}
+
+/// Serial interface type of a channel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SerialInterface {
+    /// SPI with data sampled on the rising edge of the clock
+    SpiRising = 0b00,
+    /// SPI with data sampled on the falling edge of the clock
+    SpiFalling = 0b01,
+    /// Manchester coded input, a rising edge is a logic 0
+    ManchesterRising = 0b10,
+    /// Manchester coded input, a rising edge is a logic 1
+    ManchesterFalling = 0b11,
+}
+
+/// Clock source of a channel in SPI mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpiClock {
+    /// Clock from the CKINy pin
+    External = 0b00,
+    /// Internal CKOUT clock
+    Internal = 0b01,
+    /// Internal CKOUT clock divided by 2, data sampled on falling edges
+    InternalDiv2Falling = 0b10,
+    /// Internal CKOUT clock divided by 2, data sampled on rising edges
+    InternalDiv2Rising = 0b11,
+}
+
+/// Sinc filter order
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FilterOrder {
+    FastSinc = 0b000,
+    Sinc1 = 0b001,
+    Sinc2 = 0b010,
+    Sinc3 = 0b011,
+    Sinc4 = 0b100,
+    Sinc5 = 0b101,
+}
+
+/// Channel configuration
+#[derive(Copy, Clone, Debug)]
+pub struct ChannelConfig {
+    interface: SerialInterface,
+    clock: SpiClock,
+    offset: i32,
+    right_shift: u8,
+}
+
+impl ChannelConfig {
+    /// Create a configuration for a channel with the given serial `interface`
+    /// and SPI `clock` source. The clock source is ignored in Manchester
+    /// mode
+    pub fn new(interface: SerialInterface, clock: SpiClock) -> Self {
+        ChannelConfig {
+            interface,
+            clock,
+            offset: 0,
+            right_shift: 0,
+        }
+    }
+
+    /// 24-bit calibration offset that is subtracted from each sample
+    pub fn offset(mut self, offset: i32) -> Self {
+        assert!(offset >= -(1 << 23) && offset < (1 << 23));
+        self.offset = offset;
+        self
+    }
+
+    /// Number of bits that the filter output is shifted right by, so that
+    /// it fits into 24 bits
+    pub fn right_shift(mut self, right_shift: u8) -> Self {
+        assert!(right_shift < 32);
+        self.right_shift = right_shift;
+        self
+    }
+}
+
+/// Filter configuration
+#[derive(Copy, Clone, Debug)]
+pub struct FilterConfig {
+    order: FilterOrder,
+    oversampling: u16,
+    integrator: u16,
+    fast: bool,
+}
+
+impl FilterConfig {
+    /// Create a configuration for a filter with the given sinc `order` and
+    /// `oversampling` ratio (1 - 1024)
+    pub fn new(order: FilterOrder, oversampling: u16) -> Self {
+        assert!(oversampling >= 1 && oversampling <= 1024);
+        FilterConfig {
+            order,
+            oversampling,
+            integrator: 1,
+            fast: false,
+        }
+    }
+
+    /// Integrator oversampling ratio (1 - 256). The data rate is divided by
+    /// this ratio in addition to the filter oversampling ratio.
+    pub fn integrator(mut self, integrator: u16) -> Self {
+        assert!(integrator >= 1 && integrator <= 256);
+        self.integrator = integrator;
+        self
+    }
+
+    /// Enable fast mode. In continuous mode, the first conversion after a
+    /// start is not delayed by the filter settling time.
+    pub fn fast(mut self, fast: bool) -> Self {
+        self.fast = fast;
+        self
+    }
+}
+
+/// Filter events
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    /// End of a regular conversion
+    RegularEndOfConversion,
+    /// End of an injected conversion
+    InjectedEndOfConversion,
+    /// Regular conversion overrun
+    RegularOverrun,
+    /// Injected conversion overrun
+    InjectedOverrun,
+}
+
+/// A DFSDM input channel
+pub trait Channel {
+    /// Channel number (0 - 7)
+    const NUMBER: u8;
+}
+
+/// Extension trait for the DFSDM peripheral
+pub trait DfsdmExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Enable and reset the DFSDM. If `clock_out` is not `None`, a clock of
+    /// this frequency is generated on the CKOUT pin
+    fn dfsdm<T>(
+        self,
+        clock_out: Option<T>,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> Dfsdm
+    where
+        T: Into<Hertz>;
+}
+
+impl DfsdmExt for DFSDM {
+    type Rec = rec::Dfsdm1;
+
+    fn dfsdm<T>(
+        self,
+        clock_out: Option<T>,
+        prec: rec::Dfsdm1,
+        clocks: &CoreClocks,
+    ) -> Dfsdm
+    where
+        T: Into<Hertz>,
+    {
+        Dfsdm::dfsdm1(self, clock_out.map(|f| f.into()), prec, clocks)
+    }
+}
+
+/// Digital filter for sigma delta modulators
+pub struct Dfsdm {
+    rb: DFSDM,
+    ker_ck: Hertz,
+    clock_out: Option<Hertz>,
+}
+
+impl Dfsdm {
+    /// Enable and reset the DFSDM. If `clock_out` is not `None`, a clock of
+    /// approximately this frequency is generated on the CKOUT pin.
+    ///
+    /// Panics if the DFSDM kernel clock is not running, or if `clock_out`
+    /// cannot be generated from it
+    pub fn dfsdm1(
+        dfsdm: DFSDM,
+        clock_out: Option<Hertz>,
+        prec: rec::Dfsdm1,
+        clocks: &CoreClocks,
+    ) -> Self {
+        let prec = prec.enable().reset();
+
+        let ker_ck = match prec.get_kernel_clk_mux() {
+            rec::Dfsdm1ClkSel::RCC_PCLK2 => clocks.pclk2(),
+            rec::Dfsdm1ClkSel::SYS => clocks.sys_ck(),
+        };
+
+        // Clock output divider. 0 means the output is disabled, otherwise
+        // the division ratio is CKOUTDIV + 1. RM0433 Rev 7 Section 30.7.1
+        let (ckoutdiv, clock_out) = match clock_out {
+            Some(freq) => {
+                let div = (ker_ck.0 + freq.0 - 1) / freq.0;
+                assert!(
+                    div >= 2 && div <= 256,
+                    "DFSDM clock output cannot be generated from ker_ck"
+                );
+                ((div - 1) as u8, Some(Hertz(ker_ck.0 / div)))
+            }
+            None => (0, None),
+        };
+
+        // Clock output from dfsdm_ker_ck. Clock output must be configured
+        // while the DFSDM is disabled
+        dfsdm.ch0cfgr1.modify(|_, w| unsafe {
+            w.ckoutsrc().clear_bit().ckoutdiv().bits(ckoutdiv)
+        });
+
+        // Global enable
+        dfsdm.ch0cfgr1.modify(|_, w| w.dfsdmen().set_bit());
+
+        Dfsdm {
+            rb: dfsdm,
+            ker_ck,
+            clock_out,
+        }
+    }
+
+    /// Returns the DFSDM kernel clock frequency
+    pub fn kernel_clock(&self) -> Hertz {
+        self.ker_ck
+    }
+
+    /// Returns the actual frequency of the CKOUT clock, if enabled
+    pub fn clock_out(&self) -> Option<Hertz> {
+        self.clock_out
+    }
+
+    /// Split the DFSDM into independent channels and filters
+    pub fn split(self) -> Parts {
+        Parts {
+            ch0: Channel0 {
+                _dfsdm: PhantomData,
+            },
+            ch1: Channel1 {
+                _dfsdm: PhantomData,
+            },
+            ch2: Channel2 {
+                _dfsdm: PhantomData,
+            },
+            ch3: Channel3 {
+                _dfsdm: PhantomData,
+            },
+            ch4: Channel4 {
+                _dfsdm: PhantomData,
+            },
+            ch5: Channel5 {
+                _dfsdm: PhantomData,
+            },
+            ch6: Channel6 {
+                _dfsdm: PhantomData,
+            },
+            ch7: Channel7 {
+                _dfsdm: PhantomData,
+            },
+            flt0: Filter0 {
+                _dfsdm: PhantomData,
+            },
+            flt1: Filter1 {
+                _dfsdm: PhantomData,
+            },
+            flt2: Filter2 {
+                _dfsdm: PhantomData,
+            },
+            flt3: Filter3 {
+                _dfsdm: PhantomData,
+            },
+        }
+    }
+
+    /// Disables the DFSDM and releases the peripheral
+    pub fn free(self) -> (DFSDM, rec::Dfsdm1) {
+        self.rb.ch0cfgr1.modify(|_, w| w.dfsdmen().clear_bit());
+
+        (
+            self.rb,
+            rec::Dfsdm1 {
+                _marker: PhantomData,
+            },
+        )
+    }
+}
+
+/// Channels and filters of the DFSDM
+pub struct Parts {
+    pub ch0: Channel0<DFSDM>,
+    pub ch1: Channel1<DFSDM>,
+    pub ch2: Channel2<DFSDM>,
+    pub ch3: Channel3<DFSDM>,
+    pub ch4: Channel4<DFSDM>,
+    pub ch5: Channel5<DFSDM>,
+    pub ch6: Channel6<DFSDM>,
+    pub ch7: Channel7<DFSDM>,
+    pub flt0: Filter0<DFSDM>,
+    pub flt1: Filter1<DFSDM>,
+    pub flt2: Filter2<DFSDM>,
+    pub flt3: Filter3<DFSDM>,
+}
+
+macro_rules! channels {
+    ($($CHANNELX:ident: ($number:expr, $cfgr1:ident, $cfgr2:ident),)+) => {
+        $(
+            /// DFSDM input channel
+            pub struct $CHANNELX<DFSDM> {
+                _dfsdm: PhantomData<DFSDM>,
+            }
+            unsafe impl<DFSDM> Send for $CHANNELX<DFSDM> {}
+
+            impl Channel for $CHANNELX<DFSDM> {
+                const NUMBER: u8 = $number;
+            }
+
+            impl $CHANNELX<DFSDM> {
+                /// Configure the channel. The channel is disabled while it
+                /// is being configured, and must be enabled afterwards
+                pub fn configure(&mut self, config: ChannelConfig) {
+                    // unsafe: Owned exclusive access to this channel
+                    let rb = unsafe { &*DFSDM::ptr() };
+
+                    rb.$cfgr1.modify(|_, w| w.chen().clear_bit());
+
+                    rb.$cfgr1.modify(|_, w| unsafe {
+                        w.sitp()
+                            .bits(config.interface as u8)
+                            .spicksel()
+                            .bits(config.clock as u8)
+                            .datmpx()
+                            .bits(0) // External serial input
+                            .datpack()
+                            .bits(0) // Standard mode
+                    });
+                    rb.$cfgr2.write(|w| unsafe {
+                        w.offset()
+                            .bits((config.offset as u32) & 0xFF_FFFF)
+                            .dtrbs()
+                            .bits(config.right_shift)
+                    });
+                }
+
+                /// Enable the channel
+                pub fn enable(&mut self) {
+                    // unsafe: Owned exclusive access to this channel
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cfgr1.modify(|_, w| w.chen().set_bit());
+                }
+
+                /// Disable the channel
+                pub fn disable(&mut self) {
+                    // unsafe: Owned exclusive access to this channel
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cfgr1.modify(|_, w| w.chen().clear_bit());
+                }
+            }
+        )+
+    }
+}
+
+channels! {
+    Channel0: (0, ch0cfgr1, ch0cfgr2),
+    Channel1: (1, ch1cfgr1, ch1cfgr2),
+    Channel2: (2, ch2cfgr1, ch2cfgr2),
+    Channel3: (3, ch3cfgr1, ch3cfgr2),
+    Channel4: (4, ch4cfgr1, ch4cfgr2),
+    Channel5: (5, ch5cfgr1, ch5cfgr2),
+    Channel6: (6, ch6cfgr1, ch6cfgr2),
+    Channel7: (7, ch7cfgr1, ch7cfgr2),
+}
+
+/// Convert a data register value to a signed 24-bit sample and channel
+/// number
+#[inline(always)]
+fn sample(bits: u32) -> (i32, u8) {
+    ((bits as i32) >> 8, (bits & 0b111) as u8)
+}
+
+macro_rules! filters {
+    ($($FILTERX:ident: ($cr1:ident, $cr2:ident, $isr:ident, $icr:ident,
+                        $jchgr:ident, $fcr:ident, $jdatar:ident,
+                        $rdatar:ident, $dmareq:ident),)+) => {
+        $(
+            /// DFSDM digital filter
+            pub struct $FILTERX<DFSDM> {
+                _dfsdm: PhantomData<DFSDM>,
+            }
+            unsafe impl<DFSDM> Send for $FILTERX<DFSDM> {}
+
+            impl $FILTERX<DFSDM> {
+                /// Configure the sinc filter and integrator. The filter is
+                /// disabled while it is being configured, and must be enabled
+                /// afterwards
+                pub fn configure(&mut self, config: FilterConfig) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+
+                    rb.$cr1.modify(|_, w| w.dfen().clear_bit());
+
+                    rb.$fcr.write(|w| unsafe {
+                        w.ford()
+                            .bits(config.order as u8)
+                            .fosr()
+                            .bits(config.oversampling - 1)
+                            .iosr()
+                            .bits((config.integrator - 1) as u8)
+                    });
+                    rb.$cr1.modify(|_, w| w.fast().bit(config.fast));
+                }
+
+                /// Enable the filter
+                pub fn enable(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.dfen().set_bit());
+                }
+
+                /// Disable the filter. Any conversions in progress are
+                /// stopped
+                pub fn disable(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.dfen().clear_bit());
+                }
+
+                /// Select the `channel` for regular conversions. If
+                /// `continuous` is set, conversions restart automatically
+                /// once the first has been started
+                pub fn set_regular_channel<CH: Channel>(
+                    &mut self,
+                    _channel: &CH,
+                    continuous: bool,
+                ) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| unsafe {
+                        w.rch().bits(CH::NUMBER).rcont().bit(continuous)
+                    });
+                }
+
+                /// Start a regular conversion by software
+                pub fn start_regular(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.rswstart().set_bit());
+                }
+
+                /// Stop continuous regular conversions after the current
+                /// conversion has completed
+                pub fn stop_regular(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.rcont().clear_bit());
+                }
+
+                /// Read the result of a regular conversion, returning the
+                /// sample and the number of the channel that was converted
+                pub fn read_regular(&mut self) -> nb::Result<(i32, u8), Error> {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    let isr = rb.$isr.read();
+
+                    if isr.rovrf().bit_is_set() {
+                        rb.$icr.write(|w| w.clrrovrf().set_bit());
+                        Err(nb::Error::Other(Error::Overrun))
+                    } else if isr.reocf().bit_is_set() {
+                        // Reading RDATAR clears REOCF
+                        Ok(sample(rb.$rdatar.read().bits()))
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                /// Select the group of channels for injected conversions.
+                /// Bit y of `channels` selects channel y. If `scan` is set,
+                /// all channels in the group are converted on each trigger,
+                /// otherwise one channel is converted per trigger
+                pub fn set_injected_group(&mut self, channels: u8, scan: bool) {
+                    assert!(channels != 0);
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+
+                    rb.$jchgr.write(|w| unsafe { w.jchg().bits(channels) });
+                    rb.$cr1.modify(|_, w| w.jscan().bit(scan));
+                }
+
+                /// Start an injected conversion by software
+                pub fn start_injected(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.jswstart().set_bit());
+                }
+
+                /// Read the result of an injected conversion, returning the
+                /// sample and the number of the channel that was converted
+                pub fn read_injected(&mut self) -> nb::Result<(i32, u8), Error> {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    let isr = rb.$isr.read();
+
+                    if isr.jovrf().bit_is_set() {
+                        rb.$icr.write(|w| w.clrjovrf().set_bit());
+                        Err(nb::Error::Other(Error::Overrun))
+                    } else if isr.jeocf().bit_is_set() {
+                        // Reading JDATAR clears JEOCF
+                        Ok(sample(rb.$jdatar.read().bits()))
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                /// Enable DMA requests for regular conversions
+                pub fn enable_regular_dma(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.rdmaen().set_bit());
+                }
+
+                /// Disable DMA requests for regular conversions
+                pub fn disable_regular_dma(&mut self) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    rb.$cr1.modify(|_, w| w.rdmaen().clear_bit());
+                }
+
+                /// Start listening for `event`
+                pub fn listen(&mut self, event: Event) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    match event {
+                        Event::RegularEndOfConversion => {
+                            rb.$cr2.modify(|_, w| w.reocie().set_bit())
+                        }
+                        Event::InjectedEndOfConversion => {
+                            rb.$cr2.modify(|_, w| w.jeocie().set_bit())
+                        }
+                        Event::RegularOverrun => {
+                            rb.$cr2.modify(|_, w| w.rovrie().set_bit())
+                        }
+                        Event::InjectedOverrun => {
+                            rb.$cr2.modify(|_, w| w.jovrie().set_bit())
+                        }
+                    }
+                }
+
+                /// Stop listening for `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    match event {
+                        Event::RegularEndOfConversion => {
+                            rb.$cr2.modify(|_, w| w.reocie().clear_bit())
+                        }
+                        Event::InjectedEndOfConversion => {
+                            rb.$cr2.modify(|_, w| w.jeocie().clear_bit())
+                        }
+                        Event::RegularOverrun => {
+                            rb.$cr2.modify(|_, w| w.rovrie().clear_bit())
+                        }
+                        Event::InjectedOverrun => {
+                            rb.$cr2.modify(|_, w| w.jovrie().clear_bit())
+                        }
+                    }
+                    let _ = rb.$cr2.read();
+                    let _ = rb.$cr2.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns `true` if the flag for `event` is set
+                pub fn is_pending(&self, event: Event) -> bool {
+                    // unsafe: We only read from this register
+                    let isr = unsafe { &*DFSDM::ptr() }.$isr.read();
+                    match event {
+                        Event::RegularEndOfConversion => isr.reocf().bit_is_set(),
+                        Event::InjectedEndOfConversion => isr.jeocf().bit_is_set(),
+                        Event::RegularOverrun => isr.rovrf().bit_is_set(),
+                        Event::InjectedOverrun => isr.jovrf().bit_is_set(),
+                    }
+                }
+
+                /// Clears the overrun flag for `event`. End of conversion
+                /// flags are cleared by reading the data register
+                pub fn clear_irq(&mut self, event: Event) {
+                    // unsafe: Owned exclusive access to this filter
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    match event {
+                        Event::RegularOverrun => {
+                            rb.$icr.write(|w| w.clrrovrf().set_bit())
+                        }
+                        Event::InjectedOverrun => {
+                            rb.$icr.write(|w| w.clrjovrf().set_bit())
+                        }
+                        _ => (), // Cleared by reading data
+                    }
+                    let _ = rb.$isr.read();
+                    let _ = rb.$isr.read(); // Delay 2 peripheral clocks
+                }
+            }
+
+            unsafe impl TargetAddress<PeripheralToMemory> for $FILTERX<DFSDM> {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    // unsafe: We only take the address of this register
+                    let rb = unsafe { &*DFSDM::ptr() };
+                    &rb.$rdatar as *const _ as usize
+                }
+
+                type MemSize = u32;
+
+                const REQUEST_LINE: Option<u8> = Some(DMAReq::$dmareq as u8);
+            }
+        )+
+    }
+}
+
+filters! {
+    Filter0: (flt0cr1, flt0cr2, flt0isr, flt0icr, flt0jchgr, flt0fcr,
+              flt0jdatar, flt0rdatar, DFSDM1_DMA0),
+    Filter1: (flt1cr1, flt1cr2, flt1isr, flt1icr, flt1jchgr, flt1fcr,
+              flt1jdatar, flt1rdatar, DFSDM1_DMA1),
+    Filter2: (flt2cr1, flt2cr2, flt2isr, flt2icr, flt2jchgr, flt2fcr,
+              flt2jdatar, flt2rdatar, DFSDM1_DMA2),
+    Filter3: (flt3cr1, flt3cr2, flt3isr, flt3icr, flt3jchgr, flt3fcr,
+              flt3jdatar, flt3rdatar, DFSDM1_DMA3),
+}
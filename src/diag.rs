@@ -0,0 +1,284 @@
+//! Diagnostics channel
+//!
+//! A [`Diag`] multiplexes log messages, `defmt` frames, register dumps and
+//! a report of the clock tree over any byte stream that implements
+//! `embedded_hal::serial::Write`, such as a [`Serial`](crate::serial) Tx
+//! or a USB CDC-ACM port. This gives field units without a debug probe a
+//! single diagnostics channel.
+//!
+//! # Example
+//!
+//! ```
+//! let (tx, _rx) = serial.split();
+//! let mut diag = Diag::new(tx);
+//!
+//! diag.log(format_args!("booted, reset cause {:?}", reset_reason)).unwrap();
+//! diag.clock_tree(&ccdr.clocks, &ccdr.peripheral).unwrap();
+//!
+//! // NOTE(unsafe) Reading the RCC registers has no side effects
+//! unsafe { diag.registers(0x5802_4400, 40).unwrap() };
+//! ```
+//!
+//! For `defmt`, the global logger passes its encoded bytes to
+//! [`defmt`](Diag::defmt), one frame for each message.
+//!
+//! # Framing
+//!
+//! Each frame is a [`Channel`] byte, the payload and a CRC-8 of both
+//! (polynomial 0x07, initial value 0). The frame is encoded with
+//! Consistent Overhead Byte Stuffing (COBS) and terminated with a zero
+//! byte, so that a receiver can resynchronise at the next zero after a
+//! lost byte.
+//!
+//! | Channel     | Payload                                                 |
+//! |-------------|---------------------------------------------------------|
+//! | `Log`       | UTF-8 text                                              |
+//! | `Defmt`     | `defmt` encoded bytes                                   |
+//! | `Registers` | Address, then the words read from it. Little endian u32 |
+//! | `Clocks`    | UTF-8 text, one `name frequency` pair on each line      |
+
+use core::fmt;
+use core::ptr;
+
+use crate::hal::serial;
+use crate::rcc::rec::PeripheralREC;
+use crate::rcc::CoreClocks;
+use crate::time::Hertz;
+use nb::block;
+
+/// Kind of the payload of a frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Channel {
+    /// Log messages in text
+    Log = 1,
+    /// Frames from a `defmt` global logger
+    Defmt = 2,
+    /// Dump of consecutive registers
+    Registers = 3,
+    /// Report of the clock tree
+    Clocks = 4,
+}
+
+/// Largest number of non-zero bytes in a COBS block
+const COBS_BLOCK: usize = 254;
+
+/// Diagnostics channel over a byte stream
+pub struct Diag<W> {
+    writer: W,
+    block: [u8; COBS_BLOCK],
+    len: usize,
+    crc: u8,
+}
+
+impl<W, E> Diag<W>
+where
+    W: serial::Write<u8, Error = E>,
+{
+    /// Creates a diagnostics channel that writes frames to `writer`
+    pub fn new(writer: W) -> Self {
+        Diag {
+            writer,
+            block: [0; COBS_BLOCK],
+            len: 0,
+            crc: 0,
+        }
+    }
+
+    /// Writes one frame with `payload` on `channel`
+    pub fn frame(&mut self, channel: Channel, payload: &[u8]) -> Result<(), E> {
+        self.start(channel)?;
+        for &byte in payload {
+            self.byte(byte)?;
+        }
+        self.end()
+    }
+
+    /// Writes a log message
+    pub fn log(&mut self, args: fmt::Arguments) -> Result<(), E> {
+        self.start(Channel::Log)?;
+        self.text(args)?;
+        self.end()
+    }
+
+    /// Writes the bytes of one `defmt` frame
+    pub fn defmt(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.frame(Channel::Defmt, bytes)
+    }
+
+    /// Writes a dump of `count` consecutive 32-bit registers starting at
+    /// `address`
+    ///
+    /// # Safety
+    ///
+    /// The registers are read with volatile reads. The addresses must be
+    /// mapped and clocked, and reading them must not have side effects
+    /// that the application depends on, such as clearing status flags or
+    /// popping a FIFO.
+    pub unsafe fn registers(
+        &mut self,
+        address: usize,
+        count: usize,
+    ) -> Result<(), E> {
+        self.start(Channel::Registers)?;
+        self.word(address as u32)?;
+        for i in 0..count {
+            let word = ptr::read_volatile((address as *const u32).add(i));
+            self.word(word)?;
+        }
+        self.end()
+    }
+
+    /// Writes a report of the core clocks, and of the kernel clock
+    /// multiplexers from
+    /// [`kernel_clock_snapshot`](crate::rcc::rec::PeripheralREC::kernel_clock_snapshot)
+    pub fn clock_tree(
+        &mut self,
+        clocks: &CoreClocks,
+        peripheral: &PeripheralREC,
+    ) -> Result<(), E> {
+        let core = [
+            ("sys_ck", clocks.sys_ck()),
+            ("c_ck", clocks.c_ck()),
+            ("hclk", clocks.hclk()),
+            ("pclk1", clocks.pclk1()),
+            ("pclk2", clocks.pclk2()),
+            ("pclk3", clocks.pclk3()),
+            ("pclk4", clocks.pclk4()),
+            ("timx_ker_ck", clocks.timx_ker_ck()),
+            ("timy_ker_ck", clocks.timy_ker_ck()),
+        ];
+        let optional = [
+            ("hse_ck", clocks.hse_ck()),
+            ("pll1_p_ck", clocks.pll1_p_ck()),
+            ("pll1_q_ck", clocks.pll1_q_ck()),
+            ("pll1_r_ck", clocks.pll1_r_ck()),
+            ("pll2_p_ck", clocks.pll2_p_ck()),
+            ("pll2_q_ck", clocks.pll2_q_ck()),
+            ("pll2_r_ck", clocks.pll2_r_ck()),
+            ("pll3_p_ck", clocks.pll3_p_ck()),
+            ("pll3_q_ck", clocks.pll3_q_ck()),
+            ("pll3_r_ck", clocks.pll3_r_ck()),
+            ("per_ck", clocks.per_ck()),
+        ];
+
+        self.start(Channel::Clocks)?;
+        for &(name, freq) in core.iter() {
+            self.text(format_args!("{} {}\n", name, freq.0))?;
+        }
+        for &(name, freq) in optional.iter() {
+            if let Some(Hertz(freq)) = freq {
+                self.text(format_args!("{} {}\n", name, freq))?;
+            }
+        }
+        let snapshot = peripheral.kernel_clock_snapshot(clocks);
+        self.text(format_args!("{:?}\n", snapshot))?;
+        self.end()
+    }
+
+    /// Releases the byte stream
+    pub fn free(self) -> W {
+        self.writer
+    }
+
+    /// Starts a frame on `channel`
+    fn start(&mut self, channel: Channel) -> Result<(), E> {
+        self.len = 0;
+        self.crc = 0;
+        self.byte(channel as u8)
+    }
+
+    /// Ends the frame with its CRC and the delimiter
+    fn end(&mut self) -> Result<(), E> {
+        let crc = self.crc;
+        self.encode(crc)?;
+        self.flush_block()?;
+        block!(self.writer.write(0))?;
+        block!(self.writer.flush())
+    }
+
+    /// Adds a byte of the payload
+    fn byte(&mut self, byte: u8) -> Result<(), E> {
+        self.crc = crc8(self.crc, byte);
+        self.encode(byte)
+    }
+
+    /// Adds a little endian word to the payload
+    fn word(&mut self, word: u32) -> Result<(), E> {
+        for &byte in word.to_le_bytes().iter() {
+            self.byte(byte)?;
+        }
+        Ok(())
+    }
+
+    /// Adds formatted text to the payload
+    fn text(&mut self, args: fmt::Arguments) -> Result<(), E> {
+        let mut text = Text {
+            diag: self,
+            error: None,
+        };
+        let _ = fmt::write(&mut text, args);
+        match text.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// COBS encodes `byte`
+    fn encode(&mut self, byte: u8) -> Result<(), E> {
+        if byte == 0 {
+            return self.flush_block();
+        }
+        self.block[self.len] = byte;
+        self.len += 1;
+        if self.len == COBS_BLOCK {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the current COBS block
+    fn flush_block(&mut self) -> Result<(), E> {
+        let len = self.len;
+        self.len = 0;
+        block!(self.writer.write(len as u8 + 1))?;
+        for i in 0..len {
+            block!(self.writer.write(self.block[i]))?;
+        }
+        Ok(())
+    }
+}
+
+/// Adapter from `fmt::Write` to the payload of a frame
+struct Text<'a, W, E> {
+    diag: &'a mut Diag<W>,
+    error: Option<E>,
+}
+
+impl<'a, W, E> fmt::Write for Text<'a, W, E>
+where
+    W: serial::Write<u8, Error = E>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            if let Err(e) = self.diag.byte(byte) {
+                self.error = Some(e);
+                return Err(fmt::Error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// CRC-8 with polynomial 0x07
+fn crc8(crc: u8, byte: u8) -> u8 {
+    let mut crc = crc ^ byte;
+    for _ in 0..8 {
+        crc = if crc & 0x80 != 0 {
+            (crc << 1) ^ 0x07
+        } else {
+            crc << 1
+        };
+    }
+    crc
+}
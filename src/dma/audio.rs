@@ -0,0 +1,309 @@
+//! Streaming of audio frames over a circular DMA transfer
+//!
+//! An [`AudioStream`] runs a DMA transfer in circular mode over a single
+//! buffer, and splits the buffer into two frames. While the DMA transfers
+//! one frame, the other frame is handed to the application to be processed.
+//! This works for any peripheral that can be the target of a DMA transfer,
+//! such as the SAI and the DFSDM.
+//!
+//! Each frame must be processed before the DMA returns to it, which is the
+//! time taken to transfer the other frame. A missed deadline is reported
+//! as [`Error::Overrun`].
+//!
+//! # Example
+//!
+//! ```
+//! // A buffer of two frames of 64 stereo samples
+//! #[link_section = ".axisram"]
+//! static mut BUFFER: [u32; 256] = [0; 256];
+//!
+//! let config = DmaConfig::default().memory_increment(true);
+//! let transfer: DmaTransfer<_, _, PeripheralToMemory, _, _> =
+//!     Transfer::init(streams.0, sai1, unsafe { &mut BUFFER }, None, config);
+//!
+//! let mut stream = AudioStream::new(transfer);
+//! stream.start(|sai1| sai1.enable());
+//!
+//! loop {
+//!     match stream.process(|frame| {
+//!         // `frame` contains 128 samples received from the SAI
+//!     }) {
+//!         Ok(()) => {}
+//!         Err(nb::Error::WouldBlock) => {}
+//!         Err(nb::Error::Other(Error::Overrun)) => panic!("Missed a frame"),
+//!     }
+//! }
+//! ```
+//!
+//! # Pausing
+//!
+//! [`pause`](AudioStream::pause) stops the peripheral from the closure
+//! first, and then disables the DMA stream, so that the peripheral does not
+//! see an underrun or overrun. [`resume`](AudioStream::resume) restarts the
+//! DMA at the first frame, and then starts the peripheral. This throttles
+//! or stops a pipeline without tearing down the transfer.
+//!
+//! ```
+//! stream.pause(|sai1| sai1.disable());
+//! // ...
+//! stream.resume(|sai1| sai1.enable());
+//! ```
+//!
+//! The interrupts of [`listen`](AudioStream::listen) are the half transfer
+//! (first frame released) and transfer complete (second frame released)
+//! interrupts of the stream. Both call for [`process`](AudioStream::process).
+//!
+//! # Caches
+//!
+//! The buffer should be placed in memory that is not cached by the data
+//! cache, or the cache must be cleaned (memory to peripheral) or
+//! invalidated (peripheral to memory) for each frame.
+
+use core::slice;
+use core::sync::atomic::{fence, Ordering};
+
+use embedded_dma::StaticWriteBuffer;
+
+use super::traits::{
+    DoubleBufferedConfig, DoubleBufferedStream, Stream, TargetAddress,
+};
+use super::{DBTransfer, Direction, Transfer};
+
+/// Audio stream error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The DMA reached a frame before it was processed. Samples were lost
+    /// (peripheral to memory) or repeated (memory to peripheral)
+    Overrun,
+}
+
+/// One of the two frames of the buffer
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Frame {
+    /// The first half of the buffer
+    First,
+    /// The second half of the buffer
+    Second,
+}
+
+/// A stream of fixed-size frames over a circular DMA transfer
+pub struct AudioStream<STREAM, PERIPHERAL, DIR, BUF>
+where
+    STREAM: Stream,
+    PERIPHERAL: TargetAddress<DIR>,
+    DIR: Direction,
+{
+    transfer: Transfer<STREAM, PERIPHERAL, DIR, BUF, DBTransfer>,
+    /// The frame that will be released next by the DMA
+    next: Frame,
+}
+
+impl<STREAM, CONFIG, PERIPHERAL, DIR, BUF>
+    AudioStream<STREAM, PERIPHERAL, DIR, BUF>
+where
+    STREAM: DoubleBufferedStream + Stream<Config = CONFIG>,
+    CONFIG: DoubleBufferedConfig,
+    DIR: Direction,
+    PERIPHERAL: TargetAddress<DIR>,
+    BUF: StaticWriteBuffer<Word = <PERIPHERAL as TargetAddress<DIR>>::MemSize>,
+{
+    /// Creates an audio stream from a transfer that has not been started.
+    /// The transfer is switched to circular mode, and each frame is half of
+    /// its buffer
+    ///
+    /// # Panics
+    ///
+    /// * When the transfer is double buffered or has already been started
+    /// * When the length of the buffer is not even
+    pub fn new(
+        mut transfer: Transfer<STREAM, PERIPHERAL, DIR, BUF, DBTransfer>,
+    ) -> Self {
+        assert!(
+            transfer.buf[1].is_none(),
+            "Use a single buffer, that is split into frames"
+        );
+        assert!(!STREAM::is_enabled(), "Transfer already started");
+        assert!(
+            transfer.transfer_length % 2 == 0,
+            "Buffer length must be even"
+        );
+
+        transfer.stream.set_double_buffer(false);
+        transfer.stream.set_circular_buffer(true);
+        transfer.stream.clear_interrupts();
+
+        AudioStream {
+            transfer,
+            next: Frame::First,
+        }
+    }
+
+    /// Starts the stream, the closure will be executed right after enabling
+    /// the DMA stream. Usually the closure enables the peripheral
+    pub fn start<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        self.next = Frame::First;
+        self.transfer.start(f);
+    }
+
+    /// Returns the number of words in each frame
+    pub fn frame_length(&self) -> usize {
+        usize::from(self.transfer.transfer_length / 2)
+    }
+
+    /// Returns `true` if the DMA has released the frame after `next`
+    fn other_flag(next: Frame) -> bool {
+        match next {
+            // The DMA completed the second half, and is in the first
+            Frame::First => STREAM::get_transfer_complete_flag(),
+            // The DMA completed the first half, and is in the second
+            Frame::Second => STREAM::get_half_transfer_flag(),
+        }
+    }
+
+    /// Returns the frame released by the DMA if there is one, and clears
+    /// its flag
+    fn take_frame(&mut self) -> nb::Result<Frame, Error> {
+        let frame = self.next;
+        let released = match frame {
+            Frame::First => STREAM::get_half_transfer_flag(),
+            Frame::Second => STREAM::get_transfer_complete_flag(),
+        };
+        if !released {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Both flags are set if the DMA has already moved on to the frame
+        // after this one
+        if Self::other_flag(frame) {
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+        match frame {
+            Frame::First => self.transfer.clear_half_transfer_interrupt(),
+            Frame::Second => self.transfer.clear_transfer_complete_interrupt(),
+        }
+        self.next = match frame {
+            Frame::First => Frame::Second,
+            Frame::Second => Frame::First,
+        };
+        Ok(frame)
+    }
+
+    /// Calls `f` with the next frame released by the DMA. For a peripheral
+    /// to memory transfer the frame contains received samples, for a
+    /// memory to peripheral transfer `f` fills the frame with samples to be
+    /// transmitted.
+    ///
+    /// Returns `WouldBlock` if the DMA has not released a frame. An
+    /// `Overrun` error is returned if the DMA had already reached the frame
+    /// again before it was taken, in which case `f` is not called, or
+    /// before `f` returned. The stream continues after an overrun, and the
+    /// next call processes the frame that the DMA released next.
+    pub fn process<F>(&mut self, f: F) -> nb::Result<(), Error>
+    where
+        F: FnOnce(&mut [<PERIPHERAL as TargetAddress<DIR>>::MemSize]),
+    {
+        let frame = match self.take_frame() {
+            Err(nb::Error::Other(Error::Overrun)) => {
+                // Resynchronise to the frame that the DMA releases next
+                self.transfer.clear_half_transfer_interrupt();
+                self.transfer.clear_transfer_complete_interrupt();
+                let remaining = usize::from(STREAM::get_number_of_transfers());
+                self.next = if remaining > self.frame_length() {
+                    // The DMA is in the first half, and releases it next
+                    Frame::First
+                } else {
+                    Frame::Second
+                };
+                return Err(nb::Error::Other(Error::Overrun));
+            }
+            result => result?,
+        };
+
+        let length = self.frame_length();
+        // NOTE(unsafe) The transfer owns a static buffer, and the DMA does
+        // not access this frame until it has transferred the other frame
+        let frame_slice = unsafe {
+            // NOTE(panic) The transfer always holds the buffer
+            let (ptr, _) =
+                self.transfer.buf[0].as_mut().unwrap().write_buffer();
+            let offset = match frame {
+                Frame::First => 0,
+                Frame::Second => length,
+            };
+            slice::from_raw_parts_mut(ptr.add(offset), length)
+        };
+
+        // Order the DMA flag reads before the frame accesses
+        fence(Ordering::SeqCst);
+        f(frame_slice);
+        fence(Ordering::SeqCst);
+
+        // The DMA must not have released the other frame while this one was
+        // processed, otherwise it is now accessing this frame
+        if Self::other_flag(frame) {
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+        Ok(())
+    }
+
+    /// Enables the half transfer and transfer complete interrupts, which
+    /// are signalled when a frame is released
+    pub fn listen(&mut self) {
+        self.transfer
+            .stream
+            .set_half_transfer_interrupt_enable(true);
+        self.transfer
+            .stream
+            .set_transfer_complete_interrupt_enable(true);
+    }
+
+    /// Disables the half transfer and transfer complete interrupts
+    pub fn unlisten(&mut self) {
+        self.transfer
+            .stream
+            .set_half_transfer_interrupt_enable(false);
+        self.transfer
+            .stream
+            .set_transfer_complete_interrupt_enable(false);
+    }
+
+    /// Pauses the stream, the closure will be executed right before
+    /// disabling the DMA stream. Usually the closure stops the DMA requests
+    /// of the peripheral
+    pub fn pause<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        self.transfer.pause(f);
+    }
+
+    /// Resumes a paused stream at the first frame, the closure will be
+    /// executed right after enabling the DMA stream. The frame that the DMA
+    /// was in when it was paused is discarded
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream is not paused
+    pub fn resume<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        self.next = Frame::First;
+        self.transfer.resume(f);
+    }
+
+    /// Returns `true` if the DMA stream is enabled, `false` before the
+    /// stream is started and while it is paused
+    pub fn is_running(&self) -> bool {
+        STREAM::is_enabled()
+    }
+
+    /// Releases the underlying transfer, which continues to run
+    pub fn free(self) -> Transfer<STREAM, PERIPHERAL, DIR, BUF, DBTransfer> {
+        self.transfer
+    }
+}
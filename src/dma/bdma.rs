@@ -1,21 +1,44 @@
 //! BDMA
 //!
 //! For RM0455 parts, only BDMA2 is implemented
+//!
+//! The BDMA is located in the D3 domain (SRD domain on RM0455 parts), and can
+//! only access memory in SRAM4 (SRD SRAM on RM0455 parts). Its requests are
+//! routed through DMAMUX2, which only connects to peripherals in the same
+//! domain.
+//!
+//! [`Transfer::init_bdma`] and [`Transfer::init_bdma_const`] only accept
+//! these peripherals, and a buffer that has been checked to be in SRAM4 by
+//! wrapping it in a [`Sram4Buffer`]. Prefer these over the generic
+//! constructors, which cannot check either.
+//!
+//! ```
+//! #[link_section = ".sram4.buffers"]
+//! static mut BUFFER: MaybeUninit<[u8; 10]> = MaybeUninit::uninit();
+//!
+//! let buffer = Sram4Buffer::new(unsafe { &mut BUFFER });
+//! let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+//!     Transfer::init_bdma(streams.0, i2c, buffer, None, config);
+//! ```
 
 use super::{
     config,
     traits::sealed::{Bits, Sealed},
     traits::*,
-    CurrentBuffer, DmaDirection, MemoryToPeripheral, PeripheralToMemory,
+    ConstDBTransfer, CurrentBuffer, DBTransfer, DMAError, DmaDirection,
+    MemoryToPeripheral, PeripheralToMemory, Transfer,
 };
 use core::marker::PhantomData;
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
 
+#[cfg(feature = "i2c")]
+use crate::i2c::I2c;
+#[cfg(feature = "spi")]
+use crate::spi;
 use crate::{
-    i2c::I2c,
     pac,
     rcc::{rec, rec::ResetEnable},
     //serial::{Rx, Tx},
-    spi,
 };
 
 #[cfg(not(feature = "rm0455"))]
@@ -242,6 +265,18 @@ impl<I: Instance> StreamsTuple<I> {
     }
 }
 
+/// Extension trait to split the BDMA peripherals into streams
+pub trait BdmaExt: Instance + Sized {
+    /// Splits the peripheral into streams
+    fn streams(self, prec: Self::Rec) -> StreamsTuple<Self>;
+}
+
+impl<I: Instance> BdmaExt for I {
+    fn streams(self, prec: I::Rec) -> StreamsTuple<I> {
+        StreamsTuple::new(self, prec)
+    }
+}
+
 // Macro that creates a struct representing a stream on either BDMA controller
 //
 // The implementation does the heavy lifting of mapping to the right fields on
@@ -356,6 +391,30 @@ macro_rules! bdma_stream {
                     }
                 }
 
+                #[inline(always)]
+                fn get_sync_overrun_flag() -> bool {
+                    //NOTE(unsafe) Atomic read with no side effects
+                    let dmamux = unsafe { &*I::mux_ptr() };
+                    let channel = Self::NUMBER + I::DMA_MUX_STREAM_OFFSET;
+                    dmamux.csr.read().bits() & (1 << channel) != 0
+                }
+
+                #[inline(always)]
+                fn clear_sync_overrun_flag(&mut self) {
+                    //NOTE(unsafe) We only clear the flag that belongs to the StreamX
+                    let dmamux = unsafe { &*I::mux_ptr() };
+                    let channel = Self::NUMBER + I::DMA_MUX_STREAM_OFFSET;
+                    dmamux.cfr.write(|w| unsafe { w.bits(1 << channel) });
+                }
+
+                #[inline(always)]
+                fn set_sync_overrun_interrupt_enable(&mut self, enable: bool) {
+                    //NOTE(unsafe) We only access the registers that belongs to the StreamX
+                    let dmamux = unsafe { &*I::mux_ptr() };
+                    dmamux.ccr[Self::NUMBER + I::DMA_MUX_STREAM_OFFSET]
+                        .modify(|_, w| w.soie().bit(enable));
+                }
+
                 #[inline(always)]
                 fn set_priority(&mut self, priority: config::Priority) {
                     //NOTE(unsafe) We only access the registers that belongs to the StreamX
@@ -647,6 +706,179 @@ bdma_stream!(
     ),
 );
 
+/// Start address of SRAM4
+const SRAM4_START: usize = 0x3800_0000;
+/// Size of SRAM4
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+const SRAM4_SIZE: usize = 64 * 1024;
+/// Size of SRD SRAM
+#[cfg(feature = "rm0455")]
+const SRAM4_SIZE: usize = 32 * 1024;
+/// Size of SRAM4
+#[cfg(feature = "rm0468")]
+const SRAM4_SIZE: usize = 16 * 1024;
+
+fn in_sram4(ptr: usize, len: usize) -> bool {
+    ptr >= SRAM4_START
+        && len <= SRAM4_SIZE
+        && ptr - SRAM4_START <= SRAM4_SIZE - len
+}
+
+/// A buffer located in SRAM4, the only memory accessible by the BDMA
+pub struct Sram4Buffer<BUF>(BUF);
+
+impl<BUF> Sram4Buffer<BUF>
+where
+    BUF: StaticWriteBuffer,
+{
+    /// Wraps a buffer that will be written by the BDMA
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is not entirely within SRAM4
+    pub fn new(mut buf: BUF) -> Self {
+        // NOTE(unsafe) Only the address and length are used
+        let (ptr, len) = unsafe { buf.write_buffer() };
+        let len = len * core::mem::size_of::<BUF::Word>();
+        assert!(in_sram4(ptr as usize, len), "Buffer is not in SRAM4");
+
+        Sram4Buffer(buf)
+    }
+}
+
+impl<BUF> Sram4Buffer<BUF>
+where
+    BUF: StaticReadBuffer,
+{
+    /// Wraps a buffer that will only be read by the BDMA
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is not entirely within SRAM4
+    pub fn new_const(buf: BUF) -> Self {
+        // NOTE(unsafe) Only the address and length are used
+        let (ptr, len) = unsafe { buf.read_buffer() };
+        let len = len * core::mem::size_of::<BUF::Word>();
+        assert!(in_sram4(ptr as usize, len), "Buffer is not in SRAM4");
+
+        Sram4Buffer(buf)
+    }
+}
+
+impl<BUF> Sram4Buffer<BUF> {
+    /// Releases the underlying buffer
+    pub fn free(self) -> BUF {
+        self.0
+    }
+}
+
+unsafe impl<BUF> StaticWriteBuffer for Sram4Buffer<BUF>
+where
+    BUF: StaticWriteBuffer,
+{
+    type Word = BUF::Word;
+
+    unsafe fn write_buffer(&mut self) -> (*mut Self::Word, usize) {
+        self.0.write_buffer()
+    }
+}
+
+unsafe impl<BUF> StaticReadBuffer for Sram4Buffer<BUF>
+where
+    BUF: StaticReadBuffer,
+{
+    type Word = BUF::Word;
+
+    unsafe fn read_buffer(&self) -> (*const Self::Word, usize) {
+        self.0.read_buffer()
+    }
+}
+
+/// Peripherals with DMA requests routed through DMAMUX2, and so can be used
+/// with the BDMA
+pub trait D3Peripheral: Sealed {}
+
+impl Sealed for pac::SPI6 {}
+impl D3Peripheral for pac::SPI6 {}
+#[cfg(feature = "spi")]
+impl<WORD> Sealed for spi::Spi<pac::SPI6, spi::Disabled, WORD> {}
+#[cfg(feature = "spi")]
+impl<WORD> D3Peripheral for spi::Spi<pac::SPI6, spi::Disabled, WORD> {}
+impl Sealed for pac::I2C4 {}
+impl D3Peripheral for pac::I2C4 {}
+#[cfg(feature = "i2c")]
+impl Sealed for I2c<pac::I2C4> {}
+#[cfg(feature = "i2c")]
+impl D3Peripheral for I2c<pac::I2C4> {}
+impl Sealed for pac::LPUART1 {}
+impl D3Peripheral for pac::LPUART1 {}
+#[cfg(not(feature = "rm0455"))]
+impl Sealed for pac::SAI4 {}
+#[cfg(not(feature = "rm0455"))]
+impl D3Peripheral for pac::SAI4 {}
+
+impl<STREAM, PERIPHERAL, DIR, BUF>
+    Transfer<STREAM, PERIPHERAL, DIR, Sram4Buffer<BUF>, DBTransfer>
+where
+    STREAM: DoubleBufferedStream + Stream<Config = BdmaConfig>,
+    DIR: Direction,
+    PERIPHERAL: TargetAddress<DIR> + D3Peripheral,
+    BUF: StaticWriteBuffer<Word = <PERIPHERAL as TargetAddress<DIR>>::MemSize>,
+{
+    /// Configures a BDMA transfer between a D3 domain peripheral and a
+    /// buffer in SRAM4. See [`Transfer::init`] for details
+    pub fn init_bdma(
+        stream: STREAM,
+        peripheral: PERIPHERAL,
+        memory: Sram4Buffer<BUF>,
+        double_buf: Option<Sram4Buffer<BUF>>,
+        config: BdmaConfig,
+    ) -> Self {
+        Self::init(stream, peripheral, memory, double_buf, config)
+    }
+}
+
+impl<STREAM, PERIPHERAL, DIR, BUF>
+    Transfer<STREAM, PERIPHERAL, DIR, Sram4Buffer<BUF>, ConstDBTransfer>
+where
+    STREAM: DoubleBufferedStream + Stream<Config = BdmaConfig>,
+    DIR: Direction,
+    PERIPHERAL: TargetAddress<DIR> + D3Peripheral,
+    BUF: StaticReadBuffer<Word = <PERIPHERAL as TargetAddress<DIR>>::MemSize>,
+{
+    /// Configures a BDMA transfer from a constant buffer in SRAM4 to a D3
+    /// domain peripheral. See [`Transfer::init_const`] for details
+    pub fn init_bdma_const(
+        stream: STREAM,
+        peripheral: PERIPHERAL,
+        memory: Sram4Buffer<BUF>,
+        double_buf: Option<Sram4Buffer<BUF>>,
+        config: BdmaConfig,
+    ) -> Self {
+        Self::init_const(stream, peripheral, memory, double_buf, config)
+    }
+}
+
+/// Checks DMAMUX2 request generator `generator` (0 to 7) for an overrun. The
+/// flag is cleared if set.
+///
+/// Request generators are configured directly in the `DMAMUX2` registers.
+/// An overrun means that trigger events were lost.
+pub fn check_request_generator_overrun(
+    generator: usize,
+) -> Result<(), DMAError> {
+    assert!(generator < 8, "There are 8 request generators");
+
+    // NOTE(unsafe) Atomic read with no side effects, and write-1-to-clear
+    // that only affects this generator
+    let dmamux = unsafe { &*DMAMUX2::ptr() };
+    if dmamux.rgsr.read().bits() & (1 << generator) == 0 {
+        return Ok(());
+    }
+    dmamux.rgcfr.write(|w| unsafe { w.bits(1 << generator) });
+    Err(DMAError::RequestGeneratorOverrun)
+}
+
 /// Type alias for the DMA Request Multiplexer
 ///
 pub type DMAReq = pac::dmamux2::ccr::DMAREQ_ID_A;
@@ -667,6 +899,9 @@ peripheral_target_address!(
     ),
     (pac::I2C4, rxdr, u8, P2M, DMAReq::I2C4_RX_DMA),
     (pac::I2C4, txdr, u8, M2P, DMAReq::I2C4_TX_DMA),
+);
+#[cfg(feature = "i2c")]
+peripheral_target_address!(
     (INNER: I2c<pac::I2C4>, rxdr, u8, P2M, DMAReq::I2C4_RX_DMA),
     (INNER: I2c<pac::I2C4>, txdr, u8, M2P, DMAReq::I2C4_TX_DMA),
 );
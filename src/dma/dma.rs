@@ -4,15 +4,18 @@ use super::{
     config,
     traits::sealed::{Bits, Sealed},
     traits::*,
-    CurrentBuffer, DmaDirection, FifoLevel, MemoryToPeripheral,
+    CurrentBuffer, DMAError, DmaDirection, FifoLevel, MemoryToPeripheral,
     PeripheralToMemory,
 };
 use core::marker::PhantomData;
 
+#[cfg(feature = "serial")]
+use crate::serial;
+#[cfg(feature = "spi")]
+use crate::spi;
 use crate::{
     pac::{self, DMA1, DMA2, DMAMUX1},
     rcc::{rec, rec::ResetEnable},
-    serial, spi,
 };
 
 use core::ops::Deref;
@@ -309,6 +312,18 @@ impl<I: Instance> StreamsTuple<I> {
     }
 }
 
+/// Extension trait to split the DMA1 and DMA2 peripherals into streams
+pub trait DmaExt: Instance + Sized {
+    /// Splits the peripheral into streams
+    fn streams(self, prec: Self::Rec) -> StreamsTuple<Self>;
+}
+
+impl<I: Instance> DmaExt for I {
+    fn streams(self, prec: I::Rec) -> StreamsTuple<I> {
+        StreamsTuple::new(self, prec)
+    }
+}
+
 // Macro that creates a struct representing a stream on either DMA controller
 //
 // The implementation does the heavy lifting of mapping to the right fields on
@@ -437,6 +452,30 @@ macro_rules! dma_stream {
                     }
                 }
 
+                #[inline(always)]
+                fn get_sync_overrun_flag() -> bool {
+                    //NOTE(unsafe) Atomic read with no side effects
+                    let dmamux = unsafe { &*I::mux_ptr() };
+                    let channel = Self::NUMBER + I::DMA_MUX_STREAM_OFFSET;
+                    dmamux.csr.read().bits() & (1 << channel) != 0
+                }
+
+                #[inline(always)]
+                fn clear_sync_overrun_flag(&mut self) {
+                    //NOTE(unsafe) We only clear the flag that belongs to the StreamX
+                    let dmamux = unsafe { &*I::mux_ptr() };
+                    let channel = Self::NUMBER + I::DMA_MUX_STREAM_OFFSET;
+                    dmamux.cfr.write(|w| unsafe { w.bits(1 << channel) });
+                }
+
+                #[inline(always)]
+                fn set_sync_overrun_interrupt_enable(&mut self, enable: bool) {
+                    //NOTE(unsafe) We only access the registers that belongs to the StreamX
+                    let dmamux = unsafe { &*I::mux_ptr() };
+                    dmamux.ccr[Self::NUMBER + I::DMA_MUX_STREAM_OFFSET]
+                        .modify(|_, w| w.soie().bit(enable));
+                }
+
                 #[inline(always)]
                 fn set_priority(&mut self, priority: config::Priority) {
                     //NOTE(unsafe) We only access the registers that belongs to the StreamX
@@ -803,6 +842,26 @@ macro_rules! peripheral_register_markers {
 
 peripheral_register_markers!(CCR1, CCR2, CCR3, CCR4, DMAR, ARR);
 
+/// Checks DMAMUX1 request generator `generator` (0 to 7) for an overrun. The
+/// flag is cleared if set.
+///
+/// Request generators are configured directly in the `DMAMUX1` registers.
+/// An overrun means that trigger events were lost.
+pub fn check_request_generator_overrun(
+    generator: usize,
+) -> Result<(), DMAError> {
+    assert!(generator < 8, "There are 8 request generators");
+
+    // NOTE(unsafe) Atomic read with no side effects, and write-1-to-clear
+    // that only affects this generator
+    let dmamux = unsafe { &*DMAMUX1::ptr() };
+    if dmamux.rgsr.read().bits() & (1 << generator) == 0 {
+        return Ok(());
+    }
+    dmamux.rgcfr.write(|w| unsafe { w.bits(1 << generator) });
+    Err(DMAError::RequestGeneratorOverrun)
+}
+
 /// Type alias for the DMA Request Multiplexer
 pub type DMAReq = pac::dmamux1::ccr::DMAREQ_ID_A;
 
@@ -910,14 +969,34 @@ peripheral_target_address!(
         DMAReq::UART8_TX_DMA
     ),
 );
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+peripheral_target_address!(
+    (
+        SERIAL: pac::USART10,
+        rdr,
+        tdr,
+        DMAReq::USART10_RX_DMA,
+        DMAReq::USART10_TX_DMA
+    ),
+    (
+        SERIAL: pac::UART9,
+        rdr,
+        tdr,
+        DMAReq::UART9_RX_DMA,
+        DMAReq::UART9_TX_DMA
+    ),
+);
 
 peripheral_target_address!(
     (pac::SAI1, cha.dr, u32, M2P, DMAReq::SAI1A_DMA),
     (pac::SAI1, chb.dr, u32, P2M, DMAReq::SAI1B_DMA),
+);
+#[cfg(not(feature = "rm0468"))]
+peripheral_target_address!(
     (pac::SAI2, cha.dr, u32, M2P, DMAReq::SAI2A_DMA),
     (pac::SAI2, chb.dr, u32, P2M, DMAReq::SAI2B_DMA),
 );
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 peripheral_target_address!(
     (pac::SAI3, cha.dr, u32, M2P, DMAReq::SAI3_A_DMA),
     (pac::SAI3, chb.dr, u32, P2M, DMAReq::SAI3_B_DMA),
@@ -50,8 +50,17 @@ macro_rules! peripheral_target_instance {
             const REQUEST_LINE: Option<u8> = Some($rxmux as u8);
         }
 
+        #[cfg(feature = "spi")]
+        impl<ED, WORD> spi::Spi<$peripheral, ED, WORD> {
+            /// DMAMUX request line for receiving
+            pub const RX_DMA_REQ: u8 = $rxmux as u8;
+            /// DMAMUX request line for transmitting
+            pub const TX_DMA_REQ: u8 = $txmux as u8;
+        }
+
         // For each size
         $(
+        #[cfg(feature = "spi")]
         unsafe impl TargetAddress<M2P> for spi::Spi<$peripheral, spi::Disabled, $size> {
             #[inline(always)]
             fn address(&self) -> usize {
@@ -63,6 +72,7 @@ macro_rules! peripheral_target_instance {
             const REQUEST_LINE: Option<u8> = Some($txmux as u8);
         }
 
+        #[cfg(feature = "spi")]
         unsafe impl TargetAddress<P2M> for spi::Spi<$peripheral, spi::Disabled, $size> {
             #[inline(always)]
             fn address(&self) -> usize {
@@ -101,6 +111,27 @@ macro_rules! peripheral_target_instance {
             const TRBUFF: bool = true;
         }
 
+        #[cfg(feature = "serial")]
+        impl serial::Serial<$peripheral> {
+            /// DMAMUX request line for receiving
+            pub const RX_DMA_REQ: u8 = $rxmux as u8;
+            /// DMAMUX request line for transmitting
+            pub const TX_DMA_REQ: u8 = $txmux as u8;
+        }
+
+        #[cfg(feature = "serial")]
+        impl serial::Rx<$peripheral> {
+            /// DMAMUX request line for receiving
+            pub const DMA_REQ: u8 = $rxmux as u8;
+        }
+
+        #[cfg(feature = "serial")]
+        impl serial::Tx<$peripheral> {
+            /// DMAMUX request line for transmitting
+            pub const DMA_REQ: u8 = $txmux as u8;
+        }
+
+        #[cfg(feature = "serial")]
         unsafe impl TargetAddress<M2P> for serial::Serial<$peripheral> {
             #[inline(always)]
             fn address(&self) -> usize {
@@ -113,6 +144,7 @@ macro_rules! peripheral_target_instance {
             const TRBUFF: bool = true;
         }
 
+        #[cfg(feature = "serial")]
         unsafe impl TargetAddress<P2M> for serial::Serial<$peripheral> {
             #[inline(always)]
             fn address(&self) -> usize {
@@ -125,6 +157,7 @@ macro_rules! peripheral_target_instance {
             const TRBUFF: bool = true;
         }
 
+        #[cfg(feature = "serial")]
         unsafe impl TargetAddress<M2P> for serial::Tx<$peripheral> {
             #[inline(always)]
             fn address(&self) -> usize {
@@ -138,6 +171,7 @@ macro_rules! peripheral_target_instance {
             const TRBUFF: bool = true;
         }
 
+        #[cfg(feature = "serial")]
         unsafe impl TargetAddress<P2M> for serial::Rx<$peripheral> {
             #[inline(always)]
             fn address(&self) -> usize {
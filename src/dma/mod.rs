@@ -8,6 +8,14 @@
 //! Peripheral transfers, double buffering is supported only for Peripheral To
 //! Memory and Memory to Peripheral transfers.
 //!
+//! The DMAMUX request line of a transfer is taken from the peripheral that
+//! is passed to [Transfer::init](struct.Transfer.html#method.init), see
+//! [`TargetAddress::REQUEST_LINE`](traits::TargetAddress::REQUEST_LINE), so
+//! it always matches the peripheral. The request lines of the serial and SPI
+//! drivers are also associated constants, for example
+//! `Serial::<USART3>::RX_DMA_REQ`, on the DMAMUX of the DMA controller that
+//! serves the peripheral.
+//!
 //! Given that the Cortex-M7 core is capable of reordering accesses between
 //! normal and device memory, we insert DMB instructions to ensure correct
 //! operation. See ARM DAI 0321A, Section 3.2 which discusses the use of DMB
@@ -33,6 +41,8 @@ pub mod dma; // DMA1 and DMA2
 
 pub mod bdma;
 
+pub mod audio;
+
 pub mod traits;
 use traits::{
     sealed::Bits, Direction, DoubleBufferedConfig, DoubleBufferedStream,
@@ -46,6 +56,12 @@ pub enum DMAError {
     NotReady,
     /// The user provided a buffer that is not big enough while double buffering.
     SmallBuffer,
+    /// A synchronization event occurred on the DMAMUX channel before the
+    /// previous requests were forwarded to the stream. Requests were lost.
+    SyncOverrun,
+    /// A DMAMUX request generator received a trigger event before the
+    /// previous requests were served. Requests were lost.
+    RequestGeneratorOverrun,
 }
 
 /// Possible DMA's directions.
@@ -288,7 +304,7 @@ where
     _direction: PhantomData<DIR>,
     _transfer_type: PhantomData<TXFRT>,
     buf: [Option<BUF>; 2],
-    // Used when double buffering
+    // Number of items in each buffer
     transfer_length: u16,
 }
 
@@ -545,6 +561,8 @@ macro_rules! db_transfer_def {
                 if single_buffer {
                     // Set length before the writing the new valid address.
                     self.stream.set_number_of_transfers(buf_len as u16);
+                    // Reloaded by `resume`
+                    self.transfer_length = buf_len as u16;
                 } else if buf_len != usize::from(self.transfer_length) {
                     // We can't change the transfer length while double buffering
                     return Err(DMAError::SmallBuffer);
@@ -608,10 +626,28 @@ macro_rules! db_transfer_def {
                 self.stream.clear_half_transfer_interrupt();
             }
 
+            /// Returns `true` if the half transfer flag (htif) is set. In
+            /// circular mode, the flag is set each time the stream has
+            /// transferred the first half of the buffer
             #[inline(always)]
             pub fn get_half_transfer_flag(&self) -> bool {
                 STREAM::get_half_transfer_flag()
             }
+
+            /// Enable/disable the half transfer interrupt (htie) of the DMA
+            /// stream
+            #[inline(always)]
+            pub fn set_half_transfer_interrupt_enable(&mut self, enable: bool) {
+                self.stream.set_half_transfer_interrupt_enable(enable);
+            }
+
+            /// Returns the number of items remaining in the current
+            /// transfer. In circular mode, this counts down to zero and is
+            /// then reloaded
+            #[inline(always)]
+            pub fn get_number_of_transfers(&self) -> u16 {
+                STREAM::get_number_of_transfers()
+            }
         }
     };
 }
@@ -653,6 +689,37 @@ where
         self.stream.disable()
     }
 
+    /// Resumes a paused transfer from the start of its buffer, the closure
+    /// will be executed right after enabling the stream.
+    ///
+    /// A paused stream keeps the number of items that were remaining when
+    /// it was disabled, but restarts at the memory address of the buffer.
+    /// The number of items is reloaded here, so that a circular transfer
+    /// stays aligned to its buffer. Items of the partially completed buffer
+    /// are transferred again.
+    ///
+    /// Double buffer transfers cannot be resumed, as the stream would
+    /// continue in the buffer that was current when it was paused.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream is enabled, or if the transfer is double
+    /// buffered
+    pub fn resume<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        assert!(!STREAM::is_enabled(), "Transfer is not paused");
+        assert!(
+            self.buf[1].is_none(),
+            "Double buffer transfers cannot be resumed"
+        );
+
+        self.stream.clear_interrupts();
+        self.stream.set_number_of_transfers(self.transfer_length);
+        self.start(f);
+    }
+
     /// Stops the stream and returns the underlying resources.
     pub fn free(mut self) -> (STREAM, PERIPHERAL, BUF, Option<BUF>) {
         self.stream.disable();
@@ -691,6 +758,34 @@ where
         self.stream.clear_transfer_error_interrupt();
     }
 
+    /// Checks the DMAMUX channel of the stream for a synchronization
+    /// overrun. The flag is cleared if set.
+    ///
+    /// A synchronization overrun means that DMA requests were lost, so the
+    /// transfer is missing data.
+    pub fn check_mux_errors(&mut self) -> Result<(), DMAError> {
+        if STREAM::get_sync_overrun_flag() {
+            self.stream.clear_sync_overrun_flag();
+            return Err(DMAError::SyncOverrun);
+        }
+        Ok(())
+    }
+
+    /// Enable/disable the transfer complete interrupt (tcie) of the DMA
+    /// stream
+    #[inline(always)]
+    pub fn set_transfer_complete_interrupt_enable(&mut self, enable: bool) {
+        self.stream.set_transfer_complete_interrupt_enable(enable);
+    }
+
+    /// Enable/disable the synchronization overrun interrupt (soie) of the
+    /// DMAMUX channel of the stream. The interrupt is signalled on the
+    /// DMAMUX interrupt line, not the stream's
+    #[inline(always)]
+    pub fn set_sync_overrun_interrupt_enable(&mut self, enable: bool) {
+        self.stream.set_sync_overrun_interrupt_enable(enable);
+    }
+
     /// Get the underlying stream of the transfer.
     ///
     /// # Safety
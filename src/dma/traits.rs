@@ -62,6 +62,18 @@ pub trait Stream: Sealed {
     /// Sets the request or trigger line for this stream
     fn set_request_line(&mut self, request_line: u8);
 
+    /// Get the synchronization overrun flag (sofx) of the stream's DMAMUX
+    /// channel.
+    fn get_sync_overrun_flag() -> bool;
+
+    /// Clear the synchronization overrun flag (csofx) of the stream's DMAMUX
+    /// channel.
+    fn clear_sync_overrun_flag(&mut self);
+
+    /// Enable/disable the synchronization overrun interrupt (soie) of the
+    /// stream's DMAMUX channel.
+    fn set_sync_overrun_interrupt_enable(&mut self, enable: bool);
+
     /// Set the priority the DMA stream.
     fn set_priority(&mut self, priority: config::Priority);
 
@@ -0,0 +1,560 @@
+//! Chrom-ART Accelerator (DMA2D)
+//!
+//! The DMA2D is a specialised DMA for 2D graphics operations. It can fill a
+//! rectangular area with a single colour, copy a rectangular area from one
+//! part of memory to another with or without Pixel Format Conversion (PFC) and
+//! blend two source surfaces into an output surface.
+//!
+//! Each area of memory is described by a [`Surface`]. Operations are started
+//! with one of the `start_*` methods, and complete either by polling with
+//! [`wait`](Dma2d::wait) or by listening for [`Event::TransferComplete`]. The
+//! blocking variants `fill`, `copy` and `blend` start the operation and then
+//! wait for it.
+//!
+//! # Example
+//!
+//! ```
+//! let mut dma2d = dp.DMA2D.dma2d(ccdr.peripheral.DMA2D);
+//!
+//! let fb = Surface::new(FRAMEBUFFER.as_ptr() as u32, ColorMode::Rgb565, 480, 272);
+//!
+//! // Clear the framebuffer to black
+//! unsafe { dma2d.fill(&fb, 0x0000).unwrap() };
+//!
+//! // Copy an ARGB8888 sprite into the middle of the framebuffer
+//! let sprite = Surface::new(SPRITE.as_ptr() as u32, ColorMode::Argb8888, 32, 32);
+//! let target = fb.sub_surface(224, 120, 32, 32).unwrap();
+//! unsafe { dma2d.copy(&sprite, &target).unwrap() };
+//! ```
+//!
+//! # Safety
+//!
+//! The DMA2D reads and writes memory that is accessed only by its address. The
+//! caller must ensure that each `Surface` describes memory that remains valid
+//! for the duration of the transfer, and that it is not otherwise accessed
+//! until the transfer is complete. The DMA2D cannot access the TCM memories.
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::DMA2D;
+
+/// Maximum number of pixels per line (NLR.PL)
+const MAX_PIXELS_PER_LINE: u16 = 0x3FFF;
+/// Maximum line offset (OOR.LO, FGOR.LO, BGOR.LO)
+const MAX_LINE_OFFSET: u16 = 0x3FFF;
+
+/// DMA2D error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A bus error occurred during the transfer
+    Transfer,
+    /// The peripheral rejected the configuration
+    Configuration,
+    /// A bus error occurred during an automatic CLUT load
+    ClutAccess,
+    /// The area is not contained within the surface, or its address is
+    /// beyond the end of the address space
+    OutOfBounds,
+}
+
+/// Pixel format of a surface
+///
+/// Only the formats up to and including `Argb4444` can be used for the output
+/// surface. The remaining formats are valid for the foreground and background
+/// surfaces only.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColorMode {
+    /// 32 bits per pixel
+    Argb8888 = 0,
+    /// 24 bits per pixel
+    Rgb888 = 1,
+    /// 16 bits per pixel
+    Rgb565 = 2,
+    /// 16 bits per pixel
+    Argb1555 = 3,
+    /// 16 bits per pixel
+    Argb4444 = 4,
+    /// 8 bit luminance (input only)
+    L8 = 5,
+    /// 4 bit alpha, 4 bit luminance (input only)
+    Al44 = 6,
+    /// 8 bit alpha, 8 bit luminance (input only)
+    Al88 = 7,
+    /// 4 bit luminance (input only)
+    L4 = 8,
+    /// 8 bit alpha (input only)
+    A8 = 9,
+    /// 4 bit alpha (input only)
+    A4 = 10,
+}
+
+impl ColorMode {
+    /// Returns `true` if this format can be written by the DMA2D
+    pub fn is_output_format(&self) -> bool {
+        (*self as u8) <= (ColorMode::Argb4444 as u8)
+    }
+}
+
+/// Alpha channel handling for a foreground or background surface
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Use the alpha value from each pixel
+    NoModification,
+    /// Replace the alpha value of each pixel with this value
+    Replace(u8),
+    /// Multiply the alpha value of each pixel by this value
+    Multiply(u8),
+}
+
+impl AlphaMode {
+    fn bits(&self) -> (u8, u8) {
+        match *self {
+            AlphaMode::NoModification => (0b00, 0xFF),
+            AlphaMode::Replace(alpha) => (0b01, alpha),
+            AlphaMode::Multiply(alpha) => (0b10, alpha),
+        }
+    }
+}
+
+/// Description of a rectangular area of memory
+#[derive(Copy, Clone, Debug)]
+pub struct Surface {
+    address: u32,
+    format: ColorMode,
+    width: u16,
+    height: u16,
+    line_offset: u16,
+}
+
+impl Surface {
+    /// A surface of `width` x `height` pixels starting at `address`, where
+    /// the lines are contiguous in memory
+    pub fn new(
+        address: u32,
+        format: ColorMode,
+        width: u16,
+        height: u16,
+    ) -> Self {
+        Surface {
+            address,
+            format,
+            width,
+            height,
+            line_offset: 0,
+        }
+    }
+
+    /// A rectangular area within this surface, starting at pixel (`x`, `y`)
+    ///
+    /// Returns [`Error::OutOfBounds`] if the area is not contained within
+    /// this surface.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the surface uses a format with less than 8 bits per pixel
+    pub fn sub_surface(
+        &self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+    ) -> Result<Self, Error> {
+        let contained = |start: u16, size: u16, limit: u16| {
+            start.checked_add(size).map_or(false, |end| end <= limit)
+        };
+        if !contained(x, width, self.width)
+            || !contained(y, height, self.height)
+        {
+            return Err(Error::OutOfBounds);
+        }
+        let bits = self.bits_per_pixel();
+        assert!(bits >= 8, "Cannot address sub-byte pixels");
+        let bytes = bits / 8;
+
+        let stride = u32::from(self.width) + u32::from(self.line_offset);
+        let address = (y as u32)
+            .checked_mul(stride)
+            .and_then(|pixels| pixels.checked_add(x as u32))
+            .and_then(|pixels| pixels.checked_mul(bytes))
+            .and_then(|offset| self.address.checked_add(offset))
+            .ok_or(Error::OutOfBounds)?;
+        // Larger offsets are rejected when the surface is used
+        let line_offset = self
+            .line_offset
+            .checked_add(self.width - width)
+            .ok_or(Error::OutOfBounds)?;
+
+        Ok(Surface {
+            address,
+            format: self.format,
+            width,
+            height,
+            line_offset,
+        })
+    }
+
+    /// Number of bits per pixel for this surface
+    pub fn bits_per_pixel(&self) -> u32 {
+        match self.format {
+            ColorMode::Argb8888 => 32,
+            ColorMode::Rgb888 => 24,
+            ColorMode::Rgb565
+            | ColorMode::Argb1555
+            | ColorMode::Argb4444
+            | ColorMode::Al88 => 16,
+            ColorMode::L8 | ColorMode::Al44 | ColorMode::A8 => 8,
+            ColorMode::L4 | ColorMode::A4 => 4,
+        }
+    }
+
+    /// Width of this surface in pixels
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Height of this surface in lines
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+}
+
+/// DMA2D interrupt events
+#[derive(Copy, Clone, PartialEq)]
+pub enum Event {
+    /// Transfer complete
+    TransferComplete,
+    /// Transfer error, configuration error or CLUT access error
+    Error,
+}
+
+/// Operating mode, CR.MODE
+#[derive(Copy, Clone, PartialEq)]
+enum Mode {
+    MemoryToMemory = 0b000,
+    MemoryToMemoryPfc = 0b001,
+    MemoryToMemoryBlend = 0b010,
+    RegisterToMemory = 0b011,
+}
+
+/// Extension trait for the DMA2D peripheral
+pub trait Dma2dExt {
+    /// Enable and reset the DMA2D
+    fn dma2d(self, prec: rec::Dma2d) -> Dma2d;
+}
+
+impl Dma2dExt for DMA2D {
+    fn dma2d(self, prec: rec::Dma2d) -> Dma2d {
+        Dma2d::new(self, prec)
+    }
+}
+
+/// Chrom-ART Accelerator
+pub struct Dma2d {
+    rb: DMA2D,
+}
+
+impl Dma2d {
+    /// Enable and reset the DMA2D
+    pub fn new(dma2d: DMA2D, prec: rec::Dma2d) -> Self {
+        prec.enable().reset();
+
+        Dma2d { rb: dma2d }
+    }
+
+    /// Set the output surface and start the transfer
+    fn start(&mut self, mode: Mode, output: &Surface) {
+        assert!(
+            output.format.is_output_format(),
+            "Unsupported output format"
+        );
+        assert!(output.width <= MAX_PIXELS_PER_LINE);
+        assert!(output.line_offset <= MAX_LINE_OFFSET);
+
+        // Transfer must not already be in progress
+        assert!(!self.is_busy());
+
+        // Clear any flags from the previous operation
+        self.clear_irq();
+
+        self.rb
+            .opfccr
+            .write(|w| unsafe { w.cm().bits(output.format as u8) });
+        self.rb
+            .omar
+            .write(|w| unsafe { w.ma().bits(output.address) });
+        self.rb
+            .oor
+            .write(|w| unsafe { w.lo().bits(output.line_offset) });
+        self.rb.nlr.write(|w| unsafe {
+            w.pl().bits(output.width).nl().bits(output.height)
+        });
+
+        self.rb.cr.modify(|_, w| unsafe {
+            w.mode().bits(mode as u8).start().set_bit()
+        });
+    }
+
+    /// Start filling `output` with `color`
+    ///
+    /// `color` must already be in the format of the output surface, for
+    /// example RGB565 colours in the lower 16 bits.
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn start_fill(&mut self, output: &Surface, color: u32) {
+        self.rb.ocolr.write(|w| w.bits(color));
+        self.start(Mode::RegisterToMemory, output);
+    }
+
+    /// Start copying `source` to `output`. If the formats of the surfaces
+    /// differ then Pixel Format Conversion is performed.
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn start_copy(&mut self, source: &Surface, output: &Surface) {
+        self.start_copy_with_alpha(source, AlphaMode::NoModification, output)
+    }
+
+    /// Start copying `source` to `output` with Pixel Format Conversion. The
+    /// alpha channel of the source surface is modified by `alpha`.
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn start_copy_with_alpha(
+        &mut self,
+        source: &Surface,
+        alpha: AlphaMode,
+        output: &Surface,
+    ) {
+        assert_eq!(source.width, output.width);
+        assert_eq!(source.height, output.height);
+
+        self.set_foreground(source, alpha);
+
+        let mode = if source.format == output.format
+            && alpha == AlphaMode::NoModification
+        {
+            Mode::MemoryToMemory
+        } else {
+            Mode::MemoryToMemoryPfc
+        };
+        self.start(mode, output);
+    }
+
+    /// Start blending `foreground` over `background`, writing the result to
+    /// `output`. The background and output surfaces may be the same.
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn start_blend(
+        &mut self,
+        foreground: &Surface,
+        fg_alpha: AlphaMode,
+        background: &Surface,
+        bg_alpha: AlphaMode,
+        output: &Surface,
+    ) {
+        assert_eq!(foreground.width, output.width);
+        assert_eq!(foreground.height, output.height);
+        assert_eq!(background.width, output.width);
+        assert_eq!(background.height, output.height);
+
+        self.set_foreground(foreground, fg_alpha);
+
+        let (am, alpha) = bg_alpha.bits();
+        assert!(background.line_offset <= MAX_LINE_OFFSET);
+        self.rb.bgpfccr.write(|w| {
+            w.cm()
+                .bits(background.format as u8)
+                .am()
+                .bits(am)
+                .alpha()
+                .bits(alpha)
+        });
+        self.rb.bgmar.write(|w| w.ma().bits(background.address));
+        self.rb.bgor.write(|w| w.lo().bits(background.line_offset));
+
+        self.start(Mode::MemoryToMemoryBlend, output);
+    }
+
+    unsafe fn set_foreground(&mut self, surface: &Surface, alpha: AlphaMode) {
+        let (am, alpha) = alpha.bits();
+        assert!(surface.line_offset <= MAX_LINE_OFFSET);
+        self.rb.fgpfccr.write(|w| {
+            w.cm()
+                .bits(surface.format as u8)
+                .am()
+                .bits(am)
+                .alpha()
+                .bits(alpha)
+        });
+        self.rb.fgmar.write(|w| w.ma().bits(surface.address));
+        self.rb.fgor.write(|w| w.lo().bits(surface.line_offset));
+    }
+
+    /// Fill `output` with `color`, blocking until complete
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn fill(
+        &mut self,
+        output: &Surface,
+        color: u32,
+    ) -> Result<(), Error> {
+        self.start_fill(output, color);
+        self.wait()
+    }
+
+    /// Copy `source` to `output`, blocking until complete
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn copy(
+        &mut self,
+        source: &Surface,
+        output: &Surface,
+    ) -> Result<(), Error> {
+        self.start_copy(source, output);
+        self.wait()
+    }
+
+    /// Blend `foreground` over `background` into `output`, blocking until
+    /// complete
+    ///
+    /// # Safety
+    ///
+    /// See [module level documentation](crate::dma2d)
+    pub unsafe fn blend(
+        &mut self,
+        foreground: &Surface,
+        fg_alpha: AlphaMode,
+        background: &Surface,
+        bg_alpha: AlphaMode,
+        output: &Surface,
+    ) -> Result<(), Error> {
+        self.start_blend(foreground, fg_alpha, background, bg_alpha, output);
+        self.wait()
+    }
+
+    /// Returns `true` if a transfer is in progress
+    pub fn is_busy(&self) -> bool {
+        self.rb.cr.read().start().bit_is_set()
+    }
+
+    /// Returns the result of the current transfer, without blocking
+    pub fn poll(&self) -> nb::Result<(), Error> {
+        let isr = self.rb.isr.read();
+
+        if isr.teif().bit_is_set() {
+            Err(nb::Error::Other(Error::Transfer))
+        } else if isr.ceif().bit_is_set() {
+            Err(nb::Error::Other(Error::Configuration))
+        } else if isr.caeif().bit_is_set() {
+            Err(nb::Error::Other(Error::ClutAccess))
+        } else if isr.tcif().bit_is_set() || !self.is_busy() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Block until the current transfer is complete
+    pub fn wait(&mut self) -> Result<(), Error> {
+        let result = nb::block!(self.poll());
+        self.clear_irq();
+        result
+    }
+
+    /// Abort the current transfer. Blocks until the DMA2D is idle
+    pub fn abort(&mut self) {
+        if self.is_busy() {
+            self.rb.cr.modify(|_, w| w.abort().set_bit());
+            while self.is_busy() {}
+        }
+        self.clear_irq();
+    }
+
+    /// Enable interrupts for the given `event`
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::TransferComplete => {
+                self.rb.cr.modify(|_, w| w.tcie().set_bit())
+            }
+            Event::Error => self.rb.cr.modify(|_, w| {
+                w.teie().set_bit().ceie().set_bit().caeie().set_bit()
+            }),
+        }
+    }
+
+    /// Disable interrupts for the given `event`
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::TransferComplete => {
+                self.rb.cr.modify(|_, w| w.tcie().clear_bit())
+            }
+            Event::Error => self.rb.cr.modify(|_, w| {
+                w.teie().clear_bit().ceie().clear_bit().caeie().clear_bit()
+            }),
+        }
+        let _ = self.rb.cr.read();
+        let _ = self.rb.cr.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Clear all interrupt flags
+    pub fn clear_irq(&mut self) {
+        self.rb.ifcr.write(|w| {
+            w.ctcif()
+                .set_bit()
+                .cteif()
+                .set_bit()
+                .cceif()
+                .set_bit()
+                .caecif()
+                .set_bit()
+                .cctcif()
+                .set_bit()
+                .ctwif()
+                .set_bit()
+        });
+        let _ = self.rb.isr.read();
+        let _ = self.rb.isr.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Releases the DMA2D peripheral
+    pub fn free(self) -> (DMA2D, rec::Dma2d) {
+        (
+            self.rb,
+            rec::Dma2d {
+                _marker: core::marker::PhantomData,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_surface_bounds() {
+        let fb = Surface::new(0x2400_0000, ColorMode::Rgb565, 480, 272);
+
+        let area = fb.sub_surface(224, 120, 32, 32).unwrap();
+        assert_eq!(area.address, 0x2400_0000 + (120 * 480 + 224) * 2);
+        assert_eq!(area.line_offset, 448);
+
+        let err = Some(Error::OutOfBounds);
+        assert_eq!(fb.sub_surface(449, 0, 32, 32).err(), err);
+        assert_eq!(fb.sub_surface(u16::MAX, 0, 2, 1).err(), err);
+
+        // The address of the area is beyond the end of the address space
+        let top = Surface::new(0xFFFF_0000, ColorMode::Argb8888, 480, 272);
+        assert_eq!(top.sub_surface(0, 100, 1, 1).err(), err);
+    }
+}
@@ -29,7 +29,7 @@ use smoltcp::{
     self,
     phy::{self, DeviceCapabilities},
     time::Instant,
-    wire::EthernetAddress,
+    wire::{EthernetAddress, Ipv4Address},
 };
 
 use crate::ethernet::StationManagement;
@@ -38,6 +38,10 @@ use crate::ethernet::StationManagement;
 // padding
 const ETH_BUF_SIZE: usize = 1536;
 const ETH_NUM_TD: usize = 4;
+
+/// Number of layer 3 and layer 4 filters
+pub const L3L4_FILTERS: u8 = 2;
+
 const ETH_NUM_RD: usize = 4;
 
 /// Transmit and Receive Descriptor fields
@@ -54,9 +58,132 @@ mod emac_consts {
     pub const EMAC_RDES3_BUF1V: u32 = 0x0100_0000;
     pub const EMAC_TDES2_B1L: u32 = 0x0000_3FFF;
     pub const EMAC_DES0_BUF1AP: u32 = 0xFFFF_FFFF;
+    // Write-back format
+    pub const EMAC_RDES3_CE: u32 = 0x0100_0000;
+    pub const EMAC_RDES3_GP: u32 = 0x0080_0000;
+    pub const EMAC_RDES3_RWT: u32 = 0x0040_0000;
+    pub const EMAC_RDES3_OE: u32 = 0x0020_0000;
+    pub const EMAC_RDES3_RE: u32 = 0x0010_0000;
+    pub const EMAC_RDES3_DE: u32 = 0x0008_0000;
+    pub const EMAC_TDES3_UF: u32 = 0x0000_0004;
 }
 use self::emac_consts::*;
 
+/// Error of a received or transmitted packet, as reported in its
+/// descriptor
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum PacketError {
+    /// The packet was received with a CRC error
+    Crc,
+    /// The packet was received with a dribble bit error, a number of bits
+    /// that is not a multiple of 8
+    Dribble,
+    /// The receive FIFO overflowed while the packet was received
+    Overflow,
+    /// The packet was longer than the maximum length
+    Giant,
+    /// The receive watchdog expired while the packet was received
+    Watchdog,
+    /// The PHY signalled a receive error
+    Receive,
+    /// The packet did not fit in a single receive descriptor, or had
+    /// another receive error
+    OtherReceive,
+    /// The transmit FIFO ran out of data while the packet was transmitted
+    Underflow,
+    /// The packet was not transmitted, for example after jabber timeout
+    /// or the loss of carrier
+    OtherTransmit,
+}
+
+impl PacketError {
+    /// Returns the error of a receive descriptor in write-back format that
+    /// is not valid
+    fn receive(rdes3: u32) -> Self {
+        if rdes3 & EMAC_DES3_ES == 0 {
+            PacketError::OtherReceive
+        } else if rdes3 & EMAC_RDES3_OE != 0 {
+            PacketError::Overflow
+        } else if rdes3 & EMAC_RDES3_CE != 0 {
+            PacketError::Crc
+        } else if rdes3 & EMAC_RDES3_DE != 0 {
+            PacketError::Dribble
+        } else if rdes3 & EMAC_RDES3_GP != 0 {
+            PacketError::Giant
+        } else if rdes3 & EMAC_RDES3_RWT != 0 {
+            PacketError::Watchdog
+        } else if rdes3 & EMAC_RDES3_RE != 0 {
+            PacketError::Receive
+        } else {
+            PacketError::OtherReceive
+        }
+    }
+
+    /// Returns the error of a transmit descriptor in write-back format, if
+    /// there is one
+    fn transmit(tdes3: u32) -> Option<Self> {
+        if tdes3 & (EMAC_DES3_OWN | EMAC_DES3_ES) != EMAC_DES3_ES {
+            None
+        } else if tdes3 & EMAC_TDES3_UF != 0 {
+            Some(PacketError::Underflow)
+        } else {
+            Some(PacketError::OtherTransmit)
+        }
+    }
+}
+
+/// Error and drop counters of the ethernet DMA, see
+/// [`EthernetDMA::statistics`]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// Packets received with a CRC error
+    pub rx_crc_errors: u32,
+    /// Packets received with a dribble bit error
+    pub rx_dribble_errors: u32,
+    /// Packets lost to a receive FIFO overflow
+    pub rx_overflows: u32,
+    /// Packets received with another error, including packets that were
+    /// too long
+    pub rx_other_errors: u32,
+    /// Packets dropped because no receive descriptor was available
+    pub rx_missed: u32,
+    /// Packets transmitted with a transmit FIFO underflow
+    pub tx_underflows: u32,
+    /// Packets not transmitted because of another error
+    pub tx_other_errors: u32,
+}
+
+impl Statistics {
+    fn count(&mut self, error: PacketError) {
+        let counter = match error {
+            PacketError::Crc => &mut self.rx_crc_errors,
+            PacketError::Dribble => &mut self.rx_dribble_errors,
+            PacketError::Overflow => &mut self.rx_overflows,
+            PacketError::Underflow => &mut self.tx_underflows,
+            PacketError::OtherTransmit => &mut self.tx_other_errors,
+            _ => &mut self.rx_other_errors,
+        };
+        *counter = counter.wrapping_add(1);
+    }
+}
+
+/// Counters and error callback, shared by the DMA and its tokens
+#[derive(Default)]
+struct Monitor {
+    statistics: Statistics,
+    callback: Option<fn(PacketError)>,
+}
+
+impl Monitor {
+    fn report(&mut self, error: PacketError) {
+        self.statistics.count(error);
+        if let Some(callback) = self.callback {
+            callback(error);
+        }
+    }
+}
+
 /// Transmit Descriptor representation
 ///
 /// * tdes0: transmit buffer address
@@ -144,9 +271,13 @@ impl TDesRing {
     }
 
     /// Release the next TDes to the DMA engine for transmission
-    pub fn release(&mut self) {
+    ///
+    /// Returns the error of the packet that was last transmitted with this
+    /// TDes, if there was one
+    pub fn release(&mut self) -> Option<PacketError> {
         let x = self.tdidx;
         assert!(self.td[x].tdes3 & EMAC_DES3_OWN == 0); // Owned by us
+        let error = PacketError::transmit(self.td[x].tdes3);
 
         // unsafe: tbuf is actually aligned, but with repr(packed) the
         // compiler cannot infer this
@@ -176,6 +307,7 @@ impl TDesRing {
         });
 
         self.tdidx = x;
+        error
     }
 
     /// Access the buffer pointed to by the next TDes
@@ -300,6 +432,11 @@ impl RDesRing {
         self.rd[self.rdidx].valid()
     }
 
+    /// Return the error of the current RDes, which must not be valid
+    pub fn error(&self) -> PacketError {
+        PacketError::receive(self.rd[self.rdidx].rdes3)
+    }
+
     /// Release the next RDes to the DMA engine
     pub fn release(&mut self) {
         let x = self.rdidx;
@@ -372,6 +509,7 @@ impl DesRing {
 pub struct EthernetDMA<'a> {
     ring: &'a mut DesRing,
     eth_dma: stm32::ETHERNET_DMA,
+    monitor: Monitor,
 }
 
 ///
@@ -660,12 +798,212 @@ pub unsafe fn new_unchecked<'a>(
         clock_range: csr_clock_range,
     };
 
-    let dma = EthernetDMA { ring, eth_dma };
+    let dma = EthernetDMA {
+        ring,
+        eth_dma,
+        monitor: Monitor::default(),
+    };
 
     (dma, mac)
 }
 
+/// Layer 4 protocol matched by an [`L3L4Filter`]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum L4Protocol {
+    /// Transmission Control Protocol
+    Tcp,
+    /// User Datagram Protocol
+    Udp,
+}
+
+/// Layer 3 and layer 4 filter, matching IPv4 addresses and TCP or UDP ports
+///
+/// Fields that are not set match any value. The protocol is only compared
+/// together with a port: a filter without a source or destination port
+/// matches TCP and UDP packets, and other IP packets, alike.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct L3L4Filter {
+    protocol: L4Protocol,
+    source_port: Option<u16>,
+    destination_port: Option<u16>,
+    source_address: Option<(Ipv4Address, u8)>,
+    destination_address: Option<(Ipv4Address, u8)>,
+}
+
+impl L3L4Filter {
+    /// Creates a filter for `protocol`. The protocol is only matched if a
+    /// port is set too
+    pub fn new(protocol: L4Protocol) -> Self {
+        L3L4Filter {
+            protocol,
+            source_port: None,
+            destination_port: None,
+            source_address: None,
+            destination_address: None,
+        }
+    }
+
+    /// Matches the source port
+    pub fn source_port(mut self, port: u16) -> Self {
+        self.source_port = Some(port);
+        self
+    }
+
+    /// Matches the destination port
+    pub fn destination_port(mut self, port: u16) -> Self {
+        self.destination_port = Some(port);
+        self
+    }
+
+    /// Matches the first `prefix_len` bits of the source address
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is not in the range 1 to 32
+    pub fn source_address(
+        mut self,
+        address: Ipv4Address,
+        prefix_len: u8,
+    ) -> Self {
+        assert!((1..=32).contains(&prefix_len), "Invalid prefix length");
+        self.source_address = Some((address, prefix_len));
+        self
+    }
+
+    /// Matches the first `prefix_len` bits of the destination address
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is not in the range 1 to 32
+    pub fn destination_address(
+        mut self,
+        address: Ipv4Address,
+        prefix_len: u8,
+    ) -> Self {
+        assert!((1..=32).contains(&prefix_len), "Invalid prefix length");
+        self.destination_address = Some((address, prefix_len));
+        self
+    }
+}
+
+// Accesses the MACL3L4CxR, MACL4AxR, MACL3A0xR and MACL3A1xR registers of
+// L3/L4 filter `x`
+macro_rules! l3l4_filters {
+    ($($n:literal),+) => {
+        paste::item! {
+            impl EthernetMAC {
+                /// Writes L3/L4 filter `index`. The filter is disabled
+                /// while it is changed, and stays disabled for `None`
+                fn write_l3l4_filter(
+                    &mut self,
+                    index: u8,
+                    filter: Option<&L3L4Filter>,
+                ) {
+                    let mac = &self.eth_mac;
+                    match index {
+                        $(
+                            $n => {
+                                mac.[<macl3l4c $n r>].reset();
+                                let filter = match filter {
+                                    Some(filter) => filter,
+                                    None => return,
+                                };
+
+                                let ports = (
+                                    filter.source_port.unwrap_or(0),
+                                    filter.destination_port.unwrap_or(0),
+                                );
+                                mac.[<macl4a $n r>].write(|w| unsafe {
+                                    w.[<l4sp $n>]()
+                                        .bits(ports.0)
+                                        .[<l4dp $n>]()
+                                        .bits(ports.1)
+                                });
+                                let (source, source_len) = filter
+                                    .source_address
+                                    .map(|(a, len)| (u32::from_be_bytes(a.0), len))
+                                    .unwrap_or((0, 32));
+                                let (destination, destination_len) = filter
+                                    .destination_address
+                                    .map(|(a, len)| (u32::from_be_bytes(a.0), len))
+                                    .unwrap_or((0, 32));
+                                mac.[<macl3a0 $n r>]
+                                    .write(|w| unsafe { w.[<l3a0 $n>]().bits(source) });
+                                mac.[<macl3a1 $n r>]
+                                    .write(|w| unsafe { w.[<l3a1 $n>]().bits(destination) });
+
+                                // HSBM and HDBM are the number of low bits
+                                // of the addresses that are not compared
+                                mac.[<macl3l4c $n r>].write(|w| unsafe {
+                                    w.[<l4pen $n>]()
+                                        .bit(filter.protocol == L4Protocol::Udp)
+                                        .[<l4spm $n>]()
+                                        .bit(filter.source_port.is_some())
+                                        .[<l4dpm $n>]()
+                                        .bit(filter.destination_port.is_some())
+                                        .[<l3sam $n>]()
+                                        .bit(filter.source_address.is_some())
+                                        .[<l3hsbm $n>]()
+                                        .bits(32 - source_len)
+                                        .[<l3dam $n>]()
+                                        .bit(filter.destination_address.is_some())
+                                        .[<l3hdbm $n>]()
+                                        .bits(32 - destination_len)
+                                });
+                            }
+                        )+
+                        _ => panic!("Not a L3/L4 filter"),
+                    }
+                }
+
+                /// Returns `true` if any L3/L4 filter is set
+                fn any_l3l4_filter(&self) -> bool {
+                    let mac = &self.eth_mac;
+                    false $(|| mac.[<macl3l4c $n r>].read().bits() != 0)+
+                }
+            }
+        }
+    };
+}
+
+l3l4_filters!(0, 1);
+
 impl EthernetMAC {
+    /// Sets layer 3 and layer 4 filter `index`, and enables filtering
+    ///
+    /// While filtering is enabled, IP packets are only received if they
+    /// match at least one enabled filter. Packets that are not IP, such as
+    /// ARP, are not filtered. The MAC no longer receives all packets:
+    /// unicast packets for other MAC addresses and multicast packets are
+    /// dropped too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`L3L4_FILTERS`]
+    pub fn set_l3l4_filter(&mut self, index: u8, filter: L3L4Filter) {
+        self.write_l3l4_filter(index, Some(&filter));
+
+        self.eth_mac
+            .macpfr
+            .modify(|_, w| w.ipfe().set_bit().ra().clear_bit());
+    }
+
+    /// Clears layer 3 and layer 4 filter `index`. Filtering is disabled,
+    /// and the MAC receives all packets again, once no filter is set
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`L3L4_FILTERS`]
+    pub fn clear_l3l4_filter(&mut self, index: u8) {
+        self.write_l3l4_filter(index, None);
+
+        if !self.any_l3l4_filter() {
+            self.eth_mac
+                .macpfr
+                .modify(|_, w| w.ipfe().clear_bit().ra().set_bit());
+        }
+    }
+
     /// Sets the SMI address to use for the PHY
     pub fn set_phy_addr(self, eth_phy_addr: u8) -> Self {
         Self {
@@ -720,7 +1058,7 @@ impl StationManagement for EthernetMAC {
 }
 
 /// Define TxToken type and implement consume method
-pub struct TxToken<'a>(&'a mut TDesRing);
+pub struct TxToken<'a>(&'a mut TDesRing, &'a mut Monitor);
 
 impl<'a> phy::TxToken for TxToken<'a> {
     fn consume<R, F>(
@@ -735,7 +1073,9 @@ impl<'a> phy::TxToken for TxToken<'a> {
         assert!(len <= ETH_BUF_SIZE);
 
         let result = f(unsafe { self.0.buf_as_slice_mut(len) });
-        self.0.release();
+        if let Some(error) = self.0.release() {
+            self.1.report(error);
+        }
         result
     }
 }
@@ -773,11 +1113,15 @@ impl<'a> phy::Device<'a> for EthernetDMA<'_> {
     fn receive(&mut self) -> Option<(RxToken, TxToken)> {
         // Skip all queued packets with errors.
         while self.ring.rx.available() && !self.ring.rx.valid() {
+            self.monitor.report(self.ring.rx.error());
             self.ring.rx.release()
         }
 
         if self.ring.rx.available() && self.ring.tx.available() {
-            Some((RxToken(&mut self.ring.rx), TxToken(&mut self.ring.tx)))
+            Some((
+                RxToken(&mut self.ring.rx),
+                TxToken(&mut self.ring.tx, &mut self.monitor),
+            ))
         } else {
             None
         }
@@ -785,7 +1129,7 @@ impl<'a> phy::Device<'a> for EthernetDMA<'_> {
 
     fn transmit(&mut self) -> Option<TxToken> {
         if self.ring.tx.available() {
-            Some(TxToken(&mut self.ring.tx))
+            Some(TxToken(&mut self.ring.tx, &mut self.monitor))
         } else {
             None
         }
@@ -795,9 +1139,40 @@ impl<'a> phy::Device<'a> for EthernetDMA<'_> {
 impl EthernetDMA<'_> {
     /// Return the number of packets dropped since this method was
     /// last called
+    ///
+    /// This reads the same counter as the `rx_missed` field of
+    /// [`statistics`](Self::statistics), so packets are counted by
+    /// whichever of the two is called first.
     pub fn number_packets_dropped(&self) -> u32 {
         self.eth_dma.dmacmfcr.read().mfc().bits() as u32
     }
+
+    /// Returns the error and drop counters since the DMA was created, or
+    /// since [`reset_statistics`](Self::reset_statistics)
+    ///
+    /// Receive errors are counted when smoltcp polls for a packet and the
+    /// packet is skipped. A transmit error is counted when its descriptor
+    /// is next used, so the errors of the last few packets are counted
+    /// late.
+    pub fn statistics(&mut self) -> Statistics {
+        let missed = self.number_packets_dropped();
+        let statistics = &mut self.monitor.statistics;
+        statistics.rx_missed = statistics.rx_missed.wrapping_add(missed);
+        *statistics
+    }
+
+    /// Resets the error and drop counters to zero
+    pub fn reset_statistics(&mut self) {
+        let _ = self.number_packets_dropped();
+        self.monitor.statistics = Statistics::default();
+    }
+
+    /// Calls `callback` for each packet with an error, when the error is
+    /// counted. The callback runs within smoltcp's polling of the
+    /// interface and should return quickly. `None` removes the callback
+    pub fn on_error(&mut self, callback: Option<fn(PacketError)>) {
+        self.monitor.callback = callback;
+    }
 }
 
 pub unsafe fn interrupt_handler() {
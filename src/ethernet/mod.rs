@@ -5,6 +5,45 @@
 //! - SMSC LAN8742a
 //! - Micrel KSZ8081R
 //!
+//! # Filtering
+//!
+//! To avoid interrupts for traffic that is not used, for example on a busy
+//! network, IP packets can be filtered by address and TCP or UDP port
+//! before they reach the receive ring.
+//!
+//! ```
+//! use smoltcp::wire::Ipv4Address;
+//! use stm32h7xx_hal::ethernet::{L3L4Filter, L4Protocol};
+//!
+//! // Only receive UDP packets for port 5000 from 192.168.1.0/24
+//! eth_mac.set_l3l4_filter(
+//!     0,
+//!     L3L4Filter::new(L4Protocol::Udp)
+//!         .destination_port(5000)
+//!         .source_address(Ipv4Address::new(192, 168, 1, 0), 24),
+//! );
+//! ```
+//!
+//! # Statistics
+//!
+//! Packets that the DMA reports with an error are skipped, and counted in
+//! [`Statistics`] together with the packets that were dropped because the
+//! receive ring was full. A callback can also be called for each error, for
+//! example to log it.
+//!
+//! ```
+//! fn log_error(error: PacketError) {
+//!     warn!("ethernet: {:?}", error);
+//! }
+//! eth_dma.on_error(Some(log_error));
+//!
+//! let statistics = eth_dma.statistics();
+//! info!(
+//!     "{} CRC errors, {} missed",
+//!     statistics.rx_crc_errors, statistics.rx_missed
+//! );
+//! ```
+//!
 
 /// Station Management Interface (SMI) on an ethernet PHY
 pub trait StationManagement {
@@ -33,7 +72,10 @@ pub mod phy {
 
 mod eth;
 pub use eth::{enable_interrupt, interrupt_handler, new_unchecked};
-pub use eth::{DesRing, EthernetDMA, EthernetMAC};
+pub use eth::{
+    DesRing, EthernetDMA, EthernetMAC, L3L4Filter, L4Protocol, PacketError,
+    Statistics, L3L4_FILTERS,
+};
 
 /// Marks a set of pins used to communciate to a PHY with a Reduced Media
 /// Independent Interface (RMII)
@@ -1,4 +1,58 @@
 //! External interrupt controller
+//!
+//! The EXTI controller wakes the CPU with an interrupt or an event when one
+//! of its input lines is triggered. GPIO pins are connected to lines 0 -
+//! 15 through SYSCFG, see [`ExtiPin`](crate::gpio::ExtiPin).
+//!
+//! # Example
+//!
+//! ```
+//! let mut button = gpioc.pc13.into_floating_input().into_interrupt_source(
+//!     &mut syscfg,
+//!     &mut exti,
+//!     Edge::Falling,
+//! );
+//!
+//! // In the interrupt handler
+//! if button.check_interrupt() {
+//!     button.clear_interrupt_pending_bit();
+//! }
+//! ```
+//!
+//! # Wakeup from Stop mode
+//!
+//! Interrupts ([`listen`](ExtiExt::listen)) and events
+//! ([`listen_event`](ExtiExt::listen_event)) wake the CPU from Stop
+//! mode. Events only wake the CPU from a `WFE` instruction, and do not need
+//! to be cleared.
+//!
+//! While the D3 domain runs autonomously, lines that support it can set a
+//! D3 pending flag with [`d3_pending`](ExtiExt::d3_pending) to keep the D3
+//! domain running until the flag is cleared by a BDMA channel or LPTIM
+//! output.
+//!
+//! # Event only lines
+//!
+//! A line configured with [`listen_event_only`](ExtiExt::listen_event_only)
+//! wakes the CPU from `WFE` without entering an interrupt handler, which
+//! suits an idle loop that polls its sources after each wakeup.
+//! [`software_trigger`](ExtiExt::software_trigger) triggers a configurable
+//! line as if an edge had occurred, for example to test a handler.
+//!
+//! ```
+//! exti.listen_event_only(Event::GPIO13);
+//! exti.trigger(Event::GPIO13, Edge::Falling);
+//!
+//! loop {
+//!     cortex_m::asm::wfe();
+//!     if exti.is_pending(Event::GPIO13) {
+//!         exti.unpend(Event::GPIO13);
+//!         // Handle the button
+//!     }
+//! }
+//! ```
+
+use crate::gpio::Edge;
 use crate::stm32::EXTI;
 
 /// EXTI trigger event
@@ -90,8 +144,108 @@ pub enum Event {
     HSECSS = 87,
 }
 
+impl Event {
+    /// Returns the event for GPIO pin number `pin`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` is greater than 15
+    pub fn gpio(pin: u8) -> Event {
+        match pin {
+            0 => Event::GPIO0,
+            1 => Event::GPIO1,
+            2 => Event::GPIO2,
+            3 => Event::GPIO3,
+            4 => Event::GPIO4,
+            5 => Event::GPIO5,
+            6 => Event::GPIO6,
+            7 => Event::GPIO7,
+            8 => Event::GPIO8,
+            9 => Event::GPIO9,
+            10 => Event::GPIO10,
+            11 => Event::GPIO11,
+            12 => Event::GPIO12,
+            13 => Event::GPIO13,
+            14 => Event::GPIO14,
+            15 => Event::GPIO15,
+            _ => panic!("No EXTI line for GPIO pin {}", pin),
+        }
+    }
+}
+
+/// Source that clears a D3 pending flag
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum D3PendClear {
+    /// BDMA channel 6 event
+    BdmaCh6 = 0b00,
+    /// BDMA channel 7 event
+    BdmaCh7 = 0b01,
+    /// LPTIM4 output
+    Lptim4 = 0b10,
+    /// LPTIM5 output
+    Lptim5 = 0b11,
+}
+
+/// Return a D3 pending mask or clear selection register. These are the SRD
+/// domain registers on RM0455 parts
+#[cfg(not(feature = "rm0455"))]
+macro_rules! reg_for_d3 {
+    ($self:ident, pmr1) => {
+        $self.d3pmr1
+    };
+    ($self:ident, pcr1l) => {
+        $self.d3pcr1l
+    };
+    ($self:ident, pcr1h) => {
+        $self.d3pcr1h
+    };
+    ($self:ident, pmr2) => {
+        $self.d3pmr2
+    };
+    ($self:ident, pcr2l) => {
+        $self.d3pcr2l
+    };
+    ($self:ident, pcr2h) => {
+        $self.d3pcr2h
+    };
+    ($self:ident, pmr3) => {
+        $self.d3pmr3
+    };
+    ($self:ident, pcr3h) => {
+        $self.d3pcr3h
+    };
+}
+
+#[cfg(feature = "rm0455")]
+macro_rules! reg_for_d3 {
+    ($self:ident, pmr1) => {
+        $self.srdpmr1
+    };
+    ($self:ident, pcr1l) => {
+        $self.srdpcr1l
+    };
+    ($self:ident, pcr1h) => {
+        $self.srdpcr1h
+    };
+    ($self:ident, pmr2) => {
+        $self.srdpmr2
+    };
+    ($self:ident, pcr2l) => {
+        $self.srdpcr2l
+    };
+    ($self:ident, pcr2h) => {
+        $self.srdpcr2h
+    };
+    ($self:ident, pmr3) => {
+        $self.srdpmr3
+    };
+    ($self:ident, pcr3h) => {
+        $self.srdpcr3h
+    };
+}
+
 /// Return an EXTI register for the current CPU
-#[cfg(any(feature = "rm0433", feature = "rm0455"))]
+#[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
 macro_rules! reg_for_cpu {
     ($self:ident, imr1) => {
         $self.cpuimr1
@@ -187,6 +341,12 @@ macro_rules! reg_for_cpu {
 pub trait ExtiExt {
     fn listen(&self, ev: Event);
     fn unlisten(&self, ev: Event);
+    fn listen_event(&self, ev: Event);
+    fn unlisten_event(&self, ev: Event);
+    fn listen_event_only(&self, ev: Event);
+    fn trigger(&self, ev: Event, edge: Edge);
+    fn software_trigger(&self, ev: Event);
+    fn d3_pending(&self, ev: Event, clear: Option<D3PendClear>);
     fn is_pending(&self, ev: Event) -> bool;
     fn unpend(&self, ev: Event);
 }
@@ -238,6 +398,164 @@ impl ExtiExt for EXTI {
         }
     }
 
+    /// CPU Event Enable
+    ///
+    /// The event wakes the CPU from a `WFE` instruction, including in Stop
+    /// mode
+    fn listen_event(&self, ev: Event) {
+        let line = ev as u8;
+
+        unsafe {
+            match line {
+                0..=31 => reg_for_cpu!(self, emr1)
+                    .modify(|r, w| w.bits(r.bits() | (1 << line))),
+                32..=44 | 46..=63 => reg_for_cpu!(self, emr2)
+                    .modify(|r, w| w.bits(r.bits() | (1 << (line - 32)))),
+                64..=80 | 82 | 84..=88 => reg_for_cpu!(self, emr3)
+                    .modify(|r, w| w.bits(r.bits() | (1 << (line - 64)))),
+                _ => {}
+            }
+        }
+    }
+
+    /// CPU Event Disable
+    fn unlisten_event(&self, ev: Event) {
+        let line = ev as u8;
+
+        unsafe {
+            match line {
+                0..=31 => reg_for_cpu!(self, emr1)
+                    .modify(|r, w| w.bits(r.bits() & !(1 << line))),
+                32..=44 | 46..=63 => reg_for_cpu!(self, emr2)
+                    .modify(|r, w| w.bits(r.bits() & !(1 << (line - 32)))),
+                64..=80 | 82 | 84..=88 => reg_for_cpu!(self, emr3)
+                    .modify(|r, w| w.bits(r.bits() & !(1 << (line - 64)))),
+                _ => {}
+            }
+        }
+    }
+
+    /// CPU Event Enable, with the CPU Interrupt disabled
+    ///
+    /// The event wakes the CPU from a `WFE` instruction without an
+    /// interrupt. The pending flag of a configurable event is still set
+    fn listen_event_only(&self, ev: Event) {
+        self.unlisten(ev);
+        self.listen_event(ev);
+    }
+
+    /// Select the edges that trigger the event
+    ///
+    /// Configurable events only
+    fn trigger(&self, ev: Event, edge: Edge) {
+        let line = ev as u8;
+        let rising = edge != Edge::Falling;
+        let falling = edge != Edge::Rising;
+
+        let update = |r: u32, bit: u32, set: bool| {
+            if set {
+                r | (1 << bit)
+            } else {
+                r & !(1 << bit)
+            }
+        };
+
+        unsafe {
+            match line {
+                0..=19 | 20 | 21 => {
+                    let bit = u32::from(line);
+                    self.rtsr1
+                        .modify(|r, w| w.bits(update(r.bits(), bit, rising)));
+                    self.ftsr1
+                        .modify(|r, w| w.bits(update(r.bits(), bit, falling)));
+                }
+                49 | 51 => {
+                    let bit = u32::from(line - 32);
+                    self.rtsr2
+                        .modify(|r, w| w.bits(update(r.bits(), bit, rising)));
+                    self.ftsr2
+                        .modify(|r, w| w.bits(update(r.bits(), bit, falling)));
+                }
+                82 | 84 | 85 | 86 => {
+                    let bit = u32::from(line - 64);
+                    self.rtsr3
+                        .modify(|r, w| w.bits(update(r.bits(), bit, rising)));
+                    self.ftsr3
+                        .modify(|r, w| w.bits(update(r.bits(), bit, falling)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Software Interrupt Event
+    ///
+    /// Triggers the event as if its selected edge had occurred. The
+    /// pending flag is set, and the interrupt or event is generated if it
+    /// is enabled.
+    ///
+    /// Configurable events only
+    fn software_trigger(&self, ev: Event) {
+        let line = ev as u8;
+
+        // Writing 0 has no effect. Refer to RM0433 Rev 7 - Chapter 20.6.3
+        unsafe {
+            match line {
+                0..=19 | 20 | 21 => self.swier1.write(|w| w.bits(1 << line)),
+                49 | 51 => self.swier2.write(|w| w.bits(1 << (line - 32))),
+                82 | 84 | 85 | 86 => {
+                    self.swier3.write(|w| w.bits(1 << (line - 64)))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// D3 Pending Enable
+    ///
+    /// With `Some(clear)`, the event sets a D3 pending flag that keeps the
+    /// D3 domain in Run mode until the flag is cleared by `clear`. With
+    /// `None`, the D3 pending flag is disabled.
+    ///
+    /// Only events that can wake the D3 domain autonomously: GPIO, RTC
+    /// wakeup, COMP, I2C4, LPUART1, SPI6 and LPTIM2 - LPTIM5
+    fn d3_pending(&self, ev: Event, clear: Option<D3PendClear>) {
+        let line = ev as u8;
+
+        // Each clear selection register holds 16 lines
+        macro_rules! d3_pending {
+            ($pmr:ident, $pcr:ident, $bit:expr) => {{
+                let bit = $bit;
+                let shift = 2 * u32::from(bit % 16);
+                unsafe {
+                    match clear {
+                        Some(clear) => {
+                            reg_for_d3!(self, $pcr).modify(|r, w| {
+                                w.bits(
+                                    (r.bits() & !(0b11 << shift))
+                                        | ((clear as u32) << shift),
+                                )
+                            });
+                            reg_for_d3!(self, $pmr)
+                                .modify(|r, w| w.bits(r.bits() | (1 << bit)));
+                        }
+                        None => reg_for_d3!(self, $pmr)
+                            .modify(|r, w| w.bits(r.bits() & !(1 << bit))),
+                    }
+                }
+            }};
+        }
+
+        match line {
+            0..=15 => d3_pending!(pmr1, pcr1l, line),
+            19..=21 | 25 => d3_pending!(pmr1, pcr1h, line),
+            34 | 35 | 41 => d3_pending!(pmr2, pcr2l, line - 32),
+            48..=53 => d3_pending!(pmr2, pcr2h, line - 32),
+            88 => d3_pending!(pmr3, pcr3h, line - 64),
+            _ => {}
+        }
+    }
+
     /// Indicate if the interrupt is currently pending
     ///
     /// Configurable events only
@@ -286,3 +604,53 @@ impl ExtiExt for EXTI {
         }
     }
 }
+
+/// Configurable lines in each group of 32 lines. These lines have a
+/// trigger edge and a pending flag
+const CONFIGURABLE: [u32; 3] = [
+    0x003F_FFFF,
+    (1 << 17) | (1 << 19),
+    (1 << 18) | (1 << 20) | (1 << 21) | (1 << 22),
+];
+
+/// Returns `true` if a configurable line has a trigger edge, and is enabled
+/// as an interrupt or event of the current CPU
+pub(crate) fn wakeup_armed() -> bool {
+    // NOTE(unsafe) Atomic reads with no side effects
+    let exti = unsafe { &*EXTI::ptr() };
+    let armed = [
+        (exti.rtsr1.read().bits() | exti.ftsr1.read().bits())
+            & (reg_for_cpu!(exti, imr1).read().bits()
+                | reg_for_cpu!(exti, emr1).read().bits()),
+        (exti.rtsr2.read().bits() | exti.ftsr2.read().bits())
+            & (reg_for_cpu!(exti, imr2).read().bits()
+                | reg_for_cpu!(exti, emr2).read().bits()),
+        (exti.rtsr3.read().bits() | exti.ftsr3.read().bits())
+            & (reg_for_cpu!(exti, imr3).read().bits()
+                | reg_for_cpu!(exti, emr3).read().bits()),
+    ];
+    armed
+        .iter()
+        .zip(CONFIGURABLE.iter())
+        .any(|(armed, lines)| armed & lines != 0)
+}
+
+/// Returns the lowest configurable line that is pending for the current
+/// CPU, if there is one
+pub(crate) fn first_pending() -> Option<u8> {
+    // NOTE(unsafe) Atomic reads with no side effects
+    let exti = unsafe { &*EXTI::ptr() };
+    let pending = [
+        reg_for_cpu!(exti, pr1).read().bits(),
+        reg_for_cpu!(exti, pr2).read().bits(),
+        reg_for_cpu!(exti, pr3).read().bits(),
+    ];
+    pending
+        .iter()
+        .zip(CONFIGURABLE.iter())
+        .enumerate()
+        .find_map(|(group, (pending, lines))| match pending & lines {
+            0 => None,
+            bits => Some(32 * group as u8 + bits.trailing_zeros() as u8),
+        })
+}
@@ -1,7 +1,57 @@
 //! Flash memory
+//!
+//! On RM0433 and RM0399 parts, the flash banks can be erased and
+//! programmed, see [`program`], and the option bytes can be read and
+//! changed, see [`options`]. On the dual bank parts, that is all of these
+//! except the STM32H750, a firmware image can be programmed into the
+//! inactive bank, see [`update`].
 
 use crate::stm32::{flash, FLASH};
 
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub mod options;
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub mod program;
+#[cfg(all(
+    any(feature = "rm0433", feature = "rm0399"),
+    not(any(feature = "stm32h750", feature = "stm32h750v"))
+))]
+pub mod update;
+
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub use options::{BorLevel, OptionBytes};
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub use program::{Bank, Error, UnlockedBank, FLASH_WORD, SECTOR_SIZE};
+
+// RDP values. All values other than those of level 0 and 2 are level 1
+pub(crate) const RDP_LEVEL0: u8 = 0xAA;
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub(crate) const RDP_LEVEL1: u8 = 0x55;
+const RDP_LEVEL2: u8 = 0xCC;
+
+/// Readout protection level
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RdpLevel {
+    /// No protection
+    Level0,
+    /// The flash cannot be read by a debugger. Returning to level 0 mass
+    /// erases the flash
+    Level1,
+    /// The debug port is disabled permanently
+    Level2,
+}
+
+impl RdpLevel {
+    /// Returns the level of the RDP option byte `rdp`
+    pub(crate) fn from_bits(rdp: u8) -> Self {
+        match rdp {
+            RDP_LEVEL0 => RdpLevel::Level0,
+            RDP_LEVEL2 => RdpLevel::Level2,
+            _ => RdpLevel::Level1,
+        }
+    }
+}
+
 /// Extension trait to constrain the FLASH peripheral
 pub trait FlashExt {
     /// Constrains the FLASH peripheral to play nicely with the other abstractions
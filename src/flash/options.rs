@@ -0,0 +1,228 @@
+//! Option bytes
+//!
+//! The option bytes configure the device at reset: the brown-out reset
+//! level, the readout protection, the boot addresses and the bank swap.
+//! They are read with [`Flash::option_bytes`], and changed with
+//! [`Flash::program_option_bytes`].
+//!
+//! ```
+//! let mut flash = dp.FLASH.constrain();
+//!
+//! let mut options = flash.option_bytes();
+//! options.bor_level = BorLevel::Level2;
+//! flash.program_option_bytes(&options).unwrap();
+//! ```
+//!
+//! Most option bytes take effect immediately, but the boot addresses and
+//! the bank swap only take effect after a reset. On dual core parts, the
+//! boot addresses are those of the Cortex-M7. The STM32H750 has a single
+//! bank, and no bank swap.
+//!
+//! # Brown-out reset
+//!
+//! The factory setting of the brown-out reset level is the lowest, VBOR0,
+//! which holds the part in reset only below about 1.7V. On a supply that
+//! sags slowly, the part can then run out of specification before it is
+//! reset. [`Flash::bor_level`] reads the level that is active, and
+//! [`Flash::set_bor_level`] changes it, keeping the other option bytes.
+//!
+//! ```
+//! // Reset below about 2.7V, for a 3.3V supply
+//! flash.set_bor_level(BorLevel::Level3).unwrap();
+//! assert_eq!(flash.bor_level(), BorLevel::Level3);
+//! ```
+
+use super::program::{Bank, Error, OPTKEY1, OPTKEY2};
+pub use super::RdpLevel;
+use super::{Flash, RDP_LEVEL0, RDP_LEVEL1};
+use crate::stm32::FLASH;
+
+/// Returns the current boot addresses, bits 29:14 of each address
+#[cfg(not(feature = "rm0399"))]
+fn boot_addresses(flash: &crate::stm32::flash::RegisterBlock) -> (u16, u16) {
+    let boot = flash.boot_curr.read();
+    (boot.boot_add0().bits(), boot.boot_add1().bits())
+}
+
+/// Returns the current boot addresses of the Cortex-M7, bits 29:14 of each
+/// address
+#[cfg(feature = "rm0399")]
+fn boot_addresses(flash: &crate::stm32::flash::RegisterBlock) -> (u16, u16) {
+    let boot = flash.boot7_curr.read();
+    (boot.boot_cm7_add0().bits(), boot.boot_cm7_add1().bits())
+}
+
+/// Sets the boot addresses to be programmed, bits 29:14 of each address
+fn set_boot_addresses(
+    flash: &crate::stm32::flash::RegisterBlock,
+    boot0: u16,
+    boot1: u16,
+) {
+    #[cfg(not(feature = "rm0399"))]
+    flash.boot_prgr.write(|w| unsafe {
+        w.boot_add0().bits(boot0).boot_add1().bits(boot1)
+    });
+
+    #[cfg(feature = "rm0399")]
+    flash.boot7_prgr.write(|w| unsafe {
+        w.boot_cm7_add0().bits(boot0).boot_cm7_add1().bits(boot1)
+    });
+}
+
+/// Alignment of the boot addresses
+const BOOT_ALIGN: u32 = 16 * 1024;
+
+/// Memories other than the user flash that can be booted from, as the
+/// start address and the size
+const BOOT_MEMORIES: [(u32, u32); 6] = [
+    (0x0000_0000, 0x1_0000), // ITCM
+    (0x1FF0_0000, 0x2_0000), // System memory, with the bootloader
+    (0x2000_0000, 0x2_0000), // DTCM
+    (0x2400_0000, 0x8_0000), // AXI SRAM
+    (0x3000_0000, 0x4_8000), // SRAM1 - SRAM3
+    (0x3800_0000, 0x1_0000), // SRAM4
+];
+
+/// Returns `true` if the device can boot from `address`
+fn is_boot_address(address: u32) -> bool {
+    address % BOOT_ALIGN == 0
+        && (Bank::containing(address as usize).is_some()
+            || BOOT_MEMORIES.iter().any(|&(start, size)| {
+                address >= start && address - start < size
+            }))
+}
+
+/// Brown-out reset level
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BorLevel {
+    /// VBOR0, the lowest threshold. This is the factory setting
+    Level0 = 0b00,
+    /// VBOR1, about 2.1V
+    Level1 = 0b01,
+    /// VBOR2, about 2.4V
+    Level2 = 0b10,
+    /// VBOR3, about 2.7V
+    Level3 = 0b11,
+}
+
+/// Option bytes
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct OptionBytes {
+    /// Brown-out reset level
+    pub bor_level: BorLevel,
+    /// Readout protection level
+    pub rdp_level: RdpLevel,
+    /// Boot address when the BOOT pin is low. Must be a multiple of 16kB
+    pub boot_address0: u32,
+    /// Boot address when the BOOT pin is high. Must be a multiple of 16kB
+    pub boot_address1: u32,
+    /// Bank 2 is mapped at `0x0800_0000`
+    #[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+    pub swap_bank: bool,
+}
+
+impl Flash {
+    /// Returns the current option bytes
+    pub fn option_bytes(&self) -> OptionBytes {
+        // NOTE(unsafe) Atomic reads with no side effects
+        let flash = unsafe { &*FLASH::ptr() };
+        let optsr = flash.optsr_cur.read();
+        let (boot0, boot1) = boot_addresses(flash);
+
+        let bor_level = match optsr.bor_lev().bits() {
+            0b00 => BorLevel::Level0,
+            0b01 => BorLevel::Level1,
+            0b10 => BorLevel::Level2,
+            _ => BorLevel::Level3,
+        };
+        let rdp_level = RdpLevel::from_bits(optsr.rdp().bits());
+
+        OptionBytes {
+            bor_level,
+            rdp_level,
+            boot_address0: u32::from(boot0) << 14,
+            boot_address1: u32::from(boot1) << 14,
+            #[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+            swap_bank: optsr.swap_bank_opt().bit_is_set(),
+        }
+    }
+
+    /// Returns the brown-out reset level that is currently active
+    pub fn bor_level(&self) -> BorLevel {
+        self.option_bytes().bor_level
+    }
+
+    /// Programs the brown-out reset level, which takes effect immediately.
+    /// The other option bytes are kept
+    ///
+    /// Nothing is programmed if `level` is already active, so this can be
+    /// called on each boot.
+    pub fn set_bor_level(&mut self, level: BorLevel) -> Result<(), Error> {
+        let mut options = self.option_bytes();
+        if options.bor_level == level {
+            return Ok(());
+        }
+        options.bor_level = level;
+        self.program_option_bytes(&options)
+    }
+
+    /// Programs the option bytes
+    ///
+    /// Returns [`Error::RdpLevel2`] for RDP level 2, which can never be
+    /// undone, and [`Error::BootAddress`] if a boot address is not a
+    /// multiple of 16kB in the flash, the system memory, the SRAM or the
+    /// TCM.
+    pub fn program_option_bytes(
+        &mut self,
+        options: &OptionBytes,
+    ) -> Result<(), Error> {
+        if options.rdp_level == RdpLevel::Level2 {
+            return Err(Error::RdpLevel2);
+        }
+        if !is_boot_address(options.boot_address0)
+            || !is_boot_address(options.boot_address1)
+        {
+            return Err(Error::BootAddress);
+        }
+        let rdp = match options.rdp_level {
+            RdpLevel::Level0 => RDP_LEVEL0,
+            _ => RDP_LEVEL1,
+        };
+
+        // Refer to RM0433 Rev 7 - Chapter 4.4.3
+        let flash = unsafe { &*FLASH::ptr() };
+        if flash.optcr.read().optlock().bit_is_set() {
+            flash.optkeyr.write(|w| unsafe { w.bits(OPTKEY1) });
+            flash.optkeyr.write(|w| unsafe { w.bits(OPTKEY2) });
+        }
+
+        flash.optsr_prg.modify(|_, w| unsafe {
+            w.bor_lev().bits(options.bor_level as u8).rdp().bits(rdp)
+        });
+        #[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+        flash
+            .optsr_prg
+            .modify(|_, w| w.swap_bank_opt().bit(options.swap_bank));
+        // The option registers are unlocked, and owned by self. The
+        // registers hold bits 29:14 of the addresses
+        set_boot_addresses(
+            flash,
+            (options.boot_address0 >> 14) as u16,
+            (options.boot_address1 >> 14) as u16,
+        );
+
+        flash.optcr.modify(|_, w| w.optstart().set_bit());
+        while flash.optsr_cur.read().opt_busy().bit_is_set() {}
+
+        let result = if flash.optsr_cur.read().optchangeerr().bit_is_set() {
+            flash.optccr.write(|w| w.clr_optchangeerr().set_bit());
+            Err(Error::OptionBytes)
+        } else {
+            Ok(())
+        };
+
+        flash.optcr.modify(|_, w| w.optlock().set_bit());
+        result
+    }
+}
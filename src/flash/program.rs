@@ -0,0 +1,436 @@
+//! Erasing and programming the flash banks
+//!
+//! A bank is unlocked with [`Flash::unlocked`], and locked again when the
+//! returned [`UnlockedBank`] is dropped. Sectors are erased in units of
+//! 128kB, and programmed in flash words of 32 bytes.
+//!
+//! ```
+//! let mut flash = dp.FLASH.constrain();
+//!
+//! let mut bank2 = flash.unlocked(Bank::Bank2);
+//! bank2.erase_sector(7).unwrap();
+//! bank2.program(7 * SECTOR_SIZE, b"calibration data").unwrap();
+//! ```
+//!
+//! The CPU stalls while it reads from a bank that is being erased or
+//! programmed, so code that programs a bank should be executed from the
+//! other bank or from RAM. If the data cache is enabled, invalidate it for
+//! the programmed area before reading it back.
+//!
+//! # Read while write
+//!
+//! A sector erase takes up to a few seconds. While the other bank is
+//! erased, the application keeps running from its own bank, so the erase
+//! can be started with
+//! [`start_erase_sector`](UnlockedBank::start_erase_sector) and polled with
+//! [`poll`](UnlockedBank::poll), for example while a new firmware image is
+//! downloaded.
+//!
+//! ```
+//! let mut bank2 = flash.unlocked(Bank::Bank2);
+//! bank2.start_erase_sector(0).unwrap();
+//! loop {
+//!     match bank2.poll() {
+//!         Err(nb::Error::WouldBlock) => network.poll(),
+//!         result => break result.unwrap(),
+//!     }
+//! }
+//! ```
+
+use core::ptr;
+
+use super::Flash;
+use crate::signature::FlashSize;
+use crate::stm32::FLASH;
+
+/// Size of a sector
+pub const SECTOR_SIZE: usize = 128 * 1024;
+/// Size of a flash word, the unit of programming
+pub const FLASH_WORD: usize = 32;
+
+// Keys to unlock the control and option registers
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+pub(super) const OPTKEY1: u32 = 0x0819_2A3B;
+pub(super) const OPTKEY2: u32 = 0x4C5D_6E7F;
+
+/// FLASH_CRx.PSIZE for 64-bit parallelism
+const PSIZE_X64: u8 = 0b11;
+
+/// Flash programming error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The offset is not a multiple of [`FLASH_WORD`]
+    Alignment,
+    /// The sector or address range is outside the bank
+    OutOfRange,
+    /// The area is write protected
+    WriteProtection,
+    /// An erase or program operation failed
+    Programming,
+    /// The option bytes could not be changed
+    OptionBytes,
+    /// RDP level 2 is permanent, and is not programmed by this driver
+    RdpLevel2,
+    /// A boot address is not a multiple of 16kB, or is not in the flash,
+    /// the system memory, the SRAM or the TCM
+    BootAddress,
+}
+
+/// A flash bank, by the address at which it is mapped
+///
+/// The STM32H750 has a single bank.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Bank {
+    /// The bank mapped at `0x0800_0000`
+    Bank1,
+    /// The bank mapped at `0x0810_0000`
+    #[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+    Bank2,
+}
+
+impl Bank {
+    /// Returns the address at which this bank is mapped
+    pub fn address(&self) -> usize {
+        match self {
+            Bank::Bank1 => 0x0800_0000,
+            #[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+            Bank::Bank2 => 0x0810_0000,
+        }
+    }
+
+    /// Returns the size of each bank in bytes
+    pub fn size() -> usize {
+        FlashSize::get().bytes() / BANKS.len()
+    }
+
+    /// Returns the bank that contains `address`, if any
+    pub fn containing(address: usize) -> Option<Bank> {
+        BANKS.iter().copied().find(|bank| {
+            (bank.address()..bank.address() + Bank::size()).contains(&address)
+        })
+    }
+
+    /// Returns the bank that the CPU is executing from, or `None` if it is
+    /// executing from RAM
+    pub fn executing() -> Option<Bank> {
+        Bank::containing(cortex_m::register::pc::read() as usize)
+    }
+}
+
+/// The flash banks of the part
+#[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+const BANKS: [Bank; 2] = [Bank::Bank1, Bank::Bank2];
+#[cfg(any(feature = "stm32h750", feature = "stm32h750v"))]
+const BANKS: [Bank; 1] = [Bank::Bank1];
+
+// Accesses the KEYRx, CRx, SRx and CCRx registers of each bank. The fields
+// of bank `n` end in `n`
+macro_rules! bank_registers {
+    ($($(#[$meta:meta])* $Bank:ident: $n:literal,)+) => {
+        paste::item! {
+            // NOTE(unsafe) The registers of a bank are owned by the
+            // `UnlockedBank` or `FirmwareUpdate` that calls these
+            impl Bank {
+                /// Unlocks the control register of the bank
+                pub(super) fn unlock(&self) {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => {
+                                if flash.[<cr $n>].read().[<lock $n>]().bit_is_set() {
+                                    flash.[<keyr $n>].write(|w| unsafe { w.bits(KEY1) });
+                                    flash.[<keyr $n>].write(|w| unsafe { w.bits(KEY2) });
+                                }
+                            }
+                        )+
+                    }
+                }
+
+                /// Locks the control register of the bank
+                pub(super) fn lock(&self) {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => flash.[<cr $n>].modify(|_, w| w.[<lock $n>]().set_bit()),
+                        )+
+                    }
+                }
+
+                /// Starts to erase `sector`, or the whole bank if `None`
+                pub(super) fn start_erase(&self, sector: Option<u8>) {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    // Refer to RM0433 Rev 7 - Chapter 4.3.10
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => {
+                                flash.[<cr $n>].modify(|_, w| unsafe {
+                                    let w = w.[<psize $n>]().bits(PSIZE_X64);
+                                    match sector {
+                                        Some(sector) => w
+                                            .[<ser $n>]().set_bit()
+                                            .[<snb $n>]().bits(sector),
+                                        None => w.[<ber $n>]().set_bit(),
+                                    }
+                                });
+                                flash.[<cr $n>].modify(|_, w| w.[<start $n>]().set_bit());
+                            }
+                        )+
+                    }
+                }
+
+                /// Enables programming of flash words
+                pub(super) fn enable_programming(&self) {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    // Refer to RM0433 Rev 7 - Chapter 4.3.9
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => flash.[<cr $n>].modify(|_, w| unsafe {
+                                w.[<pg $n>]().set_bit().[<psize $n>]().bits(PSIZE_X64)
+                            }),
+                        )+
+                    }
+                }
+
+                /// Ends an erase or program operation
+                pub(super) fn end_operation(&self) {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => flash.[<cr $n>].modify(|_, w| {
+                                w.[<pg $n>]()
+                                    .clear_bit()
+                                    .[<ser $n>]()
+                                    .clear_bit()
+                                    .[<ber $n>]()
+                                    .clear_bit()
+                            }),
+                        )+
+                    }
+                }
+
+                /// Returns `true` while an erase or program operation is
+                /// ongoing
+                pub(super) fn is_busy(&self) -> bool {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => {
+                                let sr = flash.[<sr $n>].read();
+                                sr.[<bsy $n>]().bit_is_set() || sr.[<qw $n>]().bit_is_set()
+                            }
+                        )+
+                    }
+                }
+
+                /// Returns the result of the last operation, and clears
+                /// the error and end of operation flags
+                pub(super) fn status(&self) -> Result<(), Error> {
+                    let flash = unsafe { &*FLASH::ptr() };
+                    match self {
+                        $(
+                            $(#[$meta])*
+                            Bank::$Bank => {
+                                let sr = flash.[<sr $n>].read();
+                                flash.[<ccr $n>].write(|w| {
+                                    w.[<clr_eop $n>]()
+                                        .set_bit()
+                                        .[<clr_wrperr $n>]()
+                                        .set_bit()
+                                        .[<clr_pgserr $n>]()
+                                        .set_bit()
+                                        .[<clr_strberr $n>]()
+                                        .set_bit()
+                                        .[<clr_incerr $n>]()
+                                        .set_bit()
+                                        .[<clr_operr $n>]()
+                                        .set_bit()
+                                        .[<clr_rdperr $n>]()
+                                        .set_bit()
+                                        .[<clr_rdserr $n>]()
+                                        .set_bit()
+                                        .[<clr_sneccerr $n>]()
+                                        .set_bit()
+                                        .[<clr_dbeccerr $n>]()
+                                        .set_bit()
+                                });
+
+                                if sr.[<wrperr $n>]().bit_is_set() {
+                                    Err(Error::WriteProtection)
+                                } else if sr.[<pgserr $n>]().bit_is_set()
+                                    || sr.[<strberr $n>]().bit_is_set()
+                                    || sr.[<incerr $n>]().bit_is_set()
+                                    || sr.[<operr $n>]().bit_is_set()
+                                    || sr.[<rdperr $n>]().bit_is_set()
+                                    || sr.[<rdserr $n>]().bit_is_set()
+                                    || sr.[<sneccerr $n>]().bit_is_set()
+                                    || sr.[<dbeccerr $n>]().bit_is_set()
+                                {
+                                    Err(Error::Programming)
+                                } else {
+                                    Ok(())
+                                }
+                            }
+                        )+
+                    }
+                }
+            }
+        }
+    };
+}
+
+bank_registers! {
+    Bank1: 1,
+    #[cfg(not(any(feature = "stm32h750", feature = "stm32h750v")))]
+    Bank2: 2,
+}
+
+impl Flash {
+    /// Unlocks `bank` for erasing and programming
+    pub fn unlocked(&mut self, bank: Bank) -> UnlockedBank<'_> {
+        // The control register of the bank is owned by the returned value.
+        // Refer to RM0433 Rev 7 - Chapter 4.5.1
+        bank.unlock();
+
+        UnlockedBank { _flash: self, bank }
+    }
+}
+
+/// An unlocked flash bank. Locked again when dropped
+pub struct UnlockedBank<'a> {
+    _flash: &'a mut Flash,
+    bank: Bank,
+}
+
+impl<'a> UnlockedBank<'a> {
+    /// Erases `sector` of the bank
+    pub fn erase_sector(&mut self, sector: u8) -> Result<(), Error> {
+        if usize::from(sector) >= Bank::size() / SECTOR_SIZE {
+            return Err(Error::OutOfRange);
+        }
+
+        self.erase(Some(sector))
+    }
+
+    /// Erases the whole bank
+    pub fn erase_bank(&mut self) -> Result<(), Error> {
+        self.erase(None)
+    }
+
+    /// Starts to erase `sector` of the bank, without waiting for the erase
+    /// to complete. The erase is completed with [`poll`](Self::poll)
+    ///
+    /// If the CPU is executing from this bank, it would stall at its next
+    /// instruction fetch anyway, so this waits for the erase to complete.
+    /// Otherwise it returns immediately.
+    pub fn start_erase_sector(&mut self, sector: u8) -> Result<(), Error> {
+        if usize::from(sector) >= Bank::size() / SECTOR_SIZE {
+            return Err(Error::OutOfRange);
+        }
+
+        if Bank::executing() == Some(self.bank) {
+            return self.erase(Some(sector));
+        }
+
+        self.bank.start_erase(Some(sector));
+        Ok(())
+    }
+
+    /// Returns `true` while an erase or program operation on the bank is
+    /// ongoing
+    pub fn is_busy(&self) -> bool {
+        self.bank.is_busy()
+    }
+
+    /// Completes an operation started by
+    /// [`start_erase_sector`](Self::start_erase_sector), and checks for
+    /// errors
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        if self.is_busy() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let result = self.bank.status();
+        self.bank.end_operation();
+        result.map_err(nb::Error::Other)
+    }
+
+    /// Programs `data` at `offset` bytes from the start of the bank, which
+    /// must be a multiple of [`FLASH_WORD`]. A partial last flash word is
+    /// padded with `0xFF`
+    ///
+    /// The area must have been erased.
+    pub fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        if offset % FLASH_WORD != 0 {
+            return Err(Error::Alignment);
+        }
+        if offset
+            .checked_add(data.len())
+            .map_or(true, |end| end > Bank::size())
+        {
+            return Err(Error::OutOfRange);
+        }
+
+        let mut address = self.bank.address() + offset;
+        for chunk in data.chunks(FLASH_WORD) {
+            let mut buffer = [0xFF; FLASH_WORD];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+
+            self.program_word(address, &buffer)?;
+            address += FLASH_WORD;
+        }
+
+        Ok(())
+    }
+
+    /// Programs one flash word at `address`
+    fn program_word(
+        &mut self,
+        address: usize,
+        buffer: &[u8; FLASH_WORD],
+    ) -> Result<(), Error> {
+        self.bank.enable_programming();
+
+        // NOTE(unsafe) The bank is unlocked and owned by self, and the
+        // address is within the bank
+        let dst = address as *mut u32;
+        for (i, word) in buffer.chunks(4).enumerate() {
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            unsafe { ptr::write_volatile(dst.add(i), word) };
+        }
+        cortex_m::asm::dsb();
+
+        let result = self.wait();
+        self.bank.end_operation();
+        result
+    }
+
+    /// Erases `sector`, or the whole bank if `None`
+    fn erase(&mut self, sector: Option<u8>) -> Result<(), Error> {
+        self.bank.start_erase(sector);
+
+        let result = self.wait();
+        self.bank.end_operation();
+        result
+    }
+
+    /// Waits for the current operation on the bank to complete, and checks
+    /// for errors
+    fn wait(&self) -> Result<(), Error> {
+        while self.bank.is_busy() {}
+        self.bank.status()
+    }
+}
+
+impl<'a> Drop for UnlockedBank<'a> {
+    fn drop(&mut self) {
+        self.bank.lock();
+    }
+}
@@ -0,0 +1,314 @@
+//! Firmware update into the inactive flash bank
+//!
+//! On dual bank parts, a new firmware image can be programmed into the bank
+//! that is not being executed from, verified, and then started by swapping
+//! the banks and resetting. The STM32H750 has a single bank, and does not
+//! have this module.
+//!
+//! The inactive bank is always mapped at `0x0810_0000`, whether or not the
+//! banks are currently swapped. The image is checked using the CRC
+//! peripheral against a standard CRC-32 (as used by zlib, Ethernet, etc.) of
+//! the image.
+//!
+//! # Examples
+//!
+//! ```
+//! use stm32h7xx_hal::flash::update::FirmwareUpdate;
+//!
+//! let flash = dp.FLASH.constrain();
+//! let mut update = FirmwareUpdate::new(flash, dp.CRC, ccdr.peripheral.CRC);
+//!
+//! // Erase enough of the inactive bank for the image
+//! update.begin(image_len).unwrap();
+//!
+//! while let Some(chunk) = receive_chunk() {
+//!     update.write(chunk).unwrap();
+//! }
+//! update.finish().unwrap();
+//!
+//! // Check the image, then swap the banks and reset
+//! update.verify(image_crc).unwrap();
+//! update.swap_and_reset().unwrap();
+//! ```
+//!
+//! The flash bank, and the option bytes if they were unlocked, are locked
+//! again when the `FirmwareUpdate` is dropped.
+//!
+//! # Data cache
+//!
+//! [`verify`](FirmwareUpdate::verify) reads the image through the AXI bus. If
+//! the data cache is enabled, invalidate it for the inactive bank first.
+
+use core::convert::Infallible;
+use core::mem::ManuallyDrop;
+use core::ptr;
+
+use cortex_m::peripheral::SCB;
+
+use super::program::{self, Bank, FLASH_WORD, OPTKEY1, OPTKEY2, SECTOR_SIZE};
+use super::Flash;
+use crate::crc::{self, Crc, CrcExt};
+use crate::rcc::rec;
+use crate::stm32::{CRC, FLASH};
+
+/// Address of the inactive bank
+const INACTIVE_BANK: usize = 0x0810_0000;
+/// The registers of the inactive bank are always those of bank 2
+const BANK: Bank = Bank::Bank2;
+
+/// Firmware update error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The image does not fit in the inactive bank
+    ImageTooLarge,
+    /// More data was written than was passed to `begin`
+    Overflow,
+    /// The inactive bank is write protected
+    WriteProtection,
+    /// An erase or program operation failed
+    Programming,
+    /// The CRC of the programmed image does not match
+    Crc { expected: u32, actual: u32 },
+    /// The image has not been verified
+    NotVerified,
+    /// The option bytes could not be changed
+    OptionBytes,
+}
+
+/// Programs a firmware image into the inactive flash bank
+pub struct FirmwareUpdate {
+    flash: Flash,
+    crc: Crc,
+    /// Size of the image
+    size: usize,
+    /// Bytes programmed so far
+    offset: usize,
+    /// Partial flash word
+    buffer: [u8; FLASH_WORD],
+    buffered: usize,
+    verified: bool,
+    /// The option bytes were unlocked by `swap_and_reset`
+    options_unlocked: bool,
+}
+
+impl FirmwareUpdate {
+    /// Returns a `FirmwareUpdate` object, taking ownership of the flash and
+    /// CRC peripherals
+    pub fn new(flash: Flash, crc: CRC, prec: rec::Crc) -> Self {
+        // CRC-32 with reflected input and output
+        let config = crc::Config::new()
+            .polynomial(crc::Polynomial::Bits32(0x04C1_1DB7))
+            .initial_value(0xFFFF_FFFF)
+            .input_reversal(crc::InputReversal::Byte)
+            .output_reversal(true)
+            .output_xor(0xFFFF_FFFF);
+
+        FirmwareUpdate {
+            flash,
+            crc: crc.crc(config, prec),
+            size: 0,
+            offset: 0,
+            buffer: [0xFF; FLASH_WORD],
+            buffered: 0,
+            verified: false,
+            options_unlocked: false,
+        }
+    }
+
+    /// Returns the size of each flash bank in bytes
+    pub fn bank_size() -> usize {
+        Bank::size()
+    }
+
+    /// Returns `true` if the banks are currently swapped, so that bank 2 is
+    /// mapped at `0x0800_0000`
+    pub fn is_swapped(&self) -> bool {
+        let flash = unsafe { &*FLASH::ptr() };
+        flash.optsr_cur.read().swap_bank_opt().bit_is_set()
+    }
+
+    /// Starts an update with an image of `size` bytes, erasing the sectors
+    /// of the inactive bank that the image will occupy
+    pub fn begin(&mut self, size: usize) -> Result<(), Error> {
+        if size > Self::bank_size() {
+            return Err(Error::ImageTooLarge);
+        }
+
+        self.size = size;
+        self.offset = 0;
+        self.buffered = 0;
+        self.verified = false;
+
+        BANK.unlock();
+
+        let sectors = (size + SECTOR_SIZE - 1) / SECTOR_SIZE;
+        for sector in 0..sectors as u8 {
+            BANK.start_erase(Some(sector));
+
+            let result = self.wait();
+            BANK.end_operation();
+            if let Err(e) = result {
+                self.lock();
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the next part of the image. Data is programmed one flash word
+    /// (32 bytes) at a time, so the last part of `data` may be buffered
+    /// until the next call or [`finish`](FirmwareUpdate::finish)
+    pub fn write(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        if self.offset + self.buffered + data.len() > self.size {
+            return Err(Error::Overflow);
+        }
+
+        while !data.is_empty() {
+            let n = core::cmp::min(FLASH_WORD - self.buffered, data.len());
+            self.buffer[self.buffered..self.buffered + n]
+                .copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+
+            if self.buffered == FLASH_WORD {
+                self.program_word()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Programs any buffered data, padded with `0xFF`, and locks the flash
+    pub fn finish(&mut self) -> Result<(), Error> {
+        if self.buffered > 0 {
+            for b in self.buffer[self.buffered..].iter_mut() {
+                *b = 0xFF;
+            }
+            self.program_word()?;
+        }
+        self.lock();
+
+        Ok(())
+    }
+
+    /// Calculates the CRC-32 of the programmed image using the CRC
+    /// peripheral, and compares it with `expected`
+    pub fn verify(&mut self, expected: u32) -> Result<(), Error> {
+        self.crc.reset();
+
+        // The CRC unit takes the most significant byte of a word first, so
+        // swap the bytes of each word to keep the order of the image
+        let image = INACTIVE_BANK as *const u8;
+        let words = self.size / 4;
+        for i in 0..words {
+            let word =
+                unsafe { ptr::read_volatile((image as *const u32).add(i)) };
+            self.crc.update_u32(&[word.swap_bytes()]);
+        }
+
+        for i in words * 4..self.size {
+            let byte = unsafe { ptr::read_volatile(image.add(i)) };
+            self.crc.update(&[byte]);
+        }
+
+        let actual = self.crc.finalize();
+        if actual != expected {
+            return Err(Error::Crc { expected, actual });
+        }
+
+        self.verified = true;
+        Ok(())
+    }
+
+    /// Swaps the flash banks and resets, starting the new image. Only
+    /// returns if the image has not been verified, or the option bytes could
+    /// not be changed
+    pub fn swap_and_reset(mut self) -> Result<Infallible, Error> {
+        if !self.verified {
+            return Err(Error::NotVerified);
+        }
+
+        // Refer to RM0433 Rev 7 - Chapter 4.3.13
+        let flash = unsafe { &*FLASH::ptr() };
+        if flash.optcr.read().optlock().bit_is_set() {
+            flash.optkeyr.write(|w| unsafe { w.bits(OPTKEY1) });
+            flash.optkeyr.write(|w| unsafe { w.bits(OPTKEY2) });
+            self.options_unlocked = true;
+        }
+
+        let swapped = flash.optsr_cur.read().swap_bank_opt().bit_is_set();
+        flash
+            .optsr_prg
+            .modify(|_, w| w.swap_bank_opt().bit(!swapped));
+        flash.optcr.modify(|_, w| w.optstart().set_bit());
+        while flash.optsr_cur.read().opt_busy().bit_is_set() {}
+
+        if flash.optsr_cur.read().optchangeerr().bit_is_set() {
+            flash.optccr.write(|w| w.clr_optchangeerr().set_bit());
+            return Err(Error::OptionBytes);
+        }
+
+        SCB::sys_reset();
+    }
+
+    /// Releases the flash and CRC peripherals
+    pub fn free(mut self) -> (Flash, CRC) {
+        self.lock();
+
+        // NOTE(unsafe) The fields are moved out exactly once, and `drop`
+        // is not run
+        let update = ManuallyDrop::new(self);
+        let (flash, crc) =
+            unsafe { (ptr::read(&update.flash), ptr::read(&update.crc)) };
+        (flash, crc.free())
+    }
+
+    /// Programs the buffered flash word at the current offset
+    fn program_word(&mut self) -> Result<(), Error> {
+        BANK.enable_programming();
+
+        let dst = (INACTIVE_BANK + self.offset) as *mut u32;
+        for (i, word) in self.buffer.chunks(4).enumerate() {
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            unsafe { ptr::write_volatile(dst.add(i), word) };
+        }
+        cortex_m::asm::dsb();
+
+        let result = self.wait();
+        BANK.end_operation();
+
+        self.offset += FLASH_WORD;
+        self.buffered = 0;
+        result
+    }
+
+    /// Waits for the current operation on the inactive bank to complete,
+    /// and checks for errors
+    fn wait(&self) -> Result<(), Error> {
+        while BANK.is_busy() {}
+
+        BANK.status().map_err(|e| match e {
+            program::Error::WriteProtection => Error::WriteProtection,
+            _ => Error::Programming,
+        })
+    }
+
+    /// Locks the inactive bank, and the option bytes if they were unlocked
+    fn lock(&mut self) {
+        BANK.lock();
+
+        if self.options_unlocked {
+            let flash = unsafe { &*FLASH::ptr() };
+            flash.optcr.modify(|_, w| w.optlock().set_bit());
+            self.options_unlocked = false;
+        }
+    }
+}
+
+impl Drop for FirmwareUpdate {
+    fn drop(&mut self) {
+        self.lock();
+    }
+}
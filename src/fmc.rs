@@ -25,6 +25,42 @@
 //!
 //! `sdram` usage is described
 //! [here](https://github.com/stm32-rs/stm32-fmc#usage).
+//!
+//! ## 8080 LCD
+//!
+//! A parallel LCD with an 8080 interface can be connected to one of the
+//! four NOR/SRAM sub-banks, with its D/C (RS) pin on an address line. It
+//! shares the data bus with an SDRAM on the same FMC. The FMC performs one
+//! external access at a time, so the banks never drive the bus together,
+//! but some care is needed:
+//!
+//! * After a read of the LCD, its data outputs can take longer to turn off
+//!   than the next access of the SDRAM takes to start. The bus turnaround
+//!   phase of [`LcdConfig`] inserts idle cycles after each access to the
+//!   LCD
+//! * While the LCD holds NWAIT, the FMC is stalled and the SDRAM cannot be
+//!   accessed. Only enable [`nwait`](LcdConfig::nwait) for LCDs that need
+//!   it, and release the bus between bursts with
+//!   [`write_pixels`](Lcd::write_pixels)
+//! * The bank is Normal memory in the default memory map, so writes to it
+//!   can be cached, merged and reordered. Mark it as Device memory with
+//!   the [MPU](crate::mpu)
+//!
+//! Configure the LCD before the FMC is moved into the SDRAM driver.
+//!
+//! ```
+//! let mut fmc = dp.FMC.fmc(ccdr.peripheral.FMC, &ccdr.clocks);
+//!
+//! // RS on A16, NE1 as the chip select
+//! let mut lcd = fmc.lcd_8080(SramBank::Ne1, LcdConfig::new(16));
+//! mpu::configure(&mut cp.MPU, &mut cp.SCB, &[lcd.region()]);
+//!
+//! let mut sdram = Sdram::new(fmc, sdram_pins, sdram_chip);
+//! let ram = sdram.init(&mut delay);
+//!
+//! lcd.write_command(0x2C);
+//! lcd.write_pixels(framebuffer);
+//! ```
 
 // From stm32_fmc
 use stm32_fmc::FmcPeripheral;
@@ -32,6 +68,7 @@ use stm32_fmc::{
     AddressPinSet, PinsSdram, Sdram, SdramChip, SdramPinSet, SdramTargetBank,
 };
 
+use crate::mpu::{Attributes, Region};
 use crate::rcc::{rec, rec::ResetEnable, CoreClocks};
 use crate::stm32;
 use crate::time::Hertz;
@@ -125,6 +162,215 @@ impl FmcExt for stm32::FMC {
     }
 }
 
+/// One of the four sub-banks of NOR/SRAM bank 1, selected by NE1 - NE4
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SramBank {
+    /// Chip select NE1, at 0x6000_0000
+    Ne1,
+    /// Chip select NE2, at 0x6400_0000
+    Ne2,
+    /// Chip select NE3, at 0x6800_0000
+    Ne3,
+    /// Chip select NE4, at 0x6C00_0000
+    Ne4,
+}
+
+impl SramBank {
+    fn index(self) -> usize {
+        match self {
+            SramBank::Ne1 => 0,
+            SramBank::Ne2 => 1,
+            SramBank::Ne3 => 2,
+            SramBank::Ne4 => 3,
+        }
+    }
+
+    /// Returns the base address of the sub-bank
+    pub fn base(self) -> u32 {
+        0x6000_0000 + self.index() as u32 * SRAM_BANK_SIZE
+    }
+
+    /// Returns an MPU region that covers the sub-bank as Device memory
+    pub fn region(self) -> Region {
+        Region {
+            base: self.base(),
+            size: SRAM_BANK_SIZE,
+            attributes: Attributes::Device,
+        }
+    }
+}
+
+/// Size of each NOR/SRAM sub-bank
+const SRAM_BANK_SIZE: u32 = 0x0400_0000;
+
+/// Configuration of an 8080 LCD on a NOR/SRAM sub-bank, with a 16-bit data
+/// bus
+///
+/// The timings are in cycles of the FMC kernel clock. The write cycle of
+/// the LCD is `address_setup + data_setup + 1` cycles.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LcdConfig {
+    /// Address line A0 - A24 connected to the D/C (RS) pin of the LCD
+    pub rs_address_line: u8,
+    /// Address setup phase, 0 - 15 cycles
+    pub address_setup: u8,
+    /// Data phase, 1 - 255 cycles. This is the width of the write or read
+    /// strobe
+    pub data_setup: u8,
+    /// Bus turnaround phase after each access, 0 - 15 cycles, before
+    /// another bank can use the bus
+    pub bus_turnaround: u8,
+    /// Extends accesses while the LCD holds NWAIT low
+    pub nwait: bool,
+}
+
+impl LcdConfig {
+    /// Configuration with the D/C (RS) pin on the address line
+    /// `rs_address_line`, conservative timings and no NWAIT
+    pub fn new(rs_address_line: u8) -> Self {
+        LcdConfig {
+            rs_address_line,
+            address_setup: 4,
+            data_setup: 8,
+            bus_turnaround: 2,
+            nwait: false,
+        }
+    }
+}
+
+/// 8080 LCD on a NOR/SRAM sub-bank of the FMC
+pub struct Lcd {
+    bank: SramBank,
+    /// Address with RS low
+    command: *mut u16,
+    /// Address with RS high
+    data: *mut u16,
+}
+
+unsafe impl Send for Lcd {}
+
+/// Number of words written to the LCD before the bus is released
+const LCD_BURST: usize = 64;
+
+impl Lcd {
+    /// Writes a command, with RS low
+    pub fn write_command(&mut self, command: u16) {
+        // NOTE(unsafe) The address is in the sub-bank owned by self
+        unsafe { core::ptr::write_volatile(self.command, command) };
+    }
+
+    /// Writes a data word, with RS high
+    pub fn write_data(&mut self, data: u16) {
+        // NOTE(unsafe) As above
+        unsafe { core::ptr::write_volatile(self.data, data) };
+    }
+
+    /// Reads a data word, with RS high
+    pub fn read_data(&mut self) -> u16 {
+        // NOTE(unsafe) As above
+        unsafe { core::ptr::read_volatile(self.data) }
+    }
+
+    /// Writes a command followed by its parameters
+    pub fn command(&mut self, command: u16, parameters: &[u16]) {
+        self.write_command(command);
+        for &parameter in parameters {
+            self.write_data(parameter);
+        }
+    }
+
+    /// Writes `pixels` as data. Every 64 words, waits for the writes to
+    /// complete, so that other masters can access the SDRAM between bursts
+    pub fn write_pixels(&mut self, pixels: &[u16]) {
+        for burst in pixels.chunks(LCD_BURST) {
+            for &pixel in burst {
+                self.write_data(pixel);
+            }
+            cortex_m::asm::dsb();
+        }
+    }
+
+    /// Returns an MPU region that covers the sub-bank of the LCD as Device
+    /// memory
+    pub fn region(&self) -> Region {
+        self.bank.region()
+    }
+}
+
+impl FMC {
+    /// Configures the NOR/SRAM sub-bank `bank` for an 8080 LCD, and enables
+    /// the FMC
+    ///
+    /// The pins are not checked. They must be in their FMC alternate
+    /// function: D0 - D15, NOE, NWE, the NE pin of `bank`, the address line
+    /// of RS and NWAIT if it is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a field of `config` is out of range
+    pub fn lcd_8080(&mut self, bank: SramBank, config: LcdConfig) -> Lcd {
+        assert!(config.rs_address_line < 25, "No such address line");
+        assert!(config.address_setup < 16, "Address setup too long");
+        assert!(config.data_setup > 0, "Data phase too short");
+        assert!(config.bus_turnaround < 16, "Bus turnaround too long");
+
+        // NOTE(unsafe) FMC_BCRx and FMC_BTRx of the sub-bank are not used
+        // by the SDRAM controller. The global fields of FMC_BCR1 are kept
+        macro_rules! sram_bank {
+            ($bcr:ident, $btr:ident) => {{
+                self.fmc.$btr.write(|w| unsafe {
+                    w.addset()
+                        .bits(config.address_setup)
+                        .datast()
+                        .bits(config.data_setup)
+                        .busturn()
+                        .bits(config.bus_turnaround)
+                });
+                self.fmc.$bcr.modify(|_, w| unsafe {
+                    w.muxen()
+                        .clear_bit()
+                        .mtyp()
+                        .bits(0b00) // SRAM
+                        .mwid()
+                        .bits(0b01) // 16 bits
+                        .faccen()
+                        .clear_bit()
+                        .bursten()
+                        .clear_bit()
+                        .wren()
+                        .set_bit()
+                        .waiten()
+                        .bit(config.nwait)
+                        .extmod()
+                        .clear_bit()
+                        .asyncwait()
+                        .bit(config.nwait)
+                        .cburstrw()
+                        .clear_bit()
+                        .mbken()
+                        .set_bit()
+                });
+            }};
+        }
+        match bank {
+            SramBank::Ne1 => sram_bank!(bcr1, btr1),
+            SramBank::Ne2 => sram_bank!(bcr2, btr2),
+            SramBank::Ne3 => sram_bank!(bcr3, btr3),
+            SramBank::Ne4 => sram_bank!(bcr4, btr4),
+        }
+        self.fmc.bcr1.modify(|_, w| w.fmcen().set_bit());
+
+        // With a 16-bit bus, HADDR[n + 1] drives A[n]
+        let base = bank.base();
+        let rs = 1 << (config.rs_address_line + 1);
+        Lcd {
+            bank,
+            command: base as *mut u16,
+            data: (base | rs) as *mut u16,
+        }
+    }
+}
+
 unsafe impl FmcPeripheral for FMC {
     const REGISTERS: *const () = stm32::FMC::ptr() as *const ();
 
@@ -0,0 +1,70 @@
+//! Fixed capacity list of values found by a driver
+//!
+//! Drivers that report a varying number of values without allocating, such
+//! as the events of a debouncer, store them in a [`Found`] and return it as
+//! an iterator.
+
+use core::marker::PhantomData;
+
+/// Values found by a driver, in the order they were pushed. The array `A`
+/// of `Option<T>` sets the capacity
+#[derive(Clone, Debug)]
+pub(crate) struct Found<T, A> {
+    found: A,
+    len: usize,
+    index: usize,
+    _item: PhantomData<T>,
+}
+
+impl<T, A> Found<T, A> {
+    /// Creates an empty list in `empty`, which must be all `None`
+    pub(crate) fn new(empty: A) -> Self {
+        Found {
+            found: empty,
+            len: 0,
+            index: 0,
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<T, A: AsMut<[Option<T>]>> Found<T, A> {
+    /// Appends `value`. Values beyond the capacity are dropped
+    pub(crate) fn push(&mut self, value: T) {
+        if let Some(slot) = self.found.as_mut().get_mut(self.len) {
+            *slot = Some(value);
+            self.len += 1;
+        }
+    }
+}
+
+impl<T: Copy, A: AsRef<[Option<T>]>> Iterator for Found<T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let value = *self.found.as_ref().get(self.index)?;
+        self.index += 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Found;
+
+    #[test]
+    fn push_and_iterate() {
+        let mut found = Found::new([None; 3]);
+        found.push(1);
+        found.push(2);
+        assert_eq!(found.clone().collect::<Vec<u8>>(), [1, 2]);
+
+        // Values beyond the capacity are dropped
+        found.push(3);
+        found.push(4);
+        assert_eq!(found.collect::<Vec<u8>>(), [1, 2, 3]);
+
+        let empty: Found<u8, _> = Found::new([None; 3]);
+        assert_eq!(empty.count(), 0);
+    }
+}
@@ -0,0 +1,361 @@
+//! Chrom-GRC (GFXMMU)
+//!
+//! The GFXMMU maps up to four virtual framebuffers onto physical memory. Each
+//! virtual framebuffer has 1024 lines of 256 (or 192) blocks of 16 bytes. For
+//! each line, a look-up table (LUT) gives the first and last visible block,
+//! and where the visible blocks are stored in physical memory. Blocks outside
+//! the visible range are not stored at all, and read as a default value.
+//!
+//! This is useful for non-rectangular displays, such as round watch faces,
+//! where a significant fraction of a rectangular framebuffer would never be
+//! seen.
+//!
+//! Masters such as the LTDC, DMA2D and the CPU access the framebuffer through
+//! its virtual address, which has a constant line pitch of
+//! [`line_pitch`](Gfxmmu::line_pitch) bytes.
+//!
+//! Only available on RM0455 parts.
+//!
+//! # Example
+//!
+//! ```
+//! let mut gfxmmu = dp.GFXMMU.gfxmmu(ccdr.peripheral.GFXMMU, BlocksPerLine::Blocks256);
+//!
+//! // Round display of 480x480 ARGB8888 pixels. Each 16-byte block holds 4
+//! // pixels
+//! let physical_size = gfxmmu.configure_lut(0, (0..480).map(|y| {
+//!     let half_chord = visible_half_width(y); // pixels
+//!     let first = (240 - half_chord) * 4 / 16;
+//!     let last = (240 + half_chord) * 4 / 16;
+//!     Some((first as u8, last as u8))
+//! })).unwrap();
+//!
+//! unsafe {
+//!     gfxmmu.set_physical_buffer(Buffer::Buffer0, FRAMEBUFFER.as_ptr() as u32);
+//!
+//!     // Use the virtual buffer as the LTDC framebuffer
+//!     let fb = gfxmmu.virtual_buffer(Buffer::Buffer0) as *const u32;
+//!     layer.enable(fb, PixelFormat::ARGB8888);
+//!     layer.resize_buffer_pitch(gfxmmu.line_pitch() / 4);
+//! }
+//! ```
+
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ptr;
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::GFXMMU;
+
+/// Number of lines in each virtual buffer
+pub const LINES: u16 = 1024;
+
+/// Size of a block, in bytes
+const BLOCK_SIZE: u32 = 16;
+
+/// Offset of the LUT from the GFXMMU base address. RM0455 Rev 6 Table 114
+const LUT_OFFSET: usize = 0x1000;
+
+/// Base address of the virtual buffers. RM0455 Rev 6 Section 14.3.2
+const VIRTUAL_BUFFER_BASE: u32 = 0x2500_0000;
+
+/// Size of each virtual buffer, in bytes
+const VIRTUAL_BUFFER_SIZE: u32 = 0x40_0000;
+
+/// Mask of the line offset in the LUT (LUTxH.LO)
+const LINE_OFFSET_MASK: u32 = 0x3F_FFF0;
+
+/// GFXMMU error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The first or last block is outside of the line
+    BlockRange,
+    /// The LUT has only 1024 lines
+    LineRange,
+    /// The offset of the line in physical memory does not fit in the LUT
+    OffsetRange,
+}
+
+/// Number of 16-byte blocks in each virtual line
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlocksPerLine {
+    /// 256 blocks, 4096 bytes per line
+    Blocks256,
+    /// 192 blocks, 3072 bytes per line
+    Blocks192,
+}
+
+/// Virtual buffers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Buffer {
+    Buffer0 = 0,
+    Buffer1 = 1,
+    Buffer2 = 2,
+    Buffer3 = 3,
+}
+
+/// GFXMMU interrupt events
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    /// An access to this virtual buffer overflowed the 8 Mbyte physical
+    /// memory window
+    Overflow(Buffer),
+    /// An access was made to an address outside of the virtual buffers
+    AddressMismatch,
+}
+
+/// Extension trait for the GFXMMU peripheral
+pub trait GfxmmuExt: Sized {
+    type Rec: ResetEnable;
+
+    fn gfxmmu(self, prec: Self::Rec, blocks: BlocksPerLine) -> Gfxmmu;
+}
+
+impl GfxmmuExt for GFXMMU {
+    type Rec = rec::Gfxmmu;
+
+    fn gfxmmu(self, prec: rec::Gfxmmu, blocks: BlocksPerLine) -> Gfxmmu {
+        Gfxmmu::new(self, prec, blocks)
+    }
+}
+
+/// Graphic MMU
+pub struct Gfxmmu {
+    rb: GFXMMU,
+    blocks: BlocksPerLine,
+}
+
+impl Gfxmmu {
+    /// Enable and reset the GFXMMU. All lines of the LUT are initially
+    /// disabled
+    pub fn new(
+        gfxmmu: GFXMMU,
+        prec: rec::Gfxmmu,
+        blocks: BlocksPerLine,
+    ) -> Self {
+        prec.enable().reset();
+
+        gfxmmu
+            .cr
+            .modify(|_, w| w._192bm().bit(blocks == BlocksPerLine::Blocks192));
+
+        Gfxmmu { rb: gfxmmu, blocks }
+    }
+
+    /// Number of bytes from the start of one virtual line to the start of
+    /// the next
+    pub fn line_pitch(&self) -> u32 {
+        match self.blocks {
+            BlocksPerLine::Blocks256 => 256 * BLOCK_SIZE,
+            BlocksPerLine::Blocks192 => 192 * BLOCK_SIZE,
+        }
+    }
+
+    /// Returns the address of the virtual `buffer`
+    pub fn virtual_buffer(&self, buffer: Buffer) -> *mut u8 {
+        (VIRTUAL_BUFFER_BASE + VIRTUAL_BUFFER_SIZE * buffer as u32) as *mut u8
+    }
+
+    /// Set the value that is returned when reading blocks that are not
+    /// visible
+    pub fn set_default_value(&mut self, value: u32) {
+        self.rb.dvr.write(|w| unsafe { w.bits(value) });
+    }
+
+    /// Set the physical memory that backs the virtual `buffer`. `address`
+    /// must be aligned to 16 bytes.
+    ///
+    /// # Safety
+    ///
+    /// The memory from `address` must be large enough for all the visible
+    /// blocks in the LUT, as returned by
+    /// [`configure_lut`](Self::configure_lut), and must not be used for
+    /// anything else while the virtual buffer is in use.
+    pub unsafe fn set_physical_buffer(&mut self, buffer: Buffer, address: u32) {
+        assert!(address % BLOCK_SIZE == 0);
+
+        // PBBA is bits 31:23, PBO is bits 22:4, so the register value is
+        // just the address
+        match buffer {
+            Buffer::Buffer0 => self.rb.b0cr.write(|w| w.bits(address)),
+            Buffer::Buffer1 => self.rb.b1cr.write(|w| w.bits(address)),
+            Buffer::Buffer2 => self.rb.b2cr.write(|w| w.bits(address)),
+            Buffer::Buffer3 => self.rb.b3cr.write(|w| w.bits(address)),
+        }
+    }
+
+    /// Configure a single `line` of the LUT. If `blocks` is `None` the line
+    /// is disabled, otherwise `blocks` gives the first and last visible
+    /// blocks. `offset` is the offset in bytes from the physical buffer base
+    /// address where the first visible block of this line is stored.
+    ///
+    /// Returns [`Error::OffsetRange`] if `offset` is not within the 4 Mbyte
+    /// that the LUT can address.
+    pub fn configure_line(
+        &mut self,
+        line: u16,
+        blocks: Option<(u8, u8)>,
+        offset: u32,
+    ) -> Result<(), Error> {
+        if line >= LINES {
+            return Err(Error::LineRange);
+        }
+
+        let (lutl, luth) = match blocks {
+            Some((first, last)) => {
+                let max = match self.blocks {
+                    BlocksPerLine::Blocks256 => 255,
+                    BlocksPerLine::Blocks192 => 191,
+                };
+                if first > last || last > max {
+                    return Err(Error::BlockRange);
+                }
+                if offset > LINE_OFFSET_MASK {
+                    return Err(Error::OffsetRange);
+                }
+
+                // The line offset is the offset of block 0 of this line,
+                // which may be negative. RM0455 Rev 6 Section 14.3.3
+                let line_offset = offset
+                    .wrapping_sub(first as u32 * BLOCK_SIZE)
+                    & LINE_OFFSET_MASK;
+
+                ((last as u32) << 16 | (first as u32) << 8 | 1, line_offset)
+            }
+            None => (0, 0),
+        };
+
+        // unsafe: The LUT is not defined in the PAC. Write two words within
+        // the LUT owned by this peripheral
+        unsafe {
+            let lut = (GFXMMU::ptr() as *mut u8).add(LUT_OFFSET) as *mut u32;
+            ptr::write_volatile(lut.add(2 * line as usize), lutl);
+            ptr::write_volatile(lut.add(2 * line as usize + 1), luth);
+        }
+
+        Ok(())
+    }
+
+    /// Configure consecutive lines of the LUT starting from `first_line`,
+    /// storing the visible blocks of each line contiguously in physical
+    /// memory. Each item of `lines` gives the first and last visible blocks
+    /// of a line, or `None` if the line is not visible.
+    ///
+    /// Returns the size in bytes of the physical memory that is required.
+    pub fn configure_lut<I>(
+        &mut self,
+        first_line: u16,
+        lines: I,
+    ) -> Result<u32, Error>
+    where
+        I: IntoIterator<Item = Option<(u8, u8)>>,
+    {
+        let mut offset: u32 = 0;
+
+        for (i, blocks) in lines.into_iter().enumerate() {
+            let line = u16::try_from(i)
+                .ok()
+                .and_then(|i| first_line.checked_add(i))
+                .ok_or(Error::LineRange)?;
+            self.configure_line(line, blocks, offset)?;
+
+            if let Some((first, last)) = blocks {
+                // configure_line checked that first <= last
+                let size = (last as u32 - first as u32 + 1) * BLOCK_SIZE;
+                offset = offset.checked_add(size).ok_or(Error::OffsetRange)?;
+            }
+        }
+
+        Ok(offset)
+    }
+
+    /// Start listening for `event`
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::Overflow(Buffer::Buffer0) => {
+                self.rb.cr.modify(|_, w| w.b0oie().set_bit())
+            }
+            Event::Overflow(Buffer::Buffer1) => {
+                self.rb.cr.modify(|_, w| w.b1oie().set_bit())
+            }
+            Event::Overflow(Buffer::Buffer2) => {
+                self.rb.cr.modify(|_, w| w.b2oie().set_bit())
+            }
+            Event::Overflow(Buffer::Buffer3) => {
+                self.rb.cr.modify(|_, w| w.b3oie().set_bit())
+            }
+            Event::AddressMismatch => {
+                self.rb.cr.modify(|_, w| w.ameie().set_bit())
+            }
+        }
+    }
+
+    /// Stop listening for `event`
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::Overflow(Buffer::Buffer0) => {
+                self.rb.cr.modify(|_, w| w.b0oie().clear_bit())
+            }
+            Event::Overflow(Buffer::Buffer1) => {
+                self.rb.cr.modify(|_, w| w.b1oie().clear_bit())
+            }
+            Event::Overflow(Buffer::Buffer2) => {
+                self.rb.cr.modify(|_, w| w.b2oie().clear_bit())
+            }
+            Event::Overflow(Buffer::Buffer3) => {
+                self.rb.cr.modify(|_, w| w.b3oie().clear_bit())
+            }
+            Event::AddressMismatch => {
+                self.rb.cr.modify(|_, w| w.ameie().clear_bit())
+            }
+        }
+        let _ = self.rb.cr.read();
+        let _ = self.rb.cr.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Returns `true` if the flag for `event` is set
+    pub fn is_pending(&self, event: Event) -> bool {
+        let sr = self.rb.sr.read();
+        match event {
+            Event::Overflow(Buffer::Buffer0) => sr.b0of().bit_is_set(),
+            Event::Overflow(Buffer::Buffer1) => sr.b1of().bit_is_set(),
+            Event::Overflow(Buffer::Buffer2) => sr.b2of().bit_is_set(),
+            Event::Overflow(Buffer::Buffer3) => sr.b3of().bit_is_set(),
+            Event::AddressMismatch => sr.amef().bit_is_set(),
+        }
+    }
+
+    /// Clear the flag for `event`
+    pub fn clear_irq(&mut self, event: Event) {
+        match event {
+            Event::Overflow(Buffer::Buffer0) => {
+                self.rb.fcr.write(|w| w.cb0of().set_bit())
+            }
+            Event::Overflow(Buffer::Buffer1) => {
+                self.rb.fcr.write(|w| w.cb1of().set_bit())
+            }
+            Event::Overflow(Buffer::Buffer2) => {
+                self.rb.fcr.write(|w| w.cb2of().set_bit())
+            }
+            Event::Overflow(Buffer::Buffer3) => {
+                self.rb.fcr.write(|w| w.cb3of().set_bit())
+            }
+            Event::AddressMismatch => {
+                self.rb.fcr.write(|w| w.camef().set_bit())
+            }
+        }
+        let _ = self.rb.sr.read();
+        let _ = self.rb.sr.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Releases the GFXMMU peripheral
+    pub fn free(self) -> (GFXMMU, rec::Gfxmmu) {
+        (
+            self.rb,
+            rec::Gfxmmu {
+                _marker: PhantomData,
+            },
+        )
+    }
+}
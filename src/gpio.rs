@@ -2,6 +2,7 @@
 
 use core::marker::PhantomData;
 
+use crate::exti::D3PendClear;
 use crate::rcc::ResetEnable;
 use crate::stm32::{EXTI, SYSCFG};
 
@@ -127,13 +128,75 @@ pub struct AF14;
 /// Alternate function 15 (type state)
 pub struct AF15;
 
+/// Pins that are returned to analog mode, their lowest power state, when
+/// the peripheral that uses them is deinitialised
+///
+/// Implemented for every pin, for `()`, for the filler types of the serial
+/// and SPI drivers, such as `NoTx`, which are returned unchanged, and for
+/// tuples of up to 4 pins.
+pub trait IntoAnalog {
+    /// The pins in analog mode
+    type Analog;
+
+    /// Configures the pins as analog inputs
+    fn into_analog(self) -> Self::Analog;
+}
+
+impl IntoAnalog for () {
+    type Analog = ();
+    fn into_analog(self) {}
+}
+
+macro_rules! into_analog_tuple {
+    ($(($($P:ident),+),)+) => {
+        $(
+            #[allow(non_snake_case)]
+            impl<$($P: IntoAnalog),+> IntoAnalog for ($($P,)+) {
+                type Analog = ($($P::Analog,)+);
+                fn into_analog(self) -> Self::Analog {
+                    let ($($P,)+) = self;
+                    ($($P.into_analog(),)+)
+                }
+            }
+        )+
+    };
+}
+into_analog_tuple! {
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+}
+
 /// External Interrupt Pin
 pub trait ExtiPin {
     fn make_interrupt_source(&mut self, syscfg: &mut SYSCFG);
     fn trigger_on_edge(&mut self, exti: &mut EXTI, level: Edge);
     fn enable_interrupt(&mut self, exti: &mut EXTI);
     fn disable_interrupt(&mut self, exti: &mut EXTI);
+    fn enable_event(&mut self, exti: &mut EXTI);
+    fn disable_event(&mut self, exti: &mut EXTI);
+    fn enable_d3_pending(&mut self, exti: &mut EXTI, clear: D3PendClear);
+    fn disable_d3_pending(&mut self, exti: &mut EXTI);
+    fn check_interrupt(&self) -> bool;
     fn clear_interrupt_pending_bit(&mut self);
+
+    /// Make the EXTI line sensitive to this pin, trigger on `edge` and
+    /// enable the interrupt
+    fn into_interrupt_source(
+        mut self,
+        syscfg: &mut SYSCFG,
+        exti: &mut EXTI,
+        edge: Edge,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        self.make_interrupt_source(syscfg);
+        self.trigger_on_edge(exti, edge);
+        self.enable_interrupt(exti);
+        self
+    }
 }
 
 macro_rules! gpio {
@@ -148,11 +211,12 @@ macro_rules! gpio {
             use embedded_hal::digital::v2::{InputPin, OutputPin,
                                             StatefulOutputPin, toggleable};
 
+            use crate::exti::{D3PendClear, Event, ExtiExt};
             use crate::rcc::{rec, ResetEnable};
             use crate::stm32::$GPIOX;
             use crate::stm32::{EXTI, SYSCFG};
             use super::{
-                Alternate, Floating, GpioExt, Input, OpenDrain,
+                Alternate, Floating, GpioExt, Input, IntoAnalog, OpenDrain,
                 Output, Speed, PullDown, PullUp, PushPull, AF0, AF1,
                 AF2, AF3, AF4, AF5, AF6, AF7, AF8, AF9, AF10, AF11,
                 AF12, AF13, AF14, AF15, Analog, Edge, ExtiPin, };
@@ -316,7 +380,7 @@ macro_rules! gpio {
 
                 /// Enable external interrupts from this pin.
                 fn enable_interrupt(&mut self, exti: &mut EXTI) {
-                    #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+                    #[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
                     let imr1 = &exti.cpuimr1;
                     #[cfg(all(feature = "rm0399", feature = "cm7"))]
                     let imr1 = &exti.c1imr1;
@@ -328,7 +392,7 @@ macro_rules! gpio {
 
                 /// Disable external interrupts from this pin
                 fn disable_interrupt(&mut self, exti: &mut EXTI) {
-                    #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+                    #[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
                     let imr1 = &exti.cpuimr1;
                     #[cfg(all(feature = "rm0399", feature = "cm7"))]
                     let imr1 = &exti.c1imr1;
@@ -338,10 +402,37 @@ macro_rules! gpio {
                     imr1.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << self.i)) });
                 }
 
+                /// Enable events from this pin, which wake the CPU from `WFE`
+                fn enable_event(&mut self, exti: &mut EXTI) {
+                    exti.listen_event(Event::gpio(self.i));
+                }
+
+                /// Disable events from this pin
+                fn disable_event(&mut self, exti: &mut EXTI) {
+                    exti.unlisten_event(Event::gpio(self.i));
+                }
+
+                /// Keep the D3 domain running after a trigger from this pin, until
+                /// cleared by `clear`
+                fn enable_d3_pending(&mut self, exti: &mut EXTI, clear: D3PendClear) {
+                    exti.d3_pending(Event::gpio(self.i), Some(clear));
+                }
+
+                /// Disable the D3 pending flag for this pin
+                fn disable_d3_pending(&mut self, exti: &mut EXTI) {
+                    exti.d3_pending(Event::gpio(self.i), None);
+                }
+
+                /// Indicate if the interrupt for this pin is pending
+                fn check_interrupt(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*EXTI::ptr()).is_pending(Event::gpio(self.i)) }
+                }
+
                 /// Clear the interrupt pending bit for this pin
                 fn clear_interrupt_pending_bit(&mut self) {
                     unsafe {
-                        #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+                        #[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
                         let pr1 = &(*EXTI::ptr()).cpupr1;
                         #[cfg(all(feature = "rm0399", feature = "cm7"))]
                         let pr1 = &(*EXTI::ptr()).c1pr1;
@@ -382,6 +473,13 @@ macro_rules! gpio {
                     _mode: PhantomData<MODE>,
                 }
 
+                impl<MODE> IntoAnalog for $PXi<MODE> {
+                    type Analog = $PXi<Analog>;
+                    fn into_analog(self) -> $PXi<Analog> {
+                        $PXi::into_analog(self)
+                    }
+                }
+
                 impl<MODE> $PXi<MODE> {
                     /// Configures the pin to operate in AF0 mode
                     pub fn into_alternate_af0(self) -> $PXi<Alternate<AF0>> {
@@ -764,7 +862,7 @@ macro_rules! gpio {
 
                     /// Enable external interrupts from this pin.
                     fn enable_interrupt(&mut self, exti: &mut EXTI) {
-                        #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+                        #[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
                         let imr1 = &exti.cpuimr1;
                         #[cfg(all(feature = "rm0399", feature = "cm7"))]
                         let imr1 = &exti.c1imr1;
@@ -776,7 +874,7 @@ macro_rules! gpio {
 
                     /// Disable external interrupts from this pin
                     fn disable_interrupt(&mut self, exti: &mut EXTI) {
-                        #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+                        #[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
                         let imr1 = &exti.cpuimr1;
                         #[cfg(all(feature = "rm0399", feature = "cm7"))]
                         let imr1 = &exti.c1imr1;
@@ -786,10 +884,37 @@ macro_rules! gpio {
                         imr1.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << $i)) });
                     }
 
+                    /// Enable events from this pin, which wake the CPU from `WFE`
+                    fn enable_event(&mut self, exti: &mut EXTI) {
+                        exti.listen_event(Event::gpio($i));
+                    }
+
+                    /// Disable events from this pin
+                    fn disable_event(&mut self, exti: &mut EXTI) {
+                        exti.unlisten_event(Event::gpio($i));
+                    }
+
+                    /// Keep the D3 domain running after a trigger from this pin, until
+                    /// cleared by `clear`
+                    fn enable_d3_pending(&mut self, exti: &mut EXTI, clear: D3PendClear) {
+                        exti.d3_pending(Event::gpio($i), Some(clear));
+                    }
+
+                    /// Disable the D3 pending flag for this pin
+                    fn disable_d3_pending(&mut self, exti: &mut EXTI) {
+                        exti.d3_pending(Event::gpio($i), None);
+                    }
+
+                    /// Indicate if the interrupt for this pin is pending
+                    fn check_interrupt(&self) -> bool {
+                        // NOTE(unsafe) atomic read with no side effects
+                        unsafe { (*EXTI::ptr()).is_pending(Event::gpio($i)) }
+                    }
+
                     /// Clear the interrupt pending bit for this pin
                     fn clear_interrupt_pending_bit(&mut self) {
                         unsafe {
-                            #[cfg(any(feature = "rm0433", feature = "rm0455"))]
+                            #[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
                             let pr1 = &(*(EXTI::ptr())).cpupr1;
                             #[cfg(all(feature = "rm0399", feature = "cm7"))]
                             let pr1 = &(*(EXTI::ptr())).c1pr1;
@@ -0,0 +1,390 @@
+//! Hash processor (HASH)
+//!
+//! The hash processor calculates SHA-1, SHA-224, SHA-256 and MD5 digests,
+//! and the corresponding keyed-hash message authentication codes (HMAC).
+//!
+//! This module is only available for the parts with the crypto accelerator
+//! (750, 753, 7B3, 7B0, 730, 733, 735).
+//!
+//! # Example
+//!
+//! ```
+//! let mut hash = dp.HASH.hash(ccdr.peripheral.HASH);
+//!
+//! let mut digest = hash.start(Algorithm::Sha256);
+//! digest.update(b"abc");
+//! let output = digest.finalize();
+//! assert_eq!(output.as_ref()[..4], [0xBA, 0x78, 0x16, 0xBF]);
+//!
+//! // HMAC
+//! let mut mac = hash.start_hmac(Algorithm::Sha256, b"key");
+//! mac.update(b"The quick brown fox jumps over the lazy dog");
+//! let output = mac.finalize();
+//! ```
+//!
+//! The data can be fed in any number of calls to
+//! [`update`](Digest::update), of any length.
+//!
+//! # DMA
+//!
+//! The DMA request of the hash processor is connected to the MDMA. After
+//! [`start_dma`](Hash::start_dma), the MDMA can write the message to the
+//! address returned by [`dma_address`](Hash::dma_address). The digest is
+//! calculated when the DMA transfer is complete, and read with
+//! [`dma_output`](Hash::dma_output).
+//!
+//! # RustCrypto
+//!
+//! With the `digest` feature, [`Sha1`], [`Sha224`], [`Sha256`] and [`Md5`]
+//! implement the `Update`, `FixedOutputDirty` and `Reset` traits of the
+//! [`digest`](https://docs.rs/digest) crate.
+
+use core::marker::PhantomData;
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::HASH;
+
+/// DATATYPE value for byte data, which is swapped into big-endian words
+const DATATYPE_BYTES: u8 = 0b10;
+/// Keys longer than a block are hashed first
+const HMAC_LONG_KEY: usize = 64;
+
+/// Hash algorithm
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Algorithm {
+    /// SHA-1, 20 byte digest
+    Sha1,
+    /// MD5, 16 byte digest
+    Md5,
+    /// SHA-224, 28 byte digest
+    Sha224,
+    /// SHA-256, 32 byte digest
+    Sha256,
+}
+
+impl Algorithm {
+    /// Returns the ALGO1 and ALGO0 bits of the CR register
+    fn algo(self) -> (bool, bool) {
+        match self {
+            Algorithm::Sha1 => (false, false),
+            Algorithm::Md5 => (false, true),
+            Algorithm::Sha224 => (true, false),
+            Algorithm::Sha256 => (true, true),
+        }
+    }
+
+    /// Returns the length of the digest in bytes
+    pub fn output_len(self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Md5 => 16,
+            Algorithm::Sha224 => 28,
+            Algorithm::Sha256 => 32,
+        }
+    }
+}
+
+/// Digest or HMAC result
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Output {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl AsRef<[u8]> for Output {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Extension trait for the HASH peripheral
+pub trait HashExt: Sized {
+    /// Enable and reset the hash processor
+    fn hash(self, prec: rec::Hash) -> Hash;
+}
+
+impl HashExt for HASH {
+    fn hash(self, prec: rec::Hash) -> Hash {
+        prec.enable().reset();
+
+        Hash { rb: self }
+    }
+}
+
+/// Hash processor
+pub struct Hash {
+    rb: HASH,
+}
+
+impl Hash {
+    /// Starts calculating a digest with `algorithm`
+    pub fn start(&mut self, algorithm: Algorithm) -> Digest<'_> {
+        let mut digest = Digest {
+            hash: self,
+            algorithm,
+            key: None,
+            partial: [0; 4],
+            partial_len: 0,
+        };
+        digest.init();
+        digest
+    }
+
+    /// Starts calculating a keyed-hash message authentication code with
+    /// `algorithm` and `key`
+    pub fn start_hmac<'a>(
+        &'a mut self,
+        algorithm: Algorithm,
+        key: &'a [u8],
+    ) -> Digest<'a> {
+        let mut digest = Digest {
+            hash: self,
+            algorithm,
+            key: Some(key),
+            partial: [0; 4],
+            partial_len: 0,
+        };
+        digest.init();
+        digest
+    }
+
+    /// Starts calculating a digest with `algorithm` from data written by
+    /// DMA. The calculation starts when the DMA transfer is complete.
+    /// `last_bits` is the number of valid bits in the last word, or 0 if
+    /// all are valid
+    pub fn start_dma(&mut self, algorithm: Algorithm, last_bits: u8) {
+        self.init(algorithm, None, true);
+        self.rb
+            .str
+            .write(|w| unsafe { w.nblw().bits(last_bits & 0x1F) });
+    }
+
+    /// Returns the address of the data input register, to be used as the
+    /// destination of a DMA transfer
+    pub fn dma_address(&self) -> usize {
+        &self.rb.din as *const _ as usize
+    }
+
+    /// Returns the digest calculated from DMA input, or `None` if the
+    /// calculation is not complete. The DMA request is disabled
+    pub fn dma_output(&mut self, algorithm: Algorithm) -> Option<Output> {
+        if !self.is_digest_complete() {
+            return None;
+        }
+        self.rb.cr.modify(|_, w| w.dmae().clear_bit());
+        Some(self.output(algorithm))
+    }
+
+    /// Releases the HASH peripheral
+    pub fn free(self) -> (HASH, rec::Hash) {
+        (
+            self.rb,
+            rec::Hash {
+                _marker: PhantomData,
+            },
+        )
+    }
+
+    /// Starts a new calculation with `algorithm`, in HMAC mode if there
+    /// is a `key`
+    fn init(&mut self, algorithm: Algorithm, key: Option<&[u8]>, dma: bool) {
+        let (algo1, algo0) = algorithm.algo();
+        self.rb.cr.write(|w| unsafe {
+            w.init()
+                .set_bit()
+                .datatype()
+                .bits(DATATYPE_BYTES)
+                .dmae()
+                .bit(dma)
+                .algo0()
+                .bit(algo0)
+                .algo1()
+                .bit(algo1)
+                .mode()
+                .bit(key.is_some())
+                .lkey()
+                .bit(key.map_or(false, |key| key.len() > HMAC_LONG_KEY))
+        });
+    }
+
+    fn is_digest_complete(&self) -> bool {
+        self.rb.sr.read().dcis().bit_is_set()
+    }
+
+    fn output(&self, algorithm: Algorithm) -> Output {
+        let mut output = Output {
+            bytes: [0; 32],
+            len: algorithm.output_len(),
+        };
+        let words = output.bytes[..output.len].chunks_mut(4);
+        for (bytes, hr) in words.zip(self.rb.hash_hr.iter()) {
+            bytes.copy_from_slice(&hr.read().bits().to_be_bytes());
+        }
+        output
+    }
+
+    fn wait_busy(&self) {
+        while self.rb.sr.read().busy().bit_is_set() {}
+    }
+
+    fn write_word(&mut self, word: u32) {
+        self.rb.din.write(|w| unsafe { w.bits(word) });
+    }
+
+    /// Starts the digest calculation, with `last_bits` valid bits in the
+    /// last word written
+    fn calculate(&mut self, last_bits: u8) {
+        self.rb.str.write(|w| unsafe { w.nblw().bits(last_bits) });
+        self.rb
+            .str
+            .write(|w| unsafe { w.nblw().bits(last_bits).dcal().set_bit() });
+    }
+}
+
+/// A digest or HMAC calculation in progress
+pub struct Digest<'a> {
+    hash: &'a mut Hash,
+    algorithm: Algorithm,
+    key: Option<&'a [u8]>,
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl<'a> Digest<'a> {
+    /// Feeds `data` to the calculation
+    pub fn update(&mut self, data: &[u8]) {
+        let mut data = data;
+
+        // Complete a partial word
+        if self.partial_len > 0 {
+            let n = core::cmp::min(4 - self.partial_len, data.len());
+            self.partial[self.partial_len..self.partial_len + n]
+                .copy_from_slice(&data[..n]);
+            self.partial_len += n;
+            data = &data[n..];
+
+            if self.partial_len < 4 {
+                return;
+            }
+            let word = u32::from_le_bytes(self.partial);
+            self.hash.write_word(word);
+            self.partial_len = 0;
+        }
+
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            self.hash.write_word(word);
+        }
+
+        let rest = words.remainder();
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+    }
+
+    /// Completes the calculation and returns the result
+    pub fn finalize(mut self) -> Output {
+        self.finish()
+    }
+
+    /// Completes the calculation, returns the result and starts a new
+    /// calculation with the same algorithm and key
+    pub fn finalize_reset(&mut self) -> Output {
+        let output = self.finish();
+        self.init();
+        output
+    }
+
+    fn init(&mut self) {
+        self.hash.init(self.algorithm, self.key, false);
+        self.partial_len = 0;
+
+        // The inner key is hashed before the message
+        if let Some(key) = self.key {
+            self.update(key);
+            self.end_phase();
+            self.hash.wait_busy();
+        }
+    }
+
+    /// Writes the last partial word and starts the calculation
+    fn end_phase(&mut self) {
+        let last_bits = 8 * self.partial_len as u8;
+        if self.partial_len > 0 {
+            self.partial[self.partial_len..]
+                .iter_mut()
+                .for_each(|b| *b = 0);
+            let word = u32::from_le_bytes(self.partial);
+            self.hash.write_word(word);
+            self.partial_len = 0;
+        }
+        self.hash.calculate(last_bits);
+    }
+
+    fn finish(&mut self) -> Output {
+        self.end_phase();
+
+        // The outer key is hashed after the message
+        if let Some(key) = self.key {
+            self.hash.wait_busy();
+            self.update(key);
+            self.end_phase();
+        }
+
+        while !self.hash.is_digest_complete() {}
+        self.hash.output(self.algorithm)
+    }
+}
+
+#[cfg(feature = "digest")]
+macro_rules! rustcrypto_digest {
+    ($($Name:ident: ($algorithm:ident, $OutputSize:ident),)+) => {
+        $(
+            #[doc = "Hash processor as a RustCrypto digest, see the [module level documentation](self)"]
+            pub struct $Name<'a> {
+                digest: Digest<'a>,
+            }
+
+            impl<'a> $Name<'a> {
+                /// Starts a new digest calculation
+                pub fn new(hash: &'a mut Hash) -> Self {
+                    $Name {
+                        digest: hash.start(Algorithm::$algorithm),
+                    }
+                }
+            }
+
+            impl digest::Update for $Name<'_> {
+                fn update(&mut self, data: impl AsRef<[u8]>) {
+                    self.digest.update(data.as_ref());
+                }
+            }
+
+            impl digest::FixedOutputDirty for $Name<'_> {
+                type OutputSize = digest::consts::$OutputSize;
+
+                fn finalize_into_dirty(
+                    &mut self,
+                    out: &mut digest::generic_array::GenericArray<u8, Self::OutputSize>,
+                ) {
+                    let output = self.digest.finish();
+                    out.copy_from_slice(output.as_ref());
+                }
+            }
+
+            impl digest::Reset for $Name<'_> {
+                fn reset(&mut self) {
+                    self.digest.init();
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "digest")]
+rustcrypto_digest! {
+    Sha1: (Sha1, U20),
+    Sha224: (Sha224, U28),
+    Sha256: (Sha256, U32),
+    Md5: (Md5, U16),
+}
@@ -0,0 +1,830 @@
+//! High-Resolution Timer (HRTIM)
+//!
+//! The HRTIM has a master timer and five timing units (A - E). Each timing
+//! unit has a 16-bit counter, four compare registers and two outputs. The
+//! outputs are driven by a crossbar: each output is set and reset by any
+//! combination of events, such as the unit's own period and compare events
+//! or those of the master timer.
+//!
+//! The counters are clocked at up to the HRTIM kernel clock `f_HRTIM`,
+//! which is `c_ck` or `timy_ker_ck` depending on `RCC_CFGR.HRTIMSEL`. At
+//! 480MHz this gives a resolution of about 2ns.
+//!
+//! This driver covers a subset of the HRTIM
+//!
+//! - Master timer and timing unit period and compare values
+//! - Output set / reset crossbar events, polarity
+//! - Complementary outputs with dead time
+//! - Burst mode
+//! - Fault inputs
+//!
+//! # Examples
+//!
+//! ```
+//! use stm32h7xx_hal::hrtim::{Compare, Hrtim, Out1, Out2};
+//!
+//! let mut hrtim = Hrtim::new(
+//!     dp.HRTIM_COMMON,
+//!     dp.HRTIM_MASTER,
+//!     ccdr.peripheral.HRTIM,
+//!     &ccdr.clocks,
+//! );
+//!
+//! // 200kHz PWM on timing unit A
+//! let mut ta = hrtim.timer_a(dp.HRTIM_TIMA, 200.khz());
+//!
+//! // Out1 is set at the start of the period and reset on CMP1. Out2 is its
+//! // complement with 50ns dead time on both edges
+//! ta.set_compare(Compare::Cmp1, ta.period() / 4);
+//! ta.set_dead_time(50.ns(), 50.ns());
+//!
+//! ta.enable_output::<Out1, _>(gpioc.pc6.into_alternate_af1());
+//! ta.enable_output::<Out2, _>(gpioc.pc7.into_alternate_af1());
+//! ta.start();
+//! ```
+//!
+//! # Crossbar
+//!
+//! Without dead time, the set and reset events of each output can be
+//! chosen independently
+//!
+//! ```
+//! use stm32h7xx_hal::hrtim::Event;
+//!
+//! // Centered pulse on Out2
+//! ta.set_compare(Compare::Cmp2, 100);
+//! ta.set_compare(Compare::Cmp3, 300);
+//! ta.set_output_events::<Out2>(&[Event::Cmp2], &[Event::Cmp3]);
+//! ```
+//!
+//! # Faults
+//!
+//! When a fault input is active, the outputs of the timing units that
+//! listen to it are forced to a safe state until the fault is cleared. The
+//! fault input pin (`HRTIM_FLTx`) must be configured in its alternate
+//! function mode.
+//!
+//! ```
+//! use stm32h7xx_hal::hrtim::{Fault, FaultPolarity, FaultState};
+//!
+//! hrtim.enable_fault(Fault::Flt1, FaultPolarity::ActiveLow, 0);
+//! ta.enable_faults(&[Fault::Flt1], FaultState::Inactive);
+//! ```
+
+use crate::gpio::gpioa::{PA10, PA11, PA12, PA8, PA9};
+use crate::gpio::gpioc::{PC6, PC7, PC8};
+use crate::gpio::gpiog::{PG6, PG7};
+use crate::gpio::{Alternate, AF1, AF2};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{
+    HRTIM_COMMON, HRTIM_MASTER, HRTIM_TIMA, HRTIM_TIMB, HRTIM_TIMC, HRTIM_TIMD,
+    HRTIM_TIME, RCC,
+};
+use crate::time::{Hertz, NanoSeconds};
+
+/// Maximum period value
+const MAX_PERIOD: u32 = 0xFFFD;
+/// Minimum period and compare value
+const MIN_COMPARE: u16 = 3;
+
+/// Returns the HRTIM kernel clock
+fn get_clk(clocks: &CoreClocks) -> Hertz {
+    // unsafe: read only
+    let rcc = unsafe { &*RCC::ptr() };
+
+    if rcc.cfgr.read().hrtimsel().bit_is_set() {
+        clocks.c_ck()
+    } else {
+        clocks.timy_ker_ck()
+    }
+}
+
+/// Calculates the prescaler and the period for `frequency`
+fn period(clk: u32, frequency: Hertz) -> (u8, u16) {
+    let ticks = clk / frequency.0;
+
+    let mut ckpsc = 0;
+    while (ticks >> ckpsc) > MAX_PERIOD {
+        ckpsc += 1;
+    }
+    assert!(ckpsc <= 7, "HRTIM frequency too low");
+
+    let period = core::cmp::max((ticks >> ckpsc) as u16, MIN_COMPARE);
+    (ckpsc, period)
+}
+
+/// The HRTIM master timer and common registers
+pub struct Hrtim {
+    common: HRTIM_COMMON,
+    master: HRTIM_MASTER,
+    clk: u32,
+}
+
+/// A timing unit of the HRTIM; it is created by calling `timer_a`,
+/// `timer_b` ... on [`Hrtim`]
+pub struct TimingUnit<TIM> {
+    tim: TIM,
+    clk: u32,
+}
+
+/// Marker struct for output 1 of a timing unit
+pub struct Out1;
+/// Marker struct for output 2 of a timing unit
+pub struct Out2;
+
+/// Outputs of a timing unit
+pub trait Output {
+    /// Index of this output, 0 for output 1
+    const INDEX: u32;
+}
+impl Output for Out1 {
+    const INDEX: u32 = 0;
+}
+impl Output for Out2 {
+    const INDEX: u32 = 1;
+}
+
+/// OutputPin marks which GPIO pins may be used as the outputs of a timing
+/// unit
+pub trait OutputPin<TIM, OUT> {}
+
+macro_rules! output_pins {
+    ($($TIMX:ident: $OUT:ident: [$($PIN:ty),*])+) => {
+        $(
+            $(
+                impl OutputPin<$TIMX, $OUT> for $PIN {}
+            )*
+        )+
+    };
+}
+
+output_pins! {
+    HRTIM_TIMA: Out1: [PC6<Alternate<AF1>>]
+    HRTIM_TIMA: Out2: [PC7<Alternate<AF1>>]
+    HRTIM_TIMB: Out1: [PC8<Alternate<AF1>>]
+    HRTIM_TIMB: Out2: [PA8<Alternate<AF2>>]
+    HRTIM_TIMC: Out1: [PA9<Alternate<AF2>>]
+    HRTIM_TIMC: Out2: [PA10<Alternate<AF2>>]
+    HRTIM_TIMD: Out1: [PA11<Alternate<AF2>>]
+    HRTIM_TIMD: Out2: [PA12<Alternate<AF2>>]
+    HRTIM_TIME: Out1: [PG6<Alternate<AF2>>]
+    HRTIM_TIME: Out2: [PG7<Alternate<AF2>>]
+}
+
+/// Compare registers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Compare {
+    Cmp1,
+    Cmp2,
+    Cmp3,
+    Cmp4,
+}
+
+/// Crossbar events that can set or reset an output
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Software set / reset, applied immediately
+    Software,
+    /// Counter of this timing unit is reset
+    Resync,
+    /// Period of this timing unit
+    Period,
+    /// Compare 1 - 4 of this timing unit
+    Cmp1,
+    Cmp2,
+    Cmp3,
+    Cmp4,
+    /// Period of the master timer
+    MasterPeriod,
+    /// Compare 1 - 4 of the master timer
+    MasterCmp1,
+    MasterCmp2,
+    MasterCmp3,
+    MasterCmp4,
+    /// Timer events 1 - 9. See the reference manual for the sources of
+    /// each timing unit
+    Timer(u8),
+    /// External events 1 - 10
+    External(u8),
+    /// Registers update
+    Update,
+}
+
+impl Event {
+    /// Bit in the SETxyR / RSTxyR registers
+    fn bit(self) -> u32 {
+        match self {
+            Event::Software => 1 << 0,
+            Event::Resync => 1 << 1,
+            Event::Period => 1 << 2,
+            Event::Cmp1 => 1 << 3,
+            Event::Cmp2 => 1 << 4,
+            Event::Cmp3 => 1 << 5,
+            Event::Cmp4 => 1 << 6,
+            Event::MasterPeriod => 1 << 7,
+            Event::MasterCmp1 => 1 << 8,
+            Event::MasterCmp2 => 1 << 9,
+            Event::MasterCmp3 => 1 << 10,
+            Event::MasterCmp4 => 1 << 11,
+            Event::Timer(n) => {
+                assert!((1..=9).contains(&n));
+                1 << (11 + n)
+            }
+            Event::External(n) => {
+                assert!((1..=10).contains(&n));
+                1 << (20 + n)
+            }
+            Event::Update => 1 << 31,
+        }
+    }
+
+    fn bits(events: &[Event]) -> u32 {
+        events.iter().fold(0, |bits, e| bits | e.bit())
+    }
+}
+
+/// Fault inputs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Fault {
+    Flt1,
+    Flt2,
+    Flt3,
+    Flt4,
+    Flt5,
+}
+
+/// Active level of a fault input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaultPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// State of the outputs while a fault is active
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaultState {
+    /// Outputs are not affected
+    NoAction = 0b00,
+    /// Outputs are forced to their active level
+    Active = 0b01,
+    /// Outputs are forced to their inactive level
+    Inactive = 0b10,
+    /// Outputs are high impedance
+    HighZ = 0b11,
+}
+
+/// HRTIM timers that can take part in burst mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Unit {
+    Master,
+    A,
+    B,
+    C,
+    D,
+    E,
+}
+
+/// Burst mode configuration
+///
+/// During a burst, the outputs of the selected timing units are idle for
+/// `idle` periods of the burst mode counter, out of every `period + 1`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BurstMode {
+    prescaler: u8,
+    period: u16,
+    idle: u16,
+    continuous: bool,
+}
+
+impl Default for BurstMode {
+    fn default() -> Self {
+        BurstMode {
+            prescaler: 0,
+            period: 1,
+            idle: 1,
+            continuous: true,
+        }
+    }
+}
+
+impl BurstMode {
+    /// The burst mode counter is clocked at `f_HRTIM / 2^prescaler`, with
+    /// `prescaler` from 0 to 15. Default 0
+    pub fn prescaler(mut self, prescaler: u8) -> Self {
+        assert!(prescaler <= 15);
+        self.prescaler = prescaler;
+        self
+    }
+
+    /// Period of the burst. Default 1
+    pub fn period(mut self, period: u16) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Number of counts the outputs are idle, less than or equal to
+    /// `period`. Default 1
+    pub fn idle(mut self, idle: u16) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    /// When `true`, bursts repeat until burst mode is disabled. Otherwise
+    /// each trigger produces a single burst. Default `true`
+    pub fn continuous(mut self, continuous: bool) -> Self {
+        self.continuous = continuous;
+        self
+    }
+}
+
+impl Hrtim {
+    /// Enables the HRTIM, taking ownership of the master timer and common
+    /// registers
+    pub fn new(
+        common: HRTIM_COMMON,
+        master: HRTIM_MASTER,
+        prec: rec::Hrtim,
+        clocks: &CoreClocks,
+    ) -> Self {
+        prec.enable().reset();
+
+        Hrtim {
+            common,
+            master,
+            clk: get_clk(clocks).0,
+        }
+    }
+
+    /// Returns the HRTIM kernel clock
+    pub fn clk(&self) -> Hertz {
+        Hertz(self.clk)
+    }
+
+    /// Configures the master timer to run at `frequency`. The period and
+    /// compare events of the master timer can be used to synchronise the
+    /// timing units
+    pub fn set_master_frequency<T>(&mut self, frequency: T)
+    where
+        T: Into<Hertz>,
+    {
+        let (ckpsc, period) = period(self.clk, frequency.into());
+
+        self.master.mcr.modify(|_, w| unsafe {
+            w.ck_psc()
+                .bits(ckpsc)
+                .cont()
+                .set_bit()
+                .preen()
+                .set_bit()
+                .mrepu()
+                .set_bit()
+        });
+        self.master.mper.write(|w| unsafe { w.mper().bits(period) });
+        self.master.mrep.write(|w| unsafe { w.mrep().bits(0) });
+
+        // Software update to load the preload registers
+        self.common.cr2.write(|w| w.mswu().set_bit());
+    }
+
+    /// Returns the period of the master timer
+    pub fn master_period(&self) -> u16 {
+        self.master.mper.read().mper().bits()
+    }
+
+    /// Sets a compare value of the master timer. The minimum is 3
+    pub fn set_master_compare(&mut self, compare: Compare, value: u16) {
+        let value = core::cmp::max(value, MIN_COMPARE);
+        match compare {
+            Compare::Cmp1 => self
+                .master
+                .mcmp1r
+                .write(|w| unsafe { w.mcmp1().bits(value) }),
+            Compare::Cmp2 => self
+                .master
+                .mcmp2r
+                .write(|w| unsafe { w.mcmp2().bits(value) }),
+            Compare::Cmp3 => self
+                .master
+                .mcmp3r
+                .write(|w| unsafe { w.mcmp3().bits(value) }),
+            Compare::Cmp4 => self
+                .master
+                .mcmp4r
+                .write(|w| unsafe { w.mcmp4().bits(value) }),
+        }
+    }
+
+    /// Starts the master timer counter
+    pub fn start_master(&mut self) {
+        self.master.mcr.modify(|_, w| w.mcen().set_bit());
+    }
+
+    /// Stops the master timer counter
+    pub fn stop_master(&mut self) {
+        self.master.mcr.modify(|_, w| w.mcen().clear_bit());
+    }
+
+    /// Enables a fault input. The input is filtered by `filter`, from 0
+    /// (no filter) to 0b1111. See the reference manual for the filter
+    /// settings
+    pub fn enable_fault(
+        &mut self,
+        fault: Fault,
+        polarity: FaultPolarity,
+        filter: u8,
+    ) {
+        assert!(filter <= 0b1111);
+        let active_high = polarity == FaultPolarity::ActiveHigh;
+
+        // FLTxE, FLTxP, FLTxSRC = 0 (pin), FLTxF
+        macro_rules! enable {
+            ($fltinr:ident, $e:ident, $p:ident, $src:ident, $f:ident) => {
+                self.common.$fltinr.modify(|_, w| unsafe {
+                    w.$e()
+                        .set_bit()
+                        .$p()
+                        .bit(active_high)
+                        .$src()
+                        .clear_bit()
+                        .$f()
+                        .bits(filter)
+                })
+            };
+        }
+        match fault {
+            Fault::Flt1 => enable!(fltinr1, flt1e, flt1p, flt1src, flt1f),
+            Fault::Flt2 => enable!(fltinr1, flt2e, flt2p, flt2src, flt2f),
+            Fault::Flt3 => enable!(fltinr1, flt3e, flt3p, flt3src, flt3f),
+            Fault::Flt4 => enable!(fltinr1, flt4e, flt4p, flt4src, flt4f),
+            Fault::Flt5 => enable!(fltinr2, flt5e, flt5p, flt5src, flt5f),
+        }
+    }
+
+    /// Disables a fault input
+    pub fn disable_fault(&mut self, fault: Fault) {
+        match fault {
+            Fault::Flt1 => {
+                self.common.fltinr1.modify(|_, w| w.flt1e().clear_bit())
+            }
+            Fault::Flt2 => {
+                self.common.fltinr1.modify(|_, w| w.flt2e().clear_bit())
+            }
+            Fault::Flt3 => {
+                self.common.fltinr1.modify(|_, w| w.flt3e().clear_bit())
+            }
+            Fault::Flt4 => {
+                self.common.fltinr1.modify(|_, w| w.flt4e().clear_bit())
+            }
+            Fault::Flt5 => {
+                self.common.fltinr2.modify(|_, w| w.flt5e().clear_bit())
+            }
+        }
+    }
+
+    /// Returns `true` if `fault` has occurred since it was last cleared
+    pub fn is_fault_pending(&self, fault: Fault) -> bool {
+        let isr = self.common.isr.read();
+        match fault {
+            Fault::Flt1 => isr.flt1().bit_is_set(),
+            Fault::Flt2 => isr.flt2().bit_is_set(),
+            Fault::Flt3 => isr.flt3().bit_is_set(),
+            Fault::Flt4 => isr.flt4().bit_is_set(),
+            Fault::Flt5 => isr.flt5().bit_is_set(),
+        }
+    }
+
+    /// Clears the flag for `fault`. If the fault input is no longer active,
+    /// the outputs can be re-enabled
+    pub fn clear_fault(&mut self, fault: Fault) {
+        self.common.icr.write(|w| match fault {
+            Fault::Flt1 => w.flt1c().set_bit(),
+            Fault::Flt2 => w.flt2c().set_bit(),
+            Fault::Flt3 => w.flt3c().set_bit(),
+            Fault::Flt4 => w.flt4c().set_bit(),
+            Fault::Flt5 => w.flt5c().set_bit(),
+        });
+        let _ = self.common.isr.read();
+        let _ = self.common.isr.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Configures and enables burst mode for `units`. Bursts are started by
+    /// [`trigger_burst`](Hrtim::trigger_burst)
+    pub fn enable_burst(&mut self, config: BurstMode, units: &[Unit]) {
+        assert!(config.idle <= config.period);
+
+        self.common.bmcr.write(|w| unsafe {
+            let w = units.iter().fold(w, |w, unit| match unit {
+                Unit::Master => w.mtbm().set_bit(),
+                Unit::A => w.tabm().set_bit(),
+                Unit::B => w.tbbm().set_bit(),
+                Unit::C => w.tcbm().set_bit(),
+                Unit::D => w.tdbm().set_bit(),
+                Unit::E => w.tebm().set_bit(),
+            });
+            w.bmom()
+                .bit(config.continuous)
+                .bmclk()
+                .bits(0b1010) // f_HRTIM
+                .bmprsc()
+                .bits(config.prescaler)
+                .bmpren()
+                .set_bit()
+        });
+        self.common
+            .bmper
+            .write(|w| unsafe { w.bmper().bits(config.period) });
+        self.common
+            .bmcmpr
+            .write(|w| unsafe { w.bmcmp().bits(config.idle) });
+        // Software trigger only
+        self.common.bmtrgr.write(|w| unsafe { w.bits(0) });
+
+        self.common.bmcr.modify(|_, w| w.bme().set_bit());
+    }
+
+    /// Starts a burst
+    pub fn trigger_burst(&mut self) {
+        self.common.bmtrgr.modify(|_, w| w.sw().set_bit());
+    }
+
+    /// Disables burst mode. The outputs return to normal operation at the
+    /// end of the current burst
+    pub fn disable_burst(&mut self) {
+        self.common.bmcr.modify(|_, w| w.bme().clear_bit());
+    }
+
+    /// Returns `true` while a burst is in progress
+    pub fn is_burst_active(&self) -> bool {
+        self.common.bmcr.read().bmstat().bit_is_set()
+    }
+
+    /// Releases the HRTIM master timer and common registers
+    pub fn free(self) -> (HRTIM_COMMON, HRTIM_MASTER) {
+        (self.common, self.master)
+    }
+}
+
+macro_rules! timing_unit {
+    ($($TIMX:ident: ($timer_x:ident, $cen:ident, $swu:ident,
+                     [$oen1:ident, $oen2:ident], [$odis1:ident, $odis2:ident],
+                     $cr:ident, $per:ident, $rep:ident,
+                     [$cmp1:ident, $cmp2:ident, $cmp3:ident, $cmp4:ident],
+                     $dt:ident, $set1:ident, $rst1:ident, $set2:ident,
+                     $rst2:ident, $out:ident, $flt:ident),)+) => {
+        $(
+            impl Hrtim {
+                /// Configures a timing unit to run at `frequency`. Output 1
+                /// is set on the period event and reset on compare 1. The
+                /// outputs are disabled and the counter is stopped
+                pub fn $timer_x<T>(
+                    &mut self,
+                    tim: $TIMX,
+                    frequency: T,
+                ) -> TimingUnit<$TIMX>
+                where
+                    T: Into<Hertz>,
+                {
+                    let mut unit = TimingUnit { tim, clk: self.clk };
+                    unit.set_frequency(frequency);
+
+                    unit.tim.$set1.write(|w| w.per().set_bit());
+                    unit.tim.$rst1.write(|w| w.cmp1().set_bit());
+                    unit.set_compare(Compare::Cmp1, MIN_COMPARE);
+
+                    unit
+                }
+            }
+
+            impl TimingUnit<$TIMX> {
+                /// Sets the frequency of this timing unit. Compare values
+                /// are not changed
+                pub fn set_frequency<T>(&mut self, frequency: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    let (ckpsc, period) = period(self.clk, frequency.into());
+
+                    self.tim.$cr.modify(|_, w| unsafe {
+                        w.ck_pscx().bits(ckpsc)
+                            .cont().set_bit()
+                            .preen().set_bit()
+                            .txrepu().set_bit()
+                    });
+                    self.tim.$per.write(|w| unsafe { w.perx().bits(period) });
+                    self.tim.$rep.write(|w| unsafe { w.repx().bits(0) });
+
+                    self.update();
+                }
+
+                /// Returns the period of this timing unit in counts. This
+                /// is the maximum compare value
+                pub fn period(&self) -> u16 {
+                    self.tim.$per.read().perx().bits()
+                }
+
+                /// Sets a compare value. The minimum is 3. The new value
+                /// takes effect at the start of the next period
+                pub fn set_compare(&mut self, compare: Compare, value: u16) {
+                    let value = core::cmp::max(value, MIN_COMPARE);
+                    match compare {
+                        Compare::Cmp1 => self.tim.$cmp1.write(|w| unsafe { w.cmp1x().bits(value) }),
+                        Compare::Cmp2 => self.tim.$cmp2.write(|w| unsafe { w.cmp2x().bits(value) }),
+                        Compare::Cmp3 => self.tim.$cmp3.write(|w| unsafe { w.cmp3x().bits(value) }),
+                        Compare::Cmp4 => self.tim.$cmp4.write(|w| unsafe { w.cmp4x().bits(value) }),
+                    }
+                }
+
+                /// Returns a compare value
+                pub fn get_compare(&self, compare: Compare) -> u16 {
+                    match compare {
+                        Compare::Cmp1 => self.tim.$cmp1.read().cmp1x().bits(),
+                        Compare::Cmp2 => self.tim.$cmp2.read().cmp2x().bits(),
+                        Compare::Cmp3 => self.tim.$cmp3.read().cmp3x().bits(),
+                        Compare::Cmp4 => self.tim.$cmp4.read().cmp4x().bits(),
+                    }
+                }
+
+                /// Sets the crossbar events that set and reset output `OUT`
+                ///
+                /// When dead time is enabled, output 2 is the complement of
+                /// output 1 and its events are ignored
+                pub fn set_output_events<OUT: Output>(
+                    &mut self,
+                    set: &[Event],
+                    reset: &[Event],
+                ) {
+                    let set = Event::bits(set);
+                    let reset = Event::bits(reset);
+
+                    if OUT::INDEX == 0 {
+                        self.tim.$set1.write(|w| unsafe { w.bits(set) });
+                        self.tim.$rst1.write(|w| unsafe { w.bits(reset) });
+                    } else {
+                        self.tim.$set2.write(|w| unsafe { w.bits(set) });
+                        self.tim.$rst2.write(|w| unsafe { w.bits(reset) });
+                    }
+                }
+
+                /// Inverts the polarity of output `OUT`. When inverted, the
+                /// output is low when it is set
+                pub fn set_inverted<OUT: Output>(&mut self, inverted: bool) {
+                    self.tim.$out.modify(|_, w| if OUT::INDEX == 0 {
+                        w.pol1().bit(inverted)
+                    } else {
+                        w.pol2().bit(inverted)
+                    });
+                }
+
+                /// Enables complementary outputs with dead time. Output 2
+                /// is the complement of output 1, with `rising` dead time
+                /// inserted before output 1 goes active and `falling` dead
+                /// time before output 2 goes active
+                ///
+                /// Panics if a dead time is more than 511 times the longest
+                /// dead time step (16 cycles of the HRTIM kernel clock)
+                pub fn set_dead_time<R, F>(&mut self, rising: R, falling: F)
+                where
+                    R: Into<NanoSeconds>,
+                    F: Into<NanoSeconds>,
+                {
+                    let ticks = |t: NanoSeconds| {
+                        (u64::from(self.clk) * u64::from(t.0) / 1_000_000_000) as u32
+                    };
+                    let rising = ticks(rising.into());
+                    let falling = ticks(falling.into());
+
+                    // t_DTG = t_HRTIM * 2^(DTPRSC - 3), DTPRSC from 3 to 7
+                    let longest = core::cmp::max(rising, falling);
+                    let mut shift = 0;
+                    while (longest >> shift) > 0x1FF {
+                        shift += 1;
+                    }
+                    assert!(shift <= 4, "HRTIM dead time too long");
+
+                    let dtr = (rising >> shift) as u16;
+                    let dtf = (falling >> shift) as u16;
+                    self.tim.$dt.write(|w| unsafe {
+                        w.dtrx().bits(dtr)
+                            .dtprsc().bits(shift as u8 + 3)
+                            .dtfx().bits(dtf)
+                    });
+                    self.tim.$out.modify(|_, w| w.dten().set_bit());
+                }
+
+                /// Disables dead time. The outputs are driven independently
+                /// by their crossbar events
+                pub fn disable_dead_time(&mut self) {
+                    self.tim.$out.modify(|_, w| w.dten().clear_bit());
+                }
+
+                /// When `true`, output `OUT` is forced to its inactive level
+                /// while a burst is idle. Otherwise it is not affected by
+                /// burst mode
+                pub fn set_burst_idle<OUT: Output>(&mut self, idle: bool) {
+                    self.tim.$out.modify(|_, w| if OUT::INDEX == 0 {
+                        w.idlem1().bit(idle)
+                    } else {
+                        w.idlem2().bit(idle)
+                    });
+                }
+
+                /// Forces the outputs of this timing unit to `state` while
+                /// any of `faults` is active
+                pub fn enable_faults(&mut self, faults: &[Fault], state: FaultState) {
+                    self.tim.$flt.write(|w| {
+                        faults.iter().fold(w, |w, fault| match fault {
+                            Fault::Flt1 => w.flt1en().set_bit(),
+                            Fault::Flt2 => w.flt2en().set_bit(),
+                            Fault::Flt3 => w.flt3en().set_bit(),
+                            Fault::Flt4 => w.flt4en().set_bit(),
+                            Fault::Flt5 => w.flt5en().set_bit(),
+                        })
+                    });
+
+                    let state = state as u8;
+                    self.tim.$out.modify(|_, w| unsafe {
+                        w.fault1().bits(state).fault2().bits(state)
+                    });
+                }
+
+                /// Enables output `OUT` on `pin`
+                pub fn enable_output<OUT, PIN>(&mut self, _pin: PIN)
+                where
+                    OUT: Output,
+                    PIN: OutputPin<$TIMX, OUT>,
+                {
+                    // unsafe: OENR is write-1-to-set, we only write our bit
+                    let common = unsafe { &*HRTIM_COMMON::ptr() };
+                    common.oenr.write(|w| if OUT::INDEX == 0 {
+                        w.$oen1().set_bit()
+                    } else {
+                        w.$oen2().set_bit()
+                    });
+                }
+
+                /// Disables output `OUT`. The output goes to its idle level
+                pub fn disable_output<OUT: Output>(&mut self) {
+                    // unsafe: ODISR is write-1-to-set, we only write our bit
+                    let common = unsafe { &*HRTIM_COMMON::ptr() };
+                    common.odisr.write(|w| if OUT::INDEX == 0 {
+                        w.$odis1().set_bit()
+                    } else {
+                        w.$odis2().set_bit()
+                    });
+                }
+
+                /// Starts the counter of this timing unit
+                pub fn start(&mut self) {
+                    // unsafe: Only our counter enable bit is modified
+                    let master = unsafe { &*HRTIM_MASTER::ptr() };
+                    master.mcr.modify(|_, w| w.$cen().set_bit());
+                }
+
+                /// Stops the counter of this timing unit
+                pub fn stop(&mut self) {
+                    // unsafe: Only our counter enable bit is modified
+                    let master = unsafe { &*HRTIM_MASTER::ptr() };
+                    master.mcr.modify(|_, w| w.$cen().clear_bit());
+                }
+
+                /// Transfers the preload registers to the active registers
+                /// immediately
+                pub fn update(&mut self) {
+                    // unsafe: CR2 bits are self-clearing, we only write our
+                    // bit
+                    let common = unsafe { &*HRTIM_COMMON::ptr() };
+                    common.cr2.write(|w| w.$swu().set_bit());
+                }
+
+                /// Releases the timing unit
+                pub fn free(mut self) -> $TIMX {
+                    self.stop();
+                    self.tim
+                }
+            }
+        )+
+    };
+}
+
+timing_unit! {
+    HRTIM_TIMA: (timer_a, tacen, taswu, [ta1oen, ta2oen], [ta1odis, ta2odis],
+                 timacr, perar, repar,
+                 [cmp1ar, cmp2ar, cmp3ar, cmp4ar],
+                 dtar, seta1r, rsta1r, seta2r, rsta2r, outar, fltar),
+    HRTIM_TIMB: (timer_b, tbcen, tbswu, [tb1oen, tb2oen], [tb1odis, tb2odis],
+                 timbcr, perbr, repbr,
+                 [cmp1br, cmp2br, cmp3br, cmp4br],
+                 dtbr, setb1r, rstb1r, setb2r, rstb2r, outbr, fltbr),
+    HRTIM_TIMC: (timer_c, tccen, tcswu, [tc1oen, tc2oen], [tc1odis, tc2odis],
+                 timccr, percr, repcr,
+                 [cmp1cr, cmp2cr, cmp3cr, cmp4cr],
+                 dtcr, setc1r, rstc1r, setc2r, rstc2r, outcr, fltcr),
+    HRTIM_TIMD: (timer_d, tdcen, tdswu, [td1oen, td2oen], [td1odis, td2odis],
+                 timdcr, perdr, repdr,
+                 [cmp1dr, cmp2dr, cmp3dr, cmp4dr],
+                 dtdr, setd1r, rstd1r, setd2r, rstd2r, outdr, fltdr),
+    HRTIM_TIME: (timer_e, tecen, teswu, [te1oen, te2oen], [te1odis, te2odis],
+                 timecr, perer, reper,
+                 [cmp1er, cmp2er, cmp3er, cmp4er],
+                 dter, sete1r, rste1r, sete2r, rste2r, outer, flter),
+}
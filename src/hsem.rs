@@ -0,0 +1,242 @@
+//! Hardware Semaphore (HSEM)
+//!
+//! The HSEM provides 32 semaphores, which synchronise processes running on
+//! different cores, or different processes on the same core, without
+//! relying on exclusive memory accesses to shared memory.
+//!
+//! Each semaphore is locked together with the ID of the core that took it,
+//! and an 8-bit process ID. Only the same core and process can release it.
+//!
+//! # Example
+//!
+//! On dual core parts, the Cortex-M7 can signal the Cortex-M4 that the
+//! clocks are configured:
+//!
+//! ```
+//! // Cortex-M7
+//! let mut hsem = dp.HSEM.hsem();
+//! hsem.take(0, 0).unwrap();
+//!
+//! let ccdr = rcc.sys_ck(400.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+//! hsem.release(0, 0);
+//!
+//! // Cortex-M4
+//! let mut hsem = dp.HSEM.hsem();
+//! hsem.listen(0);
+//! while !hsem.is_free_pending(0) {
+//!     cortex_m::asm::wfe();
+//! }
+//! hsem.clear_free_pending(0);
+//! ```
+//!
+//! # Clocking
+//!
+//! The HSEM clock is enabled by [`hsem`](HsemExt::hsem). It is not
+//! disabled by [`free`](Hsem::free), since the HSEM may also be used by the
+//! other core.
+
+use crate::stm32::{HSEM, RCC};
+use cortex_m::interrupt;
+
+/// Number of semaphores
+pub const SEMAPHORES: u8 = 32;
+
+/// ID of this core on the bus
+#[cfg(not(feature = "cm4"))]
+const COREID: u8 = 0x3;
+#[cfg(feature = "cm4")]
+const COREID: u8 = 0x1;
+
+// Interrupt register `$reg` of this core
+#[cfg(not(feature = "rm0399"))]
+macro_rules! core_register {
+    ($rb:expr, $reg:ident) => {
+        $rb.$reg
+    };
+}
+#[cfg(all(feature = "rm0399", not(feature = "cm4")))]
+macro_rules! core_register {
+    ($rb:expr, ier) => {
+        $rb.c1ier
+    };
+    ($rb:expr, icr) => {
+        $rb.c1icr
+    };
+    ($rb:expr, misr) => {
+        $rb.c1misr
+    };
+}
+#[cfg(all(feature = "rm0399", feature = "cm4"))]
+macro_rules! core_register {
+    ($rb:expr, ier) => {
+        $rb.c2ier
+    };
+    ($rb:expr, icr) => {
+        $rb.c2icr
+    };
+    ($rb:expr, misr) => {
+        $rb.c2misr
+    };
+}
+
+/// HSEM error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The semaphore is locked by another core or process
+    Locked,
+}
+
+/// Extension trait for the HSEM peripheral
+pub trait HsemExt {
+    /// Enables the HSEM clock, and returns the HSEM
+    fn hsem(self) -> Hsem;
+}
+
+impl HsemExt for HSEM {
+    fn hsem(self) -> Hsem {
+        // unsafe: Owned exclusive access to this bitfield
+        interrupt::free(|_| {
+            let rcc = unsafe { &*RCC::ptr() };
+            rcc.ahb4enr.modify(|_, w| w.hsemen().set_bit());
+        });
+
+        Hsem { rb: self }
+    }
+}
+
+/// Hardware semaphores
+pub struct Hsem {
+    rb: HSEM,
+}
+
+impl Hsem {
+    /// Takes `semaphore` for `process` with the 2-step lock: a write,
+    /// followed by a read that checks the write was successful
+    pub fn take(&mut self, semaphore: u8, process: u8) -> Result<(), Error> {
+        let r = &self.rb.r[Self::index(semaphore)];
+
+        // NOTE(unsafe) Any core and process ID can be written
+        r.write(|w| unsafe {
+            w.procid()
+                .bits(process)
+                .coreid()
+                .bits(COREID)
+                .lock()
+                .set_bit()
+        });
+        let r = r.read();
+        if r.lock().bit_is_set()
+            && r.coreid().bits() == COREID
+            && r.procid().bits() == process
+        {
+            Ok(())
+        } else {
+            Err(Error::Locked)
+        }
+    }
+
+    /// Takes `semaphore` with the 1-step lock, which is a single read. The
+    /// process ID is 0
+    pub fn fast_take(&mut self, semaphore: u8) -> Result<(), Error> {
+        // The read locks the semaphore if it is free
+        let rlr = self.rb.rlr[Self::index(semaphore)].read();
+        if rlr.lock().bit_is_set()
+            && rlr.coreid().bits() == COREID
+            && rlr.procid().bits() == 0
+        {
+            Ok(())
+        } else {
+            Err(Error::Locked)
+        }
+    }
+
+    /// Releases `semaphore`, if it was taken by `process` on this core
+    ///
+    /// The other core is interrupted if it listens for `semaphore`
+    pub fn release(&mut self, semaphore: u8, process: u8) {
+        // NOTE(unsafe) Any core and process ID can be written
+        self.rb.r[Self::index(semaphore)].write(|w| unsafe {
+            w.procid()
+                .bits(process)
+                .coreid()
+                .bits(COREID)
+                .lock()
+                .clear_bit()
+        });
+    }
+
+    /// Releases all semaphores taken by this core
+    pub fn release_all(&mut self) {
+        let key = self.rb.keyr.read().key().bits();
+
+        // NOTE(unsafe) The key is the one read from KEYR
+        self.rb
+            .cr
+            .write(|w| unsafe { w.key().bits(key).coreid().bits(COREID) });
+    }
+
+    /// Returns `true` if `semaphore` is locked
+    pub fn is_locked(&self, semaphore: u8) -> bool {
+        self.rb.r[Self::index(semaphore)].read().lock().bit_is_set()
+    }
+
+    /// Returns `true` if `semaphore` is locked by this core
+    pub fn is_locked_by_this_core(&self, semaphore: u8) -> bool {
+        let r = self.rb.r[Self::index(semaphore)].read();
+        r.lock().bit_is_set() && r.coreid().bits() == COREID
+    }
+
+    /// Starts listening for `semaphore` to be freed. An interrupt is
+    /// generated on this core when it is freed
+    pub fn listen(&mut self, semaphore: u8) {
+        let mask = Self::mask(semaphore);
+
+        // unsafe: Every bit is the enable of a semaphore. The interrupt
+        // enable register of this core is owned by this driver
+        interrupt::free(|_| {
+            core_register!(self.rb, ier)
+                .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+        });
+    }
+
+    /// Stops listening for `semaphore` to be freed
+    pub fn unlisten(&mut self, semaphore: u8) {
+        let mask = Self::mask(semaphore);
+
+        // unsafe: As above
+        interrupt::free(|_| {
+            core_register!(self.rb, ier)
+                .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        });
+    }
+
+    /// Returns `true` if `semaphore` was freed while listening
+    pub fn is_free_pending(&self, semaphore: u8) -> bool {
+        core_register!(self.rb, misr).read().bits() & Self::mask(semaphore) != 0
+    }
+
+    /// Clears the pending interrupt for `semaphore`
+    pub fn clear_free_pending(&mut self, semaphore: u8) {
+        let mask = Self::mask(semaphore);
+
+        // unsafe: Write 1 to clear, no effect on the other semaphores
+        core_register!(self.rb, icr).write(|w| unsafe { w.bits(mask) });
+    }
+
+    /// Returns the index of `semaphore` in the semaphore registers
+    fn index(semaphore: u8) -> usize {
+        assert!(semaphore < SEMAPHORES, "Not a semaphore");
+        usize::from(semaphore)
+    }
+
+    /// Returns the mask of `semaphore` in the interrupt registers
+    fn mask(semaphore: u8) -> u32 {
+        1 << Self::index(semaphore)
+    }
+
+    /// Releases the HSEM peripheral
+    pub fn free(self) -> HSEM {
+        self.rb
+    }
+}
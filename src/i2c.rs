@@ -1,25 +1,89 @@
 //! Inter Integrated Circuit (I2C)
+//!
+//! # Slave mode
+//!
+//! An I2C peripheral can also respond to a master on the bus. After
+//! creating the peripheral as usual, [`slave_enable`] configures its own
+//! addresses. Events are then read with [`slave_event`], typically from
+//! the I2C event interrupt.
+//!
+//! ```
+//! let mut i2c = dp.I2C1.i2c((scl, sda), 100.khz(), ccdr.peripheral.I2C1, &ccdr.clocks);
+//! i2c.slave_enable(SlaveConfig::new(OwnAddress::SevenBit(0x42)));
+//! i2c.listen(Event::AddressMatch);
+//! i2c.listen(Event::Receive);
+//! i2c.listen(Event::Transmit);
+//! i2c.listen(Event::Stop);
+//!
+//! // In the interrupt handler
+//! match i2c.slave_event() {
+//!     Ok(SlaveEvent::AddressMatch { .. }) => {}
+//!     Ok(SlaveEvent::Receive(byte)) => { /* store byte */ }
+//!     Ok(SlaveEvent::TransmitRequest) => i2c.slave_write(0xAA),
+//!     Ok(SlaveEvent::Stop) => { /* end of transfer */ }
+//!     Err(_) => {}
+//! }
+//! ```
+//!
+//! # Multi-master
+//!
+//! When another master wins arbitration on the bus, the blocking
+//! [Read](I2c#impl-Read), [Write](I2c#impl-Write) and
+//! [WriteRead](I2c#impl-WriteRead) transactions return
+//! [`Error::Arbitration`]. With a [`RetryPolicy`] they are instead retried
+//! after a backoff time, once the bus is free again.
+//!
+//! ```
+//! i2c.set_retry_policy(Some(RetryPolicy {
+//!     retries: 3,
+//!     backoff_cycles: 10_000,
+//! }));
+//! ```
+//!
+//! # Timing
+//!
+//! The timing of the bus is calculated for the kernel clock, see
+//! [`Timing`]. By default the SCL period is calculated for instantaneous
+//! edges, so SCL is never faster than requested, and is slower by the rise
+//! and fall times of the bus. A [`TimingConfig`] with the rise and fall
+//! times of the bus can be set after construction, and the SCL frequency
+//! that is achieved is returned.
+//!
+//! ```
+//! let config = TimingConfig::new(400.khz()).rise_time(120.ns());
+//! let scl = i2c.set_timing(&config, &ccdr.clocks)?;
+//! ```
+//!
+//! [`slave_enable`]: I2c#method.slave_enable
+//! [`slave_event`]: I2c#method.slave_event
 
 use core::cmp;
 use core::marker::PhantomData;
+use core::ops::Deref;
 
 use crate::gpio::gpioa::PA8;
 use crate::gpio::gpiob::{PB10, PB11, PB6, PB7, PB8, PB9};
 use crate::gpio::gpioc::PC9;
+#[cfg(feature = "rm0468")]
+use crate::gpio::gpioc::{PC10, PC11};
 use crate::gpio::gpiod::{PD12, PD13};
 use crate::gpio::gpiof::{PF0, PF1, PF14, PF15};
 use crate::gpio::gpioh::{PH11, PH12, PH4, PH5, PH7, PH8};
-use crate::gpio::{Alternate, AF4, AF6};
+use crate::gpio::{Alternate, IntoAnalog, AF4, AF6};
 use crate::hal::blocking::i2c::{Read, Write, WriteRead};
 use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32;
+#[cfg(feature = "rm0468")]
+use crate::stm32::I2C5;
 use crate::stm32::{I2C1, I2C2, I2C3, I2C4};
-use crate::time::Hertz;
+use crate::time::{Hertz, NanoSeconds};
 use cast::u16;
 
 /// I2C Events
 ///
 /// Each event is a possible interrupt source, if enabled
 #[derive(Copy, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Event {
     /// (TXIE)
     Transmit,
@@ -33,6 +97,8 @@ pub enum Event {
     Errors,
     /// Not Acknowledge received (NACKIE)
     NotAcknowledge,
+    /// Own address matched in slave mode (ADDRIE)
+    AddressMatch,
 }
 
 /// I2C Stop Configuration
@@ -57,12 +123,126 @@ pub enum Error {
     Arbitration,
     /// No ack received
     NotAcknowledge,
-    // Overrun, // slave mode only
-    // Pec, // SMBUS mode only
+    /// Overrun or underrun, slave mode with clock stretching disabled only
+    Overrun,
+    /// PEC mismatch, SMBUS mode only
+    Pec,
     // Timeout, // SMBUS mode only
     // Alert, // SMBUS mode only
 }
 
+/// I2C configuration error, see [try_new](I2c#method.try_new)
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The bus frequency is higher than 1MHz, the maximum for Fast-mode Plus
+    FrequencyTooHigh,
+    /// The bus frequency is zero
+    FrequencyTooLow,
+    /// The kernel clock is too slow for the bus frequency
+    ClockTooSlow,
+    /// The kernel clock is too fast for the bus frequency
+    ClockTooFast,
+    /// The rise or fall time is longer than the I2C specification allows
+    /// at the bus frequency
+    RiseFallTime,
+}
+
+/// Own address of an I2C slave
+#[derive(Copy, Clone, PartialEq)]
+pub enum OwnAddress {
+    /// 7-bit address
+    SevenBit(u8),
+    /// 10-bit address
+    TenBit(u16),
+}
+
+/// I2C slave configuration
+///
+/// ```
+/// let config = SlaveConfig::new(OwnAddress::SevenBit(0x42))
+///     .secondary_address(0x50, 2) // Also respond to 0x50 - 0x53
+///     .general_call(true);
+/// ```
+#[derive(Copy, Clone)]
+pub struct SlaveConfig {
+    address: OwnAddress,
+    secondary_address: Option<(u8, u8)>,
+    general_call: bool,
+    clock_stretching: bool,
+    pec: bool,
+}
+
+impl SlaveConfig {
+    /// Slave configuration responding to `address`
+    pub fn new(address: OwnAddress) -> Self {
+        SlaveConfig {
+            address,
+            secondary_address: None,
+            general_call: false,
+            clock_stretching: true,
+            pec: false,
+        }
+    }
+
+    /// Also respond to the 7-bit `address`, ignoring its lower `mask_bits`
+    /// bits. If `mask_bits` is 7, all addresses except the reserved
+    /// addresses are matched
+    pub fn secondary_address(mut self, address: u8, mask_bits: u8) -> Self {
+        assert!(address < 0x80, "Address must be 7 bits");
+        assert!(mask_bits <= 7);
+        self.secondary_address = Some((address, mask_bits));
+        self
+    }
+
+    /// Respond to the general call address 0x00
+    pub fn general_call(mut self, enable: bool) -> Self {
+        self.general_call = enable;
+        self
+    }
+
+    /// Stretch the clock while waiting for the application to read or
+    /// write data. Enabled by default. If disabled, the application must
+    /// always read or write data within one bus cycle
+    pub fn clock_stretching(mut self, enable: bool) -> Self {
+        self.clock_stretching = enable;
+        self
+    }
+
+    /// Enable SMBus packet error checking (PEC). See
+    /// [`slave_pec`](I2c#method.slave_pec)
+    pub fn pec(mut self, enable: bool) -> Self {
+        self.pec = enable;
+        self
+    }
+}
+
+/// Direction of a transfer addressed to an I2C slave
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Direction {
+    /// The master writes, the slave receives
+    MasterWrite,
+    /// The master reads, the slave transmits
+    MasterRead,
+}
+
+/// I2C slave events
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SlaveEvent {
+    /// One of the own addresses matched. For a 7-bit address match,
+    /// `address` is the matched address. For a 10-bit address match it is
+    /// the address header, containing the two most significant bits of
+    /// the address
+    AddressMatch { address: u8, direction: Direction },
+    /// A byte was received
+    Receive(u8),
+    /// The master is waiting for a byte, which must be written using
+    /// [`slave_write`](I2c#method.slave_write)
+    TransmitRequest,
+    /// A STOP condition was detected
+    Stop,
+}
+
 /// A trait to represent the SCL Pin of an I2C Port
 pub trait PinScl<I2C> {
     fn set_open_drain(self) -> Self;
@@ -90,9 +270,70 @@ where
     }
 }
 
+/// Retry policy for transactions that lost arbitration to another master
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries
+    pub retries: u8,
+    /// Core clock cycles to wait before the first retry. The time is
+    /// doubled for each following retry
+    pub backoff_cycles: u32,
+}
+
+impl RetryPolicy {
+    /// Returns the core clock cycles to wait before retry number `attempt`,
+    /// counting from zero, or `None` if no retries are left
+    fn backoff(&self, attempt: u8) -> Option<u32> {
+        if attempt < self.retries {
+            Some(
+                self.backoff_cycles
+                    .saturating_mul(1 << cmp::min(attempt, 16)),
+            )
+        } else {
+            None
+        }
+    }
+}
+
+/// An I2C peripheral instance
+pub trait Instance:
+    Deref<Target = stm32::i2c1::RegisterBlock> + crate::Sealed
+{
+    /// Reset and enable control of the instance
+    type Rec: ResetEnable;
+
+    /// Event interrupt of the instance
+    const EVENT_INTERRUPT: stm32::Interrupt;
+    /// Error interrupt of the instance
+    const ERROR_INTERRUPT: stm32::Interrupt;
+
+    /// Returns a pointer to the register block
+    fn ptr() -> *const stm32::i2c1::RegisterBlock;
+}
+
 #[derive(Debug)]
 pub struct I2c<I2C> {
     i2c: I2C,
+    retry: Option<RetryPolicy>,
+    /// SCL frequency of the current timing
+    frequency: Hertz,
+}
+
+impl<I2C> I2c<I2C> {
+    /// Sets the policy for retrying blocking transactions after an
+    /// arbitration loss. With `None`, the default, [`Error::Arbitration`]
+    /// is returned immediately
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry = policy;
+    }
+
+    /// Returns the SCL frequency of the current timing, for the rise and
+    /// fall times it was calculated with. This is at most the bus frequency
+    /// that was requested. With the default rise and fall times, SCL never
+    /// runs faster than this
+    pub fn scl_frequency(&self) -> Hertz {
+        self.frequency
+    }
 }
 
 pub trait I2cExt<I2C>: Sized {
@@ -119,6 +360,11 @@ pub trait I2cExt<I2C>: Sized {
         F: Into<Hertz>;
 }
 
+// OAR1 10-bit address mode
+const OAR1_OA1MODE: u32 = 1 << 10;
+// OAR1 / OAR2 own address enable
+const OAR_EN: u32 = 1 << 15;
+
 // Sequence to flush the TXDR register. This resets the TXIS and TXE
 // flags
 macro_rules! flush_txdr {
@@ -159,109 +405,276 @@ macro_rules! busy_wait {
     };
 }
 
-// Calculate I2C timing for Analog Filter ON, Digital Filter OFF
-macro_rules! i2c_timing {
-    ($i2cclk:ident, $freq:ident) => {{
-        // Refer to RM0433 Rev 7 Figure 539 for setup and hold timing:
-        //
-        // t_I2CCLK = 1 / PCLK1
-        // t_PRESC  = (PRESC + 1) * t_I2CCLK
-        // t_SCLL   = (SCLL + 1) * t_PRESC
-        // t_SCLH   = (SCLH + 1) * t_PRESC
-        //
-        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
-        // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
-        let ratio = $i2cclk / $freq;
-
-        // For the standard-mode configuration method, we must have a ratio of 4
-        // or higher
-        assert!(
-            ratio >= 4,
-            "The I2C PCLK must be at least 4 times the bus frequency!"
-        );
+/// Limits of the I2C specification for one speed mode, in picoseconds.
+/// UM10204 rev 6. Table 10
+struct Spec {
+    low_min: u64,
+    high_min: u64,
+    su_dat_min: u64,
+    vd_dat_max: u64,
+    /// Maximum rise time in nanoseconds
+    rise_max: u32,
+    /// Maximum fall time in nanoseconds
+    fall_max: u32,
+}
 
-        let (presc_reg, scll, sclh, sdadel, scldel) = if $freq > 100_000 {
-            // Fast-mode (Fm) or Fast-mode Plus (Fm+)
-            // here we pick SCLL + 1 = 2 * (SCLH + 1)
+impl Spec {
+    fn for_frequency(freq: u32) -> Spec {
+        match freq {
+            // Standard-mode (Sm)
+            f if f <= 100_000 => Spec {
+                low_min: 4_700_000,
+                high_min: 4_000_000,
+                su_dat_min: 250_000,
+                vd_dat_max: 3_450_000,
+                rise_max: 1000,
+                fall_max: 300,
+            },
+            // Fast-mode (Fm)
+            f if f <= 400_000 => Spec {
+                low_min: 1_300_000,
+                high_min: 600_000,
+                su_dat_min: 100_000,
+                vd_dat_max: 900_000,
+                rise_max: 300,
+                fall_max: 300,
+            },
+            // Fast-mode Plus (Fm+)
+            _ => Spec {
+                low_min: 500_000,
+                high_min: 260_000,
+                su_dat_min: 50_000,
+                vd_dat_max: 450_000,
+                rise_max: 120,
+                fall_max: 120,
+            },
+        }
+    }
+}
 
-            // Prescaler, 384 ticks for sclh/scll. Round up then subtract 1
-            let presc_reg = ((ratio - 1) / 384) as u8;
-            // ratio < 1200 by pclk 120MHz max., therefore presc < 16
+/// Delay of the analog noise filter in picoseconds. From H7 Datasheet
+const ANALOG_FILTER_MIN: u64 = 50_000;
+const ANALOG_FILTER_MAX: u64 = 80_000;
 
-            // Actual precale value selected
-            let presc = (presc_reg + 1) as u32;
+/// Picoseconds in one second
+const PS: u64 = 1_000_000_000_000;
 
-            let sclh = ((ratio / presc) - 3) / 3;
-            let scll = (2 * (sclh + 1)) - 1;
+/// Bus frequency and signal edges from which the [`Timing`] is calculated
+///
+/// By default the SCL period is calculated for instantaneous edges, so that
+/// SCL is not faster than `frequency` whatever the rise and fall times are,
+/// and the data hold and setup times are calculated for the maxima of the
+/// I2C specification. The rise time is set by the pull-up resistors and the
+/// bus capacitance. Setting the measured or calculated times gives a bus
+/// frequency closer to the target.
+///
+/// ```
+/// let config = TimingConfig::new(400.khz()).bus_load(2_200, 100);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimingConfig {
+    frequency: Hertz,
+    rise_time: Option<NanoSeconds>,
+    fall_time: Option<NanoSeconds>,
+}
 
-            let (sdadel, scldel) = if $freq > 400_000 {
-                // Fast-mode Plus (Fm+)
-                assert!($i2cclk >= 17_000_000); // See table in datsheet
+impl TimingConfig {
+    /// Bus frequency `frequency`, with the default rise and fall times
+    pub fn new(frequency: impl Into<Hertz>) -> Self {
+        TimingConfig {
+            frequency: frequency.into(),
+            rise_time: None,
+            fall_time: None,
+        }
+    }
 
-                let sdadel = $i2cclk / 8_000_000 / presc;
-                let scldel = $i2cclk / 4_000_000 / presc - 1;
+    /// Sets the rise time of SDA and SCL, from 30% to 70% of VDD
+    pub fn rise_time(mut self, rise_time: impl Into<NanoSeconds>) -> Self {
+        self.rise_time = Some(rise_time.into());
+        self
+    }
 
-                (sdadel, scldel)
-            } else {
-                // Fast-mode (Fm)
-                assert!($i2cclk >= 8_000_000); // See table in datsheet
+    /// Sets the fall time of SDA and SCL, from 70% to 30% of VDD
+    pub fn fall_time(mut self, fall_time: impl Into<NanoSeconds>) -> Self {
+        self.fall_time = Some(fall_time.into());
+        self
+    }
 
-                let sdadel = $i2cclk / 4_000_000 / presc;
-                let scldel = $i2cclk / 2_000_000 / presc - 1;
+    /// Sets the rise time from the resistance of the pull-up resistors in
+    /// ohms, and the capacitance of the bus in picofarads. The rise time
+    /// from 30% to 70% of VDD is 0.8473 * R * C
+    pub fn bus_load(mut self, pull_up_ohms: u32, capacitance_pf: u32) -> Self {
+        let rc = u64::from(pull_up_ohms) * u64::from(capacitance_pf);
+        self.rise_time = Some(NanoSeconds((rc * 8473 / 10_000_000) as u32));
+        self
+    }
+}
 
-                (sdadel, scldel)
-            };
+/// Timing of the I2C bus, calculated for a kernel clock
+///
+/// The SCL low and high periods meet their minima in the I2C
+/// specification, and the data hold and setup times are calculated from
+/// the rise and fall times. Of the timings that meet these, the one with
+/// the SCL frequency closest to, but not above, the target is chosen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Timing {
+    presc: u8,
+    scll: u8,
+    sclh: u8,
+    sdadel: u8,
+    scldel: u8,
+    frequency: Hertz,
+}
 
-            (
-                presc_reg,
-                scll as u8,
-                sclh as u8,
-                sdadel as u8,
-                scldel as u8,
-            )
-        } else {
-            // Standard-mode (Sm)
-            // here we pick SCLL = SCLH
-            assert!($i2cclk >= 2_000_000); // See table in datsheet
+impl Timing {
+    /// Calculates the timing for `config` from the kernel clock
+    /// `i2c_ker_ck`
+    pub fn calculate(
+        i2c_ker_ck: impl Into<Hertz>,
+        config: &TimingConfig,
+    ) -> Result<Self, ConfigError> {
+        let i2cclk = i2c_ker_ck.into().0;
+        let freq = config.frequency.0;
+        if freq > 1_000_000 {
+            return Err(ConfigError::FrequencyTooHigh);
+        }
+        if freq == 0 {
+            return Err(ConfigError::FrequencyTooLow);
+        }
 
-            // Prescaler, 512 ticks for sclh/scll. Round up then
-            // subtract 1
-            let presc = (ratio - 1) / 512;
-            let presc_reg = cmp::min(presc, 15) as u8;
+        // Minimum kernel clock, see table in datasheet
+        let min_i2cclk = match freq {
+            f if f > 400_000 => 17_000_000,
+            f if f > 100_000 => 8_000_000,
+            _ => 2_000_000,
+        };
+        if i2cclk < min_i2cclk || i2cclk / freq < 4 {
+            return Err(ConfigError::ClockTooSlow);
+        }
 
-            // Actual prescale value selected
-            let presc = (presc_reg + 1) as u32;
+        let spec = Spec::for_frequency(freq);
+        if config.rise_time.map_or(false, |t| t.0 > spec.rise_max)
+            || config.fall_time.map_or(false, |t| t.0 > spec.fall_max)
+        {
+            return Err(ConfigError::RiseFallTime);
+        }
 
-            let sclh = ((ratio / presc) - 2) / 2;
-            let scll = sclh;
+        // Refer to RM0433 Rev 7 Section 47.4.5 and Section 47.4.9. Times
+        // are in picoseconds
+        let t_i2cclk = PS / u64::from(i2cclk);
+
+        // Edges for the data hold and setup times. By default the slowest
+        // edges of the specification
+        let t_rise_max =
+            u64::from(config.rise_time.map_or(spec.rise_max, |t| t.0)) * 1000;
+        let t_fall_max =
+            u64::from(config.fall_time.map_or(spec.fall_max, |t| t.0)) * 1000;
+
+        // Edges for the SCL period. By default instantaneous, so that SCL
+        // is not faster than the target with any edges
+        let t_rise = config.rise_time.map_or(0, |t| u64::from(t.0) * 1000);
+        let t_fall = config.fall_time.map_or(0, |t| u64::from(t.0) * 1000);
+
+        // tSDADEL >= tf + tHD;DAT(min) - tAF(min) - 3 * tI2CCLK
+        // tSDADEL <= tVD;DAT(max) - tr - tAF(max) - 4 * tI2CCLK
+        // tSCLDEL >= tr + tSU;DAT(min)
+        let sdadel_min =
+            t_fall_max.saturating_sub(ANALOG_FILTER_MIN + 3 * t_i2cclk);
+        let sdadel_max = spec
+            .vd_dat_max
+            .saturating_sub(t_rise_max + ANALOG_FILTER_MAX + 4 * t_i2cclk);
+        let scldel_min = t_rise_max + spec.su_dat_min;
+
+        // t_SYNC1 + t_SYNC2: the analog filter and synchronisation to the
+        // kernel clock, after each edge of SCL
+        let t_sync = ANALOG_FILTER_MIN + 2 * t_i2cclk;
+        // Target period, and the longest accepted period at 80% of the
+        // target frequency
+        let t_scl_min = PS / u64::from(freq);
+        let t_scl_max = t_scl_min * 5 / 4;
+
+        let mut best: Option<(Timing, u64)> = None;
+        for presc in 0..16 {
+            let t_presc = (presc + 1) * t_i2cclk;
+
+            // tSCLDEL = (SCLDEL + 1) * tPRESC. One more period is left as
+            // margin
+            let scldel = match (0..16).find(|l| l * t_presc >= scldel_min) {
+                Some(scldel) => scldel,
+                None => continue,
+            };
+            let sdadel = match (0..16).find(|a| {
+                a * t_presc >= sdadel_min && a * t_presc <= sdadel_max
+            }) {
+                Some(sdadel) => sdadel,
+                None => continue,
+            };
 
-            // Speed check
-            assert!(
-                sclh < 256,
-                "The I2C PCLK is too fast for this bus frequency!"
-            );
+            for scll in 0..256 {
+                // The SCLL count alone meets the specification. t_LOW must
+                // also be more than 4 kernel clock periods
+                let t_low = (scll + 1) * t_presc + t_sync;
+                if (scll + 1) * t_presc < spec.low_min
+                    || t_low - ANALOG_FILTER_MIN <= 4 * t_i2cclk
+                {
+                    continue;
+                }
 
-            let sdadel = $i2cclk / 2_000_000 / presc;
-            let scldel = $i2cclk / 500_000 / presc - 1;
+                // Shortest high period for both the specification and the
+                // target frequency
+                let t_high_min = cmp::max(
+                    spec.high_min,
+                    t_scl_min.saturating_sub(t_low + t_rise + t_fall),
+                );
+                let sclh =
+                    (t_high_min.saturating_sub(t_sync) + t_presc - 1) / t_presc;
+                let sclh = sclh.saturating_sub(1);
+                if sclh > 255 {
+                    continue;
+                }
 
-            (
-                presc_reg,
-                scll as u8,
-                sclh as u8,
-                sdadel as u8,
-                scldel as u8,
-            )
-        };
+                let t_high = (sclh + 1) * t_presc + t_sync;
+                let t_scl = t_low + t_high + t_rise + t_fall;
+                if t_scl > t_scl_max {
+                    continue;
+                }
 
-        // Sanity check
-        assert!(presc_reg < 16);
+                let error = t_scl - t_scl_min;
+                if best.map_or(true, |(_, e)| error < e) {
+                    let timing = Timing {
+                        presc: presc as u8,
+                        scll: scll as u8,
+                        sclh: sclh as u8,
+                        sdadel: sdadel as u8,
+                        scldel: scldel as u8,
+                        frequency: Hertz((PS / t_scl) as u32),
+                    };
+                    best = Some((timing, error));
+                }
+            }
+        }
 
-        // Keep values within reasonable limits for fast per_ck
-        let sdadel = cmp::max(sdadel, 2);
-        let scldel = cmp::max(scldel, 4);
+        match best {
+            Some((timing, _)) => Ok(timing),
+            None => {
+                // Longest period that can be generated
+                let t_longest =
+                    2 * (256 * 16 * t_i2cclk + t_sync) + t_rise + t_fall;
+                if t_longest < t_scl_min {
+                    Err(ConfigError::ClockTooFast)
+                } else {
+                    Err(ConfigError::ClockTooSlow)
+                }
+            }
+        }
+    }
 
-        (presc_reg, scll, sclh, sdadel, scldel)
-    }};
+    /// Returns the SCL frequency of this timing, for the rise and fall
+    /// times of the [`TimingConfig`]. With the default rise and fall times
+    /// this is the highest frequency that SCL can run at
+    pub fn scl_frequency(&self) -> Hertz {
+        self.frequency
+    }
 }
 
 macro_rules! i2c {
@@ -278,6 +691,9 @@ macro_rules! i2c {
                 /// is out of bounds. The acceptable range is [4, 8192].
                 ///
                 /// Panics if the `frequency` is too fast. The maximum is 1MHz.
+                ///
+                /// Use [`try_new`](#method.try_new) to return an error
+                /// instead.
                 pub fn $i2cX<F> (
                     i2c: $I2CX,
                     frequency: F,
@@ -286,14 +702,33 @@ macro_rules! i2c {
                 ) -> Self where
                     F: Into<Hertz>,
                 {
-                    prec.enable().reset();
-
-                    let freq: u32 = frequency.into().0;
+                    match Self::try_new(i2c, frequency, prec, clocks) {
+                        Ok(i2c) => i2c,
+                        Err((e, _, _)) => panic!("Invalid I2C configuration: {:?}", e),
+                    }
+                }
 
-                    // Maximum f_SCL for Fast-mode Plus (Fm+)
-                    assert!(freq <= 1_000_000);
+                /// Create and initialise a new I2C peripheral.
+                ///
+                /// Returns an error if the bus frequency `frequency` cannot
+                /// be generated from the i2c_ker_ck. The error is returned
+                /// with the peripheral and its `rec`, so that the peripheral
+                /// can be configured again.
+                pub fn try_new<F> (
+                    i2c: $I2CX,
+                    frequency: F,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks
+                ) -> Result<Self, (ConfigError, $I2CX, rec::$Rec)> where
+                    F: Into<Hertz>,
+                {
+                    let config = TimingConfig::new(frequency);
+                    let timing = match Timing::calculate(clocks.$pclkX(), &config) {
+                        Ok(timing) => timing,
+                        Err(e) => return Err((e, i2c, prec)),
+                    };
 
-                    let i2c_clk: u32 = clocks.$pclkX().0;
+                    prec.enable().reset();
 
                     // Clear PE bit in I2C_CR1
                     i2c.cr1.modify(|_, w| w.pe().clear_bit());
@@ -304,24 +739,53 @@ macro_rules! i2c {
                     i2c.cr1.modify(|_, w| w.anfoff().clear_bit());
 
                     // Configure timing
-                    let (presc_reg, scll, sclh, sdadel, scldel) = i2c_timing!(i2c_clk, freq);
+                    Self::write_timing(&i2c, &timing);
+
+                    // Enable the peripheral
+                    i2c.cr1.write(|w| w.pe().set_bit());
+
+                    Ok(I2c {
+                        i2c,
+                        retry: None,
+                        frequency: timing.scl_frequency(),
+                    })
+                }
+
+                /// Sets the bus timing from `config`, calculated for the
+                /// i2c_ker_ck. Returns the SCL frequency
+                ///
+                /// Returns an error if the timing cannot be generated. The
+                /// previous timing is kept in this case.
+                pub fn set_timing(
+                    &mut self,
+                    config: &TimingConfig,
+                    clocks: &CoreClocks,
+                ) -> Result<Hertz, ConfigError> {
+                    let timing = Timing::calculate(clocks.$pclkX(), config)?;
+
+                    // TIMINGR can only be written while PE is clear
+                    let pe = self.i2c.cr1.read().pe().bit_is_set();
+                    self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+                    Self::write_timing(&self.i2c, &timing);
+                    self.i2c.cr1.modify(|_, w| w.pe().bit(pe));
+
+                    self.frequency = timing.scl_frequency();
+                    Ok(self.frequency)
+                }
+
+                fn write_timing(i2c: &$I2CX, timing: &Timing) {
                     i2c.timingr.write(|w|
                         w.presc()
-                            .bits(presc_reg)
+                            .bits(timing.presc)
                             .scll()
-                            .bits(scll)
+                            .bits(timing.scll)
                             .sclh()
-                            .bits(sclh)
+                            .bits(timing.sclh)
                             .sdadel()
-                            .bits(sdadel)
+                            .bits(timing.sdadel)
                             .scldel()
-                            .bits(scldel)
+                            .bits(timing.scldel)
                     );
-
-                    // Enable the peripheral
-                    i2c.cr1.write(|w| w.pe().set_bit());
-
-                    I2c { i2c }
                 }
 
                 /// Returns a reference to the inner peripheral
@@ -349,6 +813,7 @@ macro_rules! i2c {
                             Event::Stop => w.stopie().set_bit(),
                             Event::Errors => w.errie().set_bit(),
                             Event::NotAcknowledge => w.nackie().set_bit(),
+                            Event::AddressMatch => w.addrie().set_bit(),
                         }
                     });
                 }
@@ -363,6 +828,7 @@ macro_rules! i2c {
                             Event::Stop => w.stopie().clear_bit(),
                             Event::Errors => w.errie().clear_bit(),
                             Event::NotAcknowledge => w.nackie().clear_bit(),
+                            Event::AddressMatch => w.addrie().clear_bit(),
                         }
                     });
                     let _ = self.i2c.cr1.read();
@@ -379,6 +845,7 @@ macro_rules! i2c {
                                 .arlocf().set_bit()
                                 .ovrcf().set_bit(),
                             Event::NotAcknowledge => w.nackcf().set_bit(),
+                            Event::AddressMatch => w.addrcf().set_bit(),
                             _ => w
                         }
                     });
@@ -390,6 +857,24 @@ macro_rules! i2c {
                 pub fn free(self) -> ($I2CX, rec::$Rec) {
                     (self.i2c, rec::$Rec { _marker: PhantomData })
                 }
+
+                /// Resets the I2C peripheral and disables its clock, then
+                /// returns `pins` in analog mode
+                ///
+                /// `pins` must be pins of this I2C. The driver does not
+                /// keep the pins given to the constructor. To return them
+                /// here, keep them and use
+                /// [`i2c_unchecked`](I2cExt#tymethod.i2c_unchecked).
+                pub fn deinit<PINS: Pins<$I2CX> + IntoAnalog>(
+                    self,
+                    pins: PINS,
+                ) -> ($I2CX, rec::$Rec, PINS::Analog) {
+                    let prec = rec::$Rec { _marker: PhantomData }
+                        .reset()
+                        .disable();
+
+                    (self.i2c, prec, pins.into_analog())
+                }
             }
 
             /// Master controller methods
@@ -513,6 +998,147 @@ macro_rules! i2c {
                 }
             }
 
+            /// Slave methods
+            ///
+            /// These methods allow the I2C peripheral to respond to a master
+            /// on the bus. The peripheral remains able to act as a master
+            /// when the bus is free.
+            impl I2c<$I2CX> {
+                /// Enables slave mode with the addresses in `config`
+                pub fn slave_enable(&mut self, config: SlaveConfig) {
+                    // NOSTRETCH can only be written when PE = 0
+                    self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+
+                    // Own address 1. OA1EN must be cleared before it is
+                    // modified
+                    self.i2c.oar1.write(|w| unsafe { w.bits(0) });
+                    let oar1 = match config.address {
+                        OwnAddress::SevenBit(addr) => {
+                            assert!(addr < 0x80, "Address must be 7 bits");
+                            u32::from(addr) << 1
+                        }
+                        OwnAddress::TenBit(addr) => {
+                            assert!(addr < 0x400, "Address must be 10 bits");
+                            u32::from(addr) | OAR1_OA1MODE
+                        }
+                    };
+                    self.i2c.oar1.write(|w| unsafe { w.bits(oar1 | OAR_EN) });
+
+                    // Own address 2
+                    self.i2c.oar2.write(|w| unsafe { w.bits(0) });
+                    if let Some((addr, mask_bits)) = config.secondary_address {
+                        let oar2 = u32::from(addr) << 1
+                            | u32::from(mask_bits) << 8;
+                        self.i2c.oar2.write(|w| unsafe { w.bits(oar2 | OAR_EN) });
+                    }
+
+                    self.i2c.cr1.modify(|_, w| {
+                        w.gcen()
+                            .bit(config.general_call)
+                            .nostretch()
+                            .bit(!config.clock_stretching)
+                            .pecen()
+                            .bit(config.pec)
+                            .sbc()
+                            .bit(config.pec)
+                    });
+
+                    self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+                }
+
+                /// Disables slave mode. The own addresses are no longer
+                /// acknowledged
+                pub fn slave_disable(&mut self) {
+                    self.i2c.oar1.write(|w| unsafe { w.bits(0) });
+                    self.i2c.oar2.write(|w| unsafe { w.bits(0) });
+                    self.i2c.cr1.modify(|_, w| {
+                        w.gcen().clear_bit().pecen().clear_bit().sbc().clear_bit()
+                    });
+                }
+
+                /// Returns the next slave event, if any
+                ///
+                /// When one of the own addresses is matched, the clock is
+                /// stretched until this method returns
+                /// [`SlaveEvent::AddressMatch`]. If PEC is enabled, the
+                /// clock is stretched until [`slave_pec`](I2c#method.slave_pec)
+                /// is called instead.
+                pub fn slave_event(&mut self) -> nb::Result<SlaveEvent, Error> {
+                    let isr = self.i2c.isr.read();
+
+                    if isr.berr().is_error() {
+                        self.i2c.icr.write(|w| w.berrcf().set_bit());
+                        Err(nb::Error::Other(Error::Bus))
+                    } else if isr.arlo().is_lost() {
+                        self.i2c.icr.write(|w| w.arlocf().set_bit());
+                        Err(nb::Error::Other(Error::Arbitration))
+                    } else if isr.ovr().bit_is_set() {
+                        self.i2c.icr.write(|w| w.ovrcf().set_bit());
+                        Err(nb::Error::Other(Error::Overrun))
+                    } else if isr.pecerr().bit_is_set() {
+                        self.i2c.icr.write(|w| w.peccf().set_bit());
+                        Err(nb::Error::Other(Error::Pec))
+                    } else if isr.rxne().is_not_empty() {
+                        let byte = self.i2c.rxdr.read().rxdata().bits();
+                        Ok(SlaveEvent::Receive(byte))
+                    } else if isr.addr().bit_is_set() {
+                        let direction = if isr.dir().bit_is_set() {
+                            // Discard any data left from a previous transfer
+                            flush_txdr!(self.i2c);
+                            Direction::MasterRead
+                        } else {
+                            Direction::MasterWrite
+                        };
+                        let address = isr.addcode().bits();
+
+                        if self.i2c.cr1.read().pecen().bit_is_clear() {
+                            self.i2c.icr.write(|w| w.addrcf().set_bit());
+                        }
+                        Ok(SlaveEvent::AddressMatch { address, direction })
+                    } else if isr.txis().bit_is_set() {
+                        Ok(SlaveEvent::TransmitRequest)
+                    } else if isr.stopf().bit_is_set() {
+                        // The master NACKs the last byte it reads
+                        self.i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
+                        flush_txdr!(self.i2c);
+                        Ok(SlaveEvent::Stop)
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                /// Writes a byte in response to
+                /// [`SlaveEvent::TransmitRequest`]
+                pub fn slave_write(&mut self, byte: u8) {
+                    self.i2c.txdr.write(|w| w.txdata().bits(byte));
+                }
+
+                /// Sets the number of data bytes `length` in the transfer
+                /// that followed the last [`SlaveEvent::AddressMatch`],
+                /// and releases the clock. The PEC byte follows the data
+                /// bytes: it is checked on reception and sent on
+                /// transmission.
+                ///
+                /// Only used when PEC is enabled in [`SlaveConfig`]
+                pub fn slave_pec(&mut self, length: u8) {
+                    self.i2c.cr2.modify(|_, w| {
+                        w.nbytes()
+                            .bits(length.saturating_add(1))
+                            .reload()
+                            .clear_bit()
+                            .pecbyte()
+                            .set_bit()
+                    });
+                    self.i2c.icr.write(|w| w.addrcf().set_bit());
+                }
+
+                /// Drives the SMBus alert pin (SMBA) low when `alert` is
+                /// true. The SMBA pin must be configured separately
+                pub fn smbus_alert(&mut self, alert: bool) {
+                    self.i2c.cr1.modify(|_, w| w.alerten().bit(alert));
+                }
+            }
+
             impl I2cExt<$I2CX> for $I2CX {
                 type Rec = rec::$Rec;
 
@@ -563,10 +1189,35 @@ macro_rules! i2c {
                 }
             }
 
-            impl Write for I2c<$I2CX> {
-                type Error = Error;
+            impl I2c<$I2CX> {
+                /// Runs `transaction`, and retries it after an arbitration
+                /// loss according to the retry policy
+                fn retry_arbitration<F>(&mut self, mut transaction: F) -> Result<(), Error>
+                where
+                    F: FnMut(&mut Self) -> Result<(), Error>
+                {
+                    let mut attempt: u8 = 0;
+                    loop {
+                        match transaction(self) {
+                            Err(Error::Arbitration) => {
+                                let backoff = match self.retry.and_then(|p| p.backoff(attempt)) {
+                                    Some(backoff) => backoff,
+                                    None => return Err(Error::Arbitration),
+                                };
+
+                                // Back off, then wait for the other master
+                                // to finish its transaction
+                                cortex_m::asm::delay(backoff);
+                                while self.i2c.isr.read().busy().bit_is_set() {}
+
+                                attempt += 1;
+                            }
+                            result => return result,
+                        }
+                    }
+                }
 
-                fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+                fn write_once(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
                     // TODO support transfers of more than 255 bytes
                     assert!(bytes.len() < 256 && bytes.len() > 0);
 
@@ -593,12 +1244,8 @@ macro_rules! i2c {
 
                     Ok(())
                 }
-            }
-
-            impl WriteRead for I2c<$I2CX> {
-                type Error = Error;
 
-                fn write_read(
+                fn write_read_once(
                     &mut self,
                     addr: u8,
                     bytes: &[u8],
@@ -641,12 +1288,8 @@ macro_rules! i2c {
 
                     Ok(())
                 }
-            }
-
-            impl Read for I2c<$I2CX> {
-                type Error = Error;
 
-                fn read(
+                fn read_once(
                     &mut self,
                     addr: u8,
                     buffer: &mut [u8],
@@ -668,6 +1311,58 @@ macro_rules! i2c {
                     Ok(())
                 }
             }
+
+            impl Write for I2c<$I2CX> {
+                type Error = Error;
+
+                fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+                    self.retry_arbitration(|i2c| i2c.write_once(addr, bytes))
+                }
+            }
+
+            impl WriteRead for I2c<$I2CX> {
+                type Error = Error;
+
+                fn write_read(
+                    &mut self,
+                    addr: u8,
+                    bytes: &[u8],
+                    buffer: &mut [u8],
+                ) -> Result<(), Error> {
+                    self.retry_arbitration(|i2c| {
+                        i2c.write_read_once(addr, bytes, buffer)
+                    })
+                }
+            }
+
+            impl Read for I2c<$I2CX> {
+                type Error = Error;
+
+                fn read(
+                    &mut self,
+                    addr: u8,
+                    buffer: &mut [u8],
+                ) -> Result<(), Error> {
+                    self.retry_arbitration(|i2c| i2c.read_once(addr, buffer))
+                }
+            }
+
+            impl crate::Sealed for $I2CX {}
+            paste::item! {
+                impl Instance for $I2CX {
+                    type Rec = rec::$Rec;
+
+                    const EVENT_INTERRUPT: stm32::Interrupt =
+                        stm32::Interrupt::[<$I2CX _EV>];
+                    const ERROR_INTERRUPT: stm32::Interrupt =
+                        stm32::Interrupt::[<$I2CX _ER>];
+
+                    #[inline(always)]
+                    fn ptr() -> *const stm32::i2c1::RegisterBlock {
+                        $I2CX::ptr()
+                    }
+                }
+            }
         )+
     };
 }
@@ -746,6 +1441,21 @@ pins! {
             PH12<Alternate<AF4>>
         ]
 }
+#[cfg(feature = "rm0468")]
+pins! {
+    I2C5:
+        SCL: [
+            PA8<Alternate<AF6>>,
+            PC11<Alternate<AF4>>,
+            PF1<Alternate<AF6>>
+        ]
+
+        SDA: [
+            PC9<Alternate<AF6>>,
+            PC10<Alternate<AF4>>,
+            PF0<Alternate<AF6>>
+        ]
+}
 
 i2c!(
     I2C1: (i2c1, I2c1, pclk1),
@@ -753,11 +1463,38 @@ i2c!(
     I2C3: (i2c3, I2c3, pclk1),
     I2C4: (i2c4, I2c4, pclk4),
 );
+#[cfg(feature = "rm0468")]
+i2c!(
+    I2C5: (i2c5, I2c5, pclk1),
+);
 
 #[cfg(test)]
 mod tests {
+    use super::{ConfigError, RetryPolicy, Timing, TimingConfig};
+    use crate::time::{Hertz, NanoSeconds};
     use core::cmp;
 
+    /// Checks that the timing for `freq` can be calculated from `i2c_clk`
+    fn check_timing(i2c_clk: u32, freq: u32) -> Result<(), ConfigError> {
+        Timing::calculate(Hertz(i2c_clk), &TimingConfig::new(Hertz(freq)))
+            .map(|_| ())
+    }
+
+    /// Returns PRESC, SCLL, SCLH, SDADEL and SCLDEL for `freq` from
+    /// `i2c_clk`, with the default rise and fall times
+    fn i2c_timing(i2c_clk: u32, freq: u32) -> (u8, u8, u8, u8, u8) {
+        let timing =
+            Timing::calculate(Hertz(i2c_clk), &TimingConfig::new(Hertz(freq)))
+                .unwrap();
+        (
+            timing.presc,
+            timing.scll,
+            timing.sclh,
+            timing.sdadel,
+            timing.scldel,
+        )
+    }
+
     /// Runs a timing testcase over PCLK and I2C clock ranges
     fn i2c_timing_testcase<F>(f: F)
     where
@@ -781,11 +1518,46 @@ mod tests {
         }
     }
 
+    #[test]
+    /// Test that the timing check accepts all the testcases
+    fn i2c_check_timing() {
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
+            assert_eq!(check_timing(i2c_clk, freq), Ok(()));
+        });
+
+        assert_eq!(
+            check_timing(120_000_000, 1_001_000),
+            Err(ConfigError::FrequencyTooHigh)
+        );
+        assert_eq!(
+            check_timing(8_000_000, 2_100_000 / 3),
+            Err(ConfigError::ClockTooSlow)
+        );
+        assert_eq!(
+            check_timing(2_000_000, 1_000_000),
+            Err(ConfigError::ClockTooSlow)
+        );
+        assert_eq!(
+            check_timing(120_000_000, 1_000),
+            Err(ConfigError::ClockTooFast)
+        );
+    }
+
+    #[test]
+    /// Test that a bus frequency of zero is rejected
+    fn i2c_check_timing_zero_frequency() {
+        assert_eq!(
+            check_timing(16_000_000, 0),
+            Err(ConfigError::FrequencyTooLow)
+        );
+        assert_eq!(check_timing(0, 0), Err(ConfigError::FrequencyTooLow));
+    }
+
     #[test]
     /// Test the SCL frequency is within the expected range
     fn i2c_frequency() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, scll, sclh, _, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, scll, sclh, _, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -797,7 +1569,8 @@ mod tests {
             let t_sync1 = t_af_min + 2. * t_i2c_clk;
             let t_sync2 = t_af_min + 2. * t_i2c_clk;
 
-            // See RM0433 Rev 7 Section 47.4.9
+            // See RM0433 Rev 7 Section 47.4.9. With instantaneous edges
+            // SCL is the fastest
             let t_high_low = sclh as f32 + 1. + scll as f32 + 1.;
             let t_scl = t_sync1 + t_sync2 + (t_high_low * presc * t_i2c_clk);
             let f_scl = 1. / t_scl;
@@ -823,7 +1596,7 @@ mod tests {
     /// Test that the low period of SCL is greater than the minimum specification
     fn i2c_scl_low() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, scll, _, _, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, scll, _, _, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -853,7 +1626,7 @@ mod tests {
     /// Test the SDADEL value is greater than the minimum specification
     fn i2c_sdadel_minimum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, sdadel, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, _, _, sdadel, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -892,7 +1665,7 @@ mod tests {
     /// Test the SDADEL value is less than the maximum specification
     fn i2c_sdadel_maximum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, sdadel, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, _, _, sdadel, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -925,7 +1698,7 @@ mod tests {
     /// Test the SCLDEL value is greater than the minimum specification
     fn i2c_scldel_minimum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, _, scldel) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, _, _, _, scldel) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -960,4 +1733,52 @@ mod tests {
             assert!(t_scldel >= t_scldel_minimum);
         });
     }
+
+    #[test]
+    /// Test the timing from explicit rise and fall times
+    fn i2c_rise_fall_time() {
+        let config = TimingConfig::new(Hertz(400_000));
+        let default = Timing::calculate(Hertz(100_000_000), &config).unwrap();
+        let slow = Timing::calculate(
+            Hertz(100_000_000),
+            &config
+                .rise_time(NanoSeconds(300))
+                .fall_time(NanoSeconds(300)),
+        )
+        .unwrap();
+
+        // The reported frequency is never above the target. With the slow
+        // edges accounted for, fewer counts are needed for the period
+        assert!(default.scl_frequency().0 <= 400_000);
+        assert!(slow.scl_frequency().0 <= 400_000);
+        let counts = |t: Timing| u16::from(t.scll) + u16::from(t.sclh);
+        assert!(counts(slow) < counts(default));
+
+        // 4.7k and 100pF is a rise time of 398ns, too slow for Fast-mode
+        assert_eq!(
+            Timing::calculate(Hertz(100_000_000), &config.bus_load(4_700, 100)),
+            Err(ConfigError::RiseFallTime)
+        );
+    }
+
+    #[test]
+    fn i2c_retry_backoff() {
+        let policy = RetryPolicy {
+            retries: 3,
+            backoff_cycles: 1_000,
+        };
+        assert_eq!(policy.backoff(0), Some(1_000));
+        assert_eq!(policy.backoff(1), Some(2_000));
+        assert_eq!(policy.backoff(2), Some(4_000));
+        assert_eq!(policy.backoff(3), None);
+
+        // The backoff saturates
+        let policy = RetryPolicy {
+            retries: 255,
+            backoff_cycles: u32::MAX / 2,
+        };
+        assert_eq!(policy.backoff(1), Some(u32::MAX - 1));
+        assert_eq!(policy.backoff(2), Some(u32::MAX));
+        assert_eq!(policy.backoff(200), Some(u32::MAX));
+    }
 }
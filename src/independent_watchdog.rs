@@ -0,0 +1,161 @@
+//! Independent Watchdog
+//!
+//! The Independent Watchdog (IWDG) is clocked from the LSI oscillator, and so
+//! it keeps running even if the main clock fails. Once started it cannot be
+//! stopped except by a reset.
+//!
+//! The LSI is an RC oscillator, and its frequency varies over temperature and
+//! between devices. The timeout is calculated from the nominal LSI frequency
+//! of 32kHz, so allow some margin when choosing a period. Alternatively, the
+//! LSI frequency can be measured using
+//! [`measure_lsi`](crate::timer::Timer#method.measure_lsi)
+//! and passed to
+//! [`set_lsi_frequency`](IndependentWatchdog::set_lsi_frequency).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use stm32h7xx_hal::{pac, prelude::*};
+//! use stm32h7xx_hal::independent_watchdog::IndependentWatchdog;
+//!
+//! let dp = pac::Peripherals::take().unwrap();
+//!
+//! let mut watchdog = IndependentWatchdog::new(dp.IWDG);
+//! watchdog.start(100.ms());
+//!
+//! loop {
+//!     // Must be fed at least every 100ms
+//!     watchdog.feed();
+//! }
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::hal::watchdog::{Watchdog, WatchdogEnable};
+use crate::time::{Hertz, MilliSeconds};
+
+/// Select Independent Watchdog hardware based on core
+#[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
+use crate::stm32::IWDG;
+#[cfg(all(feature = "rm0399", feature = "cm7"))]
+use crate::stm32::IWDG1 as IWDG;
+#[cfg(all(feature = "rm0399", feature = "cm4"))]
+use crate::stm32::IWDG2 as IWDG;
+
+/// Nominal LSI frequency
+const LSI_HZ: u32 = 32_000;
+/// Maximum prescaler setting (divide by 256)
+const MAX_PR: u8 = 0b110;
+/// Maximum reload value
+const MAX_RL: u16 = 0xFFF;
+
+const KEY_FEED: u16 = 0xAAAA;
+const KEY_START: u16 = 0xCCCC;
+const KEY_ACCESS: u16 = 0x5555;
+
+/// Set once the watchdog has been started by this driver. The IWDG cannot
+/// be read back, and once started it runs until a reset
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Returns `true` if the watchdog has been started by this driver since
+/// the last reset
+pub(crate) fn is_started() -> bool {
+    STARTED.load(Ordering::Relaxed)
+}
+
+/// Implements the Independent Watchdog
+pub struct IndependentWatchdog {
+    iwdg: IWDG,
+    lsi_hz: u32,
+}
+
+impl IndependentWatchdog {
+    /// Returns an Independent Watchdog object. The watchdog is not started
+    /// until [start](WatchdogEnable::start) is called
+    pub fn new(iwdg: IWDG) -> Self {
+        IndependentWatchdog {
+            iwdg,
+            lsi_hz: LSI_HZ,
+        }
+    }
+
+    /// Sets the LSI frequency used to calculate the timeout period, in
+    /// place of the nominal 32kHz. Call this before
+    /// [start](WatchdogEnable::start)
+    pub fn set_lsi_frequency(&mut self, lsi: Hertz) {
+        assert!(lsi.0 >= 1000);
+        self.lsi_hz = lsi.0;
+    }
+
+    /// Returns the maximum timeout period that can be configured, at the
+    /// nominal LSI frequency
+    pub fn max_period() -> MilliSeconds {
+        // timeout = 4 * 2^PR * (RL + 1) / LSI
+        let ticks = (4u32 << MAX_PR) * (u32::from(MAX_RL) + 1);
+        MilliSeconds(ticks / (LSI_HZ / 1000))
+    }
+
+    /// Returns the interval in milliseconds between the watchdog being fed
+    /// and the reset
+    pub fn period(&self) -> MilliSeconds {
+        let pr = u32::from(self.iwdg.pr.read().pr().bits());
+        let rl = u32::from(self.iwdg.rlr.read().rl().bits());
+        MilliSeconds(((4 << pr) * (rl + 1)) / (self.lsi_hz / 1000))
+    }
+
+    /// Releases the IWDG peripheral. A started watchdog keeps running
+    pub fn free(self) -> IWDG {
+        self.iwdg
+    }
+}
+
+impl Watchdog for IndependentWatchdog {
+    /// Feeds the watchdog in order to avoid a reset
+    fn feed(&mut self) {
+        self.iwdg.kr.write(|w| unsafe { w.key().bits(KEY_FEED) });
+    }
+}
+
+impl WatchdogEnable for IndependentWatchdog {
+    type Time = MilliSeconds;
+    /// Starts the watchdog with a given timeout period, if this period is out
+    /// of bounds the function is going to panic
+    fn start<T>(&mut self, period: T)
+    where
+        T: Into<Self::Time>,
+    {
+        let period_ms = period.into().0;
+
+        // LSI ticks in the period. Minimum 1
+        let ticks = core::cmp::max(period_ms * (self.lsi_hz / 1000), 1);
+        assert!(ticks <= (4u32 << MAX_PR) * (u32::from(MAX_RL) + 1));
+
+        // Choose the smallest prescaler that fits, for the best resolution
+        let mut pr = 0;
+        while pr < MAX_PR && (ticks + (4 << pr) - 1) / (4 << pr) > 0x1000 {
+            pr += 1;
+        }
+        let rl = (ticks + (4 << pr) - 1) / (4 << pr) - 1;
+        let rl = core::cmp::min(rl, u32::from(MAX_RL)) as u16;
+
+        // Start the watchdog. This also starts the LSI
+        self.iwdg.kr.write(|w| unsafe { w.key().bits(KEY_START) });
+        STARTED.store(true, Ordering::Relaxed);
+
+        // Enable access to the PR, RLR and WINR registers
+        self.iwdg.kr.write(|w| unsafe { w.key().bits(KEY_ACCESS) });
+
+        self.iwdg.pr.write(|w| w.pr().bits(pr));
+        self.iwdg.rlr.write(|w| w.rl().bits(rl));
+
+        // Wait for the registers to be updated
+        while {
+            let sr = self.iwdg.sr.read();
+            sr.pvu().bit_is_set() || sr.rvu().bit_is_set()
+        } {}
+
+        // Reload the counter with the new value. This also restores write
+        // protection
+        self.feed();
+    }
+}
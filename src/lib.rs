@@ -6,25 +6,40 @@
 //!
 //! * [Power Configuration](crate::pwr)
 //! * [Reset and Clock Control](crate::rcc)
+//! * [Clock Recovery System (CRS)](crate::crs)
+//! * [System initialisation in one step](crate::system)
 //!
 //! Digital IO
 //!
 //! * [General Purpose Input / Output](crate::gpio)
 //! * [External interrupt controller](crate::exti)
+//! * [Pattern generator and logic capture](crate::pattern)
 //!
 //! Analog IO
 //!
-//! * [Analog to Digital Converter (ADC)](crate::adc)
-//! * [Digital to Analog Converter (DAC)](crate::dac)
+//! * [Analog to Digital Converter (ADC)](crate::adc) Feature gate `adc`
+//! * [Analog comparators (COMP)](crate::comp)
+//! * [Digital to Analog Converter (DAC)](crate::dac) Feature gate `dac`
+//! * [Digital filter for sigma delta modulators (DFSDM)](crate::dfsdm)
+//!   Feature gate `dfsdm`
+//! * [Operational amplifiers (OPAMP)](crate::opamp)
+//! * [Overcurrent shutdown of the timer outputs](crate::overcurrent)
+//!   Feature gate `adc`
+//! * [Three-phase current sensing](crate::motor) Feature gate `adc`, RM0433
+//!   and RM0399 parts only
+//! * [Voltage reference buffer (VREFBUF)](crate::vrefbuf)
 //!
 //! Digital Busses
 //!
-//! * [Inter Integrated Circuit (I2C)](crate::i2c)
-//! * [Serial Peripheral Interface (SPI)](crate::spi)
-//! * [Serial Data (USART/UART)](crate::serial)
-//! * [Serial Audio Interface](crate::sai)
+//! * [Inter Integrated Circuit (I2C)](crate::i2c) Feature gate `i2c`
+//! * [Serial Peripheral Interface (SPI)](crate::spi) Feature gate `spi`
+//! * [Serial Data (USART/UART)](crate::serial) Feature gate `serial`
+//! * [Serial Audio Interface](crate::sai) Feature gate `sai`
 //! * [Quad SPI](crate::qspi) Feature gate `qspi`
 //! * [Ethernet](crate::ethernet) Feature gate `ethernet`
+//! * [USB CDC-ACM serial port](crate::usb_serial) Feature gate `usb_serial`
+//! * [MDIO slave (MDIOS)](crate::mdios)
+//! * [Bit-banged I2C and SPI](crate::bitbang)
 //!
 //! External Memory
 //!
@@ -34,14 +49,61 @@
 //! Timing functions
 //!
 //! * [Pulse Width Modulation (PWM)](crate::pwm)
+//! * [High-Resolution Timer (HRTIM)](crate::hrtim) RM0433 and RM0399 parts
+//!   only
+//! * [Input Capture](crate::capture)
 //! * [Quadrature Encoder Interface](crate::qei)
 //! * [Timers](crate::timer)
 //! * [Delays](crate::delay)
 //!
+//! Graphics
+//!
+//! * [Chrom-ART Accelerator (DMA2D)](crate::dma2d) Feature gate `dma2d`
+//! * [Chrom-GRC (GFXMMU)](crate::gfxmmu) RM0455 parts only
+//!
 //! Others
 //!
-//! * [Random Number Generator](crate::rng)
+//! * [Backup SRAM](crate::backup_sram)
+//! * [Health of the backup battery](crate::coin_cell) Feature gate `rtc`
+//! * [Cryptographic processor (CRYP)](crate::cryp) Crypto parts only
+//! * [Cyclic Redundancy Check (CRC)](crate::crc)
+//! * [Diagnostics channel](crate::diag) Feature gate `diag`
+//! * [Hardware Semaphore (HSEM)](crate::hsem)
+//! * [Firmware update to the inactive flash bank](crate::flash::update)
+//!   Dual bank RM0433 and RM0399 parts only
+//! * [Flash programming](crate::flash::program) and [option
+//!   bytes](crate::flash::options) RM0433 and RM0399 parts only
+//! * [Hash processor (HASH)](crate::hash) Crypto parts only
+//! * [Independent Watchdog](crate::independent_watchdog)
+//! * [Integrity attestation](crate::secure) Crypto parts only
+//! * [On-The-Fly Decryption (OTFDEC)](crate::otfdec) Crypto RM0455 and
+//!   RM0468 parts only
+//! * [Persistent calibration storage](crate::persist)
+//! * [Placement of DMA buffers, and the MPU](crate::mpu)
+//! * [Random Number Generator](crate::rng) Feature gate `rng`
+//! * [SWO trace output](crate::trace)
+//! * [Supervision of tasks by a watchdog](crate::supervisor)
 //! * [System Window Watchdog](crate::watchdog)
+//! * [Telemetry ring buffer](crate::telemetry)
+//! * [Data watchpoints](crate::watchpoint)
+//!
+//! The feature gates `adc`, `dac`, `dfsdm`, `dma2d`, `i2c`, `rng`, `sai`,
+//! `serial` and `spi` are enabled by default. To reduce build times, disable
+//! the default features and enable only those that are used.
+//!
+//! With the feature gate `fugit`, the rates and durations of the
+//! [`fugit`](https://docs.rs/fugit) crate can be used wherever this crate
+//! takes its own [time units](crate::time). `fugit` needs a newer Rust than
+//! the MSRV of this crate.
+//!
+//! Some invariants of the drivers are checked with a policy chosen by
+//! features: the clock configuration in [`rcc`](crate::rcc), and the
+//! kernel clocks of the serial and SPI drivers. With `assert-defmt` these
+//! checks panic with `defmt::panic!`, and with `assert-error` the clock
+//! configuration errors are returned by
+//! [`Rcc::try_freeze`](crate::rcc::Rcc::try_freeze). The other checks of the
+//! drivers, such as an SPI frequency that cannot be reached, still panic
+//! with `core::panic!`.
 
 #![cfg_attr(not(test), no_std)]
 #![allow(non_camel_case_types)]
@@ -51,6 +113,13 @@ extern crate paste;
 #[derive(Debug)]
 pub enum Never {}
 
+mod sealed {
+    /// Prevents the `Instance` traits of the drivers from being implemented
+    /// outside of this crate
+    pub trait Sealed {}
+}
+pub(crate) use sealed::Sealed;
+
 #[cfg(not(feature = "device-selected"))]
 compile_error!(
     "This crate requires one of the following device features enabled:
@@ -67,6 +136,11 @@ compile_error!(
         stm32h7b3
         stm32h7b0
         stm32h7a3
+        stm32h723
+        stm32h725
+        stm32h730
+        stm32h733
+        stm32h735
 "
 );
 
@@ -79,23 +153,15 @@ pub use nb::block;
 pub use stm32h7::Variant;
 
 // Single core
-#[cfg(any(
-    feature = "stm32h742",
-    feature = "stm32h743",
-    feature = "stm32h750",
-))]
+#[cfg(any(feature = "stm32h742", feature = "stm32h743",))]
 pub use stm32h7::stm32h743 as stm32;
-#[cfg(any(
-    feature = "stm32h742v",
-    feature = "stm32h743v",
-    feature = "stm32h750v",
-))]
+#[cfg(any(feature = "stm32h742v", feature = "stm32h743v",))]
 pub use stm32h7::stm32h743v as stm32;
 
 // Single core with crypto
-#[cfg(any(feature = "stm32h753",))]
+#[cfg(any(feature = "stm32h753", feature = "stm32h750",))]
 pub use stm32h7::stm32h753 as stm32;
-#[cfg(any(feature = "stm32h753v",))]
+#[cfg(any(feature = "stm32h753v", feature = "stm32h750v",))]
 pub use stm32h7::stm32h753v as stm32;
 
 // Dual core
@@ -113,6 +179,16 @@ pub use stm32h7::stm32h757cm7 as stm32;
 ))]
 pub use stm32h7::stm32h7b3 as stm32;
 
+// High Speed Value Line
+#[cfg(any(
+    feature = "stm32h723",
+    feature = "stm32h725",
+    feature = "stm32h730",
+    feature = "stm32h733",
+    feature = "stm32h735",
+))]
+pub use stm32h7::stm32h735 as stm32;
+
 #[cfg(all(feature = "rm0433", feature = "rm0399"))]
 compile_error!("Cannot not select both rm0433 and rm0399");
 
@@ -129,13 +205,43 @@ pub use crate::stm32 as device;
 pub use crate::stm32::interrupt;
 
 #[cfg(feature = "device-selected")]
+#[macro_use]
+mod assert;
+#[cfg(feature = "device-selected")]
+mod found;
+
+#[cfg(all(feature = "device-selected", feature = "adc"))]
 pub mod adc;
 #[cfg(feature = "device-selected")]
+pub mod backup_sram;
+#[cfg(feature = "device-selected")]
+pub mod bitbang;
+#[cfg(feature = "device-selected")]
+pub mod capture;
+#[cfg(all(feature = "device-selected", feature = "rtc"))]
+pub mod coin_cell;
+#[cfg(feature = "device-selected")]
+pub mod comp;
+#[cfg(feature = "device-selected")]
+pub mod crc;
+#[cfg(feature = "device-selected")]
+pub mod crs;
+#[cfg(all(feature = "device-selected", feature = "crypto"))]
+pub mod cryp;
+#[cfg(all(feature = "device-selected", feature = "dac"))]
 pub mod dac;
 #[cfg(feature = "device-selected")]
+pub mod debounce;
+#[cfg(feature = "device-selected")]
 pub mod delay;
+#[cfg(all(feature = "device-selected", feature = "dfsdm"))]
+pub mod dfsdm;
+#[cfg(all(feature = "device-selected", feature = "diag"))]
+pub mod diag;
 #[cfg(feature = "device-selected")]
 pub mod dma;
+#[cfg(all(feature = "device-selected", feature = "dma2d"))]
+pub mod dma2d;
 #[cfg(all(
     feature = "device-selected",
     feature = "ethernet",
@@ -148,13 +254,50 @@ pub mod exti;
 pub mod flash;
 #[cfg(all(feature = "device-selected", feature = "fmc"))]
 pub mod fmc;
+#[cfg(all(feature = "device-selected", feature = "rm0455"))]
+pub mod gfxmmu;
 #[cfg(feature = "device-selected")]
 pub mod gpio;
+#[cfg(all(feature = "device-selected", feature = "crypto"))]
+pub mod hash;
+#[cfg(all(
+    feature = "device-selected",
+    any(feature = "rm0433", feature = "rm0399")
+))]
+pub mod hrtim;
 #[cfg(feature = "device-selected")]
+pub mod hsem;
+#[cfg(all(feature = "device-selected", feature = "i2c"))]
 pub mod i2c;
+#[cfg(feature = "device-selected")]
+pub mod independent_watchdog;
 #[cfg(all(feature = "device-selected", feature = "ltdc"))]
 pub mod ltdc;
 #[cfg(feature = "device-selected")]
+pub mod mdios;
+#[cfg(all(
+    feature = "device-selected",
+    feature = "adc",
+    any(feature = "rm0433", feature = "rm0399")
+))]
+pub mod motor;
+#[cfg(feature = "device-selected")]
+pub mod mpu;
+#[cfg(feature = "device-selected")]
+pub mod opamp;
+#[cfg(all(
+    feature = "device-selected",
+    feature = "crypto",
+    any(feature = "rm0455", feature = "rm0468")
+))]
+pub mod otfdec;
+#[cfg(all(feature = "device-selected", feature = "adc"))]
+pub mod overcurrent;
+#[cfg(feature = "device-selected")]
+pub mod pattern;
+#[cfg(feature = "device-selected")]
+pub mod persist;
+#[cfg(feature = "device-selected")]
 pub mod prelude;
 #[cfg(feature = "device-selected")]
 pub mod pwm;
@@ -165,30 +308,46 @@ pub mod qei;
 #[cfg(all(
     feature = "device-selected",
     feature = "quadspi",
-    not(feature = "rm0455")
+    not(any(feature = "rm0455", feature = "rm0468"))
 ))]
 pub mod qspi;
 #[cfg(feature = "device-selected")]
 pub mod rcc;
-#[cfg(feature = "device-selected")]
+#[cfg(all(feature = "device-selected", feature = "rng"))]
 pub mod rng;
 #[cfg(all(feature = "device-selected", feature = "rtc"))]
 pub mod rtc;
-#[cfg(feature = "device-selected")]
+#[cfg(all(feature = "device-selected", feature = "sai"))]
 pub mod sai;
 #[cfg(all(feature = "device-selected", feature = "sdmmc"))]
 pub mod sdmmc;
-#[cfg(feature = "device-selected")]
+#[cfg(all(feature = "device-selected", feature = "crypto"))]
+pub mod secure;
+#[cfg(all(feature = "device-selected", feature = "serial"))]
 pub mod serial;
 #[cfg(feature = "device-selected")]
 pub mod signature;
-#[cfg(feature = "device-selected")]
+#[cfg(all(feature = "device-selected", feature = "spi"))]
 pub mod spi;
 #[cfg(feature = "device-selected")]
+pub mod supervisor;
+#[cfg(feature = "device-selected")]
+pub mod system;
+#[cfg(feature = "device-selected")]
+pub mod telemetry;
+#[cfg(feature = "device-selected")]
 pub mod time;
 #[cfg(feature = "device-selected")]
 pub mod timer;
+#[cfg(feature = "device-selected")]
+pub mod trace;
 #[cfg(all(feature = "device-selected", feature = "usb_hs"))]
 pub mod usb_hs;
+#[cfg(all(feature = "device-selected", feature = "usb_serial"))]
+pub mod usb_serial;
+#[cfg(feature = "device-selected")]
+pub mod vrefbuf;
 #[cfg(feature = "device-selected")]
 pub mod watchdog;
+#[cfg(feature = "device-selected")]
+pub mod watchpoint;
@@ -101,6 +101,18 @@ impl Ltdc {
     }
 }
 
+/// Extension trait for the LTDC peripheral
+pub trait LtdcExt: Sized {
+    /// Reset and enable the LTDC, see [`Ltdc::new`]
+    fn ltdc(self, prec: rec::Ltdc, clocks: &CoreClocks) -> Ltdc;
+}
+
+impl LtdcExt for LTDC {
+    fn ltdc(self, prec: rec::Ltdc, clocks: &CoreClocks) -> Ltdc {
+        Ltdc::new(self, prec, clocks)
+    }
+}
+
 impl DisplayController for Ltdc {
     /// Initialize the LTDC with a given configuration
     ///
@@ -0,0 +1,215 @@
+//! Management Data Input/Output Slave (MDIOS)
+//!
+//! The MDIOS makes the device appear as a PHY on an MDIO bus, so that it
+//! can be managed by an Ethernet switch or a host MAC. It has 32 input
+//! registers, written by the MDIO master, and 32 output registers, read by
+//! the MDIO master.
+//!
+//! # Example
+//!
+//! ```
+//! let _mdc = gpioa.pa6.into_alternate_af11();
+//! let _mdio = gpioa.pa10.into_alternate_af11();
+//!
+//! let mut mdios = dp.MDIOS.mdios(0x01, ccdr.peripheral.MDIOS);
+//!
+//! // Status register, read by the master
+//! mdios.write_register(1, 0x7809);
+//! mdios.listen(Event::Write);
+//!
+//! // In the interrupt handler
+//! while let Some((register, value)) = mdios.take_written() {
+//!     // Handle `value` written to `register`
+//! }
+//! ```
+//!
+//! The master can read an output register at any time, so output registers
+//! should be kept up to date. [`read_flags`](Mdios::read_flags) shows which
+//! have been read.
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::MDIOS;
+
+/// Number of input and output registers
+pub const REGISTERS: u8 = 32;
+
+/// MDIOS events
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The master wrote an input register
+    Write,
+    /// The master read an output register
+    Read,
+    /// A frame error occurred
+    Error,
+}
+
+/// MDIOS frame error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A frame was received without a preamble
+    Preamble,
+    /// A frame had an invalid start bit sequence
+    Start,
+    /// A write frame had an invalid turnaround sequence
+    Turnaround,
+}
+
+/// Extension trait for the MDIOS peripheral
+pub trait MdiosExt {
+    /// Enables the MDIOS at `port_address` (0 - 31)
+    fn mdios(self, port_address: u8, prec: rec::Mdios) -> Mdios;
+}
+
+impl MdiosExt for MDIOS {
+    fn mdios(self, port_address: u8, prec: rec::Mdios) -> Mdios {
+        assert!(port_address < 32, "Port address must be less than 32");
+        prec.enable().reset();
+
+        self.cr.write(|w| unsafe {
+            w.port_address().bits(port_address).en().set_bit()
+        });
+        Mdios { rb: self }
+    }
+}
+
+// Accesses the input register DINRn or the output register DOUTRn for a
+// register number, as they are not an array in the PAC
+macro_rules! data_registers {
+    ($($n:literal),+) => {
+        paste::item! {
+            impl Mdios {
+                fn read_input(&self, register: u8) -> u16 {
+                    match register {
+                        $($n => self.rb.[<dinr $n>].read().bits() as u16,)+
+                        _ => unreachable!(),
+                    }
+                }
+
+                fn write_output(&mut self, register: u8, value: u16) {
+                    let value = u32::from(value);
+                    match register {
+                        $(
+                            $n => self.rb.[<doutr $n>]
+                                .write(|w| unsafe { w.bits(value) }),
+                        )+
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        }
+    };
+}
+
+data_registers!(
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+    21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31
+);
+
+/// MDIOS slave
+pub struct Mdios {
+    rb: MDIOS,
+}
+
+impl Mdios {
+    /// Starts listening for an event
+    pub fn listen(&mut self, event: Event) {
+        self.rb.cr.modify(|_, w| match event {
+            Event::Write => w.wrie().set_bit(),
+            Event::Read => w.rdie().set_bit(),
+            Event::Error => w.eie().set_bit(),
+        });
+    }
+
+    /// Stops listening for an event
+    pub fn unlisten(&mut self, event: Event) {
+        self.rb.cr.modify(|_, w| match event {
+            Event::Write => w.wrie().clear_bit(),
+            Event::Read => w.rdie().clear_bit(),
+            Event::Error => w.eie().clear_bit(),
+        });
+    }
+
+    /// Disables the check for the preamble, for masters that do not send
+    /// one
+    pub fn set_preamble_check(&mut self, check: bool) {
+        self.rb.cr.modify(|_, w| w.dpc().bit(!check));
+    }
+
+    /// Returns the write flags. Bit `n` is set when the master has written
+    /// input register `n`
+    pub fn write_flags(&self) -> u32 {
+        self.rb.wrfr.read().bits()
+    }
+
+    /// Reads input register `register`, and clears its write flag
+    pub fn read_register(&mut self, register: u8) -> u16 {
+        assert!(register < REGISTERS);
+
+        let value = self.read_input(register);
+        // Write-1-to-clear, only this register's flag
+        self.rb.cwrfr.write(|w| unsafe { w.bits(1 << register) });
+        value
+    }
+
+    /// Returns the lowest numbered input register that the master has
+    /// written, and its value. The write flag is cleared
+    pub fn take_written(&mut self) -> Option<(u8, u16)> {
+        let flags = self.write_flags();
+        if flags == 0 {
+            return None;
+        }
+        let register = flags.trailing_zeros() as u8;
+        Some((register, self.read_register(register)))
+    }
+
+    /// Writes output register `register`, which is then returned to the
+    /// master when it is read
+    pub fn write_register(&mut self, register: u8, value: u16) {
+        assert!(register < REGISTERS);
+
+        self.write_output(register, value);
+    }
+
+    /// Returns the read flags. Bit `n` is set when the master has read
+    /// output register `n`
+    pub fn read_flags(&self) -> u32 {
+        self.rb.rdfr.read().bits()
+    }
+
+    /// Clears the read flags in `mask`
+    pub fn clear_read_flags(&mut self, mask: u32) {
+        // Write-1-to-clear
+        self.rb.crdfr.write(|w| unsafe { w.bits(mask) });
+    }
+
+    /// Checks for a frame error. The error flag is cleared
+    pub fn check_errors(&mut self) -> Result<(), Error> {
+        let sr = self.rb.sr.read();
+        self.rb.clrfr.write(|w| {
+            w.cperf()
+                .bit(sr.perf().bit_is_set())
+                .cserf()
+                .bit(sr.serf().bit_is_set())
+                .cterf()
+                .bit(sr.terf().bit_is_set())
+        });
+
+        if sr.perf().bit_is_set() {
+            Err(Error::Preamble)
+        } else if sr.serf().bit_is_set() {
+            Err(Error::Start)
+        } else if sr.terf().bit_is_set() {
+            Err(Error::Turnaround)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Disables the MDIOS, and releases the peripheral
+    pub fn free(self) -> MDIOS {
+        self.rb.cr.modify(|_, w| w.en().clear_bit());
+        self.rb
+    }
+}
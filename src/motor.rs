@@ -0,0 +1,202 @@
+//! Three-phase current sensing for motor control
+//!
+//! Field oriented control of a three-phase motor needs the current of each
+//! phase measured at the same point of every PWM period. A
+//! [`PhaseSensing`] sets this up from the PWM channels of TIM1 and the
+//! three ADCs:
+//!
+//! * TIM1 counts center-aligned, so that the PWM period is symmetric
+//! * Channel 4 of TIM1 is used internally as the sample point, and drives
+//!   TRGO2
+//! * TRGO2 triggers an injected sequence on each of ADC1, ADC2 and ADC3,
+//!   so that the three phases are converted at the same time
+//!
+//! The ADCs and PWM channels are constructed as usual, and are then moved
+//! into a [`PhaseSensing`].
+//!
+//! ```
+//! let pwm = dp.TIM1.pwm(pins, 20.khz(), ccdr.peripheral.TIM1, &ccdr.clocks);
+//! let (adc1, adc2) = adc::adc12(dp.ADC1, dp.ADC2, &mut delay, adc12_rec, &ccdr.clocks);
+//! let adc3 = adc::Adc::adc3(dp.ADC3, &mut delay, adc3_rec, &ccdr.clocks);
+//!
+//! // The current of each phase is the first channel of a sequence. Other
+//! // channels, such as the bus voltage, can follow it
+//! let mut sensing = PhaseSensing::new(
+//!     pwm,
+//!     (adc1.enable(), InjectedSequence::new().channel(&shunt_a).channel(&vbus)),
+//!     (adc2.enable(), InjectedSequence::new().channel(&shunt_b)),
+//!     (adc3.enable(), InjectedSequence::new().channel(&shunt_c)),
+//! );
+//! sensing.listen();
+//!
+//! // In the ADC interrupt, once per PWM period
+//! if let Ok(sample) = sensing.read() {
+//!     let [a, b, c] = sample.phases();
+//!     // Run the current controller, and update the duties
+//!     sensing.pwm().0.set_duty(duty_a);
+//! }
+//! ```
+//!
+//! # Sample point
+//!
+//! The PWM channels are in PWM mode 1, so the outputs are inactive around
+//! the peak of the counter. This is the trough of the PWM waveform, where
+//! the low side switches conduct and low side shunts carry the phase
+//! currents. The sequences are triggered at the peak by default, and
+//! earlier with [`set_sample_point`](PhaseSensing::set_sample_point), for
+//! example to allow for the conversion time with high duties.
+
+use crate::adc::{
+    Adc, Enabled, Event, InjectedSequence, InjectedTrigger,
+    InjectedTriggerSource, TriggerEdge,
+};
+use crate::pwm::{Alignment, Pwm, C1, C2, C3};
+use crate::stm32::{ADC1, ADC2, ADC3, TIM1};
+
+/// The PWM channels of the three phases
+pub type PhasePwm = (Pwm<TIM1, C1>, Pwm<TIM1, C2>, Pwm<TIM1, C3>);
+
+/// Results of the injected sequences of one PWM period
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// Results of ADC1, in the order of its sequence
+    pub adc1: [u32; 4],
+    /// Results of ADC2, in the order of its sequence
+    pub adc2: [u32; 4],
+    /// Results of ADC3, in the order of its sequence
+    pub adc3: [u32; 4],
+}
+
+impl Sample {
+    /// Returns the results of the first channel of each ADC, which are the
+    /// phase currents
+    pub fn phases(&self) -> [u32; 3] {
+        [self.adc1[0], self.adc2[0], self.adc3[0]]
+    }
+}
+
+/// Timer-synchronised current sensing of the three phases
+pub struct PhaseSensing {
+    pwm: PhasePwm,
+    adc1: Adc<ADC1, Enabled>,
+    adc2: Adc<ADC2, Enabled>,
+    adc3: Adc<ADC3, Enabled>,
+}
+
+impl PhaseSensing {
+    /// Makes TIM1 center-aligned, and starts the injected sequences of the
+    /// three ADCs on each sample point
+    ///
+    /// # Panics
+    ///
+    /// Panics if a sequence is empty
+    pub fn new(
+        mut pwm: PhasePwm,
+        adc1: (Adc<ADC1, Enabled>, InjectedSequence<ADC1>),
+        adc2: (Adc<ADC2, Enabled>, InjectedSequence<ADC2>),
+        adc3: (Adc<ADC3, Enabled>, InjectedSequence<ADC3>),
+    ) -> Self {
+        // Changing to center-aligned counting only halves the period
+        pwm.0.set_alignment(Alignment::Center).unwrap();
+
+        // NOTE(unsafe) Channel 4 and TRGO2 of TIM1 are not used by the PWM
+        // channels, which are owned by self
+        let tim = unsafe { &*TIM1::ptr() };
+        let arr = tim.arr.read().arr().bits();
+        tim.ccr4.write(|w| w.ccr().bits(arr));
+        tim.ccmr2_output().modify(|_, w| unsafe {
+            w.cc4s()
+                .bits(0b00) // Output
+                .oc4m()
+                .pwm_mode2()
+                .oc4pe()
+                .set_bit()
+        });
+        // TRGO2 on OC4REF
+        tim.cr2.modify(|_, w| unsafe { w.mms2().bits(0b0111) });
+
+        let trigger = InjectedTrigger::External(
+            InjectedTriggerSource::TIM1_TRGO2,
+            TriggerEdge::Rising,
+        );
+        let (mut adc1, seq1) = adc1;
+        let (mut adc2, seq2) = adc2;
+        let (mut adc3, seq3) = adc3;
+        adc1.start_injected(&seq1, trigger);
+        adc2.start_injected(&seq2, trigger);
+        adc3.start_injected(&seq3, trigger);
+
+        PhaseSensing {
+            pwm,
+            adc1,
+            adc2,
+            adc3,
+        }
+    }
+
+    /// Moves the sample point to `ticks` timer ticks before the peak of
+    /// the counter
+    pub fn set_sample_point(&mut self, ticks: u16) {
+        // NOTE(unsafe) As above
+        let tim = unsafe { &*TIM1::ptr() };
+        let arr = tim.arr.read().arr().bits();
+        tim.ccr4.write(|w| w.ccr().bits(arr.saturating_sub(ticks)));
+    }
+
+    /// Interrupts at the end of the sequence of ADC3
+    ///
+    /// The sequences start together, so this is after the other ADCs have
+    /// completed sequences of the same length.
+    pub fn listen(&mut self) {
+        self.adc3.listen(Event::InjectedSequence);
+    }
+
+    /// Stops interrupting at the end of the sequences
+    pub fn unlisten(&mut self) {
+        self.adc3.unlisten(Event::InjectedSequence);
+    }
+
+    /// Returns the results of the three ADCs for the last PWM period
+    ///
+    /// Returns `WouldBlock` until all three sequences have completed.
+    pub fn read(&mut self) -> nb::Result<Sample, ()> {
+        if !(self.adc1.is_pending(Event::InjectedSequence)
+            && self.adc2.is_pending(Event::InjectedSequence)
+            && self.adc3.is_pending(Event::InjectedSequence))
+        {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        Ok(Sample {
+            adc1: self.adc1.read_injected()?,
+            adc2: self.adc2.read_injected()?,
+            adc3: self.adc3.read_injected()?,
+        })
+    }
+
+    /// Returns the PWM channels of the three phases, to update their duties
+    pub fn pwm(&mut self) -> &mut PhasePwm {
+        &mut self.pwm
+    }
+
+    /// Stops the injected sequences, and releases the PWM channels and the
+    /// ADCs
+    pub fn free(
+        mut self,
+    ) -> (
+        PhasePwm,
+        Adc<ADC1, Enabled>,
+        Adc<ADC2, Enabled>,
+        Adc<ADC3, Enabled>,
+    ) {
+        self.adc1.stop_injected();
+        self.adc2.stop_injected();
+        self.adc3.stop_injected();
+
+        // NOTE(unsafe) As above
+        let tim = unsafe { &*TIM1::ptr() };
+        tim.cr2.modify(|_, w| unsafe { w.mms2().bits(0b0000) });
+
+        (self.pwm, self.adc1, self.adc2, self.adc3)
+    }
+}
@@ -0,0 +1,287 @@
+//! Placement of DMA buffers and descriptors, and the MPU
+//!
+//! The DMA controllers of the H7 reach different RAMs: MDMA and the SDMMC1
+//! IDMA the AXI SRAM (D1), DMA1/DMA2 and the Ethernet DMA the AHB SRAMs
+//! (D2), and BDMA only SRAM4 (D3). None of them can reach the DTCM, where
+//! statics are placed by default.
+//!
+//! The [`dma_buffer!`](crate::dma_buffer) and
+//! [`eth_descriptors!`](crate::eth_descriptors) macros place a static in
+//! the right RAM, aligned to the 32-byte lines of the data cache.
+//!
+//! ```
+//! use stm32h7xx_hal::{dma_buffer, eth_descriptors, mpu};
+//!
+//! dma_buffer!(domain = d1, static mut TX_BUFFER: [u8; 64] = [0; 64]);
+//! dma_buffer!(domain = d3, static mut I2C_BUFFER: [u8; 16] = [0; 16]);
+//! eth_descriptors!(static mut DES_RING);
+//!
+//! // Ethernet descriptors are accessed in order by the CPU and the
+//! // Ethernet DMA, so SRAM3 is not cached
+//! mpu::configure(
+//!     &mut cp.MPU,
+//!     &mut cp.SCB,
+//!     &[mpu::Ram::Sram3.region(mpu::Attributes::NonCacheable)],
+//! );
+//! cp.SCB.enable_dcache(&mut cp.CPUID);
+//!
+//! let tx_buffer: &'static mut [u8; 64] = unsafe { &mut TX_BUFFER.0 };
+//! ```
+//!
+//! # Linker script
+//!
+//! The macros use the sections of the `memory.x` in this crate, which must
+//! be in the linker script of the application:
+//!
+//! | Domain | Section            | RAM                                  |
+//! |--------|--------------------|--------------------------------------|
+//! | `d1`   | `.axisram.buffers` | AXI SRAM                             |
+//! | `d2`   | `.sram3.buffers`   | SRAM3 (RM0433 and RM0399 parts only) |
+//! | `d3`   | `.sram4.buffers`   | SRAM4                                |
+//!
+//! The Ethernet descriptors use `.sram3.eth`, also in SRAM3.
+//!
+//! SRAM1 and SRAM2 are not used, since they are the stack and heap of the
+//! Cortex-M4 on dual core parts. The RAMs in D2 and D3 must be clocked
+//! before they are accessed, for example
+//! `dp.RCC.ahb2enr.modify(|_, w| w.sram3en().set_bit())`.
+//!
+//! # Data cache
+//!
+//! Buffers in a cached RAM must be cleaned from the data cache before a
+//! DMA transfer reads them, and invalidated after a DMA transfer writes
+//! them. Otherwise, mark the RAM as non-cacheable with [`configure`].
+
+use cortex_m::peripheral::{MPU, SCB};
+
+/// Number of regions of the MPU of the Cortex-M7
+pub const REGIONS: usize = 16;
+
+// MPU_CTRL
+const CTRL_ENABLE: u32 = 1 << 0;
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+
+// MPU_RASR. Refer to ARM DDI 0403 Version E.b Section B3.5.9
+const RASR_ENABLE: u32 = 1 << 0;
+const RASR_SIZE_SHIFT: u32 = 1;
+const RASR_B: u32 = 1 << 16;
+const RASR_C: u32 = 1 << 17;
+const RASR_S: u32 = 1 << 18;
+const RASR_TEX_SHIFT: u32 = 19;
+const RASR_AP_FULL: u32 = 0b011 << 24;
+
+// SCB_SHCSR
+const SHCSR_MEMFAULTENA: u32 = 1 << 16;
+
+/// Buffer aligned to the 32-byte lines of the data cache, so that cache
+/// maintenance on it has no effect on other data
+#[repr(C, align(32))]
+pub struct CacheAligned<T>(pub T);
+
+impl<T> core::ops::Deref for CacheAligned<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for CacheAligned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// Memory attributes of a region
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Attributes {
+    /// Normal memory, not cached and shareable. For DMA buffers and
+    /// descriptors
+    NonCacheable,
+    /// Normal memory, cached for reads and written through
+    WriteThrough,
+    /// Normal memory, cached with write-back and write-allocate. The
+    /// default for the SRAMs
+    WriteBack,
+    /// Shareable Device memory, accessed in order and without merging. For
+    /// memory-mapped devices such as an LCD on the FMC
+    Device,
+}
+
+impl Attributes {
+    /// Returns the TEX, S, C and B bits of RASR
+    fn rasr(self) -> u32 {
+        match self {
+            Attributes::NonCacheable => (0b001 << RASR_TEX_SHIFT) | RASR_S,
+            Attributes::WriteThrough => RASR_C,
+            Attributes::WriteBack => {
+                (0b001 << RASR_TEX_SHIFT) | RASR_C | RASR_B
+            }
+            Attributes::Device => RASR_S | RASR_B,
+        }
+    }
+}
+
+/// An MPU region
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Region {
+    /// Base address. Must be a multiple of `size`
+    pub base: u32,
+    /// Size in bytes. Must be a power of 2, and at least 32
+    pub size: u32,
+    /// Memory attributes
+    pub attributes: Attributes,
+}
+
+/// A RAM that can be reached by a DMA controller
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Ram {
+    /// AXI SRAM in D1
+    AxiSram,
+    /// SRAM3 in D2
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    Sram3,
+    /// SRAM4 in D3
+    Sram4,
+}
+
+impl Ram {
+    /// Returns the base address and size of the RAM, with the size rounded
+    /// up to a power of 2
+    fn extent(self) -> (u32, u32) {
+        match self {
+            #[cfg(not(feature = "rm0455"))]
+            Ram::AxiSram => (0x2400_0000, 512 * 1024),
+            #[cfg(feature = "rm0455")]
+            Ram::AxiSram => (0x2400_0000, 1024 * 1024),
+            #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+            Ram::Sram3 => (0x3004_0000, 32 * 1024),
+            #[cfg(not(feature = "rm0455"))]
+            Ram::Sram4 => (0x3800_0000, 64 * 1024),
+            #[cfg(feature = "rm0455")]
+            Ram::Sram4 => (0x3800_0000, 32 * 1024),
+        }
+    }
+
+    /// Returns a region covering the whole RAM
+    pub fn region(self, attributes: Attributes) -> Region {
+        let (base, size) = self.extent();
+        Region {
+            base,
+            size,
+            attributes,
+        }
+    }
+}
+
+/// Configures `regions` in the MPU, and enables it. Memory outside the
+/// regions keeps the default memory map
+///
+/// Regions are numbered in order, and a later region takes priority
+/// where regions overlap. Regions that are not given are disabled.
+///
+/// # Panics
+///
+/// Panics if there are more than [`REGIONS`] regions, or if the size of a
+/// region is not a power of 2 of at least 32 bytes, or the base is not a
+/// multiple of the size
+pub fn configure(mpu: &mut MPU, scb: &mut SCB, regions: &[Region]) {
+    assert!(regions.len() <= REGIONS, "Too many MPU regions");
+    for region in regions {
+        assert!(
+            region.size.is_power_of_two() && region.size >= 32,
+            "MPU region size must be a power of 2, and at least 32 bytes"
+        );
+        assert!(
+            region.base % region.size == 0,
+            "MPU region base must be a multiple of its size"
+        );
+    }
+
+    // NOTE(unsafe) The MPU is owned, and is disabled while it is changed.
+    // Refer to ARM DDI 0403 Version E.b Section B3.5
+    unsafe {
+        // Make sure outstanding transfers are done
+        cortex_m::asm::dmb();
+        scb.shcsr.modify(|r| r & !SHCSR_MEMFAULTENA);
+        mpu.ctrl.write(0);
+
+        for number in 0..REGIONS {
+            mpu.rnr.write(number as u32);
+            match regions.get(number) {
+                Some(region) => {
+                    let size = region.size.trailing_zeros() - 1;
+                    mpu.rbar.write(region.base);
+                    mpu.rasr.write(
+                        RASR_AP_FULL
+                            | region.attributes.rasr()
+                            | (size << RASR_SIZE_SHIFT)
+                            | RASR_ENABLE,
+                    );
+                }
+                None => mpu.rasr.write(0),
+            }
+        }
+
+        mpu.ctrl.write(CTRL_PRIVDEFENA | CTRL_ENABLE);
+        scb.shcsr.modify(|r| r | SHCSR_MEMFAULTENA);
+
+        // Ensure MPU settings take effect
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+}
+
+/// Places a static for DMA in the RAM of a domain, aligned to the lines of
+/// the data cache. The static is a [`CacheAligned`](crate::mpu::CacheAligned)
+///
+/// The domain is `d1` for the AXI SRAM, `d2` for SRAM3 or `d3` for SRAM4.
+/// See the [`mpu`](crate::mpu) module for the sections used.
+///
+/// ```
+/// dma_buffer!(domain = d2, static mut RX_BUFFER: [u8; 256] = [0; 256]);
+/// ```
+#[macro_export]
+macro_rules! dma_buffer {
+    (domain = d1, $(#[$attr:meta])* $vis:vis static mut $NAME:ident: $T:ty = $init:expr) => {
+        $crate::dma_buffer!(@place ".axisram.buffers", $(#[$attr])* $vis $NAME, $T, $init);
+    };
+    (domain = d2, $(#[$attr:meta])* $vis:vis static mut $NAME:ident: $T:ty = $init:expr) => {
+        $crate::dma_buffer!(@place ".sram3.buffers", $(#[$attr])* $vis $NAME, $T, $init);
+    };
+    (domain = d3, $(#[$attr:meta])* $vis:vis static mut $NAME:ident: $T:ty = $init:expr) => {
+        $crate::dma_buffer!(@place ".sram4.buffers", $(#[$attr])* $vis $NAME, $T, $init);
+    };
+    (@place $section:expr, $(#[$attr:meta])* $vis:vis $NAME:ident, $T:ty, $init:expr) => {
+        $(#[$attr])*
+        #[link_section = $section]
+        $vis static mut $NAME: $crate::mpu::CacheAligned<$T> =
+            $crate::mpu::CacheAligned($init);
+    };
+}
+
+/// Places the descriptor rings of the Ethernet DMA in SRAM3
+///
+/// SRAM3 should be marked as non-cacheable with
+/// [`mpu::configure`](crate::mpu::configure) before the data cache is
+/// enabled.
+///
+/// ```
+/// eth_descriptors!(static mut DES_RING);
+///
+/// let (eth_dma, eth_mac) = unsafe {
+///     ethernet::new_unchecked(
+///         dp.ETHERNET_MAC, dp.ETHERNET_MTL, dp.ETHERNET_DMA,
+///         &mut DES_RING,
+///         MAC_ADDRESS, ccdr.peripheral.ETH1MAC, &ccdr.clocks,
+///     )
+/// };
+/// ```
+#[macro_export]
+macro_rules! eth_descriptors {
+    ($(#[$attr:meta])* $vis:vis static mut $NAME:ident) => {
+        $(#[$attr])*
+        #[link_section = ".sram3.eth"]
+        $vis static mut $NAME: $crate::ethernet::DesRing =
+            $crate::ethernet::DesRing::new();
+    };
+}
@@ -0,0 +1,402 @@
+//! Operational amplifiers (OPAMP1 and OPAMP2)
+//!
+//! Each operational amplifier can be used as a voltage follower, as a
+//! programmable gain amplifier (PGA) with an internal resistor network, or
+//! standalone with external feedback components. The non-inverting input
+//! is either a pin or a DAC channel.
+//!
+//! The pins used in a mode are moved into the enabled operational
+//! amplifier, and returned when it is disabled. This prevents them from
+//! being used by another analog peripheral at the same time.
+//!
+//! # Example
+//!
+//! ```
+//! let (opamp1, _opamp2) = dp.OPAMP.opamp(ccdr.peripheral.OPAMP);
+//!
+//! // Calibrate the offset before the first use
+//! let mut opamp1 = opamp1;
+//! opamp1.calibrate(&mut delay, false);
+//!
+//! let vinp = gpiob.pb0.into_analog();
+//! let vout = gpioc.pc4.into_analog();
+//! let mut pga = opamp1.pga(vinp, vout, Gain::X4);
+//!
+//! // ...
+//!
+//! pga.set_gain(Gain::X8);
+//! let (opamp1, vinp, vout) = pga.disable();
+//! ```
+//!
+//! # Pins
+//!
+//! | Pin  | OPAMP1 | OPAMP2 |
+//! |------|--------|--------|
+//! | VINP | PB0    | PE9    |
+//! | VINM | PC5    | PE8    |
+//! | VOUT | PC4    | PE7    |
+//!
+//! The operational amplifiers on these parts have no timer-controlled
+//! input multiplexer, so the inputs can only be changed by software.
+
+use core::marker::PhantomData;
+
+use crate::gpio::gpiob::PB0;
+use crate::gpio::gpioc::{PC4, PC5};
+use crate::gpio::gpioe::{PE7, PE8, PE9};
+use crate::gpio::Analog;
+use crate::hal::blocking::delay::DelayUs;
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::OPAMP;
+
+/// VM_SEL values
+const VM_SEL_PIN: u8 = 0b00;
+const VM_SEL_PGA: u8 = 0b10;
+const VM_SEL_FOLLOWER: u8 = 0b11;
+
+/// CALSEL values for the NMOS and PMOS differential pairs
+const CALSEL_NMOS: u8 = 0b01;
+const CALSEL_PMOS: u8 = 0b11;
+
+/// Offset trimming settling time, in microseconds
+const TRIM_SETTLING_US: u32 = 2000;
+
+/// Gain of the programmable gain amplifier
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gain {
+    X2 = 0b00,
+    X4 = 0b01,
+    X8 = 0b10,
+    X16 = 0b11,
+}
+
+/// Offset trimming values, as found by calibration
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Trim {
+    /// NMOS differential pair trim, 0 - 31
+    pub n: u8,
+    /// PMOS differential pair trim, 0 - 31
+    pub p: u8,
+}
+
+/// The DAC channel connected to the non-inverting input. This is channel 1
+/// of DAC1 for OPAMP1, and channel 2 of DAC1 for OPAMP2
+pub struct Dac;
+
+/// Trait for non-inverting inputs of an operational amplifier
+pub trait NonInvertingInput<OPAMP> {
+    #[doc(hidden)]
+    const VP_SEL: u8;
+}
+
+/// Operational amplifier instance
+pub trait Instance: Sized {
+    /// The VINM pin
+    type Vinm;
+    /// The VOUT pin
+    type Vout;
+
+    #[doc(hidden)]
+    fn set_enabled(enabled: bool);
+    #[doc(hidden)]
+    fn set_high_speed(high_speed: bool);
+    #[doc(hidden)]
+    fn set_gain(gain: Gain);
+}
+
+/// Voltage follower (type state)
+pub struct Follower;
+/// Programmable gain amplifier (type state)
+pub struct Pga;
+/// Standalone with external feedback (type state)
+pub struct Standalone;
+
+/// Enabled operational amplifier
+pub struct Enabled<OP: Instance, MODE, VINP> {
+    op: OP,
+    vinp: VINP,
+    vinm: Option<OP::Vinm>,
+    vout: OP::Vout,
+    _mode: PhantomData<MODE>,
+}
+
+/// Extension trait for the OPAMP peripheral
+pub trait OpampExt {
+    /// Enables and resets the operational amplifiers, and splits them into
+    /// independent parts
+    fn opamp(self, prec: rec::Opamp) -> (Opamp1, Opamp2);
+}
+
+impl OpampExt for OPAMP {
+    fn opamp(self, prec: rec::Opamp) -> (Opamp1, Opamp2) {
+        prec.enable().reset();
+
+        (Opamp1 { _0: () }, Opamp2 { _0: () })
+    }
+}
+
+impl<OP: Instance, MODE, VINP> Enabled<OP, MODE, VINP> {
+    /// Selects the high-speed mode, with a higher slew rate and current
+    /// consumption. High-speed mode uses its own trimming values, see
+    /// [`set_trim`](Opamp1::set_trim)
+    pub fn set_high_speed(&mut self, high_speed: bool) {
+        OP::set_enabled(false);
+        OP::set_high_speed(high_speed);
+        OP::set_enabled(true);
+    }
+}
+
+impl<OP: Instance, VINP> Enabled<OP, Follower, VINP> {
+    /// Disables the operational amplifier and returns its pins
+    pub fn disable(self) -> (OP, VINP, OP::Vout) {
+        OP::set_enabled(false);
+        (self.op, self.vinp, self.vout)
+    }
+}
+
+impl<OP: Instance, VINP> Enabled<OP, Pga, VINP> {
+    /// Changes the gain
+    pub fn set_gain(&mut self, gain: Gain) {
+        OP::set_gain(gain);
+    }
+
+    /// Disables the operational amplifier and returns its pins
+    pub fn disable(self) -> (OP, VINP, OP::Vout) {
+        OP::set_enabled(false);
+        (self.op, self.vinp, self.vout)
+    }
+}
+
+impl<OP: Instance, VINP> Enabled<OP, Standalone, VINP> {
+    /// Disables the operational amplifier and returns its pins
+    pub fn disable(self) -> (OP, VINP, OP::Vinm, OP::Vout) {
+        OP::set_enabled(false);
+        (self.op, self.vinp, self.vinm.unwrap(), self.vout)
+    }
+}
+
+/// Differential pair of the input stage, trimmed separately
+#[derive(Copy, Clone, PartialEq)]
+enum Pair {
+    Nmos,
+    Pmos,
+}
+
+macro_rules! opamp {
+    ($($OPAMPX:ident: ($csr:ident, $otr:ident, $hsotr:ident,
+                       $VINP:ident, $VINM:ident, $VOUT:ident),)+) => {
+        $(
+            /// Disabled operational amplifier
+            pub struct $OPAMPX {
+                _0: (),
+            }
+
+            impl Instance for $OPAMPX {
+                type Vinm = $VINM<Analog>;
+                type Vout = $VOUT<Analog>;
+
+                fn set_enabled(enabled: bool) {
+                    // NOTE(unsafe) CSR of this operational amplifier, which
+                    // is owned
+                    let opamp = unsafe { &*OPAMP::ptr() };
+                    opamp.$csr.modify(|_, w| w.opaen().bit(enabled));
+                }
+
+                fn set_high_speed(high_speed: bool) {
+                    // NOTE(unsafe) As above
+                    let opamp = unsafe { &*OPAMP::ptr() };
+                    opamp.$csr.modify(|_, w| w.opahsm().bit(high_speed));
+                }
+
+                fn set_gain(gain: Gain) {
+                    // NOTE(unsafe) As above
+                    let opamp = unsafe { &*OPAMP::ptr() };
+                    opamp.$csr.modify(|_, w| unsafe { w.pga_gain().bits(gain as u8) });
+                }
+            }
+
+            impl NonInvertingInput<$OPAMPX> for $VINP<Analog> {
+                const VP_SEL: u8 = 0b00;
+            }
+
+            impl NonInvertingInput<$OPAMPX> for Dac {
+                const VP_SEL: u8 = 0b01;
+            }
+
+            impl $OPAMPX {
+                /// Enables the operational amplifier with the inverting
+                /// input `vm_sel` and a PGA gain of `gain`
+                fn enable<MODE, VINP>(
+                    self,
+                    vinp: VINP,
+                    vinm: Option<$VINM<Analog>>,
+                    vout: $VOUT<Analog>,
+                    vm_sel: u8,
+                    gain: Gain,
+                ) -> Enabled<Self, MODE, VINP>
+                where
+                    VINP: NonInvertingInput<Self>,
+                {
+                    // NOTE(unsafe) CSR of this operational amplifier, which
+                    // is owned
+                    let opamp = unsafe { &*OPAMP::ptr() };
+                    opamp.$csr.write(|w| unsafe {
+                        w.usertrim()
+                            .set_bit()
+                            .vp_sel()
+                            .bits(VINP::VP_SEL)
+                            .vm_sel()
+                            .bits(vm_sel)
+                            .pga_gain()
+                            .bits(gain as u8)
+                    });
+                    opamp.$csr.modify(|_, w| w.opaen().set_bit());
+
+                    Enabled {
+                        op: self,
+                        vinp,
+                        vinm,
+                        vout,
+                        _mode: PhantomData,
+                    }
+                }
+
+                /// Enables the operational amplifier as a voltage follower
+                pub fn follower<VINP>(
+                    self,
+                    vinp: VINP,
+                    vout: $VOUT<Analog>,
+                ) -> Enabled<Self, Follower, VINP>
+                where
+                    VINP: NonInvertingInput<Self>,
+                {
+                    self.enable(vinp, None, vout, VM_SEL_FOLLOWER, Gain::X2)
+                }
+
+                /// Enables the operational amplifier as a non-inverting
+                /// programmable gain amplifier
+                pub fn pga<VINP>(
+                    self,
+                    vinp: VINP,
+                    vout: $VOUT<Analog>,
+                    gain: Gain,
+                ) -> Enabled<Self, Pga, VINP>
+                where
+                    VINP: NonInvertingInput<Self>,
+                {
+                    self.enable(vinp, None, vout, VM_SEL_PGA, gain)
+                }
+
+                /// Enables the operational amplifier with external feedback
+                /// components on the VINM pin
+                pub fn standalone<VINP>(
+                    self,
+                    vinp: VINP,
+                    vinm: $VINM<Analog>,
+                    vout: $VOUT<Analog>,
+                ) -> Enabled<Self, Standalone, VINP>
+                where
+                    VINP: NonInvertingInput<Self>,
+                {
+                    self.enable(vinp, Some(vinm), vout, VM_SEL_PIN, Gain::X2)
+                }
+
+                /// Calibrates the input offset of the normal or high-speed
+                /// mode, and returns the trimming values. The values are
+                /// used when the operational amplifier is enabled
+                pub fn calibrate<T>(&mut self, delay: &mut T, high_speed: bool) -> Trim
+                where
+                    T: DelayUs<u32>,
+                {
+                    // NOTE(unsafe) CSR of this operational amplifier, which
+                    // is owned
+                    let opamp = unsafe { &*OPAMP::ptr() };
+                    let mut trim = Trim { n: 0, p: 0 };
+
+                    for &pair in &[Pair::Nmos, Pair::Pmos] {
+                        let calsel = match pair {
+                            Pair::Nmos => CALSEL_NMOS,
+                            Pair::Pmos => CALSEL_PMOS,
+                        };
+                        opamp.$csr.write(|w| unsafe {
+                            w.opaen()
+                                .set_bit()
+                                .usertrim()
+                                .set_bit()
+                                .calon()
+                                .set_bit()
+                                .opahsm()
+                                .bit(high_speed)
+                                .calsel()
+                                .bits(calsel)
+                        });
+
+                        // Binary search for the trimming value where CALOUT
+                        // changes
+                        let mut value: u8 = 16;
+                        let mut delta: u8 = 8;
+                        loop {
+                            self.write_trim(pair, value, high_speed);
+                            delay.delay_us(TRIM_SETTLING_US);
+                            let calout = opamp.$csr.read().calout().bit_is_set();
+                            if delta == 0 {
+                                if calout && value < 31 {
+                                    value += 1;
+                                    self.write_trim(pair, value, high_speed);
+                                }
+                                break;
+                            }
+                            if calout {
+                                value += delta;
+                            } else {
+                                value -= delta;
+                            }
+                            delta /= 2;
+                        }
+
+                        match pair {
+                            Pair::Nmos => trim.n = value,
+                            Pair::Pmos => trim.p = value,
+                        }
+                    }
+
+                    opamp.$csr.reset();
+                    trim
+                }
+
+                /// Sets the trimming values of the normal or high-speed
+                /// mode, for example from a previous calibration
+                pub fn set_trim(&mut self, trim: Trim, high_speed: bool) {
+                    self.write_trim(Pair::Nmos, trim.n, high_speed);
+                    self.write_trim(Pair::Pmos, trim.p, high_speed);
+                }
+
+                fn write_trim(&mut self, pair: Pair, value: u8, high_speed: bool) {
+                    // NOTE(unsafe) OTR or HSOTR of this operational
+                    // amplifier, which is owned
+                    let opamp = unsafe { &*OPAMP::ptr() };
+                    let value = value & 0x1F;
+                    match (pair, high_speed) {
+                        (Pair::Nmos, false) => opamp.$otr.modify(|_, w| unsafe {
+                            w.trimoffsetn().bits(value)
+                        }),
+                        (Pair::Pmos, false) => opamp.$otr.modify(|_, w| unsafe {
+                            w.trimoffsetp().bits(value)
+                        }),
+                        (Pair::Nmos, true) => opamp.$hsotr.modify(|_, w| unsafe {
+                            w.trimhsoffsetn().bits(value)
+                        }),
+                        (Pair::Pmos, true) => opamp.$hsotr.modify(|_, w| unsafe {
+                            w.trimhsoffsetp().bits(value)
+                        }),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+opamp! {
+    Opamp1: (opamp1_csr, opamp1_otr, opamp1_hsotr, PB0, PC5, PC4),
+    Opamp2: (opamp2_csr, opamp2_otr, opamp2_hsotr, PE9, PE8, PE7),
+}
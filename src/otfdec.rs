@@ -0,0 +1,378 @@
+//! On-The-Fly Decryption (OTFDEC)
+//!
+//! Each OTFDEC instance sits between an OCTOSPI interface and the AXI bus,
+//! and transparently decrypts data read from memory-mapped external flash
+//! using AES-128 in counter mode. OTFDEC1 is associated with OCTOSPI1 and
+//! OTFDEC2 with OCTOSPI2.
+//!
+//! Each OTFDEC has four independent regions. A region covers a range of the
+//! OCTOSPI memory-mapped address space with a granularity of 4 kbytes, and
+//! has its own key, nonce, firmware version and mode.
+//!
+//! This module is only available for the parts with the crypto accelerator
+//! and an OCTOSPI (7B3, 7B0, 730, 733, 735).
+//!
+//! # Example
+//!
+//! ```
+//! let mut otfdec = dp.OTFDEC1.otfdec(ccdr.peripheral.OTFD1);
+//! let (mut region1, _, _, _) = otfdec.split();
+//!
+//! region1
+//!     .configure(&RegionConfig {
+//!         start: 0x9000_0000,
+//!         end: 0x9000_FFFF,
+//!         key: [0x0123_4567, 0x89AB_CDEF, 0x0123_4567, 0x89AB_CDEF],
+//!         nonce: [0xA5A5_A5A5, 0xC3C3_C3C3],
+//!         version: 1,
+//!         mode: Mode::InstructionAndData,
+//!     })
+//!     .unwrap();
+//! region1.enable();
+//! ```
+//!
+//! The regions borrow the `Otfdec`, so its interrupts can be handled and it
+//! can be released once they are dropped. The configuration of a region is
+//! kept when it is dropped.
+
+use core::marker::PhantomData;
+use core::ops::RangeInclusive;
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::{OTFDEC1, OTFDEC2};
+
+/// Region address granularity, in bytes
+const REGION_GRANULARITY: u32 = 4096;
+
+/// OTFDEC error
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The region configuration is locked until the next reset
+    ConfigurationLocked,
+    /// The region key is locked until the next reset
+    KeyLocked,
+    /// The region must be disabled before it is configured
+    RegionEnabled,
+    /// The region start or end address is not aligned to 4 kbytes
+    Alignment,
+    /// The region is outside of the memory-mapped address range of the
+    /// associated OCTOSPI
+    AddressRange,
+}
+
+/// Decryption mode of a region
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    /// Only instruction accesses are decrypted
+    InstructionOnly = 0b00,
+    /// Only data accesses are decrypted
+    DataOnly = 0b01,
+    /// All read accesses are decrypted
+    InstructionAndData = 0b10,
+    /// Only instruction accesses are decrypted, using an enhanced cipher.
+    EnhancedInstruction = 0b11,
+}
+
+/// Configuration of a decryption region
+#[derive(Copy, Clone, Debug)]
+pub struct RegionConfig {
+    /// Address of the first byte in the region. Must be aligned to 4 kbytes
+    pub start: u32,
+    /// Address of the last byte in the region. `end + 1` must be aligned to
+    /// 4 kbytes
+    pub end: u32,
+    /// AES-128 key, least significant word first
+    pub key: [u32; 4],
+    /// Nonce, least significant word first
+    pub nonce: [u32; 2],
+    /// Firmware version, used in the counter
+    pub version: u16,
+    /// Decryption mode
+    pub mode: Mode,
+}
+
+/// OTFDEC interrupt events
+#[derive(Copy, Clone, PartialEq)]
+pub enum Event {
+    /// Security error: a read access to an encrypted region was made while
+    /// the key was not valid, or an illegal write was attempted
+    SecurityError,
+    /// Execute-only, execute-never error
+    ExecuteError,
+    /// Key error: a read access was made to a region whose key has an
+    /// invalid CRC
+    KeyError,
+}
+
+/// Extension trait for OTFDEC peripherals
+pub trait OtfdecExt<OTFDEC>: Sized {
+    type Rec: ResetEnable;
+
+    /// Enable and reset an OTFDEC peripheral
+    fn otfdec(self, prec: Self::Rec) -> Otfdec<OTFDEC>;
+}
+
+/// On-The-Fly Decryption peripheral
+pub struct Otfdec<OTFDEC> {
+    rb: OTFDEC,
+}
+
+macro_rules! otfdec {
+    ($($OTFDECX:ident: ($otfdecX:ident, $Rec:ident, $range:expr),)+) => {
+        $(
+            impl Otfdec<$OTFDECX> {
+                /// Enable and reset the OTFDEC peripheral. All regions are
+                /// initially disabled
+                pub fn $otfdecX(otfdec: $OTFDECX, prec: rec::$Rec) -> Self {
+                    prec.enable().reset();
+
+                    Otfdec { rb: otfdec }
+                }
+
+                /// Split into the four independent regions, which borrow the
+                /// OTFDEC
+                pub fn split(
+                    &mut self,
+                ) -> (
+                    Region1<'_, $OTFDECX>,
+                    Region2<'_, $OTFDECX>,
+                    Region3<'_, $OTFDECX>,
+                    Region4<'_, $OTFDECX>,
+                ) {
+                    (
+                        Region1 { _otfdec: PhantomData },
+                        Region2 { _otfdec: PhantomData },
+                        Region3 { _otfdec: PhantomData },
+                        Region4 { _otfdec: PhantomData },
+                    )
+                }
+
+                /// Enable interrupts for the given `event`
+                pub fn listen(&mut self, event: Event) {
+                    match event {
+                        Event::SecurityError => {
+                            self.rb.ier.modify(|_, w| w.seie().set_bit())
+                        }
+                        Event::ExecuteError => {
+                            self.rb.ier.modify(|_, w| w.xoneie().set_bit())
+                        }
+                        Event::KeyError => {
+                            self.rb.ier.modify(|_, w| w.keie().set_bit())
+                        }
+                    }
+                }
+
+                /// Disable interrupts for the given `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    match event {
+                        Event::SecurityError => {
+                            self.rb.ier.modify(|_, w| w.seie().clear_bit())
+                        }
+                        Event::ExecuteError => {
+                            self.rb.ier.modify(|_, w| w.xoneie().clear_bit())
+                        }
+                        Event::KeyError => {
+                            self.rb.ier.modify(|_, w| w.keie().clear_bit())
+                        }
+                    }
+                    let _ = self.rb.ier.read();
+                    let _ = self.rb.ier.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns `true` if the flag for `event` is set
+                pub fn is_pending(&self, event: Event) -> bool {
+                    let isr = self.rb.isr.read();
+                    match event {
+                        Event::SecurityError => isr.seif().bit_is_set(),
+                        Event::ExecuteError => isr.xoneif().bit_is_set(),
+                        Event::KeyError => isr.keif().bit_is_set(),
+                    }
+                }
+
+                /// Clear the flag for `event`
+                pub fn unpend(&mut self, event: Event) {
+                    match event {
+                        Event::SecurityError => {
+                            self.rb.icr.write(|w| w.seif().set_bit())
+                        }
+                        Event::ExecuteError => {
+                            self.rb.icr.write(|w| w.xoneif().set_bit())
+                        }
+                        Event::KeyError => {
+                            self.rb.icr.write(|w| w.keif().set_bit())
+                        }
+                    }
+                    let _ = self.rb.isr.read();
+                    let _ = self.rb.isr.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Releases the OTFDEC peripheral. Region configurations are
+                /// not modified
+                pub fn free(self) -> ($OTFDECX, rec::$Rec) {
+                    (self.rb, rec::$Rec { _marker: PhantomData })
+                }
+            }
+
+            impl OtfdecExt<$OTFDECX> for $OTFDECX {
+                type Rec = rec::$Rec;
+
+                fn otfdec(self, prec: rec::$Rec) -> Otfdec<$OTFDECX> {
+                    Otfdec::$otfdecX(self, prec)
+                }
+            }
+
+            otfdec_region! {
+                $OTFDECX, $range: [
+                    (Region1, r1cfgr, r1startaddr, r1endaddr, r1noncer0, r1noncer1,
+                     r1keyr0, r1keyr1, r1keyr2, r1keyr3),
+                    (Region2, r2cfgr, r2startaddr, r2endaddr, r2noncer0, r2noncer1,
+                     r2keyr0, r2keyr1, r2keyr2, r2keyr3),
+                    (Region3, r3cfgr, r3startaddr, r3endaddr, r3noncer0, r3noncer1,
+                     r3keyr0, r3keyr1, r3keyr2, r3keyr3),
+                    (Region4, r4cfgr, r4startaddr, r4endaddr, r4noncer0, r4noncer1,
+                     r4keyr0, r4keyr1, r4keyr2, r4keyr3),
+                ]
+            }
+        )+
+    }
+}
+
+macro_rules! declare_region {
+    ($($Region:ident, $doc:expr;)+) => {
+        $(
+            #[doc=$doc]
+            /// of an OTFDEC peripheral
+            pub struct $Region<'a, OTFDEC> {
+                _otfdec: PhantomData<&'a mut OTFDEC>,
+            }
+            unsafe impl<'a, OTFDEC> Send for $Region<'a, OTFDEC> {}
+        )+
+    }
+}
+declare_region! {
+    Region1, "Region 1";
+    Region2, "Region 2";
+    Region3, "Region 3";
+    Region4, "Region 4";
+}
+
+macro_rules! otfdec_region {
+    ($OTFDECX:ident, $range:expr: [
+        $(($Region:ident, $cfgr:ident, $startaddr:ident, $endaddr:ident,
+           $noncer0:ident, $noncer1:ident,
+           $keyr0:ident, $keyr1:ident, $keyr2:ident, $keyr3:ident),)+
+    ]) => {
+        $(
+            impl<'a> $Region<'a, $OTFDECX> {
+                /// Configure the address range, key, nonce, version and mode
+                /// of this region. The region must be disabled.
+                ///
+                /// The key CRC calculated by hardware can be read back with
+                /// [`key_crc`](Self::key_crc).
+                pub fn configure(
+                    &mut self,
+                    config: &RegionConfig,
+                ) -> Result<(), Error> {
+                    let range: RangeInclusive<u32> = $range;
+                    // unsafe: Owned exclusive access to this region
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+
+                    let cfgr = rb.$cfgr.read();
+                    if cfgr.configlock().bit_is_set() {
+                        return Err(Error::ConfigurationLocked);
+                    }
+                    if cfgr.keylock().bit_is_set() {
+                        return Err(Error::KeyLocked);
+                    }
+                    if cfgr.reg_en().bit_is_set() {
+                        return Err(Error::RegionEnabled);
+                    }
+                    if config.start % REGION_GRANULARITY != 0
+                        || config.end.wrapping_add(1) % REGION_GRANULARITY != 0
+                        || config.end < config.start
+                    {
+                        return Err(Error::Alignment);
+                    }
+                    if !range.contains(&config.start)
+                        || !range.contains(&config.end)
+                    {
+                        return Err(Error::AddressRange);
+                    }
+
+                    rb.$startaddr.write(|w| unsafe { w.bits(config.start) });
+                    rb.$endaddr.write(|w| unsafe { w.bits(config.end) });
+                    rb.$noncer0.write(|w| unsafe { w.bits(config.nonce[0]) });
+                    rb.$noncer1.write(|w| unsafe { w.bits(config.nonce[1]) });
+                    rb.$cfgr.modify(|_, w| unsafe {
+                        w.mode()
+                            .bits(config.mode as u8)
+                            .regx_version()
+                            .bits(config.version)
+                    });
+                    // The key must be written after the mode. RM0455 Rev 6
+                    // Section 46.3.4
+                    rb.$keyr0.write(|w| unsafe { w.bits(config.key[0]) });
+                    rb.$keyr1.write(|w| unsafe { w.bits(config.key[1]) });
+                    rb.$keyr2.write(|w| unsafe { w.bits(config.key[2]) });
+                    rb.$keyr3.write(|w| unsafe { w.bits(config.key[3]) });
+
+                    Ok(())
+                }
+
+                /// Enable decryption for this region
+                pub fn enable(&mut self) {
+                    // unsafe: Owned exclusive access to this region
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+                    rb.$cfgr.modify(|_, w| w.reg_en().set_bit());
+                }
+
+                /// Disable decryption for this region. Reads from the
+                /// region will return the raw data from memory
+                pub fn disable(&mut self) {
+                    // unsafe: Owned exclusive access to this region
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+                    rb.$cfgr.modify(|_, w| w.reg_en().clear_bit());
+                }
+
+                /// Returns `true` if decryption is enabled for this region
+                pub fn is_enabled(&self) -> bool {
+                    // unsafe: We only read from this register
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+                    rb.$cfgr.read().reg_en().bit_is_set()
+                }
+
+                /// CRC of the key, calculated by hardware when the key is
+                /// written. Can be compared with a precomputed value to check
+                /// that the key was loaded correctly
+                pub fn key_crc(&self) -> u8 {
+                    // unsafe: We only read from this register
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+                    rb.$cfgr.read().keycrc().bits()
+                }
+
+                /// Prevent further writes to the key registers of this region
+                /// until the next reset
+                pub fn lock_key(&mut self) {
+                    // unsafe: Owned exclusive access to this region
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+                    rb.$cfgr.modify(|_, w| w.keylock().set_bit());
+                }
+
+                /// Prevent further changes to the configuration of this
+                /// region until the next reset. Decryption cannot be disabled
+                /// after this call
+                pub fn lock_configuration(&mut self) {
+                    // unsafe: Owned exclusive access to this region
+                    let rb = unsafe { &*$OTFDECX::ptr() };
+                    rb.$cfgr.modify(|_, w| w.configlock().set_bit());
+                }
+            }
+        )+
+    }
+}
+
+otfdec! {
+    OTFDEC1: (otfdec1, Otfd1, 0x9000_0000..=0x9FFF_FFFF),
+    OTFDEC2: (otfdec2, Otfd2, 0x7000_0000..=0x7FFF_FFFF),
+}
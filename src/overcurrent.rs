@@ -0,0 +1,163 @@
+//! Overcurrent shutdown of the advanced timer outputs
+//!
+//! An [`OvercurrentProtection`] disables all outputs of TIM1 or TIM8 when
+//! analog watchdog 1 of an ADC sees a conversion outside its window, for
+//! example the current of a motor phase or of a power stage. The outputs
+//! stay in their idle state until [`reset`](OvercurrentProtection::reset).
+//!
+//! ```
+//! let pwm = dp.TIM1.pwm(pins, 20.khz(), ccdr.peripheral.TIM1, &ccdr.clocks);
+//! let mut adc1 = adc1.enable();
+//!
+//! // Trip above 3000 counts of the current shunt
+//! adc1.enable_analog_watchdog(&shunt, 0, 3000);
+//! let mut protection = OvercurrentProtection::new(adc1, &pwm);
+//!
+//! // Continuous conversions of the shunt are started as usual
+//! protection.adc().start_injected(&sequence, trigger);
+//!
+//! // In the ADC interrupt, which has the highest priority
+//! if protection.on_interrupt() {
+//!     // Outputs are disabled
+//! }
+//! ```
+//!
+//! # Interrupt
+//!
+//! The analog watchdogs of the ADCs are not connected to the break inputs
+//! of the timers, so the shutdown is done by the ADC interrupt: `ADC` for
+//! ADC1 and ADC2, or `ADC3` for ADC3. The interrupt should be unmasked at
+//! the highest priority, and must not share its priority with other
+//! interrupts that run for long. The outputs are disabled by a software
+//! break event, which clears MOE in hardware, within some tens of CPU
+//! cycles of the conversion.
+//!
+//! Where the response must not depend on software, the current can also
+//! be compared by a [comparator](crate::comp) connected to the break input
+//! of the timer with `connect_break`. The break input is then enabled with
+//! [`enable_system_break`](crate::pwm::Pwm::enable_system_break), without
+//! auto re-enable.
+
+use core::marker::PhantomData;
+
+use crate::adc::{Adc, Enabled, Event};
+use crate::pwm::Pwm;
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+use crate::stm32::ADC3;
+use crate::stm32::{ADC1, ADC2, TIM1, TIM8};
+
+/// Shutdown of the outputs of the timer `TIM` by analog watchdog 1 of the
+/// ADC `ADC`
+pub struct OvercurrentProtection<ADC, TIM> {
+    adc: Adc<ADC, Enabled>,
+    trips: u32,
+    _tim: PhantomData<TIM>,
+}
+
+macro_rules! overcurrent {
+    ($($(#[$meta:meta])* $ADC:ident: $TIM:ident,)+) => {
+        $(
+            $(#[$meta])*
+            impl OvercurrentProtection<$ADC, $TIM> {
+                /// Arms the protection of the outputs of `pwm`. The analog
+                /// watchdog of `adc` must be configured with
+                /// [`enable_analog_watchdog`](Adc#method.enable_analog_watchdog)
+                ///
+                /// The outputs of the timer are driven to their idle state
+                /// while they are disabled, and are not enabled again at
+                /// the next update event.
+                pub fn new<CH>(
+                    mut adc: Adc<$ADC, Enabled>,
+                    _pwm: &Pwm<$TIM, CH>,
+                ) -> Self {
+                    // NOTE(unsafe) The state of the outputs is shared with
+                    // the break inputs of the PWM channels
+                    let tim = unsafe { &*$TIM::ptr() };
+                    tim.bdtr.modify(|_, w| {
+                        w.aoe().clear_bit().ossi().set_bit().ossr().set_bit()
+                    });
+
+                    adc.unpend(Event::AnalogWatchdog1);
+                    adc.listen(Event::AnalogWatchdog1);
+
+                    OvercurrentProtection {
+                        adc,
+                        trips: 0,
+                        _tim: PhantomData,
+                    }
+                }
+
+                /// Disables the outputs if the watchdog has tripped. Must
+                /// be called from the ADC interrupt
+                ///
+                /// Returns `true` if the watchdog had tripped.
+                pub fn on_interrupt(&mut self) -> bool {
+                    if !self.adc.is_pending(Event::AnalogWatchdog1) {
+                        return false;
+                    }
+                    self.trip();
+                    self.adc.unpend(Event::AnalogWatchdog1);
+
+                    true
+                }
+
+                /// Disables the outputs, as if the watchdog had tripped
+                pub fn trip(&mut self) {
+                    // NOTE(unsafe) Atomic write to a stateless register.
+                    // The break event clears MOE and sets BIF in hardware
+                    let tim = unsafe { &*$TIM::ptr() };
+                    tim.egr.write(|w| w.bg().set_bit());
+
+                    self.trips = self.trips.saturating_add(1);
+                }
+
+                /// Returns `true` if the outputs are disabled
+                pub fn is_tripped(&self) -> bool {
+                    // NOTE(unsafe) Atomic read with no side effects
+                    let tim = unsafe { &*$TIM::ptr() };
+
+                    tim.bdtr.read().moe().bit_is_clear()
+                }
+
+                /// Returns the number of times the protection has tripped
+                pub fn trips(&self) -> u32 {
+                    self.trips
+                }
+
+                /// Enables the outputs again. If the conversions are still
+                /// outside the window, the next one trips the protection
+                /// again
+                pub fn reset(&mut self) {
+                    // NOTE(unsafe) As for `new`
+                    let tim = unsafe { &*$TIM::ptr() };
+
+                    tim.sr.modify(|_, w| w.bif().clear_bit());
+                    tim.bdtr.modify(|_, w| w.moe().enabled());
+                }
+
+                /// Returns the ADC, to start and read the conversions
+                pub fn adc(&mut self) -> &mut Adc<$ADC, Enabled> {
+                    &mut self.adc
+                }
+
+                /// Disarms the protection, and releases the ADC. The
+                /// outputs are left as they are
+                pub fn free(mut self) -> Adc<$ADC, Enabled> {
+                    self.adc.unlisten(Event::AnalogWatchdog1);
+                    self.adc
+                }
+            }
+        )+
+    };
+}
+
+overcurrent! {
+    ADC1: TIM1,
+    ADC1: TIM8,
+    ADC2: TIM1,
+    ADC2: TIM8,
+    #[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+    ADC3: TIM1,
+    #[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+    ADC3: TIM8,
+}
@@ -0,0 +1,316 @@
+//! Digital pattern generator and logic capture
+//!
+//! [`PatternGenerator`] writes a precomputed sequence of words to the bit set/reset register
+//! (BSRR) of a GPIO port by DMA, one word on each update event of a timer.
+//! This generates multi-pin digital patterns with precise timing and no CPU
+//! involvement.
+//!
+//! Each word sets the pins in its lower 16 bits and resets the pins in its
+//! upper 16 bits. Pins that are not set or reset by any word are not
+//! affected, so other pins on the same port can still be used normally. Use
+//! [`bsrr`] or [`levels`] to calculate the words.
+//!
+//! # Examples
+//!
+//! ```
+//! use stm32h7xx_hal::pattern::{levels, PatternGenerator};
+//!
+//! // PE0 - PE3 as push-pull outputs
+//! let _pe0 = gpioe.pe0.into_push_pull_output();
+//! // ...
+//!
+//! // Count from 0 to 15 on PE0 - PE3
+//! static PATTERN: [u32; 16] = { ... levels(0xF, n) ... };
+//!
+//! // One word every 1us
+//! let mut timer = dp.TIM2.timer(1.mhz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//! timer.pause();
+//! let generator: PatternGenerator<_, pac::GPIOE> =
+//!     PatternGenerator::new(timer);
+//!
+//! let config = DmaConfig::default()
+//!     .memory_increment(true)
+//!     .circular_buffer(true);
+//! let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+//!     Transfer::init_const(streams.0, generator, &PATTERN, None, config);
+//!
+//! transfer.start(|generator| generator.start());
+//! ```
+//!
+//! # Logic capture
+//!
+//! [`LogicCapture`] does the opposite: it reads the input data register
+//! (IDR) of a GPIO port into a buffer by DMA, one sample of all 16 pins on
+//! each update event of a timer. This can be used as a simple logic
+//! analyser for the pins of one port.
+//!
+//! ```
+//! use stm32h7xx_hal::pattern::LogicCapture;
+//!
+//! static mut SAMPLES: [u16; 1024] = [0; 1024];
+//!
+//! // 2MHz sample rate
+//! let mut timer = dp.TIM3.timer(2.mhz(), ccdr.peripheral.TIM3, &ccdr.clocks);
+//! timer.pause();
+//! let capture: LogicCapture<_, pac::GPIOD> = LogicCapture::new(timer);
+//!
+//! let config = DmaConfig::default().memory_increment(true);
+//! let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+//!     Transfer::init(streams.1, capture, unsafe { &mut SAMPLES }, None, config);
+//!
+//! transfer.start(|capture| capture.start());
+//! while !transfer.get_transfer_complete_flag() {}
+//! ```
+//!
+//! # Sample rate
+//!
+//! The maximum rate depends on the bus load. The DMA must access the GPIO
+//! port through the D2-to-D3 bus bridge, so rates of several MHz are
+//! possible but not guaranteed
+
+use core::marker::PhantomData;
+
+use crate::dma::{
+    traits::TargetAddress, MemoryToPeripheral, PeripheralToMemory,
+};
+use crate::stm32;
+use crate::timer::Timer;
+
+type DMAReq = stm32::dmamux1::ccr::DMAREQ_ID_A;
+
+/// Returns the BSRR word that sets the pins in `set` and resets the pins in
+/// `reset`. If a pin is in both, it is set
+pub const fn bsrr(set: u16, reset: u16) -> u32 {
+    (set as u32) | ((reset as u32) << 16)
+}
+
+/// Returns the BSRR word that drives the pins in `mask` to the levels in
+/// `levels`
+pub const fn levels(mask: u16, levels: u16) -> u32 {
+    bsrr(levels & mask, !levels & mask)
+}
+
+/// Timers that can make a DMA request on each update event
+pub trait UpdateDma {
+    /// DMAMUX1 request line for the update event
+    const REQUEST_LINE: u8;
+
+    /// Enables or disables the DMA request on the update event
+    fn update_dma(&mut self, enable: bool);
+}
+
+macro_rules! update_dma {
+    ($($TIMX:ident: $dmareq:ident,)+) => {
+        $(
+            impl UpdateDma for Timer<stm32::$TIMX> {
+                const REQUEST_LINE: u8 = DMAReq::$dmareq as u8;
+
+                fn update_dma(&mut self, enable: bool) {
+                    // unsafe: Only the DIER.UDE bit is modified, and the
+                    // Timer is owned by us
+                    let tim = unsafe { &*stm32::$TIMX::ptr() };
+                    tim.dier.modify(|_, w| w.ude().bit(enable));
+                }
+            }
+        )+
+    };
+}
+
+update_dma! {
+    TIM1: TIM1_UP,
+    TIM2: TIM2_UP,
+    TIM3: TIM3_UP,
+    TIM4: TIM4_UP,
+    TIM5: TIM5_UP,
+    TIM6: TIM6_UP,
+    TIM7: TIM7_UP,
+    TIM8: TIM8_UP,
+    TIM15: TIM15_UP,
+    TIM16: TIM16_UP,
+    TIM17: TIM17_UP,
+}
+
+/// GPIO ports
+pub trait Port {
+    /// Returns the address of the BSRR register
+    fn bsrr_address() -> usize;
+
+    /// Returns the address of the IDR register
+    fn idr_address() -> usize;
+}
+
+macro_rules! port {
+    ($($GPIOX:ident),+) => {
+        $(
+            impl Port for stm32::$GPIOX {
+                fn bsrr_address() -> usize {
+                    // unsafe: Only the address is used
+                    let gpio = unsafe { &*stm32::$GPIOX::ptr() };
+                    &gpio.bsrr as *const _ as usize
+                }
+
+                fn idr_address() -> usize {
+                    // unsafe: Only the address is used
+                    let gpio = unsafe { &*stm32::$GPIOX::ptr() };
+                    &gpio.idr as *const _ as usize
+                }
+            }
+        )+
+    };
+}
+
+port!(
+    GPIOA, GPIOB, GPIOC, GPIOD, GPIOE, GPIOF, GPIOG, GPIOH, GPIOI, GPIOJ, GPIOK
+);
+
+/// Writes a pattern to the GPIO port `GPIO`, paced by the update events of
+/// a timer
+pub struct PatternGenerator<TIM, GPIO> {
+    timer: TIM,
+    _gpio: PhantomData<GPIO>,
+}
+
+impl<TIM, GPIO> PatternGenerator<TIM, GPIO>
+where
+    TIM: UpdateDma,
+    GPIO: Port,
+{
+    /// Returns a pattern generator paced by `timer`. The frequency of the
+    /// timer is the rate at which words are written
+    pub fn new(timer: TIM) -> Self {
+        PatternGenerator {
+            timer,
+            _gpio: PhantomData,
+        }
+    }
+
+    /// Releases the timer
+    pub fn free(mut self) -> TIM {
+        self.timer.update_dma(false);
+        self.timer
+    }
+}
+
+/// Samples the inputs of the GPIO port `GPIO`, paced by the update events of
+/// a timer
+pub struct LogicCapture<TIM, GPIO> {
+    timer: TIM,
+    _gpio: PhantomData<GPIO>,
+}
+
+impl<TIM, GPIO> LogicCapture<TIM, GPIO>
+where
+    TIM: UpdateDma,
+    GPIO: Port,
+{
+    /// Returns a logic capture paced by `timer`. The frequency of the timer
+    /// is the sample rate
+    pub fn new(timer: TIM) -> Self {
+        LogicCapture {
+            timer,
+            _gpio: PhantomData,
+        }
+    }
+
+    /// Releases the timer
+    pub fn free(mut self) -> TIM {
+        self.timer.update_dma(false);
+        self.timer
+    }
+}
+
+// Starts DMA requests from the start of a timer period
+macro_rules! start {
+    ($timer:expr) => {{
+        $timer.pause();
+        $timer.reset_counter();
+        $timer.update_dma(true);
+        $timer.resume();
+    }};
+}
+
+macro_rules! stop {
+    ($timer:expr) => {{
+        $timer.pause();
+        $timer.update_dma(false);
+    }};
+}
+
+macro_rules! pattern_timer {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl<GPIO> PatternGenerator<Timer<stm32::$TIMX>, GPIO> {
+                /// Starts writing the pattern. Call this after the DMA
+                /// transfer has been started
+                pub fn start(&mut self) {
+                    start!(self.timer);
+                }
+
+                /// Stops writing the pattern. The pins remain at the levels
+                /// set by the last word written
+                pub fn stop(&mut self) {
+                    stop!(self.timer);
+                }
+            }
+
+            impl<GPIO> LogicCapture<Timer<stm32::$TIMX>, GPIO> {
+                /// Starts sampling. Call this after the DMA transfer has been
+                /// started
+                pub fn start(&mut self) {
+                    start!(self.timer);
+                }
+
+                /// Stops sampling
+                pub fn stop(&mut self) {
+                    stop!(self.timer);
+                }
+            }
+        )+
+    };
+}
+
+pattern_timer! {
+    TIM1,
+    TIM2,
+    TIM3,
+    TIM4,
+    TIM5,
+    TIM6,
+    TIM7,
+    TIM8,
+    TIM15,
+    TIM16,
+    TIM17,
+}
+
+unsafe impl<TIM, GPIO> TargetAddress<MemoryToPeripheral>
+    for PatternGenerator<TIM, GPIO>
+where
+    TIM: UpdateDma,
+    GPIO: Port,
+{
+    #[inline(always)]
+    fn address(&self) -> usize {
+        GPIO::bsrr_address()
+    }
+
+    type MemSize = u32;
+
+    const REQUEST_LINE: Option<u8> = Some(TIM::REQUEST_LINE);
+}
+
+unsafe impl<TIM, GPIO> TargetAddress<PeripheralToMemory>
+    for LogicCapture<TIM, GPIO>
+where
+    TIM: UpdateDma,
+    GPIO: Port,
+{
+    #[inline(always)]
+    fn address(&self) -> usize {
+        GPIO::idr_address()
+    }
+
+    type MemSize = u16;
+
+    const REQUEST_LINE: Option<u8> = Some(TIM::REQUEST_LINE);
+}
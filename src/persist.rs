@@ -0,0 +1,248 @@
+//! Persistent storage of calibration values
+//!
+//! Calibration values such as ADC offsets, HSI trimming or touch
+//! thresholds can be kept across resets in the backup domain. This module
+//! stores a typed [`Record`] in a [`WordStorage`], such as the RTC backup
+//! registers, with a version and a CRC calculated by the
+//! [CRC unit](crate::crc).
+//!
+//! # Example
+//!
+//! ```
+//! use stm32h7xx_hal::persist::{self, Reader, Record, Writer};
+//!
+//! struct Calibration {
+//!     adc_offset: i16,
+//!     hsi_trim: u8,
+//! }
+//!
+//! impl Record for Calibration {
+//!     const VERSION: u16 = 1;
+//!     const WORDS: usize = 1;
+//!
+//!     fn save(&self, w: &mut Writer) {
+//!         w.word((self.adc_offset as u16 as u32) | (u32::from(self.hsi_trim) << 16));
+//!     }
+//!     fn load(r: &mut Reader) -> Self {
+//!         let word = r.word();
+//!         Calibration { adc_offset: word as u16 as i16, hsi_trim: (word >> 16) as u8 }
+//!     }
+//! }
+//!
+//! let calibration = persist::load::<Calibration, _>(&rtc, 0, &mut crc)
+//!     .unwrap_or_else(|_| {
+//!         let calibration = calibrate();
+//!         persist::save(&mut rtc, 0, &mut crc, &calibration).unwrap();
+//!         calibration
+//!     });
+//! ```
+//!
+//! # Layout
+//!
+//! A record at `offset` uses `WORDS + 3` words of storage:
+//!
+//! | Word      | Content                                  |
+//! |-----------|------------------------------------------|
+//! | 0         | `0x5AFE` in bits 31:16, version in 15:0  |
+//! | 1         | `WORDS`                                  |
+//! | 2 ..      | Record data                              |
+//! | WORDS + 2 | CRC of the words above                   |
+//!
+//! The CRC unit is used with its current configuration, which must be the
+//! same for `save` and `load`.
+
+use crate::crc::Crc;
+
+/// Marker in the header word of a record
+const MAGIC: u32 = 0x5AFE;
+/// Number of header and CRC words
+const OVERHEAD: usize = 3;
+
+/// Storage that is kept across resets, addressed by 32-bit words
+pub trait WordStorage {
+    /// Number of words
+    fn words(&self) -> usize;
+    /// Reads word `index`
+    fn read_word(&self, index: usize) -> u32;
+    /// Writes word `index`
+    fn write_word(&mut self, index: usize, value: u32);
+}
+
+#[cfg(feature = "rtc")]
+impl WordStorage for crate::rtc::Rtc {
+    fn words(&self) -> usize {
+        32
+    }
+    fn read_word(&self, index: usize) -> u32 {
+        self.read_backup_reg(index as u8)
+    }
+    fn write_word(&mut self, index: usize, value: u32) {
+        self.write_backup_reg(index as u8, value)
+    }
+}
+
+/// Stored record error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The record does not fit in the storage at this offset
+    NoSpace,
+    /// There is no record at this offset
+    Empty,
+    /// The stored record has a different version
+    Version(u16),
+    /// The stored record has a different length
+    Length,
+    /// The CRC does not match. The record is corrupted
+    Crc,
+}
+
+/// Writes the data of a record, see [`Record::save`]
+pub struct Writer<'a> {
+    storage: &'a mut dyn WordStorage,
+    index: usize,
+    end: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Writes the next word
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than [`Record::WORDS`] words are written
+    pub fn word(&mut self, value: u32) {
+        assert!(self.index < self.end, "Record is longer than WORDS");
+        self.storage.write_word(self.index, value);
+        self.index += 1;
+    }
+}
+
+/// Reads the data of a record, see [`Record::load`]
+pub struct Reader<'a> {
+    storage: &'a dyn WordStorage,
+    index: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the next word
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than [`Record::WORDS`] words are read
+    pub fn word(&mut self) -> u32 {
+        assert!(self.index < self.end, "Record is longer than WORDS");
+        self.index += 1;
+        self.storage.read_word(self.index - 1)
+    }
+}
+
+/// A value that can be stored
+pub trait Record: Sized {
+    /// Version of the layout. Records with a different version are not
+    /// loaded
+    const VERSION: u16;
+    /// Number of data words
+    const WORDS: usize;
+
+    /// Writes the value as `WORDS` words
+    fn save(&self, w: &mut Writer);
+    /// Reads a value from `WORDS` words
+    fn load(r: &mut Reader) -> Self;
+}
+
+/// Calculates the CRC of the header and data words of the record at
+/// `offset`
+fn calculate_crc(
+    storage: &dyn WordStorage,
+    offset: usize,
+    words: usize,
+    crc: &mut Crc,
+) -> u32 {
+    crc.reset();
+    for index in offset..offset + 2 + words {
+        crc.update_u32(&[storage.read_word(index)]);
+    }
+    crc.finalize()
+}
+
+/// Saves `record` at `offset` in `storage`
+pub fn save<R, S>(
+    storage: &mut S,
+    offset: usize,
+    crc: &mut Crc,
+    record: &R,
+) -> Result<(), Error>
+where
+    R: Record,
+    S: WordStorage,
+{
+    let end = offset + R::WORDS + OVERHEAD;
+    if end > storage.words() {
+        return Err(Error::NoSpace);
+    }
+
+    storage.write_word(offset, (MAGIC << 16) | u32::from(R::VERSION));
+    storage.write_word(offset + 1, R::WORDS as u32);
+
+    let data = offset + 2;
+    let mut writer = Writer {
+        storage: &mut *storage,
+        index: data,
+        end: data + R::WORDS,
+    };
+    record.save(&mut writer);
+    // Unwritten words are zero, so that the CRC is reproducible
+    while writer.index < writer.end {
+        writer.word(0);
+    }
+
+    let value = calculate_crc(&*storage, offset, R::WORDS, crc);
+    storage.write_word(end - 1, value);
+    Ok(())
+}
+
+/// Loads a record from `offset` in `storage`
+pub fn load<R, S>(storage: &S, offset: usize, crc: &mut Crc) -> Result<R, Error>
+where
+    R: Record,
+    S: WordStorage,
+{
+    let end = offset + R::WORDS + OVERHEAD;
+    if end > storage.words() {
+        return Err(Error::NoSpace);
+    }
+
+    let header = storage.read_word(offset);
+    if header >> 16 != MAGIC {
+        return Err(Error::Empty);
+    }
+    let version = header as u16;
+    if version != R::VERSION {
+        return Err(Error::Version(version));
+    }
+    if storage.read_word(offset + 1) != R::WORDS as u32 {
+        return Err(Error::Length);
+    }
+    if calculate_crc(storage, offset, R::WORDS, crc)
+        != storage.read_word(end - 1)
+    {
+        return Err(Error::Crc);
+    }
+
+    let data = offset + 2;
+    let mut reader = Reader {
+        storage,
+        index: data,
+        end: data + R::WORDS,
+    };
+    Ok(R::load(&mut reader))
+}
+
+/// Erases the record at `offset`, so that the next `load` returns
+/// [`Error::Empty`]
+pub fn erase<S: WordStorage>(storage: &mut S, offset: usize) {
+    if offset < storage.words() {
+        storage.write_word(offset, 0);
+    }
+}
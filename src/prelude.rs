@@ -1,26 +1,106 @@
 //! Prelude
+//!
+//! `use stm32h7xx_hal::prelude::*;` imports the embedded-hal prelude and
+//! the extension traits of this crate. The extension traits add a
+//! constructor to the peripherals of the device crate, for example
+//! `dp.SPI1.spi(..)`, and are imported anonymously so that their names do
+//! not clash with the application.
+//!
+//! Each trait can also be imported on its own from the module that
+//! defines it, for example [`GpioExt`](crate::gpio::GpioExt). The traits
+//! are grouped here as in the list of modules:
+//!
+//! * Clocks, power and system: `RccExt`, `PwrExt`, `FlashExt`, `DelayExt`,
+//!   `ExtiExt`, `HsemExt`, `CrsExt`
+//! * GPIO and timers: `GpioExt`, `TimerExt`, `PwmExt`, `QeiExt`,
+//!   `CaptureExt`, `U32Ext`
+//! * Analog: `AdcExt`, `DacExt`, `CompExt`, `OpampExt`, `VrefBufExt`,
+//!   `DfsdmExt`
+//! * Communication: `SerialExt`, `Reconfigure`, `SpiExt`, `I2cExt`,
+//!   `SaiPdmExt`, `MdiosExt`
+//! * Memory and DMA: `DmaExt`, `BdmaExt`, `FmcExt`, `QspiExt`,
+//!   `SdmmcExt`, `OtfdecExt`
+//! * Graphics: `Dma2dExt`, `GfxmmuExt`, `LtdcExt`
+//! * Cryptography: `CrcExt`, `CrypExt`, `HashExt`, `RngExt`, `RngCore`
 pub use embedded_hal::prelude::*;
 
-pub use crate::adc::AdcExt as _stm32h7xx_hal_adc_AdcExt;
-pub use crate::dac::DacExt as _stm32h7xx_hal_dac_DacExt;
+// Clocks, power and system
+pub use crate::crs::CrsExt as _stm32h7xx_hal_crs_CrsExt;
 pub use crate::delay::DelayExt as _stm32h7xx_hal_delay_DelayExt;
-pub use crate::exti::ExtiExt as _stm32h7xx_hal_delay_ExtiExt;
+pub use crate::exti::ExtiExt as _stm32h7xx_hal_exti_ExtiExt;
 pub use crate::flash::FlashExt as _stm32h7xx_hal_flash_FlashExt;
-#[cfg(feature = "fmc")]
-pub use crate::fmc::FmcExt as _stm32h7xx_hal_fmc_FmcExt;
+pub use crate::hsem::HsemExt as _stm32h7xx_hal_hsem_HsemExt;
+pub use crate::pwr::PwrExt as _stm32h7xx_hal_pwr_PwrExt;
+pub use crate::rcc::RccExt as _stm32h7xx_hal_rcc_RccExt;
+
+// GPIO and timers
+pub use crate::capture::CaptureExt as _stm32h7xx_hal_capture_CaptureExt;
 pub use crate::gpio::GpioExt as _stm32h7xx_hal_gpio_GpioExt;
+pub use crate::pwm::PwmExt as _stm32h7xx_hal_pwm_PwmExt;
+pub use crate::qei::QeiExt as _stm32h7xx_hal_qei_QeiExt;
+pub use crate::time::U32Ext as _stm32h7xx_hal_time_U32Ext;
+pub use crate::timer::TimerExt as _stm32h7xx_hal_timer_TimerExt;
+
+// Analog
+#[cfg(feature = "adc")]
+pub use crate::adc::AdcExt as _stm32h7xx_hal_adc_AdcExt;
+pub use crate::comp::CompExt as _stm32h7xx_hal_comp_CompExt;
+#[cfg(feature = "dac")]
+pub use crate::dac::DacExt as _stm32h7xx_hal_dac_DacExt;
+#[cfg(feature = "dfsdm")]
+pub use crate::dfsdm::DfsdmExt as _stm32h7xx_hal_dfsdm_DfsdmExt;
+pub use crate::opamp::OpampExt as _stm32h7xx_hal_opamp_OpampExt;
+pub use crate::vrefbuf::VrefBufExt as _stm32h7xx_hal_vrefbuf_VrefBufExt;
+
+// Communication
+#[cfg(feature = "i2c")]
 pub use crate::i2c::I2cExt as _stm32h7xx_hal_i2c_I2cExt;
-pub use crate::pwm::PwmExt as _stm32_hal_pwm_PwmExt;
-pub use crate::pwr::PwrExt as _stm32h7xx_hal_pwr_PwrExt;
-#[cfg(all(feature = "quadspi", not(feature = "rm0455")))]
+pub use crate::mdios::MdiosExt as _stm32h7xx_hal_mdios_MdiosExt;
+#[cfg(feature = "sai")]
+pub use crate::sai::SaiPdmExt as _stm32h7xx_hal_sai_SaiPdmExt;
+#[cfg(feature = "serial")]
+pub use crate::serial::Reconfigure as _stm32h7xx_hal_serial_Reconfigure;
+#[cfg(feature = "serial")]
+pub use crate::serial::SerialExt as _stm32h7xx_hal_serial_SerialExt;
+#[cfg(feature = "spi")]
+pub use crate::spi::SpiExt as _stm32h7xx_hal_spi_SpiExt;
+
+// Memory and DMA
+pub use crate::dma::bdma::BdmaExt as _stm32h7xx_hal_dma_bdma_BdmaExt;
+pub use crate::dma::dma::DmaExt as _stm32h7xx_hal_dma_dma_DmaExt;
+#[cfg(feature = "fmc")]
+pub use crate::fmc::FmcExt as _stm32h7xx_hal_fmc_FmcExt;
+#[cfg(any(
+    feature = "stm32h7b3",
+    feature = "stm32h7b0",
+    feature = "stm32h730",
+    feature = "stm32h733",
+    feature = "stm32h735"
+))]
+pub use crate::otfdec::OtfdecExt as _stm32h7xx_hal_otfdec_OtfdecExt;
+#[cfg(all(
+    feature = "quadspi",
+    not(any(feature = "rm0455", feature = "rm0468"))
+))]
 pub use crate::qspi::QspiExt as _stm32h7xx_hal_qspi_QspiExt;
-pub use crate::rcc::RccExt as _stm32h7xx_hal_rcc_RccExt;
-pub use crate::rng::RngCore as _stm32h7xx_hal_rng_RngCore;
-pub use crate::rng::RngExt as _stm32h7xx_hal_rng_RngExt;
-pub use crate::sai::SaiPdmExt as _stm32h7xx_hal_spi_SaiPdmExt;
 #[cfg(feature = "sdmmc")]
 pub use crate::sdmmc::SdmmcExt as _stm32h7xx_hal_sdmmc_SdmmcExt;
-pub use crate::serial::SerialExt as _stm32h7xx_hal_serial_SerialExt;
-pub use crate::spi::SpiExt as _stm32h7xx_hal_spi_SpiExt;
-pub use crate::time::U32Ext as _stm32h7xx_hal_time_U32Ext;
-pub use crate::timer::TimerExt as _stm32h7xx_hal_timer_TimerExt;
+
+// Graphics
+#[cfg(feature = "dma2d")]
+pub use crate::dma2d::Dma2dExt as _stm32h7xx_hal_dma2d_Dma2dExt;
+#[cfg(feature = "rm0455")]
+pub use crate::gfxmmu::GfxmmuExt as _stm32h7xx_hal_gfxmmu_GfxmmuExt;
+#[cfg(feature = "ltdc")]
+pub use crate::ltdc::LtdcExt as _stm32h7xx_hal_ltdc_LtdcExt;
+
+// Cryptography
+pub use crate::crc::CrcExt as _stm32h7xx_hal_crc_CrcExt;
+#[cfg(feature = "crypto")]
+pub use crate::cryp::CrypExt as _stm32h7xx_hal_cryp_CrypExt;
+#[cfg(feature = "crypto")]
+pub use crate::hash::HashExt as _stm32h7xx_hal_hash_HashExt;
+#[cfg(feature = "rng")]
+pub use crate::rng::RngCore as _stm32h7xx_hal_rng_RngCore;
+#[cfg(feature = "rng")]
+pub use crate::rng::RngExt as _stm32h7xx_hal_rng_RngExt;
@@ -38,10 +38,51 @@
 //!   c0.enable()
 //! ```
 //!
+//! ## Advanced control timers
+//!
+//! On `TIM1` and `TIM8`, each of channels 1 - 3 has a complementary output
+//! (CHxN) with programmable dead-time insertion. A break input can be used
+//! to disable the outputs immediately when a fault is detected.
+//!
+//! ```
+//!   // Complementary output on PA7, with 100ns dead time
+//!   c0.enable_complementary(gpioa.pa7.into_alternate_af1());
+//!   c0.set_dead_time(100.ns(), &clocks);
+//!
+//!   // Outputs are disabled when PA6 goes low
+//!   c0.enable_break(
+//!       gpioa.pa6.into_alternate_af1(),
+//!       BreakPolarity::ActiveLow,
+//!       false,
+//!   );
+//!
+//!   // After a fault
+//!   if c0.is_break_active() {
+//!       c0.clear_break();
+//!   }
+//! ```
+//!
+//! The dead time, break input and [`Alignment`] apply to all channels of the
+//! timer.
+//!
+//! ### System break
+//!
+//! Faults of the system, such as a lockup of the core or a double ECC error,
+//! can be hardwired to the break inputs of TIM1, TIM8 and TIM15 - TIM17 with
+//! [`connect_system_break`]. The connection cannot be undone until the next
+//! system reset.
+//!
+//! ```
+//!   pwm::connect_system_break(&mut dp.SYSCFG, SystemBreak::Cm7Lockup);
+//!   pwm::connect_system_break(&mut dp.SYSCFG, SystemBreak::FlashEcc);
+//!   c0.enable_system_break(false);
+//! ```
+//!
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
 
 use crate::hal;
+use crate::stm32::SYSCFG;
 use crate::stm32::{lptim1, lptim3};
 use crate::stm32::{LPTIM1, LPTIM2, LPTIM3};
 #[cfg(not(feature = "rm0455"))]
@@ -50,30 +91,41 @@ use crate::stm32::{
     TIM1, TIM12, TIM13, TIM14, TIM15, TIM16, TIM17, TIM2, TIM3, TIM4, TIM5,
     TIM8,
 };
+#[cfg(feature = "rm0468")]
+use crate::stm32::{TIM23, TIM24};
 
 use crate::rcc::{rec, CoreClocks, ResetEnable};
-use crate::time::Hertz;
+use crate::time::{Hertz, NanoSeconds};
 use crate::timer::GetClk;
 
 use crate::gpio::gpioa::{
     PA0, PA1, PA10, PA11, PA15, PA2, PA3, PA5, PA6, PA7, PA8, PA9,
 };
 use crate::gpio::gpiob::{
-    PB0, PB1, PB10, PB11, PB13, PB14, PB15, PB3, PB4, PB5, PB6, PB7, PB8, PB9,
+    PB0, PB1, PB10, PB11, PB12, PB13, PB14, PB15, PB3, PB4, PB5, PB6, PB7, PB8,
+    PB9,
 };
 #[cfg(feature = "rm0455")]
 use crate::gpio::gpioc::PC12;
 use crate::gpio::gpioc::{PC6, PC7, PC8, PC9};
 use crate::gpio::gpiod::{PD13, PD14, PD15};
-use crate::gpio::gpioe::{PE11, PE13, PE14, PE5, PE6, PE9};
+use crate::gpio::gpioe::{
+    PE10, PE11, PE12, PE13, PE14, PE15, PE5, PE6, PE8, PE9,
+};
 use crate::gpio::gpiof::{PF6, PF7, PF8, PF9};
-use crate::gpio::gpiog::PG13;
-use crate::gpio::gpioh::{PH10, PH11, PH12, PH6, PH9};
-use crate::gpio::gpioi::{PI0, PI2, PI5, PI6, PI7};
+use crate::gpio::gpiog::{PG13, PG2};
+use crate::gpio::gpioh::{PH10, PH11, PH12, PH13, PH14, PH15, PH6, PH9};
+use crate::gpio::gpioi::{PI0, PI2, PI4, PI5, PI6, PI7};
 #[cfg(not(feature = "stm32h7b0"))]
-use crate::gpio::gpioj::{PJ10, PJ11, PJ6, PJ8, PJ9};
+use crate::gpio::gpioj::{PJ10, PJ11, PJ6, PJ7, PJ8, PJ9};
 #[cfg(not(feature = "stm32h7b0"))]
-use crate::gpio::gpiok::{PK0, PK1};
+use crate::gpio::gpiok::{PK0, PK1, PK2};
+#[cfg(feature = "rm0468")]
+use crate::gpio::{
+    gpiof::{PF0, PF1, PF11, PF12, PF13, PF14, PF2, PF3},
+    gpiog::{PG12, PG14},
+    AF13, AF14,
+};
 
 use crate::gpio::{Alternate, AF1, AF2, AF3, AF4, AF9};
 
@@ -87,6 +139,42 @@ pub trait Pins<TIM, CHANNEL> {
     type Channel;
 }
 
+/// ComplementaryPin marks which GPIO pins may be used as the complementary
+/// output (CHxN) of a PWM channel
+pub trait ComplementaryPin<TIM, CHANNEL> {}
+
+/// BreakPin marks which GPIO pins may be used as the break input (BKIN) of
+/// an advanced control timer
+pub trait BreakPin<TIM> {}
+
+/// Polarity of the break input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BreakPolarity {
+    /// Break is active when the input is low
+    ActiveLow,
+    /// Break is active when the input is high
+    ActiveHigh,
+}
+
+/// Counter alignment of a PWM timer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Alignment {
+    /// The counter counts up, and the output changes once per period
+    Edge,
+    /// The counter counts up and down, and the output changes
+    /// symmetrically about the centre of each period
+    Center,
+}
+
+/// PWM configuration error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The period is too long to keep the PWM frequency when changing to
+    /// edge-aligned counting
+    PeriodTooLong,
+}
+
 /// Marker struct for PWM channel 1 on Pins trait and Pwm struct
 pub struct C1;
 /// Marker struct for PWM channel 2 on Pins trait and Pwm struct
@@ -262,9 +350,53 @@ macro_rules! pins {
                     type Channel = Pwm<$TIMX, C4>;
                 }
             )*
+            $(
+                $( #[ $pmeta5 ] )*
+                impl ComplementaryPin<$TIMX, C1> for $CH1N {}
+            )*
+            $(
+                $( #[ $pmeta6 ] )*
+                impl ComplementaryPin<$TIMX, C2> for $CH2N {}
+            )*
+            $(
+                $( #[ $pmeta7 ] )*
+                impl ComplementaryPin<$TIMX, C3> for $CH3N {}
+            )*
+            $(
+                $( #[ $pmeta8 ] )*
+                impl ComplementaryPin<$TIMX, C4> for $CH4N {}
+            )*
+        )+
+    }
+}
+
+// Break input pins
+macro_rules! break_pins {
+    ($($TIMX:ty: [$($( #[ $pmeta:meta ] )* $BKIN:ty),*])+) => {
+        $(
+            $(
+                $( #[ $pmeta ] )*
+                impl BreakPin<$TIMX> for $BKIN {}
+            )*
         )+
     }
 }
+break_pins! {
+    TIM1: [
+        PA6<Alternate<AF1>>,
+        PB12<Alternate<AF1>>,
+        PE15<Alternate<AF1>>,
+        #[cfg(not(feature = "stm32h7b0"))]
+        PK2<Alternate<AF1>>
+    ]
+    TIM8: [
+        PA6<Alternate<AF3>>,
+        PG2<Alternate<AF3>>,
+        PI4<Alternate<AF3>>,
+        #[cfg(not(feature = "stm32h7b0"))]
+        PK2<Alternate<AF3>>
+    ]
+}
 // Single channel timers
 pins! {
     LPTIM1:
@@ -546,6 +678,46 @@ pins! {
         ]
         CH4N: []
 }
+#[cfg(feature = "rm0468")]
+pins! {
+    TIM23:
+        CH1: [
+            PF0<Alternate<AF13>>,
+            PG12<Alternate<AF13>>
+        ]
+        CH2: [
+            PF1<Alternate<AF13>>,
+            PG13<Alternate<AF13>>
+        ]
+        CH3: [
+            PF2<Alternate<AF13>>,
+            PG14<Alternate<AF13>>
+        ]
+        CH4: [
+            PF3<Alternate<AF13>>
+        ]
+        CH1N: []
+        CH2N: []
+        CH3N: []
+        CH4N: []
+    TIM24:
+        CH1: [
+            PF11<Alternate<AF14>>
+        ]
+        CH2: [
+            PF12<Alternate<AF14>>
+        ]
+        CH3: [
+            PF13<Alternate<AF14>>
+        ]
+        CH4: [
+            PF14<Alternate<AF14>>
+        ]
+        CH1N: []
+        CH2N: []
+        CH3N: []
+        CH4N: []
+}
 
 // PwmExt trait
 /// Allows the pwm() method to be added to the peripheral register structs from the device crate
@@ -665,6 +837,11 @@ tim_hal! {
     TIM16: (tim16, Tim16, u16, 16),
     TIM17: (tim17, Tim17, u16, 16),
 }
+#[cfg(feature = "rm0468")]
+tim_hal! {
+    TIM23: (tim23, Tim23, u32, 32),
+    TIM24: (tim24, Tim24, u32, 32),
+}
 
 // Implement PwmPin for timer channels
 macro_rules! tim_pin_hal {
@@ -774,6 +951,20 @@ tim_pin_hal! {
     TIM5: (C3, cc3e, ccmr2_output, oc3pe, oc3m, ccr3, u32),
     TIM5: (C4, cc4e, ccmr2_output, oc4pe, oc4m, ccr4, u32),
 }
+#[cfg(feature = "rm0468")]
+tim_pin_hal! {
+    TIM23: (C1, cc1e, ccmr1_output, oc1pe, oc1m, ccr1, u32),
+    TIM23: (C2, cc2e, ccmr1_output, oc2pe, oc2m, ccr2, u32),
+    TIM23: (C3, cc3e, ccmr2_output, oc3pe, oc3m, ccr3, u32),
+    TIM23: (C4, cc4e, ccmr2_output, oc4pe, oc4m, ccr4, u32),
+}
+#[cfg(feature = "rm0468")]
+tim_pin_hal! {
+    TIM24: (C1, cc1e, ccmr1_output, oc1pe, oc1m, ccr1, u32),
+    TIM24: (C2, cc2e, ccmr1_output, oc2pe, oc2m, ccr2, u32),
+    TIM24: (C3, cc3e, ccmr2_output, oc3pe, oc3m, ccr3, u32),
+    TIM24: (C4, cc4e, ccmr2_output, oc4pe, oc4m, ccr4, u32),
+}
 tim_pin_hal! {
     TIM8: (C1, cc1e, ccmr1_output, oc1pe, oc1m, ccr1, u16),
     TIM8: (C2, cc2e, ccmr1_output, oc2pe, oc2m, ccr2, u16),
@@ -781,6 +972,316 @@ tim_pin_hal! {
     TIM8: (C4, cc4e, ccmr2_output, oc4pe, oc4m, ccr4, u16),
 }
 
+// Complementary outputs for advanced control timers
+macro_rules! tim_complementary_hal {
+    ($($TIMX:ident: ($CH:ty, $ccxne:ident, $ccxnp:ident),)+) => {
+        $(
+            impl Pwm<$TIMX, $CH> {
+                /// Enables the complementary output of this channel on
+                /// `pin`. The complementary output is the inverse of the
+                /// channel output, with dead time inserted. It is only
+                /// active while the channel is enabled
+                pub fn enable_complementary<PIN>(&mut self, _pin: PIN)
+                where
+                    PIN: ComplementaryPin<$TIMX, $CH>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.ccer.modify(|_, w| w.$ccxnp().clear_bit().$ccxne().set_bit());
+                }
+
+                /// Disables the complementary output of this channel
+                pub fn disable_complementary(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.ccer.modify(|_, w| w.$ccxne().clear_bit());
+                }
+            }
+        )+
+    };
+}
+tim_complementary_hal! {
+    TIM1: (C1, cc1ne, cc1np),
+    TIM1: (C2, cc2ne, cc2np),
+    TIM1: (C3, cc3ne, cc3np),
+    TIM8: (C1, cc1ne, cc1np),
+    TIM8: (C2, cc2ne, cc2np),
+    TIM8: (C3, cc3ne, cc3np),
+}
+
+// Dead time and break input for advanced control timers
+macro_rules! tim_advanced_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl<CH> Pwm<$TIMX, CH> {
+                /// Sets the dead time inserted between the channel outputs
+                /// and the complementary outputs. This applies to all
+                /// channels of the timer
+                ///
+                /// # Panics
+                ///
+                /// Panics if the dead time is longer than 4032 cycles of the
+                /// timer kernel clock
+                pub fn set_dead_time<T>(&mut self, dead_time: T, clocks: &CoreClocks)
+                where
+                    T: Into<NanoSeconds>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!").0;
+                    let ticks = (u64::from(clk) * u64::from(dead_time.into().0)
+                                 + 999_999_999) / 1_000_000_000;
+
+                    // Choose the smallest tDTS that fits
+                    let (ckd, dtg) = (0..3u8)
+                        .find_map(|ckd| dead_time_generator((ticks >> ckd) as u32)
+                                  .map(|dtg| (ckd, dtg)))
+                        .expect("Dead time too long");
+
+                    // CKD can only be written while the counter is disabled
+                    let cen = tim.cr1.read().cen().is_enabled();
+                    tim.cr1.modify(|_, w| w.cen().disabled());
+                    tim.cr1.modify(|_, w| unsafe { w.ckd().bits(ckd) });
+                    tim.bdtr.modify(|_, w| w.dtg().bits(dtg));
+                    tim.cr1.modify(|_, w| w.cen().bit(cen));
+                }
+
+                /// Enables the break input on `pin`. While the break is
+                /// active, all outputs of the timer are disabled
+                ///
+                /// If `auto_reenable` is `true`, the outputs are enabled
+                /// again at the next update event after the break input is
+                /// inactive. Otherwise they remain disabled until
+                /// [`clear_break`](Pwm::clear_break)
+                pub fn enable_break<PIN>(&mut self, _pin: PIN,
+                                         polarity: BreakPolarity,
+                                         auto_reenable: bool)
+                where
+                    PIN: BreakPin<$TIMX>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // Outputs are driven to their idle state when disabled
+                    tim.bdtr.modify(|_, w| {
+                        w.bkp().bit(polarity == BreakPolarity::ActiveHigh)
+                            .aoe().bit(auto_reenable)
+                            .ossi().set_bit()
+                            .ossr().set_bit()
+                            .bke().set_bit()
+                    });
+                    let _ = tim.bdtr.read();
+                    let _ = tim.bdtr.read(); // Break takes 1 APB clock cycle
+
+                    tim.sr.modify(|_, w| w.bif().clear_bit());
+                }
+
+                /// Enables the break input for the system faults connected
+                /// with [`connect_system_break`], without a break pin
+                ///
+                /// The break input is made active high, replacing any
+                /// polarity set by [`enable_break`](Pwm::enable_break).
+                /// `auto_reenable` is as for `enable_break`.
+                pub fn enable_system_break(&mut self, auto_reenable: bool) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.bdtr.modify(|_, w| {
+                        w.bkp().set_bit()
+                            .aoe().bit(auto_reenable)
+                            .ossi().set_bit()
+                            .ossr().set_bit()
+                            .bke().set_bit()
+                    });
+                    let _ = tim.bdtr.read();
+                    let _ = tim.bdtr.read(); // Break takes 1 APB clock cycle
+
+                    tim.sr.modify(|_, w| w.bif().clear_bit());
+                }
+
+                /// Disables the break input
+                pub fn disable_break(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.bdtr.modify(|_, w| w.bke().clear_bit());
+                }
+
+                /// Returns `true` if a break has occurred since the last call
+                /// to [`clear_break`](Pwm::clear_break)
+                pub fn is_break_active(&self) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.sr.read().bif().bit_is_set()
+                }
+
+                /// Clears the break flag and enables the outputs again. If
+                /// the break input is still active, the outputs remain
+                /// disabled
+                pub fn clear_break(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.sr.modify(|_, w| w.bif().clear_bit());
+                    tim.bdtr.modify(|_, w| w.moe().enabled());
+                }
+            }
+        )+
+    };
+}
+tim_advanced_hal! {
+    TIM1,
+    TIM8,
+}
+
+/// System faults that can be connected to the break inputs of timers
+//
+// Refer to RM0433 Rev 7 - Chapter 12.3.6
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SystemBreak {
+    /// Lockup of the Cortex-M4
+    #[cfg(feature = "rm0399")]
+    Cm4Lockup,
+    /// Programmable voltage detector output
+    Pvd,
+    /// Double ECC error in the flash
+    FlashEcc,
+    /// Lockup of the Cortex-M7
+    Cm7Lockup,
+    /// Double ECC error in the backup RAM
+    BackupRamEcc,
+    /// Double ECC error in SRAM4
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    Sram4Ecc,
+    /// Double ECC error in SRAM3
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    Sram3Ecc,
+    /// Double ECC error in SRAM2
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    Sram2Ecc,
+    /// Double ECC error in SRAM1
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    Sram1Ecc,
+    /// Double ECC error in the DTCM
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    DtcmEcc,
+    /// Double ECC error in the ITCM
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    ItcmEcc,
+    /// Double ECC error in the AXI SRAM
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    AxiSramEcc,
+}
+
+/// Connects `source` to the break inputs of TIM1, TIM8, TIM15, TIM16, TIM17
+/// (and the HRTIM on parts that have it)
+///
+/// The connection is locked, and can only be undone by a system reset. The
+/// SYSCFG clock must be enabled. The break input of each timer must also be
+/// enabled, for example with
+/// [`enable_system_break`](Pwm::enable_system_break).
+pub fn connect_system_break(syscfg: &mut SYSCFG, source: SystemBreak) {
+    // The bits can only be cleared by a system reset
+    syscfg.cfgr.modify(|_, w| match source {
+        #[cfg(feature = "rm0399")]
+        SystemBreak::Cm4Lockup => w.cm4l().set_bit(),
+        SystemBreak::Pvd => w.pvdl().set_bit(),
+        SystemBreak::FlashEcc => w.flashl().set_bit(),
+        SystemBreak::Cm7Lockup => w.cm7l().set_bit(),
+        SystemBreak::BackupRamEcc => w.bkraml().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::Sram4Ecc => w.sram4l().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::Sram3Ecc => w.sram3l().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::Sram2Ecc => w.sram2l().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::Sram1Ecc => w.sram1l().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::DtcmEcc => w.dtcml().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::ItcmEcc => w.itcml().set_bit(),
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        SystemBreak::AxiSramEcc => w.axisraml().set_bit(),
+    });
+}
+
+/// Returns DTG[7:0] for a dead time of `ticks` periods of tDTS
+//
+// Refer to RM0433 Rev 7 - Section 39.4.20
+fn dead_time_generator(ticks: u32) -> Option<u8> {
+    match ticks {
+        0..=127 => Some(ticks as u8),
+        128..=254 => Some(0b1000_0000 | (((ticks + 1) / 2 - 64) as u8)),
+        255..=504 => Some(0b1100_0000 | (((ticks + 7) / 8 - 32) as u8)),
+        505..=1008 => Some(0b1110_0000 | (((ticks + 15) / 16 - 32) as u8)),
+        _ => None,
+    }
+}
+
+// Center-aligned counting
+macro_rules! tim_alignment_hal {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl<CH> Pwm<$TIMX, CH> {
+                /// Sets the counter alignment. This applies to all channels
+                /// of the timer
+                ///
+                /// In center-aligned mode the counter counts up to the
+                /// maximum duty and back down again. The maximum duty is
+                /// halved so that the PWM frequency is unchanged
+                ///
+                /// Returns [`ConfigError::PeriodTooLong`] when changing to
+                /// edge-aligned counting if the doubled maximum duty does
+                /// not fit in the auto-reload register. The alignment is
+                /// not changed in this case.
+                pub fn set_alignment(
+                    &mut self,
+                    alignment: Alignment,
+                ) -> Result<(), ConfigError> {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let center = tim.cr1.read().cms().bits() != 0;
+                    let arr = tim.arr.read().arr().bits();
+                    let arr = match (alignment, center) {
+                        (Alignment::Edge, true) => arr
+                            .checked_mul(2)
+                            .ok_or(ConfigError::PeriodTooLong)?,
+                        (Alignment::Center, false) => arr / 2,
+                        _ => arr,
+                    };
+
+                    // CMS can only be written while the counter is disabled
+                    let cen = tim.cr1.read().cen().is_enabled();
+                    tim.cr1.modify(|_, w| w.cen().disabled());
+                    tim.arr.write(|w| w.arr().bits(arr));
+                    match alignment {
+                        Alignment::Edge => {
+                            tim.cr1.modify(|_, w| w.cms().edge_aligned())
+                        }
+                        Alignment::Center => {
+                            tim.cr1.modify(|_, w| w.cms().center_aligned3())
+                        }
+                    }
+                    tim.cr1.modify(|_, w| w.cen().bit(cen));
+
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+tim_alignment_hal! {
+    TIM1,
+    TIM2,
+    TIM3,
+    TIM4,
+    TIM5,
+    TIM8,
+}
+#[cfg(feature = "rm0468")]
+tim_alignment_hal! {
+    TIM23,
+    TIM24,
+}
+
 // Low-power timers
 macro_rules! lptim_hal {
     ($($TIMX:ident: ($timX:ident, $Rec:ident, $timXpac:ident),)+) => {
@@ -894,3 +1395,62 @@ lptim_hal! {
     LPTIM4: (lptim4, Lptim4, lptim3),
     LPTIM5: (lptim5, Lptim5, lptim3),
 }
+
+impl Pwm<LPTIM2, C1> {
+    /// Configures LPTIM2 as a low frequency PWM clocked from the LSE, for
+    /// example to blink a status LED
+    ///
+    /// The kernel clock of LPTIM2 is set to the LSE, and its peripheral
+    /// clock to
+    /// [`LowPowerMode::Autonomous`](crate::rcc::LowPowerMode::Autonomous),
+    /// so that the output continues while the core is in Stop mode. The
+    /// output is enabled with a duty cycle of `duty_percent`.
+    ///
+    /// ```
+    /// let led = gpiob.pb13.into_alternate_af3();
+    /// let _blink = Pwm::lptim2_led(
+    ///     dp.LPTIM2, led, 1.hz(), 10, ccdr.peripheral.LPTIM2, &ccdr.clocks);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the LSE is not running, or if `duty_percent` is greater
+    /// than 100
+    pub fn lptim2_led<PINS, T>(
+        tim: LPTIM2,
+        pins: PINS,
+        frequency: T,
+        duty_percent: u8,
+        prec: rec::Lptim2,
+        clocks: &CoreClocks,
+    ) -> Self
+    where
+        PINS: Pins<LPTIM2, C1, Channel = Self>,
+        T: Into<Hertz>,
+    {
+        use crate::rcc::LowPowerMode;
+        use hal::PwmPin;
+
+        assert!(duty_percent <= 100, "Duty cycle must be at most 100%");
+
+        let prec = prec
+            .kernel_clk_mux(rec::Lptim2ClkSel::LSE)
+            .low_power(LowPowerMode::Autonomous);
+        assert!(
+            LPTIM2::get_clk(clocks).is_some(),
+            "The LSE must be running to clock LPTIM2"
+        );
+
+        let mut pwm =
+            lptim2::<PINS, C1>(tim, pins, frequency.into(), prec, clocks);
+
+        // Duty cycle, and the compare register can only be written while
+        // the LPTIM is enabled
+        let max = u32::from(pwm.get_max_duty());
+        let duty = max * u32::from(duty_percent) / 100;
+        pwm.enable();
+        pwm.set_duty(duty as u16);
+
+        pwm
+    }
+}
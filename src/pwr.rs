@@ -1,8 +1,13 @@
 //! Power Configuration
 //!
 //! This module configures the PWR unit to provide the core voltage
-//! `VCORE`. The voltage scaling mode is fixed at VOS1 (High
-//! Performance).
+//! `VCORE`. The voltage scaling mode is VOS1 (High Performance) by
+//! default. Lower voltage scales can be selected with
+//! [vos2](Pwr#method.vos2) and [vos3](Pwr#method.vos3), and parts that
+//! support it can use VOS0 with [vos0](Pwr#method.vos0).
+//!
+//! The voltage scale can also be lowered at runtime, see
+//! [LowPowerProfile](crate::rcc::LowPowerProfile).
 //!
 //! When the system starts up, it is in Run* mode. After the call to
 //! `freeze`, it will be in Run mode. See RM0433 Rev 7 Section 6.6.1
@@ -19,6 +24,17 @@
 //!     assert_eq!(pwrcfg.vos(), VoltageScale::Scale1);
 //! ```
 //!
+//! # Supply Configuration
+//!
+//! All parts can supply VCORE from the internal Low Dropout Regulator
+//! (LDO), or from an external source with the LDO bypassed.
+//!
+//! - Low Dropout Regulator [ldo](Pwr#method.ldo)
+//! - Bypass [bypass](Pwr#method.bypass)
+//!
+//! If neither is specified, parts without an SMPS are configured to use
+//! the LDO.
+//!
 //! # SMPS
 //!
 //! Some parts include an integrated Switched Mode Power Supply (SMPS)
@@ -26,11 +42,23 @@
 //! can be specified. Refer to RM0399 Rev 3 Table 32. for a more
 //! detailed descriptions of the possible modes.
 //!
-//! - Low Dropout Regulator [ldo](Pwr#ldo)
-//! - Switch Mode Power Supply [smps](Pwr#smps)
-//! - Bypass [bypass](Pwr#pypass)
-//! - SMPS Output at 1.8V, then LDO [smps_1v8_feeds_ldo](Pwr#smps_1v8_feeds_ldo)
-//! - SMPS Output at 2.5V, then LDO [smps_2v5_feeds_ldo](Pwr#smps_2v5_feeds_ldo)
+//! - Switch Mode Power Supply [smps](Pwr#method.smps)
+//! - SMPS Output at 1.8V, then LDO [smps_1v8_feeds_ldo](Pwr#method.smps_1v8_feeds_ldo)
+//! - SMPS Output at 2.5V, then LDO [smps_2v5_feeds_ldo](Pwr#method.smps_2v5_feeds_ldo)
+//! - SMPS Output at 1.8V to external circuits, then LDO [smps_1v8_feeds_ldo_and_external](Pwr#method.smps_1v8_feeds_ldo_and_external)
+//! - SMPS Output at 2.5V to external circuits, then LDO [smps_2v5_feeds_ldo_and_external](Pwr#method.smps_2v5_feeds_ldo_and_external)
+//! - SMPS Output at 1.8V to external circuits, LDO bypassed [smps_1v8_external_ldo_bypass](Pwr#method.smps_1v8_external_ldo_bypass)
+//! - SMPS Output at 2.5V to external circuits, LDO bypassed [smps_2v5_external_ldo_bypass](Pwr#method.smps_2v5_external_ldo_bypass)
+//!
+//! These methods are only available when the `smps` feature is
+//! enabled, which is the case for the device features of parts that
+//! include an SMPS.
+//!
+//! If no method is specified, the contents of PWR.CR3 are not modified
+//! and the part will continue to use its default supply
+//! configuration. This default varies between packages (See RM0399
+//! Section 7.8.4 Footnote 2), so it is recommended that a mode is
+//! always specified for parts with an SMPS.
 //!
 //! **Note**: Specifying the wrong mode for your hardware will cause
 //! undefined results.
@@ -48,11 +76,60 @@
 //! POR, and this is enforced by hardware. If you add or change the
 //! power supply method, `freeze` will panic until you power on reset
 //! your board.
+//!
+//! # Brown-out Reset
+//!
+//! The brown-out reset level is an option byte rather than a PWR
+//! setting. On RM0433 and RM0399 parts it is read with
+//! [`Flash::bor_level`](crate::flash::Flash::bor_level) and changed with
+//! [`Flash::set_bor_level`](crate::flash::Flash::set_bor_level).
+//!
+//! # Stop Mode
+//!
+//! [`enter_stop`] enters Stop mode with all domains in DStop (the D1, D2
+//! and D3 domains, or the CD and SRD domains on RM0455 parts), and returns
+//! after the CPU has woken up. With `audit` it first
+//! checks that the CPU can be woken, and returns an error instead of
+//! sleeping forever.
+//!
+//! ```
+//! exti.listen(Event::GPIO13);
+//! exti.trigger(Event::GPIO13, Edge::Falling);
+//!
+//! pwr::enter_stop(&mut cp.SCB, true).unwrap();
+//! ```
+//!
+//! The system wakes up running from the HSI, or the CSI if STOPWUCK is
+//! set. The PLLs are stopped, and must be enabled again before the
+//! peripherals that use them.
+//!
+//! After waking up, [`low_power_status`] tells which low-power mode each
+//! domain was in. A domain that was in DStandby has lost the state of its
+//! peripherals, which must be initialised again. [`wakeup_reason`] decodes
+//! the source that woke the CPU. Interrupt handlers run as soon as the CPU
+//! wakes up and usually clear that source, so read it with interrupts
+//! disabled:
+//!
+//! ```
+//! let reason = cortex_m::interrupt::free(|_| {
+//!     pwr::enter_stop(&mut cp.SCB, true).unwrap();
+//!     pwr::wakeup_reason()
+//! });
+//! if pwr::low_power_status().d2_standby {
+//!     // Initialise the D2 peripherals again
+//! }
+//! ```
 
 use crate::rcc::backup::BackupREC;
-use crate::stm32::PWR;
+#[cfg(all(
+    feature = "revision_v",
+    any(feature = "rm0433", feature = "rm0399")
+))]
+use crate::stm32::RCC;
 #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
-use crate::stm32::{RCC, SYSCFG};
+use crate::stm32::SYSCFG;
+use crate::stm32::{pwr, PWR};
+use cortex_m::peripheral::SCB;
 
 /// Extension trait that constrains the `PWR` peripheral
 pub trait PwrExt {
@@ -63,8 +140,8 @@ impl PwrExt for PWR {
     fn constrain(self) -> Pwr {
         Pwr {
             rb: self,
-            #[cfg(any(feature = "smps"))]
             supply_configuration: SupplyConfiguration::Default,
+            target_vos: VoltageScale::Scale1,
             #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
             enable_vos0: false,
         }
@@ -76,8 +153,8 @@ impl PwrExt for PWR {
 /// Generated by calling `constrain` on the PAC's PWR peripheral.
 pub struct Pwr {
     pub(crate) rb: PWR,
-    #[cfg(any(feature = "smps"))]
     supply_configuration: SupplyConfiguration,
+    target_vos: VoltageScale,
     #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
     enable_vos0: bool,
 }
@@ -119,25 +196,36 @@ impl PowerConfiguration {
     }
 }
 
-/// SMPS Supply Configuration - Dual Core parts
+/// VCORE Supply Configuration
 ///
-/// Refer to RM0399 Rev 3 Table 32.
-#[cfg(any(feature = "smps"))]
+/// Refer to RM0433 Rev 7 Section 6.4 for parts without an SMPS, and RM0399 Rev
+/// 3 Table 32. for parts with an SMPS.
+#[derive(Copy, Clone, PartialEq)]
 enum SupplyConfiguration {
     Default = 0,
     LDOSupply,
+    #[cfg(feature = "smps")]
     DirectSMPS,
+    #[cfg(feature = "smps")]
     SMPSFeedsIntoLDO1V8,
+    #[cfg(feature = "smps")]
     SMPSFeedsIntoLDO2V5,
-    // External SMPS loads not supported
+    #[cfg(feature = "smps")]
+    SMPSExternalFeedsIntoLDO1V8,
+    #[cfg(feature = "smps")]
+    SMPSExternalFeedsIntoLDO2V5,
+    #[cfg(feature = "smps")]
+    SMPSExternalBypass1V8,
+    #[cfg(feature = "smps")]
+    SMPSExternalBypass2V5,
     Bypass,
 }
 
-#[cfg(any(feature = "smps"))]
 macro_rules! supply_configuration_setter {
-    ($($config:ident: $name:ident, $doc:expr,)*) => {
+    ($($( #[ $pmeta:meta ] )* $config:ident: $name:ident, $doc:expr,)*) => {
         $(
             #[doc=$doc]
+            $( #[ $pmeta ] )*
             pub fn $name(mut self) -> Self {
                 self.supply_configuration = SupplyConfiguration::$config;
                 self
@@ -147,55 +235,80 @@ macro_rules! supply_configuration_setter {
 }
 
 // smpslevel / sdlevel macro
-#[cfg(all(feature = "smps", not(feature = "rm0455")))]
+#[cfg(all(feature = "smps", any(feature = "rm0433", feature = "rm0399")))]
 macro_rules! smps_level {
     ($e:expr) => {
         $e.sdlevel()
     };
 }
-#[cfg(all(feature = "smps", feature = "rm0455"))]
+#[cfg(all(feature = "smps", any(feature = "rm0455", feature = "rm0468")))]
 macro_rules! smps_level {
     ($e:expr) => {
         $e.smpslevel()
     };
 }
 
+// smpsexthp / sdexthp macro
+#[cfg(all(feature = "smps", any(feature = "rm0433", feature = "rm0399")))]
+macro_rules! smps_ext_hp {
+    ($e:expr) => {
+        $e.sdexthp()
+    };
+}
+#[cfg(all(feature = "smps", any(feature = "rm0455", feature = "rm0468")))]
+macro_rules! smps_ext_hp {
+    ($e:expr) => {
+        $e.smpsexthp()
+    };
+}
+
 /// Internal power methods
 impl Pwr {
     /// Verify that the lower byte of CR3 reads as written
-    #[cfg(any(feature = "smps"))]
     fn verify_supply_configuration(&self) {
         use SupplyConfiguration::*;
         let error = "Values in lower byte of PWR.CR3 do not match the \
                      configured power mode. These values can only be set \
                      once for each POR (Power-on-Reset). Try removing power \
                      to your board.";
+        let cr3 = self.rb.cr3.read();
 
-        match self.supply_configuration {
-            LDOSupply => {
-                assert!(self.rb.cr3.read().sden().bit_is_clear(), error);
-                assert!(self.rb.cr3.read().ldoen().bit_is_set(), error);
+        // (SMPS enabled, SMPS level, SMPS external, LDO enabled, Bypass)
+        #[cfg(feature = "smps")]
+        let expected = match self.supply_configuration {
+            LDOSupply => (false, None, false, true, false),
+            DirectSMPS => (true, None, false, false, false),
+            SMPSFeedsIntoLDO1V8 => (true, Some(1), false, true, false),
+            SMPSFeedsIntoLDO2V5 => (true, Some(2), false, true, false),
+            SMPSExternalFeedsIntoLDO1V8 => (true, Some(1), true, true, false),
+            SMPSExternalFeedsIntoLDO2V5 => (true, Some(2), true, true, false),
+            SMPSExternalBypass1V8 => (true, Some(1), true, false, true),
+            SMPSExternalBypass2V5 => (true, Some(2), true, false, true),
+            Bypass => (false, None, false, false, true),
+            Default => return, // Default configuration is NOT verified
+        };
+        #[cfg(feature = "smps")]
+        {
+            let (sden, level, ext, ldoen, bypass) = expected;
+            assert!(cr3.sden().bit() == sden, error);
+            if let Some(level) = level {
+                assert!(smps_level!(cr3).bits() == level, error);
             }
-            DirectSMPS => {
-                assert!(self.rb.cr3.read().sden().bit_is_set(), error);
-                assert!(self.rb.cr3.read().ldoen().bit_is_clear(), error);
-            }
-            SMPSFeedsIntoLDO1V8 => {
-                assert!(self.rb.cr3.read().sden().bit_is_set(), error);
-                assert!(self.rb.cr3.read().ldoen().bit_is_clear(), error);
-                assert!(smps_level!(self.rb.cr3.read()).bits() == 1, error);
-            }
-            SMPSFeedsIntoLDO2V5 => {
-                assert!(self.rb.cr3.read().sden().bit_is_set(), error);
-                assert!(self.rb.cr3.read().ldoen().bit_is_clear(), error);
-                assert!(smps_level!(self.rb.cr3.read()).bits() == 2, error);
+            assert!(smps_ext_hp!(cr3).bit() == ext, error);
+            assert!(cr3.ldoen().bit() == ldoen, error);
+            assert!(cr3.bypass().bit() == bypass, error);
+        }
+
+        #[cfg(not(feature = "smps"))]
+        match self.supply_configuration {
+            Default | LDOSupply => {
+                assert!(cr3.ldoen().bit_is_set(), error);
+                assert!(cr3.bypass().bit_is_clear(), error);
             }
             Bypass => {
-                assert!(self.rb.cr3.read().sden().bit_is_clear(), error);
-                assert!(self.rb.cr3.read().ldoen().bit_is_clear(), error);
-                assert!(self.rb.cr3.read().bypass().bit_is_set(), error);
+                assert!(cr3.ldoen().bit_is_clear(), error);
+                assert!(cr3.bypass().bit_is_set(), error);
             }
-            Default => {} // Default configuration is NOT verified
         }
     }
 
@@ -204,44 +317,65 @@ impl Pwr {
     ///
     /// Does NOT implement overdrive (back-bias)
     fn voltage_scaling_transition(&self, new_scale: VoltageScale) {
-        self.rb.d3cr.write(|w| unsafe {
-            // Manually set field values for each family
-            w.vos().bits(
-                #[cfg(not(feature = "rm0455"))]
-                match new_scale {
-                    // RM0433 Rev 7 6.8.6
-                    VoltageScale::Scale3 => 0b01,
-                    VoltageScale::Scale2 => 0b10,
-                    VoltageScale::Scale1 => 0b11,
-                    _ => unimplemented!(),
-                },
-                #[cfg(feature = "rm0455")]
-                match new_scale {
-                    // RM0455 Rev 3 6.8.6
-                    VoltageScale::Scale3 => 0b00,
-                    VoltageScale::Scale2 => 0b01,
-                    VoltageScale::Scale1 => 0b10,
-                    VoltageScale::Scale0 => 0b11,
-                },
-            )
-        });
-        while self.rb.d3cr.read().vosrdy().bit_is_clear() {}
+        voltage_scaling_transition(&self.rb, new_scale);
     }
 }
 
+/// Transition between voltage scaling levels using the D3CR / SRDCR
+/// register
+///
+/// Does NOT implement overdrive (back-bias)
+pub(crate) fn voltage_scaling_transition(
+    rb: &pwr::RegisterBlock,
+    new_scale: VoltageScale,
+) {
+    rb.d3cr.write(|w| unsafe {
+        // Manually set field values for each family
+        w.vos().bits(
+            #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+            match new_scale {
+                // RM0433 Rev 7 6.8.6
+                VoltageScale::Scale3 => 0b01,
+                VoltageScale::Scale2 => 0b10,
+                VoltageScale::Scale1 => 0b11,
+                _ => unimplemented!(),
+            },
+            #[cfg(feature = "rm0455")]
+            match new_scale {
+                // RM0455 Rev 3 6.8.6
+                VoltageScale::Scale3 => 0b00,
+                VoltageScale::Scale2 => 0b01,
+                VoltageScale::Scale1 => 0b10,
+                VoltageScale::Scale0 => 0b11,
+            },
+            #[cfg(feature = "rm0468")]
+            match new_scale {
+                // RM0468 Rev 2 6.8.6
+                VoltageScale::Scale3 => 0b01,
+                VoltageScale::Scale2 => 0b10,
+                VoltageScale::Scale1 => 0b11,
+                VoltageScale::Scale0 => 0b00,
+            },
+        )
+    });
+    while rb.d3cr.read().vosrdy().bit_is_clear() {}
+}
+
 /// Builder methods
 impl Pwr {
-    #[cfg(any(feature = "smps"))]
     supply_configuration_setter! {
         LDOSupply: ldo, "VCORE power domains supplied from the LDO. \
                          LDO voltage adjusted by VOS. \
                          LDO power mode will follow the system \
                          low-power mode.",
+        #[cfg(feature = "smps")]
         DirectSMPS: smps, "VCORE power domains are supplied from the \
                            SMPS step-down converter. SMPS output voltage \
                            adjusted by VOS. SMPS power mode will follow \
                            the system low-power mode",
-        Bypass: bypass, "VCORE is supplied from an external source",
+        Bypass: bypass, "VCORE is supplied from an external source. The \
+                         LDO is bypassed.",
+        #[cfg(feature = "smps")]
         SMPSFeedsIntoLDO1V8:
         smps_1v8_feeds_ldo, "VCORE power domains supplied from the LDO. \
                          LDO voltage adjusted by VOS. \
@@ -249,6 +383,7 @@ impl Pwr {
                          low-power mode. SMPS output voltage set to \
                          1.8V. SMPS power mode will follow \
                          the system low-power mode",
+        #[cfg(feature = "smps")]
         SMPSFeedsIntoLDO2V5:
         smps_2v5_feeds_ldo, "VCORE power domains supplied from the LDO. \
                          LDO voltage adjusted by VOS. \
@@ -256,10 +391,58 @@ impl Pwr {
                          low-power mode. SMPS output voltage set to \
                          2.5V. SMPS power mode will follow \
                          the system low-power mode",
+        #[cfg(feature = "smps")]
+        SMPSExternalFeedsIntoLDO1V8:
+        smps_1v8_feeds_ldo_and_external, "VCORE power domains supplied \
+                         from the LDO. LDO voltage adjusted by VOS. \
+                         SMPS output voltage set to 1.8V, and also \
+                         supplies external circuits. The SMPS stays in \
+                         high-power mode in all system low-power modes",
+        #[cfg(feature = "smps")]
+        SMPSExternalFeedsIntoLDO2V5:
+        smps_2v5_feeds_ldo_and_external, "VCORE power domains supplied \
+                         from the LDO. LDO voltage adjusted by VOS. \
+                         SMPS output voltage set to 2.5V, and also \
+                         supplies external circuits. The SMPS stays in \
+                         high-power mode in all system low-power modes",
+        #[cfg(feature = "smps")]
+        SMPSExternalBypass1V8:
+        smps_1v8_external_ldo_bypass, "VCORE is supplied from an external \
+                         source. The LDO is bypassed. SMPS output voltage \
+                         set to 1.8V to supply external circuits",
+        #[cfg(feature = "smps")]
+        SMPSExternalBypass2V5:
+        smps_2v5_external_ldo_bypass, "VCORE is supplied from an external \
+                         source. The LDO is bypassed. SMPS output voltage \
+                         set to 2.5V to supply external circuits",
+    }
+
+    /// Use VOS2 instead of VOS1. This limits the maximum core clock
+    /// frequency, but reduces power consumption
+    pub fn vos2(mut self) -> Self {
+        self.target_vos = VoltageScale::Scale2;
+        #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
+        {
+            self.enable_vos0 = false;
+        }
+        self
+    }
+
+    /// Use VOS3 instead of VOS1. This is the lowest voltage scale
+    /// available in Run mode, and has the lowest maximum core clock
+    /// frequency
+    pub fn vos3(mut self) -> Self {
+        self.target_vos = VoltageScale::Scale3;
+        #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
+        {
+            self.enable_vos0 = false;
+        }
+        self
     }
 
     #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
     pub fn vos0(mut self, _: &SYSCFG) -> Self {
+        self.target_vos = VoltageScale::Scale1;
         self.enable_vos0 = true;
         self
     }
@@ -273,39 +456,53 @@ impl Pwr {
 
         #[cfg(not(feature = "smps"))]
         self.rb.cr3.modify(|_, w| {
-            w.scuen().set_bit().ldoen().set_bit().bypass().clear_bit()
+            use SupplyConfiguration::*;
+
+            match self.supply_configuration {
+                Default | LDOSupply => {
+                    w.scuen().set_bit().ldoen().set_bit().bypass().clear_bit()
+                }
+                Bypass => {
+                    w.scuen().set_bit().ldoen().clear_bit().bypass().set_bit()
+                }
+            }
         });
 
         #[cfg(any(feature = "smps"))]
         self.rb.cr3.modify(|_, w| {
             use SupplyConfiguration::*;
 
-            match self.supply_configuration {
-                LDOSupply => w.sden().clear_bit().ldoen().set_bit(),
-                DirectSMPS => w.sden().set_bit().ldoen().clear_bit(),
-                SMPSFeedsIntoLDO1V8 => unsafe {
-                    let reg = w.sden().set_bit().ldoen().set_bit();
-                    smps_level!(reg).bits(1)
-                },
-                SMPSFeedsIntoLDO2V5 => unsafe {
-                    let reg = w.sden().set_bit().ldoen().set_bit();
-                    smps_level!(reg).bits(2)
-                },
-                Bypass => {
-                    w.sden().clear_bit().ldoen().clear_bit().bypass().set_bit()
-                }
-                Default => {
-                    // Default configuration. The actual reset value of
-                    // CR3 varies between packages (See RM0399 Section
-                    // 7.8.4 Footnote 2). Therefore we do not modify
-                    // anything here.
-                    w
-                }
+            // (SMPS enabled, SMPS level, SMPS external, LDO enabled, Bypass)
+            let (sden, level, ext, ldoen, bypass) =
+                match self.supply_configuration {
+                    LDOSupply => (false, 0, false, true, false),
+                    DirectSMPS => (true, 0, false, false, false),
+                    SMPSFeedsIntoLDO1V8 => (true, 1, false, true, false),
+                    SMPSFeedsIntoLDO2V5 => (true, 2, false, true, false),
+                    SMPSExternalFeedsIntoLDO1V8 => (true, 1, true, true, false),
+                    SMPSExternalFeedsIntoLDO2V5 => (true, 2, true, true, false),
+                    SMPSExternalBypass1V8 => (true, 1, true, false, true),
+                    SMPSExternalBypass2V5 => (true, 2, true, false, true),
+                    Bypass => (false, 0, false, false, true),
+                    Default => {
+                        // Default configuration. The actual reset value of
+                        // CR3 varies between packages (See RM0399 Section
+                        // 7.8.4 Footnote 2). Therefore we do not modify
+                        // anything here.
+                        return w;
+                    }
+                };
+
+            let w = smps_ext_hp!(w.sden().bit(sden)).bit(ext);
+            let w = w.ldoen().bit(ldoen).bypass().bit(bypass);
+            if level != 0 {
+                unsafe { smps_level!(w).bits(level) }
+            } else {
+                w
             }
         });
         // Verify supply configuration, panics if these values read
         // from CR3 do not match those written.
-        #[cfg(any(feature = "smps"))]
         self.verify_supply_configuration();
 
         // Validate the supply configuration. If you are stuck here, it is
@@ -317,15 +514,19 @@ impl Pwr {
 
         // We have now entered Run mode. See RM0433 Rev 7 Section 6.6.1
 
-        // go to VOS1 voltage scale for high performance
-        self.voltage_scaling_transition(VoltageScale::Scale1);
+        // go to VOS1 voltage scale for high performance, unless a lower
+        // voltage scale was requested
+        self.voltage_scaling_transition(self.target_vos);
 
         #[allow(unused_mut)]
-        let mut vos = VoltageScale::Scale1;
+        let mut vos = self.target_vos;
 
         // Enable overdrive for maximum clock
         // Syscfgen required to set enable overdrive
-        #[cfg(all(feature = "revision_v", not(feature = "rm0455")))]
+        #[cfg(all(
+            feature = "revision_v",
+            any(feature = "rm0433", feature = "rm0399")
+        ))]
         if self.enable_vos0 {
             unsafe {
                 &(*RCC::ptr()).apb4enr.modify(|_, w| w.syscfgen().enabled())
@@ -342,6 +543,14 @@ impl Pwr {
             vos = VoltageScale::Scale0;
         }
 
+        // RM0468 parts select VOS0 directly in D3CR, there is no
+        // overdrive bit
+        #[cfg(feature = "rm0468")]
+        if self.enable_vos0 {
+            self.voltage_scaling_transition(VoltageScale::Scale0);
+            vos = VoltageScale::Scale0;
+        }
+
         // Disable backup power domain write protection
         self.rb.cr1.modify(|_, w| w.dbp().set_bit());
         let backup = unsafe { BackupREC::new_singleton() };
@@ -352,3 +561,183 @@ impl Pwr {
         }
     }
 }
+
+// PWR_CPUCR of the current CPU
+#[cfg(not(all(feature = "rm0399", feature = "cm4")))]
+macro_rules! cpucr {
+    ($pwr:expr) => {
+        $pwr.cpucr
+    };
+}
+#[cfg(all(feature = "rm0399", feature = "cm4"))]
+macro_rules! cpucr {
+    ($pwr:expr) => {
+        $pwr.cpu2cr
+    };
+}
+
+/// Stop mode entry error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum StopError {
+    /// No EXTI line is armed and the IWDG does not run in Stop mode, so the
+    /// CPU would not wake up
+    NoWakeupSource,
+    /// An EXTI line is pending. It must be cleared first, because it would
+    /// not wake the CPU again
+    PendingWakeup,
+}
+
+/// Returns `true` if the IWDG was started by the
+/// [`IndependentWatchdog`](crate::independent_watchdog::IndependentWatchdog),
+/// and is not frozen in Stop mode by the option bytes
+fn iwdg_runs_in_stop() -> bool {
+    // NOTE(unsafe) Atomic read with no side effects
+    let flash = unsafe { &*crate::stm32::FLASH::ptr() };
+
+    crate::independent_watchdog::is_started()
+        && flash.optsr_cur.read().fz_iwdg_stop().bit_is_set()
+}
+
+/// Enters Stop mode, and returns once the CPU has woken up
+///
+/// With `audit`, the wakeup sources are checked first:
+///
+/// * At least one configurable EXTI line must have a trigger edge and be
+///   enabled as an interrupt or event of this CPU. This includes the RTC
+///   alarm, tamper and wakeup timer lines, and the GPIO lines. Otherwise
+///   the IWDG must have been started by the
+///   [`IndependentWatchdog`](crate::independent_watchdog::IndependentWatchdog)
+///   and not be frozen in Stop mode, so that it resets the part
+/// * No configurable EXTI line may be pending
+///
+/// Lines that are connected directly to a peripheral, such as the wakeup
+/// of a USART, are not checked.
+pub fn enter_stop(scb: &mut SCB, audit: bool) -> Result<(), StopError> {
+    if audit {
+        if crate::exti::first_pending().is_some() {
+            return Err(StopError::PendingWakeup);
+        }
+        if !crate::exti::wakeup_armed() && !iwdg_runs_in_stop() {
+            return Err(StopError::NoWakeupSource);
+        }
+    }
+
+    // Keep all domains in DStop rather than Standby, let D3 enter DStop
+    // with the CPU, and clear the STOPF and SBF flags
+    // NOTE(unsafe) The CPUCR of the current CPU is only written here
+    let pwr = unsafe { &*PWR::ptr() };
+    #[cfg(not(feature = "rm0455"))]
+    cpucr!(pwr).modify(|_, w| {
+        w.pdds_d1()
+            .clear_bit()
+            .pdds_d2()
+            .clear_bit()
+            .pdds_d3()
+            .clear_bit()
+            .run_d3()
+            .clear_bit()
+            .cssf()
+            .set_bit()
+    });
+    #[cfg(feature = "rm0455")]
+    cpucr!(pwr).modify(|_, w| {
+        w.retds_cd()
+            .clear_bit()
+            .pdds_srd()
+            .clear_bit()
+            .run_srd()
+            .clear_bit()
+            .cssf()
+            .set_bit()
+    });
+
+    scb.set_sleepdeep();
+    cortex_m::asm::dsb();
+    cortex_m::asm::wfi();
+    scb.clear_sleepdeep();
+
+    Ok(())
+}
+
+/// Low-power modes that the domains were in, see [`low_power_status`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LowPowerStatus {
+    /// The system has been in Stop mode (STOPF)
+    pub stop: bool,
+    /// The system has been in Standby mode (SBF)
+    pub standby: bool,
+    /// The D1 domain has been in DStandby (SBF_D1). Always `false` on
+    /// RM0455 parts
+    pub d1_standby: bool,
+    /// The D2 domain has been in DStandby (SBF_D2). Always `false` on
+    /// RM0455 parts
+    pub d2_standby: bool,
+}
+
+/// Returns the low-power modes that the domains have been in since the
+/// flags were last cleared, by [`enter_stop`] or
+/// [`clear_low_power_status`]
+pub fn low_power_status() -> LowPowerStatus {
+    // NOTE(unsafe) Atomic read with no side effects
+    let cpucr = cpucr!(unsafe { &*PWR::ptr() }).read();
+    #[cfg(not(feature = "rm0455"))]
+    let (d1_standby, d2_standby) =
+        (cpucr.sbf_d1().bit_is_set(), cpucr.sbf_d2().bit_is_set());
+    #[cfg(feature = "rm0455")]
+    let (d1_standby, d2_standby) = (false, false);
+
+    LowPowerStatus {
+        stop: cpucr.stopf().bit_is_set(),
+        standby: cpucr.sbf().bit_is_set(),
+        d1_standby,
+        d2_standby,
+    }
+}
+
+/// Clears the flags of [`low_power_status`]
+pub fn clear_low_power_status() {
+    // NOTE(unsafe) CSSF is write only, and the other bits are written back
+    // unchanged
+    cpucr!(unsafe { &*PWR::ptr() }).modify(|_, w| w.cssf().set_bit());
+}
+
+/// Source that woke the CPU, see [`wakeup_reason`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum WakeupReason {
+    /// A wakeup pin, numbered from 1 as WKUP1 to WKUP6
+    WakeupPin(u8),
+    /// An RTC alarm, tamper or wakeup timer event, EXTI lines 17 to 19
+    Rtc,
+    /// Another configurable EXTI line, such as a GPIO line
+    Exti(u8),
+    /// No flag of a wakeup source is set. It may have been cleared
+    /// already, or the source is a line connected directly to a peripheral
+    Unknown,
+}
+
+/// Returns the source that woke the CPU, from the wakeup pin flags of the
+/// PWR and the pending flags of the EXTI. Sources that are still pending
+/// are reported even without a low-power mode
+pub fn wakeup_reason() -> WakeupReason {
+    // NOTE(unsafe) Atomic read with no side effects
+    let wkupfr = unsafe { &*PWR::ptr() }.wkupfr.read();
+    let pins = [
+        wkupfr.wkupf1().bit_is_set(),
+        wkupfr.wkupf2().bit_is_set(),
+        wkupfr.wkupf3().bit_is_set(),
+        wkupfr.wkupf4().bit_is_set(),
+        wkupfr.wkupf5().bit_is_set(),
+        wkupfr.wkupf6().bit_is_set(),
+    ];
+    if let Some(pin) = pins.iter().position(|&flag| flag) {
+        return WakeupReason::WakeupPin(pin as u8 + 1);
+    }
+
+    match crate::exti::first_pending() {
+        Some(17..=19) => WakeupReason::Rtc,
+        Some(line) => WakeupReason::Exti(line),
+        None => WakeupReason::Unknown,
+    }
+}
@@ -1,4 +1,44 @@
 //! # Quadrature Encoder Interface
+//!
+//! The advanced control timers (`TIM1`, `TIM8`) and the general purpose
+//! timers `TIM[2-5]` can count the edges of a quadrature encoder connected to
+//! channels 1 and 2. The count and direction are read through the
+//! [`Qei`](crate::hal::Qei) trait.
+//!
+//! ```
+//! use stm32h7xx_hal::qei::{Mode, QeiConfig};
+//!
+//! let pins = (gpiob.pb6.into_alternate_af2(), gpiob.pb7.into_alternate_af2());
+//!
+//! // Count every edge of both inputs, ignoring glitches
+//! let config = QeiConfig::default().mode(Mode::BothInputs).filter(0b0100);
+//! let qei = dp.TIM4.qei_with_config(pins, ccdr.peripheral.TIM4, config);
+//!
+//! let count = qei.count();
+//! let direction = qei.direction();
+//! ```
+//!
+//! # Index pulse
+//!
+//! An encoder with an index (Z) pulse gives an absolute position after the
+//! first pulse. An [`IndexedQei`] latches the count at each index pulse,
+//! and counts revolutions in software, so that the position does not wrap
+//! with the timer. The index pulse is either connected to channel 3 of the
+//! timer, which latches the count in hardware, or to any pin with an EXTI
+//! interrupt, which calls [`index_edge`](IndexedQei::index_edge).
+//!
+//! ```
+//! // 1024 lines, counted with x4 resolution. The index on channel 3
+//! let _z = gpiob.pb8.into_alternate_af2();
+//! let mut encoder = qei.with_index(4096, IndexSource::Channel3, Index::Every);
+//!
+//! // At least once per half a turn of the 16-bit counter
+//! let position = encoder.position();
+//! if position.indexed {
+//!     let turns = position.revolutions();
+//!     let angle = position.angle();
+//! }
+//! ```
 use crate::hal::{self, Direction};
 use crate::rcc::{rec, ResetEnable};
 
@@ -150,11 +190,177 @@ pins! {
 
 }
 
+/// Edges counted by the encoder interface
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    /// Count the edges of input 1 only (x2 resolution)
+    Input1 = 0b001,
+    /// Count the edges of input 2 only (x2 resolution)
+    Input2 = 0b010,
+    /// Count the edges of both inputs (x4 resolution)
+    BothInputs = 0b011,
+}
+
+/// Configuration of the quadrature encoder interface
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QeiConfig {
+    mode: Mode,
+    filter: u8,
+    invert: bool,
+}
+
+impl Default for QeiConfig {
+    fn default() -> Self {
+        QeiConfig {
+            mode: Mode::BothInputs,
+            filter: 0,
+            invert: false,
+        }
+    }
+}
+
+impl QeiConfig {
+    /// Edges that are counted. Default `Mode::BothInputs`
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Digital input filter on both inputs, from 0 (no filter) to
+    /// 0b1111. See the reference manual for the sampling frequency and the
+    /// number of samples. Default 0
+    pub fn filter(mut self, filter: u8) -> Self {
+        assert!(filter <= 0b1111);
+        self.filter = filter;
+        self
+    }
+
+    /// Inverts input 1, which reverses the counting direction. Default
+    /// `false`
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+}
+
 /// Hardware quadrature encoder interface peripheral
 pub struct Qei<TIM> {
     tim: TIM,
 }
 
+/// Input of the index pulse
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IndexSource {
+    /// Channel 3 of the timer. The count is captured in hardware on the
+    /// rising edge of the index pulse. The pin is not checked, and must be
+    /// in the alternate function of channel 3
+    Channel3,
+    /// An EXTI interrupt. The count is read by
+    /// [`index_edge`](IndexedQei::index_edge) from the interrupt handler
+    Exti,
+}
+
+/// Index pulses that set the position to zero
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Index {
+    /// Only the first index pulse. Later pulses are ignored
+    First,
+    /// Every index pulse. The position at the pulse is moved to the
+    /// nearest whole revolution, which corrects counts that were lost and
+    /// keeps the revolutions counted
+    Every,
+}
+
+/// Position of an encoder with an index pulse
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Position {
+    /// Counts since the index pulse that set the position to zero, or
+    /// since counting started if there was none. Negative before the index
+    pub counts: i64,
+    /// `true` once an index pulse has set the position to zero
+    pub indexed: bool,
+    counts_per_revolution: u32,
+}
+
+impl Position {
+    /// Returns the number of whole revolutions
+    pub fn revolutions(&self) -> i64 {
+        self.counts
+            .div_euclid(i64::from(self.counts_per_revolution))
+    }
+
+    /// Returns the counts within the current revolution, from 0 to
+    /// counts per revolution - 1
+    pub fn angle(&self) -> u32 {
+        self.counts
+            .rem_euclid(i64::from(self.counts_per_revolution)) as u32
+    }
+}
+
+/// Quadrature encoder interface with an index pulse, that counts
+/// revolutions
+pub struct IndexedQei<TIM> {
+    qei: Qei<TIM>,
+    source: IndexSource,
+    /// Raw count at an index pulse from the EXTI interrupt
+    edge: Option<u32>,
+    tracker: Tracker,
+}
+
+/// Tracks the position from the raw counts of a counter
+struct Tracker {
+    index: Index,
+    counts_per_revolution: u32,
+    /// Maximum value of the counter
+    max: u32,
+    /// Raw count at the last update
+    last: u32,
+    counts: i64,
+    indexed: bool,
+}
+
+impl Tracker {
+    /// Updates the position to the raw count `count`, with the raw count
+    /// `at_index` latched at an index pulse since the last update
+    fn update(&mut self, at_index: Option<u32>, count: u32) {
+        match at_index {
+            Some(at_index) if !self.indexed => {
+                self.counts = wrapping_delta(count, at_index, self.max);
+                self.indexed = true;
+            }
+            Some(at_index) if self.index == Index::Every => {
+                let cpr = i64::from(self.counts_per_revolution);
+                let at =
+                    self.counts + wrapping_delta(at_index, self.last, self.max);
+                let revolution = (at + cpr / 2).div_euclid(cpr) * cpr;
+                self.counts =
+                    revolution + wrapping_delta(count, at_index, self.max);
+            }
+            _ => self.counts += wrapping_delta(count, self.last, self.max),
+        }
+        self.last = count;
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            counts: self.counts,
+            indexed: self.indexed,
+            counts_per_revolution: self.counts_per_revolution,
+        }
+    }
+}
+
+/// Returns the signed difference between two counts of a counter with the
+/// maximum value `max`
+fn wrapping_delta(count: u32, last: u32, max: u32) -> i64 {
+    let delta = count.wrapping_sub(last) & max;
+    if delta > max / 2 {
+        i64::from(delta) - i64::from(max) - 1
+    } else {
+        i64::from(delta)
+    }
+}
+
 pub trait QeiExt<TIM> {
     type Rec: ResetEnable;
 
@@ -163,6 +369,15 @@ pub trait QeiExt<TIM> {
         PINS: Pins<TIM>;
 
     fn qei_unchecked(self, prec: Self::Rec) -> Qei<TIM>;
+
+    fn qei_with_config<PINS>(
+        self,
+        _pins: PINS,
+        prec: Self::Rec,
+        config: QeiConfig,
+    ) -> Qei<TIM>
+    where
+        PINS: Pins<TIM>;
 }
 
 macro_rules! tim_hal {
@@ -173,23 +388,40 @@ macro_rules! tim_hal {
                 /// encoder interface input
                 pub fn $tim(tim: $TIM, prec: rec::$Rec) -> Self
                 {
+                    Self::with_config(tim, prec, QeiConfig::default())
+                }
+
+                /// Configures a TIM peripheral as a quadrature
+                /// encoder interface input, with the given configuration
+                pub fn with_config(
+                    tim: $TIM,
+                    prec: rec::$Rec,
+                    config: QeiConfig,
+                ) -> Self {
                     // enable and reset peripheral to a clean slate
                     prec.enable().reset();
 
-                    // Configure TxC1 and TxC2 as captures
+                    // Configure TxC1 and TxC2 as captures, with filter
                     tim.ccmr1_output().write(|w| unsafe {
                         w.cc1s()
                             .bits(0b01)
                             .cc2s()
                             .bits(0b01)
                     });
+                    tim.ccmr1_input().modify(|_, w| unsafe {
+                        w.ic1f()
+                            .bits(config.filter)
+                            .ic2f()
+                            .bits(config.filter)
+                    });
 
-                    // enable and configure to capture on rising edge
+                    // enable and configure to capture on rising edge, or
+                    // falling edge of TI1 if inverted
                     tim.ccer.write(|w| {
                         w.cc1e()
                             .set_bit()
                             .cc1p()
-                            .clear_bit()
+                            .bit(config.invert)
                             .cc2e()
                             .set_bit()
                             .cc2p()
@@ -197,7 +429,7 @@ macro_rules! tim_hal {
                     });
 
                     // configure as quadrature encoder
-                    tim.smcr.write(|w| { w.sms().bits(3) });
+                    tim.smcr.write(|w| { w.sms().bits(config.mode as u8) });
 
                     tim.arr.write(|w| unsafe { w.bits(core::u32::MAX) });
                     tim.cr1.write(|w| w.cen().set_bit());
@@ -205,10 +437,123 @@ macro_rules! tim_hal {
                     Qei { tim }
                 }
 
+                /// Resets the count to zero
+                pub fn reset_count(&mut self) {
+                    self.tim.cnt.reset();
+                }
+
                 /// Releases the TIM peripheral
                 pub fn release(self) -> $TIM {
                     self.tim
                 }
+
+                /// Adds an index pulse from `source`, with
+                /// `counts_per_revolution` counts between index pulses
+                ///
+                /// # Panics
+                ///
+                /// Panics if `counts_per_revolution` is zero
+                pub fn with_index(
+                    self,
+                    counts_per_revolution: u32,
+                    source: IndexSource,
+                    index: Index,
+                ) -> IndexedQei<$TIM> {
+                    assert!(counts_per_revolution > 0);
+
+                    if source == IndexSource::Channel3 {
+                        // Capture on the rising edge of TI3
+                        self.tim.ccmr2_input().modify(|_, w| unsafe {
+                            w.cc3s().bits(0b01)
+                        });
+                        self.tim.ccer.modify(|_, w| {
+                            w.cc3e()
+                                .set_bit()
+                                .cc3p()
+                                .clear_bit()
+                                .cc3np()
+                                .clear_bit()
+                        });
+                        self.tim.sr.modify(|_, w| w.cc3if().clear_bit());
+                    }
+
+                    IndexedQei {
+                        tracker: Tracker {
+                            index,
+                            counts_per_revolution,
+                            max: $bits::MAX as u32,
+                            last: self.tim.cnt.read().bits(),
+                            counts: 0,
+                            indexed: false,
+                        },
+                        qei: self,
+                        source,
+                        edge: None,
+                    }
+                }
+            }
+
+            impl IndexedQei<$TIM> {
+                /// Returns the current position
+                ///
+                /// This must be called at least once while the counter
+                /// counts through half of its range, so that each wrap of
+                /// the counter is seen.
+                pub fn position(&mut self) -> Position {
+                    let tim = &self.qei.tim;
+
+                    // The count at the index is read before the current
+                    // count, so that it is not later
+                    let index = match self.source {
+                        IndexSource::Channel3 => {
+                            if tim.sr.read().cc3if().bit_is_set() {
+                                // Reading CCR3 clears CC3IF
+                                Some(tim.ccr3.read().bits())
+                            } else {
+                                None
+                            }
+                        }
+                        IndexSource::Exti => self.edge.take(),
+                    };
+                    let count = tim.cnt.read().bits();
+
+                    self.tracker.update(index, count);
+                    self.tracker.position()
+                }
+
+                /// Latches the count at an index pulse. Call this from the
+                /// EXTI interrupt of the index pin, with
+                /// [`IndexSource::Exti`]
+                pub fn index_edge(&mut self) {
+                    self.edge = Some(self.qei.tim.cnt.read().bits());
+                }
+
+                /// Interrupts on an index pulse on channel 3
+                pub fn listen_index(&mut self) {
+                    self.qei.tim.dier.modify(|_, w| w.cc3ie().set_bit());
+                }
+
+                /// Stops interrupting on an index pulse on channel 3
+                pub fn unlisten_index(&mut self) {
+                    self.qei.tim.dier.modify(|_, w| w.cc3ie().clear_bit());
+                }
+
+                /// Forgets the index pulses seen, so that the next index
+                /// pulse sets the position to zero again
+                pub fn rehome(&mut self) {
+                    self.tracker.indexed = false;
+                }
+
+                /// Stops capturing on channel 3, and releases the encoder
+                /// interface
+                pub fn free(self) -> Qei<$TIM> {
+                    if self.source == IndexSource::Channel3 {
+                        let tim = &self.qei.tim;
+                        tim.dier.modify(|_, w| w.cc3ie().clear_bit());
+                        tim.ccer.modify(|_, w| w.cc3e().clear_bit());
+                    }
+                    self.qei
+                }
             }
 
             impl QeiExt<$TIM> for $TIM {
@@ -221,6 +566,15 @@ macro_rules! tim_hal {
                 fn qei_unchecked(self, prec: Self::Rec) -> Qei<$TIM> {
                     Qei::$tim(self, prec)
                 }
+
+                fn qei_with_config<PINS>(
+                    self,
+                    _pins: PINS,
+                    prec: Self::Rec,
+                    config: QeiConfig,
+                ) -> Qei<$TIM> {
+                    Qei::with_config(self, prec, config)
+                }
             }
 
             impl hal::Qei for Qei<$TIM> {
@@ -251,3 +605,58 @@ tim_hal! {
     TIM4: (tim4, Tim4, u16),
     TIM5: (tim5, Tim5, u32),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Index, Tracker};
+
+    fn tracker(index: Index) -> Tracker {
+        Tracker {
+            index,
+            counts_per_revolution: 100,
+            max: u16::MAX as u32,
+            last: 0,
+            counts: 0,
+            indexed: false,
+        }
+    }
+
+    #[test]
+    /// Test that every index pulse keeps the revolutions counted, and
+    /// corrects lost counts
+    fn index_every() {
+        let mut t = tracker(Index::Every);
+
+        // First index at 10 sets the position to zero
+        t.update(Some(10), 15);
+        assert_eq!(t.position().counts, 5);
+        assert!(t.position().indexed);
+
+        // Index pulses one turn later, with 2 counts lost, and two turns
+        // later, with 3 extra counts
+        t.update(Some(108), 120);
+        assert_eq!(t.position().counts, 112);
+        t.update(Some(211), 215);
+        assert_eq!(t.position().counts, 204);
+        assert_eq!(t.position().revolutions(), 2);
+        assert_eq!(t.position().angle(), 4);
+
+        // Through the wrap of the counter, turning backwards
+        let mut t = tracker(Index::Every);
+        t.update(Some(50), 50);
+        t.update(None, 65_500);
+        assert_eq!(t.position().counts, -86);
+        t.update(Some(65_486), 65_480);
+        assert_eq!(t.position().counts, -106);
+        assert_eq!(t.position().revolutions(), -2);
+    }
+
+    #[test]
+    /// Test that later index pulses are ignored with `Index::First`
+    fn index_first() {
+        let mut t = tracker(Index::First);
+        t.update(Some(10), 15);
+        t.update(Some(108), 120);
+        assert_eq!(t.position().counts, 110);
+    }
+}
@@ -27,6 +27,7 @@
 pub struct BackupREC {
     #[cfg(feature = "rtc")]
     pub RTC: Rtc,
+    pub BKPRAM: BkpRam,
 }
 
 impl BackupREC {
@@ -42,6 +43,54 @@ impl BackupREC {
             RTC: Rtc {
                 _marker: core::marker::PhantomData,
             },
+            BKPRAM: BkpRam {
+                _marker: core::marker::PhantomData,
+            },
+        }
+    }
+}
+
+pub use bkpram::BkpRam;
+
+mod bkpram {
+    use crate::stm32::{PWR, RCC};
+    use core::marker::PhantomData;
+    use cortex_m::interrupt;
+
+    /// Enable and retention control for the backup SRAM. See
+    /// [`BackupSram`](crate::backup_sram::BackupSram)
+    pub struct BkpRam {
+        pub(super) _marker: PhantomData<*const ()>,
+    }
+
+    unsafe impl Send for BkpRam {}
+
+    impl BkpRam {
+        /// Enables the backup regulator and the backup SRAM clock. The
+        /// backup SRAM then retains its contents in Standby and VBAT mode
+        pub(crate) fn enable(&mut self) {
+            // unsafe: Owned exclusive access to these bitfields
+            interrupt::free(|_| {
+                let pwr = unsafe { &*PWR::ptr() };
+                pwr.cr2.modify(|_, w| w.bren().set_bit());
+                while pwr.cr2.read().brrdy().bit_is_clear() {}
+
+                let rcc = unsafe { &*RCC::ptr() };
+                rcc.ahb4enr.modify(|_, w| w.bkpramen().set_bit());
+            });
+        }
+
+        /// Disables the backup SRAM clock and the backup regulator. The
+        /// contents of the backup SRAM are lost in Standby and VBAT mode
+        pub(crate) fn disable(&mut self) {
+            // unsafe: Owned exclusive access to these bitfields
+            interrupt::free(|_| {
+                let rcc = unsafe { &*RCC::ptr() };
+                rcc.ahb4enr.modify(|_, w| w.bkpramen().clear_bit());
+
+                let pwr = unsafe { &*PWR::ptr() };
+                pwr.cr2.modify(|_, w| w.bren().clear_bit());
+            });
         }
     }
 }
@@ -0,0 +1,180 @@
+//! Check of the kernel clocks against their maximum frequencies
+//!
+//! The kernel clocks of the peripherals are selected independently of the
+//! core clocks, so a PLL output that is valid for one peripheral can be
+//! too fast for another that shares it. Such an overclock often works on
+//! the bench, and shows up as flaky hardware in the field.
+//!
+//! [`check_kernel_clocks`](super::Ccdr::check_kernel_clocks) compares the
+//! kernel clocks that are currently selected with their maximum at the
+//! voltage scale the core runs at. Call it after the kernel clock
+//! multiplexers have been configured, for example once all peripherals are
+//! constructed.
+//!
+//! ```
+//! for overclock in ccdr.check_kernel_clocks() {
+//!     warn!(
+//!         "{} is {}Hz, faster than {}Hz",
+//!         overclock.clock, overclock.freq.0, overclock.max.0
+//!     );
+//! }
+//! ```
+//!
+//! The maxima are conservative values for rev V parts, and the datasheet of
+//! the part remains the reference. Kernel clocks without a known frequency,
+//! such as external clocks, are not checked.
+//!
+//! The checked clocks are those of the SPI, SDMMC, FMC, QUADSPI or OCTOSPI,
+//! ADC, U(S)ART, LPUART, I2C, SAI, FDCAN, USB and LTDC peripherals.
+
+use super::Ccdr;
+use crate::found::Found;
+use crate::pwr::VoltageScale as Voltage;
+use crate::stm32::RCC;
+use crate::time::Hertz;
+
+/// A kernel clock that is faster than its maximum
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Overclock {
+    /// Name of the kernel clock multiplexer, as in
+    /// [`KernelClockSnapshot`](super::rec::KernelClockSnapshot), or `ltdc`
+    pub clock: &'static str,
+    /// Frequency of the kernel clock
+    pub freq: Hertz,
+    /// Maximum frequency at the current voltage scale
+    pub max: Hertz,
+}
+
+/// Maximum number of kernel clocks that are checked
+const CHECKS: usize = 20;
+
+/// Kernel clocks that are faster than their maximum, returned by
+/// [`check_kernel_clocks`](super::Ccdr::check_kernel_clocks)
+#[derive(Clone, Debug)]
+pub struct Overclocks(Found<Overclock, [Option<Overclock>; CHECKS]>);
+
+impl Iterator for Overclocks {
+    type Item = Overclock;
+
+    fn next(&mut self) -> Option<Overclock> {
+        self.0.next()
+    }
+}
+
+/// Kernel clock maxima in MHz at VOS0, VOS1, VOS2 and VOS3
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+mod max {
+    pub const SPI: [u32; 4] = [200, 200, 150, 100];
+    pub const SDMMC: [u32; 4] = [200, 200, 150, 100];
+    pub const FMC: [u32; 4] = [240, 200, 150, 100];
+    #[cfg(not(feature = "revision_v"))]
+    pub const ADC: [u32; 4] = [36, 36, 36, 36];
+    #[cfg(feature = "revision_v")]
+    pub const ADC: [u32; 4] = [100, 100, 80, 50];
+    pub const USART: [u32; 4] = [100, 100, 100, 50];
+    pub const I2C: [u32; 4] = [100, 100, 100, 50];
+    pub const SAI: [u32; 4] = [150, 150, 150, 100];
+    pub const FDCAN: [u32; 4] = [125, 125, 125, 100];
+    pub const LTDC: [u32; 4] = [150, 150, 100, 50];
+}
+#[cfg(feature = "rm0468")]
+mod max {
+    pub const SPI: [u32; 4] = [200, 200, 150, 85];
+    pub const SDMMC: [u32; 4] = [200, 200, 150, 85];
+    pub const FMC: [u32; 4] = [275, 200, 150, 85];
+    pub const ADC: [u32; 4] = [100, 100, 80, 40];
+    pub const USART: [u32; 4] = [100, 100, 100, 50];
+    pub const I2C: [u32; 4] = [100, 100, 100, 50];
+    pub const SAI: [u32; 4] = [150, 150, 150, 85];
+    pub const FDCAN: [u32; 4] = [125, 125, 125, 85];
+    pub const LTDC: [u32; 4] = [150, 150, 100, 50];
+}
+#[cfg(feature = "rm0455")]
+mod max {
+    pub const SPI: [u32; 4] = [200, 200, 150, 88];
+    pub const SDMMC: [u32; 4] = [200, 200, 150, 88];
+    pub const FMC: [u32; 4] = [280, 225, 160, 88];
+    pub const ADC: [u32; 4] = [100, 100, 80, 44];
+    pub const USART: [u32; 4] = [100, 100, 100, 50];
+    pub const I2C: [u32; 4] = [100, 100, 100, 50];
+    pub const SAI: [u32; 4] = [150, 150, 150, 88];
+    pub const FDCAN: [u32; 4] = [125, 125, 125, 88];
+    pub const LTDC: [u32; 4] = [150, 150, 100, 50];
+}
+/// The USB kernel clock must be 48MHz at every voltage scale. Only a
+/// faster clock is reported
+const USB: [u32; 4] = [48; 4];
+
+impl Ccdr {
+    /// Returns the kernel clocks that are faster than their maximum at the
+    /// current voltage scale
+    ///
+    /// The kernel clock multiplexers are read directly, so selections made
+    /// outside of this crate are included.
+    pub fn check_kernel_clocks(&self) -> Overclocks {
+        let snapshot = self.peripheral.kernel_clock_snapshot(&self.clocks);
+        let vos = match self.vos {
+            Voltage::Scale0 => 0,
+            Voltage::Scale1 => 1,
+            Voltage::Scale2 => 2,
+            Voltage::Scale3 => 3,
+        };
+        let mut overclocks = Found::new([None; CHECKS]);
+
+        let mut check =
+            |clock: &'static str, freq: Option<Hertz>, max: [u32; 4]| {
+                let max = Hertz(max[vos] * 1_000_000);
+                match freq {
+                    Some(freq) if freq.0 > max.0 => {
+                        overclocks.push(Overclock { clock, freq, max })
+                    }
+                    _ => {}
+                }
+            };
+        check("spi123", snapshot.spi123.freq, max::SPI);
+        check("spi45", snapshot.spi45.freq, max::SPI);
+        check("spi6", snapshot.spi6.freq, max::SPI);
+        check("sdmmc", snapshot.sdmmc.freq, max::SDMMC);
+        check("fmc", snapshot.fmc.freq, max::FMC);
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        check("qspi", snapshot.qspi.freq, max::FMC);
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        check("octospi", snapshot.octospi.freq, max::FMC);
+        check("adc", snapshot.adc.freq, max::ADC);
+        check("usart234578", snapshot.usart234578.freq, max::USART);
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        check("usart16", snapshot.usart16.freq, max::USART);
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        check("usart16910", snapshot.usart16910.freq, max::USART);
+        check("lpuart1", snapshot.lpuart1.freq, max::USART);
+        #[cfg(not(feature = "rm0468"))]
+        check("i2c123", snapshot.i2c123.freq, max::I2C);
+        #[cfg(feature = "rm0468")]
+        check("i2c1235", snapshot.i2c1235.freq, max::I2C);
+        check("i2c4", snapshot.i2c4.freq, max::I2C);
+        check("sai1", snapshot.sai1.freq, max::SAI);
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        check("sai23", snapshot.sai23.freq, max::SAI);
+        #[cfg(feature = "rm0455")]
+        check("sai2a", snapshot.sai2a.freq, max::SAI);
+        #[cfg(feature = "rm0455")]
+        check("sai2b", snapshot.sai2b.freq, max::SAI);
+        #[cfg(not(feature = "rm0455"))]
+        check("sai4a", snapshot.sai4a.freq, max::SAI);
+        #[cfg(not(feature = "rm0455"))]
+        check("sai4b", snapshot.sai4b.freq, max::SAI);
+        check("fdcan", snapshot.fdcan.freq, max::FDCAN);
+        check("usb", snapshot.usb.freq, USB);
+
+        // The LTDC has no multiplexer, and runs from pll3_r_ck. That is
+        // only checked if the LTDC is enabled, as pll3_r_ck may be used by
+        // other peripherals
+        // unsafe: We only read from this register
+        let rcc = unsafe { &*RCC::ptr() };
+        if rcc.apb3enr.read().ltdcen().bit_is_set() {
+            check("ltdc", self.clocks.pll3_r_ck(), max::LTDC);
+        }
+
+        Overclocks(overclocks)
+    }
+}
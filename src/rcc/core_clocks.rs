@@ -1,5 +1,6 @@
 //! Structure to represent frozen core clock frequencies
 
+use super::TimerPrescaler;
 use crate::time::Hertz;
 
 /// Frozen core clock frequencies
@@ -25,6 +26,8 @@ pub struct CoreClocks {
     pub(super) hse_ck: Option<Hertz>,
     pub(super) mco1_ck: Option<Hertz>,
     pub(super) mco2_ck: Option<Hertz>,
+    pub(super) mco1_pre: u8,
+    pub(super) mco2_pre: u8,
     pub(super) pll1_p_ck: Option<Hertz>,
     pub(super) pll1_q_ck: Option<Hertz>,
     pub(super) pll1_r_ck: Option<Hertz>,
@@ -38,6 +41,8 @@ pub struct CoreClocks {
     pub(super) timy_ker_ck: Hertz,
     pub(super) sys_ck: Hertz,
     pub(super) c_ck: Hertz,
+    pub(super) timpre: TimerPrescaler,
+    pub(super) hsi48_crs: bool,
 }
 
 /// Getters for pclk and ppre
@@ -110,6 +115,13 @@ impl CoreClocks {
         lsi_ck: "lsi_ck",
     }
 
+    /// Returns `true` if `hsi48_ck` is trimmed by the clock recovery
+    /// system (CRS), see
+    /// [`Rcc::hsi48_crs_trimmed`](super::Rcc::hsi48_crs_trimmed)
+    pub fn hsi48_crs_trimmed(&self) -> bool {
+        self.hsi48_crs
+    }
+
     /// Returns `Some(frequency)` if the MCO1 output is running, otherwise
     /// `None`
     pub fn mco1_ck(&self) -> Option<Hertz> {
@@ -154,8 +166,23 @@ impl CoreClocks {
         self.timy_ker_ck
     }
 
+    /// Returns the timer kernel clock multiplier
+    pub fn timer_prescaler(&self) -> TimerPrescaler {
+        self.timpre
+    }
+
     /// Returns the core frequency
     pub fn c_ck(&self) -> Hertz {
         self.c_ck
     }
+
+    /// Replaces the nominal LSI frequency with a measured value, for
+    /// example from
+    /// [`measure_lsi`](crate::timer::Timer#method.measure_lsi).
+    /// Peripherals that are later configured with the returned clocks use
+    /// this frequency when the LSI is their kernel clock
+    pub fn with_lsi_ck(mut self, lsi: Hertz) -> Self {
+        self.lsi_ck = Some(lsi);
+        self
+    }
 }
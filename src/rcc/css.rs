@@ -0,0 +1,298 @@
+//! Clock Security System (CSS)
+//!
+//! When the CSS is enabled on the HSE, a failure of the external
+//! oscillator switches it off and generates a non-maskable interrupt
+//! (NMI). If the HSE was used directly or through PLL1 for `sys_ck`, the
+//! hardware has already switched `sys_ck` to the HSI. The PLLs and `per_ck`
+//! are left without a clock until they are moved to the HSI by
+//! [`ClockSecurity::handle_hse_failure`].
+//!
+//! ```
+//! static CSS: Mutex<RefCell<Option<ClockSecurity>>> =
+//!     Mutex::new(RefCell::new(None));
+//!
+//! let ccdr = rcc
+//!     .use_hse(8.mhz())
+//!     .hse_css()
+//!     .sys_ck(400.mhz())
+//!     .freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! cortex_m::interrupt::free(|cs| {
+//!     CSS.borrow(cs).replace(Some(ccdr.clock_security));
+//! });
+//!
+//! #[exception]
+//! fn NMI() {
+//!     cortex_m::interrupt::free(|cs| {
+//!         match CSS.borrow(cs).borrow_mut().as_mut() {
+//!             Some(css) => {
+//!                 css.handle_hse_failure();
+//!             }
+//!             // The HSE failed before the handle was stored
+//!             None => SCB::sys_reset(),
+//!         }
+//!     });
+//! }
+//! ```
+//!
+//! The NMI remains pending until the failure is handled. Later, the
+//! application can try to bring the external oscillator back with
+//! [`ClockSecurity::restart_hse`].
+//!
+//! The CSS on the LSE is enabled when the RTC is configured with
+//! `RtcClock::Lse { css: true, .. }`, and a failure is handled by
+//! `Rtc::handle_lse_css`. It is reported by [`ClockSecurity::failure`]
+//! too.
+//!
+//! Kernel clocks that select the HSE directly, such as FDCAN, are not
+//! changed.
+
+use super::{CKPERSEL, HSI, PLLSRC, SW};
+use crate::stm32::{rcc, RCC};
+
+/// An oscillator that has failed
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ClockFailure {
+    /// High Speed External oscillator
+    Hse,
+    /// Low Speed External oscillator
+    Lse,
+}
+
+/// Clock source after an HSE failure
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HseFallback {
+    /// All running PLLs were restarted from the HSI with the same
+    /// reference frequency, so their outputs are unchanged
+    Hsi,
+    /// At least one PLL could not be restarted at the same frequency
+    /// from the HSI, and is stopped
+    PllStopped,
+}
+
+/// Error restarting the HSE
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ClockSecurityError {
+    /// The HSE was not configured by `freeze`
+    NoHse,
+    /// The HSE did not become ready before the timeout
+    Timeout,
+}
+
+/// Handling of clock failures detected by the CSS
+pub struct ClockSecurity {
+    /// HSE frequency configured by `freeze`
+    hse: Option<u32>,
+    /// `sys_ck` source selected by `freeze`
+    sw: u8,
+    /// Reference dividers for the HSE
+    divm: [u8; 3],
+    /// PLLs running after `freeze`
+    pll_on: [bool; 3],
+    /// `per_ck` was from the HSE
+    per_ck_hse: bool,
+}
+
+impl ClockSecurity {
+    /// Records the configuration made by `freeze`
+    ///
+    /// # Safety
+    ///
+    /// Must only be called once, after the clocks are configured
+    pub(super) unsafe fn new_singleton(
+        rcc: &RCC,
+        hse: Option<u32>,
+        sw: u8,
+        per_ck_hse: bool,
+    ) -> Self {
+        let pllckselr = rcc.pllckselr.read();
+        let cr = rcc.cr.read();
+
+        ClockSecurity {
+            hse,
+            sw,
+            divm: [
+                pllckselr.divm1().bits(),
+                pllckselr.divm2().bits(),
+                pllckselr.divm3().bits(),
+            ],
+            pll_on: [
+                cr.pll1on().is_on(),
+                cr.pll2on().is_on(),
+                cr.pll3on().is_on(),
+            ],
+            per_ck_hse,
+        }
+    }
+
+    /// Returns the oscillator that has failed, if any. An HSE failure is
+    /// reported first
+    pub fn failure(&self) -> Option<ClockFailure> {
+        // NOTE(unsafe) Atomic read with no side effects
+        let cifr = unsafe { (*RCC::ptr()).cifr.read() };
+        if cifr.hsecssf().bit_is_set() {
+            Some(ClockFailure::Hse)
+        } else if cifr.lsecssf().bit_is_set() {
+            Some(ClockFailure::Lse)
+        } else {
+            None
+        }
+    }
+
+    /// Handles an HSE failure, and clears the pending NMI. Returns `None`
+    /// if the HSE has not failed
+    ///
+    /// The PLLs are restarted from the HSI if their reference frequency
+    /// can be kept, and `sys_ck` returns to PLL1 if it was selected by
+    /// `freeze`. `per_ck` is moved to the HSI.
+    ///
+    /// If `sys_ck` was the HSE, it remains on the HSI at a different
+    /// frequency than in `ccdr.clocks`.
+    pub fn handle_hse_failure(&mut self) -> Option<HseFallback> {
+        if self.failure() != Some(ClockFailure::Hse) {
+            return None;
+        }
+        // NOTE(unsafe) The failed HSE, and the PLL and kernel clock
+        // configurations made by `freeze` belong to this handle
+        let rcc = unsafe { &*RCC::ptr() };
+
+        rcc.cicr.write(|w| w.hsecssc().clear());
+
+        #[cfg(not(feature = "rm0455"))]
+        rcc.d1ccipr.modify(|r, w| {
+            if r.ckpersel().is_hse() {
+                w.ckpersel().variant(CKPERSEL::HSI)
+            } else {
+                w
+            }
+        });
+        #[cfg(feature = "rm0455")]
+        rcc.cdccipr.modify(|r, w| {
+            if r.ckpersel().is_hse() {
+                w.ckpersel().variant(CKPERSEL::HSI)
+            } else {
+                w
+            }
+        });
+
+        // An HSE failure is only detected if the HSE was configured
+        let hse = self.hse.unwrap();
+
+        // Keep ref_ck = HSE / DIVM = HSI / DIVM'
+        let mut divm = [0; 3];
+        let mut pll_on = self.pll_on;
+        let mut fallback = HseFallback::Hsi;
+        for i in 0..3 {
+            let m = u32::from(self.divm[i]);
+            let divm_hsi = HSI * m / hse;
+            if pll_on[i]
+                && (HSI * m % hse != 0 || !(1..=63).contains(&divm_hsi))
+            {
+                pll_on[i] = false;
+                fallback = HseFallback::PllStopped;
+            }
+            divm[i] = divm_hsi as u8;
+        }
+
+        restart_plls(rcc, PLLSRC::HSI, divm, pll_on);
+
+        if self.sw == SW::PLL1 as u8 && pll_on[0] {
+            switch_sw(rcc, self.sw);
+        }
+
+        Some(fallback)
+    }
+
+    /// Tries to restart the HSE, waiting for at most `timeout` polls of
+    /// its ready flag
+    ///
+    /// If the HSE becomes ready, the PLLs, `per_ck` and `sys_ck` return to
+    /// the configuration made by `freeze`, and the CSS is armed
+    /// again. Otherwise the HSE is switched off again.
+    pub fn restart_hse(
+        &mut self,
+        timeout: u32,
+    ) -> Result<(), ClockSecurityError> {
+        if self.hse.is_none() {
+            return Err(ClockSecurityError::NoHse);
+        }
+        // NOTE(unsafe) As above
+        let rcc = unsafe { &*RCC::ptr() };
+
+        // HSEBYP is kept by hardware after a failure
+        rcc.cr.modify(|_, w| w.hseon().on());
+        let mut polls = 0;
+        while rcc.cr.read().hserdy().is_not_ready() {
+            polls += 1;
+            if polls >= timeout {
+                rcc.cr.modify(|_, w| w.hseon().off());
+                return Err(ClockSecurityError::Timeout);
+            }
+        }
+        rcc.cr.modify(|_, w| w.hsecsson().set_bit());
+
+        // The PLLs can only be reconfigured while not used by sys_ck
+        switch_sw(rcc, SW::HSI as u8);
+        restart_plls(rcc, PLLSRC::HSE, self.divm, self.pll_on);
+
+        if self.per_ck_hse {
+            #[cfg(not(feature = "rm0455"))]
+            rcc.d1ccipr
+                .modify(|_, w| w.ckpersel().variant(CKPERSEL::HSE));
+            #[cfg(feature = "rm0455")]
+            rcc.cdccipr
+                .modify(|_, w| w.ckpersel().variant(CKPERSEL::HSE));
+        }
+
+        switch_sw(rcc, self.sw);
+
+        Ok(())
+    }
+}
+
+/// Selects `sw` as the source of `sys_ck`, and waits for the switch
+fn switch_sw(rcc: &rcc::RegisterBlock, sw: u8) {
+    rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(sw) });
+    while rcc.cfgr.read().sws().bits() != sw {}
+}
+
+/// Stops the PLLs, changes their source and reference dividers, and starts
+/// those in `pll_on`. None of the PLLs may be used by `sys_ck`
+fn restart_plls(
+    rcc: &rcc::RegisterBlock,
+    pllsrc: PLLSRC,
+    divm: [u8; 3],
+    pll_on: [bool; 3],
+) {
+    rcc.cr
+        .modify(|_, w| w.pll1on().off().pll2on().off().pll3on().off());
+    while rcc.cr.read().pll1rdy().is_ready()
+        || rcc.cr.read().pll2rdy().is_ready()
+        || rcc.cr.read().pll3rdy().is_ready()
+    {}
+
+    rcc.pllckselr.modify(|_, w| {
+        w.pllsrc()
+            .variant(pllsrc)
+            .divm1()
+            .bits(divm[0])
+            .divm2()
+            .bits(divm[1])
+            .divm3()
+            .bits(divm[2])
+    });
+
+    rcc.cr.modify(|_, w| {
+        w.pll1on()
+            .bit(pll_on[0])
+            .pll2on()
+            .bit(pll_on[1])
+            .pll3on()
+            .bit(pll_on[2])
+    });
+    while (pll_on[0] && rcc.cr.read().pll1rdy().is_not_ready())
+        || (pll_on[1] && rcc.cr.read().pll2rdy().is_not_ready())
+        || (pll_on[2] && rcc.cr.read().pll3rdy().is_not_ready())
+    {}
+}
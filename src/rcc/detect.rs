@@ -0,0 +1,195 @@
+//! HSE frequency detection
+//!
+//! Generic firmware images may run on boards with different HSE crystals.
+//! [`detect_hse`](super::Rcc::detect_hse) measures the HSE against the HSI
+//! before the clocks are frozen, using input capture on TIM17. The HSE is
+//! connected to TIM17 through the RTC prescaler.
+//!
+//! ```no_run
+//! use stm32h7xx_hal::{pac, prelude::*};
+//!
+//! let mut dp = pac::Peripherals::take().unwrap();
+//! let pwrcfg = dp.PWR.constrain().freeze();
+//!
+//! let mut rcc = dp.RCC.constrain();
+//! let rcc = match rcc.detect_hse(&mut dp.TIM17) {
+//!     Some(hse) => rcc.use_hse(hse),
+//!     None => rcc, // No HSE, continue with HSI
+//! };
+//! let ccdr = rcc.sys_ck(400.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+//! ```
+//!
+//! The accuracy of the measurement is limited by the HSI, which is
+//! typically within 1% after factory calibration. The result is rounded to
+//! the nearest common crystal frequency when it is close to one.
+
+use super::{Rcc, HSI};
+use crate::stm32::TIM17;
+use crate::time::Hertz;
+
+/// RTC prescaler setting used during the measurement
+const RTCPRE: u8 = 50;
+/// TIM17 TI1 input selection for the HSE divided by RTCPRE
+const TIM17_TI1SEL_HSE_RTC: u8 = 0b0010;
+/// Iterations to wait for the HSE to become ready, and for each capture
+const HSE_TIMEOUT: u32 = 0x10_0000;
+
+/// Common crystal frequencies, in kHz
+const COMMON_HSE_KHZ: [u32; 14] = [
+    4_000, 8_000, 10_000, 12_000, 12_288, 16_000, 16_384, 20_000, 24_000,
+    24_576, 25_000, 26_000, 32_000, 48_000,
+];
+/// Maximum difference from a common frequency, in parts per thousand
+const COMMON_HSE_TOLERANCE: u32 = 15;
+
+/// Rounds a measured frequency to the nearest common crystal frequency if
+/// it is within tolerance, otherwise to the nearest kHz
+fn round_hse(measured: u32) -> u32 {
+    let khz = (measured + 500) / 1000;
+    let diff = |common: u32| {
+        if khz > common {
+            khz - common
+        } else {
+            common - khz
+        }
+    };
+
+    COMMON_HSE_KHZ
+        .iter()
+        .copied()
+        .min_by_key(|&common| diff(common))
+        .filter(|&common| diff(common) * 1000 <= common * COMMON_HSE_TOLERANCE)
+        .unwrap_or(khz)
+        * 1000
+}
+
+impl Rcc {
+    /// Measures the frequency of the HSE oscillator against the HSI
+    ///
+    /// Returns `None` if the HSE does not start, or stops during the
+    /// measurement. If
+    /// [`bypass_hse`](Rcc::bypass_hse) has been called, the HSE is
+    /// started in bypass mode. The HSE and the RTC prescaler are returned
+    /// to their previous state afterwards, and TIM17 is reset.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sys_ck` is not the HSI with the reset prescaler settings,
+    /// as is the case before [`freeze`](Rcc::freeze) is called
+    pub fn detect_hse(&mut self, tim17: &mut TIM17) -> Option<Hertz> {
+        const EDGES_PER_CAPTURE: u64 = 8;
+        const CAPTURES: u64 = 64;
+
+        let rcc = &self.rb;
+
+        // The timer kernel clock is the HSI at reset
+        assert!(rcc.cfgr.read().sws().bits() == 0, "sys_ck is not HSI");
+        assert!(rcc.cr.read().hsidiv().is_div1());
+        assert!(
+            rcc.d1cfgr.read().bits() == 0 && rcc.d2cfgr.read().bits() == 0,
+            "Core prescalers are not at their reset values"
+        );
+
+        let hse_was_on = rcc.cr.read().hseon().is_on();
+        let rtcpre = rcc.cfgr.read().rtcpre().bits();
+
+        if !hse_was_on {
+            rcc.cr.modify(|_, w| {
+                w.hseon().on().hsebyp().bit(self.config.bypass_hse)
+            });
+        }
+        let mut timeout = HSE_TIMEOUT;
+        while rcc.cr.read().hserdy().is_not_ready() {
+            timeout -= 1;
+            if timeout == 0 {
+                rcc.cr.modify(|_, w| w.hseon().off());
+                return None;
+            }
+        }
+        rcc.cfgr.modify(|_, w| unsafe { w.rtcpre().bits(RTCPRE) });
+
+        // Enable and reset TIM17
+        rcc.apb2enr.modify(|_, w| w.tim17en().enabled());
+        rcc.apb2rstr.modify(|_, w| w.tim17rst().set_bit());
+        rcc.apb2rstr.modify(|_, w| w.tim17rst().clear_bit());
+
+        // CC1 captures TI1, connected to HSE / RTCPRE, every 8th rising edge
+        tim17.psc.write(|w| w.psc().bits(0));
+        tim17.arr.write(|w| unsafe { w.bits(0xFFFF) });
+        tim17
+            .tisel
+            .write(|w| unsafe { w.ti1sel().bits(TIM17_TI1SEL_HSE_RTC) });
+        tim17
+            .ccmr1_input()
+            .write(|w| unsafe { w.cc1s().bits(0b01).ic1psc().bits(0b11) });
+        tim17.egr.write(|w| w.ug().set_bit());
+        tim17.sr.modify(|_, w| w.cc1if().clear_bit());
+        tim17.ccer.write(|w| w.cc1e().set_bit());
+        tim17.cr1.write(|w| w.cen().set_bit());
+
+        let capture = || {
+            let mut timeout = HSE_TIMEOUT;
+            while tim17.sr.read().cc1if().bit_is_clear() {
+                timeout -= 1;
+                if timeout == 0 {
+                    return None;
+                }
+            }
+            // Reading CCR1 clears CC1IF
+            Some(tim17.ccr1.read().ccr().bits() as u16)
+        };
+        let measure = || {
+            let mut last = capture()?;
+            let mut ticks: u64 = 0;
+            for _ in 0..CAPTURES {
+                let next = capture()?;
+                ticks += u64::from(next.wrapping_sub(last));
+                last = next;
+            }
+            Some(ticks)
+        };
+        let ticks = measure();
+
+        // Reset TIM17 and restore the HSE and RTC prescaler
+        rcc.apb2rstr.modify(|_, w| w.tim17rst().set_bit());
+        rcc.apb2rstr.modify(|_, w| w.tim17rst().clear_bit());
+        rcc.apb2enr.modify(|_, w| w.tim17en().disabled());
+        rcc.cfgr.modify(|_, w| unsafe { w.rtcpre().bits(rtcpre) });
+        if !hse_was_on {
+            rcc.cr.modify(|_, w| w.hseon().off());
+        }
+
+        let ticks = ticks.filter(|&ticks| ticks > 0)?;
+        let measured =
+            u64::from(HSI) * EDGES_PER_CAPTURE * CAPTURES * u64::from(RTCPRE)
+                / ticks;
+        Some(Hertz(round_hse(measured as u32)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::round_hse;
+
+    #[test]
+    fn round_to_common() {
+        assert_eq!(round_hse(7_950_123), 8_000_000);
+        assert_eq!(round_hse(25_210_000), 25_000_000);
+        assert_eq!(round_hse(12_300_000), 12_288_000);
+    }
+
+    #[test]
+    fn round_to_nearest_common() {
+        assert_eq!(round_hse(25_000_000), 25_000_000);
+        assert_eq!(round_hse(24_900_000), 25_000_000);
+        assert_eq!(round_hse(24_576_000), 24_576_000);
+        assert_eq!(round_hse(12_288_000), 12_288_000);
+        assert_eq!(round_hse(12_150_000), 12_288_000);
+        assert_eq!(round_hse(12_000_000), 12_000_000);
+    }
+
+    #[test]
+    fn round_to_khz() {
+        assert_eq!(round_hse(14_745_400), 14_745_000);
+    }
+}
@@ -0,0 +1,259 @@
+//! Run-time fine-tuning of fractional PLLs
+//!
+//! A PLL configured with [`PllConfigStrategy::Fractional`] or
+//! [`PllConfigStrategy::FractionalNotLess`] runs with a fractional feedback
+//! divider DIVN + FRACN / 2^13. After `freeze`, FRACN can be changed
+//! without stopping the PLL, which allows its outputs to be trimmed
+//! against an external reference such as the USB SOF or a GPS PPS signal.
+//!
+//! ```
+//! let mut ccdr = rcc
+//!     .pll3_p_ck(12_288.khz())
+//!     .pll3_strategy(PllConfigStrategy::Fractional)
+//!     .freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! let mut pll3 = ccdr.fractional_plls.pll3.take().unwrap();
+//!
+//! // The audio clock is measured as too slow
+//! pll3.trim(4);
+//! let p_ck = pll3.p_ck().unwrap();
+//! ```
+//!
+//! Each FRACN step changes the VCO frequency by ref_ck / 2^13, where ref_ck
+//! is the frequency at the PFD input. The frequencies in `ccdr.clocks` are
+//! not updated, since only small adjustments are expected.
+//!
+//! # Slaving to an external clock
+//!
+//! To follow a clock that is outside of the device, such as an audio sink
+//! or source that reports its rate over a USB audio feedback endpoint,
+//! [`retune`](FractionalPll3::retune) sets the PLL relative to its
+//! frequency after `freeze`. The deviation is given in parts per million,
+//! so a control loop can be run directly on the buffer level.
+//!
+//! ```
+//! // The buffer fills up, the sink consumes samples too slowly
+//! let ppm = -(buffer_level - target_level) / 4;
+//! match pll3.retune(ppm) {
+//!     Ok(p_ck) => {}
+//!     Err(RetuneError::OutOfRange) => {} // Outside the range of FRACN
+//! }
+//! ```
+//!
+//! [`PllConfigStrategy::Fractional`]: super::PllConfigStrategy::Fractional
+//! [`PllConfigStrategy::FractionalNotLess`]: super::PllConfigStrategy::FractionalNotLess
+
+use super::pll::calc_vco_ck;
+use crate::stm32::RCC;
+use crate::time::Hertz;
+use cortex_m::interrupt;
+
+/// Maximum value of FRACN
+pub const FRACN_MAX: u16 = 8191;
+
+/// Fractional PLL retuning error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum RetuneError {
+    /// The frequency cannot be reached without changing DIVN
+    OutOfRange,
+}
+
+/// Returns FRACN for a VCO `ppm` parts per million away from the VCO at
+/// `nominal`. The multiplier of the PLL is `n` + FRACN / 2^13
+fn retune_fracn(n: u32, nominal: u16, ppm: i32) -> Option<u16> {
+    let divn = i64::from(n) << 13;
+    let total = divn + i64::from(nominal);
+    let target =
+        (total * (1_000_000 + i64::from(ppm)) + 500_000).div_euclid(1_000_000);
+    let fracn = target - divn;
+
+    if (0..=i64::from(FRACN_MAX)).contains(&fracn) {
+        Some(fracn as u16)
+    } else {
+        None
+    }
+}
+
+/// Fractional PLLs that can be fine-tuned after `freeze`. A PLL is `None`
+/// unless it was configured with a fractional strategy
+#[non_exhaustive]
+pub struct FractionalPlls {
+    /// PLL1
+    pub pll1: Option<FractionalPll1>,
+    /// PLL2
+    pub pll2: Option<FractionalPll2>,
+    /// PLL3
+    pub pll3: Option<FractionalPll3>,
+}
+
+impl FractionalPlls {
+    /// Creates handles for the PLLs that are running in fractional mode
+    ///
+    /// # Safety
+    ///
+    /// Must only be called once, after the PLLs are configured
+    pub(super) unsafe fn new_singleton(rcc: &RCC, pllsrc: u32) -> Self {
+        let pllckselr = rcc.pllckselr.read();
+        let pllcfgr = rcc.pllcfgr.read();
+
+        // Reference clock at the PFD input
+        let ref_ck = |divm: u8| {
+            if divm == 0 {
+                0
+            } else {
+                pllsrc / u32::from(divm)
+            }
+        };
+
+        FractionalPlls {
+            pll1: if pllcfgr.pll1fracen().is_set() {
+                Some(FractionalPll1 {
+                    ref_ck: ref_ck(pllckselr.divm1().bits()),
+                    nominal: rcc.pll1fracr.read().fracn1().bits(),
+                })
+            } else {
+                None
+            },
+            pll2: if pllcfgr.pll2fracen().is_set() {
+                Some(FractionalPll2 {
+                    ref_ck: ref_ck(pllckselr.divm2().bits()),
+                    nominal: rcc.pll2fracr.read().fracn2().bits(),
+                })
+            } else {
+                None
+            },
+            pll3: if pllcfgr.pll3fracen().is_set() {
+                Some(FractionalPll3 {
+                    ref_ck: ref_ck(pllckselr.divm3().bits()),
+                    nominal: rcc.pll3fracr.read().fracn3().bits(),
+                })
+            } else {
+                None
+            },
+        }
+    }
+}
+
+macro_rules! fractional_pll {
+    ($($PLLX:ident: $doc:expr, ($pllXfracen:ident, $pllXdivr:ident, $divnX:ident,
+        $pllXfracr:ident, $fracnX:ident,
+        [$($ck:ident: ($div:ident, $diven:ident),)+]),)+) => {
+        $(
+            #[doc=$doc]
+            /// running in fractional mode
+            pub struct $PLLX {
+                /// Reference clock at the PFD input
+                ref_ck: u32,
+                /// FRACN after `freeze`
+                nominal: u16,
+            }
+
+            impl $PLLX {
+                /// Returns the current value of FRACN
+                pub fn fracn(&self) -> u16 {
+                    // NOTE(unsafe) Atomic read with no side effects
+                    let rcc = unsafe { &*RCC::ptr() };
+                    rcc.$pllXfracr.read().$fracnX().bits()
+                }
+
+                /// Sets FRACN (0 - 8191). The PLL continues to run, and its
+                /// outputs move to the new frequency
+                pub fn set_fracn(&mut self, fracn: u16) {
+                    let fracn = fracn.min(FRACN_MAX);
+
+                    // unsafe: Owned exclusive access to this PLL's FRACR
+                    // register and FRACEN bit
+                    interrupt::free(|_| {
+                        let rcc = unsafe { &*RCC::ptr() };
+
+                        // FRACN is latched when FRACEN is set
+                        rcc.pllcfgr.modify(|_, w| w.$pllXfracen().reset());
+                        rcc.$pllXfracr.modify(|_, w| w.$fracnX().bits(fracn));
+                        rcc.pllcfgr.modify(|_, w| w.$pllXfracen().set());
+                    });
+                }
+
+                /// Adds `delta` to FRACN, saturating at 0 and 8191. Returns
+                /// the new value
+                pub fn trim(&mut self, delta: i16) -> u16 {
+                    let fracn = (i32::from(self.fracn()) + i32::from(delta))
+                        .max(0)
+                        .min(i32::from(FRACN_MAX)) as u16;
+                    self.set_fracn(fracn);
+                    fracn
+                }
+
+                /// Sets FRACN so that the outputs are `ppm` parts per
+                /// million away from their frequency after `freeze`, and
+                /// returns the new frequency of the VCO
+                ///
+                /// The PLL continues to run. Returns an error if the
+                /// frequency is outside of the range that FRACN covers at
+                /// the current DIVN, and leaves FRACN unchanged
+                pub fn retune(&mut self, ppm: i32) -> Result<Hertz, RetuneError> {
+                    // NOTE(unsafe) Atomic read with no side effects
+                    let rcc = unsafe { &*RCC::ptr() };
+                    let n = u32::from(rcc.$pllXdivr.read().$divnX().bits()) + 1;
+
+                    let fracn = retune_fracn(n, self.nominal, ppm)
+                        .ok_or(RetuneError::OutOfRange)?;
+                    self.set_fracn(fracn);
+
+                    Ok(self.vco_ck())
+                }
+
+                /// Returns the current VCO frequency
+                pub fn vco_ck(&self) -> Hertz {
+                    // NOTE(unsafe) Atomic read with no side effects
+                    let rcc = unsafe { &*RCC::ptr() };
+                    let n = u32::from(rcc.$pllXdivr.read().$divnX().bits()) + 1;
+
+                    Hertz(calc_vco_ck(self.ref_ck, n, self.fracn()))
+                }
+
+                $(
+                    /// Returns `Some(frequency)` of this output at the
+                    /// current value of FRACN if it is enabled, otherwise
+                    /// `None`
+                    pub fn $ck(&self) -> Option<Hertz> {
+                        // NOTE(unsafe) Atomic read with no side effects
+                        let rcc = unsafe { &*RCC::ptr() };
+                        if rcc.pllcfgr.read().$diven().is_disabled() {
+                            return None;
+                        }
+                        let div = u32::from(rcc.$pllXdivr.read().$div().bits()) + 1;
+
+                        Some(Hertz(self.vco_ck().0 / div))
+                    }
+                )+
+            }
+        )+
+    };
+}
+
+fractional_pll! {
+    FractionalPll1: "PLL1", (pll1fracen, pll1divr, divn1, pll1fracr, fracn1,
+        [p_ck: (divp1, divp1en), q_ck: (divq1, divq1en), r_ck: (divr1, divr1en),]),
+    FractionalPll2: "PLL2", (pll2fracen, pll2divr, divn2, pll2fracr, fracn2,
+        [p_ck: (divp2, divp2en), q_ck: (divq2, divq2en), r_ck: (divr2, divr2en),]),
+    FractionalPll3: "PLL3", (pll3fracen, pll3divr, divn3, pll3fracr, fracn3,
+        [p_ck: (divp3, divp3en), q_ck: (divq3, divq3en), r_ck: (divr3, divr3en),]),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retune_range() {
+        // No change
+        assert_eq!(retune_fracn(40, 4096, 0), Some(4096));
+        // 40.5 * 8192 = 331776 steps, so 100ppm is 33.2 steps
+        assert_eq!(retune_fracn(40, 4096, 100), Some(4129));
+        assert_eq!(retune_fracn(40, 4096, -100), Some(4063));
+        // Beyond the range of FRACN
+        assert_eq!(retune_fracn(40, 8000, 1000), None);
+        assert_eq!(retune_fracn(40, 100, -1000), None);
+    }
+}
@@ -1,6 +1,10 @@
 //! Micro-Controller Out (MCO) pins
 
-use super::Rcc;
+use super::{CoreClocks, Rcc};
+use crate::gpio::gpioa::PA8;
+use crate::gpio::gpioc::PC9;
+use crate::gpio::{Alternate, AF0};
+use crate::stm32::RCC;
 use crate::time::Hertz;
 
 pub use crate::stm32::rcc::cfgr::MCO1_A as MCO1;
@@ -149,9 +153,9 @@ macro_rules! mco1_setters {
                 /// Set the MCO1 output frequency. The clock is sourced from
                 #[doc=$doc]
                 ///
-                /// This only enables the signal within the RCC block, it does
-                /// not enable the MCO1 output pin itself (use the GPIO for
-                /// that).
+                /// This only selects the source and the prescaler. The output
+                /// is enabled when its pin is passed to
+                /// [`CoreClocks::mco1`](super::CoreClocks::mco1).
                 pub fn $mco_setter<F>(mut self, freq: F) -> Self
                 where
                     F: Into<Hertz>,
@@ -180,9 +184,9 @@ macro_rules! mco2_setters {
                 /// Set the MCO2 output frequency. The clock is sourced from
                 #[doc=$doc]
                 ///
-                /// This only enables the signal within the RCC block, it does
-                /// not enable the MCO2 output pin itself (use the GPIO for
-                /// that).
+                /// This only selects the source and the prescaler. The output
+                /// is enabled when its pin is passed to
+                /// [`CoreClocks::mco2`](super::CoreClocks::mco2).
                 pub fn $mco_setter<F>(mut self, freq: F) -> Self
                 where
                     F: Into<Hertz>,
@@ -203,3 +207,71 @@ mco2_setters! {
     mco2_from_csi: CSI "CSI"
     //mco2_from_lsi: LSI "the LSI",    UNIMPLEMENTED
 }
+
+/// Marks a pin as an MCO1 output
+pub trait PinMco1 {}
+/// Marks a pin as an MCO2 output
+pub trait PinMco2 {}
+
+impl PinMco1 for PA8<Alternate<AF0>> {}
+impl PinMco2 for PC9<Alternate<AF0>> {}
+
+macro_rules! mco_output {
+    ($($MCO:ident: $mco:ident, $PIN:ident, $mco_ck:ident, $mco_pre:ident,
+       $mcopre:ident, $setter:expr;)+) => {
+        $(
+            /// Micro-Controller Out on a pin
+            pub struct $MCO<PIN> {
+                pin: PIN,
+                frequency: Hertz,
+            }
+
+            impl<PIN> $MCO<PIN> {
+                /// Returns the frequency of the output
+                pub fn frequency(&self) -> Hertz {
+                    self.frequency
+                }
+
+                /// Disables the output and releases the pin
+                pub fn free(self) -> PIN {
+                    // unsafe: Only the prescaler of this output is written,
+                    // which is owned by self
+                    let rcc = unsafe { &*RCC::ptr() };
+                    rcc.cfgr.modify(|_, w| w.$mcopre().bits(0));
+
+                    self.pin
+                }
+            }
+
+            impl CoreClocks {
+                /// Enables the output on `pin`
+                ///
+                /// # Panics
+                ///
+                /// Panics if the output was not configured with a builder
+                /// method before `freeze`
+                pub fn $mco<PIN: $PIN>(&self, pin: PIN) -> $MCO<PIN> {
+                    let frequency = self.$mco_ck.expect(concat!(
+                        stringify!($MCO),
+                        " is not configured. Use a ",
+                        $setter,
+                        " method before freeze"
+                    ));
+
+                    // unsafe: Only the prescaler of this output is written.
+                    // The pin is moved into the result, so the output is
+                    // enabled once
+                    let rcc = unsafe { &*RCC::ptr() };
+                    rcc.cfgr.modify(|_, w| w.$mcopre().bits(self.$mco_pre));
+
+                    $MCO { pin, frequency }
+                }
+            }
+        )+
+    };
+}
+
+mco_output! {
+    Mco1: mco1, PinMco1, mco1_ck, mco1_pre, mco1pre, "mco1_from_*";
+    Mco2: mco2, PinMco2, mco2_ck, mco2_pre, mco2pre, "mco2_from_*";
+}
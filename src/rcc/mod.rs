@@ -142,7 +142,6 @@
 use crate::pwr::PowerConfiguration;
 use crate::pwr::VoltageScale as Voltage;
 use crate::stm32::rcc::cfgr::SW_A as SW;
-use crate::stm32::rcc::cfgr::TIMPRE_A as TIMPRE;
 use crate::stm32::rcc::d1cfgr::HPRE_A as HPRE;
 use crate::stm32::rcc::pllckselr::PLLSRC_A as PLLSRC;
 use crate::stm32::{RCC, SYSCFG};
@@ -154,21 +153,41 @@ use crate::stm32::rcc::cdccipr::CKPERSEL_A as CKPERSEL;
 use crate::stm32::rcc::d1ccipr::CKPERSEL_A as CKPERSEL;
 
 pub mod backup;
+mod budget;
 mod core_clocks;
+mod css;
+mod detect;
+mod fracn;
 mod pll;
+pub mod pll_token;
+mod profile;
 pub mod rec;
+mod switch;
 
+pub use budget::{Overclock, Overclocks};
 pub use core_clocks::CoreClocks;
-pub use pll::{PllConfig, PllConfigStrategy};
+pub use css::{ClockFailure, ClockSecurity, ClockSecurityError, HseFallback};
+pub use fracn::{
+    FractionalPll1, FractionalPll2, FractionalPll3, FractionalPlls,
+    RetuneError, FRACN_MAX,
+};
+pub use pll::{pll_dividers, PllConfig, PllConfigStrategy, PllDividers};
+pub use pll_token::{
+    Pll1Q, Pll1R, Pll2P, Pll2Q, Pll2R, Pll3P, Pll3Q, Pll3R, PllSource,
+};
+pub use profile::LowPowerProfile;
 pub use rec::{LowPowerMode, PeripheralREC, ResetEnable};
+pub use switch::{SwitchError, SysClkSource};
 
 mod mco;
 use mco::{MCO1Config, MCO2Config, MCO1, MCO2};
+pub use mco::{Mco1, Mco2, PinMco1, PinMco2};
 
 /// Configuration of the core clocks
 pub struct Config {
     hse: Option<u32>,
     bypass_hse: bool,
+    hse_css: bool,
     sys_ck: Option<u32>,
     per_ck: Option<u32>,
     rcc_hclk: Option<u32>,
@@ -181,6 +200,65 @@ pub struct Config {
     pll1: PllConfig,
     pll2: PllConfig,
     pll3: PllConfig,
+    timpre: TimerPrescaler,
+    hsi48_crs: bool,
+}
+
+/// Error from [`Rcc::try_freeze`](crate::rcc::Rcc::try_freeze)
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// `pll1_p_ck` is used to generate `sys_ck`, so it cannot be set to a
+    /// different frequency
+    Pll1PClock,
+    /// `sys_ck` is too fast for the voltage scale
+    SysClockTooFast,
+    /// `hclk` is too fast for the voltage scale
+    HclkTooFast,
+    /// A `pclk` is too fast for the voltage scale
+    PclkTooFast,
+    /// A `pclk` was set to 0Hz
+    PclkZero,
+    /// The clock security system was requested without the HSE
+    HseCssWithoutHse,
+}
+
+impl ConfigError {
+    /// Returns a description of the error for the panic message of
+    /// [`freeze`](crate::rcc::Rcc::freeze)
+    fn message(self) -> &'static str {
+        match self {
+            ConfigError::Pll1PClock => {
+                "pll1_p_ck cannot be set independently of sys_ck"
+            }
+            ConfigError::SysClockTooFast => {
+                "sys_ck is too fast for the voltage scale"
+            }
+            ConfigError::HclkTooFast => {
+                "hclk is too fast for the voltage scale"
+            }
+            ConfigError::PclkTooFast => {
+                "pclk is too fast for the voltage scale"
+            }
+            ConfigError::PclkZero => "pclk must not be 0Hz",
+            ConfigError::HseCssWithoutHse => "hse_css requires use_hse",
+        }
+    }
+}
+
+/// Timer kernel clock multiplier, `RCC_CFGR.TIMPRE`
+///
+/// The kernel clock of the timers on APB1 (`timx_ker_ck`) and APB2
+/// (`timy_ker_ck`) is a multiple of the APB clock, but never faster than
+/// `hclk`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TimerPrescaler {
+    /// The timer kernel clock is `hclk` if the APB prescaler is 1 or 2,
+    /// otherwise 2 × `pclk`. This is the reset value
+    DefaultX2,
+    /// The timer kernel clock is `hclk` if the APB prescaler is 1, 2 or 4,
+    /// otherwise 4 × `pclk`
+    DefaultX4,
 }
 
 /// Extension trait that constrains the `RCC` peripheral
@@ -196,6 +274,7 @@ impl RccExt for RCC {
             config: Config {
                 hse: None,
                 bypass_hse: false,
+                hse_css: false,
                 sys_ck: None,
                 per_ck: None,
                 rcc_hclk: None,
@@ -208,6 +287,8 @@ impl RccExt for RCC {
                 pll1: PllConfig::default(),
                 pll2: PllConfig::default(),
                 pll3: PllConfig::default(),
+                timpre: TimerPrescaler::DefaultX2,
+                hsi48_crs: false,
             },
             rb: self,
         }
@@ -242,6 +323,12 @@ pub struct Ccdr {
     /// Peripheral reset / enable / kernel clock control
     pub peripheral: PeripheralREC,
 
+    /// PLLs running in fractional mode, that can be fine-tuned
+    pub fractional_plls: FractionalPlls,
+
+    /// Handling of HSE and LSE failures
+    pub clock_security: ClockSecurity,
+
     // Yes, it lives (locally)! We retain the right to switch most
     // PKSUs on the fly, to fine-tune PLL frequencies, and to enable /
     // reset peripherals.
@@ -249,12 +336,35 @@ pub struct Ccdr {
     // TODO: Remove this once all permitted RCC register accesses
     // after freeze are enumerated in this struct
     pub(crate) rb: RCC,
+
+    // Current voltage scale
+    vos: Voltage,
+
+    // Configuration to restore when leaving a low-power profile
+    low_power: Option<profile::SavedRunMode>,
 }
 
 const HSI: u32 = 64_000_000; // Hz
 const CSI: u32 = 4_000_000; // Hz
 const HSI48: u32 = 48_000_000; // Hz
 const LSI: u32 = 32_000; // Hz
+/// The LSE oscillator is always a 32.768 kHz watch crystal
+const LSE: u32 = 32_768; // Hz
+
+/// Returns the LSE frequency if the LSE is running
+///
+/// The LSE is started with the backup domain, independently of `freeze`,
+/// so it is not part of [`CoreClocks`]
+pub(crate) fn lse_ck() -> Option<Hertz> {
+    // unsafe: read only
+    let bdcr = unsafe { (*RCC::ptr()).bdcr.read() };
+
+    if bdcr.lserdy().is_ready() {
+        Some(Hertz(LSE))
+    } else {
+        None
+    }
+}
 
 /// Setter defintion for pclk 1 - 4
 macro_rules! pclk_setter {
@@ -325,6 +435,15 @@ impl Rcc {
         self
     }
 
+    /// Enables the clock security system (CSS) on the HSE. A failure of
+    /// the HSE generates an NMI, see [`ClockSecurity`]
+    ///
+    /// The CSS cannot be disabled again until reset.
+    pub fn hse_css(mut self) -> Self {
+        self.config.hse_css = true;
+        self
+    }
+
     /// Set input frequency to the SCGU
     pub fn sys_ck<F>(mut self, freq: F) -> Self
     where
@@ -343,6 +462,27 @@ impl Rcc {
         self
     }
 
+    /// Set the timer kernel clock multiplier. The default is
+    /// [`DefaultX2`](TimerPrescaler::DefaultX2)
+    ///
+    /// With a large APB prescaler, `DefaultX4` allows timers to run faster
+    /// than the other APB peripherals.
+    pub fn timer_prescaler(mut self, timpre: TimerPrescaler) -> Self {
+        self.config.timpre = timpre;
+        self
+    }
+
+    /// Marks `hsi48_ck` as trimmed by the clock recovery system (CRS)
+    ///
+    /// The CRS must be enabled with automatic trimming by the
+    /// [`crs`](crate::crs) module after `freeze`. Drivers that need an
+    /// accurate 48MHz clock, such as USB, can then check
+    /// [`CoreClocks::hsi48_crs_trimmed`].
+    pub fn hsi48_crs_trimmed(mut self) -> Self {
+        self.config.hsi48_crs = true;
+        self
+    }
+
     /// Set peripheral clock frequency
     pub fn per_ck<F>(mut self, freq: F) -> Self
     where
@@ -396,6 +536,52 @@ impl Rcc {
     }
 }
 
+/// Calculates the APB prescaler that will be chosen for a requested
+/// `pclk` frequency, given the AHB frequency `hclk`. This is a `const fn`,
+/// so it can be evaluated at compile time.
+///
+/// Returns the division ratio: 1, 2, 4, 8 or 16, or `None` if `pclk` is
+/// zero
+pub const fn apb_prescaler(hclk: u32, pclk: u32) -> Option<u8> {
+    if pclk == 0 {
+        return None;
+    }
+
+    // Division rounded up
+    let ratio = hclk / pclk + (hclk % pclk != 0) as u32;
+    let ppre = if ratio <= 1 {
+        1
+    } else if ratio == 2 {
+        2
+    } else if ratio <= 5 {
+        4
+    } else if ratio <= 11 {
+        8
+    } else {
+        16
+    };
+    Some(ppre)
+}
+
+/// Calculates the timer kernel clock for an APB prescaler `ppre` (the
+/// division ratio), given the AHB frequency `hclk`. This is a `const fn`,
+/// so it can be evaluated at compile time.
+pub const fn timer_kernel_clock(
+    hclk: u32,
+    ppre: u8,
+    timpre: TimerPrescaler,
+) -> u32 {
+    let multiplier = match timpre {
+        TimerPrescaler::DefaultX2 => 2,
+        TimerPrescaler::DefaultX4 => 4,
+    };
+    if ppre as u32 <= multiplier {
+        hclk
+    } else {
+        hclk / (ppre as u32 / multiplier)
+    }
+}
+
 /// Divider calculator for pclk 1 - 4
 ///
 /// Also calulate tim[xy]_ker_clk if there are timers on this bus
@@ -410,32 +596,29 @@ macro_rules! ppre_calculate {
                 .unwrap_or_else(|| core::cmp::min($max, $hclk / 2));
 
             // Calculate suitable divider
-            let ($bits, $ppre) = match ($hclk + $pclk - 1) / $pclk
-            {
-                0 => unreachable!(),
-                1 => (0b000, 1 as u8),
-                2 => (0b100, 2),
-                3..=5 => (0b101, 4),
-                6..=11 => (0b110, 8),
-                _ => (0b111, 16),
+            hal_assert!($pclk != 0, ConfigError::PclkZero, "pclk must not be 0Hz");
+            let $ppre = match apb_prescaler($hclk, $pclk) {
+                Some(ppre) => ppre,
+                None => unreachable!(),
+            };
+            let $bits = match $ppre {
+                1 => 0b000,
+                2 => 0b100,
+                4 => 0b101,
+                8 => 0b110,
+                _ => 0b111,
             };
 
             // Calculate real APBn clock
             let $pclk = $hclk / u32::from($ppre);
 
             // Check in range
-            assert!($pclk <= $max);
+            hal_assert!($pclk <= $max, ConfigError::PclkTooFast,
+                        "pclk is too fast for the voltage scale");
 
             $(
-                let $rcc_tim_ker_clk = match ($bits, &$timpre)
-                {
-                    (0b101, TIMPRE::DEFAULTX2) => $hclk / 2,
-                    (0b110, TIMPRE::DEFAULTX4) => $hclk / 2,
-                    (0b110, TIMPRE::DEFAULTX2) => $hclk / 4,
-                    (0b111, TIMPRE::DEFAULTX4) => $hclk / 4,
-                    (0b111, TIMPRE::DEFAULTX2) => $hclk / 8,
-                    _ => $hclk,
-                };
+                let $rcc_tim_ker_clk =
+                    timer_kernel_clock($hclk, $ppre, $timpre);
             )*
         )+
     };
@@ -452,7 +635,7 @@ impl Rcc {
 
         // See RM0433 Rev 7 Table 17. FLASH recommended number of wait
         // states and programming delay
-        #[cfg(not(feature = "rm0455"))]
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
         let (wait_states, progr_delay) = match vos {
             // VOS 0 range VCORE 1.26V - 1.40V
             Voltage::Scale0 => match rcc_aclk_mhz {
@@ -492,6 +675,40 @@ impl Rcc {
             },
         };
 
+        // See RM0468 Rev 2 Table 16
+        #[cfg(feature = "rm0468")]
+        let (wait_states, progr_delay) = match vos {
+            // VOS 0 range VCORE 1.26V - 1.40V
+            Voltage::Scale0 => match rcc_aclk_mhz {
+                0..=69 => (0, 0),
+                70..=139 => (1, 1),
+                140..=209 => (2, 2),
+                210..=274 => (3, 3),
+                _ => (7, 3),
+            },
+            // VOS 1 range VCORE 1.15V - 1.26V
+            Voltage::Scale1 => match rcc_aclk_mhz {
+                0..=66 => (0, 0),
+                67..=132 => (1, 1),
+                133..=199 => (2, 2),
+                _ => (7, 3),
+            },
+            // VOS 2 range VCORE 1.05V - 1.15V
+            Voltage::Scale2 => match rcc_aclk_mhz {
+                0..=49 => (0, 0),
+                50..=99 => (1, 1),
+                100..=149 => (2, 2),
+                _ => (7, 3),
+            },
+            // VOS 3 range VCORE 0.95V - 1.05V
+            Voltage::Scale3 => match rcc_aclk_mhz {
+                0..=34 => (0, 0),
+                35..=69 => (1, 1),
+                70..=84 => (2, 2),
+                _ => (7, 3),
+            },
+        };
+
         // See RM0455 Rev 3 Table 15
         #[cfg(feature = "rm0455")]
         let (wait_states, progr_delay) = match vos {
@@ -545,7 +762,7 @@ impl Rcc {
 
     /// Setup sys_ck
     /// Returns sys_ck frequency, and a pll1_p_ck
-    fn sys_ck_setup(&mut self) -> (Hertz, bool) {
+    fn sys_ck_setup(&mut self) -> Result<(Hertz, bool), ConfigError> {
         // Compare available with wanted clocks
         let srcclk = self.config.hse.unwrap_or(HSI); // Available clocks
         let sys_ck = self.config.sys_ck.unwrap_or(srcclk);
@@ -559,19 +776,19 @@ impl Rcc {
             // Therefore we must use pll1_p_ck
             let pll1_p_ck = match self.config.pll1.p_ck {
                 Some(p_ck) => {
-                    assert!(p_ck == sys_ck,
-                            "Error: Cannot set pll1_p_ck independently as it must be used to generate sys_ck");
+                    hal_assert!(p_ck == sys_ck, ConfigError::Pll1PClock,
+                                "Error: Cannot set pll1_p_ck independently as it must be used to generate sys_ck");
                     Some(p_ck)
                 }
                 None => Some(sys_ck),
             };
             self.config.pll1.p_ck = pll1_p_ck;
 
-            (Hertz(sys_ck), true)
+            Ok((Hertz(sys_ck), true))
         } else {
             // sys_ck is derived directly from a source clock
             // (HSE/HSI). pll1_p_ck can be as requested
-            (Hertz(sys_ck), false)
+            Ok((Hertz(sys_ck), false))
         }
     }
 
@@ -613,11 +830,35 @@ impl Rcc {
     /// function may also panic if a clock specification can be
     /// achieved, but the mechanism for doing so is not yet
     /// implemented here.
-    pub fn freeze(
+    pub fn freeze(self, pwrcfg: PowerConfiguration, syscfg: &SYSCFG) -> Ccdr {
+        match self.try_freeze(pwrcfg, syscfg) {
+            Ok(ccdr) => ccdr,
+            Err(e) => {
+                hal_panic!("Invalid clock configuration: {}", e.message())
+            }
+        }
+    }
+
+    /// Freeze the core clocks, as [`freeze`](Self::freeze)
+    ///
+    /// # Errors
+    ///
+    /// With the `assert-error` feature, returns an error if a clock is out
+    /// of range for the voltage scale, or if the clock specification is
+    /// inconsistent. These are checked before `sys_ck` is switched, but
+    /// after the PLL dividers have been written and the LSI enabled.
+    /// Without it, this function panics instead, like `freeze`.
+    ///
+    /// # Panics
+    ///
+    /// This function may still panic if a clock specification cannot be
+    /// achieved by the PLLs or the MCO prescalers, or if the mechanism for
+    /// doing so is not yet implemented here.
+    pub fn try_freeze(
         mut self,
         pwrcfg: PowerConfiguration,
         syscfg: &SYSCFG,
-    ) -> Ccdr {
+    ) -> Result<Ccdr, ConfigError> {
         // We do not reset RCC here. This routine must assert when
         // the previous state of the RCC peripheral is unacceptable.
 
@@ -629,7 +870,12 @@ impl Rcc {
         self.mco2_setup();
 
         // sys_ck from PLL if needed, else HSE or HSI
-        let (sys_ck, sys_use_pll1_p) = self.sys_ck_setup();
+        let (sys_ck, sys_use_pll1_p) = self.sys_ck_setup()?;
+        hal_assert!(
+            self.config.hse.is_some() || !self.config.hse_css,
+            ConfigError::HseCssWithoutHse,
+            "hse_css requires use_hse"
+        );
 
         // Configure traceclk from PLL if needed
         self.traceclk_setup(sys_use_pll1_p);
@@ -657,8 +903,11 @@ impl Rcc {
         // do so it would need to ensure all PLLxON bits are clear
         // before changing the value of HSIDIV
         let hsi = HSI;
-        assert!(rcc.cr.read().hsion().is_on(), "HSI oscillator must be on!");
-        assert!(rcc.cr.read().hsidiv().is_div1());
+        hal_assert!(
+            rcc.cr.read().hsion().is_on(),
+            "HSI oscillator must be on!"
+        );
+        hal_assert!(rcc.cr.read().hsidiv().is_div1(), "HSIDIV must be 1");
 
         let csi = CSI;
         let hsi48 = HSI48;
@@ -683,12 +932,12 @@ impl Rcc {
         let sys_d1cpre_ck = sys_ck.0 / d1cpre_div;
 
         // Timer prescaler selection
-        let timpre = TIMPRE::DEFAULTX2;
+        let timpre = self.config.timpre;
 
         // Refer to part datasheet "General operating conditions"
         // table for (rev V). We do not assert checks for earlier
         // revisions which may have lower limits.
-        #[cfg(not(feature = "rm0455"))]
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
         let (sys_d1cpre_ck_max, rcc_hclk_max, pclk_max) = match pwrcfg.vos {
             Voltage::Scale0 => (480_000_000, 240_000_000, 120_000_000),
             Voltage::Scale1 => (400_000_000, 200_000_000, 100_000_000),
@@ -696,6 +945,24 @@ impl Rcc {
             _ => (200_000_000, 100_000_000, 50_000_000),
         };
 
+        // On RM0468 parts, 550MHz in VOS0 requires the CPU_FREQ_BOOST
+        // option bit. Otherwise the limit is 520MHz
+        #[cfg(feature = "rm0468")] // 723 / 725 / 730 / 733 / 735
+        let cpu_freq_boost = {
+            let flash = unsafe { &(*crate::stm32::FLASH::ptr()) };
+            flash.optsr2_cur.read().cpufreq_boost().bit_is_set()
+        };
+        #[cfg(feature = "rm0468")]
+        let (sys_d1cpre_ck_max, rcc_hclk_max, pclk_max) = match pwrcfg.vos {
+            Voltage::Scale0 if cpu_freq_boost => {
+                (550_000_000, 275_000_000, 137_500_000)
+            }
+            Voltage::Scale0 => (520_000_000, 260_000_000, 130_000_000),
+            Voltage::Scale1 => (400_000_000, 200_000_000, 100_000_000),
+            Voltage::Scale2 => (300_000_000, 150_000_000, 75_000_000),
+            _ => (170_000_000, 85_000_000, 42_500_000),
+        };
+
         #[cfg(feature = "rm0455")] // 7B3 / 7A3 / 7B0
         let (sys_d1cpre_ck_max, rcc_hclk_max, pclk_max) = match pwrcfg.vos {
             Voltage::Scale0 => (280_000_000, 280_000_000, 140_000_000),
@@ -705,7 +972,11 @@ impl Rcc {
         };
 
         // Check resulting sys_d1cpre_ck
-        assert!(sys_d1cpre_ck <= sys_d1cpre_ck_max);
+        hal_assert!(
+            sys_d1cpre_ck <= sys_d1cpre_ck_max,
+            ConfigError::SysClockTooFast,
+            "sys_ck is too fast for the voltage scale"
+        );
 
         // Get AHB clock or sensible default
         #[cfg(not(feature = "rm0455"))]
@@ -713,7 +984,11 @@ impl Rcc {
         #[cfg(feature = "rm0455")]
         let rcc_hclk = self.config.rcc_hclk.unwrap_or(sys_d1cpre_ck);
 
-        assert!(rcc_hclk <= rcc_hclk_max);
+        hal_assert!(
+            rcc_hclk <= rcc_hclk_max,
+            ConfigError::HclkTooFast,
+            "hclk is too fast for the voltage scale"
+        );
 
         // Estimate divisor
         let (hpre_bits, hpre_div) =
@@ -732,7 +1007,11 @@ impl Rcc {
 
         // Calculate real AXI and AHB clock
         let rcc_hclk = sys_d1cpre_ck / hpre_div;
-        assert!(rcc_hclk <= rcc_hclk_max);
+        hal_assert!(
+            rcc_hclk <= rcc_hclk_max,
+            ConfigError::HclkTooFast,
+            "hclk is too fast for the voltage scale"
+        );
         let rcc_aclk = rcc_hclk; // AXI clock is always equal to AHB clock on H7
 
         // Calculate ppreN dividers and real rcc_pclkN frequencies
@@ -782,7 +1061,9 @@ impl Rcc {
         rcc.cr.modify(|_, w| w.hsi48on().on());
         while rcc.cr.read().hsi48rdy().is_not_ready() {}
 
-        // Set the MCO outputs.
+        // Set the MCO sources. The outputs stay disabled until their pin is
+        // passed to `CoreClocks::mco1` or `CoreClocks::mco2`, which write
+        // the prescaler.
         //
         // It is highly recommended to configure these bits only after
         // reset, before enabling the external oscillators and the PLLs.
@@ -790,11 +1071,11 @@ impl Rcc {
             w.mco1()
                 .variant(self.config.mco1.source)
                 .mco1pre()
-                .bits(mco_1_pre)
+                .bits(0)
                 .mco2()
                 .variant(self.config.mco2.source)
                 .mco2pre()
-                .bits(mco_2_pre)
+                .bits(0)
         });
 
         // HSE
@@ -806,6 +1087,11 @@ impl Rcc {
                 });
                 while rcc.cr.read().hserdy().is_not_ready() {}
 
+                // Clock security system
+                if self.config.hse_css {
+                    rcc.cr.modify(|_, w| w.hsecsson().set_bit());
+                }
+
                 Some(Hertz(hse))
             }
             None => None,
@@ -874,7 +1160,8 @@ impl Rcc {
         rcc.cdccipr.modify(|_, w| w.ckpersel().variant(ckpersel));
 
         // Set timer clocks prescaler setting
-        rcc.cfgr.modify(|_, w| w.timpre().variant(timpre));
+        rcc.cfgr
+            .modify(|_, w| w.timpre().bit(timpre == TimerPrescaler::DefaultX4));
 
         // Select system clock source
         let swbits = match (sys_use_pll1_p, self.config.hse.is_some()) {
@@ -886,7 +1173,7 @@ impl Rcc {
         while rcc.cfgr.read().sws().bits() != swbits {}
 
         // IO compensation cell - Requires CSI clock and SYSCFG
-        assert!(rcc.cr.read().csirdy().is_ready());
+        hal_assert!(rcc.cr.read().csirdy().is_ready(), "CSI must be ready");
         rcc.apb4enr.modify(|_, w| w.syscfgen().enabled());
 
         // Enable the compensation cell, using back-bias voltage code
@@ -897,7 +1184,7 @@ impl Rcc {
         while syscfg.cccsr.read().ready().bit_is_clear() {}
 
         // Return frozen clock configuration
-        Ccdr {
+        Ok(Ccdr {
             clocks: CoreClocks {
                 hclk: Hertz(rcc_hclk),
                 pclk1: Hertz(rcc_pclk1),
@@ -916,6 +1203,8 @@ impl Rcc {
                 hse_ck,
                 mco1_ck,
                 mco2_ck,
+                mco1_pre: mco_1_pre,
+                mco2_pre: mco_2_pre,
                 pll1_p_ck,
                 pll1_q_ck,
                 pll1_r_ck,
@@ -929,13 +1218,74 @@ impl Rcc {
                 timy_ker_ck: Hertz(rcc_timy_ker_ck),
                 sys_ck,
                 c_ck: Hertz(sys_d1cpre_ck),
+                timpre,
+                hsi48_crs: self.config.hsi48_crs,
             },
             peripheral: unsafe {
                 // unsafe: we consume self which was a singleton, hence
                 // we can safely create a singleton here
                 PeripheralREC::new_singleton()
             },
+            fractional_plls: unsafe {
+                // unsafe: as above
+                FractionalPlls::new_singleton(
+                    rcc,
+                    self.config.hse.unwrap_or(HSI),
+                )
+            },
+            clock_security: unsafe {
+                // unsafe: as above
+                ClockSecurity::new_singleton(
+                    rcc,
+                    self.config.hse,
+                    swbits,
+                    ckpersel == CKPERSEL::HSE,
+                )
+            },
             rb: self.rb,
+            vos: pwrcfg.vos,
+            low_power: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apb_prescaler, timer_kernel_clock, TimerPrescaler};
+
+    #[test]
+    fn apb_prescaler_ratio() {
+        let hclk = 200_000_000;
+        assert_eq!(apb_prescaler(hclk, hclk), Some(1));
+        assert_eq!(apb_prescaler(hclk, 2 * hclk), Some(1));
+        assert_eq!(apb_prescaler(hclk, hclk / 2), Some(2));
+        // Rounded up, so that pclk is not faster than requested
+        assert_eq!(apb_prescaler(hclk, hclk / 2 - 1), Some(4));
+        assert_eq!(apb_prescaler(hclk, hclk / 4), Some(4));
+        assert_eq!(apb_prescaler(hclk, hclk / 8), Some(8));
+        assert_eq!(apb_prescaler(hclk, 1), Some(16));
+        assert_eq!(apb_prescaler(u32::MAX, u32::MAX - 1), Some(2));
+        assert_eq!(apb_prescaler(hclk, 0), None);
+    }
+
+    #[test]
+    fn timer_kernel_clock_multiplier() {
+        let hclk = 200_000_000;
+        for &(ppre, x2, x4) in &[
+            (1, hclk, hclk),
+            (2, hclk, hclk),
+            (4, hclk / 2, hclk),
+            (8, hclk / 4, hclk / 2),
+            (16, hclk / 8, hclk / 4),
+        ] {
+            assert_eq!(
+                timer_kernel_clock(hclk, ppre, TimerPrescaler::DefaultX2),
+                x2
+            );
+            assert_eq!(
+                timer_kernel_clock(hclk, ppre, TimerPrescaler::DefaultX4),
+                x4
+            );
         }
     }
 }
@@ -80,28 +80,130 @@ macro_rules! vco_output_divider_setup {
     }};
 }
 
+/// Dividers for a Phase Locked Loop (PLL) calculated by [`pll_dividers`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PllDividers {
+    /// Input divider (DIVM)
+    pub m: u32,
+    /// Feedback divider (DIVN)
+    pub n: u32,
+    /// Output divider (DIVP)
+    pub p: u32,
+    /// Reference clock at the PFD input
+    pub ref_ck: u32,
+    /// Resulting VCO frequency
+    pub vco_ck: u32,
+    /// Resulting P clock frequency
+    pub p_ck: u32,
+}
+
+/// Calculates the PLL dividers that
+/// [`PllConfigStrategy::Normal`](PllConfigStrategy::Normal) will
+/// choose for an output frequency `output` from a PLL source clock
+/// `pllsrc`. `pll1` selects the additional restrictions on the PLL1 P
+/// divider.
+///
+/// Returns `None` if the output cannot be achieved.
+///
+/// This is a `const fn`, so clock configurations can be checked at compile
+/// time
+///
+/// ```
+/// use stm32h7xx_hal::rcc::{pll_dividers, PllDividers};
+///
+/// const PLL1: Option<PllDividers> = pll_dividers(8_000_000, 400_000_000, true);
+/// const SYS_CK: u32 = match PLL1 {
+///     Some(d) => d.p_ck,
+///     None => 0,
+/// };
+///
+/// // Fails to compile if sys_ck is not 400MHz
+/// const _: [(); 1] = [(); (SYS_CK == 400_000_000) as usize];
+/// ```
+pub const fn pll_dividers(
+    pllsrc: u32,
+    output: u32,
+    pll1: bool,
+) -> Option<PllDividers> {
+    // VCOL
+    let (vco_min, vco_max) = (150_000_000, 420_000_000);
+
+    if pllsrc == 0 || output == 0 {
+        return None;
+    }
+
+    // VCO output frequency. Choose the highest VCO frequency
+    let p = if output > vco_max / 2 {
+        1
+    } else if pll1 {
+        ((vco_max / output) | 1) - 1 // Must be even or unity
+    } else {
+        vco_max / output
+    };
+    let vco_ck_target = output * p;
+
+    if p > 128 || vco_ck_target < vco_min || vco_ck_target > vco_max {
+        return None;
+    }
+
+    // Input divisor, resulting in a reference clock in the range
+    // 1 to 2 MHz. Choose the highest reference clock (lowest m)
+    let m = (pllsrc + 1_999_999) / 2_000_000;
+    if m >= 64 {
+        return None;
+    }
+
+    // Calculate resulting reference clock
+    let ref_ck = pllsrc / m;
+    if ref_ck < 1_000_000 || ref_ck > 2_000_000 {
+        return None;
+    }
+
+    // Feedback divider. Integer only
+    let n = vco_ck_target / ref_ck;
+    if n < 4 || n > 512 {
+        return None;
+    }
+
+    let vco_ck = ref_ck * n;
+
+    Some(PllDividers {
+        m,
+        n,
+        p,
+        ref_ck,
+        vco_ck,
+        p_ck: vco_ck / p,
+    })
+}
+
+/// Expands to `true` for PLL1, which is set up with an identifier for its
+/// P divider
+macro_rules! is_pll1 {
+    () => {
+        false
+    };
+    ($pll1_p:ident) => {
+        true
+    };
+}
+
 /// Setup PFD input frequency and VCO output frequency
 ///
 macro_rules! vco_setup {
     // Normal: VCOL, highest PFD frequency, highest VCO frequency
     (NORMAL: $pllsrc:ident, $output:ident,
      $rcc:ident, $pllXvcosel:ident, $pllXrge:ident $(,$pll1_p:ident)*) => {{
-         // VCO output frequency. Choose the highest VCO frequency
-         let (vco_min, vco_max) = (150_000_000, 420_000_000);
+         let is_pll1 = is_pll1!($($pll1_p)*);
 
-         let (vco_ck_target, pll_x_p) = {
-             vco_output_divider_setup! { $output, vco_min, vco_max $(, $pll1_p)* }
-         };
+         // Highest PFD frequency, highest VCO frequency
+         let dividers = pll_dividers($pllsrc, $output, is_pll1)
+             .expect("PLL output frequency not achievable");
 
-         // Input divisor, resulting in a reference clock in the range
-         // 1 to 2 MHz. Choose the highest reference clock (lowest m)
-         let pll_x_m = ($pllsrc + 1_999_999) / 2_000_000;
-
-         assert!(pll_x_m < 64);
-
-         // Calculate resulting reference clock
-         let ref_x_ck = $pllsrc / pll_x_m;
-         assert!((1_000_000..=2_000_000).contains(&ref_x_ck));
+         let pll_x_m = dividers.m;
+         let pll_x_p = dividers.p;
+         let ref_x_ck = dividers.ref_ck;
+         let vco_ck_target = $output * pll_x_p;
 
          // Configure VCO
          $rcc.pllcfgr.modify(|_, w| {
@@ -117,10 +219,18 @@ macro_rules! vco_setup {
     (ITERATIVE: $pllsrc:ident, $output:ident,
      $rcc:ident, $pllXvcosel:ident, $pllXrge:ident $(,$pll1_p:ident)*) => {{
          // VCO output frequency limits
-         #[cfg(all(not(feature = "rm0455"), not(feature = "revision_v")))]
+         #[cfg(all(
+             any(feature = "rm0433", feature = "rm0399"),
+             not(feature = "revision_v")
+         ))]
          let (vco_min, vco_max) = (192_000_000, 836_000_000);
-         #[cfg(all(not(feature = "rm0455"), feature = "revision_v"))]
+         #[cfg(all(
+             any(feature = "rm0433", feature = "rm0399"),
+             feature = "revision_v"
+         ))]
          let (vco_min, vco_max) = (192_000_000, 960_000_000);
+         #[cfg(feature = "rm0468")]
+         let (vco_min, vco_max) = (192_000_000, 836_000_000);
          #[cfg(feature = "rm0455")]
          let (vco_min, vco_max) = (128_000_000, 560_000_000);
 
@@ -353,7 +463,7 @@ fn calc_ck_div(
 /// ref_clk - Frequency at the PFD input
 /// pll_n - Integer-N part of the divider
 /// pll_fracn - Fractional-N part of the divider
-fn calc_vco_ck(ref_ck: u32, pll_n: u32, pll_fracn: u16) -> u32 {
+pub(super) fn calc_vco_ck(ref_ck: u32, pll_n: u32, pll_fracn: u16) -> u32 {
     (ref_ck as f32 * (pll_n as f32 + (pll_fracn as f32 / FRACN_DIVISOR))) as u32
 }
 
@@ -386,7 +496,8 @@ impl Rcc {
 #[cfg(test)]
 mod tests {
     use crate::rcc::pll::{
-        calc_ck_div, calc_fracn, calc_vco_ck, PllConfigStrategy,
+        calc_ck_div, calc_fracn, calc_vco_ck, pll_dividers, PllConfigStrategy,
+        PllDividers,
     };
 
     macro_rules! dummy_method {
@@ -752,4 +863,30 @@ mod tests {
         println!();
         assert!(output_r >= pll_r_target as f32);
     }
+
+    #[test]
+    /// Test compile-time PLL divider calculation
+    fn pll_dividers_const() {
+        const PLL1: Option<PllDividers> =
+            pll_dividers(25_000_000, 400_000_000, true);
+
+        let d = PLL1.unwrap();
+        println!("{:?}", d);
+        assert_eq!(d.m, 13);
+        assert_eq!(d.p, 1);
+        assert!((1_000_000..=2_000_000).contains(&d.ref_ck));
+        assert!(f32::abs(d.p_ck as f32 - 400e6) < 400e6 / 100.0);
+
+        // PLL1 P divider must be even or unity
+        let d = pll_dividers(25_000_000, 100_000_000, true).unwrap();
+        assert_eq!(d.p, 4);
+        let d = pll_dividers(25_000_000, 100_000_000, false).unwrap();
+        assert_eq!(d.p, 4);
+        let d = pll_dividers(25_000_000, 70_000_000, false).unwrap();
+        assert_eq!(d.p, 6);
+
+        // Not achievable
+        assert_eq!(pll_dividers(25_000_000, 1_000_000, true), None);
+        assert_eq!(pll_dividers(0, 400_000_000, true), None);
+    }
 }
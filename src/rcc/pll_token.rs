@@ -0,0 +1,133 @@
+//! Tokens proving that a PLL output is running
+//!
+//! Many kernel clock multiplexers can select a PLL output, but the output
+//! only runs if it was requested from `freeze`. A token for each PLL output
+//! can only be obtained from [`CoreClocks`] if the output is running, and
+//! the `kernel_*_mux_pll` methods of the peripherals take the token to
+//! select the output.
+//!
+//! ```
+//! let ccdr = rcc.pll3_p_ck(100.mhz()).pll3_r_ck(50.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! // Panics early, instead of the I2C running from a stopped clock
+//! let pll3_r = ccdr.clocks.pll3_r_token().expect("pll3_r_ck not running");
+//!
+//! ccdr.peripheral.kernel_i2c123_clk_mux_pll(pll3_r);
+//! ```
+//!
+//! The tokens are an opt-in check. The `kernel_*_mux` methods can still
+//! select any source, including a stopped PLL output, and the peripheral
+//! constructors do not take a token. Instead the constructors check the
+//! kernel clock when they are called, and panic or return an error if it
+//! is not running.
+
+use super::{rec, CoreClocks};
+use crate::time::Hertz;
+
+macro_rules! pll_token {
+    ($($Token:ident: ($token:ident, $pll_ck:ident, $doc:expr),)+) => {
+        $(
+            /// Token proving that
+            #[doc=$doc]
+            /// is running
+            #[derive(Copy, Clone, Debug)]
+            pub struct $Token {
+                frequency: Hertz,
+            }
+
+            impl $Token {
+                /// Returns the frequency of the PLL output
+                pub fn frequency(&self) -> Hertz {
+                    self.frequency
+                }
+            }
+        )+
+
+        impl CoreClocks {
+            $(
+                /// Returns a token if
+                #[doc=$doc]
+                /// is running, otherwise `None`
+                pub fn $token(&self) -> Option<$Token> {
+                    self.$pll_ck.map(|frequency| $Token { frequency })
+                }
+            )+
+        }
+    };
+}
+
+pll_token! {
+    Pll1Q: (pll1_q_token, pll1_q_ck, "pll1_q_ck"),
+    Pll1R: (pll1_r_token, pll1_r_ck, "pll1_r_ck"),
+    Pll2P: (pll2_p_token, pll2_p_ck, "pll2_p_ck"),
+    Pll2Q: (pll2_q_token, pll2_q_ck, "pll2_q_ck"),
+    Pll2R: (pll2_r_token, pll2_r_ck, "pll2_r_ck"),
+    Pll3P: (pll3_p_token, pll3_p_ck, "pll3_p_ck"),
+    Pll3Q: (pll3_q_token, pll3_q_ck, "pll3_q_ck"),
+    Pll3R: (pll3_r_token, pll3_r_ck, "pll3_r_ck"),
+}
+
+/// A PLL output that can be selected by kernel clock multiplexers of type
+/// `SEL`
+pub trait PllSource<SEL> {
+    /// Returns the selection of this PLL output
+    fn sel(&self) -> SEL;
+}
+
+macro_rules! pll_source {
+    ($($SEL:ident: [$($Token:ident: $Variant:ident),+],)+) => {
+        $(
+            $(
+                impl PllSource<rec::$SEL> for $Token {
+                    fn sel(&self) -> rec::$SEL {
+                        rec::$SEL::$Variant
+                    }
+                }
+            )+
+        )+
+    };
+}
+
+pll_source! {
+    AdcClkSel: [Pll2P: PLL2_P, Pll3R: PLL3_R],
+    FdcanClkSel: [Pll1Q: PLL1_Q, Pll2Q: PLL2_Q],
+    FmcClkSel: [Pll1Q: PLL1_Q, Pll2R: PLL2_R],
+    I2c4ClkSel: [Pll3R: PLL3_R],
+    Lptim1ClkSel: [Pll2P: PLL2_P, Pll3R: PLL3_R],
+    Lptim2ClkSel: [Pll2P: PLL2_P, Pll3R: PLL3_R],
+    RngClkSel: [Pll1Q: PLL1_Q],
+    Sai1ClkSel: [Pll1Q: PLL1_Q, Pll2P: PLL2_P, Pll3P: PLL3_P],
+    SdmmcClkSel: [Pll1Q: PLL1_Q, Pll2R: PLL2_R],
+    Spi123ClkSel: [Pll1Q: PLL1_Q, Pll2P: PLL2_P, Pll3P: PLL3_P],
+    Spi45ClkSel: [Pll2Q: PLL2_Q, Pll3Q: PLL3_Q],
+    Spi6ClkSel: [Pll2Q: PLL2_Q, Pll3Q: PLL3_Q],
+    Usart234578ClkSel: [Pll2Q: PLL2_Q, Pll3Q: PLL3_Q],
+    UsbClkSel: [Pll1Q: PLL1_Q, Pll3Q: PLL3_Q],
+}
+
+#[cfg(not(feature = "rm0468"))]
+pll_source! {
+    I2c123ClkSel: [Pll3R: PLL3_R],
+}
+
+#[cfg(feature = "rm0468")]
+pll_source! {
+    I2c1235ClkSel: [Pll3R: PLL3_R],
+}
+
+#[cfg(not(feature = "rm0455"))]
+pll_source! {
+    Lptim345ClkSel: [Pll2P: PLL2_P, Pll3R: PLL3_R],
+}
+
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pll_source! {
+    QspiClkSel: [Pll1Q: PLL1_Q, Pll2R: PLL2_R],
+    Usart16ClkSel: [Pll2Q: PLL2_Q, Pll3Q: PLL3_Q],
+}
+
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+pll_source! {
+    OctospiClkSel: [Pll1Q: PLL1_Q, Pll2R: PLL2_R],
+    Usart16910ClkSel: [Pll2Q: PLL2_Q, Pll3Q: PLL3_Q],
+}
@@ -0,0 +1,236 @@
+//! Low-power Run mode profiles
+//!
+//! A [`LowPowerProfile`] is a curated set of clock and voltage settings
+//! that reduces the current consumption in Run mode, without having to
+//! work through the reference manual. Each profile
+//!
+//! - Switches `sys_ck` to an internal oscillator
+//! - Reduces the AHB and APB prescalers
+//! - Lowers `VCORE` to VOS3, with flash wait states to match
+//! - Places the flash in low-power mode when the D1 domain is in DStop
+//! - Stops PLL1 if none of its outputs are still required
+//!
+//! The profile is entered at runtime by calling
+//! [`enter_low_power_run`](super::Ccdr::enter_low_power_run) and exited by
+//! calling [`exit_low_power_run`](super::Ccdr::exit_low_power_run), which
+//! restores the configuration from `freeze`.
+//!
+//! ```no_run
+//! use stm32h7xx_hal::{pac, prelude::*, rcc::LowPowerProfile};
+//!
+//! let dp = pac::Peripherals::take().unwrap();
+//! let pwrcfg = dp.PWR.constrain().freeze();
+//! let mut ccdr = dp.RCC.constrain().sys_ck(400.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! ccdr.enter_low_power_run(LowPowerProfile::Hsi64);
+//! assert_eq!(ccdr.clocks.sys_ck().0, 64_000_000);
+//!
+//! // ...
+//!
+//! ccdr.exit_low_power_run();
+//! assert_eq!(ccdr.clocks.sys_ck().0, 400_000_000);
+//! ```
+//!
+//! # Peripherals
+//!
+//! Peripheral drivers calculate their dividers from a copy of
+//! [`CoreClocks`] when they are created. If a peripheral's bus clock
+//! changes, that peripheral must be re-initialised with the updated
+//! `ccdr.clocks` before it is used. Peripherals with a kernel clock that
+//! does not depend on `sys_ck` or the bus prescalers are not affected.
+//!
+//! Since PLL1 is stopped if `pll1_q_ck` was not configured, the trace clock
+//! `pll1_r_ck` is not available in low-power profiles.
+
+use super::{Ccdr, CoreClocks, Rcc, Voltage, CSI, HPRE, HSI, SW};
+use crate::pwr::voltage_scaling_transition;
+use crate::stm32::PWR;
+use crate::time::Hertz;
+
+/// Low-power Run mode profiles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LowPowerProfile {
+    /// `sys_ck` from HSI at 64MHz. `hclk` is 32MHz and `pclk1` - `pclk4`
+    /// are 16MHz
+    Hsi64,
+    /// `sys_ck` from CSI at 4MHz. `hclk` and `pclk1` - `pclk4` are 4MHz
+    Csi4,
+}
+
+impl LowPowerProfile {
+    /// Returns (sys_ck, sw, hpre, hpre_div, ppre_bits, ppre)
+    fn settings(self) -> (u32, SW, HPRE, u32, u8, u8) {
+        match self {
+            LowPowerProfile::Hsi64 => (HSI, SW::HSI, HPRE::DIV2, 2, 0b100, 2),
+            LowPowerProfile::Csi4 => (CSI, SW::CSI, HPRE::DIV1, 1, 0b000, 1),
+        }
+    }
+}
+
+/// Configuration saved when entering a low-power profile
+pub(super) struct SavedRunMode {
+    clocks: CoreClocks,
+    vos: Voltage,
+    d1cfgr: u32,
+    d2cfgr: u32,
+    d3cfgr: u32,
+    sw: u8,
+    pll1_on: bool,
+}
+
+impl Ccdr {
+    /// Enters the low-power Run mode `profile`
+    ///
+    /// The frequencies in `self.clocks` are updated to reflect the new
+    /// configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a low-power profile is already active. On RM0433 and
+    /// RM0399 parts, panics if VOS0 was selected, as overdrive must remain
+    /// enabled until the next reset
+    pub fn enter_low_power_run(&mut self, profile: LowPowerProfile) {
+        assert!(
+            self.low_power.is_none(),
+            "A low-power profile is already active"
+        );
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        assert!(
+            self.vos != Voltage::Scale0,
+            "Low-power profiles cannot be used with overdrive (VOS0)"
+        );
+
+        let rcc = &self.rb;
+        // unsafe: Only we modify D3CR and CR1.FLPS after freeze
+        let pwr = unsafe { &*PWR::ptr() };
+
+        let (sys_ck, sw, hpre, hpre_div, ppre_bits, ppre) = profile.settings();
+
+        let saved = SavedRunMode {
+            clocks: self.clocks,
+            vos: self.vos,
+            d1cfgr: rcc.d1cfgr.read().bits(),
+            d2cfgr: rcc.d2cfgr.read().bits(),
+            d3cfgr: rcc.d3cfgr.read().bits(),
+            sw: rcc.cfgr.read().sw().bits(),
+            pll1_on: rcc.cr.read().pll1on().is_on(),
+        };
+
+        // Ensure the oscillator is on and stable
+        match profile {
+            LowPowerProfile::Hsi64 => {
+                rcc.cr.modify(|_, w| w.hsion().on());
+                while rcc.cr.read().hsirdy().is_not_ready() {}
+            }
+            LowPowerProfile::Csi4 => {
+                rcc.cr.modify(|_, w| w.csion().on());
+                while rcc.cr.read().csirdy().is_not_ready() {}
+            }
+        }
+
+        // Reduce the clock frequency first
+        let sw = sw as u8;
+        rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(sw) });
+        while rcc.cfgr.read().sws().bits() != sw {}
+
+        // Core Prescaler / AHB Prescaler / APB3 Prescaler
+        rcc.d1cfgr.modify(|_, w| unsafe {
+            w.d1cpre()
+                .bits(0)
+                .d1ppre()
+                .bits(ppre_bits)
+                .hpre()
+                .variant(hpre)
+        });
+        while rcc.d1cfgr.read().d1cpre().bits() != 0 {}
+
+        // APB1 / APB2 Prescaler
+        rcc.d2cfgr.modify(|_, w| unsafe {
+            w.d2ppre1().bits(ppre_bits).d2ppre2().bits(ppre_bits)
+        });
+
+        // APB4 Prescaler
+        rcc.d3cfgr
+            .modify(|_, w| unsafe { w.d3ppre().bits(ppre_bits) });
+
+        let hclk = sys_ck / hpre_div;
+        let pclk = hclk / u32::from(ppre);
+
+        // Flash wait states for the new clock at VOS3, then lower VCORE
+        Rcc::flash_setup(hclk, Voltage::Scale3);
+        voltage_scaling_transition(pwr, Voltage::Scale3);
+
+        // Flash in low-power mode when D1 enters DStop
+        pwr.cr1.modify(|_, w| w.flps().set_bit());
+
+        // Stop PLL1 if pll1_q_ck is not used
+        if self.clocks.pll1_q_ck.is_none() {
+            rcc.cr.modify(|_, w| w.pll1on().off());
+            self.clocks.pll1_p_ck = None;
+            self.clocks.pll1_r_ck = None;
+        }
+
+        let pclk = Hertz(pclk);
+        self.clocks.sys_ck = Hertz(sys_ck);
+        self.clocks.c_ck = Hertz(sys_ck);
+        self.clocks.hclk = Hertz(hclk);
+        self.clocks.pclk1 = pclk;
+        self.clocks.pclk2 = pclk;
+        self.clocks.pclk3 = pclk;
+        self.clocks.pclk4 = pclk;
+        self.clocks.ppre1 = ppre;
+        self.clocks.ppre2 = ppre;
+        self.clocks.ppre3 = ppre;
+        self.clocks.ppre4 = ppre;
+        // APB prescaler is 1 or 2, so the timer kernel clocks are hclk
+        self.clocks.timx_ker_ck = Hertz(hclk);
+        self.clocks.timy_ker_ck = Hertz(hclk);
+
+        self.vos = Voltage::Scale3;
+        self.low_power = Some(saved);
+    }
+
+    /// Exits a low-power Run mode profile, restoring the clock and voltage
+    /// configuration from before
+    /// [`enter_low_power_run`](Ccdr::enter_low_power_run)
+    ///
+    /// Does nothing if no low-power profile is active
+    pub fn exit_low_power_run(&mut self) {
+        let saved = match self.low_power.take() {
+            Some(saved) => saved,
+            None => return,
+        };
+
+        let rcc = &self.rb;
+        // unsafe: Only we modify D3CR and CR1.FLPS after freeze
+        let pwr = unsafe { &*PWR::ptr() };
+
+        // Raise VCORE first, then flash wait states for the original clock
+        voltage_scaling_transition(pwr, saved.vos);
+        Rcc::flash_setup(saved.clocks.aclk().0, saved.vos);
+        pwr.cr1.modify(|_, w| w.flps().clear_bit());
+
+        // Restart PLL1 if it was stopped
+        if saved.pll1_on && rcc.cr.read().pll1on().is_off() {
+            rcc.cr.modify(|_, w| w.pll1on().on());
+            while rcc.cr.read().pll1rdy().is_not_ready() {}
+        }
+
+        // Restore prescalers
+        rcc.d1cfgr.write(|w| unsafe { w.bits(saved.d1cfgr) });
+        rcc.d2cfgr.write(|w| unsafe { w.bits(saved.d2cfgr) });
+        rcc.d3cfgr.write(|w| unsafe { w.bits(saved.d3cfgr) });
+
+        // Restore system clock source
+        rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(saved.sw) });
+        while rcc.cfgr.read().sws().bits() != saved.sw {}
+
+        self.clocks = saved.clocks;
+        self.vos = saved.vos;
+    }
+
+    /// Returns `true` if a low-power Run mode profile is active
+    pub fn is_low_power_run(&self) -> bool {
+        self.low_power.is_some()
+    }
+}
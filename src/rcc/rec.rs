@@ -17,6 +17,36 @@
 //! [`PeripheralRec`](struct.PeripheralREC.html) itself. These methods are named
 //! `kernel_xxxx_clk_mux()`.
 //!
+//! Both kinds of multiplexer also have a `_mux_pll` variant, which selects a
+//! PLL output only if given a token proving that the output is running. The
+//! variant is optional, the plain methods are not restricted. See
+//! [`pll_token`](super::pll_token).
+//!
+//! # Dual core
+//!
+//! On dual core parts, the Cortex-M7 configures the clocks with `freeze`.
+//! Tokens for the peripherals used by the Cortex-M4 are given up by the
+//! Cortex-M7 with `split_for_cm4`, and taken by the Cortex-M4 with
+//! `take_for_cm4`. Both cores must use the same type for the set of
+//! tokens, so that each peripheral has a driver on only one core:
+//!
+//! ```
+//! // Shared between both cores
+//! type Cm4Peripherals = (rec::Fdcan, rec::Spi1);
+//!
+//! // Cortex-M7
+//! let ccdr = rcc.freeze(pwrcfg, &dp.SYSCFG);
+//! rec::split_for_cm4::<Cm4Peripherals>((
+//!     ccdr.peripheral.FDCAN,
+//!     ccdr.peripheral.SPI1,
+//! ));
+//!
+//! // Cortex-M4, after the Cortex-M7 has signalled with a hardware
+//! // semaphore that the tokens are given up
+//! let (fdcan, spi1) =
+//!     unsafe { rec::take_for_cm4::<Cm4Peripherals>() }.unwrap();
+//! ```
+//!
 //! # Reset/Enable Example
 //!
 //! ```
@@ -57,12 +87,29 @@
 //! // Can't set group kernel clock (it would also affect I2C3)
 //! // ccdr.peripheral.kernel_i2c123_clk_mux(I2c123ClkSel::HSI_KER);
 //! ```
+//!
+//! # Power Report
+//!
+//! With the `power-report` feature, [`power_report`] lists every peripheral
+//! that still has its clock enabled, for example before entering a low-power
+//! mode. Peripherals whose [`LowPowerMode`] does not match the intended
+//! [`StopDepth`] are flagged.
+//!
+//! ```
+//! for p in rec::power_report() {
+//!     info!("{} on {}, kernel clock {:?}", p.name, p.bus, p.kernel_clock);
+//!     if let Some(issue) = p.issue(StopDepth::System) {
+//!         warn!("{}: {:?}", p.name, issue);
+//!     }
+//! }
+//! ```
 #![deny(missing_docs)]
 
 use core::marker::PhantomData;
 
-use super::Rcc;
+use super::{lse_ck, CoreClocks, PllSource, Rcc};
 use crate::stm32::{rcc, RCC};
+use crate::time::Hertz;
 use cortex_m::interrupt;
 
 /// A trait for Resetting, Enabling and Disabling a single peripheral
@@ -75,10 +122,76 @@ pub trait ResetEnable {
     fn reset(self) -> Self;
 }
 
+/// A set of peripheral tokens. Implemented for each token, and for tuples
+/// of up to 8 tokens
+pub trait PeripheralSet: Sized {
+    /// Creates the tokens
+    ///
+    /// # Safety
+    ///
+    /// Only one instance of each token may exist, across both cores
+    #[doc(hidden)]
+    unsafe fn steal() -> Self;
+}
+
+macro_rules! peripheral_set_tuple {
+    ($(($($P:ident),+),)+) => {
+        $(
+            impl<$($P: PeripheralSet),+> PeripheralSet for ($($P,)+) {
+                unsafe fn steal() -> Self {
+                    ($($P::steal(),)+)
+                }
+            }
+        )+
+    };
+}
+
+peripheral_set_tuple! {
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+}
+
+/// Gives up the peripheral tokens in `set`, so that they can be taken by
+/// the Cortex-M4 with `take_for_cm4`. See the [module level
+/// documentation](self)
+#[cfg(all(feature = "rm0399", not(feature = "cm4")))]
+pub fn split_for_cm4<S: PeripheralSet>(set: S) {
+    // The tokens have no state: forgetting them is enough
+    core::mem::forget(set);
+}
+
+/// Takes the peripheral tokens in `S`, which were given up by the
+/// Cortex-M7 with `split_for_cm4`. Returns `None` if called more than once
+///
+/// # Safety
+///
+/// The Cortex-M7 must have given up every token in `S` with
+/// `split_for_cm4` before this is called, for example by signalling with a
+/// hardware semaphore after the call. Otherwise the same peripheral can be
+/// driven from both cores.
+#[cfg(all(feature = "rm0399", feature = "cm4"))]
+pub unsafe fn take_for_cm4<S: PeripheralSet>() -> Option<S> {
+    use core::sync::atomic::{AtomicBool, Ordering};
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+
+    if TAKEN.swap(true, Ordering::AcqRel) {
+        None
+    } else {
+        // The caller guarantees that the Cortex-M7 has given up these tokens
+        Some(S::steal())
+    }
+}
+
 /// The clock gating state of a peripheral in low-power mode
 ///
 /// See RM0433 rev 7. Section 8.5.11
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum LowPowerMode {
     /// Kernel and bus interface clocks are not provided in low-power modes.
     Off,
@@ -95,6 +208,96 @@ impl Default for LowPowerMode {
     }
 }
 
+/// The intended depth of a Stop mode, see [`power_report`]
+#[cfg(feature = "power-report")]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum StopDepth {
+    /// D1 and D2 in DStop, with autonomous peripherals in D3 / SRD still
+    /// running
+    Autonomous,
+    /// The whole system in Stop mode
+    System,
+}
+
+/// A peripheral configuration that is inconsistent with a [`StopDepth`]
+#[cfg(feature = "power-report")]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PowerIssue {
+    /// The peripheral is in autonomous mode, which keeps the D3 / SRD
+    /// domain running and prevents the system from entering Stop
+    PreventsStop,
+    /// The peripheral could run autonomously in D3 / SRD, but is not in
+    /// autonomous mode so its clocks stop
+    NotAutonomous,
+}
+
+/// The clock state of an enabled peripheral, see [`power_report`]
+#[cfg(feature = "power-report")]
+#[derive(Copy, Clone, Debug)]
+pub struct PeripheralPower {
+    /// Name of the peripheral
+    pub name: &'static str,
+    /// Bus the peripheral is connected to
+    pub bus: &'static str,
+    /// Kernel clock multiplexer of the peripheral, if any
+    pub kernel_clock: Option<&'static str>,
+    /// Low power mode of the peripheral
+    pub low_power: LowPowerMode,
+    /// The peripheral can run autonomously in D3 / SRD
+    pub autonomous_capable: bool,
+    enabled: bool,
+}
+
+#[cfg(feature = "power-report")]
+impl PeripheralPower {
+    /// Returns the issue, if any, with the low power mode of this
+    /// peripheral in Stop mode with `depth`
+    pub fn issue(&self, depth: StopDepth) -> Option<PowerIssue> {
+        match (depth, self.low_power) {
+            (StopDepth::System, LowPowerMode::Autonomous) => {
+                Some(PowerIssue::PreventsStop)
+            }
+            (StopDepth::Autonomous, LowPowerMode::Enabled)
+                if self.autonomous_capable =>
+            {
+                Some(PowerIssue::NotAutonomous)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns every peripheral that has its clock enabled
+///
+/// The clock enable, low power enable and autonomous mode registers are
+/// read directly, so peripherals enabled outside of this crate are included
+#[cfg(feature = "power-report")]
+pub fn power_report() -> impl Iterator<Item = PeripheralPower> {
+    POWER_TABLE.iter().map(|p| p()).filter(|p| p.enabled)
+}
+
+// Whether a peripheral can run autonomously in D3 / SRD
+#[cfg(feature = "power-report")]
+macro_rules! power_autonomous {
+    () => {
+        false
+    };
+    ($Auto:ident) => {
+        true
+    };
+}
+
+// The kernel clock of a peripheral, if any
+#[cfg(feature = "power-report")]
+macro_rules! power_kernel_clock {
+    () => {
+        None
+    };
+    ($clk:expr) => {
+        Some($clk)
+    };
+}
+
 impl Rcc {
     /// Returns all the peripherals resets / enables / kernel clocks.
     ///
@@ -116,6 +319,267 @@ impl Rcc {
     }
 }
 
+/// The selection and resolved frequency of a kernel clock multiplexer
+#[derive(Clone, Copy, Debug)]
+pub struct KernelClock<T> {
+    /// Kernel clock source selection
+    pub sel: T,
+    /// Frequency of the selected source. `None` if the source is not
+    /// running, or if its frequency is not known (for example I2S_CKIN)
+    pub freq: Option<Hertz>,
+}
+
+impl<T> KernelClock<T> {
+    fn new(sel: T) -> Self {
+        KernelClock { sel, freq: None }
+    }
+}
+
+// Resolves the frequency of each kernel clock in a snapshot from the typed
+// selection. Multiplexers with partially specified fields are marked
+// (Variant), and reserved values resolve to `None`
+macro_rules! kernel_clock_frequencies {
+    (@sel $sel:expr,) => { Some($sel) };
+    (@sel $sel:expr, Variant) => {
+        match $sel {
+            stm32h7::Variant::Val(sel) => Some(sel),
+            stm32h7::Variant::Res(_) => None,
+        }
+    };
+    ($snapshot:ident: $(
+        $(#[$meta:meta])* $field:ident: $Sel:ident $(($Variant:ident))* {
+            $($source:ident => $freq:expr,)+
+        }
+    )+) => {
+        $(
+            $(#[$meta])*
+            {
+                let clock = &mut $snapshot.$field;
+                #[allow(unreachable_patterns)]
+                let freq = match kernel_clock_frequencies!(
+                    @sel clock.sel, $($Variant)*
+                ) {
+                    $(Some($Sel::$source) => $freq,)+
+                    _ => None,
+                };
+                clock.freq = freq;
+            }
+        )+
+    };
+}
+
+/// Fills in the frequency of the selected source of every kernel clock in
+/// `snapshot`. Sources with an unknown frequency, such as I2S_CKIN, are
+/// left as `None`
+fn resolve_kernel_clocks(
+    snapshot: &mut KernelClockSnapshot,
+    clocks: &CoreClocks,
+) {
+    kernel_clock_frequencies! { snapshot:
+        usb: UsbClkSel {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI48 => clocks.hsi48_ck(),
+        }
+        adc: AdcClkSel(Variant) {
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_R => clocks.pll3_r_ck(),
+            PER => clocks.per_ck(),
+        }
+        rng: RngClkSel {
+            HSI48 => clocks.hsi48_ck(),
+            PLL1_Q => clocks.pll1_q_ck(),
+            LSI => clocks.lsi_ck(),
+            LSE => lse_ck(),
+        }
+        sdmmc: SdmmcClkSel {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_R => clocks.pll2_r_ck(),
+        }
+        fmc: FmcClkSel {
+            RCC_HCLK3 => Some(clocks.hclk()),
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_R => clocks.pll2_r_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        qspi: QspiClkSel {
+            RCC_HCLK3 => Some(clocks.hclk()),
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_R => clocks.pll2_r_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        octospi: OctospiClkSel {
+            RCC_HCLK3 => Some(clocks.hclk()),
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_R => clocks.pll2_r_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(not(feature = "rm0468"))]
+        i2c123: I2c123ClkSel {
+            RCC_PCLK1 => Some(clocks.pclk1()),
+            PLL3_R => clocks.pll3_r_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+        }
+        #[cfg(feature = "rm0468")]
+        i2c1235: I2c1235ClkSel {
+            RCC_PCLK1 => Some(clocks.pclk1()),
+            PLL3_R => clocks.pll3_r_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+        }
+        cec: CecClkSel(Variant) {
+            LSI => clocks.lsi_ck(),
+            // CEC is clocked from csi_ker_ck / 122
+            CSI_KER => clocks.csi_ck().map(|f| Hertz(f.0 / 122)),
+            LSE => lse_ck(),
+        }
+        lptim1: Lptim1ClkSel(Variant) {
+            RCC_PCLK1 => Some(clocks.pclk1()),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_R => clocks.pll3_r_ck(),
+            LSI => clocks.lsi_ck(),
+            PER => clocks.per_ck(),
+            LSE => lse_ck(),
+        }
+        usart234578: Usart234578ClkSel(Variant) {
+            RCC_PCLK1 => Some(clocks.pclk1()),
+            PLL2_Q => clocks.pll2_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+            LSE => lse_ck(),
+        }
+        fdcan: FdcanClkSel(Variant) {
+            HSE => clocks.hse_ck(),
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_Q => clocks.pll2_q_ck(),
+        }
+        swp: SwpClkSel {
+            PCLK => Some(clocks.pclk1()),
+            HSI_KER => clocks.hsi_ck(),
+        }
+        dfsdm1: Dfsdm1ClkSel {
+            RCC_PCLK2 => Some(clocks.pclk2()),
+            SYS => Some(clocks.sys_ck()),
+        }
+        sai1: Sai1ClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        sai23: Sai23ClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(feature = "rm0455")]
+        sai2a: Sai2AClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(feature = "rm0455")]
+        sai2b: Sai2BClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+        spi123: Spi123ClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+        spi45: Spi45ClkSel(Variant) {
+            APB => Some(clocks.pclk2()),
+            PLL2_Q => clocks.pll2_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+            HSE => clocks.hse_ck(),
+        }
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        usart16: Usart16ClkSel(Variant) {
+            RCC_PCLK2 => Some(clocks.pclk2()),
+            PLL2_Q => clocks.pll2_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+            LSE => lse_ck(),
+        }
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        usart16910: Usart16910ClkSel(Variant) {
+            RCC_PCLK2 => Some(clocks.pclk2()),
+            PLL2_Q => clocks.pll2_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+            LSE => lse_ck(),
+        }
+        lptim2: Lptim2ClkSel(Variant) {
+            RCC_PCLK4 => Some(clocks.pclk4()),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_R => clocks.pll3_r_ck(),
+            LSI => clocks.lsi_ck(),
+            PER => clocks.per_ck(),
+            LSE => lse_ck(),
+        }
+        #[cfg(not(feature = "rm0455"))]
+        lptim345: Lptim345ClkSel(Variant) {
+            RCC_PCLK4 => Some(clocks.pclk4()),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_R => clocks.pll3_r_ck(),
+            LSI => clocks.lsi_ck(),
+            PER => clocks.per_ck(),
+            LSE => lse_ck(),
+        }
+        i2c4: I2c4ClkSel {
+            RCC_PCLK4 => Some(clocks.pclk4()),
+            PLL3_R => clocks.pll3_r_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+        }
+        lpuart1: Lpuart1ClkSel(Variant) {
+            RCC_PCLK4 => Some(clocks.pclk4()),
+            PLL2_Q => clocks.pll2_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+            LSE => lse_ck(),
+        }
+        spi6: Spi6ClkSel(Variant) {
+            RCC_PCLK4 => Some(clocks.pclk4()),
+            PLL2_Q => clocks.pll2_q_ck(),
+            PLL3_Q => clocks.pll3_q_ck(),
+            HSI_KER => clocks.hsi_ck(),
+            CSI_KER => clocks.csi_ck(),
+            HSE => clocks.hse_ck(),
+        }
+        #[cfg(not(feature = "rm0455"))]
+        sai4a: Sai4AClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+        #[cfg(not(feature = "rm0455"))]
+        sai4b: Sai4BClkSel(Variant) {
+            PLL1_Q => clocks.pll1_q_ck(),
+            PLL2_P => clocks.pll2_p_ck(),
+            PLL3_P => clocks.pll3_p_ck(),
+            PER => clocks.per_ck(),
+        }
+    }
+}
+
 // This macro uses the paste::item! macro to create identifiers.
 //
 // https://crates.io/crates/paste
@@ -164,7 +628,86 @@ macro_rules! peripheral_reset_and_enable_control {
                         )+
                     }
                 }
+
+                /// Returns the current selection of every kernel clock
+                /// multiplexer, together with the frequency of the selected
+                /// source
+                ///
+                /// ```
+                /// let ccdr = ...; // From RCC
+                ///
+                /// let snapshot = ccdr.peripheral.kernel_clock_snapshot(&ccdr.clocks);
+                /// assert_eq!(snapshot.sdmmc.freq, ccdr.clocks.pll1_q_ck());
+                /// ```
+                pub fn kernel_clock_snapshot(&self, clocks: &CoreClocks)
+                                             -> KernelClockSnapshot {
+                    // unsafe: We only read from these registers
+                    let rcc = unsafe { &*RCC::ptr() };
+
+                    let mut snapshot = KernelClockSnapshot {
+                        $(
+                            $(
+                                $(
+                                    #[ $tmeta ]
+                                    [< $pk:lower >]: KernelClock::new(
+                                        rcc.[< $ccip r >].read()
+                                            .[< $pk:lower sel >]().variant()
+                                    ),
+                                )*
+                                $(
+                                    $(
+                                        #[ $tmeta ]
+                                        [< $pk_g:lower >]: KernelClock::new(
+                                            rcc.[< $ccip_g r >].read()
+                                                .[< $pk_g:lower sel >]().variant()
+                                        ),
+                                    )*
+                                )*
+                            )*
+                        )+
+                    };
+                    resolve_kernel_clocks(&mut snapshot, clocks);
+                    snapshot
+                }
             }
+
+            /// Snapshot of every kernel clock multiplexer. Returned by
+            /// [kernel_clock_snapshot](crate::rcc::rec::PeripheralREC#method.kernel_clock_snapshot)
+            #[derive(Clone, Copy, Debug)]
+            #[non_exhaustive]
+            pub struct KernelClockSnapshot {
+                $(
+                    $(
+                        $(
+                            #[ $tmeta ]
+                            #[doc = $clk_doc " kernel clock"]
+                            pub [< $pk:lower >]: KernelClock<
+                                variant_return_type!([< $pk ClkSel >] $(, $Variant)*)
+                            >,
+                        )*
+                        $(
+                            $(
+                                #[ $tmeta ]
+                                #[doc = $clk_doc_g " kernel clock"]
+                                pub [< $pk_g:lower >]: KernelClock<
+                                    variant_return_type!([< $pk_g ClkSel >] $(, $Variant_g)*)
+                                >,
+                            )*
+                        )*
+                    )*
+                )+
+            }
+            /// Clock state of every peripheral
+            #[cfg(feature = "power-report")]
+            const POWER_TABLE: &[fn() -> PeripheralPower] = &[
+                $(
+                    $(
+                        #[ $tmeta ]
+                        $( #[ $pmeta ] )*
+                        $p::power_state,
+                    )*
+                )+
+            ];
             $(
                 $(
                     #[ $tmeta ]
@@ -288,6 +831,42 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                     self
                 }
             }
+            #[cfg(feature = "power-report")]
+            $( #[ $pmeta ] )*
+            impl $p {
+                /// Returns the clock state of this peripheral
+                fn power_state() -> PeripheralPower {
+                    // unsafe: We only read from these registers
+                    let rcc = unsafe { &*RCC::ptr() };
+
+                    let lpen = rcc.[< $AXBn:lower lpenr >].read()
+                        .[< $p:lower lpen >]().bit_is_set();
+                    #[allow(unused_mut)]
+                    let mut amen = false;
+                    $(
+                        let amr = unsafe { autonomous!($Auto) };
+                        amen = amr.read().[< $p:lower amen >]().bit_is_set();
+                    )*
+
+                    PeripheralPower {
+                        name: stringify!($p_upper),
+                        bus: stringify!($AXBn),
+                        kernel_clock: power_kernel_clock!(
+                            $( stringify!($pk) )*
+                            $( stringify!($pk_g) )*
+                            $( $clk_doc_f )*
+                        ),
+                        low_power: match (lpen, amen) {
+                            (_, true) => LowPowerMode::Autonomous,
+                            (true, false) => LowPowerMode::Enabled,
+                            (false, false) => LowPowerMode::Off,
+                        },
+                        autonomous_capable: power_autonomous!($( $Auto )*),
+                        enabled: rcc.[< $AXBn:lower enr >].read()
+                            .[< $p:lower en >]().bit_is_set(),
+                    }
+                }
+            }
             $( #[ $pmeta ] )*
             unsafe impl Send for $p {}
             $( #[ $pmeta ] )*
@@ -332,6 +911,12 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                 }
             }
             $( #[ $pmeta ] )*
+            impl PeripheralSet for $p {
+                unsafe fn steal() -> Self {
+                    $p { _marker: PhantomData }
+                }
+            }
+            $( #[ $pmeta ] )*
             impl $p {
                 $(      // Individual kernel clocks
                     #[inline(always)]
@@ -366,6 +951,18 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                         };
                         ccip.read().[< $pk:lower sel >]().variant()
                     }
+
+                    #[inline(always)]
+                    /// Select a PLL output as the kernel clock for
+                    #[doc=$clk_doc "."]
+                    /// `pll` is a token that proves the output is running,
+                    /// see [`pll_token`](super::pll_token)
+                    pub fn [< kernel_ $clk _mux_pll >]<PLL>(self, pll: PLL) -> Self
+                    where
+                        PLL: PllSource<[< $pk ClkSel >]>,
+                    {
+                        self.[< kernel_ $clk _mux >](pll.sel())
+                    }
                 )*
             }
             $(          // Individual kernel clocks
@@ -427,6 +1024,17 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                             });
                             self
                         }
+
+                        /// Select a PLL output as the kernel clock for
+                        #[doc=$clk_doc_g "."]
+                        /// `pll` is a token that proves the output is
+                        /// running, see [`pll_token`](super::pll_token)
+                        pub fn [< kernel_ $pk_g:lower _clk_mux_pll >]<PLL>(&mut self, pll: PLL) -> &mut Self
+                        where
+                            PLL: PllSource<[< $pk_g ClkSel >]>,
+                        {
+                            self.[< kernel_ $pk_g:lower _clk_mux >](pll.sel())
+                        }
                     )*
                 )*
             }
@@ -471,7 +1079,7 @@ peripheral_reset_and_enable_control! {
     AHB1, "AMBA High-performance Bus (AHB1) peripherals" => [
         Dma2, Dma1
     ];
-    #[cfg(not(feature = "rm0455"))]
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
     AHB1, "" => [
         Usb1Otg [group clk: Usb d2ccip2 "USB"],
         Usb2Otg [group clk: Usb],
@@ -479,6 +1087,12 @@ peripheral_reset_and_enable_control! {
         #[cfg(any(feature = "rm0399"))] Art,
         Adc12 [group clk: Adc(Variant) d3ccip "ADC"]
     ];
+    #[cfg(feature = "rm0468")]
+    AHB1, "" => [
+        Usb1Otg [group clk: Usb d2ccip2 "USB"],
+        Eth1Mac,
+        Adc12 [group clk: Adc(Variant) d3ccip "ADC"]
+    ];
     #[cfg(feature = "rm0455")]
     AHB1, "" => [
         Usb1Otg [group clk: Usb cdccip2 "USB"],
@@ -495,6 +1109,10 @@ peripheral_reset_and_enable_control! {
     AHB2, "" => [
         Rng [kernel clk: Rng d2ccip2 "RNG"]
     ];
+    #[cfg(feature = "rm0468")]
+    AHB2, "" => [
+        Cordic, Fmac
+    ];
     #[cfg(feature = "rm0455")]
     AHB2, "" => [
         Rng [kernel clk: Rng cdccip2 "RNG"]
@@ -503,18 +1121,32 @@ peripheral_reset_and_enable_control! {
 
     #[cfg(all())]
     AHB3, "AMBA High-performance Bus (AHB3) peripherals" => [
-        Jpgdec, Dma2d, Mdma
+        Dma2d, Mdma
     ];
-    #[cfg(not(feature = "rm0455"))]
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
     AHB3, "" => [
+        Jpgdec,
         Sdmmc1 [group clk: Sdmmc d1ccip "SDMMC"],
         Fmc [kernel clk: Fmc d1ccip "FMC"],
         Qspi [kernel clk: Qspi d1ccip "QUADSPI"]
     ];
+    #[cfg(feature = "rm0468")]
+    AHB3, "" => [
+        Iomngr, Otfd1, Otfd2,
+
+        Sdmmc1 [group clk: Sdmmc d1ccip "SDMMC"],
+        Fmc [kernel clk: Fmc d1ccip "FMC"],
+        Octospi1 [group clk: Octospi d1ccip "OCTOSPI"],
+        Octospi2 [group clk: Octospi]
+    ];
     #[cfg(feature = "rm0455")]
     AHB3, "" => [
+        Jpgdec, Gfxmmu, Iomngr, Otfd1, Otfd2,
+
         Sdmmc1 [group clk: Sdmmc cdccip "SDMMC"],
-        Fmc [kernel clk: Fmc cdccip "FMC"]
+        Fmc [kernel clk: Fmc cdccip "FMC"],
+        Octospi1 [group clk: Octospi cdccip "OCTOSPI"],
+        Octospi2 [group clk: Octospi]
     ];
 
 
@@ -533,9 +1165,6 @@ peripheral_reset_and_enable_control! {
 
     #[cfg(all())]
     APB1L, "Advanced Peripheral Bus 1L (APB1L) peripherals" => [
-        I2c2 [group clk: I2c123],
-        I2c3 [group clk: I2c123],
-
         Spi2 [group clk: Spi123],
         Spi3 [group clk: Spi123],
 
@@ -547,11 +1176,14 @@ peripheral_reset_and_enable_control! {
         Uart7 [group clk: Usart234578],
         Uart8 [group clk: Usart234578]
     ];
-    #[cfg(not(feature = "rm0455"))]
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
     APB1L, "" => [
         Dac12,
 
         I2c1 [group clk: I2c123 d2ccip2 "I2C1/2/3"],
+        I2c2 [group clk: I2c123],
+        I2c3 [group clk: I2c123],
+
         Cec [kernel clk: Cec(Variant) d2ccip2 "CEC"],
         Lptim1 [kernel clk: Lptim1(Variant) d2ccip2 "LPTIM1"],
         Usart2 [group clk: Usart234578(Variant) d2ccip2 "USART2/3/4/5/7/8"]
@@ -561,10 +1193,26 @@ peripheral_reset_and_enable_control! {
         Dac1,
 
         I2c1 [group clk: I2c123 cdccip2 "I2C1/2/3"],
+        I2c2 [group clk: I2c123],
+        I2c3 [group clk: I2c123],
+
         Cec [kernel clk: Cec(Variant) cdccip2 "CEC"],
         Lptim1 [kernel clk: Lptim1(Variant) cdccip2 "LPTIM1"],
         Usart2 [group clk: Usart234578(Variant) cdccip2 "USART2/3/4/5/7/8"]
     ];
+    #[cfg(feature = "rm0468")]
+    APB1L, "" => [
+        Dac12,
+
+        I2c1 [group clk: I2c1235 d2ccip2 "I2C1/2/3/5"],
+        I2c2 [group clk: I2c1235],
+        I2c3 [group clk: I2c1235],
+        I2c5 [group clk: I2c1235],
+
+        Cec [kernel clk: Cec(Variant) d2ccip2 "CEC"],
+        Lptim1 [kernel clk: Lptim1(Variant) d2ccip2 "LPTIM1"],
+        Usart2 [group clk: Usart234578(Variant) d2ccip2 "USART2/3/4/5/7/8"]
+    ];
 
 
     #[cfg(all())]
@@ -576,6 +1224,10 @@ peripheral_reset_and_enable_control! {
         Fdcan [kernel clk: Fdcan(Variant) d2ccip1 "FDCAN"],
         Swp [kernel clk: Swp d2ccip1 "SWPMI"]
     ];
+    #[cfg(feature = "rm0468")]
+    APB1H, "" => [
+        Tim23, Tim24
+    ];
     #[cfg(feature = "rm0455")]
     APB1H, "" => [
         Fdcan [kernel clk: Fdcan(Variant) cdccip1 "FDCAN"],
@@ -585,11 +1237,12 @@ peripheral_reset_and_enable_control! {
 
     #[cfg(all())]
     APB2, "Advanced Peripheral Bus 2 (APB2) peripherals" => [
-        Hrtim,
         Tim1, Tim8, Tim15, Tim16, Tim17
     ];
-    #[cfg(not(feature = "rm0455"))]
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
     APB2, "" => [
+        Hrtim,
+
         Dfsdm1 [kernel clk: Dfsdm1 d2ccip1 "DFSDM1"],
 
         Sai1 [kernel clk: Sai1(Variant) d2ccip1 "SAI1"],
@@ -603,8 +1256,25 @@ peripheral_reset_and_enable_control! {
         Usart1 [group clk: Usart16(Variant) d2ccip2 "USART1/6"],
         Usart6 [group clk: Usart16]
     ];
+    #[cfg(feature = "rm0468")]
+    APB2, "" => [
+        Dfsdm1 [kernel clk: Dfsdm1 d2ccip1 "DFSDM1"],
+
+        Sai1 [kernel clk: Sai1(Variant) d2ccip1 "SAI1"],
+
+        Spi1 [group clk: Spi123(Variant) d2ccip1 "SPI1/2/3"],
+        Spi4 [group clk: Spi45(Variant) d2ccip1 "SPI4/5"],
+        Spi5 [group clk: Spi45],
+
+        Usart1 [group clk: Usart16910(Variant) d2ccip2 "USART1/6/9/10"],
+        Usart6 [group clk: Usart16910],
+        Uart9 [group clk: Usart16910],
+        Usart10 [group clk: Usart16910]
+    ];
     #[cfg(feature = "rm0455")]
     APB2, "" => [
+        Hrtim,
+
         Dfsdm1 [kernel clk: Dfsdm1 cdccip1 "DFSDM1"],
 
         Sai1 [kernel clk: Sai1(Variant) cdccip1 "SAI1"],
@@ -618,7 +1288,9 @@ peripheral_reset_and_enable_control! {
         Spi5 [group clk: Spi45],
 
         Usart1 [group clk: Usart16910(Variant) cdccip2 "USART1/6/9/10"],
-        Usart6 [group clk: Usart16910]
+        Usart6 [group clk: Usart16910],
+        Uart9 [group clk: Usart16910],
+        Usart10 [group clk: Usart16910]
     ];
 
 
@@ -642,12 +1314,17 @@ peripheral_reset_and_enable_control! {
         (Auto) Lptim5 [group clk: Lptim345],
 
         (Auto) I2c4 [kernel clk: I2c4 d3ccip "I2C4"],
+        (Auto) Lpuart1 [kernel clk: Lpuart1(Variant) d3ccip "LPUART1"],
         (Auto) Spi6 [kernel clk: Spi6(Variant) d3ccip "SPI6"],
         (Auto) Sai4 [kernel clk_a: Sai4A(Variant) d3ccip
             "Sub-Block A of SAI4"]
             [kernel clk_b: Sai4B(Variant) d3ccip
             "Sub-Block B of SAI4"]
     ];
+    #[cfg(feature = "rm0468")]
+    APB4, "" => [
+        Dts
+    ];
     #[cfg(feature = "rm0455")]
     APB4, "" => [
         Dac2,// TODO (Auto), DAC2AMEN missing from D3AMR upstream
@@ -656,6 +1333,7 @@ peripheral_reset_and_enable_control! {
         (Auto) Lptim3,// TODO [group clk: Lptim3(Variant) srdccip "LPTIM3"],
 
         (Auto) I2c4 [kernel clk: I2c4 srdccip "I2C4"],
+        (Auto) Lpuart1 [kernel clk: Lpuart1(Variant) srdccip "LPUART1"],
         (Auto) Spi6 [kernel clk: Spi6(Variant) srdccip "SPI6"]
     ];
 }
@@ -0,0 +1,135 @@
+//! Run-time switching of the system clock source
+//!
+//! [`switch_sys_ck`](super::Ccdr::switch_sys_ck) changes the source of
+//! `sys_ck` after `freeze`, keeping the core, AHB and APB prescalers. This
+//! allows an application to drop to the HSI during flash writes or
+//! EMC-sensitive windows, and return to the PLL afterwards.
+//!
+//! ```no_run
+//! use stm32h7xx_hal::{pac, prelude::*, rcc::SysClkSource};
+//!
+//! let dp = pac::Peripherals::take().unwrap();
+//! let pwrcfg = dp.PWR.constrain().freeze();
+//! let mut ccdr = dp.RCC.constrain().sys_ck(400.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! ccdr.switch_sys_ck(SysClkSource::Hsi).unwrap();
+//! assert_eq!(ccdr.clocks.sys_ck().0, 64_000_000);
+//!
+//! // ...
+//!
+//! ccdr.switch_sys_ck(SysClkSource::Pll1).unwrap();
+//! assert_eq!(ccdr.clocks.sys_ck().0, 400_000_000);
+//! ```
+//!
+//! The flash wait states are increased before switching to a faster clock,
+//! and reduced after switching to a slower clock. As with
+//! [low-power profiles](super::LowPowerProfile), peripherals that use a bus
+//! clock must be re-initialised with the updated `ccdr.clocks`.
+
+use super::{Ccdr, Rcc, CSI, HSI, SW};
+use crate::time::Hertz;
+
+/// Source of `sys_ck`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SysClkSource {
+    /// High Speed Internal oscillator, 64MHz
+    Hsi,
+    /// Low-power internal oscillator, 4MHz
+    Csi,
+    /// High Speed External oscillator, if configured by `freeze`
+    Hse,
+    /// `pll1_p_ck`, if configured by `freeze`
+    Pll1,
+}
+
+/// Error switching the system clock source
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum SwitchError {
+    /// The HSE or PLL1 is not running
+    NotRunning,
+    /// A low-power profile is active. Exit the profile first
+    LowPowerRun,
+}
+
+impl Ccdr {
+    /// Switches `sys_ck` to `source`
+    ///
+    /// The core, AHB and APB prescalers are unchanged. The frequencies in
+    /// `self.clocks` are updated to reflect the new configuration.
+    pub fn switch_sys_ck(
+        &mut self,
+        source: SysClkSource,
+    ) -> Result<(), SwitchError> {
+        if self.low_power.is_some() {
+            return Err(SwitchError::LowPowerRun);
+        }
+
+        let rcc = &self.rb;
+
+        // Ensure the target is on and stable
+        let (sys_ck, sw) = match source {
+            SysClkSource::Hsi => {
+                rcc.cr.modify(|_, w| w.hsion().on());
+                while rcc.cr.read().hsirdy().is_not_ready() {}
+                (HSI, SW::HSI)
+            }
+            SysClkSource::Csi => {
+                rcc.cr.modify(|_, w| w.csion().on());
+                while rcc.cr.read().csirdy().is_not_ready() {}
+                (CSI, SW::CSI)
+            }
+            SysClkSource::Hse => match self.clocks.hse_ck {
+                Some(hse) if rcc.cr.read().hserdy().is_ready() => {
+                    (hse.0, SW::HSE)
+                }
+                _ => return Err(SwitchError::NotRunning),
+            },
+            SysClkSource::Pll1 => match self.clocks.pll1_p_ck {
+                Some(p_ck) if rcc.cr.read().pll1rdy().is_ready() => {
+                    (p_ck.0, SW::PLL1)
+                }
+                _ => return Err(SwitchError::NotRunning),
+            },
+        };
+
+        // Derived clocks keep their ratio to sys_ck
+        let clocks = &self.clocks;
+        let old = clocks.sys_ck.0;
+        let scale = |ck: Hertz| Hertz(sys_ck / (old / ck.0));
+        let c_ck = scale(clocks.c_ck);
+        let hclk = scale(clocks.hclk);
+        let pclk1 = scale(clocks.pclk1);
+        let pclk2 = scale(clocks.pclk2);
+        let pclk3 = scale(clocks.pclk3);
+        let pclk4 = scale(clocks.pclk4);
+        let timx_ker_ck = scale(clocks.timx_ker_ck);
+        let timy_ker_ck = scale(clocks.timy_ker_ck);
+
+        // Increase wait states before the clock becomes faster
+        if sys_ck > old {
+            Rcc::flash_setup(hclk.0, self.vos);
+        }
+
+        let sw = sw as u8;
+        rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(sw) });
+        while rcc.cfgr.read().sws().bits() != sw {}
+
+        // Reduce wait states after the clock has become slower
+        if sys_ck < old {
+            Rcc::flash_setup(hclk.0, self.vos);
+        }
+
+        self.clocks.sys_ck = Hertz(sys_ck);
+        self.clocks.c_ck = c_ck;
+        self.clocks.hclk = hclk;
+        self.clocks.pclk1 = pclk1;
+        self.clocks.pclk2 = pclk2;
+        self.clocks.pclk3 = pclk3;
+        self.clocks.pclk4 = pclk4;
+        self.clocks.timx_ker_ck = timx_ker_ck;
+        self.clocks.timy_ker_ck = timy_ker_ck;
+
+        Ok(())
+    }
+}
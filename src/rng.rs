@@ -1,4 +1,64 @@
 //! Random Number Generator
+//!
+//! # Error recovery
+//!
+//! The RNG detects faults in its kernel clock and in the entropy source.
+//! When [`next`](Rng::next) finds a seed error, it performs the recovery
+//! sequence from the reference manual, and only returns an error if the
+//! seed error persists. A clock error cannot be recovered in software; the
+//! RNG resumes automatically when the kernel clock is correct again.
+//!
+//! # rand_core
+//!
+//! With the `rand` feature, [`Rng`] implements [`rand_core::RngCore`] and
+//! [`rand_core::CryptoRng`], so it can be used directly or to seed software
+//! random number generators.
+//!
+//! # Software PRNG
+//!
+//! Each word from the RNG takes tens of kernel clock cycles. A [`SoftRng`]
+//! is seeded from the RNG, and returns words from the xoshiro128++
+//! generator at a few cycles each. The RNG can be released and its kernel
+//! clock stopped while the [`SoftRng`] is used, until the [`ReseedPolicy`]
+//! asks for a reseed.
+//!
+//! ```
+//! let mut rng = dp.RNG.constrain(ccdr.peripheral.RNG, &ccdr.clocks);
+//! let mut soft = rng.soft_rng(ReseedPolicy::After(1 << 20)).unwrap();
+//!
+//! let x = soft.next_u32();
+//!
+//! if soft.needs_reseed() {
+//!     soft.reseed(&mut rng).unwrap();
+//! }
+//! ```
+//!
+//! The output of a [`SoftRng`] is predictable from its state, so it does
+//! not implement [`rand_core::CryptoRng`].
+//!
+//! # Entropy pool
+//!
+//! The RNG has no DMA request. An [`EntropyPool`] keeps a buffer of words
+//! from the RNG, which is refilled from the RNG interrupt while it is not
+//! full, so that bursts of random bytes, such as the handshakes of a TLS
+//! connection, do not wait for the RNG.
+//!
+//! ```
+//! static mut POOL: [u32; 256] = [0; 256];
+//! let mut pool = EntropyPool::new(unsafe { &mut POOL });
+//! rng.listen();
+//!
+//! // In the RNG interrupt
+//! if pool.refill(&mut rng)? {
+//!     rng.unlisten();
+//! }
+//!
+//! // Elsewhere
+//! let mut key = [0u8; 32];
+//! if pool.fill_bytes(&mut key) {
+//!     rng.listen();
+//! }
+//! ```
 
 use core::cmp;
 use core::mem;
@@ -15,6 +75,10 @@ pub enum ErrorKind {
     SeedError,
 }
 
+/// Number of words to discard after a seed error, see RM0433 Rev 6 Section
+/// 33.3.7
+const SEED_ERROR_DISCARD: usize = 12;
+
 trait KerClk {
     fn kernel_clk(prec: rec::Rng, clocks: &CoreClocks) -> Option<Hertz>;
 }
@@ -63,14 +127,27 @@ pub struct Rng {
 
 impl Rng {
     /// Returns 32 bits of randomness, or error
+    ///
+    /// A seed error is recovered if possible, see the [module level
+    /// documentation](self)
     pub fn next(&mut self) -> Result<u32, ErrorKind> {
+        let mut recovered = false;
         loop {
             let status = self.rb.sr.read();
             if status.cecs().bit() {
+                // The clock error interrupt status is cleared by
+                // software, the current status by hardware
+                self.rb.sr.modify(|_, w| w.ceis().clear_bit());
                 return Err(ErrorKind::ClockError);
             }
-            if status.secs().bit() {
-                return Err(ErrorKind::SeedError);
+            if status.secs().bit() || status.seis().bit() {
+                if recovered {
+                    self.rb.sr.modify(|_, w| w.seis().clear_bit());
+                    return Err(ErrorKind::SeedError);
+                }
+                self.recover_seed_error();
+                recovered = true;
+                continue;
             }
             if status.drdy().bit() {
                 return Ok(self.rb.dr.read().rndata().bits());
@@ -78,11 +155,202 @@ impl Rng {
         }
     }
 
+    /// Returns 32 bits of randomness if a word is ready, or an error
+    ///
+    /// Errors are handled as by [`next`](Rng::next)
+    pub fn try_next(&mut self) -> nb::Result<u32, ErrorKind> {
+        let status = self.rb.sr.read();
+        if status.cecs().bit() || status.secs().bit() || status.seis().bit() {
+            return self.next().map_err(nb::Error::Other);
+        }
+        if status.drdy().bit() {
+            Ok(self.rb.dr.read().rndata().bits())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Interrupts when a word is ready, or on an error
+    pub fn listen(&mut self) {
+        self.rb.cr.modify(|_, w| w.ie().enabled());
+    }
+
+    /// Stops interrupting when a word is ready
+    pub fn unlisten(&mut self) {
+        self.rb.cr.modify(|_, w| w.ie().disabled());
+    }
+
+    /// Seed error recovery sequence
+    ///
+    /// See RM0433 Rev 6 Section 33.3.7
+    fn recover_seed_error(&mut self) {
+        self.rb.sr.modify(|_, w| w.seis().clear_bit());
+
+        // Clean the pipeline
+        for _ in 0..SEED_ERROR_DISCARD {
+            let _ = self.rb.dr.read();
+        }
+
+        if self.rb.sr.read().seis().bit() {
+            // The error persists. Restart the RNG
+            self.rb.cr.modify(|_, w| w.rngen().disabled());
+            self.rb.cr.modify(|_, w| w.rngen().enabled());
+        }
+    }
+
+    /// Returns a software PRNG, seeded from this RNG
+    pub fn soft_rng(
+        &mut self,
+        policy: ReseedPolicy,
+    ) -> Result<SoftRng, ErrorKind> {
+        let mut soft = SoftRng {
+            s: [0; 4],
+            policy,
+            remaining: 0,
+        };
+        soft.reseed(self)?;
+        Ok(soft)
+    }
+
     pub fn release(self) -> RNG {
         self.rb
     }
 }
 
+/// When a [`SoftRng`] should be reseeded from the RNG
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReseedPolicy {
+    /// A reseed is never needed
+    Never,
+    /// A reseed is needed after this number of words
+    After(u32),
+}
+
+/// Software pseudo-random number generator (xoshiro128++), seeded from the
+/// RNG
+pub struct SoftRng {
+    s: [u32; 4],
+    policy: ReseedPolicy,
+    /// Words until a reseed is needed
+    remaining: u32,
+}
+
+impl SoftRng {
+    /// Returns 32 pseudo-random bits
+    ///
+    /// Words continue to be returned after a reseed is needed
+    pub fn next_u32(&mut self) -> u32 {
+        self.remaining = self.remaining.saturating_sub(1);
+
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(7).wrapping_add(s[0]);
+        let t = s[1] << 9;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+
+        result
+    }
+
+    /// Returns `true` if the [`ReseedPolicy`] asks for a reseed
+    pub fn needs_reseed(&self) -> bool {
+        match self.policy {
+            ReseedPolicy::Never => false,
+            ReseedPolicy::After(_) => self.remaining == 0,
+        }
+    }
+
+    /// Replaces the state with a new seed from `rng`
+    pub fn reseed(&mut self, rng: &mut Rng) -> Result<(), ErrorKind> {
+        loop {
+            for word in self.s.iter_mut() {
+                *word = rng.next()?;
+            }
+            // The all-zero state is a fixed point of the generator
+            if self.s != [0; 4] {
+                break;
+            }
+        }
+        if let ReseedPolicy::After(words) = self.policy {
+            self.remaining = words;
+        }
+        Ok(())
+    }
+}
+
+/// Pool of words from the RNG, see the [module level
+/// documentation](self)
+pub struct EntropyPool<'a> {
+    words: &'a mut [u32],
+    /// Index of the oldest word
+    read: usize,
+    /// Number of words in the pool
+    len: usize,
+}
+
+impl<'a> EntropyPool<'a> {
+    /// Creates an empty pool that holds up to `words.len()` words
+    pub fn new(words: &'a mut [u32]) -> Self {
+        EntropyPool {
+            words,
+            read: 0,
+            len: 0,
+        }
+    }
+
+    /// Moves the words that are ready from `rng` into the pool. Returns
+    /// `true` if the pool is full
+    pub fn refill(&mut self, rng: &mut Rng) -> Result<bool, ErrorKind> {
+        while !self.is_full() {
+            match rng.try_next() {
+                Ok(word) => {
+                    let write = (self.read + self.len) % self.words.len();
+                    self.words[write] = word;
+                    self.len += 1;
+                }
+                Err(nb::Error::WouldBlock) => return Ok(false),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the number of random bytes in the pool
+    pub fn available(&self) -> usize {
+        self.len * mem::size_of::<u32>()
+    }
+
+    /// Returns `true` if the pool is full
+    pub fn is_full(&self) -> bool {
+        self.len == self.words.len()
+    }
+
+    /// Fills `dest` with random bytes from the pool. Returns `false`, and
+    /// takes nothing, if there are not enough bytes in the pool
+    ///
+    /// Each word is only used once, so the unused bytes of the last word
+    /// are discarded.
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) -> bool {
+        let needed = (dest.len() + 3) / 4;
+        if needed > self.len {
+            return false;
+        }
+
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.words[self.read].to_ne_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+            self.words[self.read] = 0;
+            self.read = (self.read + 1) % self.words.len();
+        }
+        self.len -= needed;
+        true
+    }
+}
+
 impl rng::Read for Rng {
     type Error = ErrorKind;
 
@@ -124,3 +392,105 @@ rng_core!(u32, u16, u8);
 // Test host may have > 32-bit types, which we don't consider.
 #[cfg(not(test))]
 rng_core!(usize);
+
+#[cfg(feature = "rand")]
+impl rand_core::RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        self.next().expect("RNG error")
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = rand_core::RngCore::next_u32(self) as u64;
+        let high = rand_core::RngCore::next_u32(self) as u64;
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::RngCore::try_fill_bytes(self, dest).expect("RNG error")
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill(dest).map_err(|e| {
+            let code = match e {
+                ErrorKind::ClockError => 1,
+                ErrorKind::SeedError => 2,
+            };
+            // unwrap: code is not zero
+            rand_core::Error::from(
+                core::num::NonZeroU32::new(
+                    rand_core::Error::CUSTOM_START + code,
+                )
+                .unwrap(),
+            )
+        })
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand_core::CryptoRng for Rng {}
+
+#[cfg(feature = "rand")]
+impl rand_core::RngCore for SoftRng {
+    fn next_u32(&mut self) -> u32 {
+        SoftRng::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_rng_known_answer() {
+        // Reference output of xoshiro128++ for the seed [1, 2, 3, 4], as in
+        // the rand_xoshiro crate
+        let mut soft = SoftRng {
+            s: [1, 2, 3, 4],
+            policy: ReseedPolicy::Never,
+            remaining: 0,
+        };
+        let expected = [
+            641, 1573767, 3222811527, 3517856514, 836907274, 4247214768,
+            3867114732, 1355841295, 495546011, 621204420,
+        ];
+        for &word in expected.iter() {
+            assert_eq!(soft.next_u32(), word);
+        }
+        assert!(!soft.needs_reseed());
+    }
+
+    #[test]
+    fn soft_rng_reseed_policy() {
+        let mut soft = SoftRng {
+            s: [1, 2, 3, 4],
+            policy: ReseedPolicy::After(2),
+            remaining: 2,
+        };
+        soft.next_u32();
+        assert!(!soft.needs_reseed());
+        soft.next_u32();
+        assert!(soft.needs_reseed());
+        // Words continue to be returned
+        soft.next_u32();
+        assert!(soft.needs_reseed());
+    }
+}
@@ -1,4 +1,36 @@
 //! Real-Time Clock
+//!
+//! The RTC is clocked from the LSE, LSI or a division of the HSE, and
+//! provides a calendar, two alarms, a periodic wakeup timer, timestamps,
+//! tamper detection and 32 backup registers.
+//!
+//! Calendar values are represented using the types from [`chrono`].
+//!
+//! # Without a 32kHz crystal
+//!
+//! Boards without an LSE crystal can clock the RTC from the HSE, divided to
+//! below 1MHz by RTCPRE. [`RtcClock::hse`] chooses a divider for which the
+//! calendar counts exact seconds. The HSE stops in Stop and Standby mode, and
+//! so does the calendar.
+//!
+//! The time then only stays approximately right while the device runs. A
+//! [`DriftTracker`] measures the drift of the RTC against an external time
+//! reference, such as GNSS, NTP or a host, and cancels it with the smooth
+//! calibration of the RTC.
+//!
+//! ```
+//! let clock = RtcClock::hse(ccdr.clocks.hse_ck().unwrap()).unwrap();
+//! let mut rtc = Rtc::open_or_init(dp.RTC, backup.RTC, clock, &ccdr.clocks);
+//! rtc.set_date_time(reference);
+//!
+//! // Measure the drift over at least 10 minutes
+//! let mut tracker = DriftTracker::new(600);
+//!
+//! // Each time a reference time is received
+//! if let Some(ppm) = tracker.update(&mut rtc, reference) {
+//!     // The RTC was `ppm` parts per million fast over the last interval
+//! }
+//! ```
 
 use cast::{f32, i32, u16, u32, u8};
 use chrono::prelude::*;
@@ -15,9 +47,74 @@ pub enum Event {
     AlarmB,
     Wakeup,
     Timestamp,
+    Tamper,
     LseCss,
 }
 
+/// RTC Alarms
+#[derive(Copy, Clone, PartialEq)]
+pub enum Alarm {
+    AlarmA,
+    AlarmB,
+}
+
+/// Day on which an alarm triggers
+#[derive(Copy, Clone, PartialEq)]
+pub enum AlarmDay {
+    /// Day of the month, 1..=31
+    Date(u8),
+    /// Day of the week
+    Weekday(Weekday),
+}
+
+/// Alarm match configuration
+///
+/// Fields that are `None` are masked, and so match any value. For example,
+/// an alarm with only `second` set triggers once every minute.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub struct AlarmConfig {
+    pub day: Option<AlarmDay>,
+    pub hour: Option<u8>,
+    pub minute: Option<u8>,
+    pub second: Option<u8>,
+}
+
+impl AlarmConfig {
+    /// Alarm that triggers every day at `time`
+    pub fn daily(time: NaiveTime) -> Self {
+        AlarmConfig {
+            day: None,
+            hour: Some(time.hour() as u8),
+            minute: Some(time.minute() as u8),
+            second: Some(time.second() as u8),
+        }
+    }
+
+    /// Alarm that triggers once at `date_time`, and thereafter at the same
+    /// time on the same day of the month
+    pub fn date_time(date_time: NaiveDateTime) -> Self {
+        AlarmConfig {
+            day: Some(AlarmDay::Date(date_time.day() as u8)),
+            ..AlarmConfig::daily(date_time.time())
+        }
+    }
+}
+
+/// RTC Tamper inputs
+#[derive(Copy, Clone, PartialEq)]
+pub enum Tamper {
+    Tamper1,
+    Tamper2,
+    Tamper3,
+}
+
+/// Active edge of a tamper input
+#[derive(Copy, Clone, PartialEq)]
+pub enum TamperEdge {
+    Rising,
+    Falling,
+}
+
 #[derive(Copy, Clone, PartialEq)]
 pub enum DstState {
     /// Standard Time
@@ -53,6 +150,28 @@ pub enum RtcClock {
     Hse { divider: u8 },
 }
 
+/// Frequency below which the divided HSE must be
+const HSE_RTC_MAX: u32 = 1_000_000;
+
+impl RtcClock {
+    /// Returns the HSE clock source with the smallest divider for which the
+    /// RTC clock is below 1MHz and the calendar counts exact seconds. For
+    /// example, a 25MHz HSE is divided by 32
+    ///
+    /// Returns `None` if there is no such divider for `hse_ck`
+    pub fn hse(hse_ck: Hertz) -> Option<Self> {
+        (2..64)
+            .find(|&divider| {
+                hse_ck.0 % divider == 0
+                    && hse_ck.0 / divider < HSE_RTC_MAX
+                    && prescalers(hse_ck.0 / divider).is_some()
+            })
+            .map(|divider| RtcClock::Hse {
+                divider: divider as u8,
+            })
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// An error preventing the RTC from initializing
 pub enum InitError {
@@ -61,6 +180,13 @@ pub enum InitError {
     ConfigMismatch,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CalibrationError {
+    /// The correction is outside of the range of the smooth calibration,
+    /// -487.3ppm to +488.2ppm
+    OutOfRange,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DstError {
     ClockNotInitialized,
@@ -154,11 +280,18 @@ impl Rtc {
                 Some(freq)
             }
             RtcClock::Hse { divider } => {
-                // Set HSE divider
-                assert!(divider < 64, "HSE Divider larger than 63");
+                // Set HSE divider. RTCPRE values 0 and 1 stop the clock
+                assert!(
+                    (2..64).contains(&divider),
+                    "HSE Divider must be between 2 and 63"
+                );
                 rcc.cfgr.modify(|_, w| w.rtcpre().bits(divider));
 
-                clocks.hse_ck().map(|x| Hertz(x.0 / u32(divider)))
+                clocks.hse_ck().map(|x| {
+                    let ker_ck = x.0 / u32(divider);
+                    assert!(ker_ck < HSE_RTC_MAX, "HSE Divider too small");
+                    Hertz(ker_ck)
+                })
             }
             RtcClock::Lsi => clocks.lsi_ck(),
         }
@@ -191,31 +324,8 @@ impl Rtc {
         rtc.cr.modify(|_, w| w.bypshad().set_bit());
 
         // Configure prescaler for 1Hz clock
-        // Want to maximize a_pre_max for power reasons, though it reduces the
-        // subsecond precision.
-        let total_div = ker_ck;
-        let a_pre_max = 1 << 7;
-        let s_pre_max = 1 << 15;
-
-        let (a_pre, s_pre) = if total_div <= a_pre_max {
-            (total_div, 1)
-        } else if total_div % a_pre_max == 0 {
-            (a_pre_max, total_div / a_pre_max)
-        } else {
-            let mut a_pre = a_pre_max;
-            while a_pre > 1 {
-                if total_div % a_pre == 0 {
-                    break;
-                }
-                a_pre -= 1;
-            }
-            let s_pre = total_div / a_pre;
-            (a_pre, s_pre)
-        };
-        assert!(
-            a_pre <= a_pre_max && s_pre <= s_pre_max,
-            "Invalid RTC prescaler value"
-        );
+        let (a_pre, s_pre) =
+            prescalers(ker_ck).expect("Invalid RTC prescaler value");
 
         rtc.prer.write(|w| {
             w.prediv_s()
@@ -230,6 +340,34 @@ impl Rtc {
         Rtc { reg: rtc, prec }
     }
 
+    /// Sets the smooth calibration, which speeds up (positive `ppm`) or
+    /// slows down (negative `ppm`) the RTC by up to about 488 parts per
+    /// million
+    ///
+    /// The calibration is applied in steps of 0.954ppm, spread over each 2^20
+    /// cycles of the RTC clock.
+    pub fn calibrate(&mut self, ppm: f32) -> Result<(), CalibrationError> {
+        let (calp, calm) =
+            calibration_registers(ppm).ok_or(CalibrationError::OutOfRange)?;
+
+        // A new calibration can only be written once the previous one is
+        // applied
+        while self.reg.isr.read().recalpf().bit_is_set() {}
+        self.reg
+            .calr
+            .write(|w| unsafe { w.calp().bit(calp).calm().bits(calm) });
+        Ok(())
+    }
+
+    /// Returns the current smooth calibration in parts per million
+    pub fn calibration(&self) -> f32 {
+        let calr = self.reg.calr.read();
+        let calp = if calr.calp().bit_is_set() { 512 } else { 0 };
+        let calm = i32::from(calr.calm().bits());
+
+        (calp - calm) as f32 / CALIBRATION_STEPS_PER_PPM
+    }
+
     /// Reads the value of a 32-bit backup register
     ///
     /// # Panics
@@ -594,7 +732,7 @@ impl Rtc {
     ///
     /// Clears the timestamp interrupt flags.
     pub fn read_timestamp(&self) -> Option<NaiveDateTime> {
-        if !self.reg.isr.read().tsf().bit_is_clear() {
+        if self.reg.isr.read().tsf().bit_is_clear() {
             return None;
         }
 
@@ -631,7 +769,192 @@ impl Rtc {
         Some(date.and_time(time))
     }
 
-    // TODO: Alarms
+    /// Configures `alarm` to trigger when the calendar matches `config`
+    ///
+    /// Any previous configuration of this alarm is replaced, and the alarm is
+    /// enabled. Use [`listen`](Rtc::listen) to generate an interrupt.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a field of `config` is out of range
+    pub fn set_alarm(&mut self, alarm: Alarm, config: AlarmConfig) {
+        let (msk4, wdsel, day) = match config.day {
+            None => (true, false, 1),
+            Some(AlarmDay::Date(date)) => {
+                assert!((1..=31).contains(&date), "Invalid alarm date");
+                (false, false, date)
+            }
+            Some(AlarmDay::Weekday(wd)) => {
+                (false, true, wd.number_from_monday() as u8)
+            }
+        };
+        let (msk3, hour) = match config.hour {
+            None => (true, 0),
+            Some(h) => {
+                assert!(h < 24, "Invalid alarm hour");
+                (false, h)
+            }
+        };
+        let (msk2, minute) = match config.minute {
+            None => (true, 0),
+            Some(m) => {
+                assert!(m < 60, "Invalid alarm minute");
+                (false, m)
+            }
+        };
+        let (msk1, second) = match config.second {
+            None => (true, 0),
+            Some(s) => {
+                assert!(s < 60, "Invalid alarm second");
+                (false, s)
+            }
+        };
+
+        macro_rules! write_alarm {
+            ($alrmXr:ident, $alrXe:ident, $alrXwf:ident, $alrXf:ident) => {{
+                // Disable alarm and wait for write access
+                self.reg.cr.modify(|_, w| w.$alrXe().clear_bit());
+                self.reg.isr.modify(|_, w| w.$alrXf().clear_bit());
+                while self.reg.isr.read().$alrXwf().bit_is_clear() {}
+
+                self.reg.$alrmXr.write(|w| unsafe {
+                    w.msk4()
+                        .bit(msk4)
+                        .wdsel()
+                        .bit(wdsel)
+                        .dt()
+                        .bits(day / 10)
+                        .du()
+                        .bits(day % 10)
+                        .msk3()
+                        .bit(msk3)
+                        .pm()
+                        .clear_bit()
+                        .ht()
+                        .bits(hour / 10)
+                        .hu()
+                        .bits(hour % 10)
+                        .msk2()
+                        .bit(msk2)
+                        .mnt()
+                        .bits(minute / 10)
+                        .mnu()
+                        .bits(minute % 10)
+                        .msk1()
+                        .bit(msk1)
+                        .st()
+                        .bits(second / 10)
+                        .su()
+                        .bits(second % 10)
+                });
+
+                self.reg.cr.modify(|_, w| w.$alrXe().set_bit());
+            }};
+        }
+
+        match alarm {
+            Alarm::AlarmA => write_alarm!(alrmar, alrae, alrawf, alraf),
+            Alarm::AlarmB => write_alarm!(alrmbr, alrbe, alrbwf, alrbf),
+        }
+    }
+
+    /// Disables `alarm`
+    pub fn disable_alarm(&mut self, alarm: Alarm) {
+        match alarm {
+            Alarm::AlarmA => {
+                self.reg.cr.modify(|_, w| w.alrae().clear_bit());
+                self.reg.isr.modify(|_, w| w.alraf().clear_bit());
+            }
+            Alarm::AlarmB => {
+                self.reg.cr.modify(|_, w| w.alrbe().clear_bit());
+                self.reg.isr.modify(|_, w| w.alrbf().clear_bit());
+            }
+        }
+    }
+
+    /// Enables the `tamper` input, detecting on `edge`
+    ///
+    /// When a tamper event is detected, the backup registers are erased
+    /// unless `erase_backup` is false. A timestamp is also captured if
+    /// timestamps are enabled.
+    pub fn enable_tamper(
+        &mut self,
+        tamper: Tamper,
+        edge: TamperEdge,
+        erase_backup: bool,
+    ) {
+        let falling = edge == TamperEdge::Falling;
+
+        // Timestamp on tamper detection
+        self.reg.tampcr.modify(|_, w| w.tampts().set_bit());
+
+        match tamper {
+            Tamper::Tamper1 => {
+                self.reg.tampcr.modify(|_, w| w.tamp1e().clear_bit());
+                self.reg.isr.modify(|_, w| w.tamp1f().clear_bit());
+                self.reg.tampcr.modify(|_, w| {
+                    w.tamp1trg()
+                        .bit(falling)
+                        .tamp1noerase()
+                        .bit(!erase_backup)
+                        .tamp1e()
+                        .set_bit()
+                });
+            }
+            Tamper::Tamper2 => {
+                self.reg.tampcr.modify(|_, w| w.tamp2e().clear_bit());
+                self.reg.isr.modify(|_, w| w.tamp2f().clear_bit());
+                self.reg.tampcr.modify(|_, w| {
+                    w.tamp2trg()
+                        .bit(falling)
+                        .tamp2noerase()
+                        .bit(!erase_backup)
+                        .tamp2e()
+                        .set_bit()
+                });
+            }
+            Tamper::Tamper3 => {
+                self.reg.tampcr.modify(|_, w| w.tamp3e().clear_bit());
+                self.reg.isr.modify(|_, w| w.tamp3f().clear_bit());
+                self.reg.tampcr.modify(|_, w| {
+                    w.tamp3trg()
+                        .bit(falling)
+                        .tamp3noerase()
+                        .bit(!erase_backup)
+                        .tamp3e()
+                        .set_bit()
+                });
+            }
+        }
+    }
+
+    /// Disables the `tamper` input
+    pub fn disable_tamper(&mut self, tamper: Tamper) {
+        match tamper {
+            Tamper::Tamper1 => {
+                self.reg.tampcr.modify(|_, w| w.tamp1e().clear_bit());
+                self.reg.isr.modify(|_, w| w.tamp1f().clear_bit());
+            }
+            Tamper::Tamper2 => {
+                self.reg.tampcr.modify(|_, w| w.tamp2e().clear_bit());
+                self.reg.isr.modify(|_, w| w.tamp2f().clear_bit());
+            }
+            Tamper::Tamper3 => {
+                self.reg.tampcr.modify(|_, w| w.tamp3e().clear_bit());
+                self.reg.isr.modify(|_, w| w.tamp3f().clear_bit());
+            }
+        }
+    }
+
+    /// Returns `true` if a tamper event has been detected on `tamper`
+    pub fn is_tampered(&self, tamper: Tamper) -> bool {
+        let isr = self.reg.isr.read();
+        match tamper {
+            Tamper::Tamper1 => isr.tamp1f().bit_is_set(),
+            Tamper::Tamper2 => isr.tamp2f().bit_is_set(),
+            Tamper::Tamper3 => isr.tamp3f().bit_is_set(),
+        }
+    }
 
     /// Start listening for `event`
     pub fn listen(&mut self, exti: &mut EXTI, event: Event) {
@@ -673,6 +996,11 @@ impl Rtc {
                 exti.rtsr1.modify(|_, w| w.tr18().enabled());
                 self.reg.cr.modify(|_, w| w.tsie().set_bit());
             }
+            Event::Tamper => {
+                exti.listen(ExtiEvent::RTC_OTHER);
+                exti.rtsr1.modify(|_, w| w.tr18().enabled());
+                self.reg.tampcr.modify(|_, w| w.tampie().set_bit());
+            }
         }
     }
 
@@ -708,6 +1036,11 @@ impl Rtc {
                 exti.unlisten(ExtiEvent::RTC_OTHER);
                 exti.rtsr1.modify(|_, w| w.tr18().disabled());
             }
+            Event::Tamper => {
+                self.reg.tampcr.modify(|_, w| w.tampie().clear_bit());
+                exti.unlisten(ExtiEvent::RTC_OTHER);
+                exti.rtsr1.modify(|_, w| w.tr18().disabled());
+            }
         }
     }
 
@@ -722,6 +1055,12 @@ impl Rtc {
             Event::AlarmB => self.reg.isr.read().alrbf().bit_is_set(),
             Event::Wakeup => self.reg.isr.read().wutf().bit_is_set(),
             Event::Timestamp => self.reg.isr.read().tsf().bit_is_set(),
+            Event::Tamper => {
+                let isr = self.reg.isr.read();
+                isr.tamp1f().bit_is_set()
+                    || isr.tamp2f().bit_is_set()
+                    || isr.tamp3f().bit_is_set()
+            }
         }
     }
 
@@ -752,6 +1091,17 @@ impl Rtc {
                 self.reg.isr.modify(|_, w| w.tsf().clear_bit());
                 exti.unpend(ExtiEvent::RTC_OTHER);
             }
+            Event::Tamper => {
+                self.reg.isr.modify(|_, w| {
+                    w.tamp1f()
+                        .clear_bit()
+                        .tamp2f()
+                        .clear_bit()
+                        .tamp3f()
+                        .clear_bit()
+                });
+                exti.unpend(ExtiEvent::RTC_OTHER);
+            }
         }
     }
 
@@ -773,3 +1123,204 @@ impl Rtc {
         self.prec.kernel_clk_mux(backup::RtcClkSel::LSI);
     }
 }
+
+/// Returns the asynchronous and synchronous prescalers that divide `ker_ck`
+/// to 1Hz, if there are any
+///
+/// The asynchronous prescaler is maximised for power reasons, though it
+/// reduces the subsecond precision.
+fn prescalers(ker_ck: u32) -> Option<(u32, u32)> {
+    let a_pre_max = 1 << 7;
+    let s_pre_max = 1 << 15;
+
+    let a_pre = if ker_ck <= a_pre_max {
+        ker_ck
+    } else {
+        (1..=a_pre_max).rev().find(|a_pre| ker_ck % a_pre == 0)?
+    };
+    if a_pre == 0 {
+        return None;
+    }
+    let s_pre = ker_ck / a_pre;
+
+    if s_pre <= s_pre_max {
+        Some((a_pre, s_pre))
+    } else {
+        None
+    }
+}
+
+/// Calibration steps of 2^-20 in one part per million
+const CALIBRATION_STEPS_PER_PPM: f32 = 1.048_576;
+
+/// Largest corrections of the smooth calibration, in parts per million
+const CALIBRATION_MIN: f32 = -487.3;
+const CALIBRATION_MAX: f32 = 488.2;
+
+/// Returns CALP and CALM of the smooth calibration closest to `ppm`, if it
+/// is in range
+fn calibration_registers(ppm: f32) -> Option<(bool, u16)> {
+    let steps = ppm * CALIBRATION_STEPS_PER_PPM;
+    let steps = (if steps < 0.0 {
+        steps - 0.5
+    } else {
+        steps + 0.5
+    }) as i32;
+    // CALP adds 512 pulses, each step of CALM masks one pulse
+    match steps {
+        1..=512 => Some((true, (512 - steps) as u16)),
+        -511..=0 => Some((false, -steps as u16)),
+        _ => None,
+    }
+}
+
+/// Returns the drift in parts per million of an interval of `elapsed`
+/// microseconds that the RTC counted as `counted` microseconds
+fn drift_ppm(counted: i64, elapsed: i64) -> f32 {
+    (counted - elapsed) as f32 * 1_000_000.0 / elapsed as f32
+}
+
+/// Returns the calibration that cancels `drift`, saturating at the range
+/// of the smooth calibration
+fn corrected_calibration(calibration: f32, drift: f32) -> f32 {
+    let calibration = calibration - drift;
+    if calibration < CALIBRATION_MIN {
+        CALIBRATION_MIN
+    } else if calibration > CALIBRATION_MAX {
+        CALIBRATION_MAX
+    } else {
+        calibration
+    }
+}
+
+/// Drift of the RTC against an external time reference
+///
+/// The drift is measured over intervals of at least `min_interval` seconds
+/// of the reference, and cancelled by changing the smooth calibration of the
+/// RTC. The tracker does not change the time of the RTC: if the application
+/// sets it, for example when the offset from the reference grows too large,
+/// call [`restart`](DriftTracker::restart) afterwards.
+pub struct DriftTracker {
+    /// Shortest interval over which the drift is measured, in seconds
+    min_interval: i64,
+    /// RTC and reference times at the start of the interval
+    start: Option<(NaiveDateTime, NaiveDateTime)>,
+    /// Drift over the last interval, in parts per million
+    drift: Option<f32>,
+}
+
+impl DriftTracker {
+    /// Creates a tracker that measures the drift over at least
+    /// `min_interval` seconds
+    pub fn new(min_interval: u32) -> Self {
+        DriftTracker {
+            min_interval: i64::from(min_interval),
+            start: None,
+            drift: None,
+        }
+    }
+
+    /// Compares the RTC with `reference`, the current time of the external
+    /// reference
+    ///
+    /// Once `min_interval` has passed since the start of the interval,
+    /// returns the drift of the RTC in parts per million, positive when the
+    /// RTC is fast. The calibration of the RTC is then corrected by the
+    /// drift, saturating at the range of the smooth calibration, and a new
+    /// interval starts.
+    ///
+    /// Returns `None` while the interval is shorter, or if the calendar of
+    /// the RTC is not initialized.
+    pub fn update(
+        &mut self,
+        rtc: &mut Rtc,
+        reference: NaiveDateTime,
+    ) -> Option<f32> {
+        let now = rtc.date_time()?;
+        let (rtc_start, reference_start) = match self.start {
+            Some(start) => start,
+            None => {
+                self.start = Some((now, reference));
+                return None;
+            }
+        };
+
+        let elapsed = reference
+            .signed_duration_since(reference_start)
+            .num_microseconds()?;
+        if elapsed < self.min_interval * 1_000_000 {
+            return None;
+        }
+        let counted =
+            now.signed_duration_since(rtc_start).num_microseconds()?;
+        let drift = drift_ppm(counted, elapsed);
+
+        let calibration = corrected_calibration(rtc.calibration(), drift);
+        let _ = rtc.calibrate(calibration);
+
+        self.start = Some((now, reference));
+        self.drift = Some(drift);
+        Some(drift)
+    }
+
+    /// Returns the drift measured over the last interval in parts per
+    /// million, if an interval has completed
+    pub fn drift(&self) -> Option<f32> {
+        self.drift
+    }
+
+    /// Starts a new interval at the next update, for example after the time
+    /// of the RTC was set
+    pub fn restart(&mut self) {
+        self.start = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescalers_to_1hz() {
+        assert_eq!(prescalers(32_768), Some((128, 256)));
+        assert_eq!(prescalers(32_000), Some((128, 250)));
+        assert_eq!(prescalers(1_000_000), Some((125, 8000)));
+        assert_eq!(prescalers(100), Some((100, 1)));
+        assert_eq!(prescalers(128 * 32_768), Some((128, 32_768)));
+    }
+
+    #[test]
+    fn prescalers_out_of_range() {
+        assert_eq!(prescalers(0), None);
+        assert_eq!(prescalers(128 * 32_769), None);
+        // Prime, so only divided by the synchronous prescaler
+        assert_eq!(prescalers(999_983), None);
+    }
+
+    #[test]
+    fn calibration_steps() {
+        assert_eq!(calibration_registers(0.0), Some((false, 0)));
+        assert_eq!(calibration_registers(1.0), Some((true, 511)));
+        assert_eq!(calibration_registers(-1.0), Some((false, 1)));
+        assert_eq!(calibration_registers(CALIBRATION_MAX), Some((true, 0)));
+        assert_eq!(calibration_registers(CALIBRATION_MIN), Some((false, 511)));
+        assert_eq!(calibration_registers(500.0), None);
+        assert_eq!(calibration_registers(-500.0), None);
+    }
+
+    #[test]
+    fn drift() {
+        // 60us fast over 10 minutes
+        let drift = drift_ppm(600_000_060, 600_000_000);
+        assert!((drift - 0.1).abs() < 1e-6);
+        let drift = drift_ppm(599_990_000, 600_000_000);
+        assert!((drift + 16.666_667).abs() < 1e-3);
+    }
+
+    #[test]
+    fn drift_correction_saturates() {
+        assert_eq!(corrected_calibration(10.0, 2.5), 7.5);
+        assert_eq!(corrected_calibration(400.0, -100.0), CALIBRATION_MAX);
+        assert_eq!(corrected_calibration(-400.0, 100.0), CALIBRATION_MIN);
+    }
+}
@@ -2,6 +2,44 @@
 //!
 //! Inter-IC Sound.
 //!
+//! Each SAI has two sub-blocks, A and B. One sub-block is the master and
+//! generates the bit clock, frame sync and optionally the master clock
+//! (MCLK). The other sub-block can be configured as a synchronous slave, for
+//! example to receive from a codec while the master transmits.
+//!
+//! The master clock is derived from the kernel clock of the SAI. This is
+//! typically PLL3_P, which should be set up to be at least 256 times the
+//! audio sample rate (512 times with oversampling).
+//!
+//! ```
+//! let ccdr = rcc
+//!     .pll3_p_ck(Hertz(48_000 * 257))
+//!     .freeze(vos, &dp.SYSCFG);
+//!
+//! let sai1_rec = ccdr.peripheral.SAI1.kernel_clk_mux(Sai1ClkSel::PLL3_P);
+//! let master = I2SChanConfig::new(I2SDir::Tx);
+//! let slave = I2SChanConfig::new(I2SDir::Rx).set_sync_type(I2SSync::Internal);
+//!
+//! let mut audio = dp.SAI1.i2s_ch_a(
+//!     sai1_pins,
+//!     48.khz(),
+//!     I2SDataSize::BITS_24,
+//!     sai1_rec,
+//!     &ccdr.clocks,
+//!     master,
+//!     Some(slave),
+//! );
+//! audio.listen(SaiChannel::ChannelA, Event::Data);
+//! audio.enable();
+//! ```
+//!
+//! Data can be streamed either by servicing the FIFO request interrupt
+//! ([`Event::Data`](crate::sai::Event::Data)) with the
+//! [`FullDuplex`](crate::traits::i2s::FullDuplex) methods, or by DMA after
+//! calling [`enable_dma`](crate::sai::Sai::enable_dma). The FIFO threshold
+//! at which a request is made is set by
+//! [`set_fifo_threshold`](I2SChanConfig::set_fifo_threshold).
+//!
 use core::convert::TryInto;
 
 use crate::rcc::{rec, CoreClocks, ResetEnable};
@@ -9,9 +47,13 @@ use crate::sai::{GetClkSAI, Sai, SaiChannel, CLEAR_ALL_FLAGS_BITS, INTERFACE};
 use crate::stm32;
 use crate::time::Hertz;
 
-use crate::stm32::{SAI1, SAI2};
+use crate::stm32::SAI1;
+#[cfg(not(feature = "rm0468"))]
+use crate::stm32::SAI2;
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+use crate::stm32::SAI3;
 #[cfg(not(feature = "rm0455"))]
-use crate::stm32::{SAI3, SAI4};
+use crate::stm32::SAI4;
 
 #[cfg(feature = "rm0455")]
 use crate::device::sai1::ch::sr;
@@ -88,6 +130,17 @@ pub enum I2SSync {
     External = 0b10,
 }
 
+/// FIFO threshold. Interrupt and DMA requests are generated when the FIFO
+/// level crosses this threshold
+#[derive(Copy, Clone, PartialEq)]
+pub enum I2SFifoThreshold {
+    Empty = 0b000,
+    Quarter = 0b001,
+    Half = 0b010,
+    ThreeQuarter = 0b011,
+    Full = 0b100,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum I2SError {
     NoChannelAvailable,
@@ -175,6 +228,7 @@ pub struct I2SChanConfig {
     mute_counter: u8,
     tristate: bool,
     frame_size: Option<u8>,
+    fifo_threshold: I2SFifoThreshold,
 }
 
 impl I2SChanConfig {
@@ -201,6 +255,7 @@ impl I2SChanConfig {
             mute_counter: 0,
             tristate: false,
             frame_size: None,
+            fifo_threshold: I2SFifoThreshold::Quarter,
         }
     }
 
@@ -314,6 +369,13 @@ impl I2SChanConfig {
         self.frame_size = frame_size;
         self
     }
+
+    /// Set the FIFO threshold for interrupt and DMA requests, defaults to
+    /// `Quarter`
+    pub fn set_fifo_threshold(mut self, threshold: I2SFifoThreshold) -> Self {
+        self.fifo_threshold = threshold;
+        self
+    }
 }
 
 /// I2S Interface
@@ -599,12 +661,18 @@ macro_rules! i2s {
 }
 
 i2s! {
-    SAI1, Sai1: [i2s_sai1_ch_a, i2s_sai1_ch_b],
+    SAI1, Sai1: [i2s_sai1_ch_a, i2s_sai1_ch_b]
+}
+#[cfg(not(feature = "rm0468"))]
+i2s! {
     SAI2, Sai2: [i2s_sai2_ch_a, i2s_sai2_ch_b]
 }
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+i2s! {
+    SAI3, Sai3: [i2s_sai3_ch_a, i2s_sai3_ch_b]
+}
 #[cfg(not(feature = "rm0455"))]
 i2s! {
-    SAI3, Sai3: [i2s_sai3_ch_a, i2s_sai3_ch_b],
     SAI4, Sai4: [i2s_sai4_ch_a, i2s_sai4_ch_b]
 }
 
@@ -671,7 +739,7 @@ fn i2s_config_channel(
         });
         audio_ch.cr2.modify(|_, w| {
             w.fth()
-                .quarter1()
+                .bits(config.fifo_threshold as u8)
                 .tris()
                 .bit(config.tristate)
                 .mute()
@@ -820,6 +888,9 @@ pins! {
             PE3<Alternate<AF6>>,
             PF6<Alternate<AF6>>
         ]
+}
+#[cfg(not(feature = "rm0468"))]
+pins! {
     SAI2:
         MCLK_A: [
             PE0<Alternate<AF10>>,
@@ -861,7 +932,7 @@ pins! {
             PG10<Alternate<AF10>>
         ]
 }
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 pins! {
     SAI3:
         MCLK_A: [
@@ -888,6 +959,9 @@ pins! {
         SD_B: [
             PD9<Alternate<AF6>>
         ]
+}
+#[cfg(not(feature = "rm0455"))]
+pins! {
     SAI4:
         MCLK_A: [
             PE2<Alternate<AF8>>
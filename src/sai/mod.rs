@@ -7,11 +7,15 @@ use crate::stm32::sai1::CH;
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::sai4::CH;
 
-use crate::stm32::{SAI1, SAI2};
+use crate::stm32::SAI1;
+#[cfg(not(feature = "rm0468"))]
+use crate::stm32::SAI2;
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
+use crate::stm32::SAI3;
 #[cfg(not(feature = "rm0455"))]
-use crate::stm32::{SAI3, SAI4};
+use crate::stm32::SAI4;
 
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 use crate::rcc::rec::Sai23ClkSelGetter;
 
 // clocks
@@ -26,7 +30,7 @@ pub use pdm::SaiPdmExt;
 mod i2s;
 pub use i2s::{
     I2SChanConfig, I2SClockStrobe, I2SCompanding, I2SComplement, I2SDataSize,
-    I2SDir, I2SMode, I2SProtocol, I2SSync, SaiI2sExt, I2S,
+    I2SDir, I2SFifoThreshold, I2SMode, I2SProtocol, I2SSync, SaiI2sExt, I2S,
 };
 
 /// Trait for associating clocks with SAI instances
@@ -75,11 +79,11 @@ macro_rules! impl_sai_ker_ck {
 impl_sai_ker_ck! {
     Sai1, get_kernel_clk_mux, get_kernel_clk_mux, Sai1ClkSel, Sai1ClkSel: SAI1
 }
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 impl_sai_ker_ck! {
     Sai2, get_kernel_clk_mux, get_kernel_clk_mux, Sai23ClkSel, Sai23ClkSel: SAI2
 }
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 impl_sai_ker_ck! {
     Sai3, get_kernel_clk_mux, get_kernel_clk_mux, Sai23ClkSel, Sai23ClkSel: SAI3
 }
@@ -233,7 +237,7 @@ macro_rules! sai_hal {
                 pub fn mute(&mut self, channel: SaiChannel) {
                     match channel {
                         SaiChannel::ChannelA => &self.rb.cha.cr2.modify(|_, w| w.mute().enabled()),
-                        SaiChannel::ChannelB => &self.rb.cha.cr2.modify(|_, w| w.mute().enabled()),
+                        SaiChannel::ChannelB => &self.rb.chb.cr2.modify(|_, w| w.mute().enabled()),
                     };
                 }
 
@@ -273,6 +277,33 @@ macro_rules! sai_hal {
                     };
                 }
 
+                /// Disable DMA for the SAI peripheral.
+                pub fn disable_dma(&mut self, channel: SaiChannel) {
+                    match channel {
+                        SaiChannel::ChannelA => self.rb.cha.cr1.modify(|_, w| w.dmaen().disabled()),
+                        SaiChannel::ChannelB => self.rb.chb.cr1.modify(|_, w| w.dmaen().disabled()),
+                    };
+                }
+
+                /// Returns `true` if the flag for `event` is set on the `channel`
+                ///
+                /// For Event::Data this indicates that the FIFO threshold has
+                /// been crossed
+                pub fn is_pending(&self, channel: SaiChannel, event: Event) -> bool {
+                    let sr = match channel {
+                        SaiChannel::ChannelA => self.rb.cha.sr.read(),
+                        SaiChannel::ChannelB => self.rb.chb.sr.read(),
+                    };
+                    match event {
+                        Event::Overdue              => sr.ovrudr().bit_is_set(),
+                        Event::Muted                => sr.mutedet().bit_is_set(),
+                        Event::WrongClock           => sr.wckcfg().bit_is_set(),
+                        Event::Data                 => sr.freq().bit_is_set(),
+                        Event::AnticipatedFrameSync => sr.afsdet().bit_is_set(),
+                        Event::LateFrameSync        => sr.lfsdet().bit_is_set(),
+                    }
+                }
+
                 /// Releases the SAI peripheral
                 pub fn free(self) -> ($SAIX, rec::$Rec) {
                     // Refer to RM0433 Rev 7 51.4.15 Disabling the SAI
@@ -308,10 +339,16 @@ macro_rules! sai_hal {
 
 sai_hal! {
     SAI1: (sai1, Sai1),
+}
+#[cfg(not(feature = "rm0468"))]
+sai_hal! {
     SAI2: (sai2, Sai2),
 }
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 sai_hal! {
     SAI3: (sai3, Sai3),
+}
+#[cfg(not(feature = "rm0455"))]
+sai_hal! {
     SAI4: (sai4, Sai4),
 }
@@ -55,12 +55,55 @@
 //!     info!("SD Card Connected: {:?}", card);
 //! }
 //! ```
+//!
+//! ## Multi-block writes
+//!
+//! For sustained writes, such as logging, use
+//! [`write_blocks`](crate::sdmmc::Sdmmc#method.write_blocks). The card is told how
+//! many blocks will follow, so that it can pre-erase them, and the data is
+//! transferred by the internal DMA (IDMA) of the SDMMC.
+//!
+//! The IDMA reads word aligned buffers directly. Other buffers are copied
+//! through a [`StagingBuffer`](crate::sdmmc::StagingBuffer), if one is
+//! set. Both must be in memory that the IDMA can access: AXI SRAM for
+//! `SDMMC1`, and also the D2 SRAMs for `SDMMC2`.
+//!
+//! ```
+//! #[link_section = ".axisram"]
+//! static mut STAGING: StagingBuffer = StagingBuffer::new();
+//!
+//! sdmmc.set_staging_buffer(unsafe { &mut STAGING });
+//! sdmmc.write_blocks(address, &data)?;
+//! ```
+//!
+//! If the data cache is enabled, a buffer written directly should be in a
+//! non-cacheable region, or cleaned from the cache before the write. The
+//! driver cleans the staging buffer itself.
+//!
+//! ## Timeouts
+//!
+//! Each phase of a transaction has a deadline, so that a card that stops
+//! responding returns an error instead of hanging the driver. A data phase
+//! is timed by the data timer of the SDMMC, and returns
+//! [`Error::Timeout`](crate::sdmmc::Error). Waiting for the SDMMC or for
+//! a busy card returns
+//! [`Error::SoftwareTimeout`](crate::sdmmc::Error) with the
+//! [`Phase`](crate::sdmmc::Phase) that did not complete.
+//!
+//! ```
+//! sdmmc.set_timeouts(Timeouts {
+//!     busy: 500.ms(),
+//!     ..Default::default()
+//! });
+//! ```
 
 // Adapted from stm32f4xx-hal
 // https://github.com/stm32-rs/stm32f4xx-hal/blob/master/src/sdio.rs
 
 use core::fmt;
 
+use cortex_m::peripheral::CBP;
+
 use sdio_host::{
     BusWidth, CardCapacity, CardStatus, CurrentState, SDStatus, CID, CSD, OCR,
     SCR,
@@ -71,7 +114,7 @@ use crate::gpio::gpiob::{PB14, PB15, PB3, PB4, PB8, PB9};
 use crate::gpio::gpioc::{PC1, PC10, PC11, PC12, PC6, PC7, PC8, PC9};
 use crate::gpio::gpiod::{PD2, PD6, PD7};
 use crate::gpio::gpiog::PG11;
-use crate::time::Hertz;
+use crate::time::{Hertz, MilliSeconds};
 
 use crate::gpio::{Alternate, AF10, AF11, AF12, AF9};
 //use crate::gpio:::{AF7, AF8};
@@ -230,7 +273,7 @@ impl Default for Signalling {
 #[derive(Debug, Copy, Clone)]
 pub enum Error {
     Timeout,
-    SoftwareTimeout,
+    SoftwareTimeout(Phase),
     UnsupportedCardVersion,
     UnsupportedCardType,
     Crc,
@@ -239,6 +282,117 @@ pub enum Error {
     NoCard,
     BadClock,
     SignalingSwitchFailed,
+    TxUnderFlow,
+    DmaTransfer,
+}
+
+/// Phase of a transaction that did not complete before its deadline, see
+/// [`Timeouts`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Phase {
+    /// A state machine of the SDMMC did not become idle, or a command did
+    /// not complete
+    Command,
+    /// The card stayed busy, either powering up or programming after a
+    /// write
+    Busy,
+    /// A DMA transfer did not end, although the data timer of the SDMMC
+    /// did not expire for any block
+    Data,
+}
+
+/// Deadlines for the phases of a transaction with the card
+///
+/// A card that stops responding makes the driver return an error once a
+/// deadline has passed, instead of waiting for it indefinitely.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Timeouts {
+    /// Deadline for the state machines of the SDMMC to become idle, and for
+    /// a command to complete
+    pub command: MilliSeconds,
+    /// Deadline for the card to start sending or accepting each block of
+    /// data. Counted by the data timer of the SDMMC, and reported as
+    /// [`Error::Timeout`]. A DMA transfer of `n` blocks also has a deadline
+    /// of `n + 1` times this, reported as [`Phase::Data`]
+    pub data: MilliSeconds,
+    /// Deadline for the card to power up, and to finish programming after
+    /// a write
+    pub busy: MilliSeconds,
+}
+
+impl Default for Timeouts {
+    /// The maximum busy times of the SD specification, with some margin.
+    /// The data timeout is the write timeout of SDXC cards, which is longer
+    /// than the read timeout
+    fn default() -> Self {
+        Timeouts {
+            command: MilliSeconds(10),
+            data: MilliSeconds(500),
+            busy: MilliSeconds(1000),
+        }
+    }
+}
+
+/// Deadline counted down in AHB clock cycles
+struct Deadline {
+    cycles: u64,
+}
+
+impl Deadline {
+    /// Counts down `cycles`, and returns `true` once the deadline has
+    /// passed
+    fn expired(&mut self, cycles: u32) -> bool {
+        self.cycles = self.cycles.saturating_sub(u64::from(cycles));
+        self.cycles == 0
+    }
+}
+
+/// SDMMC configuration error, see [try_new](Sdmmc#method.try_new)
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// sdmmc_ker_ck is not running
+    KernelClockNotRunning,
+    /// sdmmc_ker_ck is too fast to generate the identification mode clock
+    KernelClockTooFast,
+}
+
+/// Number of blocks in a [`StagingBuffer`]
+pub const STAGING_BLOCKS: usize = 16;
+
+/// Buffer through which [`write_blocks`](Sdmmc#method.write_blocks) copies
+/// data that is not word aligned. Aligned to the 32-byte lines of the data
+/// cache
+#[repr(align(32))]
+pub struct StagingBuffer([u8; STAGING_BLOCKS * 512]);
+
+impl StagingBuffer {
+    /// Creates a staging buffer
+    pub const fn new() -> Self {
+        StagingBuffer([0; STAGING_BLOCKS * 512])
+    }
+
+    /// Cleans the buffer from the data cache, so that the IDMA reads the
+    /// data written by the CPU
+    fn clean_dcache(&self) {
+        let start = self.0.as_ptr() as usize;
+
+        // NOTE(unsafe) Cleaning by address has no effect on other memory,
+        // and no effect if the data cache is disabled
+        unsafe {
+            let cbp = &*CBP::ptr();
+            for line in (start..start + self.0.len()).step_by(32) {
+                cbp.dccmvac.write(line as u32);
+            }
+        }
+        cortex_m::asm::dsb();
+    }
+}
+
+impl Default for StagingBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// A SD command
@@ -330,6 +484,10 @@ pub struct Sdmmc<SDMMC> {
     signalling: Signalling,
     /// Card
     card: Option<Card>,
+    /// Staging buffer for unaligned writes
+    staging: Option<&'static mut StagingBuffer>,
+    /// Deadlines of the command, data and busy phases
+    timeouts: Timeouts,
 }
 impl<SDMMC> fmt::Debug for Sdmmc<SDMMC> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -387,6 +545,33 @@ impl<S> Sdmmc<S> {
             _ => Err(Error::BadClock),
         }
     }
+
+    /// Sets the deadlines of the command, data and busy phases
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Returns the deadlines of the command, data and busy phases
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    /// Starts a deadline of `timeout`
+    ///
+    /// Each poll of the SDMMC counts down the fewest AHB clock cycles that
+    /// it can take, so the deadline may pass later than `timeout`, but
+    /// never earlier.
+    fn deadline(&self, timeout: MilliSeconds) -> Deadline {
+        Deadline {
+            cycles: u64::from(self.hclk.0) * u64::from(timeout.0) / 1000,
+        }
+    }
+
+    /// Fewest AHB clock cycles taken by a command with a short response:
+    /// 48 bits in each direction, and 8 bus cycles between them
+    fn command_cycles(&self) -> u32 {
+        104 * (self.hclk.0 / self.clock.0).max(1)
+    }
 }
 
 macro_rules! sdmmc {
@@ -427,9 +612,7 @@ macro_rules! sdmmc {
                     self.clock = new_clock;
 
                     // CPSMACT and DPSMACT must be 0 to set CLKDIV
-                    while self.sdmmc.star.read().dpsmact().bit_is_set()
-                        || self.sdmmc.star.read().cpsmact().bit_is_set()
-                    {}
+                    self.wait_idle()?;
 
                     self.sdmmc
                         .clkcr
@@ -439,21 +622,52 @@ macro_rules! sdmmc {
                 }
 
                 /// Initialise SDMMC peripheral
+                ///
+                /// # Panics
+                ///
+                /// Panics if sdmmc_ker_ck is not running, or if it is too
+                /// fast to generate the identification mode clock. Use
+                /// [`try_new`](#method.try_new) to return an error instead
                 pub fn $sdmmcX(
                     sdmmc: $SDMMCX,
                     bus_width: BusWidth,
                     prec: rec::$Rec,
                     clocks: &CoreClocks,
                 ) -> Self {
-                    // Enable and reset peripheral
-                    let prec = prec.enable().reset();
+                    match Self::try_new(sdmmc, bus_width, prec, clocks) {
+                        Ok(sdmmc) => sdmmc,
+                        Err((ConfigError::KernelClockNotRunning, _, _)) => {
+                            panic!("sdmmc_ker_ck not running!")
+                        }
+                        Err((ConfigError::KernelClockTooFast, _, _)) => {
+                            panic!("SDMMC too slow. Cannot be generated from ker_ck")
+                        }
+                    }
+                }
 
+                /// Initialise SDMMC peripheral
+                ///
+                /// Returns an error if sdmmc_ker_ck is not running, or if
+                /// it is too fast to generate the identification mode clock.
+                /// The error is returned with the peripheral and its `rec`,
+                /// so that the peripheral can be configured again
+                pub fn try_new(
+                    sdmmc: $SDMMCX,
+                    bus_width: BusWidth,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> Result<Self, (ConfigError, $SDMMCX, rec::$Rec)> {
                     let hclk = clocks.hclk();
                     let ker_ck = match prec.get_kernel_clk_mux() {
                         rec::SdmmcClkSel::PLL1_Q => clocks.pll1_q_ck(),
                         rec::SdmmcClkSel::PLL2_R => clocks.pll2_r_ck(),
-                    }
-                    .expect("sdmmc_ker_ck not running!");
+                    };
+                    let ker_ck = match ker_ck {
+                        Some(ker_ck) => ker_ck,
+                        None => {
+                            return Err((ConfigError::KernelClockNotRunning, sdmmc, prec))
+                        }
+                    };
 
                     // For tuning the phase of the receive sampling clock, a
                     // DLYB block can be connected between sdmmc_io_in_ck and
@@ -461,8 +675,15 @@ macro_rules! sdmmc {
 
                     // While the SD/SDIO card or eMMC is in identification mode,
                     // the SDMMC_CK frequency must be less than 400 kHz.
-                    let (clkdiv, clock) = Self::clk_div(ker_ck, 400_000)
-                        .expect("SDMMC too slow. Cannot be generated from ker_ck");
+                    let (clkdiv, clock) = match Self::clk_div(ker_ck, 400_000) {
+                        Ok(div) => div,
+                        Err(_) => {
+                            return Err((ConfigError::KernelClockTooFast, sdmmc, prec))
+                        }
+                    };
+
+                    // Enable and reset peripheral
+                    let prec = prec.enable().reset();
 
                     // Configure clock
                     sdmmc.clkcr.write(|w| unsafe {
@@ -484,7 +705,7 @@ macro_rules! sdmmc {
                         .power
                         .modify(|_, w| unsafe { w.pwrctrl().bits(PowerCtrl::Off as u8) });
 
-                    Sdmmc {
+                    Ok(Sdmmc {
                         sdmmc,
                         ker_ck,
                         hclk,
@@ -492,7 +713,9 @@ macro_rules! sdmmc {
                         card: None,
                         clock,
                         signalling: Default::default(),
-                    }
+                        staging: None,
+                        timeouts: Timeouts::default(),
+                    })
 
                     // drop prec: ker_ck can no longer be modified
                 }
@@ -520,6 +743,7 @@ macro_rules! sdmmc {
                         return Err(Error::UnsupportedCardVersion);
                     };
 
+                    let mut deadline = self.deadline(self.timeouts.busy);
                     let ocr = loop {
                         // Signal that next command is a app command
                         self.cmd(Cmd::app_cmd(0))?; // CMD55
@@ -540,6 +764,9 @@ macro_rules! sdmmc {
                             // Power up done
                             break ocr;
                         }
+                        if deadline.expired(2 * self.command_cycles()) {
+                            return Err(Error::SoftwareTimeout(Phase::Busy));
+                        }
                     };
 
                     if ocr.high_capacity() {
@@ -585,9 +812,7 @@ macro_rules! sdmmc {
                     self.cmd(Cmd::cmd6(acmd_arg))?; // ACMD6: Bus Width
 
                     // CPSMACT and DPSMACT must be 0 to set WIDBUS
-                    while self.sdmmc.star.read().dpsmact().bit_is_set()
-                        || self.sdmmc.star.read().cpsmact().bit_is_set()
-                    {}
+                    self.wait_idle()?;
                     self.sdmmc.clkcr.modify(|_, w| unsafe {
                         w.widbus().bits(match width {
                             BusWidth::One => 0,
@@ -646,13 +871,27 @@ macro_rules! sdmmc {
                     self.clock
                 }
 
+                /// Waits for the command and data state machines to be
+                /// idle
+                fn wait_idle(&self) -> Result<(), Error> {
+                    let mut deadline = self.deadline(self.timeouts.command);
+                    while self.sdmmc.star.read().dpsmact().bit_is_set()
+                        || self.sdmmc.star.read().cpsmact().bit_is_set()
+                    {
+                        if deadline.expired(1) {
+                            return Err(Error::SoftwareTimeout(Phase::Command));
+                        }
+                    }
+                    Ok(())
+                }
+
                 /// Start a transfer
                 fn start_datapath_transfer(
                     &self,
                     length_bytes: u32,
                     block_size: u8,
                     direction: Dir,
-                ) {
+                ) -> Result<(), Error> {
                     assert!(block_size <= 14, "Block size up to 2^14 bytes");
 
                     // Block Size must be greater than 0 ( != 1 byte) in DDR mode
@@ -668,14 +907,16 @@ macro_rules! sdmmc {
                     };
 
                     // Command AND Data state machines must be idle
-                    while self.sdmmc.star.read().dpsmact().bit_is_set()
-                        || self.sdmmc.star.read().cpsmact().bit_is_set()
-                    {}
+                    self.wait_idle()?;
 
                     // Data timeout, in bus cycles
+                    let datatime = u64::from(self.clock.0)
+                        * u64::from(self.timeouts.data.0)
+                        / 1000;
+                    let datatime = datatime.min(u64::from(u32::MAX)) as u32;
                     self.sdmmc
                         .dtimer
-                        .write(|w| unsafe { w.datatime().bits(5_000_000) });
+                        .write(|w| unsafe { w.datatime().bits(datatime) });
                     // Data length, in bytes
                     self.sdmmc
                         .dlenr
@@ -689,6 +930,8 @@ macro_rules! sdmmc {
                             .dten()
                             .set_bit() // Enable transfer
                     });
+
+                    Ok(())
                 }
 
                 /// Read block from card.
@@ -704,7 +947,7 @@ macro_rules! sdmmc {
                     self.cmd(Cmd::set_block_length(512))?; // CMD16
 
                     // Setup read command
-                    self.start_datapath_transfer(512, 9, Dir::CardToHost);
+                    self.start_datapath_transfer(512, 9, Dir::CardToHost)?;
                     self.cmd(Cmd::read_single_block(address))?;
 
                     let mut i = 0;
@@ -751,7 +994,7 @@ macro_rules! sdmmc {
                     self.cmd(Cmd::set_block_length(512))?; // CMD16
 
                     // Setup read command
-                    self.start_datapath_transfer(512 * n_blocks as u32, 9, Dir::CardToHost);
+                    self.start_datapath_transfer(512 * n_blocks as u32, 9, Dir::CardToHost)?;
                     self.cmd(Cmd::read_multiple_blocks(address))?;
 
                     let mut i = 0;
@@ -794,7 +1037,7 @@ macro_rules! sdmmc {
                     self.cmd(Cmd::set_block_length(512))?; // CMD16
 
                     // Setup write command
-                    self.start_datapath_transfer(512, 9, Dir::HostToCard);
+                    self.start_datapath_transfer(512, 9, Dir::HostToCard)?;
                     self.cmd(Cmd::write_single_block(address))?; // CMD24
 
                     let mut i = 0;
@@ -824,19 +1067,181 @@ macro_rules! sdmmc {
                     err_from_datapath_sm!(status);
                     self.clear_static_interrupt_flags();
 
-                    let mut timeout: u32 = 0xFFFF_FFFF;
-
                     // Try to read card status (ACMD13)
-                    while timeout > 0 {
+                    let mut deadline = self.deadline(self.timeouts.busy);
+                    loop {
                         match self.read_sd_status() {
                             Ok(_) => return Ok(()),
                             Err(Error::Timeout) => (), // Try again
                             Err(e) => return Err(e),
                         }
 
-                        timeout -= 1;
+                        if deadline.expired(self.command_cycles()) {
+                            return Err(Error::SoftwareTimeout(Phase::Busy));
+                        }
+                    }
+                }
+
+                /// Sets the buffer through which
+                /// [`write_blocks`](#method.write_blocks) copies data that
+                /// is not word aligned
+                pub fn set_staging_buffer(
+                    &mut self,
+                    staging: &'static mut StagingBuffer,
+                ) {
+                    self.staging = Some(staging);
+                }
+
+                /// Write multiple blocks to card. The length of the buffer
+                /// must be a multiple of 512.
+                ///
+                /// `address` is the block address.
+                ///
+                /// The number of blocks is sent to the card first (ACMD23),
+                /// so that it can pre-erase them, and the blocks are
+                /// transferred by the IDMA. A buffer that is not word
+                /// aligned is copied through the staging buffer, or written
+                /// one block at a time if no staging buffer is set.
+                pub fn write_blocks(
+                    &mut self,
+                    address: u32,
+                    buffer: &[u8],
+                ) -> Result<(), Error> {
+                    let _card = self.card()?;
+
+                    assert!(buffer.len() % 512 == 0,
+                            "Buffer length must be a multiple of 512");
+                    if buffer.is_empty() {
+                        return Ok(());
+                    }
+
+                    if buffer.as_ptr() as usize % 4 == 0 {
+                        let n_blocks = (buffer.len() / 512) as u32;
+                        return self.write_blocks_idma(address, buffer.as_ptr(), n_blocks);
+                    }
+
+                    let mut address = address;
+                    match self.staging.take() {
+                        Some(staging) => {
+                            let mut result = Ok(());
+                            for chunk in buffer.chunks(STAGING_BLOCKS * 512) {
+                                staging.0[..chunk.len()].copy_from_slice(chunk);
+                                staging.clean_dcache();
+
+                                let n_blocks = (chunk.len() / 512) as u32;
+                                result = self.write_blocks_idma(
+                                    address, staging.0.as_ptr(), n_blocks);
+                                if result.is_err() {
+                                    break;
+                                }
+                                address += n_blocks;
+                            }
+                            self.staging = Some(staging);
+                            result
+                        }
+                        None => {
+                            for block in buffer.chunks(512) {
+                                let mut wb = [0u8; 512];
+                                wb.copy_from_slice(block);
+                                self.write_block(address, &wb)?;
+                                address += 1;
+                            }
+                            Ok(())
+                        }
+                    }
+                }
+
+                /// Write `n_blocks` blocks at `data` to card using the
+                /// IDMA. `data` must be word aligned
+                fn write_blocks_idma(
+                    &mut self,
+                    address: u32,
+                    data: *const u8,
+                    n_blocks: u32,
+                ) -> Result<(), Error> {
+                    let card = self.card()?;
+                    let rca = card.rca;
+
+                    self.cmd(Cmd::set_block_length(512))?; // CMD16
+
+                    // Pre-erase the blocks
+                    self.cmd(Cmd::app_cmd(rca << 16))?; // APP
+                    self.cmd(Cmd::set_wr_blk_erase_count(n_blocks))?; // ACMD23
+
+                    // Configure the IDMA in single buffer mode
+                    self.sdmmc
+                        .idmabase0r
+                        .write(|w| unsafe { w.idmabase0().bits(data as u32) });
+                    self.sdmmc.idmactrlr.write(|w| w.idmaen().set_bit());
+
+                    let result = self.write_idma_transfer(address, n_blocks);
+
+                    self.sdmmc.idmactrlr.write(|w| w.idmaen().clear_bit());
+                    if result.is_err()
+                        && self.sdmmc.star.read().dpsmact().bit_is_set()
+                    {
+                        // Abort the DPSM, so that the next transfer can
+                        // start. The transfer has failed already
+                        let _ = self.cmd(Cmd::stop_transmission()); // CMD12
+                    }
+                    self.clear_static_interrupt_flags();
+                    result?;
+
+                    // Wait for the card to finish programming
+                    let mut deadline = self.deadline(self.timeouts.busy);
+                    loop {
+                        match self.read_status() {
+                            Ok(r1) if r1.state() == CurrentState::Transfer => return Ok(()),
+                            Ok(_) | Err(Error::Timeout) => (), // Try again
+                            Err(e) => return Err(e),
+                        }
+
+                        if deadline.expired(self.command_cycles()) {
+                            return Err(Error::SoftwareTimeout(Phase::Busy));
+                        }
                     }
-                    Err(Error::SoftwareTimeout)
+                }
+
+                /// Transfers `n_blocks` blocks from the IDMA to the card
+                fn write_idma_transfer(
+                    &self,
+                    address: u32,
+                    n_blocks: u32,
+                ) -> Result<(), Error> {
+                    // Setup write command
+                    self.start_datapath_transfer(512 * n_blocks, 9, Dir::HostToCard)?;
+                    self.cmd(Cmd::write_multiple_blocks(address))?; // CMD25
+
+                    // The data timer bounds each block. This deadline also
+                    // ends the wait if the DPSM does not run
+                    let timeout = self.timeouts.data.0
+                        .saturating_mul(n_blocks.saturating_add(1));
+                    let mut deadline = self.deadline(MilliSeconds(timeout));
+                    let mut status;
+                    while {
+                        status = self.sdmmc.star.read();
+                        !(status.txunderr().bit()
+                          || status.dcrcfail().bit()
+                          || status.dtimeout().bit()
+                          || status.idmate().bit()
+                          || status.dataend().bit())
+                    } {
+                        // Each poll takes at least one AHB clock cycle
+                        if deadline.expired(1) {
+                            let _ = self.cmd(Cmd::stop_transmission()); // CMD12
+                            return Err(Error::SoftwareTimeout(Phase::Data));
+                        }
+                    }
+
+                    self.cmd(Cmd::stop_transmission())?; // CMD12
+
+                    err_from_datapath_sm!(status);
+                    if status.txunderr().bit() {
+                        return Err(Error::TxUnderFlow);
+                    } else if status.idmate().bit() {
+                        return Err(Error::DmaTransfer);
+                    }
+                    Ok(())
                 }
 
                 /// Query the card status (CMD13, returns R1)
@@ -859,7 +1264,7 @@ macro_rules! sdmmc {
                     self.cmd(Cmd::app_cmd(card.rca << 16))?; // APP
 
                     // Prepare the transfer
-                    self.start_datapath_transfer(64, 6, Dir::CardToHost);
+                    self.start_datapath_transfer(64, 6, Dir::CardToHost)?;
                     self.cmd(Cmd::card_status(0))?; // ACMD13
 
                     let mut status = [0u32; 16];
@@ -900,7 +1305,7 @@ macro_rules! sdmmc {
                     self.cmd(Cmd::set_block_length(8))?; // CMD16
                     self.cmd(Cmd::app_cmd(card.rca << 16))?;
 
-                    self.start_datapath_transfer(8, 3, Dir::CardToHost);
+                    self.start_datapath_transfer(8, 3, Dir::CardToHost)?;
                     self.cmd(Cmd::cmd51())?;
 
                     let mut scr = [0; 2];
@@ -957,7 +1362,7 @@ macro_rules! sdmmc {
                         };
 
                     // Prepare the transfer
-                    self.start_datapath_transfer(64, 6, Dir::CardToHost);
+                    self.start_datapath_transfer(64, 6, Dir::CardToHost)?;
                     self.cmd(Cmd::cmd6(set_function))?; // CMD6
 
                     let mut status = [0u32; 16];
@@ -1077,7 +1482,12 @@ macro_rules! sdmmc {
                     });
 
                     // CP state machine must be idle
-                    while self.sdmmc.star.read().cpsmact().bit_is_set() {}
+                    let mut deadline = self.deadline(self.timeouts.command);
+                    while self.sdmmc.star.read().cpsmact().bit_is_set() {
+                        if deadline.expired(1) {
+                            return Err(Error::SoftwareTimeout(Phase::Command));
+                        }
+                    }
 
                     // Command arg
                     self.sdmmc
@@ -1102,17 +1512,16 @@ macro_rules! sdmmc {
                             .set_bit()
                     });
 
-                    let mut timeout: u32 = 0xFFFF_FFFF;
-
                     let mut status;
                     if cmd.resp == Response::None {
                         // Wait for CMDSENT or a timeout
                         while {
                             status = self.sdmmc.star.read();
                             !(status.ctimeout().bit() || status.cmdsent().bit())
-                                && timeout > 0
                         } {
-                            timeout -= 1;
+                            if deadline.expired(1) {
+                                return Err(Error::SoftwareTimeout(Phase::Command));
+                            }
                         }
                     } else {
                         // Wait for CMDREND or CCRCFAIL or a timeout
@@ -1121,16 +1530,15 @@ macro_rules! sdmmc {
                             !(status.ctimeout().bit()
                               || status.cmdrend().bit()
                               || status.ccrcfail().bit())
-                                && timeout > 0
                         } {
-                            timeout -= 1;
+                            if deadline.expired(1) {
+                                return Err(Error::SoftwareTimeout(Phase::Command));
+                            }
                         }
                     }
 
                     if status.ctimeout().bit_is_set() {
                         return Err(Error::Timeout);
-                    } else if timeout == 0 {
-                        return Err(Error::SoftwareTimeout);
                     } else if status.ccrcfail().bit() {
                         return Err(Error::Crc);
                     }
@@ -1221,6 +1629,16 @@ impl Cmd {
         Cmd::new(24, addr, Response::Short)
     }
 
+    /// CMD25: Multiple Block Write
+    const fn write_multiple_blocks(addr: u32) -> Cmd {
+        Cmd::new(25, addr, Response::Short)
+    }
+
+    /// ACMD23: Number of blocks to pre-erase before writing
+    const fn set_wr_blk_erase_count(n_blocks: u32) -> Cmd {
+        Cmd::new(23, n_blocks, Response::Short)
+    }
+
     const fn app_op_cmd(arg: u32) -> Cmd {
         Cmd::new(41, arg, Response::Short)
     }
@@ -0,0 +1,194 @@
+//! Integrity attestation
+//!
+//! An attestation [`Report`] combines a SHA-256 measurement of flash
+//! regions, calculated with the [hash processor](crate::hash), with the
+//! readout protection (RDP) level and the secure-area status from the
+//! option bytes. It can be sent to a remote party to check the firmware
+//! running on the device.
+//!
+//! This module is only available for the parts with the hash processor
+//! (750, 753, 7B3, 7B0, 730, 733, 735).
+//!
+//! # Example
+//!
+//! ```
+//! use stm32h7xx_hal::{flash, secure};
+//!
+//! let mut hash = dp.HASH.hash(ccdr.peripheral.HASH);
+//!
+//! // unsafe: The bootloader occupies the first 128kB of flash
+//! let bootloader =
+//!     unsafe { core::slice::from_raw_parts(0x0800_0000 as *const u8, 0x2_0000) };
+//!
+//! let report = secure::attest(&mut hash, &[bootloader]);
+//! assert_eq!(report.rdp, flash::RdpLevel::Level1);
+//!
+//! send_to_server(&report.to_bytes());
+//! ```
+//!
+//! The measurement includes the address and length of each region, so the
+//! same data at a different location gives a different measurement.
+//!
+//! The report is not signed. A remote party can only trust it if the code
+//! that produces it is itself protected, for example by running it from a
+//! secure area.
+
+use crate::flash::RdpLevel;
+use crate::hash::{Algorithm, Hash, Output};
+use crate::stm32::FLASH;
+
+// Encoding of a secure area in a report, as in FLASH_SCAR_CURx
+const SCAR_END_SHIFT: u32 = 16;
+const SCAR_DMES: u32 = 1 << 31;
+
+/// Length of the encoded report, see [`Report::to_bytes`]
+pub const REPORT_LEN: usize = 44;
+
+/// Secure area of a flash bank, as programmed in the option bytes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SecureArea {
+    /// The SEC_AREA_START field. See the reference manual for the
+    /// granularity on each part
+    pub start: u16,
+    /// The SEC_AREA_END field
+    pub end: u16,
+    /// The secure area is erased when the RDP level is lowered to level 0
+    pub erase_on_regression: bool,
+}
+
+impl SecureArea {
+    fn new(start: u16, end: u16, erase_on_regression: bool) -> Option<Self> {
+        // The area is disabled when it ends before it starts
+        if start > end {
+            return None;
+        }
+        Some(SecureArea {
+            start,
+            end,
+            erase_on_regression,
+        })
+    }
+}
+
+/// Returns the flash registers. Only the read-only option status registers
+/// are used
+fn flash() -> &'static crate::stm32::flash::RegisterBlock {
+    // NOTE(unsafe) Atomic reads with no side effects
+    unsafe { &*FLASH::ptr() }
+}
+
+/// Returns the current readout protection level
+pub fn rdp_level() -> RdpLevel {
+    RdpLevel::from_bits(flash().optsr_cur.read().rdp().bits())
+}
+
+/// Returns `true` if the security option is enabled, so that secure areas
+/// are protected
+pub fn security_enabled() -> bool {
+    flash().optsr_cur.read().security().bit_is_set()
+}
+
+/// Returns the secure areas of flash bank 1 and bank 2. Single bank parts
+/// have no secure area in bank 2
+pub fn secure_areas() -> [Option<SecureArea>; 2] {
+    let scar1 = flash().scar_cur1.read();
+    let bank1 = SecureArea::new(
+        scar1.sec_area_start1().bits(),
+        scar1.sec_area_end1().bits(),
+        scar1.dmes1().bit_is_set(),
+    );
+
+    #[cfg(not(any(feature = "rm0468", feature = "stm32h7b0")))]
+    let bank2 = {
+        let scar2 = flash().scar_cur2.read();
+        SecureArea::new(
+            scar2.sec_area_start2().bits(),
+            scar2.sec_area_end2().bits(),
+            scar2.dmes2().bit_is_set(),
+        )
+    };
+    #[cfg(any(feature = "rm0468", feature = "stm32h7b0"))]
+    let bank2 = None;
+
+    [bank1, bank2]
+}
+
+/// Attestation report
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Report {
+    /// SHA-256 measurement of the regions
+    pub measurement: Output,
+    /// Readout protection level
+    pub rdp: RdpLevel,
+    /// The security option is enabled
+    pub security: bool,
+    /// Secure areas of bank 1 and bank 2
+    pub secure_areas: [Option<SecureArea>; 2],
+}
+
+impl Report {
+    /// Encodes the report for transmission
+    ///
+    /// | Bytes   | Content                                       |
+    /// |---------|-----------------------------------------------|
+    /// | 0 - 31  | Measurement                                   |
+    /// | 32      | RDP level: 0, 1 or 2                          |
+    /// | 33      | Bit 0: security option enabled                |
+    /// | 34 - 35 | Reserved, zero                                |
+    /// | 36 - 39 | Secure area of bank 1, FLASH_SCAR_CUR1 layout |
+    /// | 40 - 43 | Secure area of bank 2, FLASH_SCAR_CUR2 layout |
+    ///
+    /// Multi-byte values are little-endian. A disabled secure area is
+    /// encoded as start 1, end 0
+    pub fn to_bytes(&self) -> [u8; REPORT_LEN] {
+        let mut bytes = [0; REPORT_LEN];
+        bytes[..32].copy_from_slice(self.measurement.as_ref());
+        bytes[32] = match self.rdp {
+            RdpLevel::Level0 => 0,
+            RdpLevel::Level1 => 1,
+            RdpLevel::Level2 => 2,
+        };
+        bytes[33] = self.security as u8;
+
+        for (area, bytes) in self
+            .secure_areas
+            .iter()
+            .zip(bytes[36..].chunks_exact_mut(4))
+        {
+            let scar = match area {
+                Some(area) => {
+                    u32::from(area.start)
+                        | (u32::from(area.end) << SCAR_END_SHIFT)
+                        | if area.erase_on_regression {
+                            SCAR_DMES
+                        } else {
+                            0
+                        }
+                }
+                None => 1,
+            };
+            bytes.copy_from_slice(&scar.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Measures `regions` and returns an attestation report
+///
+/// For each region, its address and length (as little-endian 32 bit
+/// values) and then its contents are fed to the SHA-256 calculation
+pub fn attest(hash: &mut Hash, regions: &[&[u8]]) -> Report {
+    let mut digest = hash.start(Algorithm::Sha256);
+    for region in regions {
+        digest.update(&(region.as_ptr() as u32).to_le_bytes());
+        digest.update(&(region.len() as u32).to_le_bytes());
+        digest.update(region);
+    }
+
+    Report {
+        measurement: digest.finalize(),
+        rdp: rdp_level(),
+        security: security_enabled(),
+        secure_areas: secure_areas(),
+    }
+}
@@ -1,8 +1,109 @@
 //! Serial
+//!
+//! # Wakeup from Stop mode
+//!
+//! When its kernel clock is HSI, CSI or LSE, a USART can wake the device from
+//! Stop mode on reception of a start bit, an address or a complete
+//! character. The kernel clock can be selected using the
+//! [`PeripheralREC`](crate::rcc::PeripheralREC) before the USART is
+//! created.
+//!
+//! ```
+//! let mut serial = dp.USART1.serial(
+//!     (tx, rx),
+//!     9_600.bps(),
+//!     ccdr.peripheral.USART1.kernel_clk_mux(rec::Usart16ClkSel::HSI_KER),
+//!     &ccdr.clocks,
+//! ).unwrap();
+//!
+//! serial.enable_wakeup_from_stop(WakeupEvent::StartBit).unwrap();
+//! serial.listen(Event::Wakeup);
+//! exti.listen(exti::Event::USART1);
+//! ```
+//!
+//! # LPUART1
+//!
+//! LPUART1 is in the D3 domain, and is used in the same way as the other
+//! USARTs. Its baud rate generator is designed for low kernel clock
+//! frequencies, so it can run from the LSE at up to 9600 baud. In
+//! combination with [`LowPowerMode::Autonomous`] its kernel clock keeps
+//! running when the D1 domain is stopped, and it can wake the device from
+//! Stop mode.
+//!
+//! ```
+//! let mut serial = dp.LPUART1.serial(
+//!     (tx, rx),
+//!     9_600.bps(),
+//!     ccdr.peripheral
+//!         .LPUART1
+//!         .kernel_clk_mux(rec::Lpuart1ClkSel::LSE)
+//!         .low_power(LowPowerMode::Autonomous),
+//!     &ccdr.clocks,
+//! ).unwrap();
+//!
+//! serial.enable_wakeup_from_stop(WakeupEvent::StartBit).unwrap();
+//! serial.listen(Event::Wakeup);
+//! exti.listen(exti::Event::LPUART1);
+//! ```
+//!
+//! [`LowPowerMode::Autonomous`]: crate::rcc::LowPowerMode::Autonomous
+//!
+//! # Reception with DMA
+//!
+//! At high baud rates, [`CircularRx`] receives continuously into a circular
+//! DMA buffer. Frames of any length are delimited by the idle line
+//! detection of the USART: when the line has been idle for one character
+//! time after a frame, everything received since the previous frame is
+//! copied into a user buffer.
+//!
+//! ```
+//! #[link_section = ".axisram.buffers"]
+//! static mut BUFFER: [u8; 256] = [0; 256];
+//!
+//! let (tx, rx) = serial.split();
+//! let mut rx = CircularRx::new(
+//!     streams.1,
+//!     rx,
+//!     unsafe { &mut BUFFER },
+//!     DmaConfig::default(),
+//! );
+//! rx.listen_idle();
+//!
+//! // In the USART interrupt
+//! let mut frame = [0; 256];
+//! if let Some(len) = rx.read_frame(&mut frame) {
+//!     // frame[..len] contains the data received since the last frame
+//! }
+//! ```
+//!
+//! The circular buffer must be large enough to hold the data received
+//! between two calls to [`read_frame`](CircularRx::read_frame) or
+//! [`read`](CircularRx::read), otherwise data is overwritten. If the data
+//! cache is enabled, the buffer should be in a non-cacheable region.
+//!
+//! # Half-duplex
+//!
+//! With [`half_duplex`](Serial#method.half_duplex), the USART transmits
+//! and receives on the TX pin only. On a line shared by several nodes,
+//! [`bus_write`](Serial#method.bus_write) monitors the line while
+//! transmitting and reports a collision if another node transmits at the
+//! same time
+//!
+//! ```
+//! let mut serial = dp.USART1.serial((tx, serial::NoRx), 100_000.bps(), ...).unwrap();
+//! serial.half_duplex(true);
+//!
+//! match serial.bus_write(&frame) {
+//!     Err(serial::Error::Collision) => { /* back off and retry */ }
+//!     ...
+//! }
+//! ```
 
 use core::fmt;
 use core::marker::PhantomData;
+use core::ops::Deref;
 use core::ptr;
+use core::sync::atomic::{fence, Ordering};
 
 use embedded_hal::blocking::serial as serial_block;
 use embedded_hal::prelude::*;
@@ -15,10 +116,18 @@ use stm32h7::Variant::Val;
 
 #[cfg(feature = "rm0455")]
 use crate::stm32::rcc::cdccip2r::{USART16910SEL_A, USART234578SEL_A};
-#[cfg(not(feature = "rm0455"))]
+#[cfg(feature = "rm0468")]
+use crate::stm32::rcc::d2ccip2r::{USART16910SEL_A, USART234578SEL_A};
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
 use crate::stm32::rcc::d2ccip2r::{USART16SEL_A, USART234578SEL_A};
+#[cfg(not(feature = "rm0455"))]
+use crate::stm32::rcc::d3ccipr::LPUART1SEL_A;
+#[cfg(feature = "rm0455")]
+use crate::stm32::rcc::srdccipr::LPUART1SEL_A;
 
-use crate::stm32::{UART4, UART5, UART7, UART8};
+use crate::stm32::{LPUART1, UART4, UART5, UART7, UART8};
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+use crate::stm32::{UART9, USART10};
 use crate::stm32::{USART1, USART2, USART3, USART6};
 
 use crate::gpio::gpioa::{
@@ -36,10 +145,22 @@ use crate::gpio::gpioh::{PH13, PH14};
 use crate::gpio::gpioi::PI9;
 #[cfg(not(feature = "stm32h7b0"))]
 use crate::gpio::gpioj::{PJ8, PJ9};
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+use crate::gpio::{
+    gpiod::{PD14, PD15},
+    gpioe::{PE15, PE2, PE3},
+    gpiog::{PG0, PG1, PG11, PG12, PG15},
+};
 
-use crate::gpio::{Alternate, AF11, AF14, AF4, AF6, AF7, AF8};
-use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::dma::{
+    dma::DmaConfig,
+    traits::{DoubleBufferedStream, Stream},
+    DBTransfer, PeripheralToMemory, Transfer,
+};
+use crate::gpio::{Alternate, IntoAnalog, AF11, AF14, AF3, AF4, AF6, AF7, AF8};
+use crate::rcc::{lse_ck, rec, CoreClocks, ResetEnable};
 use crate::time::Hertz;
+use embedded_dma::StaticWriteBuffer;
 
 use crate::Never;
 
@@ -55,6 +176,53 @@ pub enum Error {
     Overrun,
     /// Parity check error
     Parity,
+    /// A byte received in half-duplex mode differed from the byte
+    /// transmitted, see [bus_write](Serial#method.bus_write)
+    Collision,
+}
+
+/// Serial configuration error, see
+/// [try_new](Serial#method.try_new)
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The kernel clock is not running
+    KernelClockNotRunning,
+    /// The baud rate cannot be generated from the kernel clock
+    BaudRate,
+    /// The number of stop bits is not supported
+    StopBits,
+}
+
+/// Returns the USART baud rate divisor for 16 times oversampling
+fn usart_brr(usart_ker_ck: u32, baudrate: u32) -> Result<u16, ConfigError> {
+    let usartdiv = usart_ker_ck.checked_div(baudrate).unwrap_or(0);
+    if !(16..=0xFFFF).contains(&usartdiv) {
+        return Err(ConfigError::BaudRate);
+    }
+    Ok(usartdiv as u16)
+}
+
+/// Returns the LPUART baud rate divisor, BRR = 256 * f_ker / baud, rounded
+/// to the nearest integer. Values below 0x300 are not allowed
+fn lpuart_brr(lpuart_ker_ck: u32, baudrate: u32) -> Result<u32, ConfigError> {
+    if baudrate == 0 {
+        return Err(ConfigError::BaudRate);
+    }
+    let brr = ((u64::from(lpuart_ker_ck) << 8) + u64::from(baudrate / 2))
+        / u64::from(baudrate);
+    if !(0x300..=0xF_FFFF).contains(&brr) {
+        return Err(ConfigError::BaudRate);
+    }
+    Ok(brr as u32)
+}
+
+/// Returns the number of bytes in a circular buffer of length `len` that
+/// have been written and not yet read. `remaining` is the number of
+/// transfers remaining in the current DMA cycle
+fn circular_available(len: usize, remaining: usize, read: usize) -> usize {
+    let write = len - remaining;
+    (write + len - read) % len
 }
 
 /// Interrupt event
@@ -66,8 +234,27 @@ pub enum Event {
     Txe,
     /// Idle line state detected
     Idle,
+    /// Wakeup from Stop mode, see
+    /// [enable_wakeup_from_stop](Serial#method.enable_wakeup_from_stop)
+    Wakeup,
+}
+
+/// Events that can wake the device from Stop mode
+#[derive(Copy, Clone, PartialEq)]
+pub enum WakeupEvent {
+    /// A frame containing this 7-bit address is received
+    AddressMatch(u8),
+    /// A start bit is detected
+    StartBit,
+    /// A complete character is received
+    RxNotEmpty,
 }
 
+/// Wakeup from Stop mode is not possible with the current kernel clock.
+/// The kernel clock must be HSI, CSI or LSE
+#[derive(Debug)]
+pub struct InvalidWakeupClock;
+
 pub mod config {
     use crate::time::Hertz;
 
@@ -183,6 +370,20 @@ pub struct NoRx;
 /// A filler type for when the Ck pin is unnecessary
 pub struct NoCk;
 
+macro_rules! filler_into_analog {
+    ($($NoPin:ident),+) => {
+        $(
+            impl IntoAnalog for $NoPin {
+                type Analog = $NoPin;
+                fn into_analog(self) -> $NoPin {
+                    self
+                }
+            }
+        )+
+    };
+}
+filler_into_analog!(NoTx, NoRx, NoCk);
+
 macro_rules! usart_pins {
     ($($USARTX:ty: TX: [$($TX:ty),*] RX: [$($RX:ty),*] CK: [$($CK:ty),*])+) => {
         $(
@@ -349,6 +550,67 @@ uart_pins! {
             PJ9<Alternate<AF8>>
         ]
 }
+uart_pins! {
+    LPUART1:
+        TX: [
+            NoTx,
+            PA9<Alternate<AF3>>,
+            PB6<Alternate<AF8>>
+        ]
+        RX: [
+            NoRx,
+            PA10<Alternate<AF3>>,
+            PB7<Alternate<AF8>>
+        ]
+}
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+usart_pins! {
+    USART10:
+        TX: [
+            NoTx,
+            PE3<Alternate<AF11>>,
+            PG12<Alternate<AF4>>
+        ]
+        RX: [
+            NoRx,
+            PE2<Alternate<AF11>>,
+            PG11<Alternate<AF4>>
+        ]
+        CK: [
+            NoCk,
+            PE15<Alternate<AF11>>,
+            PG15<Alternate<AF4>>
+        ]
+}
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+uart_pins! {
+    UART9:
+        TX: [
+            NoTx,
+            PD15<Alternate<AF11>>,
+            PG1<Alternate<AF11>>
+        ]
+        RX: [
+            NoRx,
+            PD14<Alternate<AF11>>,
+            PG0<Alternate<AF11>>
+        ]
+}
+
+/// A USART, UART or LPUART peripheral instance
+///
+/// The LPUART has its own register block, so the register block is the
+/// target of `Deref` rather than a fixed type.
+pub trait Instance: Deref + crate::Sealed {
+    /// Reset and enable control of the instance
+    type Rec: ResetEnable;
+
+    /// Global interrupt of the instance
+    const INTERRUPT: stm32::Interrupt;
+
+    /// Returns a pointer to the register block
+    fn ptr() -> *const <Self as Deref>::Target;
+}
 
 /// Serial abstraction
 pub struct Serial<USART> {
@@ -365,6 +627,20 @@ pub struct Tx<USART> {
     _usart: PhantomData<USART>,
 }
 
+/// Serial receiver with a circular DMA buffer
+pub struct CircularRx<STREAM, USART, BUF>
+where
+    STREAM: Stream,
+    Rx<USART>: crate::dma::traits::TargetAddress<PeripheralToMemory>,
+{
+    transfer: Transfer<STREAM, Rx<USART>, PeripheralToMemory, BUF, DBTransfer>,
+    /// Address of the buffer
+    buffer: usize,
+    len: usize,
+    /// Index of the next byte to be read
+    read: usize,
+}
+
 pub trait SerialExt<USART>: Sized {
     type Rec: ResetEnable;
 
@@ -408,6 +684,19 @@ pub trait SerialExt<USART>: Sized {
     }
 }
 
+/// Changes the configuration of a serial port that is already in use
+pub trait Reconfigure {
+    /// Waits for the transmission to complete, then sets the baud rate and
+    /// the frame from `config`. Interrupts and DMA remain enabled
+    ///
+    /// On an error, the serial port keeps its previous baud rate
+    fn reconfigure(
+        &mut self,
+        config: impl Into<config::Config>,
+        clocks: &CoreClocks,
+    ) -> Result<(), ConfigError>;
+}
+
 macro_rules! usart {
     ($(
         $USARTX:ident: ($usartX:ident, $Rec:ident, $pclkX:ident),
@@ -416,39 +705,33 @@ macro_rules! usart {
             /// Configures a USART peripheral to provide serial
             /// communication
             impl Serial<$USARTX> {
-                pub fn $usartX(
+                /// Returns an error if the kernel clock is not running, or
+                /// if the baud rate cannot be generated from it. The error
+                /// is returned with the peripheral and its `rec`, so that
+                /// the peripheral can be configured again
+                pub fn try_new(
                     usart: $USARTX,
                     config: impl Into<config::Config>,
                     prec: rec::$Rec,
                     clocks: &CoreClocks
-                ) -> Result<Self, config::InvalidConfig>
+                ) -> Result<Self, (ConfigError, $USARTX, rec::$Rec)>
                 {
-                    use crate::stm32::usart1::cr2::STOP_A as STOP;
-                    use self::config::*;
-
                     let config = config.into();
 
-                    // Enable clock for USART and reset
-                    prec.enable().reset();
-
                     // Get kernel clock
-	                let usart_ker_ck = match Self::kernel_clk(clocks) {
-                        Some(ker_hz) => ker_hz.0,
-                        _ => panic!("$USARTX kernel clock not running!")
+                    let usart_ker_ck = match Self::kernel_clk(clocks) {
+                        Some(ker_ck) => ker_ck.0,
+                        None => {
+                            return Err((ConfigError::KernelClockNotRunning, usart, prec))
+                        }
                     };
 
+                    // Enable clock for USART and reset
+                    let prec = prec.enable().reset();
+
                     // Prescaler not used for now
-                    let usart_ker_ck_presc = usart_ker_ck;
                     usart.presc.reset();
 
-                    // Calculate baudrate divisor
-                    let usartdiv = usart_ker_ck_presc / config.baudrate.0;
-                    assert!(usartdiv <= 65_536);
-
-                    // 16 times oversampling, OVER8 = 0
-                    let brr = usartdiv as u16;
-                    usart.brr.write(|w| { w.brr().bits(brr) });
-
                     // disable hardware flow control
                     // TODO enable DMA
                     // usart.cr3.write(|w| w.rtse().clear_bit().ctse().clear_bit());
@@ -457,8 +740,33 @@ macro_rules! usart {
                     usart.cr2.reset();
                     usart.cr3.reset();
 
+                    let mut serial = Serial { usart };
+                    if let Err(e) = serial.configure(&config, usart_ker_ck) {
+                        return Err((e, serial.usart, prec.disable()));
+                    }
+
+                    Ok(serial)
+                }
+
+                /// Sets the baud rate and the frame, and enables the USART
+                fn configure(
+                    &mut self,
+                    config: &config::Config,
+                    usart_ker_ck: u32,
+                ) -> Result<(), ConfigError> {
+                    use crate::stm32::usart1::cr2::STOP_A as STOP;
+                    use self::config::*;
+
+                    // Prescaler not used for now
+                    let usart_ker_ck_presc = usart_ker_ck;
+
+                    // Calculate baudrate divisor.
+                    // 16 times oversampling, OVER8 = 0
+                    let brr = usart_brr(usart_ker_ck_presc, config.baudrate.0)?;
+                    self.usart.brr.write(|w| { w.brr().bits(brr) });
+
                     // Set stop bits
-                    usart.cr2.write(|w| {
+                    self.usart.cr2.modify(|_, w| {
                         w.stop().variant(match config.stopbits {
                             StopBits::STOP0P5 => STOP::STOP0P5,
                             StopBits::STOP1 => STOP::STOP1,
@@ -469,7 +777,7 @@ macro_rules! usart {
 
                     // Enable transmission and receiving
                     // and configure frame
-                    usart.cr1.write(|w| {
+                    self.usart.cr1.modify(|_, w| {
                         w.fifoen()
                             .set_bit() // FIFO mode enabled
                             .over8()
@@ -497,7 +805,53 @@ macro_rules! usart {
                             })
                     });
 
-                    Ok(Serial { usart })
+                    Ok(())
+                }
+            }
+
+            usart_common! { $USARTX: ($usartX, $Rec), }
+        )+
+    }
+}
+
+// Methods common to USARTs and LPUARTs
+macro_rules! usart_common {
+    ($(
+        $USARTX:ident: ($usartX:ident, $Rec:ident),
+    )+) => {
+        $(
+            impl crate::Sealed for $USARTX {}
+            impl Instance for $USARTX {
+                type Rec = rec::$Rec;
+
+                const INTERRUPT: stm32::Interrupt = stm32::Interrupt::$USARTX;
+
+                #[inline(always)]
+                fn ptr() -> *const <Self as Deref>::Target {
+                    $USARTX::ptr()
+                }
+            }
+
+            impl Serial<$USARTX> {
+                /// Configures the peripheral to provide serial communication
+                ///
+                /// # Panics
+                ///
+                /// Panics if the kernel clock is not running. Use
+                /// [`try_new`](#method.try_new) to return an error instead
+                pub fn $usartX(
+                    usart: $USARTX,
+                    config: impl Into<config::Config>,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks
+                ) -> Result<Self, config::InvalidConfig>
+                {
+                    Self::try_new(usart, config, prec, clocks).map_err(|(e, _, _)| match e {
+                        ConfigError::KernelClockNotRunning => {
+                            hal_panic!("$USARTX kernel clock not running!")
+                        }
+                        _ => config::InvalidConfig,
+                    })
                 }
 
                 /// Enables the Rx DMA stream.
@@ -532,6 +886,9 @@ macro_rules! usart {
                         Event::Idle => {
                             self.usart.cr1.modify(|_, w| w.idleie().enabled())
                         },
+                        Event::Wakeup => {
+                            self.usart.cr3.modify(|_, w| w.wufie().set_bit())
+                        },
                     }
                 }
 
@@ -547,11 +904,68 @@ macro_rules! usart {
                         Event::Idle => {
                             self.usart.cr1.modify(|_, w| w.idleie().disabled())
                         },
+                        Event::Wakeup => {
+                            self.usart.cr3.modify(|_, w| w.wufie().clear_bit())
+                        },
                     }
                     let _ = self.usart.cr1.read();
                     let _ = self.usart.cr1.read(); // Delay 2 peripheral clocks
                 }
 
+                /// Allows `event` to wake the device from Stop mode
+                ///
+                /// Returns an error if the kernel clock for this USART does
+                /// not support wakeup from Stop mode. The kernel clock must
+                /// be HSI, CSI or LSE.
+                ///
+                /// To generate an interrupt on wakeup, listen for
+                /// [`Event::Wakeup`] and enable the corresponding EXTI line.
+                pub fn enable_wakeup_from_stop(&mut self, event: WakeupEvent)
+                                               -> Result<(), InvalidWakeupClock>
+                {
+                    if !Self::kernel_clk_in_stop() {
+                        return Err(InvalidWakeupClock);
+                    }
+
+                    // WUS and ADD can only be written when UE = 0
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+
+                    match event {
+                        WakeupEvent::AddressMatch(addr) => {
+                            assert!(addr < 0x80, "Address must be 7 bits");
+                            self.usart.cr2.modify(|_, w| {
+                                w.addm7().set_bit().add().bits(addr)
+                            });
+                            self.usart.cr3.modify(|_, w| unsafe { w.wus().bits(0b00) });
+                        },
+                        WakeupEvent::StartBit => {
+                            self.usart.cr3.modify(|_, w| unsafe { w.wus().bits(0b10) });
+                        },
+                        WakeupEvent::RxNotEmpty => {
+                            self.usart.cr3.modify(|_, w| unsafe { w.wus().bits(0b11) });
+                        },
+                    }
+
+                    self.usart.cr1.modify(|_, w| w.uesm().set_bit().ue().enabled());
+
+                    Ok(())
+                }
+
+                /// Prevents the USART from waking the device from Stop mode
+                pub fn disable_wakeup_from_stop(&mut self) {
+                    self.usart.cr1.modify(|_, w| w.uesm().clear_bit());
+                }
+
+                /// Return true if the wakeup from Stop mode flag is set
+                pub fn is_wakeup(& self) -> bool {
+                    unsafe { (*$USARTX::ptr()).isr.read().wuf().bit_is_set() }
+                }
+
+                /// Clears the wakeup from Stop mode flag
+                pub fn clear_wakeup(&mut self) {
+                    self.usart.icr.write(|w| w.wucf().set_bit());
+                }
+
                 /// Return true if the line idle status is set
                 pub fn is_idle(& self) -> bool {
                     unsafe { (*$USARTX::ptr()).isr.read().idle().bit_is_set() }
@@ -567,6 +981,55 @@ macro_rules! usart {
                     unsafe { (*$USARTX::ptr()).isr.read().rxne().bit_is_set() }
                 }
 
+                /// Enables or disables single-wire half-duplex mode
+                ///
+                /// In this mode the TX pin is used for both transmission
+                /// and reception, and the RX pin is not used. For a line
+                /// shared with other nodes, configure the TX pin as open
+                /// drain with a pull-up.
+                pub fn half_duplex(&mut self, enable: bool) {
+                    // HDSEL can only be written when the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    self.usart.cr3.modify(|_, w| w.hdsel().bit(enable));
+                    self.usart.cr1.modify(|_, w| w.ue().set_bit());
+                }
+
+                /// Transmits `bytes` on a shared half-duplex line, checking
+                /// that each byte is received back unchanged
+                ///
+                /// Returns [`Error::Collision`] as soon as a byte differs
+                /// or is received with a framing or noise error, which
+                /// indicates that another node transmitted at the same
+                /// time. A byte that is not received back by the end of its
+                /// transmission is also a collision, so the call does not
+                /// wait on the line. Any bytes received before the call are
+                /// discarded.
+                pub fn bus_write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+                    while self.is_rxne() {
+                        let _ = serial::Read::read(self);
+                    }
+
+                    for &byte in bytes {
+                        let _ = block!(serial::Write::write(self, byte));
+                        // The receiver samples the stop bit before the
+                        // transmission is complete, so the echo is
+                        // available once TC is set. TC is set after one
+                        // frame whatever the state of the line
+                        let _ = block!(serial::Write::flush(self));
+                        match serial::Read::read(self) {
+                            Ok(echo) if echo == byte => {}
+                            Ok(_)
+                            | Err(nb::Error::WouldBlock)
+                            | Err(nb::Error::Other(Error::Framing))
+                            | Err(nb::Error::Other(Error::Noise)) => {
+                                return Err(Error::Collision)
+                            }
+                            Err(nb::Error::Other(e)) => return Err(e),
+                        }
+                    }
+                    Ok(())
+                }
+
                 pub fn split(self) -> (Tx<$USARTX>, Rx<$USARTX>) {
                     (
                         Tx {
@@ -584,6 +1047,49 @@ macro_rules! usart {
 
                     self.usart
                 }
+
+                /// Waits for the transmission to complete, resets the
+                /// USART and disables its clock, then returns `pins` in
+                /// analog mode
+                ///
+                /// `pins` must be pins of this USART. The driver does not
+                /// keep the pins given to the constructor. To return them
+                /// here, keep them and use
+                /// [`serial_unchecked`](SerialExt#tymethod.serial_unchecked).
+                pub fn deinit<PINS: Pins<$USARTX> + IntoAnalog>(
+                    self,
+                    pins: PINS,
+                ) -> ($USARTX, rec::$Rec, PINS::Analog) {
+                    let usart = self.release();
+                    let prec = rec::$Rec { _marker: PhantomData }
+                        .reset()
+                        .disable();
+
+                    (usart, prec, pins.into_analog())
+                }
+            }
+
+            impl Reconfigure for Serial<$USARTX> {
+                fn reconfigure(
+                    &mut self,
+                    config: impl Into<config::Config>,
+                    clocks: &CoreClocks,
+                ) -> Result<(), ConfigError> {
+                    let config = config.into();
+                    let ker_ck = Self::kernel_clk(clocks)
+                        .ok_or(ConfigError::KernelClockNotRunning)?.0;
+
+                    // Wait until both TXFIFO and shift register are empty
+                    while self.usart.isr.read().tc().bit_is_clear() {}
+
+                    // The baud rate and the frame can only be written when
+                    // the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    let result = self.configure(&config, ker_ck);
+                    self.usart.cr1.modify(|_, w| w.ue().set_bit());
+
+                    result
+                }
             }
 
             impl SerialExt<$USARTX> for $USARTX {
@@ -678,17 +1184,145 @@ macro_rules! usart {
                     unsafe { &*$USARTX::ptr() }.cr3.modify(|_, w| w.dmar().clear_bit());
                 }
 
+                /// Start listening for `Idle` event
+                pub fn listen_idle(&mut self) {
+                    // unsafe: idleie bit accessed by Rx part only
+                    unsafe { &*$USARTX::ptr() }.cr1.modify(|_, w| w.idleie().enabled());
+                }
+
+                /// Stop listening for `Idle` event
+                pub fn unlisten_idle(&mut self) {
+                    // unsafe: idleie bit accessed by Rx part only
+                    let cr1 = &unsafe { &*$USARTX::ptr() }.cr1;
+                    cr1.modify(|_, w| w.idleie().disabled());
+                    let _ = cr1.read();
+                    let _ = cr1.read(); // Delay 2 peripheral clocks
+                }
+
                 /// Return true if the line idle status is set
                 pub fn is_idle(& self) -> bool {
                     unsafe { (*$USARTX::ptr()).isr.read().idle().bit_is_set() }
                 }
 
+                /// Clears the line idle status
+                pub fn clear_idle(&mut self) {
+                    // unsafe: write-only register, idlecf bit accessed by Rx
+                    // part only
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.idlecf().set_bit()) };
+                    let _ = unsafe { (*$USARTX::ptr()).isr.read() };
+                    let _ = unsafe { (*$USARTX::ptr()).isr.read() }; // Delay 2 peripheral clocks
+                }
+
                 /// Return true if the rx register is not empty (and can be read)
                 pub fn is_rxne(& self) -> bool {
                     unsafe { (*$USARTX::ptr()).isr.read().rxne().bit_is_set() }
                 }
             }
 
+            impl<STREAM, BUF> CircularRx<STREAM, $USARTX, BUF>
+            where
+                STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+                BUF: StaticWriteBuffer<Word = u8>,
+            {
+                /// Starts receiving into `buffer` with a circular DMA
+                /// transfer on `stream`. Memory increment and circular mode
+                /// are enabled in `config`
+                pub fn new(
+                    stream: STREAM,
+                    rx: Rx<$USARTX>,
+                    mut buffer: BUF,
+                    config: DmaConfig,
+                ) -> Self {
+                    // unsafe: Only used to read bytes that the DMA has
+                    // already written
+                    let (ptr, len) = unsafe { buffer.static_write_buffer() };
+                    let config = config.memory_increment(true).circular_buffer(true);
+
+                    let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+                        Transfer::init(stream, rx, buffer, None, config);
+                    transfer.start(|rx| {
+                        rx.clear_idle();
+                        rx.enable_dma_rx();
+                    });
+
+                    CircularRx {
+                        transfer,
+                        buffer: ptr as usize,
+                        len,
+                        read: 0,
+                    }
+                }
+
+                /// Returns the number of bytes received and not yet read
+                pub fn available(&self) -> usize {
+                    let remaining = self.transfer.get_number_of_transfers() as usize;
+                    circular_available(self.len, remaining, self.read)
+                }
+
+                /// Copies received bytes into `buf`, returning the number
+                /// of bytes copied
+                pub fn read(&mut self, buf: &mut [u8]) -> usize {
+                    let count = core::cmp::min(self.available(), buf.len());
+
+                    // Ensure the DMA writes are visible before reading
+                    fence(Ordering::SeqCst);
+                    for byte in buf[..count].iter_mut() {
+                        // unsafe: read is always less than len
+                        *byte = unsafe {
+                            ptr::read_volatile((self.buffer + self.read) as *const u8)
+                        };
+                        self.read = (self.read + 1) % self.len;
+                    }
+                    count
+                }
+
+                /// If the line has become idle since the last call, clears
+                /// the idle status and copies the bytes received since the
+                /// last frame into `buf`. Returns the number of bytes
+                /// copied, or `None` if the line has not become idle
+                ///
+                /// If the frame is longer than `buf`, the remaining bytes
+                /// are returned by the next call to `read`
+                pub fn read_frame(&mut self, buf: &mut [u8]) -> Option<usize> {
+                    // Rx is owned by the transfer
+                    let mut rx: Rx<$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    if !rx.is_idle() {
+                        return None;
+                    }
+                    rx.clear_idle();
+
+                    Some(self.read(buf))
+                }
+
+                /// Start listening for `Idle` event
+                pub fn listen_idle(&mut self) {
+                    // Rx is owned by the transfer
+                    let mut rx: Rx<$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.listen_idle();
+                }
+
+                /// Stop listening for `Idle` event
+                pub fn unlisten_idle(&mut self) {
+                    // Rx is owned by the transfer
+                    let mut rx: Rx<$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.unlisten_idle();
+                }
+
+                /// Stops reception and releases the DMA stream, the serial
+                /// receiver and the buffer
+                pub fn free(self) -> (STREAM, Rx<$USARTX>, BUF) {
+                    let (stream, mut rx, buffer, _) = self.transfer.free();
+                    rx.disable_dma_rx();
+                    (stream, rx, buffer)
+                }
+            }
+
             impl serial::Write<u8> for Serial<$USARTX> {
                 type Error = Never;
 
@@ -787,6 +1421,107 @@ macro_rules! usart {
     }
 }
 
+macro_rules! lpuart {
+    ($(
+        $LPUARTX:ident: ($lpuartX:ident, $Rec:ident),
+    )+) => {
+        $(
+            /// Configures a LPUART peripheral to provide serial
+            /// communication
+            impl Serial<$LPUARTX> {
+                /// Returns an error if the kernel clock is not running, or
+                /// if the baud rate cannot be generated from it. The error
+                /// is returned with the peripheral and its `rec`, so that
+                /// the peripheral can be configured again
+                ///
+                /// The LPUART supports 1 or 2 stop bits. The kernel clock
+                /// must be between 3 and 4096 times the baud rate, so
+                /// the LSE can be used for baud rates up to 9600
+                pub fn try_new(
+                    usart: $LPUARTX,
+                    config: impl Into<config::Config>,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks
+                ) -> Result<Self, (ConfigError, $LPUARTX, rec::$Rec)>
+                {
+                    let config = config.into();
+
+                    // Get kernel clock
+                    let lpuart_ker_ck = match Self::kernel_clk(clocks) {
+                        Some(ker_ck) => ker_ck.0,
+                        None => {
+                            return Err((ConfigError::KernelClockNotRunning, usart, prec))
+                        }
+                    };
+
+                    // Enable clock for LPUART and reset
+                    let prec = prec.enable().reset();
+
+                    // Prescaler not used for now
+                    usart.presc.reset();
+
+                    // Reset registers to disable advanced LPUART features
+                    usart.cr2.reset();
+                    usart.cr3.reset();
+
+                    let mut serial = Serial { usart };
+                    if let Err(e) = serial.configure(&config, lpuart_ker_ck) {
+                        return Err((e, serial.usart, prec.disable()));
+                    }
+
+                    Ok(serial)
+                }
+
+                /// Sets the baud rate and the frame, and enables the LPUART
+                fn configure(
+                    &mut self,
+                    config: &config::Config,
+                    lpuart_ker_ck: u32,
+                ) -> Result<(), ConfigError> {
+                    use self::config::*;
+
+                    // Calculate baudrate divisor
+                    let brr = lpuart_brr(lpuart_ker_ck, config.baudrate.0)?;
+
+                    // Set stop bits
+                    let stop = match config.stopbits {
+                        StopBits::STOP1 => 0b00,
+                        StopBits::STOP2 => 0b10,
+                        _ => return Err(ConfigError::StopBits),
+                    };
+                    self.usart.brr.write(|w| unsafe { w.bits(brr) });
+                    self.usart.cr2.modify(|_, w| unsafe { w.stop().bits(stop) });
+
+                    // Enable transmission and receiving
+                    // and configure frame
+                    self.usart.cr1.modify(|_, w| {
+                        w.fifoen()
+                            .set_bit() // FIFO mode enabled
+                            .ue()
+                            .set_bit()
+                            .te()
+                            .set_bit()
+                            .re()
+                            .set_bit()
+                            .m1()
+                            .clear_bit()
+                            .m0()
+                            .bit(config.wordlength == WordLength::DataBits9)
+                            .pce()
+                            .bit(config.parity != Parity::ParityNone)
+                            .ps()
+                            .bit(config.parity == Parity::ParityOdd)
+                    });
+
+                    Ok(())
+                }
+            }
+
+            usart_common! { $LPUARTX: ($lpuartX, $Rec), }
+        )+
+    }
+}
+
 macro_rules! usart_sel {
 	($ccip:ident, $SEL:ident, $sel:ident, $PCLK:ident, $pclk:ident;
      $($USARTX:ident: $doc:expr,)+) => {
@@ -804,10 +1539,21 @@ macro_rules! usart_sel {
                         Val($SEL::PLL3_Q) => clocks.pll3_q_ck(),
                         Val($SEL::HSI_KER) => clocks.hsi_ck(),
                         Val($SEL::CSI_KER) => clocks.csi_ck(),
-                        Val($SEL::LSE) => unimplemented!(),
+                        Val($SEL::LSE) => lse_ck(),
                         _ => unreachable!(),
                     }
                 }
+
+                /// Returns `true` if the current kernel clock for
+                #[doc=$doc]
+                /// can run in Stop mode
+                fn kernel_clk_in_stop() -> bool {
+                    // unsafe: read only
+                    let ccip = unsafe { (*stm32::RCC::ptr()).$ccip.read() };
+
+                    matches!(ccip.$sel().variant(),
+                             Val($SEL::HSI_KER) | Val($SEL::CSI_KER) | Val($SEL::LSE))
+                }
             }
         )+
     }
@@ -824,20 +1570,43 @@ usart! {
     UART7: (uart7, Uart7, pclk1),
     UART8: (uart8, Uart8, pclk1),
 }
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+usart! {
+    USART10: (usart10, Usart10, pclk2),
 
-#[cfg(not(feature = "rm0455"))]
+    UART9: (uart9, Uart9, pclk2),
+}
+
+lpuart! {
+    LPUART1: (lpuart1, Lpuart1),
+}
+
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
 usart_sel! {
     d2ccip2r, USART16SEL_A, usart16sel, RCC_PCLK2, pclk2;
 
     USART1: "USART1",
     USART6: "USART6",
 }
+#[cfg(feature = "rm0468")]
+usart_sel! {
+    d2ccip2r, USART16910SEL_A, usart16910sel, RCC_PCLK2, pclk2;
+
+    USART1: "USART1",
+    USART6: "USART6",
+    USART10: "USART10",
+
+    UART9: "UART9",
+}
 #[cfg(feature = "rm0455")]
 usart_sel! {
     cdccip2r, USART16910SEL_A, usart16910sel, RCC_PCLK2, pclk2;
 
     USART1: "USART1",
     USART6: "USART6",
+    USART10: "USART10",
+
+    UART9: "UART9",
 }
 
 #[cfg(not(feature = "rm0455"))]
@@ -865,6 +1634,19 @@ usart_sel! {
     UART7: "UART7",
 }
 
+#[cfg(not(feature = "rm0455"))]
+usart_sel! {
+    d3ccipr, LPUART1SEL_A, lpuart1sel, RCC_PCLK4, pclk4;
+
+    LPUART1: "LPUART1",
+}
+#[cfg(feature = "rm0455")]
+usart_sel! {
+    srdccipr, LPUART1SEL_A, lpuart1sel, RCC_PCLK4, pclk4;
+
+    LPUART1: "LPUART1",
+}
+
 impl<USART> fmt::Write for Tx<USART>
 where
     Tx<USART>: serial::Write<u8>,
@@ -874,3 +1656,40 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usart_brr_range() {
+        assert_eq!(usart_brr(100_000_000, 115_200), Ok(868));
+        assert_eq!(usart_brr(100_000_000, 6_250_000), Ok(16));
+        assert_eq!(
+            usart_brr(100_000_000, 7_000_000),
+            Err(ConfigError::BaudRate)
+        );
+        assert_eq!(usart_brr(100_000_000, 1_000), Err(ConfigError::BaudRate));
+        assert_eq!(usart_brr(100_000_000, 0), Err(ConfigError::BaudRate));
+    }
+
+    #[test]
+    fn lpuart_brr_range() {
+        // 9600 baud from the LSE
+        assert_eq!(lpuart_brr(32_768, 9_600), Ok(0x36A));
+        assert_eq!(lpuart_brr(32_768, 19_200), Err(ConfigError::BaudRate));
+        assert_eq!(lpuart_brr(100_000_000, 115_200), Ok(222_222));
+        assert_eq!(lpuart_brr(100_000_000, 9_600), Err(ConfigError::BaudRate));
+        assert_eq!(lpuart_brr(100_000_000, 0), Err(ConfigError::BaudRate));
+    }
+
+    #[test]
+    fn circular_wrap() {
+        // Nothing written
+        assert_eq!(circular_available(256, 256, 0), 0);
+        assert_eq!(circular_available(256, 156, 0), 100);
+        assert_eq!(circular_available(256, 156, 90), 10);
+        // Write index wrapped around
+        assert_eq!(circular_available(256, 246, 200), 66);
+    }
+}
@@ -1,7 +1,7 @@
 //! Serial Peripheral Interface (SPI)
 //!
 //! This module implements the [embedded-hal](embedded-hal) traits for
-//! master mode SPI.
+//! master and slave mode SPI.
 //!
 //! # Usage
 //!
@@ -43,6 +43,56 @@
 //! let _: spi:Spi<_, _, u8> = dp.SPI1.spi((sck, spi::NoMiso, mosi), spi::MODE_0, 1.mhz(), ccdr.peripheral.SPI1, &ccdr.clocks);
 //! ```
 //!
+//! ## Hardware CS
+//!
+//! In master mode the CS output can be managed by the SPI peripheral with
+//! [`Config::manage_cs`], with a configurable polarity, delay and
+//! de-assertion between data frames. Transactions of a fixed number of
+//! frames can be followed by a hardware CRC
+//!
+//! ```
+//! let config = spi::Config::new(spi::MODE_0)
+//!     .manage_cs()
+//!     .suspend_when_inactive()
+//!     .cs_delay(1e-6)
+//!     .inter_word_delay(500e-9)
+//!     .transfer_size(4)
+//!     .crc(0x07, 8);
+//! ```
+//!
+//! For devices that need a guaranteed CS high time between transactions,
+//! give the times with [`Config::cs_setup_time`] and
+//! [`Config::cs_idle_time`]. They are converted to cycles of the SCK that
+//! is generated from the kernel clock, and creating the SPI fails with
+//! [`ConfigError::CsTiming`] if they are too long for the hardware
+//!
+//! ```
+//! let config = spi::Config::new(spi::MODE_0)
+//!     .manage_cs()
+//!     .suspend_when_inactive()
+//!     .cs_setup_time(100.ns())
+//!     .cs_idle_time(200.ns());
+//! ```
+//!
+//! ## Slave mode
+//!
+//! The SPI can also operate as a slave with [`Config::slave`]. The
+//! frequency is then ignored. When the slave is selected by the external
+//! NSS pin, pass it as the fourth element of the pins tuple
+//!
+//! ```
+//! let config = spi::Config::new(spi::MODE_0)
+//!     .slave()
+//!     .manage_cs()
+//!     .underrun(spi::UnderrunPolicy::RepeatTransmitted,
+//!               spi::UnderrunDetection::StartOfFrame);
+//!
+//! let spi = dp.SPI1.spi((sck, miso, mosi, nss), config, 1.mhz(), ccdr.peripheral.SPI1, &ccdr.clocks);
+//! ```
+//!
+//! The Texas Instruments synchronous serial frame format can be selected
+//! with [`Config::frame_format`], in both master and slave mode.
+//!
 //! ## Clocks
 //!
 //! The bitrate calculation is based upon the clock currently assigned
@@ -68,29 +118,32 @@ use crate::stm32;
 use crate::stm32::spi1::{cfg1::MBR_A as MBR, cfg2::COMM_A as COMM};
 use core::convert::From;
 use core::marker::PhantomData;
+use core::ops::Deref;
 use core::ptr;
 use stm32h7::Variant::Val;
 
 use crate::stm32::{SPI1, SPI2, SPI3, SPI4, SPI5, SPI6};
 
-use crate::gpio::gpioa::{PA12, PA5, PA6, PA7, PA9};
-use crate::gpio::gpiob::{PB10, PB13, PB14, PB15, PB2, PB3, PB4, PB5};
+use crate::gpio::gpioa::{PA11, PA12, PA15, PA4, PA5, PA6, PA7, PA9};
+use crate::gpio::gpiob::{
+    PB10, PB12, PB13, PB14, PB15, PB2, PB3, PB4, PB5, PB9,
+};
 use crate::gpio::gpioc::{PC1, PC10, PC11, PC12, PC2, PC3};
 use crate::gpio::gpiod::{PD3, PD6, PD7};
-use crate::gpio::gpioe::{PE12, PE13, PE14, PE2, PE5, PE6};
-use crate::gpio::gpiof::{PF11, PF7, PF8, PF9};
-use crate::gpio::gpiog::{PG11, PG12, PG13, PG14, PG9};
-use crate::gpio::gpioh::{PH6, PH7};
-use crate::gpio::gpioi::{PI1, PI2, PI3};
+use crate::gpio::gpioe::{PE11, PE12, PE13, PE14, PE2, PE4, PE5, PE6};
+use crate::gpio::gpiof::{PF11, PF6, PF7, PF8, PF9};
+use crate::gpio::gpiog::{PG10, PG11, PG12, PG13, PG14, PG8, PG9};
+use crate::gpio::gpioh::{PH5, PH6, PH7};
+use crate::gpio::gpioi::{PI0, PI1, PI2, PI3};
 #[cfg(not(feature = "stm32h7b0"))]
 use crate::gpio::gpioj::{PJ10, PJ11};
 #[cfg(not(feature = "stm32h7b0"))]
-use crate::gpio::gpiok::PK0;
+use crate::gpio::gpiok::{PK0, PK1};
 
-use crate::gpio::{Alternate, AF5, AF6, AF7, AF8};
+use crate::gpio::{Alternate, IntoAnalog, AF5, AF6, AF7, AF8};
 
 use crate::rcc::{rec, CoreClocks, ResetEnable};
-use crate::time::Hertz;
+use crate::time::{Hertz, NanoSeconds};
 
 /// SPI error
 #[derive(Debug)]
@@ -104,15 +157,111 @@ pub enum Error {
     Crc,
 }
 
+/// SPI configuration error, see [try_new](Spi#method.try_new)
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The kernel clock is not running
+    KernelClockNotRunning,
+    /// The bus frequency is higher than the kernel clock
+    FrequencyTooHigh,
+    /// The bus frequency is zero
+    FrequencyTooLow,
+    /// The CRC size is not supported by this SPI
+    CrcSize,
+    /// The transfer size is too large for this SPI
+    TransferSize,
+    /// A CS setup or idle time is longer than 15 cycles of SCK
+    CsTiming,
+}
+
+/// Returns the master baud rate prescaler nearest to the ratio of
+/// `spi_ker_ck` to `spi_freq`. The bus frequency can be higher than
+/// `spi_freq`, for example 25MHz for 20MHz from a 100MHz kernel clock
+fn master_baud_rate(
+    spi_ker_ck: u32,
+    spi_freq: u32,
+) -> Result<MBR, ConfigError> {
+    Ok(match spi_ker_ck.checked_div(spi_freq) {
+        None => return Err(ConfigError::FrequencyTooLow),
+        Some(0) => return Err(ConfigError::FrequencyTooHigh),
+        Some(1..=2) => MBR::DIV2,
+        Some(3..=5) => MBR::DIV4,
+        Some(6..=11) => MBR::DIV8,
+        Some(12..=23) => MBR::DIV16,
+        Some(24..=47) => MBR::DIV32,
+        Some(48..=95) => MBR::DIV64,
+        Some(96..=191) => MBR::DIV128,
+        _ => MBR::DIV256,
+    })
+}
+
+/// Returns the division of the kernel clock by the master baud rate
+/// prescaler `mbr`
+fn master_baud_rate_divider(mbr: MBR) -> u32 {
+    match mbr {
+        MBR::DIV2 => 2,
+        MBR::DIV4 => 4,
+        MBR::DIV8 => 8,
+        MBR::DIV16 => 16,
+        MBR::DIV32 => 32,
+        MBR::DIV64 => 64,
+        MBR::DIV128 => 128,
+        MBR::DIV256 => 256,
+    }
+}
+
+/// Returns the number of cycles of an SCK at `sck` for a delay of at least
+/// `delay`, or an error if this is more than 15 cycles
+fn time_cycles(delay: NanoSeconds, sck: u32) -> Result<u8, ConfigError> {
+    let product = u64::from(delay.0) * u64::from(sck);
+    let cycles = (product + 999_999_999) / 1_000_000_000;
+    if cycles > 0xF {
+        return Err(ConfigError::CsTiming);
+    }
+    Ok(cycles as u8)
+}
+
+/// Returns the number of SCK cycles for a delay of `delay` seconds
+fn delay_cycles(delay: f32, spi_freq: u32) -> u8 {
+    let mut cycles: u32 = (delay * spi_freq as f32) as u32;
+
+    // If the delay is specified as non-zero, add 1 to the delay cycles
+    // before truncation to an integer to ensure that we have at least as
+    // many cycles as required.
+    if delay > 0.0_f32 {
+        cycles += 1;
+    }
+
+    // The cycle delay may not be more than 4 bits wide for the
+    // configuration register.
+    cycles.min(0xF) as u8
+}
+
 /// Enabled SPI peripheral (type state)
 pub struct Enabled;
 /// Disabled SPI peripheral (type state)
 pub struct Disabled;
 
+/// An SPI peripheral instance
+pub trait Instance:
+    Deref<Target = stm32::spi1::RegisterBlock> + crate::Sealed
+{
+    /// Reset and enable control of the instance
+    type Rec: ResetEnable;
+
+    /// Global interrupt of the instance
+    const INTERRUPT: stm32::Interrupt;
+
+    /// Returns a pointer to the register block
+    fn ptr() -> *const stm32::spi1::RegisterBlock;
+}
+
 pub trait Pins<SPI> {}
 pub trait PinSck<SPI> {}
 pub trait PinMiso<SPI> {}
 pub trait PinMosi<SPI> {}
+pub trait PinNss<SPI> {}
 
 impl<SPI, SCK, MISO, MOSI> Pins<SPI> for (SCK, MISO, MOSI)
 where
@@ -122,6 +271,18 @@ where
 {
 }
 
+/// A pin for the hardware slave select (NSS) can be passed as the fourth
+/// element of the tuple. This is required when the NSS is managed by the
+/// SPI peripheral.
+impl<SPI, SCK, MISO, MOSI, NSS> Pins<SPI> for (SCK, MISO, MOSI, NSS)
+where
+    SCK: PinSck<SPI>,
+    MISO: PinMiso<SPI>,
+    MOSI: PinMosi<SPI>,
+    NSS: PinNss<SPI>,
+{
+}
+
 /// Specifies the communication mode of the SPI interface.
 #[derive(Copy, Clone)]
 pub enum CommunicationMode {
@@ -135,6 +296,47 @@ pub enum CommunicationMode {
     Receiver,
 }
 
+/// Specifies the frame format of the SPI interface.
+#[derive(Copy, Clone, PartialEq)]
+pub enum FrameFormat {
+    /// Motorola frame format, the default.
+    Motorola,
+
+    /// Texas Instruments synchronous serial frame format.
+    ///
+    /// In this format the clock polarity and phase, the bit order and the
+    /// NSS signal are fixed by the hardware. The SPI mode passed to
+    /// [`Config::new`] is ignored.
+    Ti,
+}
+
+/// Specifies the data transmitted by a slave in the case of an underrun,
+/// i.e. when the master requests data and the TX FIFO is empty.
+#[derive(Copy, Clone, PartialEq)]
+pub enum UnderrunPolicy {
+    /// A constant pattern is transmitted.
+    Pattern(u32),
+
+    /// The last data received from the master is transmitted.
+    RepeatReceived,
+
+    /// The last data transmitted by the slave is transmitted again.
+    RepeatTransmitted,
+}
+
+/// Specifies when an underrun is detected in slave mode.
+#[derive(Copy, Clone, PartialEq)]
+pub enum UnderrunDetection {
+    /// At the beginning of a data frame.
+    StartOfFrame,
+
+    /// At the end of the last data frame.
+    EndOfFrame,
+
+    /// At the beginning of an active NSS signal.
+    StartOfSlaveSelect,
+}
+
 /// A structure for specifying SPI configuration.
 ///
 /// This structure uses builder semantics to generate the configuration.
@@ -154,6 +356,16 @@ pub struct Config {
     managed_cs: bool,
     suspend_when_inactive: bool,
     communication_mode: CommunicationMode,
+    slave: bool,
+    frame_format: FrameFormat,
+    underrun_policy: UnderrunPolicy,
+    underrun_detection: UnderrunDetection,
+    inter_word_delay: Option<f32>,
+    cs_setup_time: Option<NanoSeconds>,
+    cs_idle_time: Option<NanoSeconds>,
+    cs_active_high: bool,
+    transfer_size: u16,
+    crc: Option<(u32, u8)>,
 }
 
 impl Config {
@@ -169,6 +381,16 @@ impl Config {
             managed_cs: false,
             suspend_when_inactive: false,
             communication_mode: CommunicationMode::FullDuplex,
+            slave: false,
+            frame_format: FrameFormat::Motorola,
+            underrun_policy: UnderrunPolicy::Pattern(0),
+            underrun_detection: UnderrunDetection::StartOfFrame,
+            inter_word_delay: None,
+            cs_setup_time: None,
+            cs_idle_time: None,
+            cs_active_high: false,
+            transfer_size: 0,
+            crc: None,
         }
     }
 
@@ -198,6 +420,9 @@ impl Config {
 
     /// CS pin is automatically managed by the SPI peripheral.
     ///
+    /// In slave mode, the slave is selected by the external NSS pin.
+    /// Otherwise a slave is always selected.
+    ///
     /// # Note
     /// SPI is configured in "endless transaction" mode, which means that the SPI CSn pin will
     /// assert when the first data is sent and will not de-assert.
@@ -224,6 +449,117 @@ impl Config {
         self.communication_mode = mode;
         self
     }
+
+    /// Operate as a slave on the SPI bus.
+    ///
+    /// The bus clock is generated by the master, so the frequency passed
+    /// to the constructor is ignored. Use `manage_cs()` to select the slave
+    /// with the external NSS pin.
+    pub fn slave(mut self) -> Self {
+        self.slave = true;
+        self
+    }
+
+    /// Select the frame format of the SPI bus.
+    pub fn frame_format(mut self, format: FrameFormat) -> Self {
+        self.frame_format = format;
+        self
+    }
+
+    /// Select the data transmitted in slave mode when an underrun
+    /// occurs, and when the underrun is detected.
+    ///
+    /// By default, a pattern of zeros is transmitted and underruns are
+    /// detected at the beginning of a data frame.
+    pub fn underrun(
+        mut self,
+        policy: UnderrunPolicy,
+        detection: UnderrunDetection,
+    ) -> Self {
+        self.underrun_policy = policy;
+        self.underrun_detection = detection;
+        self
+    }
+
+    /// Specify a delay between consecutive data frames in master mode.
+    ///
+    /// Note:
+    /// * When CS is managed with `manage_cs()` and `suspend_when_inactive()`,
+    /// CS is de-asserted for this time between each data frame. By default
+    /// there is no delay, unless `suspend_when_inactive()` is used, in which
+    /// case the delay is the same as the `cs_delay()`.
+    ///
+    /// Arguments:
+    /// * `delay` - The delay between data frames in seconds. At most 15
+    /// SCK cycles.
+    pub fn inter_word_delay(mut self, delay: f32) -> Self {
+        self.inter_word_delay = Some(delay);
+        self
+    }
+
+    /// Specify the time from CS assertion to the first edge of SCK in
+    /// master mode. Replaces `cs_delay()`
+    ///
+    /// The number of SCK cycles is calculated from the SCK frequency that
+    /// is generated from the kernel clock, and rounds the time up. An
+    /// error is returned when the SPI is created if the time is longer
+    /// than 15 cycles of SCK.
+    pub fn cs_setup_time(mut self, time: impl Into<NanoSeconds>) -> Self {
+        self.cs_setup_time = Some(time.into());
+        self
+    }
+
+    /// Specify the idle time between consecutive data frames in master
+    /// mode. Replaces `inter_word_delay()`
+    ///
+    /// With `suspend_when_inactive()`, CS is de-asserted for at least this
+    /// time between data frames. The time is calculated and checked as
+    /// for `cs_setup_time()`.
+    ///
+    /// Note:
+    /// * The SPI has no hold time between the last edge of SCK and CS
+    /// de-assertion, which is about half a cycle of SCK.
+    pub fn cs_idle_time(mut self, time: impl Into<NanoSeconds>) -> Self {
+        self.cs_idle_time = Some(time.into());
+        self
+    }
+
+    /// The CS pin is active high. By default, CS is active low.
+    pub fn cs_active_high(mut self) -> Self {
+        self.cs_active_high = true;
+        self
+    }
+
+    /// Specify the number of data frames in each transaction.
+    ///
+    /// When a master has transferred this number of frames, the
+    /// transaction ends and CS is de-asserted, if it is managed by the
+    /// SPI peripheral. The End Of Transfer flag is set, see
+    /// [`is_eot`](Spi#method.is_eot). By default the size is zero, so that
+    /// transactions do not end.
+    ///
+    /// Note:
+    /// * SPI4, SPI5 and SPI6 support up to 1023 frames.
+    pub fn transfer_size(mut self, frames: u16) -> Self {
+        self.transfer_size = frames;
+        self
+    }
+
+    /// Enable hardware CRC calculation.
+    ///
+    /// The CRC is transmitted after the last data frame of each
+    /// transaction, and the received CRC is checked. This requires a
+    /// non-zero `transfer_size()`. A CRC error is reported as
+    /// [`Error::Crc`].
+    ///
+    /// Arguments:
+    /// * `polynomial` - The CRC polynomial, without the highest order bit.
+    /// * `bits` - The length of the CRC in bits, from 4 to 32. SPI4, SPI5
+    /// and SPI6 support up to 16 bits.
+    pub fn crc(mut self, polynomial: u32, bits: u8) -> Self {
+        self.crc = Some((polynomial, bits));
+        self
+    }
 }
 
 impl From<Mode> for Config {
@@ -239,11 +575,26 @@ pub struct NoMiso;
 /// A filler type for when the Mosi pin is unnecessary
 pub struct NoMosi;
 
+macro_rules! filler_into_analog {
+    ($($NoPin:ident),+) => {
+        $(
+            impl IntoAnalog for $NoPin {
+                type Analog = $NoPin;
+                fn into_analog(self) -> $NoPin {
+                    self
+                }
+            }
+        )+
+    };
+}
+filler_into_analog!(NoSck, NoMiso, NoMosi);
+
 macro_rules! pins {
     ($($SPIX:ty:
        SCK: [$($( #[ $pmeta1:meta ] )* $SCK:ty),*]
        MISO: [$($( #[ $pmeta2:meta ] )* $MISO:ty),*]
-       MOSI: [$($( #[ $pmeta3:meta ] )* $MOSI:ty),*])+) => {
+       MOSI: [$($( #[ $pmeta3:meta ] )* $MOSI:ty),*]
+       NSS: [$($( #[ $pmeta4:meta ] )* $NSS:ty),*])+) => {
         $(
             $(
                 $( #[ $pmeta1 ] )*
@@ -257,6 +608,10 @@ macro_rules! pins {
                 $( #[ $pmeta3 ] )*
                 impl PinMosi<$SPIX> for $MOSI {}
             )*
+            $(
+                $( #[ $pmeta4 ] )*
+                impl PinNss<$SPIX> for $NSS {}
+            )*
         )+
     }
 }
@@ -281,6 +636,11 @@ pins! {
             PB5<Alternate<AF5>>,
             PD7<Alternate<AF5>>
         ]
+        NSS: [
+            PA4<Alternate<AF5>>,
+            PA15<Alternate<AF5>>,
+            PG10<Alternate<AF5>>
+        ]
     SPI2:
         SCK: [
             NoSck,
@@ -304,6 +664,13 @@ pins! {
             PC3<Alternate<AF5>>,
             PI3<Alternate<AF5>>
         ]
+        NSS: [
+            PA11<Alternate<AF5>>,
+            PB4<Alternate<AF7>>,
+            PB9<Alternate<AF5>>,
+            PB12<Alternate<AF5>>,
+            PI0<Alternate<AF5>>
+        ]
     SPI3:
         SCK: [
             NoSck,
@@ -322,6 +689,10 @@ pins! {
             PC12<Alternate<AF6>>,
             PD6<Alternate<AF5>>
         ]
+        NSS: [
+            PA4<Alternate<AF6>>,
+            PA15<Alternate<AF6>>
+        ]
     SPI4:
         SCK: [
             NoSck,
@@ -338,6 +709,10 @@ pins! {
             PE6<Alternate<AF5>>,
             PE14<Alternate<AF5>>
         ]
+        NSS: [
+            PE4<Alternate<AF5>>,
+            PE11<Alternate<AF5>>
+        ]
     SPI5:
         SCK: [
             NoSck,
@@ -360,6 +735,12 @@ pins! {
             #[cfg(not(feature = "stm32h7b0"))]
             PJ10<Alternate<AF5>>
         ]
+        NSS: [
+            PF6<Alternate<AF5>>,
+            PH5<Alternate<AF5>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            PK1<Alternate<AF5>>
+        ]
     SPI6:
         SCK: [
             NoSck,
@@ -381,6 +762,11 @@ pins! {
             PB5<Alternate<AF8>>,
             PG14<Alternate<AF5>>
         ]
+        NSS: [
+            PA4<Alternate<AF8>>,
+            PA15<Alternate<AF7>>,
+            PG8<Alternate<AF5>>
+        ]
 }
 
 /// Interrupt events
@@ -448,6 +834,12 @@ macro_rules! spi {
             // For each $TY
             $(
                 impl Spi<$SPIX, Enabled, $TY> {
+                    /// # Panics
+                    ///
+                    /// Panics if the kernel clock is not running, or if
+                    /// `freq` is higher than the kernel clock. Use
+                    /// [`try_new`](#method.try_new) to return an error
+                    /// instead
                     pub fn $spiX<T, CONFIG>(
                         spi: $SPIX,
                         config: CONFIG,
@@ -459,72 +851,133 @@ macro_rules! spi {
                         T: Into<Hertz>,
                         CONFIG: Into<Config>,
                     {
+                        match Self::try_new(spi, config, freq, prec, clocks) {
+                            Ok(spi) => spi,
+                            Err((ConfigError::KernelClockNotRunning, _, _)) => {
+                                hal_panic!("$SPIX kernel clock not running!")
+                            }
+                            Err((e, _, _)) => panic!("Invalid SPI frequency: {:?}", e),
+                        }
+                    }
+
+                    /// Returns an error if the kernel clock is not running,
+                    /// or if `freq` cannot be generated from it. The error is
+                    /// returned with the peripheral and its `rec`, so that
+                    /// the peripheral can be configured again
+                    pub fn try_new<T, CONFIG>(
+                        spi: $SPIX,
+                        config: CONFIG,
+                        freq: T,
+                        prec: rec::$Rec,
+                        clocks: &CoreClocks,
+                    ) -> Result<Self, (ConfigError, $SPIX, rec::$Rec)>
+                    where
+                        T: Into<Hertz>,
+                        CONFIG: Into<Config>,
+                    {
+                        let config: Config = config.into();
+                        let spi_freq = freq.into().0;
+
+                        // Check the configuration before the peripheral is
+                        // enabled, so that it can be returned on an error
+                        let check = || -> Result<(MBR, u8, u8), ConfigError> {
+                            let spi_ker_ck = Self::kernel_clk(clocks)
+                                .ok_or(ConfigError::KernelClockNotRunning)?.0;
+
+                            // The baud rate is not used in slave mode
+                            let mbr = if config.slave {
+                                MBR::DIV2
+                            } else {
+                                master_baud_rate(spi_ker_ck, spi_freq)?
+                            };
+
+                            if config.transfer_size > Self::MAX_TRANSFER_SIZE {
+                                return Err(ConfigError::TransferSize);
+                            }
+                            if let Some((_, bits)) = config.crc {
+                                if bits < 4 || bits > Self::MAX_CRC_BITS {
+                                    return Err(ConfigError::CrcSize);
+                                }
+                            }
+
+                            // Calculate the CS->transaction cycle delay bits,
+                            // from the SCK that is generated
+                            let sck = spi_ker_ck / master_baud_rate_divider(mbr);
+                            let start_cycle_delay = match config.cs_setup_time {
+                                Some(time) => time_cycles(time, sck)?,
+                                None => delay_cycles(config.cs_delay, sck),
+                            };
+
+                            // If CS suspends while data is inactive, we also require an
+                            // "inter-data" delay.
+                            let interdata_cycle_delay = match (config.cs_idle_time, config.inter_word_delay) {
+                                (Some(time), _) => time_cycles(time, sck)?,
+                                (None, Some(delay)) => delay_cycles(delay, sck),
+                                (None, None) if config.suspend_when_inactive => start_cycle_delay,
+                                (None, None) => 0,
+                            };
+
+                            Ok((mbr, start_cycle_delay, interdata_cycle_delay))
+                        };
+                        let (mbr, start_cycle_delay, interdata_cycle_delay) =
+                            match check() {
+                                Ok(checked) => checked,
+                                Err(e) => return Err((e, spi, prec)),
+                            };
+
                         // Enable clock for SPI
                         prec.enable();
 
                         // Disable SS output
                         spi.cfg2.write(|w| w.ssoe().disabled());
 
-                        let config: Config = config.into();
-
-                        let spi_freq = freq.into().0;
-	                    let spi_ker_ck = match Self::kernel_clk(clocks) {
-                            Some(ker_hz) => ker_hz.0,
-                            _ => panic!("$SPIX kernel clock not running!")
+                        let (udrcfg, udrdr) = match config.underrun_policy {
+                            UnderrunPolicy::Pattern(p) => (0b00, p),
+                            UnderrunPolicy::RepeatReceived => (0b01, 0),
+                            UnderrunPolicy::RepeatTransmitted => (0b10, 0),
                         };
-                        let mbr = match spi_ker_ck / spi_freq {
-                            0 => unreachable!(),
-                            1..=2 => MBR::DIV2,
-                            3..=5 => MBR::DIV4,
-                            6..=11 => MBR::DIV8,
-                            12..=23 => MBR::DIV16,
-                            24..=47 => MBR::DIV32,
-                            48..=95 => MBR::DIV64,
-                            96..=191 => MBR::DIV128,
-                            _ => MBR::DIV256,
+                        let udrdet = match config.underrun_detection {
+                            UnderrunDetection::StartOfFrame => 0b00,
+                            UnderrunDetection::EndOfFrame => 0b01,
+                            UnderrunDetection::StartOfSlaveSelect => 0b10,
                         };
+                        spi.cfg1.modify(|_, w| unsafe {
+                            w.udrcfg().bits(udrcfg).udrdet().bits(udrdet)
+                        });
+                        spi.udrdr.write(|w| unsafe { w.udrdr().bits(udrdr) });
+
                         spi.cfg1.modify(|_, w| {
                             w.mbr()
                                 .variant(mbr) // master baud rate
                         });
                         spi!(DSIZE, spi, $TY); // modify CFG1 for DSIZE
 
-                        // ssi: select slave = master mode
-                        spi.cr1.write(|w| w.ssi().slave_not_selected());
-
-                        // Calculate the CS->transaction cycle delay bits.
-                        let (start_cycle_delay, interdata_cycle_delay) = {
-                            let mut delay: u32 = (config.cs_delay * spi_freq as f32) as u32;
-
-                            // If the cs-delay is specified as non-zero, add 1 to the delay cycles
-                            // before truncation to an integer to ensure that we have at least as
-                            // many cycles as required.
-                            if config.cs_delay > 0.0_f32 {
-                                delay += 1;
+                        match config.crc {
+                            Some((polynomial, bits)) => {
+                                spi.crcpoly.write(|w| unsafe { w.crcpoly().bits(polynomial) });
+                                spi.cfg1.modify(|_, w| unsafe {
+                                    w.crcsize().bits(bits - 1).crcen().set_bit()
+                                });
                             }
+                            None => spi.cfg1.modify(|_, w| w.crcen().clear_bit()),
+                        }
 
-                            if delay > 0xF {
-                                delay = 0xF;
-                            }
+                        // tsize: number of data frames, or zero for an
+                        // endless transaction
+                        spi.cr2.write(|w| unsafe { w.bits(config.transfer_size.into()) });
 
-                            // If CS suspends while data is inactive, we also require an
-                            // "inter-data" delay.
-                            if config.suspend_when_inactive {
-                                (delay as u8, delay as u8)
-                            } else {
-                                (delay as u8, 0_u8)
-                            }
-                        };
+                        // ssi: select slave = master mode. In slave mode
+                        // with software slave management, the slave is
+                        // always selected
+                        spi.cr1.write(|w| w.ssi().bit(!config.slave));
 
-                        // The calculated cycle delay may not be more than 4 bits wide for the
-                        // configuration register.
                         let communication_mode = match config.communication_mode {
                             CommunicationMode::Transmitter => COMM::TRANSMITTER,
                             CommunicationMode::Receiver => COMM::RECEIVER,
                             CommunicationMode::FullDuplex => COMM::FULLDUPLEX,
                         };
 
-                        // mstr: master configuration
+                        // mstr: master or slave configuration
                         // lsbfrst: MSB first
                         // comm: full-duplex
                         spi.cfg2.write(|w| {
@@ -534,7 +987,7 @@ macro_rules! spi {
                                 .cpol()
                                 .bit(config.mode.polarity == Polarity::IdleHigh)
                                 .master()
-                                .master()
+                                .bit(!config.slave)
                                 .lsbfrst()
                                 .msbfirst()
                                 .ssom()
@@ -547,16 +1000,22 @@ macro_rules! spi {
                                 .bits(start_cycle_delay)
                                 .midi()
                                 .bits(interdata_cycle_delay)
+                                .ssiop()
+                                .bit(config.cs_active_high)
                                 .ioswp()
                                 .bit(config.swap_miso_mosi == true)
                                 .comm()
                                 .variant(communication_mode)
                         });
+                        if config.frame_format == FrameFormat::Ti {
+                            // SP = 0b001: TI frame format
+                            spi.cfg2.modify(|_, w| unsafe { w.sp().bits(0b001) });
+                        }
 
                         // spe: enable the SPI bus
-                        spi.cr1.write(|w| w.ssi().slave_not_selected().spe().enabled());
+                        spi.cr1.write(|w| w.ssi().bit(!config.slave).spe().enabled());
 
-                        Spi { spi, _word: PhantomData, _ed: PhantomData }
+                        Ok(Spi { spi, _word: PhantomData, _ed: PhantomData })
                     }
 
                     /// Disables the SPI peripheral. Any SPI operation is
@@ -566,10 +1025,13 @@ macro_rules! spi {
                     /// CRC calculation is re-initialized. Clocks are not
                     /// disabled.
                     pub fn disable(self) -> Spi<$SPIX, Disabled, $TY> {
-                        // Master communication must be suspended before the peripheral is disabled
-                        self.spi.cr1.modify(|_, w| w.csusp().requested());
-                        while self.spi.sr.read().eot().is_completed() {}
-                        self.spi.cr1.write(|w| w.ssi().slave_not_selected().spe().disabled());
+                        let master = self.spi.cfg2.read().master().is_master();
+                        if master {
+                            // Master communication must be suspended before the peripheral is disabled
+                            self.spi.cr1.modify(|_, w| w.csusp().requested());
+                            while self.spi.sr.read().eot().is_completed() {}
+                        }
+                        self.spi.cr1.write(|w| w.ssi().bit(master).spe().disabled());
                         Spi {
                             spi: self.spi,
                             _word: PhantomData,
@@ -583,7 +1045,8 @@ macro_rules! spi {
                     /// Clears the MODF flag, the SSI flag, and sets the SPE bit.
                     pub fn enable(mut self) -> Spi<$SPIX, Enabled, $TY> {
                         self.clear_modf(); // SPE cannot be set when MODF is set
-                        self.spi.cr1.write(|w| w.ssi().slave_not_selected().spe().enabled());
+                        let master = self.spi.cfg2.read().master().is_master();
+                        self.spi.cr1.write(|w| w.ssi().bit(master).spe().enabled());
                         Spi {
                             spi: self.spi,
                             _word: PhantomData,
@@ -605,6 +1068,20 @@ macro_rules! spi {
                         self.spi.cfg1.modify(|_,w| w.txdmaen().enabled());
                     }
 
+                    /// Sets the number of data frames in each transaction,
+                    /// see [`Config::transfer_size`]
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `frames` is too large for this SPI
+                    pub fn set_transfer_size(&mut self, frames: u16) {
+                        assert!(
+                            frames <= Spi::<$SPIX, Enabled, $TY>::MAX_TRANSFER_SIZE,
+                            "Transfer size too large for $SPIX"
+                        );
+                        self.spi.cr2.write(|w| unsafe { w.bits(frames.into()) });
+                    }
+
                     /// Deconstructs the SPI peripheral and returns the component parts.
                     pub fn free(self) -> ($SPIX, rec::$Rec) {
                         (self.spi, rec::$Rec { _marker: PhantomData })
@@ -623,6 +1100,24 @@ macro_rules! spi {
                         &mut self.spi
                     }
 
+                    /// Resets the SPI peripheral and disables its clock,
+                    /// then returns `pins` in analog mode
+                    ///
+                    /// `pins` must be pins of this SPI. The driver does not
+                    /// keep the pins given to the constructor. To return
+                    /// them here, keep them and use
+                    /// [`spi_unchecked`](SpiExt#tymethod.spi_unchecked).
+                    pub fn deinit<PINS: Pins<$SPIX> + IntoAnalog>(
+                        self,
+                        pins: PINS,
+                    ) -> ($SPIX, rec::$Rec, PINS::Analog) {
+                        let prec = rec::$Rec { _marker: PhantomData }
+                            .reset()
+                            .disable();
+
+                        (self.spi, prec, pins.into_analog())
+                    }
+
                     /// Enable interrupts for the given `event`:
                     ///  - Received data ready to be read (RXP)
                     ///  - Transmit data register empty (TXP)
@@ -701,6 +1196,34 @@ macro_rules! spi {
                         self.spi.sr.read().ovr().is_overrun()
                     }
 
+                    /// Return `true` if the EOT flag is set, i.e. the
+                    /// number of data frames set by the transfer size
+                    /// has been transferred. This includes the CRC, if
+                    /// enabled
+                    pub fn is_eot(&self) -> bool {
+                        self.spi.sr.read().eot().is_completed()
+                    }
+
+                    /// Clears the EOT and TXTF flags, which indicate
+                    /// the end of a transaction.
+                    pub fn clear_eot(&mut self) {
+                        self.spi.ifcr.write(|w| w.eotc().clear().txtfc().clear());
+                        let _ = self.spi.sr.read();
+                        let _ = self.spi.sr.read(); // Delay 2 peripheral clocks
+                    }
+
+                    /// Returns the CRC calculated from the transmitted
+                    /// data frames of the current transaction
+                    pub fn tx_crc(&self) -> u32 {
+                        self.spi.txcrc.read().txcrc().bits()
+                    }
+
+                    /// Returns the CRC calculated from the received data
+                    /// frames of the current transaction
+                    pub fn rx_crc(&self) -> u32 {
+                        self.spi.rxcrc.read().rxcrc().bits()
+                    }
+
                     /// Clears the MODF flag, which indicates that a
                     /// mode fault has occurred.
                     pub fn clear_modf(&mut self) {
@@ -800,6 +1323,18 @@ macro_rules! spi {
                 impl hal::blocking::spi::write::Default<$TY>
                     for Spi<$SPIX, Enabled, $TY> {}
             )+
+
+            impl crate::Sealed for $SPIX {}
+            impl Instance for $SPIX {
+                type Rec = rec::$Rec;
+
+                const INTERRUPT: stm32::Interrupt = stm32::Interrupt::$SPIX;
+
+                #[inline(always)]
+                fn ptr() -> *const stm32::spi1::RegisterBlock {
+                    $SPIX::ptr()
+                }
+            }
         )+
 	}
 }
@@ -808,6 +1343,11 @@ macro_rules! spi123sel {
 	($($SPIX:ident,)+) => {
 	    $(
             impl<WORD> Spi<$SPIX, Enabled, WORD> {
+                /// Maximum CRC length in bits
+                const MAX_CRC_BITS: u8 = 32;
+                /// Maximum number of data frames in a transaction
+                const MAX_TRANSFER_SIZE: u16 = 0xFFFF;
+
                 /// Returns the frequency of the current kernel clock
                 /// for SPI1, SPI2, SPI3
                 fn kernel_clk(clocks: &CoreClocks) -> Option<Hertz> {
@@ -834,6 +1374,11 @@ macro_rules! spi45sel {
 	($($SPIX:ident,)+) => {
 	    $(
             impl<WORD> Spi<$SPIX, Enabled, WORD> {
+                /// Maximum CRC length in bits
+                const MAX_CRC_BITS: u8 = 16;
+                /// Maximum number of data frames in a transaction
+                const MAX_TRANSFER_SIZE: u16 = 0x3FF;
+
                 /// Returns the frequency of the current kernel clock
                 /// for SPI4, SPI5
                 fn kernel_clk(clocks: &CoreClocks) -> Option<Hertz> {
@@ -860,6 +1405,11 @@ macro_rules! spi6sel {
 	($($SPIX:ident,)+) => {
 	    $(
             impl<WORD> Spi<$SPIX, Enabled, WORD> {
+                /// Maximum CRC length in bits
+                const MAX_CRC_BITS: u8 = 16;
+                /// Maximum number of data frames in a transaction
+                const MAX_TRANSFER_SIZE: u16 = 0x3FF;
+
                 /// Returns the frequency of the current kernel clock
                 /// for SPI6
                 fn kernel_clk(clocks: &CoreClocks) -> Option<Hertz> {
@@ -901,3 +1451,48 @@ spi45sel! {
 spi6sel! {
     SPI6,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn master_baud_rate_prescaler() {
+        assert_eq!(master_baud_rate(100_000_000, 50_000_000), Ok(MBR::DIV2));
+        assert_eq!(master_baud_rate(100_000_000, 30_000_000), Ok(MBR::DIV4));
+        // Faster than requested
+        assert_eq!(master_baud_rate(100_000_000, 20_000_000), Ok(MBR::DIV4));
+        assert_eq!(master_baud_rate(100_000_000, 1_000_000), Ok(MBR::DIV128));
+        assert_eq!(master_baud_rate(100_000_000, 100_000), Ok(MBR::DIV256));
+        assert_eq!(
+            master_baud_rate(100_000_000, 200_000_000),
+            Err(ConfigError::FrequencyTooHigh)
+        );
+        assert_eq!(
+            master_baud_rate(100_000_000, 0),
+            Err(ConfigError::FrequencyTooLow)
+        );
+    }
+
+    #[test]
+    fn delay_cycles_round_up() {
+        assert_eq!(delay_cycles(0.0, 1_000_000), 0);
+        assert_eq!(delay_cycles(1.5e-6, 1_000_000), 2);
+        assert_eq!(delay_cycles(2.5e-6, 1_000_000), 3);
+        assert_eq!(delay_cycles(1e-3, 1_000_000), 0xF);
+    }
+
+    #[test]
+    fn time_cycles_round_up() {
+        assert_eq!(time_cycles(NanoSeconds(0), 1_000_000), Ok(0));
+        assert_eq!(time_cycles(NanoSeconds(1_000), 1_000_000), Ok(1));
+        assert_eq!(time_cycles(NanoSeconds(1_001), 1_000_000), Ok(2));
+        // 100MHz kernel clock divided by 128
+        let sck = 100_000_000 / master_baud_rate_divider(MBR::DIV128);
+        assert_eq!(time_cycles(NanoSeconds(19_200), sck), Ok(15));
+        assert_eq!(
+            time_cycles(NanoSeconds(19_201), sck),
+            Err(ConfigError::CsTiming)
+        );
+    }
+}
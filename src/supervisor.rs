@@ -0,0 +1,244 @@
+//! Supervision of multiple tasks by a single watchdog
+//!
+//! Each task registers a [`Heartbeat`] with a deadline, and checks in at
+//! least once in every deadline. The [`Supervisor`] is serviced from a
+//! periodic timer interrupt, and only feeds the watchdog if every task has
+//! checked in within its deadline. A task that stops running therefore
+//! resets the device, even if other tasks and the timer interrupt still
+//! run.
+//!
+//! # Example
+//!
+//! ```
+//! static HEARTBEATS: Heartbeats = Heartbeats::new();
+//!
+//! let mut watchdog = IndependentWatchdog::new(dp.IWDG);
+//! watchdog.start(500.ms());
+//!
+//! // Serviced every 10ms
+//! let mut supervisor = Supervisor::new(&HEARTBEATS, watchdog, 10.ms());
+//! let mut timer = dp.TIM2.timer(100.hz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//! timer.listen(Event::TimeOut);
+//!
+//! let network = supervisor.register(100.ms()).unwrap();
+//! let logger = supervisor.register(250.ms()).unwrap();
+//!
+//! // In the network task
+//! network.check_in();
+//!
+//! // In the TIM2 interrupt
+//! timer.clear_irq();
+//! if let Err(supervisor::Error::Overdue(task)) = supervisor.service() {
+//!     // The watchdog will reset the device
+//! }
+//! ```
+//!
+//! The watchdog timeout should be longer than the service period, so that
+//! the watchdog is fed while all tasks are healthy.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::hal::watchdog::Watchdog;
+use crate::time::MilliSeconds;
+
+/// Maximum number of tasks
+pub const MAX_TASKS: usize = 32;
+
+/// Supervisor error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The task with this index missed its deadline. The watchdog is no
+    /// longer fed
+    Overdue(usize),
+}
+
+/// Check-in flags of the tasks, shared between the tasks and the
+/// [`Supervisor`]. Usually a `static`
+pub struct Heartbeats {
+    flags: AtomicU32,
+}
+
+impl Heartbeats {
+    /// Creates the check-in flags
+    pub const fn new() -> Self {
+        Heartbeats {
+            flags: AtomicU32::new(0),
+        }
+    }
+}
+
+impl Default for Heartbeats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Heartbeat of a registered task
+pub struct Heartbeat<'a> {
+    heartbeats: &'a Heartbeats,
+    index: usize,
+}
+
+impl<'a> Heartbeat<'a> {
+    /// Checks in, showing that the task is running
+    pub fn check_in(&self) {
+        self.heartbeats
+            .flags
+            .fetch_or(1 << self.index, Ordering::Relaxed);
+    }
+
+    /// Returns the index of the task
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Feeds a watchdog only while all tasks check in
+pub struct Supervisor<'a, WD> {
+    heartbeats: &'a Heartbeats,
+    watchdog: WD,
+    /// Service period in milliseconds
+    period: u32,
+    /// Number of registered tasks
+    tasks: usize,
+    /// Deadline of each task in milliseconds
+    deadline: [u32; MAX_TASKS],
+    /// Time since each task last checked in
+    elapsed: [u32; MAX_TASKS],
+    /// A task has missed its deadline
+    overdue: Option<usize>,
+}
+
+impl<'a, WD: Watchdog> Supervisor<'a, WD> {
+    /// Creates a supervisor that is serviced every `period`, and feeds
+    /// `watchdog`. The watchdog should already be started
+    pub fn new<T>(heartbeats: &'a Heartbeats, watchdog: WD, period: T) -> Self
+    where
+        T: Into<MilliSeconds>,
+    {
+        heartbeats.flags.store(0, Ordering::Relaxed);
+
+        Supervisor {
+            heartbeats,
+            watchdog,
+            period: period.into().0,
+            tasks: 0,
+            deadline: [0; MAX_TASKS],
+            elapsed: [0; MAX_TASKS],
+            overdue: None,
+        }
+    }
+
+    /// Registers a task that checks in at least every `deadline`. Returns
+    /// `None` if [`MAX_TASKS`] tasks are already registered
+    ///
+    /// The deadline is rounded up to a multiple of the service period
+    pub fn register<T>(&mut self, deadline: T) -> Option<Heartbeat<'a>>
+    where
+        T: Into<MilliSeconds>,
+    {
+        if self.tasks == MAX_TASKS {
+            return None;
+        }
+        let index = self.tasks;
+        self.deadline[index] = deadline.into().0;
+        self.elapsed[index] = 0;
+        self.tasks += 1;
+
+        Some(Heartbeat {
+            heartbeats: self.heartbeats,
+            index,
+        })
+    }
+
+    /// Checks the tasks, and feeds the watchdog if they all checked in
+    /// within their deadlines. Must be called every service period
+    ///
+    /// Once a task has missed its deadline, the watchdog is never fed
+    /// again, even if the task checks in later.
+    pub fn service(&mut self) -> Result<(), Error> {
+        if let Some(task) = self.overdue {
+            return Err(Error::Overdue(task));
+        }
+
+        let flags = self.heartbeats.flags.swap(0, Ordering::Relaxed);
+        for task in 0..self.tasks {
+            if flags & (1 << task) != 0 {
+                self.elapsed[task] = 0;
+            } else {
+                self.elapsed[task] += self.period;
+                if self.elapsed[task] > self.deadline[task] {
+                    self.overdue = Some(task);
+                    return Err(Error::Overdue(task));
+                }
+            }
+        }
+
+        self.watchdog.feed();
+        Ok(())
+    }
+
+    /// Returns the time since the task with heartbeat `task` last checked
+    /// in, as of the last service
+    pub fn elapsed(&self, task: &Heartbeat) -> MilliSeconds {
+        MilliSeconds(self.elapsed[task.index])
+    }
+
+    /// Releases the watchdog. A started watchdog keeps running
+    pub fn free(self) -> WD {
+        self.watchdog
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the times it is fed
+    struct Counter(u32);
+
+    impl Watchdog for Counter {
+        fn feed(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn feeds_while_tasks_check_in() {
+        let heartbeats = Heartbeats::new();
+        let mut supervisor =
+            Supervisor::new(&heartbeats, Counter(0), MilliSeconds(10));
+        let fast = supervisor.register(MilliSeconds(10)).unwrap();
+        let slow = supervisor.register(MilliSeconds(30)).unwrap();
+
+        for _ in 0..3 {
+            fast.check_in();
+            assert_eq!(supervisor.service(), Ok(()));
+        }
+        assert_eq!(supervisor.elapsed(&slow), MilliSeconds(30));
+        slow.check_in();
+        fast.check_in();
+        assert_eq!(supervisor.service(), Ok(()));
+        assert_eq!(supervisor.elapsed(&slow), MilliSeconds(0));
+
+        assert_eq!(supervisor.free().0, 4);
+    }
+
+    #[test]
+    fn overdue_task_stops_feeding() {
+        let heartbeats = Heartbeats::new();
+        let mut supervisor =
+            Supervisor::new(&heartbeats, Counter(0), MilliSeconds(10));
+        let task = supervisor.register(MilliSeconds(20)).unwrap();
+
+        assert_eq!(supervisor.service(), Ok(()));
+        assert_eq!(supervisor.service(), Ok(()));
+        assert_eq!(supervisor.service(), Err(Error::Overdue(task.index())));
+
+        // The task checking in later does not feed the watchdog again
+        task.check_in();
+        assert_eq!(supervisor.service(), Err(Error::Overdue(task.index())));
+        assert_eq!(supervisor.free().0, 2);
+    }
+}
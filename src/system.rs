@@ -0,0 +1,117 @@
+//! System initialisation in one step
+//!
+//! Most applications start by setting up the power supply, freezing the
+//! clocks and creating a delay provider. The [`init!`](crate::init!) macro
+//! does this from the device and core peripherals and a
+//! [`ClockProfile`], and returns the result as a [`System`]:
+//!
+//! ```
+//! use stm32h7xx_hal::{pac, prelude::*, system::ClockProfile};
+//!
+//! let cp = cortex_m::Peripherals::take().unwrap();
+//! let dp = pac::Peripherals::take().unwrap();
+//!
+//! let mut system = stm32h7xx_hal::init!(dp, cp, ClockProfile::Hsi(200.mhz()));
+//!
+//! // The remaining peripherals are used as usual
+//! let gpioe = dp.GPIOE.split(system.ccdr.peripheral.GPIOE);
+//! let mut led = gpioe.pe1.into_push_pull_output();
+//! system.delay.delay_ms(500_u16);
+//! ```
+//!
+//! The macro moves `PWR` and `RCC` out of `dp`, and `SYST` out of `cp`, so
+//! that the other peripherals remain available. [`init`] is the same
+//! without the macro, and the granular API of [`pwr`](crate::pwr) and
+//! [`rcc`](crate::rcc) is still available for configurations that a
+//! profile does not cover.
+//!
+//! # Power supply
+//!
+//! The supply configuration is the default for the part. Boards that
+//! supply VCORE from the SMPS or an external source must configure the
+//! supply with [`PwrExt`](crate::pwr::PwrExt) instead.
+
+use cortex_m::peripheral::SYST;
+
+use crate::delay::{Delay, DelayExt};
+use crate::pwr::PwrExt;
+use crate::rcc::backup::BackupREC;
+use crate::rcc::{Ccdr, RccExt};
+use crate::stm32::{PWR, RCC, SYSCFG};
+use crate::time::Hertz;
+
+/// Clock configuration of [`init`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClockProfile {
+    /// `sys_ck` from HSI at 64MHz without a PLL, as after reset
+    Hsi64,
+    /// `sys_ck` at the given frequency from PLL1, fed by the HSI
+    Hsi(Hertz),
+    /// `sys_ck` at the frequency `sys_ck` from PLL1, fed by an HSE crystal
+    /// at the frequency `hse`
+    Hse {
+        /// Frequency of the crystal
+        hse: Hertz,
+        /// Frequency of `sys_ck`
+        sys_ck: Hertz,
+    },
+}
+
+/// The subsystems set up by [`init`]
+pub struct System {
+    /// Frozen clocks, and the reset and enable control of the peripherals
+    pub ccdr: Ccdr,
+    /// Delay provider on the SysTick
+    pub delay: Delay,
+    /// Write access to the backup domain, if it is not disabled
+    pub backup: Option<BackupREC>,
+}
+
+/// Sets up the power supply, freezes the clocks for `profile`, and
+/// configures the SysTick as a delay provider
+///
+/// Voltage scale VOS1 is used, which allows the highest `sys_ck` without
+/// overdrive.
+///
+/// # Panics
+///
+/// Panics if the clocks cannot be configured for `profile`, as for
+/// [`freeze`](crate::rcc::Rcc::freeze)
+pub fn init(
+    pwr: PWR,
+    rcc: RCC,
+    syscfg: &SYSCFG,
+    syst: SYST,
+    profile: ClockProfile,
+) -> System {
+    let mut pwrcfg = pwr.constrain().freeze();
+    let backup = pwrcfg.backup();
+
+    let rcc = rcc.constrain();
+    let rcc = match profile {
+        ClockProfile::Hsi64 => rcc,
+        ClockProfile::Hsi(sys_ck) => rcc.sys_ck(sys_ck),
+        ClockProfile::Hse { hse, sys_ck } => rcc.use_hse(hse).sys_ck(sys_ck),
+    };
+    let ccdr = rcc.freeze(pwrcfg, syscfg);
+    let delay = syst.delay(ccdr.clocks);
+
+    System {
+        ccdr,
+        delay,
+        backup,
+    }
+}
+
+/// Calls [`system::init`](crate::system::init) with the peripherals that
+/// it needs from the device peripherals `dp` and core peripherals `cp`
+///
+/// ```
+/// let mut system = stm32h7xx_hal::init!(dp, cp, ClockProfile::Hsi64);
+/// ```
+#[macro_export]
+macro_rules! init {
+    ($dp:ident, $cp:ident, $profile:expr) => {
+        $crate::system::init($dp.PWR, $dp.RCC, &$dp.SYSCFG, $cp.SYST, $profile)
+    };
+}
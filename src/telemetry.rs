@@ -0,0 +1,229 @@
+//! Telemetry ring buffer
+//!
+//! A [`Telemetry`] log keeps the last events of the application in storage
+//! that is kept across resets, such as the [backup SRAM](crate::backup_sram).
+//! After a watchdog reset in the field, the events that led up to it can be
+//! read back and reported. Each event is stored with a CRC calculated by
+//! the [CRC unit](crate::crc), so an event that was being written when the
+//! reset occurred is skipped.
+//!
+//! # Example
+//!
+//! ```
+//! let sram = BackupSram::new(backup.BKPRAM);
+//! let mut log = Telemetry::new(sram, 0, 1024, &mut crc).unwrap();
+//!
+//! // Report the events from before the reset
+//! for entry in log.iter(&mut crc) {
+//!     diag.log(format_args!("{:?}", entry)).unwrap();
+//! }
+//!
+//! log.append(&mut crc, EVENT_BOOT, rtc_seconds, [reset_reason as u32, 0]);
+//! ```
+//!
+//! # Layout
+//!
+//! The region of `words` words at `offset` is divided into slots of
+//! [`SLOT_WORDS`] words, and the oldest event is replaced when all slots
+//! are used:
+//!
+//! | Word | Content                                  |
+//! |------|------------------------------------------|
+//! | 0    | `0x7E1E` in bits 31:16, code in 15:0     |
+//! | 1    | Sequence number                          |
+//! | 2    | Timestamp                                |
+//! | 3, 4 | Data                                     |
+//! | 5    | CRC of the words above                   |
+//!
+//! The sequence number counts up across resets, so the order of the events
+//! is found from the slots alone. The CRC unit is used with its current
+//! configuration, which must be the same for `append` and `iter`.
+//!
+//! The backup SRAM is cacheable by default, and the data cache is lost at
+//! a reset. Configure the backup SRAM as write-through with the MPU, or
+//! clean the data cache after `append`.
+
+use crate::crc::Crc;
+use crate::persist::{Error, WordStorage};
+
+/// Marker in the first word of an event
+const MAGIC: u32 = 0x7E1E;
+/// Number of words in each slot
+pub const SLOT_WORDS: usize = 6;
+
+/// An event read back from the log
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Sequence number, counting up from the first event in the log
+    pub sequence: u32,
+    /// Code of the event, defined by the application
+    pub code: u16,
+    /// Timestamp of the event, in units defined by the application
+    pub timestamp: u32,
+    /// Data of the event, such as the program counter of a fault
+    pub data: [u32; 2],
+}
+
+/// Ring buffer of events in storage kept across resets
+pub struct Telemetry<S> {
+    storage: S,
+    offset: usize,
+    slots: usize,
+    /// Slot for the next event
+    next: usize,
+    /// Sequence number of the next event
+    sequence: u32,
+}
+
+impl<S: WordStorage> Telemetry<S> {
+    /// Uses `words` words of `storage` from `offset` for the log, and
+    /// continues after the newest event that is already stored
+    ///
+    /// Returns [`Error::NoSpace`] if the region does not fit in the
+    /// storage, or is smaller than one slot.
+    pub fn new(
+        storage: S,
+        offset: usize,
+        words: usize,
+        crc: &mut Crc,
+    ) -> Result<Self, Error> {
+        let slots = words / SLOT_WORDS;
+        let end = offset.checked_add(words);
+        if slots == 0 || end.map_or(true, |end| end > storage.words()) {
+            return Err(Error::NoSpace);
+        }
+
+        let mut log = Telemetry {
+            storage,
+            offset,
+            slots,
+            next: 0,
+            sequence: 0,
+        };
+        let newest = (0..slots)
+            .filter_map(|slot| log.read(slot, crc).map(|e| (slot, e)))
+            .max_by_key(|&(_, entry)| entry.sequence);
+        if let Some((slot, entry)) = newest {
+            log.next = (slot + 1) % slots;
+            log.sequence = entry.sequence.wrapping_add(1);
+        }
+
+        Ok(log)
+    }
+
+    /// Appends an event, replacing the oldest event if the log is full,
+    /// and returns its sequence number
+    pub fn append(
+        &mut self,
+        crc: &mut Crc,
+        code: u16,
+        timestamp: u32,
+        data: [u32; 2],
+    ) -> u32 {
+        let sequence = self.sequence;
+        let base = self.offset + self.next * SLOT_WORDS;
+        let words = [
+            (MAGIC << 16) | u32::from(code),
+            sequence,
+            timestamp,
+            data[0],
+            data[1],
+        ];
+
+        // The CRC is written last, so that an event interrupted by a reset
+        // is not valid
+        for (i, &word) in words.iter().enumerate() {
+            self.storage.write_word(base + i, word);
+        }
+        let value = calculate_crc(&self.storage, base, crc);
+        self.storage.write_word(base + SLOT_WORDS - 1, value);
+
+        self.next = (self.next + 1) % self.slots;
+        self.sequence = sequence.wrapping_add(1);
+        sequence
+    }
+
+    /// Returns the valid events, from the oldest to the newest
+    pub fn iter<'a>(&'a self, crc: &'a mut Crc) -> Entries<'a, S> {
+        Entries {
+            log: self,
+            crc,
+            index: 0,
+        }
+    }
+
+    /// Returns the number of events that fit in the log
+    pub fn capacity(&self) -> usize {
+        self.slots
+    }
+
+    /// Erases all events. Sequence numbers continue from the last event
+    pub fn clear(&mut self) {
+        for slot in 0..self.slots {
+            self.storage.write_word(self.offset + slot * SLOT_WORDS, 0);
+        }
+    }
+
+    /// Releases the storage. The events are kept
+    pub fn free(self) -> S {
+        self.storage
+    }
+
+    /// Reads the event in `slot`, if it is valid
+    fn read(&self, slot: usize, crc: &mut Crc) -> Option<Entry> {
+        let base = self.offset + slot * SLOT_WORDS;
+        let header = self.storage.read_word(base);
+        if header >> 16 != MAGIC
+            || calculate_crc(&self.storage, base, crc)
+                != self.storage.read_word(base + SLOT_WORDS - 1)
+        {
+            return None;
+        }
+
+        Some(Entry {
+            sequence: self.storage.read_word(base + 1),
+            code: header as u16,
+            timestamp: self.storage.read_word(base + 2),
+            data: [
+                self.storage.read_word(base + 3),
+                self.storage.read_word(base + 4),
+            ],
+        })
+    }
+}
+
+/// Events of a [`Telemetry`] log, see [`iter`](Telemetry::iter)
+pub struct Entries<'a, S> {
+    log: &'a Telemetry<S>,
+    crc: &'a mut Crc,
+    /// Number of slots visited, starting at the oldest
+    index: usize,
+}
+
+impl<'a, S: WordStorage> Iterator for Entries<'a, S> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        while self.index < self.log.slots {
+            let slot = (self.log.next + self.index) % self.log.slots;
+            self.index += 1;
+            if let Some(entry) = self.log.read(slot, self.crc) {
+                return Some(entry);
+            }
+        }
+        None
+    }
+}
+
+/// Calculates the CRC of the words of the slot at `base`, except the CRC
+fn calculate_crc<S: WordStorage>(
+    storage: &S,
+    base: usize,
+    crc: &mut Crc,
+) -> u32 {
+    crc.reset();
+    for index in base..base + SLOT_WORDS - 1 {
+        crc.update_u32(&[storage.read_word(index)]);
+    }
+    crc.finalize()
+}
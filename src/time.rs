@@ -1,4 +1,17 @@
 //! Time units
+//!
+//! # fugit
+//!
+//! With the feature gate `fugit`, the rates and durations of the
+//! [`fugit`](https://docs.rs/fugit) crate convert from and to these units.
+//! The constructors of this crate take `Into<Hertz>`, `Into<MilliSeconds>`
+//! and similar, so they also accept `fugit` values.
+//!
+//! ```
+//! use fugit::RateExtU32;
+//!
+//! let timer = dp.TIM2.timer(10.kHz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//! ```
 
 use core::fmt;
 use core::time::Duration;
@@ -234,6 +247,85 @@ impl Into<Duration> for NanoSeconds {
     }
 }
 
+// Conversions from and to the fixed-point types of fugit
+#[cfg(feature = "fugit")]
+mod fugit_conversions {
+    use super::{Hertz, MicroSeconds, MilliSeconds, NanoSeconds};
+    use fugit::{
+        HertzU32, KilohertzU32, MegahertzU32, MicrosDurationU32,
+        MillisDurationU32, NanosDurationU32,
+    };
+
+    impl From<HertzU32> for Hertz {
+        fn from(rate: HertzU32) -> Self {
+            Hertz(rate.raw())
+        }
+    }
+    impl From<KilohertzU32> for Hertz {
+        fn from(rate: KilohertzU32) -> Self {
+            Hertz(rate.to_Hz())
+        }
+    }
+    impl From<MegahertzU32> for Hertz {
+        fn from(rate: MegahertzU32) -> Self {
+            Hertz(rate.to_Hz())
+        }
+    }
+    impl From<Hertz> for HertzU32 {
+        fn from(rate: Hertz) -> Self {
+            HertzU32::from_raw(rate.0)
+        }
+    }
+
+    impl From<MillisDurationU32> for MilliSeconds {
+        fn from(duration: MillisDurationU32) -> Self {
+            MilliSeconds(duration.ticks())
+        }
+    }
+    impl From<MicrosDurationU32> for MicroSeconds {
+        fn from(duration: MicrosDurationU32) -> Self {
+            MicroSeconds(duration.ticks())
+        }
+    }
+    impl From<NanosDurationU32> for NanoSeconds {
+        fn from(duration: NanosDurationU32) -> Self {
+            NanoSeconds(duration.ticks())
+        }
+    }
+    impl From<MilliSeconds> for MillisDurationU32 {
+        fn from(duration: MilliSeconds) -> Self {
+            MillisDurationU32::from_ticks(duration.0)
+        }
+    }
+    impl From<MicroSeconds> for MicrosDurationU32 {
+        fn from(duration: MicroSeconds) -> Self {
+            MicrosDurationU32::from_ticks(duration.0)
+        }
+    }
+    impl From<NanoSeconds> for NanosDurationU32 {
+        fn from(duration: NanoSeconds) -> Self {
+            NanosDurationU32::from_ticks(duration.0)
+        }
+    }
+
+    // Periods, for constructors that take a frequency
+    impl From<MillisDurationU32> for Hertz {
+        fn from(period: MillisDurationU32) -> Self {
+            MilliSeconds::from(period).into()
+        }
+    }
+    impl From<MicrosDurationU32> for Hertz {
+        fn from(period: MicrosDurationU32) -> Self {
+            MicroSeconds::from(period).into()
+        }
+    }
+    impl From<NanosDurationU32> for Hertz {
+        fn from(period: NanosDurationU32) -> Self {
+            NanoSeconds::from(period).into()
+        }
+    }
+}
+
 // /// A monotonic nondecreasing timer
 // #[derive(Clone, Copy)]
 // pub struct MonoTimer {
@@ -1,6 +1,73 @@
 //! Timers
+//!
+//! # Low Power Timers
+//!
+//! The low power timers (`LPTIM[1-5]`) can be clocked from the LSE or LSI by
+//! setting their kernel clock mux. LPTIM2-5 are in the D3 / SRD domain, so if
+//! their peripheral clock is set to
+//! [`LowPowerMode::Autonomous`](crate::rcc::LowPowerMode::Autonomous) they
+//! continue to count in Stop mode and can wake the core through the EXTI.
+//!
+//! ```
+//! let mut lptim2_rec = ccdr.peripheral.LPTIM2
+//!     .kernel_clk_mux(rec::Lptim2ClkSel::LSI)
+//!     .low_power(LowPowerMode::Autonomous);
+//!
+//! let mut timer = dp.LPTIM2.timer(1.hz(), lptim2_rec, &ccdr.clocks).pause();
+//! timer.listen(Event::TimeOut);
+//! let timer = timer.resume();
+//!
+//! exti.listen(exti::Event::LPTIM2);
+//! exti.unmask(exti::Event::LPTIM2);
+//! ```
+//!
+//! ## Pulse counting
+//!
+//! A LPTIM can count the edges on its IN1 input, for example from an energy
+//! meter or a flow sensor. The edges are sampled by the kernel clock, so
+//! with an LSE or LSI kernel clock and
+//! [`LowPowerMode::Autonomous`](crate::rcc::LowPowerMode::Autonomous) the
+//! LPTIM keeps counting while the core is in Stop mode.
+//!
+//! ```
+//! let lptim2_rec = ccdr.peripheral.LPTIM2
+//!     .kernel_clk_mux(rec::Lptim2ClkSel::LSE)
+//!     .low_power(LowPowerMode::Autonomous);
+//!
+//! // Count the rising edges on the LPTIM2_IN1 pin
+//! let _in1 = gpiod.pd12.into_alternate_af3();
+//! let mut counter = LpTimer::lptim2_pulse_counter(
+//!     dp.LPTIM2, TriggerEdge::Rising, 0, 0b01, lptim2_rec, &ccdr.clocks);
+//!
+//! let pulses = counter.counter();
+//! counter.reset_counter();
+//! ```
+//!
+//! The counter wraps after 0xFFFF pulses. The `TimeOut` event is signalled
+//! when it wraps, and can wake the core from Stop mode through the EXTI.
+//!
+//! # LSI Measurement
+//!
+//! The LSI is an RC oscillator, and its frequency can differ from the
+//! nominal 32kHz by several percent. TIM16 can capture the LSI through an
+//! internal connection, and so measure its frequency against the timer's
+//! kernel clock.
+//!
+//! ```
+//! let mut timer = Timer::tim16(dp.TIM16, ccdr.peripheral.TIM16, &ccdr.clocks);
+//! let lsi = timer.measure_lsi();
+//!
+//! // Use the measured frequency for the RTC...
+//! let clocks = ccdr.clocks.with_lsi_ck(lsi);
+//! let rtc = Rtc::open_or_init(dp.RTC, backup.RTC, RtcClock::Lsi, &clocks);
+//!
+//! // ...and the watchdog
+//! let mut watchdog = IndependentWatchdog::new(dp.IWDG);
+//! watchdog.set_lsi_frequency(lsi);
+//! ```
 
 // TODO: on the h7x3 at least, only TIM2, TIM3, TIM4, TIM5 can support 32 bits.
+// On RM0468 parts, TIM23 and TIM24 are also 32 bit.
 // TIM1 is 16 bit.
 
 use core::convert::TryFrom;
@@ -17,6 +84,8 @@ use crate::stm32::{
     TIM1, TIM12, TIM13, TIM14, TIM15, TIM16, TIM17, TIM2, TIM3, TIM4, TIM5,
     TIM6, TIM7, TIM8,
 };
+#[cfg(feature = "rm0468")]
+use crate::stm32::{TIM23, TIM24};
 
 use cast::{u16, u32};
 use void::Void;
@@ -26,7 +95,7 @@ use crate::stm32::rcc::{cdccip2r as ccip2r, srdccipr};
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::rcc::{d2ccip2r as ccip2r, d3ccipr as srdccipr};
 
-use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::rcc::{lse_ck, rec, CoreClocks, ResetEnable};
 use crate::stm32;
 use crate::time::Hertz;
 use stm32h7::Variant::Val;
@@ -54,6 +123,10 @@ impl_tim_ker_ck! {
     timx_ker_ck: TIM2, TIM3, TIM4, TIM5, TIM6, TIM7, TIM12, TIM13, TIM14
     timy_ker_ck: TIM1, TIM8, TIM15, TIM16, TIM17
 }
+#[cfg(feature = "rm0468")]
+impl_tim_ker_ck! {
+    timx_ker_ck: TIM23, TIM24
+}
 
 /// LPTIM1 Kernel Clock
 impl GetClk for LPTIM1 {
@@ -69,8 +142,8 @@ impl GetClk for LPTIM1 {
             Val(ccip2r::LPTIM1SEL_A::RCC_PCLK1) => Some(clocks.pclk1()),
             Val(ccip2r::LPTIM1SEL_A::PLL2_P) => clocks.pll2_p_ck(),
             Val(ccip2r::LPTIM1SEL_A::PLL3_R) => clocks.pll3_r_ck(),
-            Val(ccip2r::LPTIM1SEL_A::LSE) => unimplemented!(),
-            Val(ccip2r::LPTIM1SEL_A::LSI) => unimplemented!(),
+            Val(ccip2r::LPTIM1SEL_A::LSE) => lse_ck(),
+            Val(ccip2r::LPTIM1SEL_A::LSI) => clocks.lsi_ck(),
             Val(ccip2r::LPTIM1SEL_A::PER) => clocks.per_ck(),
             _ => unreachable!(),
         }
@@ -90,8 +163,8 @@ impl GetClk for LPTIM2 {
             Val(srdccipr::LPTIM2SEL_A::RCC_PCLK4) => Some(clocks.pclk4()),
             Val(srdccipr::LPTIM2SEL_A::PLL2_P) => clocks.pll2_p_ck(),
             Val(srdccipr::LPTIM2SEL_A::PLL3_R) => clocks.pll3_r_ck(),
-            Val(srdccipr::LPTIM2SEL_A::LSE) => unimplemented!(),
-            Val(srdccipr::LPTIM2SEL_A::LSI) => unimplemented!(),
+            Val(srdccipr::LPTIM2SEL_A::LSE) => lse_ck(),
+            Val(srdccipr::LPTIM2SEL_A::LSI) => clocks.lsi_ck(),
             Val(srdccipr::LPTIM2SEL_A::PER) => clocks.per_ck(),
             _ => unreachable!(),
         }
@@ -110,8 +183,8 @@ impl GetClk for LPTIM3 {
             0 => Some(clocks.pclk4()),
             1 => clocks.pll2_p_ck(),
             2 => clocks.pll3_r_ck(),
-            3 => unimplemented!(),
-            4 => unimplemented!(),
+            3 => lse_ck(),
+            4 => clocks.lsi_ck(),
             5 => clocks.per_ck(),
             _ => unreachable!(),
         }
@@ -133,8 +206,8 @@ macro_rules! impl_clk_lptim345 {
                         Val(srdccipr::LPTIM345SEL_A::RCC_PCLK4) => Some(clocks.pclk4()),
                         Val(srdccipr::LPTIM345SEL_A::PLL2_P) => clocks.pll2_p_ck(),
                         Val(srdccipr::LPTIM345SEL_A::PLL3_R) => clocks.pll3_r_ck(),
-                        Val(srdccipr::LPTIM345SEL_A::LSE) => unimplemented!(),
-                        Val(srdccipr::LPTIM345SEL_A::LSI) => unimplemented!(),
+                        Val(srdccipr::LPTIM345SEL_A::LSE) => lse_ck(),
+                        Val(srdccipr::LPTIM345SEL_A::LSI) => clocks.lsi_ck(),
                         Val(srdccipr::LPTIM345SEL_A::PER) => clocks.per_ck(),
                         _ => unreachable!(),
                     }
@@ -201,6 +274,52 @@ pub enum Event {
     TimeOut,
 }
 
+/// Active edge of the external trigger for a LPTIM
+#[derive(Copy, Clone, PartialEq)]
+pub enum TriggerEdge {
+    Rising = 0b01,
+    Falling = 0b10,
+    Both = 0b11,
+}
+
+/// IN1 input of a LPTIM pulse counter
+///
+/// The internal inputs of each LPTIM are listed in the LPTIM input/trigger
+/// connections of the reference manual
+#[derive(Copy, Clone, PartialEq)]
+pub enum PulseInput {
+    /// The GPIO pin
+    Pin = 0b00,
+    /// Internal input 1
+    Internal1 = 0b01,
+    /// Internal input 2
+    Internal2 = 0b10,
+    /// Internal input 3
+    Internal3 = 0b11,
+}
+
+/// Digital filter of a LPTIM input, as the number of consecutive kernel
+/// clock cycles for which the input must be stable
+#[derive(Copy, Clone, PartialEq)]
+pub enum InputFilter {
+    /// Any level change is an edge
+    None = 0b00,
+    Clocks2 = 0b01,
+    Clocks4 = 0b10,
+    Clocks8 = 0b11,
+}
+
+/// Encoder mode for a LPTIM
+#[derive(Copy, Clone, PartialEq)]
+pub enum EncoderMode {
+    /// The counter is updated on rising edges of the inputs
+    RisingEdges = 0b00,
+    /// The counter is updated on falling edges of the inputs
+    FallingEdges = 0b01,
+    /// The counter is updated on both edges of the inputs
+    BothEdges = 0b10,
+}
+
 macro_rules! hal {
     ($($TIMX:ident: ($timX:ident, $Rec:ident, $cntType:ty),)+) => {
         $(
@@ -459,6 +578,69 @@ macro_rules! hal {
     }
 }
 
+/// TIM16 TI1 input selection for the LSI
+const TIM16_TI1SEL_LSI: u32 = 0b0001;
+
+impl Timer<TIM16> {
+    /// Measures the frequency of the LSI against the kernel clock of TIM16,
+    /// using input capture on the internal connection from the LSI. This
+    /// takes about 8ms
+    ///
+    /// The timer is left paused, and must be reconfigured before it is used
+    /// as a timer again
+    pub fn measure_lsi(&mut self) -> Hertz {
+        // Captures of 8 LSI cycles each
+        const EDGES_PER_CAPTURE: u64 = 8;
+        const CAPTURES: u64 = 32;
+
+        self.pause();
+
+        // Divide the kernel clock so that 8 cycles of the slowest LSI fit
+        // in the 16-bit counter
+        let psc = (self.clk - 1) / 200_000_000;
+        let tick = self.clk / (psc + 1);
+        self.tim.psc.write(|w| w.psc().bits(psc as u16));
+        self.tim.arr.write(|w| unsafe { w.bits(0xFFFF) });
+
+        // CC1 captures TI1, connected to the LSI, every 8th rising edge
+        self.tim.ccer.modify(|_, w| w.cc1e().clear_bit());
+        self.tim
+            .tisel
+            .write(|w| unsafe { w.bits(TIM16_TI1SEL_LSI) });
+        self.tim
+            .ccmr1_input()
+            .write(|w| unsafe { w.cc1s().bits(0b01).ic1psc().bits(0b11) });
+        self.apply_freq();
+        self.tim
+            .sr
+            .modify(|_, w| w.cc1if().clear_bit().cc1of().clear_bit());
+        self.tim.ccer.modify(|_, w| w.cc1e().set_bit());
+        self.resume();
+
+        let tim = &self.tim;
+        let capture = || {
+            while tim.sr.read().cc1if().bit_is_clear() {}
+            // Reading CCR1 clears CC1IF
+            tim.ccr1.read().ccr().bits() as u16
+        };
+
+        let mut last = capture();
+        let mut ticks: u64 = 0;
+        for _ in 0..CAPTURES {
+            let next = capture();
+            ticks += u64::from(next.wrapping_sub(last));
+            last = next;
+        }
+
+        self.pause();
+        self.tim.ccer.modify(|_, w| w.cc1e().clear_bit());
+        self.tim.tisel.reset();
+
+        let lsi = u64::from(tick) * EDGES_PER_CAPTURE * CAPTURES / ticks;
+        Hertz(lsi as u32)
+    }
+}
+
 hal! {
     // Advanced-control
     TIM1: (tim1, Tim1, u16),
@@ -484,6 +666,12 @@ hal! {
     TIM16: (tim16, Tim16, u16),
     TIM17: (tim17, Tim17, u16),
 }
+#[cfg(feature = "rm0468")]
+hal! {
+    // General-purpose
+    TIM23: (tim23, Tim23, u32),
+    TIM24: (tim24, Tim24, u32),
+}
 
 macro_rules! lptim_hal {
     ($($TIMX:ident: ($timx:ident, $Rec:ident, $timXpac:ident),)+) => {
@@ -579,6 +767,31 @@ macro_rules! lptim_hal {
                     let _ = self.tim.cr.read(); // Delay 2 peripheral clocks
                 }
 
+                /// Starts a single count down at the `timeout` frequency. The
+                /// timer stops after the first autoreload match.
+                ///
+                /// If an external trigger is set, counting starts at the first
+                /// trigger edge
+                pub fn start_one_shot<T>(&mut self, timeout: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    // Reset: counter must be running
+                    self.reset_counter();
+
+                    // Disable counter
+                    self.tim.cr.write(|w| w.enable().disabled());
+
+                    // Set prescale and ARR
+                    self.priv_set_freq(timeout); // side effect: enables counter
+
+                    // Clear IRQ
+                    self.clear_irq();
+
+                    // Start counter in single mode
+                    self.tim.cr.write(|w| w.sngstrt().set_bit().enable().enabled());
+                }
+
                 /// Disables the LPTIM peripheral
                 pub fn pause(self) -> LpTimer<$TIMX, Disabled> {
                     // Disable the entire timer
@@ -633,6 +846,22 @@ macro_rules! lptim_hal {
                     }
                 }
 
+                /// Start counting on an edge of an external trigger, instead of
+                /// when the counter is started by software. `source` selects
+                /// the trigger input, see the LPTIM input/trigger connections
+                /// in the reference manual (RM0433 Rev 7 Table 365). If `edge` is
+                /// `None`, the counter is started by software.
+                ///
+                /// The counter must be disabled.
+                pub fn set_trigger(&mut self, edge: Option<TriggerEdge>, source: u8) {
+                    assert!(source < 8);
+                    let trigen = edge.map(|e| e as u8).unwrap_or(0);
+
+                    self.tim.cfgr.modify(|_, w| unsafe {
+                        w.trigen().bits(trigen).trigsel().bits(source)
+                    });
+                }
+
                 /// Enables the LPTIM, and starts counting
                 pub fn resume(self) -> LpTimer<$TIMX, Enabled> {
                     // Enable and start counting
@@ -734,3 +963,138 @@ lptim_hal! {
     LPTIM4: (lptim4, Lptim4, lptim3),
     LPTIM5: (lptim5, Lptim5, lptim3),
 }
+
+// Encoder mode is only available on LPTIM1 and LPTIM2
+macro_rules! lptim_encoder {
+    ($($TIMX:ident: ($timx_encoder:ident, $Rec:ident, $timXpac:ident),)+) => {
+        $(
+            impl LpTimer<$TIMX, Enabled> {
+                /// Configures a LPTIM peripheral as a quadrature encoder
+                /// interface on its IN1 and IN2 inputs. The counter counts
+                /// up and down between 0 and 0xFFFF.
+                ///
+                /// The LPTIM kernel clock must be at least 4 times faster
+                /// than the input signals.
+                pub fn $timx_encoder(tim: $TIMX, mode: EncoderMode,
+                                     prec: rec::$Rec, clocks: &CoreClocks
+                ) -> Self {
+                    // enable and reset peripheral to a clean state
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!").0;
+
+                    // Write CFGR: LPTIM must be disabled. Internal clock
+                    // without prescaler
+                    tim.cfgr.modify(|_, w| unsafe {
+                        w.presc()
+                            .variant($timXpac::cfgr::PRESC_A::DIV1)
+                            .cksel()
+                            .clear_bit()
+                            .ckpol()
+                            .bits(mode as u8)
+                            .enc()
+                            .set_bit()
+                    });
+
+                    // Enable
+                    tim.cr.write(|w| w.enable().enabled());
+
+                    // Write ARR: LPTIM must be enabled
+                    tim.arr.write(|w| w.arr().bits(0xFFFF));
+                    while tim.isr.read().arrok().bit_is_clear() {}
+                    tim.icr.write(|w| w.arrokcf().clear());
+
+                    // Start counter
+                    tim.cr.write(|w| w.cntstrt().set_bit().enable().enabled());
+
+                    LpTimer {
+                        clk,
+                        tim,
+                        timeout: Hertz(0),
+                        _enabled: PhantomData,
+                    }
+                }
+            }
+        )+
+    }
+}
+
+lptim_encoder! {
+    LPTIM1: (lptim1_encoder, Lptim1, lptim1),
+    LPTIM2: (lptim2_encoder, Lptim2, lptim1),
+}
+
+macro_rules! lptim_pulse_counter {
+    ($($TIMX:ident: ($timx_pulse_counter:ident, $Rec:ident, $timXpac:ident),)+) => {
+        $(
+            impl LpTimer<$TIMX, Enabled> {
+                /// Configures a LPTIM peripheral to count the `edge`s on
+                /// its IN1 input. The counter counts up from 0 to 0xFFFF,
+                /// and then wraps.
+                ///
+                /// `input` selects the IN1 input. The input must be stable
+                /// for the cycles of `filter` for an edge to be counted.
+                ///
+                /// The LPTIM kernel clock must be faster than the pulses.
+                pub fn $timx_pulse_counter(tim: $TIMX, edge: TriggerEdge,
+                                           input: PulseInput, filter: InputFilter,
+                                           prec: rec::$Rec, clocks: &CoreClocks
+                ) -> Self {
+                    // enable and reset peripheral to a clean state
+                    prec.enable().reset();
+
+                    let clk = $TIMX::get_clk(clocks)
+                        .expect("Timer input clock not running!").0;
+
+                    // Select the IN1 input. LPTIM must be disabled
+                    tim.cfgr2.modify(|_, w| unsafe { w.in1sel().bits(input as u8) });
+
+                    // Write CFGR: LPTIM must be disabled. Internal clock
+                    // samples IN1, and the counter is updated on each edge
+                    tim.cfgr.modify(|_, w| unsafe {
+                        w.presc()
+                            .variant($timXpac::cfgr::PRESC_A::DIV1)
+                            .cksel()
+                            .clear_bit()
+                            .ckpol()
+                            .bits(edge as u8 - 1)
+                            .ckflt()
+                            .bits(filter as u8)
+                            .countmode()
+                            .set_bit()
+                    });
+
+                    // Enable
+                    tim.cr.write(|w| w.enable().enabled());
+
+                    // Write ARR: LPTIM must be enabled
+                    tim.arr.write(|w| w.arr().bits(0xFFFF));
+                    while tim.isr.read().arrok().bit_is_clear() {}
+                    tim.icr.write(|w| w.arrokcf().clear());
+
+                    // Start counter
+                    tim.cr.write(|w| w.cntstrt().set_bit().enable().enabled());
+
+                    LpTimer {
+                        clk,
+                        tim,
+                        timeout: Hertz(0),
+                        _enabled: PhantomData,
+                    }
+                }
+            }
+        )+
+    }
+}
+
+lptim_pulse_counter! {
+    LPTIM1: (lptim1_pulse_counter, Lptim1, lptim1),
+    LPTIM2: (lptim2_pulse_counter, Lptim2, lptim1),
+    LPTIM3: (lptim3_pulse_counter, Lptim3, lptim3),
+}
+#[cfg(not(feature = "rm0455"))]
+lptim_pulse_counter! {
+    LPTIM4: (lptim4_pulse_counter, Lptim4, lptim3),
+    LPTIM5: (lptim5_pulse_counter, Lptim5, lptim3),
+}
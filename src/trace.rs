@@ -0,0 +1,186 @@
+//! SWO trace output
+//!
+//! [`swo`] configures the Serial Wire Output (SWO) for ITM output, with a
+//! prescaler calculated from the trace clock. This allows `log` or `defmt`
+//! output over ITM stimulus port 0 without calculating divider constants
+//! by hand.
+//!
+//! # Example
+//!
+//! ```
+//! use stm32h7xx_hal::trace;
+//!
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! let mut dp = pac::Peripherals::take().unwrap();
+//!
+//! let ccdr = rcc.sys_ck(400.mhz()).freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! let baud = trace::swo(
+//!     &mut cp.ITM,
+//!     &mut cp.DCB,
+//!     &mut dp.DBGMCU,
+//!     &ccdr.clocks,
+//!     2.mhz(),
+//! )
+//! .unwrap();
+//!
+//! cortex_m::iprintln!(&mut cp.ITM.stim[0], "SWO at {} baud", baud.0);
+//! ```
+//!
+//! The debugger must be set to the returned baud rate, using NRZ (UART)
+//! encoding.
+//!
+//! # Trace clock
+//!
+//! The SWO prescaler is clocked by the trace clock. This is `pll1_r_ck`
+//! when `sys_ck` is derived from PLL1, and `sys_ck` otherwise. After a
+//! call to [`switch_sys_ck`](crate::rcc::Ccdr::switch_sys_ck), call
+//! [`swo`] again so that the baud rate is recalculated.
+//!
+//! The SWO is output on PB3 in alternate function 0, which is its state
+//! after reset. Any other configuration of PB3 disables the output.
+
+use core::ptr;
+
+use cortex_m::peripheral::{DCB, ITM};
+
+use crate::rcc::CoreClocks;
+use crate::stm32::{DBGMCU, RCC};
+use crate::time::Hertz;
+
+/// Base address of the SWO
+const SWO_BASE: usize = 0x5C00_3000;
+/// Base address of the SWO funnel, SWTF
+const SWTF_BASE: usize = 0x5C00_4000;
+
+// CoreSight register offsets
+const SWO_CODR: usize = 0x010;
+const SWO_SPPR: usize = 0x0F0;
+const SWTF_CTRL: usize = 0x000;
+const LAR: usize = 0xFB0;
+
+/// CoreSight lock access key
+const LAR_KEY: u32 = 0xC5AC_CE55;
+/// SWO_SPPR value for NRZ (UART) encoding
+const SPPR_NRZ: u32 = 0b10;
+/// Maximum value of the SWO_CODR prescaler
+const CODR_MAX: u32 = 0x1FFF;
+
+/// SWTF_CTRL port enable for this core
+#[cfg(not(feature = "cm4"))]
+const SWTF_CTRL_ENS: u32 = 1 << 0;
+#[cfg(feature = "cm4")]
+const SWTF_CTRL_ENS: u32 = 1 << 1;
+
+// ITM_TCR
+const ITM_TCR_ITMENA: u32 = 1 << 0;
+const ITM_TCR_SYNCENA: u32 = 1 << 2;
+const ITM_TCR_TRACEBUSID_SHIFT: u32 = 16;
+
+/// RCC_CFGR SWS value when `sys_ck` is derived from PLL1
+const SWS_PLL1: u32 = 0b011;
+
+/// SWO configuration error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The requested baud rate is faster than the trace clock
+    BaudTooHigh,
+    /// The requested baud rate is too slow for the SWO prescaler
+    BaudTooLow,
+    /// `sys_ck` is derived from PLL1, but `pll1_r_ck` is not running
+    NoTraceClock,
+}
+
+/// Returns the frequency of the trace clock
+pub fn trace_ck(clocks: &CoreClocks) -> Option<Hertz> {
+    // NOTE(unsafe) Atomic read with no side effects
+    let sws = unsafe { (*RCC::ptr()).cfgr.read().sws().bits() };
+
+    if u32::from(sws) == SWS_PLL1 {
+        clocks.pll1_r_ck()
+    } else {
+        Some(clocks.sys_ck())
+    }
+}
+
+/// Writes a CoreSight register
+///
+/// # Safety
+///
+/// `base + offset` must be the address of a CoreSight register
+unsafe fn write(base: usize, offset: usize, value: u32) {
+    ptr::write_volatile((base + offset) as *mut u32, value);
+}
+
+/// Configures the SWO for ITM output at `baud`, and enables ITM stimulus
+/// port 0
+///
+/// Returns the actual baud rate, which is the trace clock divided by an
+/// integer
+pub fn swo(
+    itm: &mut ITM,
+    dcb: &mut DCB,
+    dbgmcu: &mut DBGMCU,
+    clocks: &CoreClocks,
+    baud: Hertz,
+) -> Result<Hertz, Error> {
+    let trace_ck = trace_ck(clocks).ok_or(Error::NoTraceClock)?.0;
+
+    if baud.0 == 0 {
+        return Err(Error::BaudTooLow);
+    }
+    if baud.0 > trace_ck {
+        return Err(Error::BaudTooHigh);
+    }
+
+    // Round to the nearest baud rate
+    let divider = (trace_ck + baud.0 / 2) / baud.0;
+    if divider - 1 > CODR_MAX {
+        return Err(Error::BaudTooLow);
+    }
+
+    // Debug clocks for the trace components
+    #[cfg(not(feature = "rm0455"))]
+    dbgmcu.cr.modify(|_, w| {
+        w.traceclken()
+            .set_bit()
+            .d1dbgcken()
+            .set_bit()
+            .d3dbgcken()
+            .set_bit()
+    });
+    #[cfg(feature = "rm0455")]
+    dbgmcu.cr.modify(|_, w| {
+        w.traceclken()
+            .set_bit()
+            .cddbgcken()
+            .set_bit()
+            .srddbgcken()
+            .set_bit()
+    });
+
+    // NOTE(unsafe) The SWO and SWTF are only used by the trace output
+    unsafe {
+        write(SWO_BASE, LAR, LAR_KEY);
+        write(SWO_BASE, SWO_CODR, divider - 1);
+        write(SWO_BASE, SWO_SPPR, SPPR_NRZ);
+
+        write(SWTF_BASE, LAR, LAR_KEY);
+        let ctrl = ptr::read_volatile((SWTF_BASE + SWTF_CTRL) as *const u32);
+        write(SWTF_BASE, SWTF_CTRL, ctrl | SWTF_CTRL_ENS);
+    }
+
+    dcb.enable_trace();
+
+    // NOTE(unsafe) ITM registers are only used by the trace output
+    unsafe {
+        itm.lar.write(LAR_KEY);
+        itm.tcr.write(
+            (1 << ITM_TCR_TRACEBUSID_SHIFT) | ITM_TCR_SYNCENA | ITM_TCR_ITMENA,
+        );
+        itm.ter[0].write(1);
+    }
+
+    Ok(Hertz(trace_ck / divider))
+}
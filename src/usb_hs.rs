@@ -14,7 +14,7 @@ use crate::gpio::{
     Alternate, AF10,
 };
 
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 use crate::gpio::{
     gpiob::{PB14, PB15},
     AF12,
@@ -25,7 +25,7 @@ use crate::time::Hertz;
 pub use synopsys_usb_otg::UsbBus;
 use synopsys_usb_otg::UsbPeripheral;
 
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 pub struct USB1 {
     pub usb_global: stm32::OTG1_HS_GLOBAL,
     pub usb_device: stm32::OTG1_HS_DEVICE,
@@ -36,7 +36,7 @@ pub struct USB1 {
     pub hclk: Hertz,
 }
 
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 pub struct USB2 {
     pub usb_global: stm32::OTG2_HS_GLOBAL,
     pub usb_device: stm32::OTG2_HS_DEVICE,
@@ -47,7 +47,7 @@ pub struct USB2 {
     pub hclk: Hertz,
 }
 
-#[cfg(feature = "rm0455")]
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
 pub struct USB1 {
     pub usb_global: stm32::OTG1_HS_GLOBAL,
     pub usb_device: stm32::OTG1_HS_DEVICE,
@@ -102,11 +102,11 @@ usb_peripheral! {
 }
 pub type Usb1BusType = UsbBus<USB1>;
 
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 usb_peripheral! {
     USB2, OTG2_HS_GLOBAL, usb2otgen, usb2otgrst
 }
-#[cfg(not(feature = "rm0455"))]
+#[cfg(not(any(feature = "rm0455", feature = "rm0468")))]
 pub type Usb2BusType = UsbBus<USB2>;
 
 pub struct USB1_ULPI {
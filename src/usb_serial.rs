@@ -0,0 +1,300 @@
+//! USB CDC-ACM serial port
+//!
+//! A [`CdcAcm`] is a USB device with a single CDC-ACM serial port from the
+//! `usbd-serial` crate, over one of the [USB OTG peripherals](crate::usb_hs).
+//!
+//! ```
+//! let usb_bus = UsbBus::new(usb, unsafe { &mut EP_MEMORY });
+//! let mut cdc = CdcAcm::new(
+//!     &usb_bus,
+//!     UsbVidPid(0x16c0, 0x27dd),
+//!     "Fake company",
+//!     "Serial port",
+//!     "TEST",
+//! );
+//!
+//! loop {
+//!     if cdc.poll() {
+//!         let mut buf = [0u8; 64];
+//!         if let Ok(count) = cdc.read(&mut buf) {
+//!             let _ = cdc.write(&buf[..count]);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # USB to UART adapter
+//!
+//! A [`Bridge`] forwards the data between the CDC-ACM port and a
+//! [`Serial`](crate::serial::Serial). When the host changes the line
+//! coding, the baud rate, parity and stop bits of the serial port are
+//! changed to match it.
+//!
+//! ```
+//! let serial = dp
+//!     .USART1
+//!     .serial((tx, rx), 115_200.bps(), ccdr.peripheral.USART1, &ccdr.clocks)
+//!     .unwrap();
+//! let mut bridge = cdc.bridge(serial, &ccdr.clocks);
+//!
+//! loop {
+//!     if let Err(e) = bridge.poll() {
+//!         // The line coding from the host is not supported, and the
+//!         // serial port keeps its previous configuration
+//!     }
+//! }
+//! ```
+//!
+//! Call `poll` at least once per USB frame, or from the USB interrupt and
+//! the interrupts of the serial port. The serial port runs with 7 or 8 data
+//! bits and a parity bit, or 8 data bits without parity.
+
+use crate::hal::serial;
+use crate::rcc::CoreClocks;
+use crate::serial::config::{Config, Parity, StopBits, WordLength};
+use crate::serial::{ConfigError, Reconfigure};
+use crate::time::Hertz;
+
+use usb_device::bus::{UsbBus, UsbBusAllocator};
+use usb_device::device::{UsbDevice, UsbDeviceBuilder, UsbVidPid};
+use usb_device::UsbError;
+use usbd_serial::{LineCoding, ParityType, SerialPort, USB_CLASS_CDC};
+
+/// USB device with a CDC-ACM serial port
+pub struct CdcAcm<'a, B: UsbBus> {
+    device: UsbDevice<'a, B>,
+    port: SerialPort<'a, B>,
+}
+
+impl<'a, B: UsbBus> CdcAcm<'a, B> {
+    /// Creates a USB device with a CDC-ACM serial port on `bus`
+    pub fn new(
+        bus: &'a UsbBusAllocator<B>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+        serial_number: &'a str,
+    ) -> Self {
+        // The class must be allocated before the device is built
+        let port = SerialPort::new(bus);
+        let device = UsbDeviceBuilder::new(bus, vid_pid)
+            .manufacturer(manufacturer)
+            .product(product)
+            .serial_number(serial_number)
+            .device_class(USB_CLASS_CDC)
+            .build();
+
+        CdcAcm { device, port }
+    }
+
+    /// Polls the USB device. Returns `true` if the serial port may have
+    /// data to read or space to write
+    pub fn poll(&mut self) -> bool {
+        self.device.poll(&mut [&mut self.port])
+    }
+
+    /// Reads received data into `buf`, and returns the number of bytes read
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, UsbError> {
+        self.port.read(buf)
+    }
+
+    /// Writes data from `buf`, and returns the number of bytes written
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, UsbError> {
+        self.port.write(buf)
+    }
+
+    /// Returns the line coding last set by the host
+    pub fn line_coding(&self) -> &LineCoding {
+        self.port.line_coding()
+    }
+
+    /// Returns `true` if the host has asserted DTR, which usually means that
+    /// a terminal has opened the port
+    pub fn dtr(&self) -> bool {
+        self.port.dtr()
+    }
+
+    /// Returns the serial port class
+    pub fn port(&mut self) -> &mut SerialPort<'a, B> {
+        &mut self.port
+    }
+
+    /// Returns the USB device
+    pub fn device(&mut self) -> &mut UsbDevice<'a, B> {
+        &mut self.device
+    }
+
+    /// Bridges the CDC-ACM port to the serial port `serial`, which is
+    /// reconfigured when the host changes the line coding
+    pub fn bridge<S>(self, serial: S, clocks: &CoreClocks) -> Bridge<'a, B, S>
+    where
+        S: serial::Read<u8> + serial::Write<u8> + Reconfigure,
+    {
+        Bridge {
+            cdc: self,
+            serial,
+            clocks: *clocks,
+            coding: None,
+            to_serial: [0; BUFFER],
+            to_serial_len: 0,
+            to_serial_pos: 0,
+            to_usb: [0; BUFFER],
+            to_usb_len: 0,
+        }
+    }
+}
+
+/// Bridge error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The data bits or the parity of the line coding are not supported
+    LineCoding,
+    /// The serial port cannot be configured for the line coding
+    Serial(ConfigError),
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Serial(e)
+    }
+}
+
+/// Size of the buffers in each direction, one full speed packet
+const BUFFER: usize = 64;
+
+/// Line coding as compared between polls: data rate, data bits, parity and
+/// stop bits
+type Coding = (u32, u8, u8, u8);
+
+/// Bridge between a CDC-ACM port and a serial port
+pub struct Bridge<'a, B: UsbBus, S> {
+    cdc: CdcAcm<'a, B>,
+    serial: S,
+    clocks: CoreClocks,
+    /// Last line coding seen
+    coding: Option<Coding>,
+    to_serial: [u8; BUFFER],
+    to_serial_len: usize,
+    to_serial_pos: usize,
+    to_usb: [u8; BUFFER],
+    to_usb_len: usize,
+}
+
+impl<'a, B, S> Bridge<'a, B, S>
+where
+    B: UsbBus,
+    S: serial::Read<u8> + serial::Write<u8> + Reconfigure,
+{
+    /// Polls the USB device, applies a new line coding to the serial port,
+    /// and forwards the data that is waiting in each direction
+    ///
+    /// Returns an error once for each line coding that cannot be applied.
+    /// Data is forwarded at the previous configuration in this case.
+    /// Bytes received by the serial port with an error are dropped.
+    pub fn poll(&mut self) -> Result<(), Error> {
+        self.cdc.poll();
+        let result = self.apply_line_coding();
+
+        // USB to serial
+        if self.to_serial_pos == self.to_serial_len {
+            self.to_serial_pos = 0;
+            self.to_serial_len =
+                self.cdc.port.read(&mut self.to_serial).unwrap_or(0);
+        }
+        while self.to_serial_pos < self.to_serial_len {
+            let byte = self.to_serial[self.to_serial_pos];
+            if self.serial.write(byte).is_err() {
+                break;
+            }
+            self.to_serial_pos += 1;
+        }
+
+        // Serial to USB
+        while self.to_usb_len < BUFFER {
+            match self.serial.read() {
+                Ok(byte) => {
+                    self.to_usb[self.to_usb_len] = byte;
+                    self.to_usb_len += 1;
+                }
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(_)) => {}
+            }
+        }
+        if self.to_usb_len > 0 {
+            if let Ok(count) =
+                self.cdc.port.write(&self.to_usb[..self.to_usb_len])
+            {
+                self.to_usb.copy_within(count..self.to_usb_len, 0);
+                self.to_usb_len -= count;
+            }
+        }
+
+        result
+    }
+
+    /// Returns the CDC-ACM device
+    pub fn cdc(&mut self) -> &mut CdcAcm<'a, B> {
+        &mut self.cdc
+    }
+
+    /// Returns the serial port
+    pub fn serial(&mut self) -> &mut S {
+        &mut self.serial
+    }
+
+    /// Releases the CDC-ACM device and the serial port. Data that has not
+    /// been forwarded yet is lost
+    pub fn free(self) -> (CdcAcm<'a, B>, S) {
+        (self.cdc, self.serial)
+    }
+
+    /// Reconfigures the serial port if the host has changed the line coding
+    fn apply_line_coding(&mut self) -> Result<(), Error> {
+        let line_coding = self.cdc.port.line_coding();
+        let coding = (
+            line_coding.data_rate(),
+            line_coding.data_bits(),
+            line_coding.parity_type() as u8,
+            line_coding.stop_bits() as u8,
+        );
+        if self.coding == Some(coding) {
+            return Ok(());
+        }
+        self.coding = Some(coding);
+
+        let config = config(line_coding).ok_or(Error::LineCoding)?;
+        self.serial.reconfigure(config, &self.clocks)?;
+        Ok(())
+    }
+}
+
+/// Returns the serial configuration for `line_coding`, if it is supported
+fn config(line_coding: &LineCoding) -> Option<Config> {
+    let parity = match line_coding.parity_type() {
+        ParityType::None => Parity::ParityNone,
+        ParityType::Odd => Parity::ParityOdd,
+        ParityType::Mark | ParityType::Space => return None,
+        _ => Parity::ParityEven,
+    };
+    // The word length of the serial port includes the parity bit
+    let bits = line_coding.data_bits()
+        + if parity == Parity::ParityNone { 0 } else { 1 };
+    let wordlength = match bits {
+        8 => WordLength::DataBits8,
+        9 => WordLength::DataBits9,
+        _ => return None,
+    };
+    let stopbits = match line_coding.stop_bits() {
+        usbd_serial::StopBits::One => StopBits::STOP1,
+        usbd_serial::StopBits::OnePointFive => StopBits::STOP1P5,
+        usbd_serial::StopBits::Two => StopBits::STOP2,
+    };
+
+    Some(Config {
+        baudrate: Hertz(line_coding.data_rate()),
+        wordlength,
+        parity,
+        stopbits,
+    })
+}
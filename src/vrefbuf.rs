@@ -0,0 +1,153 @@
+//! Voltage reference buffer (VREFBUF)
+//!
+//! The voltage reference buffer can drive the VREF+ pin with an internal
+//! reference voltage, which is then used by the ADCs and DACs. On packages
+//! where VREF+ is bonded out, it can instead be left in high-impedance mode
+//! so that an external reference is used.
+//!
+//! # Example
+//!
+//! ```
+//! let mut vref = dp.VREFBUF.vrefbuf(ccdr.peripheral.VREF);
+//!
+//! // VREF+ is driven at 2.5V
+//! vref.enable(Scale::V2_5);
+//! vref.wait_ready();
+//!
+//! assert_eq!(vref.scale().millivolts(), 2500);
+//! ```
+//!
+//! The output voltage is only stable once VRR is set, see
+//! [`is_ready`](VrefBuf::is_ready). VDDA must be higher than the selected
+//! voltage.
+
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::VREFBUF;
+
+/// Output voltage of the reference buffer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Scale {
+    /// 2.5V
+    V2_5 = 0b000,
+    /// 2.048V
+    V2_048 = 0b001,
+    /// 1.8V
+    V1_8 = 0b010,
+    /// 1.5V
+    V1_5 = 0b011,
+}
+
+impl Scale {
+    /// Nominal output voltage in millivolts
+    pub fn millivolts(self) -> u32 {
+        match self {
+            Scale::V2_5 => 2500,
+            Scale::V2_048 => 2048,
+            Scale::V1_8 => 1800,
+            Scale::V1_5 => 1500,
+        }
+    }
+
+    fn from_bits(vrs: u8) -> Self {
+        match vrs {
+            0b000 => Scale::V2_5,
+            0b001 => Scale::V2_048,
+            0b010 => Scale::V1_8,
+            _ => Scale::V1_5,
+        }
+    }
+}
+
+/// Extension trait for the voltage reference buffer
+pub trait VrefBufExt {
+    fn vrefbuf(self, prec: rec::Vref) -> VrefBuf;
+}
+
+impl VrefBufExt for VREFBUF {
+    fn vrefbuf(self, prec: rec::Vref) -> VrefBuf {
+        prec.enable().reset();
+
+        VrefBuf { rb: self }
+    }
+}
+
+/// Voltage reference buffer
+pub struct VrefBuf {
+    rb: VREFBUF,
+}
+
+impl VrefBuf {
+    /// Drives VREF+ from the internal reference at `scale`
+    ///
+    /// The output is stable once [`is_ready`](VrefBuf::is_ready) returns
+    /// `true`
+    pub fn enable(&mut self, scale: Scale) {
+        // unsafe: All scales are valid VRS values
+        self.rb.csr.modify(|_, w| unsafe {
+            w.hiz().clear_bit().vrs().bits(scale as u8).envr().set_bit()
+        });
+    }
+
+    /// Disables the reference buffer. VREF+ is pulled to ground, unless
+    /// high-impedance mode is selected
+    pub fn disable(&mut self) {
+        self.rb.csr.modify(|_, w| w.envr().clear_bit());
+    }
+
+    /// Changes the output voltage
+    ///
+    /// If the buffer is enabled, the output is stable once
+    /// [`is_ready`](VrefBuf::is_ready) returns `true` again
+    pub fn set_scale(&mut self, scale: Scale) {
+        // unsafe: As above
+        self.rb
+            .csr
+            .modify(|_, w| unsafe { w.vrs().bits(scale as u8) });
+    }
+
+    /// Returns the selected output voltage
+    pub fn scale(&self) -> Scale {
+        Scale::from_bits(self.rb.csr.read().vrs().bits())
+    }
+
+    /// Selects high-impedance mode
+    ///
+    /// When the buffer is disabled, VREF+ is left floating so that an
+    /// external reference can be used. When the buffer is enabled, the
+    /// internal reference is connected to VREF+ without the output buffer
+    /// (hold mode)
+    pub fn set_high_impedance(&mut self, hiz: bool) {
+        self.rb.csr.modify(|_, w| w.hiz().bit(hiz));
+    }
+
+    /// Returns `true` if the buffer is enabled and the output voltage has
+    /// reached its final value
+    pub fn is_ready(&self) -> bool {
+        self.rb.csr.read().vrr().bit_is_set()
+    }
+
+    /// Waits until the output voltage has reached its final value
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is not enabled
+    pub fn wait_ready(&self) {
+        assert!(
+            self.rb.csr.read().envr().bit_is_set(),
+            "The reference buffer is not enabled"
+        );
+        while !self.is_ready() {}
+    }
+
+    /// Sets the trimming value (0 - 63), overriding the factory
+    /// calibration
+    pub fn set_trim(&mut self, trim: u8) {
+        // unsafe: All 6 bit values are valid
+        self.rb.ccr.write(|w| unsafe { w.trim().bits(trim & 0x3F) });
+    }
+
+    /// Releases the VREFBUF peripheral
+    pub fn free(self) -> VREFBUF {
+        self.rb
+    }
+}
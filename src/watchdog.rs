@@ -6,7 +6,7 @@ use crate::time::{Hertz, MilliSeconds};
 use cast::u8;
 
 /// Select Window Watchdog hardware based on core
-#[cfg(any(feature = "rm0433", feature = "rm0455"))]
+#[cfg(any(feature = "rm0433", feature = "rm0455", feature = "rm0468"))]
 use crate::stm32::WWDG;
 #[cfg(all(feature = "rm0399", feature = "cm7"))]
 use crate::stm32::WWDG1 as WWDG;
@@ -28,6 +28,7 @@ pub struct SystemWindowWatchdog {
     wwdg: WWDG,
     down_counter: u8,
     pclk3_frequency: Hertz,
+    early_wakeup: Option<fn(&mut SystemWindowWatchdog)>,
 }
 
 impl SystemWindowWatchdog {
@@ -40,6 +41,34 @@ impl SystemWindowWatchdog {
             wwdg,
             down_counter: 0,
             pclk3_frequency: ccdr.clocks.pclk3(),
+            early_wakeup: None,
+        }
+    }
+
+    /// Calls `callback` from [`on_interrupt`](Self::on_interrupt) for each
+    /// early wakeup, or no longer calls a callback with `None`
+    ///
+    /// The callback can log the state of the application, or feed the
+    /// watchdog to recover. The early wakeup interrupt must also be enabled
+    /// with [`listen`](Self::listen).
+    pub fn on_early_wakeup(
+        &mut self,
+        callback: Option<fn(&mut SystemWindowWatchdog)>,
+    ) {
+        self.early_wakeup = callback;
+    }
+
+    /// Handles the WWDG interrupt. Must be called from the interrupt
+    /// handler
+    ///
+    /// If the early wakeup is pending, it is cleared and the callback set
+    /// with [`on_early_wakeup`](Self::on_early_wakeup) is called.
+    pub fn on_interrupt(&mut self) {
+        if self.is_pending(Event::EarlyWakeup) {
+            self.unpend(Event::EarlyWakeup);
+            if let Some(callback) = self.early_wakeup {
+                callback(self);
+            }
         }
     }
 
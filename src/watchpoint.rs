@@ -0,0 +1,196 @@
+//! Data watchpoints
+//!
+//! The Data Watchpoint and Trace unit (DWT) has comparators that can trap
+//! accesses to a memory range. This is useful to find what overwrites a
+//! variable unexpectedly, for example a DMA transfer with a wrong buffer.
+//!
+//! # Example
+//!
+//! ```
+//! use stm32h7xx_hal::watchpoint::{Access, Watchpoints};
+//!
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! let mut watchpoints = Watchpoints::new(cp.DWT, &mut cp.DCB);
+//!
+//! // Trap writes to a 32 byte buffer
+//! let wp = watchpoints
+//!     .set(buffer.as_ptr() as usize, 32, Access::Write)
+//!     .unwrap();
+//! ```
+//!
+//! When a watchpoint is hit, the core halts if a debugger is attached. The
+//! comparators only see accesses by the core, not by the DMA. However a
+//! DMA overwrite often shows up as a later access by the core to the wrong
+//! location.
+//!
+//! Without a debugger, call
+//! [`enable_debug_monitor`](Watchpoints::enable_debug_monitor) and define a
+//! `DebugMonitor` exception handler instead. The handler can find the
+//! watchpoint with [`hit`](Watchpoints::hit).
+//!
+//! The number of comparators is read from the hardware. Comparators that
+//! are already in use, for example by a debugger, are not used.
+
+use cortex_m::peripheral::{DCB, DWT};
+
+// DWT_CTRL
+const CTRL_NUMCOMP_SHIFT: u32 = 28;
+
+// DWT_FUNCTIONx
+const FUNCTION_MASK: u32 = 0xF;
+const FUNCTION_MATCHED: u32 = 1 << 24;
+
+// DCB_DEMCR
+const DEMCR_MON_EN: u32 = 1 << 16;
+
+/// Largest number of address bits that can be masked. Fewer bits may be
+/// implemented
+const MASK_MAX: u32 = 31;
+
+/// Accesses that trigger a watchpoint
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Access {
+    Read = 0b0101,
+    Write = 0b0110,
+    ReadWrite = 0b0111,
+}
+
+/// Watchpoint error
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// All comparators are in use
+    NoComparator,
+    /// The size is not a power of two, or the address is not aligned to the
+    /// size
+    Alignment,
+    /// The size is larger than the comparators support
+    TooLarge,
+}
+
+/// A watchpoint set with [`Watchpoints::set`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Watchpoint {
+    index: u8,
+}
+
+impl Watchpoint {
+    /// Index of the DWT comparator used by this watchpoint
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+/// Data watchpoints
+pub struct Watchpoints {
+    dwt: DWT,
+    num: u8,
+    /// Comparators used by this driver
+    used: u16,
+}
+
+impl Watchpoints {
+    /// Takes ownership of the DWT and enables it
+    pub fn new(dwt: DWT, dcb: &mut DCB) -> Self {
+        dcb.enable_trace();
+
+        let num = (dwt.ctrl.read() >> CTRL_NUMCOMP_SHIFT) as u8;
+        Watchpoints { dwt, num, used: 0 }
+    }
+
+    /// Returns the number of comparators implemented by the hardware
+    pub fn comparators(&self) -> u8 {
+        self.num
+    }
+
+    /// Returns the number of comparators that are not in use
+    pub fn available(&self) -> u8 {
+        (0..self.num).filter(|&i| !self.in_use(i)).count() as u8
+    }
+
+    fn in_use(&self, index: u8) -> bool {
+        self.dwt.c[usize::from(index)].function.read() & FUNCTION_MASK != 0
+    }
+
+    /// Sets a watchpoint on the `size` bytes at `address`
+    ///
+    /// `size` must be a power of two, and `address` must be aligned to
+    /// `size`
+    pub fn set(
+        &mut self,
+        address: usize,
+        size: usize,
+        access: Access,
+    ) -> Result<Watchpoint, Error> {
+        if !size.is_power_of_two() || address & (size - 1) != 0 {
+            return Err(Error::Alignment);
+        }
+        let mask = size.trailing_zeros();
+        if mask > MASK_MAX {
+            return Err(Error::TooLarge);
+        }
+
+        let index = (0..self.num)
+            .find(|&i| !self.in_use(i))
+            .ok_or(Error::NoComparator)?;
+        let comparator = &self.dwt.c[usize::from(index)];
+
+        // NOTE(unsafe) This comparator is not in use
+        unsafe {
+            comparator.comp.write(address as u32);
+            comparator.mask.write(mask);
+
+            // Unimplemented mask bits read as zero
+            if comparator.mask.read() != mask {
+                comparator.mask.write(0);
+                return Err(Error::TooLarge);
+            }
+
+            comparator.function.write(access as u32);
+        }
+
+        self.used |= 1 << index;
+        Ok(Watchpoint { index })
+    }
+
+    /// Clears a watchpoint, so that its comparator can be used again
+    pub fn clear(&mut self, watchpoint: Watchpoint) {
+        // NOTE(unsafe) This comparator is owned by the watchpoint
+        unsafe {
+            self.dwt.c[usize::from(watchpoint.index)].function.write(0);
+        }
+        self.used &= !(1 << watchpoint.index);
+    }
+
+    /// Returns a watchpoint that was hit since the last call
+    ///
+    /// The MATCHED flag is cleared when it is read, so the flags of all the
+    /// watchpoints up to the one returned are cleared
+    pub fn hit(&mut self) -> Option<Watchpoint> {
+        let used = self.used;
+        let dwt = &self.dwt;
+        (0..self.num)
+            .filter(|&i| used & (1 << i) != 0)
+            .find(|&i| {
+                dwt.c[usize::from(i)].function.read() & FUNCTION_MATCHED != 0
+            })
+            .map(|index| Watchpoint { index })
+    }
+
+    /// Enables the `DebugMonitor` exception, which is taken when a
+    /// watchpoint is hit and no debugger is attached
+    pub fn enable_debug_monitor(&mut self, dcb: &mut DCB) {
+        // NOTE(unsafe) Only the monitor enable is set
+        unsafe { dcb.demcr.modify(|r| r | DEMCR_MON_EN) };
+    }
+
+    /// Clears all watchpoints set by this driver, and releases the DWT
+    pub fn free(mut self) -> DWT {
+        for index in 0..self.num {
+            if self.used & (1 << index) != 0 {
+                self.clear(Watchpoint { index });
+            }
+        }
+        self.dwt
+    }
+}